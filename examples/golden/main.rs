@@ -0,0 +1,50 @@
+//! Renders the golden scenes and compares them with the reference images in `tests/golden`.
+//!
+//! ```text
+//! cargo run --example golden --features golden [-- --bless]
+//! ```
+//!
+//! Without a GPU, install lavapipe (`mesa-vulkan-drivers`) and select it with
+//! `VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json`.
+//!
+//! The CI runs it with lavapipe as well (`.github/workflows/golden.yml`), so the reference images
+//! have to be blessed with lavapipe to match within the tolerance.
+
+use hotrod::golden::{GoldenHarness, GoldenOutcome};
+use hotrod::logging::LevelFilter;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    hotrod::logging::init_logger(Some(LevelFilter::Info)).expect("Unable to init logger");
+
+    let bless = std::env::args().any(|arg| arg == "--bless");
+    let reference_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    let mut harness = match GoldenHarness::new(reference_dir) {
+        Ok(harness) => harness.with_bless(bless),
+        Err(e) => {
+            eprintln!("Failed to create the golden harness: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut failed = false;
+    for (scene, result) in harness.check_all() {
+        match result {
+            Ok(GoldenOutcome::Matched(comparison)) => {
+                println!("ok       {} ({comparison:?})", scene.name())
+            }
+            Ok(GoldenOutcome::Blessed) => println!("blessed  {}", scene.name()),
+            Err(e) => {
+                println!("FAILED   {}: {e}", scene.name());
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
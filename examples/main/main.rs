@@ -1,4 +1,6 @@
+use hotrod::engine::system::backend::Vulkan;
 use hotrod::engine::system::canvas::buffered_layer::BufferedCanvasLayer;
+use hotrod::engine::system::commands::CommandRecorder;
 use hotrod::engine::system::vulkan::beautiful_lines::{BeautifulLine, Vertex2d};
 use hotrod::engine::system::vulkan::textured::{Textured, TexturedIndexed, Vertex2dUv};
 use hotrod::engine::system::vulkan::triangles::{Triangles, TrianglesIndexed};
@@ -14,7 +16,7 @@ use image::GenericImageView;
 use std::io::Cursor;
 use std::ops::{Div, Mul};
 use std::sync::Arc;
-use std::time::{Duration, Instant, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 
 const IMAGE_DATA: &[u8] = include_bytes!(concat!("rust-logo-256x256.png"));
 
@@ -30,7 +32,7 @@ fn main() {
     loop {
         let loop_start = Instant::now();
         let response = engine.update(|mut ctx| {
-            let abort = ctx.events.iter().any(|e| match e {
+            let abort = ctx.events.iter().any(|e| match &e.event {
                 Event::Quit { .. } => true,
                 Event::KeyDown { keycode, .. } => {
                     matches!(keycode, Some(Keycode::Escape))
@@ -88,7 +90,7 @@ fn main() {
                             .unwrap(),
                     );
 
-                    buffers.push(commands.build().unwrap());
+                    buffers.push(Vulkan::commands(commands.build().unwrap()));
                 }
 
                 if ttf.is_none() {
@@ -126,20 +128,19 @@ fn main() {
                         .unwrap();
 
                     ttf = Some((texture, surface.height() as f32 / surface.width() as f32));
-                    buffers.push(commands.build().unwrap());
+                    buffers.push(Vulkan::commands(commands.build().unwrap()));
                 }
 
                 let mut commands = context.inner.create_render_buffer_builder().unwrap();
                 let mut canvas = BufferedCanvasLayer::new(
-                    context.inner.create_render_buffer_builder().unwrap(),
+                    CommandRecorder::new(context.inner).unwrap(),
                     Arc::clone(context.pipelines),
                 );
 
                 canvas.draw_rect(Pos::new(20.0, 20.0), Dim::new(50.0, 50.0));
 
-                let time = UNIX_EPOCH.elapsed().unwrap_or_default().subsec_millis() as f32
-                    * std::f32::consts::PI.mul(2.0)
-                    / 10.0;
+                let time =
+                    context.elapsed.subsec_millis() as f32 * std::f32::consts::PI.mul(2.0) / 10.0;
 
                 context
                     .pipelines
@@ -379,7 +380,7 @@ fn main() {
                 }
 
                 buffers.push(canvas.flush(context.inner, &context.pipelines));
-                buffers.push(commands.build().unwrap());
+                buffers.push(Vulkan::commands(commands.build().unwrap()));
                 buffers
             })
             .map(|_| !abort)
@@ -0,0 +1,411 @@
+//! Scripted scenes to measure the performance of the pipelines over a fixed duration. The
+//! resulting [`BenchReport`] can be stored as JSON and compared between releases to track
+//! performance regressions.
+//!
+//! ```no_run
+//! use hotrod::bench::{BenchConfig, BenchScene};
+//! use hotrod::engine::Engine;
+//!
+//! let mut engine = Engine::default();
+//! let report = BenchConfig::default()
+//!     .with_scene(BenchScene::new("many-sprites").with_sprites(50_000))
+//!     .run(&mut engine)
+//!     .unwrap();
+//! report.write_json("bench.json").unwrap();
+//! ```
+
+use crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer;
+use crate::engine::system::commands::{CommandRecorder, RecordedCommands};
+use crate::engine::system::vulkan::textured::{Textured, Vertex2dUv};
+use crate::engine::system::vulkan::DrawError;
+use crate::engine::{Engine, RenderContext};
+use sdl2::event::Event;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A scene that draws the given amount of primitives every frame, their positions change each
+/// frame so that nothing can be reused between frames.
+#[derive(Debug, Clone, PartialEq, Eq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct BenchScene {
+    pub name: String,
+    /// Textured quads drawn through a [`BufferedCanvasLayer`]
+    pub sprites: usize,
+    /// Textured quads drawn directly through [`TexturedPipeline::draw`] with one item per quad,
+    /// so the recording cost per item is not hidden by the batching of the canvas
+    ///
+    /// [`TexturedPipeline::draw`]: crate::engine::system::vulkan::textured::TexturedPipeline::draw
+    pub textured: usize,
+    /// Lines drawn through a [`BufferedCanvasLayer`]
+    pub lines: usize,
+    /// Distinct strings drawn through the font renderer, ignored without the
+    /// `ttf-font-renderer` feature
+    pub texts: usize,
+}
+
+impl BenchScene {
+    const SPRITE_SIZE: f32 = 16.0;
+    const TEXT_SIZE: u16 = 16;
+
+    #[inline]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            sprites: 0,
+            textured: 0,
+            lines: 0,
+            texts: 0,
+        }
+    }
+
+    #[inline]
+    pub fn with_sprites(mut self, sprites: usize) -> Self {
+        self.sprites = sprites;
+        self
+    }
+
+    #[inline]
+    pub fn with_textured(mut self, textured: usize) -> Self {
+        self.textured = textured;
+        self
+    }
+
+    #[inline]
+    pub fn with_lines(mut self, lines: usize) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    #[inline]
+    pub fn with_texts(mut self, texts: usize) -> Self {
+        self.texts = texts;
+        self
+    }
+
+    fn draw(&self, context: RenderContext, frame: u64) -> Result<Vec<RecordedCommands>, Error> {
+        let (width, height) = (context.width.max(1) as f32, context.height.max(1) as f32);
+        let offset = frame as f32 * 2.0;
+        let position = |index: usize, a: f32, b: f32| {
+            (
+                (index as f32 * a + offset) % width,
+                (index as f32 * b + offset * 0.5) % height,
+            )
+        };
+
+        let mut layer = BufferedCanvasLayer::new(
+            CommandRecorder::new(context.inner)?,
+            Arc::clone(context.pipelines),
+        );
+
+        let texture = context.pipelines.placeholders().missing();
+        for index in 0..self.sprites {
+            let (x, y) = position(index, 37.0, 53.0);
+            layer.draw_textured_rect(
+                [x, y],
+                [Self::SPRITE_SIZE, Self::SPRITE_SIZE],
+                texture.clone(),
+            );
+        }
+
+        for index in 0..self.lines {
+            let (x, y) = position(index, 41.0, 29.0);
+            layer.set_draw_color([(index % 7) as f32 / 7.0, 0.5, 1.0, 1.0]);
+            layer.draw_line([x, y], [width - x, height - y]);
+        }
+
+        let mut commands = vec![layer.flush(context.inner, context.pipelines)];
+
+        if self.textured > 0 {
+            let mut recorder = CommandRecorder::new(context.inner)?;
+            let [w, h] = [Self::SPRITE_SIZE; 2];
+            let textured = (0..self.textured)
+                .map(|index| {
+                    let (x, y) = position(index, 43.0, 31.0);
+                    let corner = |u: f32, v: f32| Vertex2dUv {
+                        pos: [x + u * w, y + v * h],
+                        uv: [u, v],
+                    };
+                    Textured {
+                        vertices: vec![
+                            corner(0.0, 0.0),
+                            corner(1.0, 0.0),
+                            corner(1.0, 1.0),
+                            corner(1.0, 1.0),
+                            corner(0.0, 1.0),
+                            corner(0.0, 0.0),
+                        ],
+                        texture: texture.clone(),
+                    }
+                })
+                .collect::<Vec<_>>();
+            context
+                .pipelines
+                .texture
+                .draw(recorder.recorder_mut(), &textured)?;
+            commands.push(recorder.finish()?);
+        }
+
+        #[cfg(feature = "ttf-font-renderer")]
+        if self.texts > 0 {
+            let RenderContext {
+                inner,
+                pipelines,
+                font_renderer,
+                ..
+            } = context;
+            let mut recorder = CommandRecorder::new(inner)?;
+            let textures = crate::engine::system::ttf::TextTextures::new(pipelines, inner);
+            let texts = (0..self.texts)
+                .map(|index| {
+                    let (x, y) = position(index, 61.0, 17.0);
+                    font_renderer.prepare_render(
+                        &textures,
+                        &format!("hotrod bench {index}"),
+                        Self::TEXT_SIZE,
+                        [255, 255, 255, 255],
+                        x,
+                        y,
+                    )
+                })
+                .collect::<Vec<_>>();
+            pipelines.texture.draw(recorder.recorder_mut(), &texts)?;
+            commands.push(recorder.finish()?);
+        }
+
+        Ok(commands)
+    }
+}
+
+/// How the [`BenchScene`]s are run, each scene is rendered for `warmup` without measuring and
+/// then for `duration` while measuring.
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct BenchConfig {
+    pub warmup: Duration,
+    pub duration: Duration,
+    pub scenes: Vec<BenchScene>,
+}
+
+impl Default for BenchConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            warmup: Duration::from_secs(1),
+            duration: Duration::from_secs(10),
+            scenes: Vec::default(),
+        }
+    }
+}
+
+impl BenchConfig {
+    /// The scenes that are used to track the performance across releases.
+    pub fn standard() -> Self {
+        Self::default()
+            .with_scene(BenchScene::new("sprites-10k").with_sprites(10_000))
+            .with_scene(BenchScene::new("textured-10k").with_textured(10_000))
+            .with_scene(BenchScene::new("lines-10k").with_lines(10_000))
+            .with_scene(BenchScene::new("texts-500").with_texts(500))
+            .with_scene(
+                BenchScene::new("mixed")
+                    .with_sprites(5_000)
+                    .with_lines(5_000)
+                    .with_texts(250),
+            )
+    }
+
+    #[inline]
+    pub fn with_warmup(mut self, warmup: Duration) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    #[inline]
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    #[inline]
+    pub fn with_scene(mut self, scene: BenchScene) -> Self {
+        self.scenes.push(scene);
+        self
+    }
+
+    /// Runs all scenes one after another. The frame rate is not limited, but the swapchain might
+    /// still wait for the vertical sync. Closing the window aborts the benchmark.
+    pub fn run(&self, engine: &mut Engine) -> Result<BenchReport, Error> {
+        let gpu = engine.gpu_info();
+        let mut report = BenchReport {
+            hotrod_version: env!("CARGO_PKG_VERSION").to_string(),
+            gpu: gpu.device_name.clone(),
+            driver: format!(
+                "{} {}",
+                gpu.driver_name.as_deref().unwrap_or("unknown"),
+                gpu.driver_version()
+            ),
+            vulkan_api: gpu.api_version.to_string(),
+            scenes: Vec::with_capacity(self.scenes.len()),
+        };
+
+        for scene in &self.scenes {
+            info!("Running bench scene {:?}", scene.name);
+            report.scenes.push(self.run_scene(engine, scene)?);
+        }
+
+        Ok(report)
+    }
+
+    fn run_scene(&self, engine: &mut Engine, scene: &BenchScene) -> Result<SceneReport, Error> {
+        let start = Instant::now();
+        let mut measure_start = None;
+        let mut frame_times = Vec::new();
+        let mut render_times = Vec::new();
+        let mut record_times = Vec::new();
+        let mut size = (0, 0);
+
+        loop {
+            let frame_start = Instant::now();
+            let mut record_time = Duration::ZERO;
+            if measure_start.is_none() && frame_start.duration_since(start) >= self.warmup {
+                measure_start = Some(frame_start);
+            }
+            if let Some(measure_start) = measure_start {
+                if frame_start.duration_since(measure_start) >= self.duration {
+                    break;
+                }
+            }
+
+            let response = engine.update(|ctx| {
+                if ctx
+                    .events
+                    .iter()
+                    .any(|e| matches!(e.event, Event::Quit { .. }))
+                {
+                    return Err(Error::Aborted);
+                }
+                size = (ctx.width, ctx.height);
+                let frame = ctx.frame;
+                let mut result = Ok(());
+                ctx.render(|context| {
+                    let record_start = Instant::now();
+                    let commands = scene.draw(context, frame);
+                    record_time = record_start.elapsed();
+                    match commands {
+                        Ok(commands) => commands,
+                        Err(e) => {
+                            result = Err(e);
+                            Vec::new()
+                        }
+                    }
+                })?;
+                result
+            });
+            response.data?;
+
+            if measure_start.is_some() {
+                frame_times.push(frame_start.elapsed());
+                render_times.push(engine.render_stats().duration);
+                record_times.push(record_time);
+            }
+        }
+
+        let duration = measure_start.map(|s| s.elapsed()).unwrap_or_default();
+        Ok(SceneReport {
+            scene: scene.clone(),
+            width: size.0,
+            height: size.1,
+            frames: frame_times.len() as u64,
+            duration_secs: duration.as_secs_f64(),
+            fps: frame_times.len() as f64 / duration.as_secs_f64().max(f64::EPSILON),
+            frame_time: FrameTimes::from(frame_times),
+            render_time: FrameTimes::from(render_times),
+            record_time: FrameTimes::from(record_times),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct BenchReport {
+    pub hotrod_version: String,
+    pub gpu: String,
+    pub driver: String,
+    pub vulkan_api: String,
+    pub scenes: Vec<SceneReport>,
+}
+
+impl BenchReport {
+    #[inline]
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn write_json(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_json()?)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct SceneReport {
+    pub scene: BenchScene,
+    pub width: u32,
+    pub height: u32,
+    /// The amount of measured frames
+    pub frames: u64,
+    pub duration_secs: f64,
+    pub fps: f64,
+    /// The duration of a whole [`Engine::update`]
+    pub frame_time: FrameTimes,
+    /// The duration of rendering the frame, see [`RenderStats`]
+    ///
+    /// [`RenderStats`]: crate::engine::RenderStats
+    pub render_time: FrameTimes,
+    /// The duration of recording the command buffers of the scene on the CPU, which includes
+    /// the reference counting of the bound pipelines, layouts and descriptor sets
+    pub record_time: FrameTimes,
+}
+
+/// The distribution of durations, in milliseconds.
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize,
+)]
+pub struct FrameTimes {
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl From<Vec<Duration>> for FrameTimes {
+    fn from(mut durations: Vec<Duration>) -> Self {
+        if durations.is_empty() {
+            return Self::default();
+        }
+
+        durations.sort_unstable();
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let percentile = |p: usize| millis(durations[(durations.len() - 1) * p / 100]);
+        Self {
+            min_ms: millis(durations[0]),
+            avg_ms: millis(durations.iter().sum::<Duration>()) / durations.len() as f64,
+            p50_ms: percentile(50),
+            p95_ms: percentile(95),
+            p99_ms: percentile(99),
+            max_ms: millis(durations[durations.len() - 1]),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("The benchmark was aborted by closing the window")]
+    Aborted,
+    #[error("Failed to render: {0}")]
+    DrawError(#[from] DrawError),
+    #[error("Vulkan System Error: {0}")]
+    VulkanSystemError(#[from] crate::engine::system::vulkan::Error),
+    #[error("Failed to serialize the report: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Failed to write the report: {0}")]
+    IoError(#[from] std::io::Error),
+}
@@ -0,0 +1,156 @@
+//! Settings for players who are sensitive to motion or to flashing lights. The engine limits the
+//! changes of the [`AmbientLight`] on its own, the helpers of [`crate::support`] (like the
+//! [`Weather`]) consult the options passed to them, and games can query them with
+//! [`Engine::accessibility`] for their own effects:
+//!
+//! ```no_run
+//! # use hotrod::engine::Engine;
+//! # let engine = Engine::default();
+//! let shake_amplitude = engine.accessibility().scale_motion(12.0);
+//! ```
+//!
+//! [`AmbientLight`]: crate::engine::system::vulkan::desc::binding_202_ambient_light::AmbientLight
+//! [`Weather`]: crate::support::weather::Weather
+//! [`Engine::accessibility`]: crate::engine::Engine::accessibility
+
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize),
+    serde(default)
+)]
+pub struct AccessibilityOptions {
+    /// Tones down particles, camera shakes and transitions, see
+    /// [`AccessibilityOptions::motion_scale`]
+    pub reduced_motion: bool,
+    /// The share of the motion that is kept with [`AccessibilityOptions::reduced_motion`], in
+    /// `0.0..=1.0`
+    pub reduced_motion_scale: f32,
+    /// Limits how fast the brightness of the whole screen changes, see [`FlashLimiter`]
+    pub limit_flashes: bool,
+    /// The maximum change of the relative luminance (`0.0..=1.0`) per second with
+    /// [`AccessibilityOptions::limit_flashes`]. The default needs more than a second for a
+    /// flash from black to white and back, well below the three flashes per second of the
+    /// WCAG.
+    pub max_luminance_change: f32,
+}
+
+impl Default for AccessibilityOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            reduced_motion: false,
+            reduced_motion_scale: 0.25,
+            limit_flashes: false,
+            max_luminance_change: 1.5,
+        }
+    }
+}
+
+impl AccessibilityOptions {
+    #[inline]
+    pub fn with_reduced_motion(mut self, reduced_motion: bool) -> Self {
+        self.reduced_motion = reduced_motion;
+        self
+    }
+
+    #[inline]
+    pub fn with_reduced_motion_scale(mut self, scale: f32) -> Self {
+        self.reduced_motion_scale = scale.clamp(0.0, 1.0);
+        self
+    }
+
+    #[inline]
+    pub fn with_limit_flashes(mut self, limit_flashes: bool) -> Self {
+        self.limit_flashes = limit_flashes;
+        self
+    }
+
+    #[inline]
+    pub fn with_max_luminance_change(mut self, max_luminance_change: f32) -> Self {
+        self.max_luminance_change = max_luminance_change;
+        self
+    }
+
+    /// The factor to multiply speeds, amplitudes and the amount of moving things with: `1.0`
+    /// unless [`AccessibilityOptions::reduced_motion`] is enabled.
+    #[inline]
+    pub fn motion_scale(&self) -> f32 {
+        if self.reduced_motion {
+            self.reduced_motion_scale.clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// The value multiplied with the [`AccessibilityOptions::motion_scale`].
+    #[inline]
+    pub fn scale_motion(&self, value: f32) -> f32 {
+        value * self.motion_scale()
+    }
+
+    /// The duration of an animated transition multiplied with the
+    /// [`AccessibilityOptions::motion_scale`], so a scale of zero skips the transition.
+    #[inline]
+    pub fn scale_transition(&self, duration: Duration) -> Duration {
+        duration.mul_f32(self.motion_scale())
+    }
+}
+
+/// Follows a color that covers the whole screen, like the ambient light or a fullscreen fade,
+/// and limits the change of its relative luminance to the
+/// [`AccessibilityOptions::max_luminance_change`] while
+/// [`AccessibilityOptions::limit_flashes`] is enabled. A sudden change, like a lightning strike
+/// or an explosion, is turned into a gradual one.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct FlashLimiter {
+    /// The previous color, [`None`] before the first one
+    previous: Option<[f32; 3]>,
+}
+
+impl FlashLimiter {
+    /// The relative luminance of a linear RGB color.
+    #[inline]
+    pub fn luminance([r, g, b]: [f32; 3]) -> f32 {
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    /// Continues from the given color without limiting the change to it.
+    #[inline]
+    pub fn reset(&mut self, rgb: [f32; 3]) {
+        self.previous = Some(rgb);
+    }
+
+    /// The color to show instead of `rgb`, `delta` after the previous call. The color moves from
+    /// the previous one towards `rgb` as far as the luminance is allowed to change.
+    pub fn limit(
+        &mut self,
+        rgb: [f32; 3],
+        delta: Duration,
+        options: &AccessibilityOptions,
+    ) -> [f32; 3] {
+        let previous = match self.previous {
+            Some(previous) if options.limit_flashes => previous,
+            _ => {
+                self.previous = Some(rgb);
+                return rgb;
+            }
+        };
+
+        let from = Self::luminance(previous);
+        let to = Self::luminance(rgb);
+        let max_change = options.max_luminance_change.max(0.0) * delta.as_secs_f32();
+        let change = to - from;
+        // the luminance is linear, so moving the color by a share moves the luminance by it
+        let share = if change.abs() > max_change {
+            max_change / change.abs()
+        } else {
+            1.0
+        };
+        let limited = std::array::from_fn(|i| previous[i] + (rgb[i] - previous[i]) * share);
+        self.previous = Some(limited);
+        limited
+    }
+}
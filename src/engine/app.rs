@@ -0,0 +1,109 @@
+//! A main loop for simple games: the [`Application`] is driven by [`Engine::run`], which polls
+//! the events, updates and renders the app, paces the frame rate and stops on errors or when the
+//! window is closed.
+//!
+//! ```no_run
+//! use hotrod::engine::app::{Application, LoopControl};
+//! use hotrod::engine::{BeforeRenderContext, Engine, RenderContext};
+//! use hotrod::engine::system::commands::RecordedCommands;
+//!
+//! #[derive(Default)]
+//! struct Game {
+//!     x: f32,
+//! }
+//!
+//! impl Application for Game {
+//!     fn update(&mut self, ctx: &mut BeforeRenderContext) -> LoopControl {
+//!         self.x += 100.0 * ctx.delta.as_secs_f32();
+//!         LoopControl::Continue
+//!     }
+//!
+//!     fn render(&mut self, _context: RenderContext) -> Vec<RecordedCommands> {
+//!         Vec::new()
+//!     }
+//! }
+//!
+//! let mut engine = Engine::default();
+//! engine.run(Game::default());
+//! ```
+//!
+//! [`Engine::run`]: crate::engine::Engine::run
+
+use crate::engine::event::EngineEvent;
+use crate::engine::system::commands::RecordedCommands;
+use crate::engine::system::vulkan::DrawError;
+use crate::engine::{BeforeRenderContext, Engine, RenderContext};
+use sdl2::event::Event;
+
+/// Whether [`Engine::run`] continues with the next frame.
+///
+/// [`Engine::run`]: crate::engine::Engine::run
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LoopControl {
+    #[default]
+    Continue,
+    Exit,
+}
+
+impl LoopControl {
+    /// [`LoopControl::Exit`] if either of both is.
+    #[inline]
+    pub fn and(self, other: LoopControl) -> LoopControl {
+        if self == LoopControl::Exit || other == LoopControl::Exit {
+            LoopControl::Exit
+        } else {
+            LoopControl::Continue
+        }
+    }
+}
+
+/// A game that is driven by [`Engine::run`]. Each frame, [`Application::on_event`] is called for
+/// the polled events, then [`Application::update`] and [`Application::render`]. Rendering needs
+/// the default [`RenderMode::Secondary`].
+///
+/// [`Engine::run`]: crate::engine::Engine::run
+/// [`RenderMode::Secondary`]: crate::engine::builder::RenderMode::Secondary
+pub trait Application {
+    /// Called for every polled event, before [`Application::update`]. Exits on
+    /// [`Event::Quit`] by default.
+    fn on_event(&mut self, event: &EngineEvent) -> LoopControl {
+        match event.event {
+            Event::Quit { .. } => LoopControl::Exit,
+            _ => LoopControl::Continue,
+        }
+    }
+
+    /// Updates the state of the game, the events of the frame are in the context as well.
+    fn update(&mut self, ctx: &mut BeforeRenderContext) -> LoopControl {
+        let _ = ctx;
+        LoopControl::Continue
+    }
+
+    /// The commands to render the frame with, see [`BeforeRenderContext::render`]. Not called
+    /// for the frame in which the app exits.
+    fn render(&mut self, context: RenderContext) -> Vec<RecordedCommands>;
+
+    /// Called before the first frame and whenever the size of the drawable changed, in pixels.
+    fn on_resize(&mut self, width: u32, height: u32) {
+        let _ = (width, height);
+    }
+
+    /// Called when rendering a frame failed. By default, [transient] errors (like while the
+    /// window is minimized) skip the frame, other errors are logged and exit.
+    ///
+    /// [transient]: DrawError::is_transient
+    fn on_render_error(&mut self, error: DrawError) -> LoopControl {
+        if error.is_transient() {
+            debug!("Skipped the frame: {error}");
+            LoopControl::Continue
+        } else {
+            error!("Failed to render the frame: {error}");
+            LoopControl::Exit
+        }
+    }
+
+    /// Called once after the last frame, for example to save the game.
+    fn on_exit(&mut self, engine: &mut Engine) {
+        let _ = engine;
+    }
+}
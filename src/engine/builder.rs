@@ -1,7 +1,17 @@
+use crate::engine::accessibility::AccessibilityOptions;
+use crate::engine::input::VirtualCursorConfig;
+use crate::engine::parts::sdl::WindowPlacement;
+use crate::engine::plugin::EnginePlugin;
+use crate::engine::system::resolution::DynamicResolution;
+use crate::engine::system::viewport::ViewportLayout;
+use crate::engine::system::vulkan::color_filter::ColorFilter;
+use crate::engine::system::vulkan::textures::UploadBudget;
 use crate::engine::{Engine, Error};
 use crate::support::image::RawRgbaImage;
 use std::borrow::Cow;
-use vulkano::image::SampleCount;
+use std::path::PathBuf;
+use vulkano::format::Format;
+use vulkano::image::{ImageUsage, SampleCount};
 use vulkano::instance::InstanceCreateInfo;
 
 pub struct EngineBuilder<'a> {
@@ -10,12 +20,36 @@ pub struct EngineBuilder<'a> {
     pub(crate) window_width: u32,
     pub(crate) window_height: u32,
     pub(crate) fullscreen: bool,
+    pub(crate) window_placement: WindowPlacement,
     pub(crate) instance_info: InstanceCreateInfo,
     pub(crate) target_frame_rate: u16,
+    pub(crate) fixed_update_rate: u16,
+    pub(crate) vsync: bool,
     pub(crate) background_clear_color: Option<[f32; 4]>,
     #[cfg(feature = "ttf-sdl2")]
     pub(crate) font_renderer_ttf: Option<Cow<'static, [u8]>>,
     pub(crate) msaa: Option<SampleCount>,
+    pub(crate) depth_buffer: Option<Format>,
+    pub(crate) swapchain_image_usage: ImageUsage,
+    pub(crate) dynamic_rendering: bool,
+    pub(crate) render_mode: RenderMode,
+    pub(crate) upload_budget: UploadBudget,
+    pub(crate) dynamic_resolution: Option<DynamicResolution>,
+    pub(crate) viewport_layout: ViewportLayout,
+    pub(crate) crash_report_dir: Option<PathBuf>,
+    pub(crate) pipeline_cache_file: Option<PathBuf>,
+    pub(crate) plugins: Vec<Box<dyn EnginePlugin>>,
+    pub(crate) virtual_cursor: Option<VirtualCursorConfig>,
+    pub(crate) color_filters: bool,
+    pub(crate) color_filter: Option<ColorFilter>,
+    pub(crate) frame_capture: bool,
+    pub(crate) accessibility: AccessibilityOptions,
+    #[cfg(feature = "ui-egui")]
+    pub(crate) egui_options: crate::engine::system::vulkan::egui::EguiPipelineOptions,
+    #[cfg(feature = "audio")]
+    pub(crate) audio: Option<crate::engine::system::audio::AudioConfig>,
+    #[cfg(feature = "steamworks")]
+    pub(crate) steam_app_id: Option<u32>,
 }
 
 impl EngineBuilder<'_> {
@@ -60,12 +94,40 @@ impl EngineBuilder<'_> {
         self
     }
 
+    /// The display and the position to open the window at, for example the
+    /// [`EngineConfig::window_placement`] of the previous run.
+    #[inline]
+    pub fn with_window_placement(mut self, placement: WindowPlacement) -> Self {
+        self.window_placement = placement;
+        self
+    }
+
     #[inline]
     pub fn with_target_frame_rate(mut self, target_frame_rate: u16) -> Self {
         self.target_frame_rate = target_frame_rate;
         self
     }
 
+    /// The steps per second of the simulation in [`Engine::run_fixed`], independent of the
+    /// target frame rate.
+    #[inline]
+    pub fn with_fixed_update_rate(mut self, fixed_update_rate: u16) -> Self {
+        self.fixed_update_rate = fixed_update_rate;
+        self
+    }
+
+    /// Whether the frame rate is paced by the vsync of the platform instead of sleeping in
+    /// [`FpsManager::delay`], see [`FpsManager::set_vsync`]. Enabled by default on Android, where
+    /// the display refresh rate is used as the target frame rate.
+    ///
+    /// [`FpsManager::delay`]: crate::engine::system::fps::FpsManager::delay
+    /// [`FpsManager::set_vsync`]: crate::engine::system::fps::FpsManager::set_vsync
+    #[inline]
+    pub fn with_vsync(mut self, vsync: bool) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
     #[inline]
     pub fn with_background_clear_color(mut self, color: [f32; 4]) -> Self {
         self.background_clear_color = Some(color);
@@ -82,18 +144,273 @@ impl EngineBuilder<'_> {
         self
     }
 
+    /// Renders with the given samples per pixel. The frames are rendered into a transient
+    /// multisampled image, which is resolved into the swapchain image (or the intermediate image)
+    /// at the end of the pass, see [`RenderPassLayout`]. The render pass, the dynamic rendering
+    /// and the [`RenderTarget`]s share the samples, so every pipeline created with
+    /// [`VulkanSystem::graphics_pipeline_render_pass_info`] matches them. If the device does not
+    /// support the sample count, the next lower one is used instead.
+    ///
+    /// [`RenderPassLayout`]: crate::engine::system::vulkan::utils::pipeline::RenderPassLayout
+    /// [`RenderTarget`]: crate::engine::system::vulkan::render_target::RenderTarget
+    /// [`VulkanSystem::graphics_pipeline_render_pass_info`]: crate::engine::system::vulkan::system::VulkanSystem::graphics_pipeline_render_pass_info
     #[inline]
     pub fn with_msaa(mut self, msaa: SampleCount) -> Self {
         self.msaa = Some(msaa);
         self
     }
 
+    /// Adds a depth attachment of the given format to the render pass, which is cleared to `1.0`
+    /// every frame. Pipelines that want z-ordering instead of being sorted manually can use
+    /// [`GraphicsPipelineRenderPassInfo::depth_tested_stencil_state`]. [`Format::D16_UNORM`] is
+    /// supported by all devices, [`Format::D32_SFLOAT`] by most.
+    ///
+    /// [`GraphicsPipelineRenderPassInfo::depth_tested_stencil_state`]: crate::engine::system::vulkan::system::GraphicsPipelineRenderPassInfo::depth_tested_stencil_state
+    #[inline]
+    pub fn with_depth_buffer(mut self, format: Format) -> Self {
+        self.depth_buffer = Some(format);
+        self
+    }
+
+    /// Requests additional usages of the images that are rendered into, like
+    /// [`ImageUsage::TRANSFER_SRC`] for screenshots or [`ImageUsage::STORAGE`] for post effects
+    /// in compute shaders. If the surface does not support the usage for the swapchain images,
+    /// the frames are rendered into intermediate images which are copied into the swapchain, see
+    /// [`VulkanSystem::image_usage`]. Building fails if the format of the window does not support
+    /// the usage at all, which is common for [`ImageUsage::STORAGE`] and `SRGB` formats.
+    ///
+    /// [`VulkanSystem::image_usage`]: crate::engine::system::vulkan::system::VulkanSystem::image_usage
+    #[inline]
+    pub fn with_swapchain_image_usage(mut self, usage: ImageUsage) -> Self {
+        self.swapchain_image_usage = usage;
+        self
+    }
+
+    /// Renders the frames with dynamic rendering instead of a render pass and framebuffers, see
+    /// [`VulkanSystem::with_dynamic_rendering`]. Disabled by default.
+    ///
+    /// [`VulkanSystem::with_dynamic_rendering`]: crate::engine::system::vulkan::system::VulkanSystem::with_dynamic_rendering
+    #[inline]
+    pub fn with_dynamic_rendering(mut self, enabled: bool) -> Self {
+        self.dynamic_rendering = enabled;
+        self
+    }
+
+    /// How the game records its commands, see [`RenderMode`].
+    #[inline]
+    pub fn with_render_mode(mut self, mode: RenderMode) -> Self {
+        self.render_mode = mode;
+        self
+    }
+
+    /// Renders the world at a resolution that is lowered while the frame times exceed their
+    /// target, while the UI stays at the native resolution. Only the commands passed as world to
+    /// [`BeforeRenderContext::render_layered`] are scaled.
+    ///
+    /// [`BeforeRenderContext::render_layered`]: crate::engine::BeforeRenderContext::render_layered
+    #[inline]
+    pub fn with_dynamic_resolution(mut self, resolution: DynamicResolution) -> Self {
+        self.dynamic_resolution = Some(resolution);
+        self
+    }
+
+    /// Enforces an aspect ratio with letterbox bars or splits the window into several viewports,
+    /// see [`Engine::set_viewport_layout`].
+    #[inline]
+    pub fn with_viewport_layout(mut self, layout: ViewportLayout) -> Self {
+        self.viewport_layout = layout;
+        self
+    }
+
+    /// Limits the amount of texture uploads per frame to avoid hitches when many textures are
+    /// loaded at once. Until uploaded, textures are drawn with the missing [`PlaceholderTextures`].
+    ///
+    /// [`PlaceholderTextures`]: crate::engine::system::vulkan::textures::PlaceholderTextures
+    #[inline]
+    pub fn with_texture_upload_budget(mut self, budget: UploadBudget) -> Self {
+        self.upload_budget = budget;
+        self
+    }
+
+    /// Initializes Steam for the given app id, see [`Steam`]. If the game was not launched
+    /// through Steam, building fails with [`steam::Error::RestartRequired`] while Steam restarts
+    /// it; place a `steam_appid.txt` next to the executable to prevent this during development.
+    ///
+    /// [`Steam`]: crate::engine::system::steam::Steam
+    /// [`steam::Error::RestartRequired`]: crate::engine::system::steam::Error::RestartRequired
+    #[inline]
+    #[cfg(feature = "steamworks")]
+    pub fn with_steam_app_id(mut self, app_id: u32) -> Self {
+        self.steam_app_id = Some(app_id);
+        self
+    }
+
+    /// Writes a report into the given directory when the game panics, see
+    /// [`crash::install_panic_hook`].
+    ///
+    /// [`crash::install_panic_hook`]: crate::engine::crash::install_panic_hook
+    #[inline]
+    pub fn with_crash_reports(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.crash_report_dir = Some(dir.into());
+        self
+    }
+
+    /// Loads the compiled pipelines of previous runs from the file and writes them back when the
+    /// [`Engine`] is dropped, which avoids the stutter of compiling them again. A file of
+    /// another device or driver is ignored. See [`VulkanSystem::with_pipeline_cache_file`].
+    ///
+    /// [`VulkanSystem::with_pipeline_cache_file`]: crate::engine::system::vulkan::system::VulkanSystem::with_pipeline_cache_file
+    #[inline]
+    pub fn with_pipeline_cache_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.pipeline_cache_file = Some(path.into());
+        self
+    }
+
+    /// The blend mode and output color space of the [`EguiPipeline`], for example when the
+    /// swapchain is not sRGB. By default, the [`EguiPipelineOptions::default`] are used.
+    ///
+    /// [`EguiPipeline`]: crate::engine::system::vulkan::egui::EguiPipeline
+    /// [`EguiPipelineOptions::default`]: crate::engine::system::vulkan::egui::EguiPipelineOptions
+    #[inline]
+    #[cfg(feature = "ui-egui")]
+    pub fn with_egui_options(
+        mut self,
+        options: crate::engine::system::vulkan::egui::EguiPipelineOptions,
+    ) -> Self {
+        self.egui_options = options;
+        self
+    }
+
+    /// Opens the audio device with the given configuration, see [`AudioSystem`]. By default, the
+    /// audio device is opened with the [`AudioConfig::default`].
+    ///
+    /// [`AudioSystem`]: crate::engine::system::audio::AudioSystem
+    /// [`AudioConfig::default`]: crate::engine::system::audio::AudioConfig::default
+    #[inline]
+    #[cfg(feature = "audio")]
+    pub fn with_audio(mut self, config: crate::engine::system::audio::AudioConfig) -> Self {
+        self.audio = Some(config);
+        self
+    }
+
+    /// Does not open an audio device, for example for dedicated tools.
+    #[inline]
+    #[cfg(feature = "audio")]
+    pub fn without_audio(mut self) -> Self {
+        self.audio = None;
+        self
+    }
+
+    /// Adds a plugin, which is built together with the engine, see [`EnginePlugin`].
+    #[inline]
+    pub fn with_plugin(mut self, plugin: impl EnginePlugin) -> Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    /// Draws a [`VirtualCursor`] that is moved with a game controller and synthesizes mouse
+    /// events for egui and the game, see [`Engine::virtual_cursor_mut`] to set its texture. The
+    /// [`ThreadedEngine`] neither moves nor draws it.
+    ///
+    /// [`VirtualCursor`]: crate::engine::input::VirtualCursor
+    /// [`ThreadedEngine`]: crate::engine::threaded::ThreadedEngine
+    #[inline]
+    pub fn with_virtual_cursor(mut self, config: VirtualCursorConfig) -> Self {
+        self.virtual_cursor = Some(config);
+        self
+    }
+
+    /// Requests [`ImageUsage::TRANSFER_SRC`] for the images that are rendered into, so that a
+    /// [`ColorFilter`] can be enabled at runtime with [`Engine::set_color_filter`]. Disabled by
+    /// default, unless a filter is set with [`EngineBuilder::with_color_filter`].
+    #[inline]
+    pub fn with_color_filters(mut self, enabled: bool) -> Self {
+        self.color_filters = enabled;
+        self
+    }
+
+    /// Allows reading back the rendered frames, for example to capture a frame sequence with
+    /// [`Engine::start_frame_capture`]. This adds [`ImageUsage::TRANSFER_SRC`] to the rendered
+    /// images, which might require intermediate images on some platforms.
+    #[inline]
+    pub fn with_frame_capture(mut self, enabled: bool) -> Self {
+        self.frame_capture = enabled;
+        self
+    }
+
+    /// The [`ColorFilter`] to apply from the first frame on, like a daltonization the player
+    /// enabled in the settings, see [`Engine::set_color_filter`].
+    #[inline]
+    pub fn with_color_filter(mut self, filter: Option<ColorFilter>) -> Self {
+        self.color_filter = filter;
+        self
+    }
+
+    /// The motion and flash settings of the player, see [`Engine::set_accessibility`].
+    #[inline]
+    pub fn with_accessibility(mut self, options: AccessibilityOptions) -> Self {
+        self.accessibility = options;
+        self
+    }
+
+    /// Applies the user adjustable settings of the [`EngineConfig`], for example loaded from a
+    /// settings file. An invalid MSAA sample count disables MSAA.
+    pub fn with_config(mut self, config: &EngineConfig) -> Self {
+        self.window_width = config.window_width;
+        self.window_height = config.window_height;
+        self.fullscreen = config.fullscreen;
+        self.window_placement = config.window_placement;
+        self.target_frame_rate = config.target_frame_rate;
+        self.background_clear_color = config.background_clear_color;
+        self.msaa = config.msaa_samples.and_then(|samples| {
+            SampleCount::try_from(samples)
+                .map_err(|_| warn!("Ignoring invalid MSAA sample count {samples}"))
+                .ok()
+        });
+        self.upload_budget = config.upload_budget;
+        self.color_filter = config.color_filter;
+        self.accessibility = config.accessibility;
+        self
+    }
+
+    /// The user adjustable settings of this builder, to be stored in a settings file.
+    pub fn config(&self) -> EngineConfig {
+        EngineConfig {
+            window_width: self.window_width,
+            window_height: self.window_height,
+            fullscreen: self.fullscreen,
+            window_placement: self.window_placement,
+            target_frame_rate: self.target_frame_rate,
+            background_clear_color: self.background_clear_color,
+            msaa_samples: self.msaa.map(u32::from),
+            upload_budget: self.upload_budget,
+            color_filter: self.color_filter,
+            accessibility: self.accessibility,
+        }
+    }
+
     #[inline]
     pub fn build(self) -> Result<Engine, Error> {
         Engine::new(self)
     }
 }
 
+/// How the commands of a frame are recorded, see [`EngineBuilder::with_render_mode`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum RenderMode {
+    /// The game returns secondary command buffers, which can be recorded on multiple threads,
+    /// see [`BeforeRenderContext::render`].
+    ///
+    /// [`BeforeRenderContext::render`]: crate::engine::BeforeRenderContext::render
+    #[default]
+    Secondary,
+    /// The game records directly into the primary command buffer of the frame, which is simpler
+    /// and cheaper for small apps, see [`BeforeRenderContext::render_inline`].
+    ///
+    /// [`BeforeRenderContext::render_inline`]: crate::engine::BeforeRenderContext::render_inline
+    Inline,
+}
+
 impl Default for EngineBuilder<'static> {
     #[inline]
     fn default() -> Self {
@@ -103,12 +420,66 @@ impl Default for EngineBuilder<'static> {
             window_width: 1024,
             window_height: 768,
             fullscreen: false,
+            window_placement: WindowPlacement::Undefined,
             instance_info: InstanceCreateInfo::application_from_cargo_toml(),
             target_frame_rate: 60,
+            fixed_update_rate: 60,
+            vsync: cfg!(target_os = "android"),
             background_clear_color: None,
             #[cfg(feature = "ttf-sdl2")]
             font_renderer_ttf: None,
             msaa: None,
+            depth_buffer: None,
+            swapchain_image_usage: ImageUsage::empty(),
+            dynamic_rendering: false,
+            render_mode: RenderMode::default(),
+            upload_budget: UploadBudget::default(),
+            dynamic_resolution: None,
+            viewport_layout: ViewportLayout::default(),
+            crash_report_dir: None,
+            pipeline_cache_file: None,
+            plugins: Vec::default(),
+            virtual_cursor: None,
+            color_filters: false,
+            color_filter: None,
+            frame_capture: false,
+            accessibility: AccessibilityOptions::default(),
+            #[cfg(feature = "ui-egui")]
+            egui_options: Default::default(),
+            #[cfg(feature = "audio")]
+            audio: Some(crate::engine::system::audio::AudioConfig::default()),
+            #[cfg(feature = "steamworks")]
+            steam_app_id: None,
         }
     }
 }
+
+/// The user adjustable subset of the [`EngineBuilder`] settings. Missing fields are filled with
+/// their defaults when deserializing, so settings files of older releases can still be loaded.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize),
+    serde(default)
+)]
+pub struct EngineConfig {
+    pub window_width: u32,
+    pub window_height: u32,
+    pub fullscreen: bool,
+    /// See [`Engine::update_window_config`] to remember the placement between runs
+    pub window_placement: WindowPlacement,
+    pub target_frame_rate: u16,
+    pub background_clear_color: Option<[f32; 4]>,
+    /// The MSAA sample count (`2`, `4`, `8`, ...), [`None`] to disable MSAA
+    pub msaa_samples: Option<u32>,
+    pub upload_budget: UploadBudget,
+    pub color_filter: Option<ColorFilter>,
+    pub accessibility: AccessibilityOptions,
+}
+
+impl Default for EngineConfig {
+    #[inline]
+    fn default() -> Self {
+        EngineBuilder::default().config()
+    }
+}
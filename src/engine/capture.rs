@@ -0,0 +1,154 @@
+//! Offline capture of numbered PNG frames, for example for trailers. While a
+//! [`FrameSequence`] is captured with [`Engine::start_frame_capture`], the engine pretends that
+//! exactly one frame of the sequence passed between two updates: the
+//! [`BeforeRenderContext::delta`], the `elapsed` time and the [`FrameTime`] of the shaders
+//! advance at the frame rate of the sequence, no matter how long rendering and encoding the
+//! frame actually took. [`Engine::delay`] does not sleep meanwhile.
+//!
+//! This needs [`EngineBuilder::with_frame_capture`].
+//!
+//! ```no_run
+//! # use hotrod::engine::builder::EngineBuilder;
+//! # use hotrod::engine::capture::FrameSequence;
+//! let mut engine = EngineBuilder::default()
+//!     .with_frame_capture(true)
+//!     .build()
+//!     .unwrap();
+//! engine
+//!     .start_frame_capture(FrameSequence::new("trailer", 60).with_frame_limit(60 * 30))
+//!     .unwrap();
+//! ```
+//!
+//! [`Engine::start_frame_capture`]: crate::engine::Engine::start_frame_capture
+//! [`Engine::delay`]: crate::engine::Engine::delay
+//! [`BeforeRenderContext::delta`]: crate::engine::BeforeRenderContext::delta
+//! [`FrameTime`]: crate::engine::system::vulkan::desc::binding_102_frame_time::FrameTime
+//! [`EngineBuilder::with_frame_capture`]: crate::engine::builder::EngineBuilder::with_frame_capture
+
+use crate::engine::system::vulkan::ReadbackError;
+use crate::support::image::RawRgbaImage;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where and at which rate frames are captured, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameSequence {
+    directory: PathBuf,
+    prefix: String,
+    frame_rate: u16,
+    /// Stops the capture after this amount of frames
+    frame_limit: Option<u64>,
+    /// The amount of frames that were updated
+    updated: u64,
+    /// The amount of frames that were written
+    written: u64,
+    /// The elapsed time of the engine when the capture started
+    start_elapsed: Duration,
+}
+
+impl FrameSequence {
+    /// Writes the frames as `frame_000000.png`, `frame_000001.png`, ... into the `directory`,
+    /// which is created if needed.
+    pub fn new(directory: impl Into<PathBuf>, frame_rate: u16) -> Self {
+        Self {
+            directory: directory.into(),
+            prefix: "frame_".to_string(),
+            frame_rate: frame_rate.max(1),
+            frame_limit: None,
+            updated: 0,
+            written: 0,
+            start_elapsed: Duration::ZERO,
+        }
+    }
+
+    #[inline]
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Stops the capture by itself after the amount of frames.
+    #[inline]
+    pub fn with_frame_limit(mut self, frames: u64) -> Self {
+        self.frame_limit = Some(frames);
+        self
+    }
+
+    #[inline]
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    #[inline]
+    pub fn frame_rate(&self) -> u16 {
+        self.frame_rate
+    }
+
+    /// The time between two frames of the sequence.
+    #[inline]
+    pub fn frame_delta(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / f64::from(self.frame_rate))
+    }
+
+    /// The amount of frames written so far.
+    #[inline]
+    pub fn written_frames(&self) -> u64 {
+        self.written
+    }
+
+    /// Whether the [`FrameSequence::with_frame_limit`] was reached.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.frame_limit.is_some_and(|limit| self.written >= limit)
+    }
+
+    /// The path of the frame with the index.
+    #[inline]
+    pub fn frame_path(&self, index: u64) -> PathBuf {
+        self.directory
+            .join(format!("{}{index:06}.png", self.prefix))
+    }
+
+    pub(crate) fn start(&mut self, elapsed: Duration) -> Result<(), FrameCaptureError> {
+        std::fs::create_dir_all(&self.directory)?;
+        self.start_elapsed = elapsed;
+        Ok(())
+    }
+
+    /// The `delta` and the `elapsed` time of the next frame.
+    pub(crate) fn next_frame(&mut self) -> (Duration, Duration) {
+        // the first frame continues from the time the capture started
+        let elapsed = self.start_elapsed
+            + Duration::from_secs_f64(self.updated as f64 / f64::from(self.frame_rate));
+        self.updated += 1;
+        (self.frame_delta(), elapsed)
+    }
+
+    pub(crate) fn write(&mut self, image: &RawRgbaImage) -> Result<(), FrameCaptureError> {
+        // the alpha of the swapchain image is meaningless for a video frame
+        let data = image
+            .data()
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect::<Vec<u8>>();
+        image::save_buffer(
+            self.frame_path(self.written),
+            &data,
+            image.width(),
+            image.height(),
+            image::ColorType::Rgba8,
+        )?;
+        self.written += 1;
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FrameCaptureError {
+    #[error("Failed to read back the frame: {0}")]
+    ReadbackError(#[from] ReadbackError),
+    #[error("Failed to encode the frame: {0}")]
+    ImageError(#[from] image::ImageError),
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+}
@@ -0,0 +1,133 @@
+use crate::engine::system::vulkan::system::VulkanSystem;
+use crate::engine::RenderStats;
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext {
+    device: None,
+    render_stats: None,
+});
+
+/// What is known about the engine at the time of a panic.
+struct CrashContext {
+    device: Option<String>,
+    render_stats: Option<RenderStats>,
+}
+
+/// Installs a panic hook that writes a report into the given directory before calling the
+/// previously installed hook. The report contains the panic message, a backtrace, the chosen GPU
+/// and its driver, the swapchain format, the enabled device features and the [`RenderStats`] of
+/// the last frame, so users can attach it to bug reports about crashes on drivers the developers
+/// have no access to.
+///
+/// This is called by the engine when built with [`EngineBuilder::with_crash_reports`], but can
+/// be called earlier to also cover panics during the initialization. Installing it again only
+/// adds another hook.
+///
+/// [`EngineBuilder::with_crash_reports`]: crate::engine::builder::EngineBuilder::with_crash_reports
+pub fn install_panic_hook(dir: impl Into<PathBuf>) {
+    let dir = dir.into();
+    let previous = std::panic::take_hook();
+    INSTALLED.store(true, Ordering::Relaxed);
+    std::panic::set_hook(Box::new(move |info| {
+        match write_report(&dir, info) {
+            Ok(path) => eprintln!("A crash report was written to {}", path.display()),
+            Err(e) => eprintln!(
+                "Failed to write the crash report into {}: {e}",
+                dir.display()
+            ),
+        }
+        previous(info);
+    }));
+}
+
+/// Whether [`install_panic_hook`] was called.
+#[inline]
+pub fn is_panic_hook_installed() -> bool {
+    INSTALLED.load(Ordering::Relaxed)
+}
+
+pub(crate) fn record_device(vulkan: &VulkanSystem) {
+    if !is_panic_hook_installed() {
+        return;
+    }
+
+    let mut description = vulkan.gpu_info().to_string();
+    if let Some(swapchain) = vulkan.swapchain() {
+        let _ = writeln!(
+            description,
+            "Swapchain: {:?}, {} images",
+            swapchain.present_mode(),
+            swapchain.image_count()
+        );
+    }
+
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.device = Some(description);
+    }
+}
+
+pub(crate) fn record_render_stats(stats: RenderStats) {
+    if is_panic_hook_installed() {
+        if let Ok(mut context) = CONTEXT.lock() {
+            context.render_stats = Some(stats);
+        }
+    }
+}
+
+fn write_report(dir: &Path, info: &PanicHookInfo) -> std::io::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut report = String::new();
+    let _ = writeln!(report, "hotrod {} crash report", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "Time: {timestamp} (unix)");
+    let _ = writeln!(
+        report,
+        "OS: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(
+        report,
+        "Thread: {}",
+        std::thread::current().name().unwrap_or("<unnamed>")
+    );
+    let _ = writeln!(report, "Panic: {info}");
+    let _ = writeln!(report);
+
+    // never block (or panic again) while the panicking thread might hold the lock
+    match CONTEXT.try_lock() {
+        Ok(context) => {
+            report.push_str(
+                context
+                    .device
+                    .as_deref()
+                    .unwrap_or("GPU: not initialized\n"),
+            );
+            match &context.render_stats {
+                Some(stats) => {
+                    let _ = writeln!(report, "Last frame: {stats:?}");
+                }
+                None => report.push_str("Last frame: none rendered\n"),
+            }
+        }
+        Err(_) => report.push_str("Engine state: unavailable\n"),
+    }
+
+    let _ = writeln!(report);
+    let _ = writeln!(report, "Backtrace:\n{}", Backtrace::force_capture());
+
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("crash-{timestamp}.txt"));
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
@@ -0,0 +1,65 @@
+use crate::engine::parts::sdl::DisplayInfo;
+use sdl2::event::Event;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+
+/// A sdl2 [`Event`] together with the point in time it happened at and the frame it was polled
+/// in. This allows to implement input buffering windows or double-click detection independent
+/// of the frame rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineEvent {
+    pub event: Event,
+    /// When the event was created by sdl2, on the same clock as [`Instant::now`]
+    pub timestamp: Instant,
+    /// The frame in which the event was polled, see [`Engine::frame`]
+    ///
+    /// [`Engine::frame`]: crate::engine::Engine::frame
+    pub frame: u64,
+    /// Set by the engine on the event after which the window was shown on a different display
+    /// or the display changed, like its DPI or refresh rate. The engine already adapted the
+    /// scale of egui and (with vsync) the target frame rate, but games might want to update
+    /// their settings UI.
+    pub display_changed: Option<DisplayInfo>,
+}
+
+impl EngineEvent {
+    /// Converts the sdl2 timestamp (milliseconds since sdl2 was initialized) of the event to an
+    /// [`Instant`] by its age relative to `now` and `ticks_now` (see
+    /// [`sdl2::TimerSubsystem::ticks`]), which must be sampled together.
+    pub(crate) fn new(event: Event, frame: u64, now: Instant, ticks_now: u32) -> Self {
+        let age = Duration::from_millis(u64::from(ticks_now.saturating_sub(event.get_timestamp())));
+        Self {
+            timestamp: now.checked_sub(age).unwrap_or(now),
+            event,
+            frame,
+            display_changed: None,
+        }
+    }
+
+    /// The time that passed between this and the `earlier` event.
+    #[inline]
+    pub fn duration_since(&self, earlier: &EngineEvent) -> Duration {
+        self.timestamp.saturating_duration_since(earlier.timestamp)
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> Event {
+        self.event
+    }
+}
+
+impl Deref for EngineEvent {
+    type Target = Event;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.event
+    }
+}
+
+impl From<EngineEvent> for Event {
+    #[inline]
+    fn from(value: EngineEvent) -> Self {
+        value.event
+    }
+}
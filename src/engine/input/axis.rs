@@ -0,0 +1,296 @@
+use crate::engine::types::world2d::Dim;
+
+/// Maps the magnitude (`0..=1`) of an axis after the dead zone to the output magnitude.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    /// Finer control for small deflections
+    Quadratic,
+    /// Even finer control for small deflections
+    Cubic,
+    /// `magnitude.powf(exponent)`, values below `1.0` make small deflections more sensitive.
+    /// Create it with [`ResponseCurve::exponential`], exponents below
+    /// [`ResponseCurve::MIN_EXPONENT`] are raised to it.
+    Exponential(f32),
+}
+
+impl ResponseCurve {
+    /// The smallest exponent of [`ResponseCurve::Exponential`]. With an exponent of zero or
+    /// below, the smallest deflection would already be reported as fully deflected.
+    pub const MIN_EXPONENT: f32 = 0.05;
+
+    /// An [`ResponseCurve::Exponential`] curve with the exponent clamped to at least
+    /// [`ResponseCurve::MIN_EXPONENT`]. `NaN` is treated as linear.
+    #[inline]
+    pub fn exponential(exponent: f32) -> Self {
+        Self::Exponential(Self::clamp_exponent(exponent))
+    }
+
+    #[inline]
+    fn clamp_exponent(exponent: f32) -> f32 {
+        if exponent.is_nan() {
+            1.0
+        } else {
+            exponent.max(Self::MIN_EXPONENT)
+        }
+    }
+
+    /// Maps the magnitude in `0..=1`, a magnitude of zero (like within the dead zone) stays zero
+    /// for every curve.
+    #[inline]
+    pub fn apply(self, magnitude: f32) -> f32 {
+        if magnitude <= 0.0 {
+            return 0.0;
+        }
+        match self {
+            ResponseCurve::Linear => magnitude,
+            ResponseCurve::Quadratic => magnitude * magnitude,
+            ResponseCurve::Cubic => magnitude * magnitude * magnitude,
+            // also clamped here, as the variant can be created (or deserialized) directly
+            ResponseCurve::Exponential(exponent) => magnitude.powf(Self::clamp_exponent(exponent)),
+        }
+    }
+}
+
+/// A normalized axis value in the range `-1.0..=1.0`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, PartialOrd)]
+pub struct AxisValue(f32);
+
+impl AxisValue {
+    pub const NEUTRAL: Self = Self(0.0);
+
+    /// Clamps the value into `-1.0..=1.0`.
+    #[inline]
+    pub fn new(value: f32) -> Self {
+        Self(value.clamp(-1.0, 1.0))
+    }
+
+    /// Normalizes the raw value of an sdl2 controller axis.
+    #[inline]
+    pub fn from_raw(value: i16) -> Self {
+        Self::new(f32::from(value) / f32::from(i16::MAX))
+    }
+
+    #[inline]
+    pub fn value(self) -> f32 {
+        self.0
+    }
+
+    #[inline]
+    pub fn is_neutral(self) -> bool {
+        self.0 == 0.0
+    }
+}
+
+impl From<AxisValue> for f32 {
+    #[inline]
+    fn from(value: AxisValue) -> Self {
+        value.0
+    }
+}
+
+/// The normalized value of two axes (like a thumb stick), within the unit circle.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct StickValue {
+    pub x: AxisValue,
+    pub y: AxisValue,
+}
+
+impl StickValue {
+    pub const NEUTRAL: Self = Self {
+        x: AxisValue::NEUTRAL,
+        y: AxisValue::NEUTRAL,
+    };
+
+    #[inline]
+    pub fn magnitude(self) -> f32 {
+        self.x.0.hypot(self.y.0)
+    }
+
+    #[inline]
+    pub fn is_neutral(self) -> bool {
+        self.x.is_neutral() && self.y.is_neutral()
+    }
+
+    /// The value scaled by `scale`, e.g. a movement speed or the screen distance to pan a
+    /// [`Map2dView`] by per second.
+    ///
+    /// [`Map2dView`]: crate::support::world2d::view::Map2dView
+    #[inline]
+    pub fn to_dim(self, scale: f32) -> Dim<f32> {
+        Dim::new(self.x.0 * scale, self.y.0 * scale)
+    }
+}
+
+/// Configuration of a single axis, applied in the order: dead zone, response curve,
+/// sensitivity and inversion.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct AxisConfig {
+    /// Magnitudes below are reported as neutral, the remaining range is rescaled to `0..=1`
+    pub dead_zone: f32,
+    /// Magnitudes above are reported as fully deflected, for axes that never reach their end
+    pub outer_dead_zone: f32,
+    pub curve: ResponseCurve,
+    pub sensitivity: f32,
+    pub inverted: bool,
+}
+
+impl Default for AxisConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.1,
+            outer_dead_zone: 1.0,
+            curve: ResponseCurve::default(),
+            sensitivity: 1.0,
+            inverted: false,
+        }
+    }
+}
+
+impl AxisConfig {
+    #[inline]
+    pub fn with_dead_zone(mut self, dead_zone: f32) -> Self {
+        self.dead_zone = dead_zone;
+        self
+    }
+
+    #[inline]
+    pub fn with_outer_dead_zone(mut self, outer_dead_zone: f32) -> Self {
+        self.outer_dead_zone = outer_dead_zone;
+        self
+    }
+
+    #[inline]
+    pub fn with_curve(mut self, curve: ResponseCurve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    #[inline]
+    pub fn with_sensitivity(mut self, sensitivity: f32) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+
+    #[inline]
+    pub fn with_inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    #[inline]
+    pub fn apply_raw(&self, value: i16) -> AxisValue {
+        self.apply(AxisValue::from_raw(value).value())
+    }
+
+    pub fn apply(&self, value: f32) -> AxisValue {
+        let magnitude = rescale(value.abs(), self.dead_zone, self.outer_dead_zone);
+        if magnitude == 0.0 {
+            // within the dead zone, before the curve could map it to anything else
+            return AxisValue::NEUTRAL;
+        }
+        let magnitude = self.curve.apply(magnitude) * self.sensitivity;
+        let sign = if self.inverted { -1.0 } else { 1.0 };
+        AxisValue::new(magnitude.copysign(value) * sign)
+    }
+}
+
+/// Configuration of two axes that belong together, like the axes of a thumb stick.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct StickConfig {
+    /// Deflections with a magnitude below are reported as neutral; unlike an axial dead zone,
+    /// this does not snap diagonal movement to the axes
+    pub radial_dead_zone: f32,
+    /// Applied after the radial dead zone, usually with an axial dead zone of `0.0`
+    pub x: AxisConfig,
+    /// Applied after the radial dead zone, usually with an axial dead zone of `0.0`
+    pub y: AxisConfig,
+}
+
+impl Default for StickConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            radial_dead_zone: 0.15,
+            x: AxisConfig::default().with_dead_zone(0.0),
+            y: AxisConfig::default().with_dead_zone(0.0),
+        }
+    }
+}
+
+impl StickConfig {
+    #[inline]
+    pub fn with_radial_dead_zone(mut self, dead_zone: f32) -> Self {
+        self.radial_dead_zone = dead_zone;
+        self
+    }
+
+    #[inline]
+    pub fn with_x(mut self, x: AxisConfig) -> Self {
+        self.x = x;
+        self
+    }
+
+    #[inline]
+    pub fn with_y(mut self, y: AxisConfig) -> Self {
+        self.y = y;
+        self
+    }
+
+    #[inline]
+    pub fn apply_raw(&self, x: i16, y: i16) -> StickValue {
+        self.apply(
+            AxisValue::from_raw(x).value(),
+            AxisValue::from_raw(y).value(),
+        )
+    }
+
+    pub fn apply(&self, x: f32, y: f32) -> StickValue {
+        let magnitude = x.hypot(y);
+        if magnitude <= self.radial_dead_zone || magnitude == 0.0 {
+            return StickValue::NEUTRAL;
+        }
+
+        let scale = rescale(magnitude.min(1.0), self.radial_dead_zone, 1.0) / magnitude;
+        let x = self.x.apply(x * scale).value();
+        let y = self.y.apply(y * scale).value();
+
+        // keep the result within the unit circle, even with increased sensitivity
+        let magnitude = x.hypot(y);
+        let scale = if magnitude > 1.0 {
+            1.0 / magnitude
+        } else {
+            1.0
+        };
+        StickValue {
+            x: AxisValue::new(x * scale),
+            y: AxisValue::new(y * scale),
+        }
+    }
+}
+
+/// Rescales `magnitude` from `inner..=outer` to `0..=1`.
+#[inline]
+fn rescale(magnitude: f32, inner: f32, outer: f32) -> f32 {
+    if magnitude <= inner {
+        0.0
+    } else if magnitude >= outer || outer <= inner {
+        1.0
+    } else {
+        (magnitude - inner) / (outer - inner)
+    }
+}
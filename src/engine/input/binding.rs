@@ -0,0 +1,85 @@
+use crate::engine::input::{AxisConfig, InputBinding, InputBindingParseError};
+use sdl2::controller::Axis;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// An axis of any connected game controller. It is (de)serialized by its sdl2 name, e.g.
+/// `"leftx"` or `"righttrigger"`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub struct ControllerAxis(pub Axis);
+
+impl Display for ControllerAxis {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.string())
+    }
+}
+
+impl FromStr for ControllerAxis {
+    type Err = InputBindingParseError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Axis::from_string(s)
+            .map(Self)
+            .ok_or_else(|| InputBindingParseError::UnknownControllerAxis(s.to_string()))
+    }
+}
+
+impl From<ControllerAxis> for String {
+    #[inline]
+    fn from(value: ControllerAxis) -> Self {
+        value.to_string()
+    }
+}
+
+impl TryFrom<String> for ControllerAxis {
+    type Error = InputBindingParseError;
+
+    #[inline]
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// An input that provides the value of an axis action of an [`ActionMap`].
+///
+/// [`ActionMap`]: crate::engine::system::input::ActionMap
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum AxisBinding {
+    /// Two inputs for the negative and positive direction, like `A` and `D`
+    Digital {
+        negative: InputBinding,
+        positive: InputBinding,
+    },
+    /// An analog axis, like a thumb stick or a trigger
+    Controller {
+        axis: ControllerAxis,
+        config: AxisConfig,
+    },
+}
+
+impl AxisBinding {
+    #[inline]
+    pub fn digital(negative: InputBinding, positive: InputBinding) -> Self {
+        Self::Digital { negative, positive }
+    }
+
+    /// A controller axis with the default [`AxisConfig`].
+    #[inline]
+    pub fn controller(axis: Axis) -> Self {
+        Self::Controller {
+            axis: ControllerAxis(axis),
+            config: AxisConfig::default(),
+        }
+    }
+}
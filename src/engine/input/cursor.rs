@@ -0,0 +1,331 @@
+use crate::engine::input::StickConfig;
+use crate::engine::system::vulkan::textured::{TexturedPipeline, TexturedQuad};
+use crate::engine::system::vulkan::textures::{PlaceholderTextures, TextureId};
+use crate::engine::types::world2d::{Dim, Pos};
+use sdl2::controller::{Axis, Button};
+use sdl2::event::Event;
+use sdl2::mouse::{MouseButton, MouseState};
+use std::time::Instant;
+
+/// Configuration of a [`VirtualCursor`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VirtualCursorConfig {
+    /// The horizontal and the vertical axis that move the cursor
+    pub axes: [Axis; 2],
+    pub stick: StickConfig,
+    /// The speed in pixels per second at full deflection
+    pub max_speed: f32,
+    /// How fast the cursor reaches the speed of the deflection, in pixels per second squared
+    pub acceleration: f32,
+    /// Clicks like the left mouse button
+    pub primary_button: Button,
+    /// Clicks like the right mouse button
+    pub secondary_button: Button,
+    /// The size of the drawn cursor in window coordinates
+    pub size: [f32; 2],
+    /// The point of the drawn cursor that is at the position of the cursor, in window
+    /// coordinates from its top left corner
+    pub hotspot: [f32; 2],
+}
+
+impl Default for VirtualCursorConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            axes: [Axis::LeftX, Axis::LeftY],
+            stick: StickConfig::default(),
+            max_speed: 1200.0,
+            acceleration: 6000.0,
+            primary_button: Button::A,
+            secondary_button: Button::X,
+            size: [32.0, 32.0],
+            hotspot: [0.0, 0.0],
+        }
+    }
+}
+
+impl VirtualCursorConfig {
+    #[inline]
+    pub fn with_axes(mut self, horizontal: Axis, vertical: Axis) -> Self {
+        self.axes = [horizontal, vertical];
+        self
+    }
+
+    #[inline]
+    pub fn with_stick(mut self, stick: StickConfig) -> Self {
+        self.stick = stick;
+        self
+    }
+
+    #[inline]
+    pub fn with_max_speed(mut self, max_speed: f32) -> Self {
+        self.max_speed = max_speed;
+        self
+    }
+
+    #[inline]
+    pub fn with_acceleration(mut self, acceleration: f32) -> Self {
+        self.acceleration = acceleration;
+        self
+    }
+
+    #[inline]
+    pub fn with_buttons(mut self, primary: Button, secondary: Button) -> Self {
+        self.primary_button = primary;
+        self.secondary_button = secondary;
+        self
+    }
+
+    #[inline]
+    pub fn with_size(mut self, size: [f32; 2], hotspot: [f32; 2]) -> Self {
+        self.size = size;
+        self.hotspot = hotspot;
+        self
+    }
+}
+
+/// A mouse cursor that is moved with a thumb stick of a game controller, for couch or TV UIs
+/// without a mouse. The cursor synthesizes the sdl2 mouse events for its movement and its
+/// clicks, so egui and the [`ActionMap`] react to it like to a real mouse. It is shown once the
+/// stick or a button is used and hidden again when the real mouse is moved.
+///
+/// The synthesized events carry [`VirtualCursor::MOUSE_ID`] as mouse instance id (`which`).
+/// The events of the controller are passed on as well, so the buttons of the cursor should not
+/// be bound to other actions of the UI.
+///
+/// [`ActionMap`]: crate::engine::system::input::ActionMap
+pub struct VirtualCursor {
+    config: VirtualCursorConfig,
+    /// [`None`] to draw the white placeholder
+    texture: Option<TextureId<TexturedPipeline>>,
+    pos: Pos<f32>,
+    velocity: Dim<f32>,
+    /// The raw values of the [`VirtualCursorConfig::axes`]
+    axes: [i16; 2],
+    /// The pressed buttons, to report them in the mouse state
+    buttons: u32,
+    /// Button changes since the last [`VirtualCursor::update`]
+    clicks: Vec<(MouseButton, bool)>,
+    visible: bool,
+    previous_update: Option<Instant>,
+}
+
+impl VirtualCursor {
+    /// The mouse instance id of the synthesized events
+    pub const MOUSE_ID: u32 = u32::MAX - 1;
+
+    pub fn new(config: VirtualCursorConfig) -> Self {
+        Self {
+            config,
+            texture: None,
+            pos: Pos::new(0.0, 0.0),
+            velocity: Dim::new(0.0, 0.0),
+            axes: [0, 0],
+            buttons: 0,
+            clicks: Vec::new(),
+            visible: false,
+            previous_update: None,
+        }
+    }
+
+    #[inline]
+    pub fn with_texture(mut self, texture: TextureId<TexturedPipeline>) -> Self {
+        self.set_texture(texture);
+        self
+    }
+
+    #[inline]
+    pub fn set_texture(&mut self, texture: TextureId<TexturedPipeline>) {
+        self.texture = Some(texture);
+    }
+
+    #[inline]
+    pub fn config(&self) -> &VirtualCursorConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub fn config_mut(&mut self) -> &mut VirtualCursorConfig {
+        &mut self.config
+    }
+
+    /// The position in window coordinates.
+    #[inline]
+    pub fn position(&self) -> Pos<f32> {
+        self.pos
+    }
+
+    /// Moves the cursor without synthesizing a motion event.
+    #[inline]
+    pub fn set_position(&mut self, pos: Pos<f32>) {
+        self.pos = pos;
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    #[inline]
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn on_sdl2_event(&mut self, event: &Event) {
+        match event {
+            Event::ControllerAxisMotion { axis, value, .. } => {
+                if let Some(index) = self.config.axes.iter().position(|a| a == axis) {
+                    self.axes[index] = *value;
+                }
+            }
+            Event::ControllerButtonDown { button, .. } => self.on_button(*button, true),
+            Event::ControllerButtonUp { button, .. } => self.on_button(*button, false),
+            Event::MouseMotion { which, x, y, .. } if *which != Self::MOUSE_ID => {
+                self.pos = Pos::new(*x as f32, *y as f32);
+                self.velocity = Dim::new(0.0, 0.0);
+                self.visible = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn on_button(&mut self, button: Button, pressed: bool) {
+        let button = if button == self.config.primary_button {
+            MouseButton::Left
+        } else if button == self.config.secondary_button {
+            MouseButton::Right
+        } else {
+            return;
+        };
+        let mask = Self::button_mask(button);
+        if pressed == (self.buttons & mask != 0) {
+            return;
+        }
+        if pressed {
+            self.buttons |= mask;
+        } else {
+            self.buttons &= !mask;
+        }
+        self.clicks.push((button, pressed));
+        self.visible = true;
+    }
+
+    #[inline]
+    fn button_mask(button: MouseButton) -> u32 {
+        match button {
+            MouseButton::Left => 1 << 0,
+            MouseButton::Middle => 1 << 1,
+            MouseButton::Right => 1 << 2,
+            MouseButton::X1 => 1 << 3,
+            MouseButton::X2 => 1 << 4,
+            MouseButton::Unknown => 0,
+        }
+    }
+
+    /// Moves the cursor by the deflection of the stick since the previous update, keeps it
+    /// within the window of the given size (in window coordinates) and returns the synthesized
+    /// mouse events for the window with the given id. The `timestamp` is the current
+    /// [`sdl2::TimerSubsystem::ticks`].
+    pub fn update(
+        &mut self,
+        now: Instant,
+        timestamp: u32,
+        window_id: u32,
+        [width, height]: [u32; 2],
+    ) -> Vec<Event> {
+        let delta = self
+            .previous_update
+            .replace(now)
+            .map(|previous| now.duration_since(previous).as_secs_f32())
+            .unwrap_or_default();
+
+        let stick = self.config.stick.apply_raw(self.axes[0], self.axes[1]);
+        let target = stick.to_dim(self.config.max_speed);
+        let difference = target - self.velocity;
+        let max_change = self.config.acceleration * delta;
+        let change = difference.x.hypot(difference.y);
+        self.velocity += if change > max_change && change > 0.0 {
+            difference * (max_change / change)
+        } else {
+            difference
+        };
+
+        let mut events = Vec::with_capacity(self.clicks.len() + 1);
+        let previous = self.pixel_pos();
+        if self.velocity.x != 0.0 || self.velocity.y != 0.0 {
+            self.visible = true;
+            self.pos += self.velocity * delta;
+        }
+        self.pos.x = self.pos.x.clamp(0.0, width.saturating_sub(1) as f32);
+        self.pos.y = self.pos.y.clamp(0.0, height.saturating_sub(1) as f32);
+
+        let (x, y) = self.pixel_pos();
+        if (x, y) != previous {
+            events.push(Event::MouseMotion {
+                timestamp,
+                window_id,
+                which: Self::MOUSE_ID,
+                mousestate: MouseState::from_sdl_state(self.buttons),
+                x,
+                y,
+                xrel: x - previous.0,
+                yrel: y - previous.1,
+            });
+        }
+
+        events.extend(self.clicks.drain(..).map(|(mouse_btn, pressed)| {
+            if pressed {
+                Event::MouseButtonDown {
+                    timestamp,
+                    window_id,
+                    which: Self::MOUSE_ID,
+                    mouse_btn,
+                    clicks: 1,
+                    x,
+                    y,
+                }
+            } else {
+                Event::MouseButtonUp {
+                    timestamp,
+                    window_id,
+                    which: Self::MOUSE_ID,
+                    mouse_btn,
+                    clicks: 1,
+                    x,
+                    y,
+                }
+            }
+        }));
+
+        events
+    }
+
+    #[inline]
+    fn pixel_pos(&self) -> (i32, i32) {
+        (self.pos.x.round() as i32, self.pos.y.round() as i32)
+    }
+
+    /// The quad to draw the cursor with in pixels, [`None`] while it is hidden. The
+    /// `drawable_scale` converts the window coordinates to pixels, see
+    /// [`SdlParts::drawable_scale`].
+    ///
+    /// [`SdlParts::drawable_scale`]: crate::engine::parts::sdl::SdlParts::drawable_scale
+    pub fn quad(
+        &self,
+        placeholders: &PlaceholderTextures<TexturedPipeline>,
+        drawable_scale: f32,
+    ) -> Option<TexturedQuad> {
+        self.visible.then(|| {
+            TexturedQuad::new(
+                [
+                    (self.pos.x - self.config.hotspot[0]) * drawable_scale,
+                    (self.pos.y - self.config.hotspot[1]) * drawable_scale,
+                ],
+                self.config.size.map(|v| v * drawable_scale),
+                self.texture
+                    .clone()
+                    .unwrap_or_else(|| placeholders.white().clone()),
+            )
+        })
+    }
+}
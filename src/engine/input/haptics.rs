@@ -0,0 +1,334 @@
+use rustc_hash::FxHashMap;
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+use sdl2::GameControllerSubsystem;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// A single step of a [`RumblePattern`], the intensities are in the range `0.0..=1.0`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RumbleStep {
+    /// Intensity of the low frequency (usually the left, heavy) motor
+    pub low_frequency: f32,
+    /// Intensity of the high frequency (usually the right, light) motor
+    pub high_frequency: f32,
+    pub duration: Duration,
+}
+
+impl RumbleStep {
+    #[inline]
+    pub fn new(intensity: f32, duration: Duration) -> Self {
+        Self {
+            low_frequency: intensity,
+            high_frequency: intensity,
+            duration,
+        }
+    }
+
+    #[inline]
+    pub fn pause(duration: Duration) -> Self {
+        Self::new(0.0, duration)
+    }
+}
+
+/// Describes the intensity of a rumble over time: it rises linearly to `intensity` during
+/// `attack`, stays there for `sustain` and falls back to zero during `release`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RumbleEnvelope {
+    pub intensity: f32,
+    pub attack: Duration,
+    pub sustain: Duration,
+    pub release: Duration,
+}
+
+impl RumbleEnvelope {
+    /// The time between two steps when converting into a [`RumblePattern`].
+    pub const RESOLUTION: Duration = Duration::from_millis(16);
+
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        self.attack + self.sustain + self.release
+    }
+
+    pub fn intensity_at(&self, elapsed: Duration) -> f32 {
+        if elapsed < self.attack {
+            self.intensity * elapsed.as_secs_f32() / self.attack.as_secs_f32()
+        } else if elapsed < self.attack + self.sustain {
+            self.intensity
+        } else if elapsed < self.duration() {
+            let released = elapsed - self.attack - self.sustain;
+            self.intensity * (1.0 - released.as_secs_f32() / self.release.as_secs_f32())
+        } else {
+            0.0
+        }
+    }
+}
+
+impl From<RumbleEnvelope> for RumblePattern {
+    fn from(envelope: RumbleEnvelope) -> Self {
+        let mut steps = Vec::new();
+        let mut elapsed = Duration::ZERO;
+        while elapsed < envelope.duration() {
+            let duration = RumbleEnvelope::RESOLUTION.min(envelope.duration() - elapsed);
+            steps.push(RumbleStep::new(
+                envelope.intensity_at(elapsed + duration / 2),
+                duration,
+            ));
+            elapsed += duration;
+        }
+        Self { steps }
+    }
+}
+
+/// A sequence of [`RumbleStep`]s. It can be parsed from a compact notation of whitespace or
+/// comma separated steps `LOW[/HIGH]:DURATION`, where the intensities are in `0.0..=1.0` and the
+/// duration ends with `ms` or `s`. For example `"1.0:120ms 0:80ms 0.3/0.8:0.5s"` rumbles at full
+/// intensity, pauses and then rumbles mostly with the high frequency motor.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RumblePattern {
+    steps: Vec<RumbleStep>,
+}
+
+impl RumblePattern {
+    #[inline]
+    pub fn new(steps: Vec<RumbleStep>) -> Self {
+        Self { steps }
+    }
+
+    #[inline]
+    pub fn with_step(mut self, step: RumbleStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    #[inline]
+    pub fn steps(&self) -> &[RumbleStep] {
+        &self.steps
+    }
+
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        self.steps.iter().map(|step| step.duration).sum()
+    }
+
+    /// The index of the step that is active after `elapsed`.
+    fn step_index_at(&self, elapsed: Duration) -> Option<usize> {
+        let mut end = Duration::ZERO;
+        self.steps.iter().position(|step| {
+            end += step.duration;
+            elapsed < end
+        })
+    }
+}
+
+impl FromStr for RumblePattern {
+    type Err = RumblePatternParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn intensity(value: &str) -> Result<f32, RumblePatternParseError> {
+            value
+                .parse::<f32>()
+                .ok()
+                .filter(|v| (0.0..=1.0).contains(v))
+                .ok_or_else(|| RumblePatternParseError::InvalidIntensity(value.to_string()))
+        }
+
+        fn duration(value: &str) -> Result<Duration, RumblePatternParseError> {
+            let parsed = if let Some(millis) = value.strip_suffix("ms") {
+                millis.parse::<u64>().ok().map(Duration::from_millis)
+            } else if let Some(secs) = value.strip_suffix('s') {
+                secs.parse::<f32>()
+                    .ok()
+                    .and_then(|secs| Duration::try_from_secs_f32(secs).ok())
+            } else {
+                None
+            };
+            parsed.ok_or_else(|| RumblePatternParseError::InvalidDuration(value.to_string()))
+        }
+
+        s.split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|step| !step.is_empty())
+            .map(|step| {
+                let (intensities, time) = step
+                    .split_once(':')
+                    .ok_or_else(|| RumblePatternParseError::MissingDuration(step.to_string()))?;
+                let (low, high) = match intensities.split_once('/') {
+                    Some((low, high)) => (intensity(low)?, intensity(high)?),
+                    None => (intensity(intensities)?, intensity(intensities)?),
+                };
+                Ok(RumbleStep {
+                    low_frequency: low,
+                    high_frequency: high,
+                    duration: duration(time)?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self::new)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RumblePatternParseError {
+    #[error("The step '{0}' is missing its duration")]
+    MissingDuration(String),
+    #[error("Invalid intensity '{0}', expected a value between 0.0 and 1.0")]
+    InvalidIntensity(String),
+    #[error("Invalid duration '{0}', expected a value ending with 'ms' or 's'")]
+    InvalidDuration(String),
+}
+
+/// Which controllers a rumble is played on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HapticsTarget {
+    All,
+    /// The controller with the given joystick instance id, as in the `which` of the controller
+    /// button and axis events
+    Controller(u32),
+}
+
+struct Playback {
+    pattern: RumblePattern,
+    start: Instant,
+    step: Option<usize>,
+}
+
+struct Controller {
+    controller: GameController,
+    playback: Option<Playback>,
+}
+
+/// Rumble for all connected game controllers. Controllers without rumble support and a missing
+/// game controller subsystem are silently ignored, so games don't need to check for support.
+pub struct Haptics {
+    controllers: FxHashMap<u32, Controller>,
+    subsystem: Option<GameControllerSubsystem>,
+}
+
+impl Haptics {
+    pub fn new(subsystem: Option<GameControllerSubsystem>) -> Self {
+        if subsystem.is_none() {
+            info!("No game controller subsystem available, haptics are disabled");
+        }
+        Self {
+            controllers: FxHashMap::default(),
+            subsystem,
+        }
+    }
+
+    /// Whether at least one connected controller supports rumble.
+    pub fn is_supported(&self) -> bool {
+        self.controllers.values().any(|c| c.controller.has_rumble())
+    }
+
+    /// Opens and closes the controllers as they are connected and disconnected.
+    pub fn on_sdl2_event(&mut self, event: &Event) {
+        match event {
+            Event::ControllerDeviceAdded { which, .. } => {
+                let Some(subsystem) = &self.subsystem else {
+                    return;
+                };
+                match subsystem.open(*which) {
+                    Ok(controller) => {
+                        self.controllers.insert(
+                            controller.instance_id(),
+                            Controller {
+                                controller,
+                                playback: None,
+                            },
+                        );
+                    }
+                    Err(e) => warn!("Failed to open game controller {which}: {e}"),
+                }
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                self.controllers.remove(which);
+            }
+            _ => {}
+        }
+    }
+
+    /// Rumbles with the same intensity on both motors for the given duration.
+    #[inline]
+    pub fn rumble(&mut self, target: HapticsTarget, intensity: f32, duration: Duration) {
+        self.play(
+            target,
+            RumblePattern::default().with_step(RumbleStep::new(intensity, duration)),
+        );
+    }
+
+    /// Plays the pattern, replacing the currently played pattern of the targeted controllers.
+    pub fn play(&mut self, target: HapticsTarget, pattern: impl Into<RumblePattern>) {
+        let pattern = pattern.into();
+        let start = Instant::now();
+        for controller in self.targeted(target) {
+            controller.playback = Some(Playback {
+                pattern: pattern.clone(),
+                start,
+                step: None,
+            });
+        }
+        self.update(start);
+    }
+
+    pub fn stop(&mut self, target: HapticsTarget) {
+        for controller in self.targeted(target) {
+            if controller.playback.take().is_some() {
+                let _ = controller.controller.set_rumble(0, 0, 0);
+            }
+        }
+    }
+
+    /// Advances the played patterns, called once per frame by the engine.
+    pub fn update(&mut self, now: Instant) {
+        for (id, controller) in &mut self.controllers {
+            let Some(playback) = &mut controller.playback else {
+                continue;
+            };
+
+            let index = playback
+                .pattern
+                .step_index_at(now.saturating_duration_since(playback.start));
+
+            // an empty pattern is finished right away, but still stops the replaced pattern
+            if index != playback.step || index.is_none() {
+                playback.step = index;
+                let (low, high, duration) = match index {
+                    Some(index) => {
+                        let step = &playback.pattern.steps[index];
+                        (step.low_frequency, step.high_frequency, step.duration)
+                    }
+                    None => (0.0, 0.0, Duration::ZERO),
+                };
+
+                if index.is_none() {
+                    controller.playback = None;
+                }
+
+                if let Err(e) = controller.controller.set_rumble(
+                    motor_speed(low),
+                    motor_speed(high),
+                    duration.as_millis().min(u128::from(u32::MAX)) as u32,
+                ) {
+                    // no support for rumble, do not try again until the next pattern
+                    debug!("Failed to rumble game controller {id}: {e}");
+                    controller.playback = None;
+                }
+            }
+        }
+    }
+
+    fn targeted(&mut self, target: HapticsTarget) -> impl Iterator<Item = &mut Controller> {
+        self.controllers
+            .iter_mut()
+            .filter(move |(id, _)| match target {
+                HapticsTarget::All => true,
+                HapticsTarget::Controller(target) => **id == target,
+            })
+            .map(|(_, controller)| controller)
+    }
+}
+
+#[inline]
+fn motor_speed(intensity: f32) -> u16 {
+    (intensity.clamp(0.0, 1.0) * f32::from(u16::MAX)) as u16
+}
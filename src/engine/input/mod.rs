@@ -0,0 +1,418 @@
+use rustc_hash::FxHashMap;
+use sdl2::controller::Button;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use std::borrow::Borrow;
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+use std::str::FromStr;
+
+mod axis;
+mod binding;
+mod cursor;
+mod haptics;
+mod text;
+pub use axis::*;
+pub use binding::*;
+pub use cursor::*;
+pub use haptics::*;
+pub use text::*;
+
+const MOUSE_PREFIX: &str = "Mouse ";
+const CONTROLLER_PREFIX: &str = "Controller ";
+
+/// A physical input that can be bound to an action of an [`InputMap`]. It is (de)serialized as
+/// its human-readable name, e.g. `"Space"`, `"Left Shift"`, `"Mouse Left"` or `"Controller a"`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub enum InputBinding {
+    Key(Keycode),
+    MouseButton(MouseButton),
+    /// A button of any connected game controller
+    ControllerButton(Button),
+}
+
+impl InputBinding {
+    /// The binding and whether it was pressed (`true`) or released (`false`), if the event
+    /// refers to a bindable input. Repeated key events are ignored.
+    pub fn from_event(event: &Event) -> Option<(Self, bool)> {
+        match event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                repeat: false,
+                ..
+            } => Some((Self::Key(*keycode), true)),
+            Event::KeyUp {
+                keycode: Some(keycode),
+                repeat: false,
+                ..
+            } => Some((Self::Key(*keycode), false)),
+            Event::MouseButtonDown { mouse_btn, .. } if *mouse_btn != MouseButton::Unknown => {
+                Some((Self::MouseButton(*mouse_btn), true))
+            }
+            Event::MouseButtonUp { mouse_btn, .. } if *mouse_btn != MouseButton::Unknown => {
+                Some((Self::MouseButton(*mouse_btn), false))
+            }
+            Event::ControllerButtonDown { button, .. } => {
+                Some((Self::ControllerButton(*button), true))
+            }
+            Event::ControllerButtonUp { button, .. } => {
+                Some((Self::ControllerButton(*button), false))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for InputBinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputBinding::Key(keycode) => write!(f, "{}", keycode.name()),
+            InputBinding::MouseButton(button) => write!(
+                f,
+                "{MOUSE_PREFIX}{}",
+                match button {
+                    MouseButton::Unknown => "Unknown",
+                    MouseButton::Left => "Left",
+                    MouseButton::Middle => "Middle",
+                    MouseButton::Right => "Right",
+                    MouseButton::X1 => "X1",
+                    MouseButton::X2 => "X2",
+                }
+            ),
+            InputBinding::ControllerButton(button) => {
+                write!(f, "{CONTROLLER_PREFIX}{}", button.string())
+            }
+        }
+    }
+}
+
+impl FromStr for InputBinding {
+    type Err = InputBindingParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(button) = s.strip_prefix(MOUSE_PREFIX) {
+            Ok(Self::MouseButton(match button {
+                "Left" => MouseButton::Left,
+                "Middle" => MouseButton::Middle,
+                "Right" => MouseButton::Right,
+                "X1" => MouseButton::X1,
+                "X2" => MouseButton::X2,
+                _ => return Err(InputBindingParseError::UnknownMouseButton(s.to_string())),
+            }))
+        } else if let Some(button) = s.strip_prefix(CONTROLLER_PREFIX) {
+            Button::from_string(button)
+                .map(Self::ControllerButton)
+                .ok_or_else(|| InputBindingParseError::UnknownControllerButton(s.to_string()))
+        } else {
+            Keycode::from_name(s)
+                .map(Self::Key)
+                .ok_or_else(|| InputBindingParseError::UnknownKey(s.to_string()))
+        }
+    }
+}
+
+impl From<InputBinding> for String {
+    #[inline]
+    fn from(value: InputBinding) -> Self {
+        value.to_string()
+    }
+}
+
+impl TryFrom<String> for InputBinding {
+    type Error = InputBindingParseError;
+
+    #[inline]
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum InputBindingParseError {
+    #[error("Unknown key: {0}")]
+    UnknownKey(String),
+    #[error("Unknown mouse button: {0}")]
+    UnknownMouseButton(String),
+    #[error("Unknown controller button: {0}")]
+    UnknownControllerButton(String),
+    #[error("Unknown controller axis: {0}")]
+    UnknownControllerAxis(String),
+}
+
+/// What happens if a captured [`InputBinding`] is already bound to other actions.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// The binding is kept for the other actions as well
+    Keep,
+    /// The binding is removed from the other actions
+    #[default]
+    Steal,
+    /// The binding is not applied and the capture continues with the next input
+    Reject,
+}
+
+/// The result of capturing the next input (see [`InputMap::capture_next_input`]) or of
+/// [`InputMap::rebind`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebindOutcome<A> {
+    /// The binding was applied to the action. The conflicting actions still have the binding
+    /// only with [`ConflictResolution::Keep`].
+    Bound {
+        action: A,
+        binding: InputBinding,
+        conflicts: Vec<A>,
+    },
+    /// The binding was not applied because of [`ConflictResolution::Reject`]
+    Rejected {
+        action: A,
+        binding: InputBinding,
+        conflicts: Vec<A>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Capture<A> {
+    action: A,
+    replace: bool,
+    resolution: ConflictResolution,
+}
+
+/// Maps [`InputBinding`]s to game-defined actions. Besides resolving events to actions, it
+/// supports rebinding an action to the next input of the user, as used by controls settings.
+///
+/// With the `serde-io` feature, the bindings can be (de)serialized; a pending capture is not.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize),
+    serde(bound(
+        serialize = "A: serde::Serialize + Eq + Hash",
+        deserialize = "A: serde::Deserialize<'de> + Eq + Hash"
+    ))
+)]
+pub struct InputMap<A> {
+    bindings: FxHashMap<A, Vec<InputBinding>>,
+    #[cfg_attr(feature = "serde-io", serde(skip))]
+    capture: Option<Capture<A>>,
+}
+
+impl<A> Default for InputMap<A> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            bindings: FxHashMap::default(),
+            capture: None,
+        }
+    }
+}
+
+impl<A: Clone + Eq + Hash> InputMap<A> {
+    #[inline]
+    pub fn with_binding(mut self, action: A, binding: InputBinding) -> Self {
+        self.bind(action, binding);
+        self
+    }
+
+    /// Adds the binding to the action, unless it is already bound to it.
+    pub fn bind(&mut self, action: A, binding: InputBinding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes the binding from the action, returns whether it was bound.
+    pub fn unbind<Q>(&mut self, action: &Q, binding: InputBinding) -> bool
+    where
+        A: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.bindings.get_mut(action) {
+            Some(bindings) => {
+                let len = bindings.len();
+                bindings.retain(|b| *b != binding);
+                len != bindings.len()
+            }
+            None => false,
+        }
+    }
+
+    #[inline]
+    pub fn clear<Q>(&mut self, action: &Q)
+    where
+        A: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.bindings.remove(action);
+    }
+
+    #[inline]
+    pub fn bindings<Q>(&self, action: &Q) -> &[InputBinding]
+    where
+        A: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.bindings.get(action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// All actions with at least one binding.
+    pub fn bound_actions(&self) -> impl Iterator<Item = &A> + '_ {
+        self.bindings
+            .iter()
+            .filter(|(_, bindings)| !bindings.is_empty())
+            .map(|(action, _)| action)
+    }
+
+    /// All actions the binding is bound to.
+    pub fn actions(&self, binding: InputBinding) -> impl Iterator<Item = A> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| action.clone())
+    }
+
+    /// All actions other than `action` the binding is bound to.
+    pub fn conflicts<Q>(&self, action: &Q, binding: InputBinding) -> Vec<A>
+    where
+        A: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.actions(binding)
+            .filter(|a| a.borrow() != action)
+            .collect()
+    }
+
+    /// All bindings that are bound to more than one action, together with those actions.
+    pub fn all_conflicts(&self) -> Vec<(InputBinding, Vec<A>)> {
+        let mut by_binding = FxHashMap::<InputBinding, Vec<A>>::default();
+        for (action, bindings) in &self.bindings {
+            for binding in bindings {
+                by_binding.entry(*binding).or_default().push(action.clone());
+            }
+        }
+        by_binding
+            .into_iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .collect()
+    }
+
+    /// The actions triggered by the event and whether they were pressed (`true`) or released
+    /// (`false`). Nothing is triggered while an input is captured.
+    pub fn triggered<'a>(&'a self, event: &Event) -> impl Iterator<Item = (A, bool)> + 'a {
+        InputBinding::from_event(event)
+            .filter(|_| self.capture.is_none())
+            .into_iter()
+            .flat_map(move |(binding, pressed)| {
+                self.actions(binding).map(move |action| (action, pressed))
+            })
+    }
+
+    /// Replaces the bindings of the action with the next pressed input passed to
+    /// [`InputMap::on_event`].
+    #[inline]
+    pub fn capture_next_input(&mut self, action: A, resolution: ConflictResolution) {
+        self.capture = Some(Capture {
+            action,
+            replace: true,
+            resolution,
+        });
+    }
+
+    /// Like [`InputMap::capture_next_input`] but adds the input to the existing bindings.
+    #[inline]
+    pub fn capture_additional_input(&mut self, action: A, resolution: ConflictResolution) {
+        self.capture = Some(Capture {
+            action,
+            replace: false,
+            resolution,
+        });
+    }
+
+    /// The action for which the next input is currently captured.
+    #[inline]
+    pub fn capturing(&self) -> Option<&A> {
+        self.capture.as_ref().map(|capture| &capture.action)
+    }
+
+    #[inline]
+    pub fn cancel_capture(&mut self) {
+        self.capture = None;
+    }
+
+    /// Completes a pending capture (see [`InputMap::capture_next_input`]) if the event is a
+    /// pressed input.
+    pub fn on_event(&mut self, event: &Event) -> Option<RebindOutcome<A>> {
+        let capture = self.capture.as_ref()?;
+        let (binding, true) = InputBinding::from_event(event)? else {
+            return None;
+        };
+
+        let Capture {
+            action,
+            replace,
+            resolution,
+        } = capture.clone();
+        let outcome = self.apply(action, binding, replace, resolution);
+        if let RebindOutcome::Bound { .. } = outcome {
+            self.capture = None;
+        }
+        Some(outcome)
+    }
+
+    /// Replaces the bindings of the action with the given one, like a completed
+    /// [`InputMap::capture_next_input`], with the same handling of conflicting actions.
+    #[inline]
+    pub fn rebind(
+        &mut self,
+        action: A,
+        binding: InputBinding,
+        resolution: ConflictResolution,
+    ) -> RebindOutcome<A> {
+        self.apply(action, binding, true, resolution)
+    }
+
+    fn apply(
+        &mut self,
+        action: A,
+        binding: InputBinding,
+        replace: bool,
+        resolution: ConflictResolution,
+    ) -> RebindOutcome<A> {
+        let conflicts = self.conflicts(&action, binding);
+
+        if !conflicts.is_empty() {
+            match resolution {
+                ConflictResolution::Keep => {}
+                ConflictResolution::Steal => {
+                    for other in &conflicts {
+                        self.unbind(other, binding);
+                    }
+                }
+                ConflictResolution::Reject => {
+                    return RebindOutcome::Rejected {
+                        action,
+                        binding,
+                        conflicts,
+                    };
+                }
+            }
+        }
+
+        if replace {
+            self.clear(&action);
+        }
+        self.bind(action.clone(), binding);
+
+        RebindOutcome::Bound {
+            action,
+            binding,
+            conflicts,
+        }
+    }
+}
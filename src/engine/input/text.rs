@@ -0,0 +1,88 @@
+use sdl2::keyboard::TextInputUtil;
+use sdl2::rect::Rect;
+
+/// Controls whether sdl2 emits `TextInput` and `TextEditing` events. On devices without a
+/// physical keyboard (Steam Deck, mobile), an active text input also shows the on-screen
+/// keyboard, so it should only be active while the user is actually typing.
+///
+/// The text input is active while it is requested by the game (see [`TextInput::start`]) or
+/// while a text field of egui has the focus.
+pub struct TextInput {
+    util: TextInputUtil,
+    requested: bool,
+    requested_rect: Option<Rect>,
+    egui_rect: Option<Rect>,
+    applied_rect: Option<Rect>,
+}
+
+impl TextInput {
+    /// Creates the [`TextInput`] in the inactive state, stopping the text input sdl2 starts by
+    /// default.
+    pub fn new(util: TextInputUtil) -> Self {
+        util.stop();
+        Self {
+            util,
+            requested: false,
+            requested_rect: None,
+            egui_rect: None,
+            applied_rect: None,
+        }
+    }
+
+    /// Requests the text input until [`TextInput::stop`] is called. The `rect` is the area (in
+    /// pixels) of the text that is being edited, which should not be covered by the on-screen
+    /// keyboard or the candidate list of an IME.
+    #[inline]
+    pub fn start(&mut self, rect: Option<Rect>) {
+        self.requested = true;
+        self.requested_rect = rect;
+        self.apply();
+    }
+
+    /// Releases the request of [`TextInput::start`]. The text input stays active while egui
+    /// wants it.
+    #[inline]
+    pub fn stop(&mut self) {
+        self.requested = false;
+        self.requested_rect = None;
+        self.apply();
+    }
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.util.is_active()
+    }
+
+    #[inline]
+    pub fn has_screen_keyboard_support(&self) -> bool {
+        self.util.has_screen_keyboard_support()
+    }
+
+    /// Updates the area of the focused egui text field, [`None`] if there is none.
+    #[inline]
+    pub(crate) fn set_egui_rect(&mut self, rect: Option<Rect>) {
+        if self.egui_rect != rect {
+            self.egui_rect = rect;
+            self.apply();
+        }
+    }
+
+    fn apply(&mut self) {
+        if self.requested || self.egui_rect.is_some() {
+            let rect = if self.requested {
+                self.requested_rect
+            } else {
+                self.egui_rect
+            };
+            if let Some(rect) = rect.filter(|rect| self.applied_rect != Some(*rect)) {
+                self.util.set_rect(rect);
+            }
+            self.applied_rect = rect;
+            if !self.util.is_active() {
+                self.util.start();
+            }
+        } else if self.util.is_active() {
+            self.util.stop();
+        }
+    }
+}
@@ -1,50 +1,127 @@
-use crate::engine::builder::EngineBuilder;
-use crate::engine::parts::sdl::SdlParts;
-use crate::engine::system::fps::FpsManager;
+use crate::engine::accessibility::{AccessibilityOptions, FlashLimiter};
+use crate::engine::app::{Application, LoopControl};
+use crate::engine::builder::{EngineBuilder, EngineConfig, RenderMode};
+use crate::engine::event::EngineEvent;
+use crate::engine::input::{Haptics, TextInput, VirtualCursor};
+use crate::engine::parts::sdl::{DisplayInfo, SdlParts, WindowBadge, WindowPlacement};
+use crate::engine::plugin::{EnginePlugin, PluginBuildContext, PluginError};
+use crate::engine::system::commands::RecordedCommands;
+use crate::engine::system::fps::{FixedStep, FixedSteps, FixedTimestep, FpsManager};
+use crate::engine::system::input::ActionMap;
+use crate::engine::system::resolution::DynamicResolution;
+use crate::engine::system::viewport::{ViewportLayout, ViewportPos, ViewportRect};
 use crate::engine::system::vulkan::beautiful_lines::BeautifulLinePipeline;
+use crate::engine::system::vulkan::color_filter::ColorFilter;
+use crate::engine::system::vulkan::desc::binding_202_ambient_light::AmbientLight;
+use crate::engine::system::vulkan::draw_filter::DrawFilter;
+use crate::engine::system::vulkan::gpu_info::GpuInfo;
+use crate::engine::system::vulkan::memory::MemoryStats;
+use crate::engine::system::vulkan::pipeline_cache::PipelineCacheError;
 use crate::engine::system::vulkan::pipelines::VulkanPipelines;
-use crate::engine::system::vulkan::DrawError;
-use sdl2::event::{Event, WindowEvent};
-use sdl2::keyboard::Keycode;
-use sdl2::video::{FullscreenType, WindowBuildError};
+use crate::engine::system::vulkan::render_target::RenderTarget;
+use crate::engine::system::vulkan::textured::{TexturedPipeline, TexturedQuad};
+use crate::engine::system::vulkan::textures::TextureId;
+use crate::engine::system::vulkan::textures::{UploadBudget, UploadProgress};
+use crate::engine::system::vulkan::{DrawError, ReadbackError, UploadError};
+use crate::engine::types::world2d::Pos;
+use crate::support::image::RawRgbaImage;
+use crate::support::world2d::spatial_audio::SpatialAudio;
+use crate::support::world2d::view::Map2dView;
+use sdl2::video::WindowBuildError;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use system::vulkan::system::VulkanSystem;
-use vulkano::command_buffer::SecondaryAutoCommandBuffer;
-use vulkano::image::SampleCount;
+use system::vulkan::system::{InlineCommandBufferBuilder, VulkanSystem};
+use update::{Lifecycle, UpdateState};
+use vulkano::command_buffer::SecondaryCommandBufferAbstract;
+use vulkano::format::Format;
+use vulkano::image::{ImageUsage, SampleCount};
 use vulkano::instance::{Instance, InstanceExtensions};
-use vulkano::swapchain::Surface;
+use vulkano::swapchain::{ColorSpace, Surface};
 use vulkano::{LoadingError, Validated, VulkanError, VulkanLibrary};
 
+pub mod accessibility;
+pub mod app;
 pub mod builder;
+#[cfg(feature = "frame-capture")]
+pub mod capture;
+pub mod crash;
+pub mod event;
+pub mod input;
 pub mod parts;
+pub mod plugin;
 pub mod system;
+pub mod threaded;
 pub mod types;
+mod update;
 
 pub struct Engine {
     vulkan_system: VulkanSystem,
     vulkan_pipelines: Arc<VulkanPipelines>,
     #[cfg(feature = "ui-egui")]
     egui_system: system::egui::EguiSystem,
+    /// Additional contexts, rendered on top of the main [`system::egui::EguiSystem`] in the
+    /// order of their creation
+    #[cfg(feature = "ui-egui")]
+    egui_contexts: Vec<NamedEguiContext>,
     #[cfg(feature = "ttf-font-renderer")]
     font_renderer: crate::engine::system::ttf::FontRenderer,
-    #[cfg(feature = "ui-egui")]
-    // drop after the vulkan system! (last is fine, too)
-    sdl: SdlParts,
-    framerate_manager: FpsManager,
+    /// See [`Engine::run_fixed`]
+    fixed_timestep: FixedTimestep,
+    /// The events of frames without a fixed step, for the next step, see [`Engine::run_fixed`]
+    fixed_step_events: Vec<EngineEvent>,
+    /// The time [`Engine::delay`] slept after the previous frame
+    previous_sleep: Duration,
+    /// See [`EngineBuilder::with_render_mode`]
+    render_mode: RenderMode,
+    /// See [`EngineBuilder::with_dynamic_resolution`]
+    dynamic_resolution: Option<DynamicResolution>,
+    /// What the world is rendered into while the dynamic resolution is enabled
+    world_target: Option<WorldTarget>,
+    /// See [`Engine::start_frame_capture`]
+    #[cfg(feature = "frame-capture")]
+    frame_capture: Option<capture::FrameSequence>,
+    /// Shared with the [`ThreadedEngine`], drop last for the window and the steam client
+    ///
+    /// [`ThreadedEngine`]: threaded::ThreadedEngine
+    state: UpdateState,
 }
 
 impl Engine {
     pub fn new(builder: EngineBuilder) -> Result<Self, Error> {
+        if let Some(dir) = builder.crash_report_dir.clone() {
+            crash::install_panic_hook(dir);
+        }
+
+        // before the vulkan instance is created, so the overlay can hook into it
+        #[cfg(feature = "steamworks")]
+        let steam = builder
+            .steam_app_id
+            .map(system::steam::Steam::init)
+            .transpose()?;
+
         info!("SDL2 Version {}", sdl2::version::version());
         info!(
             "SDL2 Video Drivers: {:?}",
             sdl2::video::drivers().collect::<Vec<_>>()
         );
 
+        // let the platform draw the candidate list of an IME, egui only shows the composition
+        sdl2::hint::set("SDL_IME_SHOW_UI", "1");
+
         let context = sdl2::init().map_err(Error::SdlError)?;
         let video_subsystem = context.video().map_err(Error::SdlError)?;
         let event_pump = context.event_pump().map_err(Error::SdlError)?;
+        let timer = context.timer().map_err(Error::SdlError)?;
+        let game_controller = context
+            .game_controller()
+            .map_err(|e| warn!("Failed to init the game controller subsystem: {e}"))
+            .ok();
+        #[cfg(feature = "audio")]
+        let audio = builder.audio.and_then(|config| {
+            system::audio::AudioSystem::new(&context, config)
+                .map_err(|e| warn!("Failed to init the audio system: {e}"))
+                .ok()
+        });
 
         info!(
             "SDL2 Chosen Video Driver: {}",
@@ -59,6 +136,7 @@ impl Engine {
             )
             .resizable()
             .vulkan()
+            .allow_highdpi()
             .build()
             .map_err(Error::SdlWindowBuildError)?;
 
@@ -86,48 +164,159 @@ impl Engine {
             builder.window_height,
             BeautifulLinePipeline::REQUIRED_FEATURES,
             builder.msaa.unwrap_or(SampleCount::Sample1),
+            builder.depth_buffer,
+            if builder.color_filters || builder.color_filter.is_some() || builder.frame_capture {
+                builder.swapchain_image_usage | ImageUsage::TRANSFER_SRC
+            } else {
+                builder.swapchain_image_usage
+            },
         )?;
 
+        if builder.dynamic_rendering {
+            vulkan_system = vulkan_system.with_dynamic_rendering();
+        }
+
+        if let Some(path) = builder.pipeline_cache_file.clone() {
+            vulkan_system = vulkan_system.with_pipeline_cache_file(path);
+        }
+
         if let Some(clear_color) = builder.background_clear_color {
             vulkan_system.set_clear_value(clear_color);
         }
 
+        vulkan_system.set_upload_budget(builder.upload_budget);
+        vulkan_system.set_viewport_layout(builder.viewport_layout);
+        vulkan_system.set_color_filter(builder.color_filter);
+        crash::record_device(&vulkan_system);
+        debug!("{}", vulkan_system.gpu_info());
+
+        let mut framerate_manager = FpsManager::new(builder.target_frame_rate);
+        framerate_manager.set_vsync(builder.vsync);
+        if builder.vsync {
+            match window.display_mode() {
+                Ok(mode) if mode.refresh_rate > 0 => {
+                    info!("Pacing frames by vsync at {} Hz", mode.refresh_rate);
+                    framerate_manager.set_target_frame_rate(mode.refresh_rate as u16);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to query the display refresh rate: {e}"),
+            }
+        }
+
+        let mut plugins = builder.plugins;
+        for plugin in &mut plugins {
+            plugin
+                .build(&mut PluginBuildContext {
+                    vulkan_system: &mut vulkan_system,
+                })
+                .map_err(|e| Error::PluginBuildError(plugin.name().to_string(), e))?;
+            info!("Built the plugin {}", plugin.name());
+        }
+
+        #[cfg(feature = "ui-egui")]
+        let vulkan_pipelines = VulkanPipelines::new(&vulkan_system, builder.egui_options)?;
+        #[cfg(not(feature = "ui-egui"))]
+        let vulkan_pipelines = VulkanPipelines::new(&vulkan_system)?;
+
         let mut this = Self {
-            vulkan_pipelines: Arc::new(VulkanPipelines::try_from(&vulkan_system)?),
+            vulkan_pipelines: Arc::new(vulkan_pipelines),
             #[cfg(feature = "ui-egui")]
             egui_system: system::egui::EguiSystem::default(),
+            #[cfg(feature = "ui-egui")]
+            egui_contexts: Vec::default(),
             vulkan_system,
-            sdl: SdlParts {
-                video_subsystem,
-                event_pump,
-                // drop after the vulkan system!
-                window,
-                window_maximized: false,
-                #[cfg(feature = "ttf-sdl2")]
-                ttf: sdl2::ttf::init()
-                    .map_err(|e| Error::SdlError(format!("Failed to init TTF module: {e}")))?,
-                context,
-                window_icon: None,
-            }
-            .maybe_with_window_icon(builder.window_icon),
-            framerate_manager: FpsManager::new(builder.target_frame_rate),
+            fixed_timestep: FixedTimestep::new(builder.fixed_update_rate),
+            fixed_step_events: Vec::new(),
+            previous_sleep: Duration::ZERO,
+            render_mode: builder.render_mode,
+            dynamic_resolution: builder.dynamic_resolution,
+            world_target: None,
+            #[cfg(feature = "frame-capture")]
+            frame_capture: None,
             #[cfg(feature = "ttf-font-renderer")]
             font_renderer: crate::engine::system::ttf::FontRenderer::new(
                 builder.font_renderer_ttf.expect("Missing TrueType Font"),
             ),
+            state: UpdateState {
+                #[cfg(feature = "audio")]
+                audio,
+                sdl: SdlParts {
+                    video_subsystem,
+                    event_pump,
+                    timer,
+                    // drop after the vulkan system!
+                    window,
+                    window_maximized: false,
+                    #[cfg(feature = "ttf-sdl2")]
+                    ttf: sdl2::ttf::init()
+                        .map_err(|e| Error::SdlError(format!("Failed to init TTF module: {e}")))?,
+                    context,
+                    window_icon: None,
+                    window_icon_image: None,
+                    window_badge: None,
+                    display: None,
+                }
+                .maybe_with_window_icon(builder.window_icon),
+                framerate_manager,
+                haptics: Haptics::new(game_controller),
+                action_map: ActionMap::default(),
+                spatial_audio: SpatialAudio::default(),
+                text_input: TextInput::new(video_subsystem.text_input()),
+                frame: 0,
+                terminating: false,
+                started: Instant::now(),
+                previous_update: None,
+                render_stats: RenderStats::default(),
+                plugins,
+                virtual_cursor: builder.virtual_cursor.map(VirtualCursor::new),
+                accessibility: builder.accessibility,
+                ambient_light: AmbientLight::default(),
+                flash_limiter: FlashLimiter::default(),
+                #[cfg(feature = "steamworks")]
+                steam,
+            },
         };
 
+        this.state.sdl.place_window(builder.window_placement);
         this.set_fullscreen(builder.fullscreen);
 
+        if let Some(display) = this.state.sdl.update_display() {
+            if let Some(refresh_rate) = this.state.on_display_changed(&display) {
+                this.set_fps(refresh_rate);
+            }
+        }
+        this.update_pixels_per_point();
+
         Ok(this)
     }
 
     pub fn update<T>(&mut self, f: impl FnOnce(BeforeRenderContext) -> T) -> RenderResponse<T> {
         let start = Instant::now();
         let events = self.poll_events();
-        let (width, height) = self.sdl.window.vulkan_drawable_size();
+        let (width, height) = self.state.sdl.window.vulkan_drawable_size();
+        let (delta, elapsed) = self.state.advance_clock(start);
+        #[cfg(feature = "frame-capture")]
+        let (delta, elapsed) = self.update_frame_capture().unwrap_or((delta, elapsed));
+
+        if let Some(light) = self.state.before_frame(delta) {
+            self.vulkan_system.set_ambient_light(light);
+        }
+
+        if let Some(resolution) = &mut self.dynamic_resolution {
+            let target = self.state.framerate_manager.target_frame_duration();
+            let frame_time = self
+                .vulkan_system
+                .gpu_frame_time()
+                .unwrap_or_else(|| delta.saturating_sub(self.previous_sleep));
+            resolution.update(frame_time, target);
+        }
+        let [viewport_width, viewport_height] = self.vulkan_system.viewport_extent();
+        self.update_world_target(viewport_width, viewport_height);
 
         let data = f(BeforeRenderContext {
+            frame: self.state.frame,
+            delta,
+            elapsed,
             engine: self,
             events,
             width,
@@ -135,8 +324,14 @@ impl Engine {
             start,
         });
 
+        self.state.after_frame();
         #[cfg(feature = "ttf-font-renderer")]
         self.font_renderer.on_frame_completed();
+        // releases the atlases of textures that were dropped
+        self.vulkan_pipelines
+            .texture
+            .texture_registry()
+            .retain_alive();
 
         RenderResponse {
             data,
@@ -145,64 +340,874 @@ impl Engine {
         }
     }
 
-    fn poll_events(&mut self) -> Vec<Event> {
-        let mut allow_maximize_change = true;
-        let events = self.sdl.event_pump.poll_iter().collect();
+    /// Runs the main loop until the [`Application`] exits or the platform terminates the app,
+    /// see the [`app`] module. The app is returned afterward.
+    pub fn run<A: Application>(&mut self, mut app: A) -> A {
+        let mut size = None;
+        loop {
+            let response = self.update(|mut ctx| {
+                let mut control = LoopControl::Continue;
+                for event in &ctx.events {
+                    control = control.and(app.on_event(event));
+                }
+                if size != Some((ctx.width, ctx.height)) {
+                    size = Some((ctx.width, ctx.height));
+                    app.on_resize(ctx.width, ctx.height);
+                }
+                control = control.and(app.update(&mut ctx));
+                if control == LoopControl::Exit {
+                    return control;
+                }
+                match ctx.render(|context| app.render(context)) {
+                    Ok(()) => control,
+                    Err(e) => app.on_render_error(e),
+                }
+            });
 
-        for event in &events {
-            #[cfg(feature = "ui-egui")]
-            self.egui_system.on_sdl2_event(event);
-
-            match event {
-                Event::Window {
-                    win_event: WindowEvent::Resized(..) | WindowEvent::SizeChanged(..),
-                    ..
-                } => {
-                    self.vulkan_system.recreate_swapchain();
+            if response.data == LoopControl::Exit || self.is_terminating() {
+                break;
+            }
+            self.delay();
+        }
+        app.on_exit(self);
+        app
+    }
+
+    /// Like [`Engine::update`], but with a fixed-timestep simulation: `fixed_update` is called
+    /// for every step of the [`FixedTimestep`] that is due for the time of this frame (possibly
+    /// none), then `render` is called with the [`FixedSteps`], whose [`FixedSteps::alpha`]
+    /// interpolates between the previous and the latest step.
+    ///
+    /// The [`FixedStep::first_of_frame`] receives the events of all frames since the previous
+    /// step in its context, so no event is lost on frames without a step (when rendering faster
+    /// than the fixed rate). The further steps of a frame receive no events. The context of
+    /// `render` has the events of this frame only, whether or not they were handled by a step.
+    ///
+    /// ```no_run
+    /// # use hotrod::engine::Engine;
+    /// # use std::cell::Cell;
+    /// # let mut engine = Engine::default();
+    /// let (previous, current) = (Cell::new(0.0_f32), Cell::new(0.0_f32));
+    /// loop {
+    ///     engine.run_fixed(
+    ///         |ctx, step| {
+    ///             for _event in &ctx.events {
+    ///                 // handle the input
+    ///             }
+    ///             previous.set(current.get());
+    ///             current.set(current.get() + 100.0 * step.delta.as_secs_f32());
+    ///         },
+    ///         |_ctx, steps| {
+    ///             let _x = previous.get() + (current.get() - previous.get()) * steps.alpha;
+    ///             // draw at x with ctx.render(..)
+    ///         },
+    ///     );
+    ///     engine.delay();
+    /// }
+    /// ```
+    pub fn run_fixed<T>(
+        &mut self,
+        mut fixed_update: impl FnMut(&mut BeforeRenderContext, FixedStep),
+        render: impl FnOnce(BeforeRenderContext, FixedSteps) -> T,
+    ) -> RenderResponse<T> {
+        self.update(|mut ctx| {
+            let steps = ctx.engine.fixed_timestep.advance(ctx.delta);
+            if !steps.dropped.is_zero() {
+                debug!(
+                    "Frame took too long, skipped {:?} of the fixed-timestep simulation",
+                    steps.dropped
+                );
+            }
+
+            let frame_events = std::mem::take(&mut ctx.events);
+            let mut step_events = std::mem::take(&mut ctx.engine.fixed_step_events);
+            step_events.extend(frame_events.iter().cloned());
+
+            if steps.steps == 0 {
+                // kept for the next step
+                ctx.engine.fixed_step_events = step_events;
+            } else {
+                ctx.events = step_events;
+                for tick in steps.ticks() {
+                    fixed_update(
+                        &mut ctx,
+                        FixedStep {
+                            tick,
+                            delta: steps.step,
+                            first_of_frame: tick == steps.first_tick,
+                        },
+                    );
+                    ctx.events.clear();
                 }
-                Event::KeyUp {
-                    keycode: Some(Keycode::F11),
-                    repeat: false,
-                    ..
-                } if allow_maximize_change => {
-                    self.set_fullscreen(!self.sdl.window_maximized);
-                    allow_maximize_change = false;
+                // reuses the allocation
+                ctx.engine.fixed_step_events = std::mem::take(&mut ctx.events);
+            }
+
+            ctx.events = frame_events;
+            render(ctx, steps)
+        })
+    }
+
+    /// The accumulator of [`Engine::run_fixed`].
+    #[inline]
+    pub fn fixed_timestep(&self) -> &FixedTimestep {
+        &self.fixed_timestep
+    }
+
+    /// To change the rate or to [`FixedTimestep::reset`] it.
+    #[inline]
+    pub fn fixed_timestep_mut(&mut self) -> &mut FixedTimestep {
+        &mut self.fixed_timestep
+    }
+
+    fn poll_events(&mut self) -> Vec<EngineEvent> {
+        let polled = self.state.poll_events();
+
+        #[cfg(feature = "ui-egui")]
+        for event in &polled.events {
+            self.egui_system.on_routed_sdl2_event(&event.event);
+            for named in &mut self.egui_contexts {
+                named.system.on_routed_sdl2_event(&event.event);
+            }
+        }
+
+        for lifecycle in &polled.lifecycle {
+            match lifecycle {
+                Lifecycle::Suspend => self.suspend(),
+                Lifecycle::Resume => {
+                    if let Err(e) = self.resume() {
+                        error!("Failed to resume rendering: {e}");
+                    }
                 }
-                _ => {}
             }
         }
 
-        events
+        if polled.toggle_fullscreen {
+            self.set_fullscreen(!self.state.sdl.window_maximized);
+        }
+        if polled.resized {
+            self.vulkan_system.recreate_swapchain();
+        }
+        if let Some(refresh_rate) = polled.refresh_rate {
+            self.set_fps(refresh_rate);
+        }
+        if polled.display_changed {
+            self.update_pixels_per_point();
+        }
+
+        polled.events
+    }
+
+    /// Passes the [`SdlParts::pixels_per_point`] to egui and to the shaders, see
+    /// [`VulkanSystem::set_pixels_per_point`].
+    fn update_pixels_per_point(&mut self) {
+        let pixels_per_point = self.state.sdl.pixels_per_point();
+        if pixels_per_point != self.vulkan_system.pixels_per_point() {
+            info!("Scaling the UI with {pixels_per_point} pixels per point");
+        }
+        self.vulkan_system.set_pixels_per_point(pixels_per_point);
+        #[cfg(feature = "ui-egui")]
+        {
+            let window_scale = self.state.sdl.drawable_scale();
+            let systems = std::iter::once(&mut self.egui_system)
+                .chain(self.egui_contexts.iter_mut().map(|named| &mut named.system));
+            for system in systems {
+                system.set_native_pixels_per_point(pixels_per_point);
+                system.set_window_scale(window_scale);
+            }
+        }
+    }
+
+    /// The pixels per point the UI is scaled with, see [`SdlParts::pixels_per_point`].
+    #[inline]
+    pub fn pixels_per_point(&self) -> f32 {
+        self.vulkan_system.pixels_per_point()
+    }
+
+    /// The display the window is shown on, [`None`] if it could not be queried, see
+    /// [`EngineEvent::display_changed`].
+    #[inline]
+    pub fn display(&self) -> Option<&DisplayInfo> {
+        self.state.sdl.display.as_ref()
+    }
+
+    /// The current display and position of the window, see [`SdlParts::window_placement`].
+    #[inline]
+    pub fn window_placement(&self) -> Option<WindowPlacement> {
+        self.state.sdl.window_placement()
+    }
+
+    /// Moves the window, for example onto another display.
+    #[inline]
+    pub fn set_window_placement(&mut self, placement: WindowPlacement) {
+        self.state.sdl.place_window(placement);
+    }
+
+    /// Stores the current size, placement and fullscreen state of the window in the config, so
+    /// the window opens the same way on the next start when the config is passed to
+    /// [`EngineBuilder::with_config`].
+    #[inline]
+    pub fn update_window_config(&self, config: &mut EngineConfig) {
+        self.state.sdl.update_window_config(config);
+    }
+
+    /// Releases the swapchain and the surface of the window, see [`VulkanSystem::suspend`]. This
+    /// happens automatically when the app enters the background on mobile platforms, rendering is
+    /// skipped until [`Engine::resume`].
+    pub fn suspend(&mut self) {
+        self.vulkan_system.suspend();
+        self.state.pause_audio();
+    }
+
+    /// Creates a new surface for the window and continues rendering after [`Engine::suspend`].
+    pub fn resume(&mut self) -> Result<(), Error> {
+        if !self.vulkan_system.is_suspended() {
+            return Ok(());
+        }
+
+        let instance = Arc::clone(self.vulkan_system.device().instance());
+        // SAFETY: the window outlives the vulkan system, see `Engine::sdl`
+        let surface = unsafe { Surface::from_window_ref(instance, &self.state.sdl.window) }
+            .map_err(|e| Error::SdlCreateVulkanSurfaceError(e.to_string()))?;
+        let (width, height) = self.state.sdl.window.vulkan_drawable_size();
+        self.vulkan_system.resume(surface, width, height)?;
+        self.state.resume_audio();
+        Ok(())
+    }
+
+    /// Whether rendering is suspended, see [`Engine::suspend`].
+    #[inline]
+    pub fn is_suspended(&self) -> bool {
+        self.vulkan_system.is_suspended()
+    }
+
+    /// Whether the platform announced that it is about to terminate the app, which is the last
+    /// chance to save the game state. On Android, the process might be killed without any further
+    /// events.
+    #[inline]
+    pub fn is_terminating(&self) -> bool {
+        self.state.terminating
+    }
+
+    /// The refresh rate of the display the window is on in Hz, if known.
+    pub fn display_refresh_rate(&self) -> Option<u16> {
+        self.state
+            .sdl
+            .window
+            .display_mode()
+            .ok()
+            .and_then(|mode| u16::try_from(mode.refresh_rate).ok())
+            .filter(|rate| *rate > 0)
+    }
+
+    /// The plugin of the given type, if it was added with [`EngineBuilder::with_plugin`].
+    #[inline]
+    pub fn plugin<P: EnginePlugin>(&self) -> Option<&P> {
+        self.state.plugin()
+    }
+
+    #[inline]
+    pub fn plugin_mut<P: EnginePlugin>(&mut self) -> Option<&mut P> {
+        self.state.plugin_mut()
+    }
+
+    #[inline]
+    pub fn haptics(&self) -> &Haptics {
+        &self.state.haptics
+    }
+
+    #[inline]
+    pub fn haptics_mut(&mut self) -> &mut Haptics {
+        &mut self.state.haptics
+    }
+
+    /// The bindings of the named actions, updated with the polled events at the start of every
+    /// frame.
+    #[inline]
+    pub fn action_map(&self) -> &ActionMap {
+        &self.state.action_map
+    }
+
+    #[inline]
+    pub fn action_map_mut(&mut self) -> &mut ActionMap {
+        &mut self.state.action_map
+    }
+
+    /// Updated at the end of every frame, see [`SpatialAudio::set_listener`].
+    #[inline]
+    pub fn spatial_audio(&self) -> &SpatialAudio {
+        &self.state.spatial_audio
+    }
+
+    #[inline]
+    pub fn spatial_audio_mut(&mut self) -> &mut SpatialAudio {
+        &mut self.state.spatial_audio
+    }
+
+    /// See [`EngineBuilder::with_virtual_cursor`]
+    #[inline]
+    pub fn virtual_cursor(&self) -> Option<&VirtualCursor> {
+        self.state.virtual_cursor.as_ref()
+    }
+
+    /// See [`EngineBuilder::with_virtual_cursor`]
+    #[inline]
+    pub fn virtual_cursor_mut(&mut self) -> Option<&mut VirtualCursor> {
+        self.state.virtual_cursor.as_mut()
+    }
+
+    #[inline]
+    pub fn text_input(&self) -> &TextInput {
+        &self.state.text_input
+    }
+
+    /// See [`TextInput::start`]
+    #[inline]
+    pub fn start_text_input(&mut self, rect: Option<sdl2::rect::Rect>) {
+        self.state.text_input.start(rect);
+    }
+
+    /// See [`TextInput::stop`]
+    #[inline]
+    pub fn stop_text_input(&mut self) {
+        self.state.text_input.stop();
+    }
+
+    /// Activates the text input while any egui context has a focused text field.
+    #[cfg(feature = "ui-egui")]
+    fn update_egui_text_input(&mut self) {
+        let rect = std::iter::once(&self.egui_system)
+            .chain(self.egui_contexts.iter().map(|named| &named.system))
+            .find_map(system::egui::EguiSystem::text_input_rect);
+        self.state.text_input.set_egui_rect(rect);
+    }
+
+    /// [`None`] if the audio is disabled with [`EngineBuilder::without_audio`] or no audio device
+    /// could be opened.
+    #[inline]
+    #[cfg(feature = "audio")]
+    pub fn audio(&self) -> Option<&system::audio::AudioSystem> {
+        self.state.audio.as_ref()
+    }
+
+    #[inline]
+    #[cfg(feature = "audio")]
+    pub fn audio_mut(&mut self) -> Option<&mut system::audio::AudioSystem> {
+        self.state.audio.as_mut()
+    }
+
+    /// [`None`] unless initialized with [`EngineBuilder::with_steam_app_id`].
+    #[inline]
+    #[cfg(feature = "steamworks")]
+    pub fn steam(&self) -> Option<&system::steam::Steam> {
+        self.state.steam.as_ref()
+    }
+
+    #[inline]
+    #[cfg(feature = "steamworks")]
+    pub fn steam_mut(&mut self) -> Option<&mut system::steam::Steam> {
+        self.state.steam.as_mut()
+    }
+
+    /// Writes the pipeline cache into the file of [`EngineBuilder::with_pipeline_cache_file`],
+    /// which also happens when the [`VulkanSystem`] is dropped, for the [`ThreadedEngine`] when
+    /// its render thread shuts down. Saving it earlier, like after the loading screen, keeps the
+    /// cache if the game crashes later on.
+    ///
+    /// [`ThreadedEngine`]: threaded::ThreadedEngine
+    #[inline]
+    pub fn save_pipeline_cache(&self) -> Result<(), PipelineCacheError> {
+        self.vulkan_system.save_pipeline_cache()
+    }
+
+    /// Information about the chosen GPU and driver for diagnostics, see [`GpuInfo`].
+    #[inline]
+    pub fn gpu_info(&self) -> GpuInfo {
+        self.vulkan_system.gpu_info()
+    }
+
+    /// The size of the images that are rendered into, see [`VulkanSystem::image_extent`].
+    #[inline]
+    pub fn image_extent(&self) -> [u32; 2] {
+        self.vulkan_system.image_extent()
+    }
+
+    /// The format of the main render target, custom pipelines and offscreen targets that are
+    /// composited onto it should match it.
+    #[inline]
+    pub fn image_format(&self) -> Format {
+        self.vulkan_system.image_format()
+    }
+
+    /// The color space the window is presented in, see [`VulkanSystem::image_color_space`].
+    #[inline]
+    pub fn image_color_space(&self) -> Option<ColorSpace> {
+        self.vulkan_system.image_color_space()
+    }
+
+    #[inline]
+    pub fn image_count(&self) -> usize {
+        self.vulkan_system.image_count()
+    }
+
+    #[inline]
+    pub fn samples(&self) -> SampleCount {
+        self.vulkan_system.samples()
+    }
+
+    /// Creates an offscreen target to render into, see [`VulkanSystem::create_render_target`].
+    #[inline]
+    pub fn create_render_target(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderTarget, UploadError> {
+        self.vulkan_system.create_render_target(width, height)
+    }
+
+    /// The color multiplied into the output of the world2d pipelines, see [`AmbientLight`].
+    /// This is the light set with [`Engine::set_ambient_light`], the shown light might still be
+    /// approaching it, see [`AccessibilityOptions::limit_flashes`].
+    #[inline]
+    pub fn ambient_light(&self) -> AmbientLight {
+        self.state.ambient_light
+    }
+
+    /// With [`AccessibilityOptions::limit_flashes`], the light changes gradually over the next
+    /// frames instead, see [`FlashLimiter`].
+    #[inline]
+    pub fn set_ambient_light(&mut self, light: impl Into<AmbientLight>) {
+        let light = self.state.set_ambient_light(light.into());
+        self.vulkan_system.set_ambient_light(light);
+    }
+
+    #[inline]
+    pub fn accessibility(&self) -> &AccessibilityOptions {
+        &self.state.accessibility
+    }
+
+    /// Changes the motion and flash settings, for example from an options menu of the game.
+    /// Disabling [`AccessibilityOptions::limit_flashes`] shows the [`Engine::ambient_light`] at
+    /// once instead of the light the limit approached so far.
+    pub fn set_accessibility(&mut self, options: AccessibilityOptions) {
+        if let Some(light) = self.state.set_accessibility(options) {
+            self.vulkan_system.set_ambient_light(light);
+        }
+    }
+
+    #[inline]
+    pub fn color_filter(&self) -> Option<ColorFilter> {
+        self.vulkan_system.color_filter()
+    }
+
+    /// Applies the [`ColorFilter`] to the whole frame, or disables it with [`None`]. This needs
+    /// [`EngineBuilder::with_color_filters`], see [`VulkanSystem::set_color_filter`].
+    #[inline]
+    pub fn set_color_filter(&mut self, filter: Option<ColorFilter>) {
+        self.vulkan_system.set_color_filter(filter);
+    }
+
+    /// Calls `f` with the next rendered frame, needs [`EngineBuilder::with_frame_capture`]. See
+    /// [`VulkanSystem::capture_next_frame`].
+    ///
+    /// ```no_run
+    /// # use hotrod::engine::builder::EngineBuilder;
+    /// # let mut engine = EngineBuilder::default().with_frame_capture(true).build().unwrap();
+    /// engine.capture_next_frame(|result| match result {
+    ///     Ok(image) => println!("Captured {}x{}", image.width(), image.height()),
+    ///     Err(e) => eprintln!("Failed to take the screenshot: {e}"),
+    /// });
+    /// ```
+    #[inline]
+    pub fn capture_next_frame(
+        &mut self,
+        f: impl FnOnce(Result<&RawRgbaImage, &ReadbackError>) + Send + 'static,
+    ) {
+        self.vulkan_system.capture_next_frame(f);
+    }
+
+    /// The [`RenderStats`] of the last rendered frame.
+    #[inline]
+    pub fn render_stats(&self) -> RenderStats {
+        self.state.render_stats
+    }
+
+    /// The number of the current frame, incremented after every [`Engine::update`].
+    #[inline]
+    pub fn frame(&self) -> u64 {
+        self.state.frame
     }
 
     #[inline]
     pub fn set_fps(&mut self, fps: u16) {
-        self.framerate_manager.set_target_frame_rate(fps);
+        self.state.framerate_manager.set_target_frame_rate(fps);
         #[cfg(feature = "egui")]
-        self.egui_system.set_target_frame_rate(fps);
+        {
+            self.egui_system.set_target_frame_rate(fps);
+            for named in &mut self.egui_contexts {
+                named.system.set_target_frame_rate(fps);
+            }
+        }
+    }
+
+    /// Replaces the icon of the window, see [`EngineBuilder::with_window_icon`]. A
+    /// [`WindowBadge`] that was set is drawn onto the new icon as well.
+    #[inline]
+    pub fn set_window_icon(&mut self, icon: impl Into<RawRgbaImage>) {
+        self.state.sdl.set_window_icon(icon.into());
+    }
+
+    /// Draws the badge onto the window icon, which most platforms also show in the taskbar, or
+    /// removes it with [`None`]. Has no effect as long as no window icon is set. Setting the same
+    /// badge again, like an unchanged progress, does not update the icon.
+    #[inline]
+    pub fn set_window_badge(&mut self, badge: Option<WindowBadge>) {
+        self.state.sdl.set_window_badge(badge);
     }
 
     pub fn set_fullscreen(&mut self, fullscreen: bool) {
-        self.sdl.window_maximized = fullscreen;
-        if self.sdl.window_maximized {
-            self.sdl.window.maximize();
-            if let Err(e) = self.sdl.window.set_fullscreen(FullscreenType::True) {
-                error!("Enabling fullscreen failed: {e}");
+        self.state.set_fullscreen(fullscreen);
+        #[cfg(feature = "egui")]
+        {
+            self.egui_system.set_fullscreen(fullscreen);
+            for named in &mut self.egui_contexts {
+                named.system.set_fullscreen(fullscreen);
             }
-        } else {
-            if let Err(e) = self.sdl.window.set_fullscreen(FullscreenType::Off) {
-                error!("Disabling fullscreen failed: {e}");
+        }
+    }
+
+    #[inline]
+    pub fn set_texture_upload_budget(&mut self, budget: UploadBudget) {
+        self.vulkan_system.set_upload_budget(budget);
+    }
+
+    /// The progress of the texture uploads that are processed within the [`UploadBudget`].
+    #[inline]
+    pub fn texture_upload_progress(&self) -> UploadProgress {
+        self.vulkan_system.image_system().upload_progress()
+    }
+
+    /// Creates an additional, independent egui context with the given name (or replaces the
+    /// [`EguiInputRouting`] of an existing one). Its ui is updated with
+    /// [`BeforeRenderContext::update_egui_ctx`] and rendered on top of the main egui context.
+    ///
+    /// [`EguiInputRouting`]: system::egui::EguiInputRouting
+    #[cfg(feature = "ui-egui")]
+    pub fn create_egui_context(
+        &mut self,
+        name: impl Into<String>,
+        routing: system::egui::EguiInputRouting,
+    ) {
+        let name = name.into();
+        match self
+            .egui_contexts
+            .iter_mut()
+            .find(|named| named.name == name)
+        {
+            Some(named) => named.system.set_input_routing(routing),
+            None => {
+                let mut egui_system = system::egui::EguiSystem::default();
+                egui_system.set_native_pixels_per_point(self.vulkan_system.pixels_per_point());
+                egui_system.set_window_scale(self.state.sdl.drawable_scale());
+                egui_system.set_input_routing(routing);
+                self.egui_contexts.push(NamedEguiContext {
+                    name,
+                    system: egui_system,
+                    textures: system::vulkan::egui::EguiTextures::default(),
+                });
             }
-            self.sdl.window.restore();
         }
-        self.sdl.window.set_bordered(!self.sdl.window_maximized);
-        #[cfg(feature = "egui")]
-        self.egui_system.set_fullscreen(fullscreen);
+    }
+
+    /// Removes the additional egui context with the given name, returns whether it existed.
+    #[cfg(feature = "ui-egui")]
+    pub fn remove_egui_context(&mut self, name: &str) -> bool {
+        let len = self.egui_contexts.len();
+        self.egui_contexts.retain(|named| named.name != name);
+        len != self.egui_contexts.len()
+    }
+
+    /// Switches to skip the draws of single pipelines and a recorder for the draws of a frame,
+    /// see [`DrawFilter`].
+    #[inline]
+    pub fn draw_filter(&self) -> &DrawFilter {
+        self.vulkan_system.basic_buffers_manager().draw_filter()
+    }
+
+    /// See [`VulkanSystem::gpu_frame_time`]
+    #[inline]
+    pub fn gpu_frame_time(&self) -> Option<Duration> {
+        self.vulkan_system.gpu_frame_time()
+    }
+
+    /// Statistics of the memory allocator shared by all buffers and images of the engine.
+    #[inline]
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.vulkan_system.memory_stats()
+    }
+
+    /// The frame time statistics collected in [`Engine::delay`].
+    #[inline]
+    pub fn fps_manager(&self) -> &FpsManager {
+        &self.state.framerate_manager
     }
 
     #[inline]
     pub fn delay(&mut self) -> Duration {
-        self.framerate_manager.delay()
+        #[cfg(feature = "frame-capture")]
+        if self.frame_capture.is_some() {
+            return Duration::ZERO;
+        }
+        self.previous_sleep = if self.is_suspended() {
+            self.state.framerate_manager.delay_without_present()
+        } else {
+            self.state.framerate_manager.delay()
+        };
+        self.previous_sleep
+    }
+
+    /// Captures the following frames into the [`FrameSequence`] at its fixed frame rate, see
+    /// the [`capture`] module. Needs [`EngineBuilder::with_frame_capture`]. A capture that is
+    /// already running is stopped first.
+    ///
+    /// [`FrameSequence`]: capture::FrameSequence
+    #[cfg(feature = "frame-capture")]
+    pub fn start_frame_capture(
+        &mut self,
+        mut sequence: capture::FrameSequence,
+    ) -> Result<(), capture::FrameCaptureError> {
+        self.stop_frame_capture();
+        let elapsed = self
+            .previous_update
+            .map(|previous| previous.duration_since(self.state.started))
+            .unwrap_or_default();
+        sequence.start(elapsed)?;
+        info!(
+            "Capturing frames at {} fps into {}",
+            sequence.frame_rate(),
+            sequence.directory().display()
+        );
+        self.vulkan_system
+            .set_fixed_frame_delta(Some(sequence.frame_delta()));
+        self.frame_capture = Some(sequence);
+        Ok(())
+    }
+
+    /// Stops the capture after writing the last rendered frame and continues with the wall
+    /// clock. Returns the sequence, [`None`] if no frames were captured.
+    #[cfg(feature = "frame-capture")]
+    pub fn stop_frame_capture(&mut self) -> Option<capture::FrameSequence> {
+        let mut sequence = self.frame_capture.take()?;
+        if let Err(e) = self.write_captured_frame(&mut sequence) {
+            error!("Failed to write the last captured frame: {e}");
+        }
+        self.vulkan_system.set_fixed_frame_delta(None);
+        // the time spent capturing is not a frame of the game
+        self.state.previous_update = None;
+        info!(
+            "Captured {} frames into {}",
+            sequence.written_frames(),
+            sequence.directory().display()
+        );
+        Some(sequence)
+    }
+
+    /// The running capture, see [`Engine::start_frame_capture`].
+    #[inline]
+    #[cfg(feature = "frame-capture")]
+    pub fn frame_capture(&self) -> Option<&capture::FrameSequence> {
+        self.frame_capture.as_ref()
+    }
+
+    /// Writes the previous frame and returns the `delta` and `elapsed` time of the next one.
+    #[cfg(feature = "frame-capture")]
+    fn update_frame_capture(&mut self) -> Option<(Duration, Duration)> {
+        let mut sequence = self.frame_capture.take()?;
+        if let Err(e) = self.write_captured_frame(&mut sequence) {
+            error!("Stopping the frame capture: {e}");
+            self.frame_capture = Some(sequence);
+            self.stop_frame_capture();
+            return None;
+        }
+        if sequence.is_complete() {
+            self.frame_capture = Some(sequence);
+            self.stop_frame_capture();
+            return None;
+        }
+        self.vulkan_system.request_frame_capture();
+        let times = sequence.next_frame();
+        self.frame_capture = Some(sequence);
+        Some(times)
+    }
+
+    #[cfg(feature = "frame-capture")]
+    fn write_captured_frame(
+        &mut self,
+        sequence: &mut capture::FrameSequence,
+    ) -> Result<(), capture::FrameCaptureError> {
+        if let Some(image) = self.vulkan_system.take_frame_capture()? {
+            sequence.write(&image)?;
+        }
+        Ok(())
+    }
+
+    /// See [`EngineBuilder::with_render_mode`]
+    #[inline]
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    #[inline]
+    pub fn dynamic_resolution(&self) -> Option<&DynamicResolution> {
+        self.dynamic_resolution.as_ref()
+    }
+
+    /// To change the bounds or override the scale, see [`DynamicResolution::set_override`].
+    #[inline]
+    pub fn dynamic_resolution_mut(&mut self) -> Option<&mut DynamicResolution> {
+        self.dynamic_resolution.as_mut()
+    }
+
+    /// See [`EngineBuilder::with_dynamic_resolution`], [`None`] renders the world at the native
+    /// resolution again.
+    #[inline]
+    pub fn set_dynamic_resolution(&mut self, resolution: Option<DynamicResolution>) {
+        self.dynamic_resolution = resolution;
+    }
+
+    #[inline]
+    pub fn viewport_layout(&self) -> &ViewportLayout {
+        self.vulkan_system.viewport_layout()
+    }
+
+    /// Enforces the aspect ratio of the layout with bars around the viewports and splits the
+    /// window for split screens, see [`ViewportLayout`]. The `width` and `height` of the
+    /// [`RenderContext`] and the world of [`BeforeRenderContext::render_layered`] are those of a
+    /// single viewport, the input can be mapped with [`Engine::window_to_viewport`].
+    #[inline]
+    pub fn set_viewport_layout(&mut self, layout: ViewportLayout) {
+        self.vulkan_system.set_viewport_layout(layout);
+    }
+
+    /// The viewports in pixels of the rendered image, see [`VulkanSystem::viewports`].
+    #[inline]
+    pub fn viewports(&self) -> Vec<ViewportRect> {
+        self.vulkan_system.viewports()
+    }
+
+    /// The viewport the [`RenderContext`] renders into.
+    #[inline]
+    fn first_viewport(&self) -> ViewportRect {
+        self.vulkan_system
+            .viewports()
+            .first()
+            .copied()
+            .unwrap_or_else(|| ViewportRect::full(self.vulkan_system.image_extent()))
+    }
+
+    /// The viewport below the window coordinates of a sdl2 event (like
+    /// [`Event::MouseMotion`]) and the position in pixels within it, [`None`] over the bars.
+    ///
+    /// [`Event::MouseMotion`]: sdl2::event::Event::MouseMotion
+    #[inline]
+    pub fn window_to_viewport(&self, x: i32, y: i32) -> Option<ViewportPos> {
+        self.window_pos_to_viewport(Pos::new(x as f32, y as f32))
+    }
+
+    fn window_pos_to_viewport(&self, pos: Pos<f32>) -> Option<ViewportPos> {
+        let scale = self.state.sdl.drawable_scale();
+        self.vulkan_system.viewport_layout().locate(
+            self.vulkan_system.image_extent(),
+            [pos.x * scale, pos.y * scale],
+        )
+    }
+
+    /// The position of the mouse in window coordinates, that of the [`VirtualCursor`] while it
+    /// is shown. Window coordinates are points, not pixels, on HiDPI displays.
+    pub fn mouse_window_pos(&self) -> Pos<f32> {
+        if let Some(cursor) = self
+            .state
+            .virtual_cursor
+            .as_ref()
+            .filter(|c| c.is_visible())
+        {
+            return cursor.position();
+        }
+        let state = self.state.sdl.event_pump.mouse_state();
+        Pos::new(state.x() as f32, state.y() as f32)
+    }
+
+    /// The viewport below the mouse and the position in pixels within it, [`None`] over the
+    /// bars, see [`Engine::mouse_window_pos`] and [`Engine::window_to_viewport`].
+    #[inline]
+    pub fn mouse_viewport_pos(&self) -> Option<ViewportPos> {
+        self.window_pos_to_viewport(self.mouse_window_pos())
+    }
+
+    /// The scale the world is currently rendered at, `1.0` without dynamic resolution.
+    #[inline]
+    pub fn world_scale(&self) -> f32 {
+        self.dynamic_resolution
+            .as_ref()
+            .map_or(1.0, DynamicResolution::scale)
+    }
+
+    /// (Re-)creates the [`WorldTarget`] for the current scale, the world is rendered at the
+    /// native resolution if that fails.
+    fn update_world_target(&mut self, width: u32, height: u32) {
+        let Some(resolution) = &self.dynamic_resolution else {
+            self.world_target = None;
+            return;
+        };
+
+        let [width, height] = resolution.scaled_extent(width, height);
+        if self
+            .world_target
+            .as_ref()
+            .is_some_and(|world| world.target.extent() == [width, height])
+        {
+            return;
+        }
+
+        self.world_target = match self.vulkan_system.create_render_target(width, height) {
+            Ok(target) => {
+                let target = target.with_clear_value(self.vulkan_system.clear_value());
+                match target.texture(&self.vulkan_pipelines.texture) {
+                    Ok(texture) => Some(WorldTarget { target, texture }),
+                    Err(e) => {
+                        error!("Failed to create the texture of the world target: {e}");
+                        None
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to create the world target of {width}x{height}: {e}");
+                None
+            }
+        };
+    }
+
+    /// Moves the recording and submission of the frames to a dedicated render thread, while the
+    /// SDL events are polled and the game is updated on the calling thread, see
+    /// [`threaded`](crate::engine::threaded). The `renderer` records the commands of a frame
+    /// from the packet the update filled. Egui is not rendered on this path, the egui contexts
+    /// are dropped.
+    pub fn into_threaded<P, R>(self, renderer: R) -> Result<threaded::ThreadedEngine<P>, Error>
+    where
+        P: Default + Send + 'static,
+        R: FnMut(RenderContext, &mut P) -> Vec<RecordedCommands> + Send + 'static,
+    {
+        threaded::ThreadedEngine::spawn(self, renderer)
+    }
+
+    /// Records the [`RenderStats`] of the frame and passes them to the plugins.
+    fn finish_frame(&mut self, frame: u64, duration: Duration, command_buffers: usize) {
+        self.state.on_render_stats(RenderStats {
+            frame,
+            duration,
+            command_buffers,
+            memory: self.vulkan_system.memory_stats(),
+        });
     }
 }
 
@@ -233,11 +1238,28 @@ pub enum Error {
     VulkanSystemError(#[from] system::vulkan::Error),
     #[error("Failed to create a Vulkan System Pipeline: {0}")]
     PipelineSystemCreateError(#[from] system::vulkan::PipelineCreateError),
+    #[cfg(feature = "steamworks")]
+    #[error("Steam Error: {0}")]
+    SteamError(#[from] system::steam::Error),
+    #[error("Failed to build the plugin {0}: {1}")]
+    PluginBuildError(String, PluginError),
+    #[error("Failed to start the render thread: {0}")]
+    RenderThreadSpawnError(std::io::Error),
+    #[error("The render thread terminated")]
+    RenderThreadTerminated,
 }
 
 pub struct BeforeRenderContext<'a> {
     engine: &'a mut Engine,
-    pub events: Vec<Event>,
+    pub events: Vec<EngineEvent>,
+    /// See [`Engine::frame`]
+    pub frame: u64,
+    /// The time between the start of the previous frame and the start of this one, zero for the
+    /// first frame
+    pub delta: Duration,
+    /// The time between the creation of the [`Engine`] and the start of this frame, a monotonic
+    /// clock for animations and time uniforms of shaders
+    pub elapsed: Duration,
     pub width: u32,
     pub height: u32,
     pub start: Instant,
@@ -246,68 +1268,623 @@ pub struct BeforeRenderContext<'a> {
 impl<'a> BeforeRenderContext<'a> {
     #[cfg(feature = "ui-egui")]
     pub fn update_egui(&mut self, f: impl FnOnce(&egui::Context)) {
-        self.engine
+        let engine = &mut *self.engine;
+        let plugins = &mut engine.state.plugins;
+        engine
             .egui_system
-            .update(self.width, self.height, &mut self.engine.sdl, f)
+            .update(self.width, self.height, &mut engine.state.sdl, |ctx| {
+                f(ctx);
+                for plugin in plugins.iter_mut() {
+                    plugin.update_egui(ctx);
+                }
+            });
+        self.engine.update_egui_text_input();
+    }
+
+    /// Like [`BeforeRenderContext::update_egui`] but for the additional egui context with the
+    /// given name, which has to be created with [`Engine::create_egui_context`] first. Returns
+    /// `false` without calling `f` if there is no egui context with the name.
+    #[cfg(feature = "ui-egui")]
+    pub fn update_egui_ctx(&mut self, name: &str, f: impl FnOnce(&egui::Context)) -> bool {
+        let (width, height) = (self.width, self.height);
+        let engine = &mut *self.engine;
+        let Some(named) = engine
+            .egui_contexts
+            .iter_mut()
+            .find(|named| named.name == name)
+        else {
+            return false;
+        };
+        named.system.update(width, height, &mut engine.state.sdl, f);
+        engine.update_egui_text_input();
+        true
+    }
+
+    #[inline]
+    pub fn haptics(&mut self) -> &mut Haptics {
+        &mut self.engine.state.haptics
+    }
+
+    /// See [`Engine::action_map_mut`]
+    #[inline]
+    pub fn action_map(&mut self) -> &mut ActionMap {
+        &mut self.engine.state.action_map
+    }
+
+    /// See [`Engine::spatial_audio_mut`]
+    #[inline]
+    pub fn spatial_audio(&mut self) -> &mut SpatialAudio {
+        &mut self.engine.state.spatial_audio
+    }
+
+    /// See [`EngineBuilder::with_virtual_cursor`]
+    #[inline]
+    pub fn virtual_cursor(&mut self) -> Option<&mut VirtualCursor> {
+        self.engine.state.virtual_cursor.as_mut()
+    }
+
+    /// The position of the mouse in pixels of the viewport the world is rendered into, which is
+    /// the screen of a [`Map2dView`] with the `width` and `height` of the [`RenderContext`]. The
+    /// HiDPI scale and the bars of the [`ViewportLayout`] are accounted for. [`None`] while the
+    /// mouse is over the bars or over another viewport of a split screen, see
+    /// [`Engine::mouse_viewport_pos`] for those.
+    pub fn mouse_screen_pos(&self) -> Option<Pos<f32>> {
+        self.engine
+            .mouse_viewport_pos()
+            .filter(|viewport_pos| viewport_pos.index == 0)
+            .map(|viewport_pos| Pos::new(viewport_pos.pos[0], viewport_pos.pos[1]))
+    }
+
+    /// The [`BeforeRenderContext::mouse_screen_pos`] in the world of the view.
+    #[inline]
+    pub fn mouse_world_pos(&self, view: &Map2dView) -> Option<Pos<f32>> {
+        self.mouse_screen_pos()
+            .map(|pos| view.position_screen_to_world(pos))
+    }
+
+    /// See [`Engine::plugin_mut`]
+    #[inline]
+    pub fn plugin<P: EnginePlugin>(&mut self) -> Option<&mut P> {
+        self.engine.plugin_mut()
+    }
+
+    #[inline]
+    #[cfg(feature = "steamworks")]
+    pub fn steam(&mut self) -> Option<&mut system::steam::Steam> {
+        self.engine.state.steam.as_mut()
+    }
+
+    /// See [`Engine::audio_mut`]
+    #[inline]
+    #[cfg(feature = "audio")]
+    pub fn audio(&mut self) -> Option<&mut system::audio::AudioSystem> {
+        self.engine.audio_mut()
+    }
+
+    /// See [`TextInput::start`]
+    #[inline]
+    pub fn start_text_input(&mut self, rect: Option<sdl2::rect::Rect>) {
+        self.engine.start_text_input(rect);
+    }
+
+    /// See [`TextInput::stop`]
+    #[inline]
+    pub fn stop_text_input(&mut self) {
+        self.engine.stop_text_input();
+    }
+
+    /// See [`Engine::set_color_filter`]
+    #[inline]
+    pub fn set_color_filter(&mut self, filter: Option<ColorFilter>) {
+        self.engine.set_color_filter(filter);
+    }
+
+    /// See [`Engine::accessibility`]
+    #[inline]
+    pub fn accessibility(&self) -> &AccessibilityOptions {
+        &self.engine.state.accessibility
+    }
+
+    /// See [`Engine::set_ambient_light`]
+    #[inline]
+    pub fn set_ambient_light(&mut self, light: impl Into<AmbientLight>) {
+        self.engine.set_ambient_light(light);
     }
 
     pub fn render<F1>(self, f1: F1) -> Result<(), DrawError>
     where
-        F1: FnOnce(RenderContext) -> Vec<Arc<SecondaryAutoCommandBuffer>>,
+        F1: FnOnce(RenderContext) -> Vec<RecordedCommands>,
     {
-        self.engine
+        self.render_with(None::<fn(RenderContext) -> Vec<RecordedCommands>>, f1)
+    }
+
+    /// Like [`BeforeRenderContext::render`], but the commands are recorded from the
+    /// [`FramePacket`] by the engine. Failing to record the packet is logged and renders an
+    /// empty frame.
+    ///
+    /// [`FramePacket`]: system::packet::FramePacket
+    pub fn render_packet(
+        self,
+        packet: &system::packet::FramePacket,
+        textures: &system::packet::PacketTextures,
+    ) -> Result<(), DrawError> {
+        self.render(|mut context| match packet.record(&mut context, textures) {
+            Ok(recorded) => recorded,
+            Err(e) => {
+                error!("Failed to record FramePacket: {e}");
+                Vec::new()
+            }
+        })
+    }
+
+    /// The output of the main egui context of this frame, to be drawn as part of a
+    /// [`FramePacket`](system::packet::FramePacket). Call it after
+    /// [`BeforeRenderContext::update_egui`].
+    #[inline]
+    #[cfg(feature = "ui-egui")]
+    pub fn capture_egui(&self) -> system::packet::EguiPrimitives {
+        system::packet::EguiPrimitives::capture(&self.engine.egui_system)
+    }
+
+    /// Like [`BeforeRenderContext::render`], but with the world and the UI rendered separately.
+    /// With the [`EngineBuilder::with_dynamic_resolution`], the commands of the world are
+    /// rendered at the reduced resolution into an offscreen target, which is then scaled up
+    /// to the window, while the commands of the UI (and plugins and egui) are rendered at the
+    /// native resolution on top of it. Both callbacks draw in window coordinates, as the
+    /// [`RenderContext::width`] and [`RenderContext::height`] are not scaled.
+    ///
+    /// Commands of the world that are not part of a render pass, like uploads, are executed
+    /// before any rendering as usual.
+    pub fn render_layered<F0, F1>(self, world: F0, ui: F1) -> Result<(), DrawError>
+    where
+        F0: FnOnce(RenderContext) -> Vec<RecordedCommands>,
+        F1: FnOnce(RenderContext) -> Vec<RecordedCommands>,
+    {
+        self.render_with(Some(world), ui)
+    }
+
+    fn render_with<F0, F1>(self, world: Option<F0>, f1: F1) -> Result<(), DrawError>
+    where
+        F0: FnOnce(RenderContext) -> Vec<RecordedCommands>,
+        F1: FnOnce(RenderContext) -> Vec<RecordedCommands>,
+    {
+        if self.engine.render_mode != RenderMode::Secondary {
+            return Err(DrawError::RenderModeMismatch(self.engine.render_mode));
+        }
+
+        let start = Instant::now();
+        let mut command_buffers = 0;
+        // the frame is still rendered without the failed commands
+        let mut failure = None;
+        let viewport = self.engine.first_viewport();
+        let [width, height] = viewport.extent();
+        let result = self
+            .engine
             .vulkan_system
             .render(self.width, self.height, |render_context| {
                 let mut commands = Vec::default();
 
                 #[cfg(feature = "ui-egui")]
-                if let Err(e) = self
-                    .engine
-                    .vulkan_pipelines
-                    .egui
-                    .prepare(&self.engine.egui_system)
-                {
-                    error!("Failed to prepare rendering for egui: {e}");
+                prepare_egui(
+                    &self.engine.vulkan_pipelines,
+                    &self.engine.egui_system,
+                    &self.engine.egui_contexts,
+                );
+
+                match (world, &self.engine.world_target) {
+                    (Some(world), Some(world_target)) => {
+                        let world_context = render_context.with_render_target(&world_target.target);
+                        let (prepare, render): (Vec<_>, Vec<_>) = world(RenderContext {
+                            inner: &world_context,
+                            pipelines: &self.engine.vulkan_pipelines,
+                            frame: self.frame,
+                            delta: self.delta,
+                            elapsed: self.elapsed,
+                            width,
+                            height,
+                            #[cfg(feature = "ttf-font-renderer")]
+                            font_renderer: &mut self.engine.font_renderer,
+                        })
+                        .into_iter()
+                        .map(RecordedCommands::into_inner)
+                        .partition(|command| command.inheritance_info().render_pass.is_none());
+
+                        commands.extend(prepare);
+                        render_context.render_into(
+                            &world_target.target,
+                            render
+                                .into_iter()
+                                .map(|command| command as Arc<dyn SecondaryCommandBufferAbstract>),
+                        );
+
+                        let composite = render_context
+                            .create_render_buffer_builder()
+                            .map_err(DrawError::FailedToCreateCommandBuffer)
+                            .and_then(|mut builder| {
+                                let quad = TexturedQuad::new(
+                                    [0.0, 0.0],
+                                    [width as f32, height as f32],
+                                    world_target.texture.clone(),
+                                );
+                                if let Err(e) = self
+                                    .engine
+                                    .vulkan_pipelines
+                                    .texture
+                                    .draw_quads(&mut builder, &[quad])
+                                {
+                                    error!("Failed to draw the world target: {e}");
+                                }
+                                builder
+                                    .build()
+                                    .map_err(DrawError::FailedToBuildCommandBuffer)
+                            });
+                        match composite {
+                            Ok(command) => commands.push(command),
+                            Err(e) => failure = Some(e),
+                        }
+                    }
+                    (Some(world), None) => commands.extend(
+                        world(RenderContext {
+                            inner: render_context,
+                            pipelines: &self.engine.vulkan_pipelines,
+                            frame: self.frame,
+                            delta: self.delta,
+                            elapsed: self.elapsed,
+                            width,
+                            height,
+                            #[cfg(feature = "ttf-font-renderer")]
+                            font_renderer: &mut self.engine.font_renderer,
+                        })
+                        .into_iter()
+                        .map(RecordedCommands::into_inner),
+                    ),
+                    (None, _) => {}
                 }
 
-                commands.extend(f1(RenderContext {
-                    inner: render_context,
-                    pipelines: &self.engine.vulkan_pipelines,
-                    width: self.width,
-                    height: self.height,
-                    #[cfg(feature = "ttf-font-renderer")]
-                    font_renderer: &mut self.engine.font_renderer,
-                }));
+                commands.extend(
+                    f1(RenderContext {
+                        inner: render_context,
+                        pipelines: &self.engine.vulkan_pipelines,
+                        frame: self.frame,
+                        delta: self.delta,
+                        elapsed: self.elapsed,
+                        width,
+                        height,
+                        #[cfg(feature = "ttf-font-renderer")]
+                        font_renderer: &mut self.engine.font_renderer,
+                    })
+                    .into_iter()
+                    .map(RecordedCommands::into_inner),
+                );
+
+                for plugin in &mut self.engine.state.plugins {
+                    commands.extend(
+                        plugin
+                            .render(RenderContext {
+                                inner: render_context,
+                                pipelines: &self.engine.vulkan_pipelines,
+                                frame: self.frame,
+                                delta: self.delta,
+                                elapsed: self.elapsed,
+                                width,
+                                height,
+                                #[cfg(feature = "ttf-font-renderer")]
+                                font_renderer: &mut self.engine.font_renderer,
+                            })
+                            .into_iter()
+                            .map(RecordedCommands::into_inner),
+                    );
+                }
 
                 #[cfg(feature = "ui-egui")]
                 {
                     let mut builder = render_context.create_render_buffer_builder().unwrap();
-                    if let Err(e) = self
-                        .engine
-                        .vulkan_pipelines
-                        .egui
-                        .draw(&mut builder, &self.engine.egui_system)
-                    {
-                        error!("Failed to render egui: {e}");
-                    }
-
+                    draw_egui(
+                        &mut builder,
+                        &self.engine.vulkan_pipelines,
+                        &self.engine.egui_system,
+                        &self.engine.egui_contexts,
+                    );
                     commands.push(builder.build().unwrap());
                 }
 
+                if let Some(quad) = self
+                    .engine
+                    .state
+                    .virtual_cursor
+                    .as_ref()
+                    .and_then(|cursor| {
+                        cursor.quad(
+                            self.engine.vulkan_pipelines.texture.placeholders(),
+                            self.engine.state.sdl.drawable_scale(),
+                        )
+                    })
+                {
+                    // the cursor is in window coordinates, but drawn into the viewport
+                    let quad = quad.translated([-(viewport.x as f32), -(viewport.y as f32)]);
+                    let cursor = render_context
+                        .create_render_buffer_builder()
+                        .map_err(DrawError::FailedToCreateCommandBuffer)
+                        .and_then(|mut builder| {
+                            draw_virtual_cursor(&mut builder, &self.engine.vulkan_pipelines, quad);
+                            builder
+                                .build()
+                                .map_err(DrawError::FailedToBuildCommandBuffer)
+                        });
+                    match cursor {
+                        Ok(command) => commands.push(command),
+                        Err(e) => failure = Some(e),
+                    }
+                }
+
+                command_buffers = commands.len();
                 commands
-            })
+            });
+
+        self.engine
+            .finish_frame(self.frame, start.elapsed(), command_buffers);
+        result.and(failure.map_or(Ok(()), Err))
+    }
+
+    /// Like [`BeforeRenderContext::render`], but for the [`RenderMode::Inline`]: the callback
+    /// records its draw commands directly into the primary command buffer of the frame instead
+    /// of returning secondary command buffers, see [`VulkanSystem::render_inline`]. The plugins
+    /// are rendered with [`EnginePlugin::render_inline`] and egui on top of everything as usual.
+    /// The dynamic resolution is applied with [`BeforeRenderContext::render_inline_layered`].
+    pub fn render_inline<F1>(self, f1: F1) -> Result<(), DrawError>
+    where
+        F1: FnOnce(RenderContext, &mut InlineCommandBufferBuilder),
+    {
+        self.render_inline_with(
+            None::<fn(RenderContext, &mut InlineCommandBufferBuilder)>,
+            f1,
+        )
+    }
+
+    /// Like [`BeforeRenderContext::render_layered`], but for the [`RenderMode::Inline`]. With the
+    /// [`EngineBuilder::with_dynamic_resolution`], the world is recorded into a primary command
+    /// buffer of its own at the reduced resolution, see [`VulkanSystem::render_inline_layered`].
+    pub fn render_inline_layered<F0, F1>(self, world: F0, ui: F1) -> Result<(), DrawError>
+    where
+        F0: FnOnce(RenderContext, &mut InlineCommandBufferBuilder),
+        F1: FnOnce(RenderContext, &mut InlineCommandBufferBuilder),
+    {
+        self.render_inline_with(Some(world), ui)
+    }
+
+    fn render_inline_with<F0, F1>(self, world: Option<F0>, f1: F1) -> Result<(), DrawError>
+    where
+        F0: FnOnce(RenderContext, &mut InlineCommandBufferBuilder),
+        F1: FnOnce(RenderContext, &mut InlineCommandBufferBuilder),
+    {
+        if self.engine.render_mode != RenderMode::Inline {
+            return Err(DrawError::RenderModeMismatch(self.engine.render_mode));
+        }
+
+        let start = Instant::now();
+        let viewport = self.engine.first_viewport();
+        let [width, height] = viewport.extent();
+        let world_target = self
+            .engine
+            .world_target
+            .as_ref()
+            .filter(|_| world.is_some());
+        let result = self.engine.vulkan_system.render_inline_layered(
+            self.width,
+            self.height,
+            world_target.map(|world_target| &world_target.target),
+            |render_context, world_builder, builder| {
+                #[cfg(feature = "ui-egui")]
+                prepare_egui(
+                    &self.engine.vulkan_pipelines,
+                    &self.engine.egui_system,
+                    &self.engine.egui_contexts,
+                );
+
+                match (world, world_builder, world_target) {
+                    (Some(world), Some(world_builder), Some(world_target)) => {
+                        let world_context = render_context.with_render_target(&world_target.target);
+                        world(
+                            RenderContext {
+                                inner: &world_context,
+                                pipelines: &self.engine.vulkan_pipelines,
+                                frame: self.frame,
+                                delta: self.delta,
+                                elapsed: self.elapsed,
+                                width,
+                                height,
+                                #[cfg(feature = "ttf-font-renderer")]
+                                font_renderer: &mut self.engine.font_renderer,
+                            },
+                            world_builder,
+                        );
+
+                        let quad = TexturedQuad::new(
+                            [0.0, 0.0],
+                            [width as f32, height as f32],
+                            world_target.texture.clone(),
+                        );
+                        if let Err(e) = self
+                            .engine
+                            .vulkan_pipelines
+                            .texture
+                            .draw_quads(builder, &[quad])
+                        {
+                            error!("Failed to draw the world target: {e}");
+                        }
+                    }
+                    (Some(world), _, _) => world(
+                        RenderContext {
+                            inner: render_context,
+                            pipelines: &self.engine.vulkan_pipelines,
+                            frame: self.frame,
+                            delta: self.delta,
+                            elapsed: self.elapsed,
+                            width,
+                            height,
+                            #[cfg(feature = "ttf-font-renderer")]
+                            font_renderer: &mut self.engine.font_renderer,
+                        },
+                        builder,
+                    ),
+                    (None, _, _) => {}
+                }
+
+                f1(
+                    RenderContext {
+                        inner: render_context,
+                        pipelines: &self.engine.vulkan_pipelines,
+                        frame: self.frame,
+                        delta: self.delta,
+                        elapsed: self.elapsed,
+                        width,
+                        height,
+                        #[cfg(feature = "ttf-font-renderer")]
+                        font_renderer: &mut self.engine.font_renderer,
+                    },
+                    builder,
+                );
+
+                for plugin in &mut self.engine.state.plugins {
+                    plugin.render_inline(
+                        RenderContext {
+                            inner: render_context,
+                            pipelines: &self.engine.vulkan_pipelines,
+                            frame: self.frame,
+                            delta: self.delta,
+                            elapsed: self.elapsed,
+                            width,
+                            height,
+                            #[cfg(feature = "ttf-font-renderer")]
+                            font_renderer: &mut self.engine.font_renderer,
+                        },
+                        builder,
+                    );
+                }
+
+                #[cfg(feature = "ui-egui")]
+                draw_egui(
+                    builder,
+                    &self.engine.vulkan_pipelines,
+                    &self.engine.egui_system,
+                    &self.engine.egui_contexts,
+                );
+
+                if let Some(quad) = self
+                    .engine
+                    .state
+                    .virtual_cursor
+                    .as_ref()
+                    .and_then(|cursor| {
+                        cursor.quad(
+                            self.engine.vulkan_pipelines.texture.placeholders(),
+                            self.engine.state.sdl.drawable_scale(),
+                        )
+                    })
+                {
+                    // the cursor is in window coordinates, but drawn into the viewport
+                    let quad = quad.translated([-(viewport.x as f32), -(viewport.y as f32)]);
+                    draw_virtual_cursor(builder, &self.engine.vulkan_pipelines, quad);
+                }
+            },
+        );
+
+        self.engine.finish_frame(self.frame, start.elapsed(), 0);
+        result
+    }
+}
+
+#[cfg(feature = "ui-egui")]
+fn prepare_egui(
+    pipelines: &VulkanPipelines,
+    egui_system: &system::egui::EguiSystem,
+    egui_contexts: &[NamedEguiContext],
+) {
+    if let Err(e) = pipelines.egui.prepare(egui_system) {
+        error!("Failed to prepare rendering for egui: {e}");
+    }
+
+    for named in egui_contexts {
+        if let Err(e) = pipelines.egui.prepare_with(&named.system, &named.textures) {
+            error!("Failed to prepare rendering for egui '{}': {e}", named.name);
+        }
+    }
+}
+
+#[cfg(feature = "ui-egui")]
+fn draw_egui<P: system::vulkan::egui::EguiCallbackCommandBuffer>(
+    builder: &mut vulkano::command_buffer::AutoCommandBufferBuilder<P>,
+    pipelines: &VulkanPipelines,
+    egui_system: &system::egui::EguiSystem,
+    egui_contexts: &[NamedEguiContext],
+) {
+    if let Err(e) = pipelines.egui.draw(builder, egui_system) {
+        error!("Failed to render egui: {e}");
+    }
+
+    for named in egui_contexts {
+        if let Err(e) = pipelines
+            .egui
+            .draw_with(builder, &named.system, &named.textures)
+        {
+            error!("Failed to render egui '{}': {e}", named.name);
+        }
+    }
+}
+
+fn draw_virtual_cursor<P>(
+    builder: &mut vulkano::command_buffer::AutoCommandBufferBuilder<P>,
+    pipelines: &VulkanPipelines,
+    quad: TexturedQuad,
+) {
+    if let Err(e) = pipelines.texture.draw_quads(builder, &[quad]) {
+        error!("Failed to draw the virtual cursor: {e}");
     }
 }
 
+/// The offscreen target of the world with the dynamic resolution, see
+/// [`BeforeRenderContext::render_layered`].
+struct WorldTarget {
+    target: RenderTarget,
+    texture: TextureId<TexturedPipeline>,
+}
+
+#[cfg(feature = "ui-egui")]
+struct NamedEguiContext {
+    name: String,
+    system: system::egui::EguiSystem,
+    textures: system::vulkan::egui::EguiTextures,
+}
+
 pub struct RenderContext<'a, 'b> {
     pub inner: &'a system::vulkan::system::RenderContext<'b>,
     pub pipelines: &'a Arc<VulkanPipelines>,
+    /// See [`BeforeRenderContext::frame`]
+    pub frame: u64,
+    /// See [`BeforeRenderContext::delta`]
+    pub delta: Duration,
+    /// See [`BeforeRenderContext::elapsed`]
+    pub elapsed: Duration,
+    /// The size of the viewport the commands render into, which is the size of the window
+    /// unless a [`ViewportLayout`] adds bars or splits it, see [`Engine::set_viewport_layout`]
     pub width: u32,
     pub height: u32,
     #[cfg(feature = "ttf-font-renderer")]
     pub font_renderer: &'a mut crate::engine::system::ttf::FontRenderer,
 }
 
+/// Statistics of a rendered frame, see [`Engine::render_stats`].
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct RenderStats {
+    pub frame: u64,
+    /// The time spent in [`BeforeRenderContext::render`], including the wait for the swapchain
+    pub duration: Duration,
+    /// The amount of secondary command buffers that were executed, zero with the
+    /// [`RenderMode::Inline`]
+    pub command_buffers: usize,
+    pub memory: MemoryStats,
+}
+
 pub struct RenderResponse<T> {
     pub data: T,
     pub start: Instant,
@@ -1,18 +1,148 @@
+use crate::engine::builder::EngineConfig;
 use crate::support::image::RawRgbaImage;
 use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::Rect;
 use sdl2::surface::Surface;
-use sdl2::video::Window;
-use sdl2::{EventPump, Sdl, VideoSubsystem};
+use sdl2::video::{Window, WindowPos};
+use sdl2::{EventPump, Sdl, TimerSubsystem, VideoSubsystem};
+
+/// An overlay that is drawn onto the window icon, which most platforms also show in the taskbar.
+/// SDL2 has no access to platform specific taskbar badges, so this works wherever the window icon
+/// does.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WindowBadge {
+    /// A bar along the bottom edge of the icon, filled from `0.0` (empty) to `1.0` (full)
+    Progress(f32),
+    /// A filled circle in the top right corner of the icon, like for unseen notifications
+    Dot([u8; 4]),
+}
+
+impl WindowBadge {
+    const PROGRESS_COLOR: [u8; 4] = [76, 175, 80, 255];
+    const PROGRESS_BACKGROUND: [u8; 4] = [32, 32, 32, 255];
+
+    /// Draws the badge onto the RGBA pixels of an image with the given size.
+    fn draw(&self, data: &mut [u8], width: u32, height: u32) {
+        let (width, height) = (width as usize, height as usize);
+        if width == 0 || height == 0 || data.len() < width * height * 4 {
+            return;
+        }
+        let mut set_pixel = |x: usize, y: usize, color: [u8; 4]| {
+            let index = (y * width + x) * 4;
+            data[index..index + 4].copy_from_slice(&color);
+        };
+
+        match *self {
+            WindowBadge::Progress(progress) => {
+                let bar_height = (height / 6).max(1);
+                let filled = (width as f32 * progress.clamp(0.0, 1.0)).round() as usize;
+                for y in height - bar_height..height {
+                    for x in 0..width {
+                        if x < filled {
+                            set_pixel(x, y, Self::PROGRESS_COLOR);
+                        } else {
+                            set_pixel(x, y, Self::PROGRESS_BACKGROUND);
+                        }
+                    }
+                }
+            }
+            WindowBadge::Dot(color) => {
+                let radius = (width.min(height) as f32 / 5.0).max(1.0);
+                let center = [width as f32 - radius, radius];
+                for y in 0..height {
+                    for x in 0..width {
+                        let dx = x as f32 + 0.5 - center[0];
+                        let dy = y as f32 + 0.5 - center[1];
+                        if dx * dx + dy * dy <= radius * radius {
+                            set_pixel(x, y, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The display a window is shown on, see [`EngineEvent::display_changed`].
+///
+/// [`EngineEvent::display_changed`]: crate::engine::event::EngineEvent::display_changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayInfo {
+    pub index: i32,
+    pub name: Option<String>,
+    /// The area in screen coordinates that is not covered by taskbars or docks
+    pub usable_bounds: Rect,
+    /// [`None`] if unknown
+    pub refresh_rate: Option<u16>,
+    /// The horizontal DPI, [`None`] if unknown
+    pub dpi: Option<f32>,
+    /// The factor to scale the UI with, derived from the DPI, `1.0` if unknown, see
+    /// [`DisplayInfo::scale_factor_for_dpi`]
+    pub scale_factor: f32,
+}
+
+impl DisplayInfo {
+    /// The DPI of a display with a [`DisplayInfo::scale_factor`] of `1.0`
+    pub const BASE_DPI: f32 = if cfg!(target_os = "macos") {
+        72.0
+    } else {
+        96.0
+    };
+
+    /// The steps the [`DisplayInfo::scale_factor`] is rounded to
+    pub const SCALE_FACTOR_STEP: f32 = 0.25;
+
+    /// The [`DisplayInfo::scale_factor`] for a display with the DPI. It is rounded to the
+    /// [`DisplayInfo::SCALE_FACTOR_STEP`], so that inexact DPI reports (like 101 instead of 96)
+    /// do not blur the UI, and is never below `1.0`.
+    pub fn scale_factor_for_dpi(dpi: f32) -> f32 {
+        let factor =
+            (dpi / Self::BASE_DPI / Self::SCALE_FACTOR_STEP).round() * Self::SCALE_FACTOR_STEP;
+        if factor.is_finite() {
+            factor.max(1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Where the window is opened, see [`EngineBuilder::with_window_placement`]. Positions are
+/// stored relative to the usable bounds of their display, so they are still valid after the
+/// displays were rearranged. If the display does not exist anymore, the window is placed on the
+/// primary display instead, and a window that would not be visible is moved onto the display.
+///
+/// [`EngineBuilder::with_window_placement`]: crate::engine::builder::EngineBuilder::with_window_placement
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum WindowPlacement {
+    /// Wherever the platform places new windows
+    #[default]
+    Undefined,
+    /// Centered on the display with the index
+    Centered { display: i32 },
+    /// At the position in screen coordinates relative to the top left corner of the usable
+    /// bounds of the display with the index
+    Positioned { display: i32, x: i32, y: i32 },
+}
 
 pub struct SdlParts {
     pub video_subsystem: VideoSubsystem,
     pub event_pump: EventPump,
+    pub timer: TimerSubsystem,
     pub window: Window,
     pub window_maximized: bool,
     #[cfg(feature = "ttf-sdl2")]
     pub ttf: sdl2::ttf::Sdl2TtfContext,
     pub context: Sdl,
     pub window_icon: Option<Surface<'static>>,
+    /// The icon without the [`SdlParts::window_badge`], to redraw it when the badge changes
+    pub(crate) window_icon_image: Option<RawRgbaImage>,
+    pub(crate) window_badge: Option<WindowBadge>,
+    /// See [`SdlParts::update_display`]
+    pub(crate) display: Option<DisplayInfo>,
 }
 
 impl SdlParts {
@@ -24,8 +154,180 @@ impl SdlParts {
     }
 
     pub(crate) fn set_window_icon(&mut self, image: RawRgbaImage) {
-        let (data, width, height) = image.destruct();
-        let mut data = data.into_owned();
+        self.window_icon_image = Some(image);
+        self.update_window_icon();
+    }
+
+    pub(crate) fn set_window_badge(&mut self, badge: Option<WindowBadge>) {
+        if self.window_badge != badge {
+            self.window_badge = badge;
+            self.update_window_icon();
+        }
+    }
+
+    /// The pixels of the drawable per window coordinate. Platforms that scale windows themselves,
+    /// like macOS and Wayland, report the size and the mouse positions of a window on HiDPI
+    /// displays in coordinates that are larger than its pixels, so this is greater than `1.0`
+    /// there. `1.0` if unknown.
+    pub fn drawable_scale(&self) -> f32 {
+        let (width, _) = self.window.size();
+        let (drawable_width, _) = self.window.vulkan_drawable_size();
+        if width == 0 || drawable_width == 0 {
+            1.0
+        } else {
+            drawable_width as f32 / width as f32
+        }
+    }
+
+    /// The pixels per point to scale the UI with: the [`SdlParts::drawable_scale`] on platforms
+    /// that scale windows themselves, the [`DisplayInfo::scale_factor`] otherwise.
+    pub fn pixels_per_point(&self) -> f32 {
+        let drawable_scale = self.drawable_scale();
+        if drawable_scale > 1.0 {
+            drawable_scale
+        } else {
+            self.display
+                .as_ref()
+                .map_or(1.0, |display| display.scale_factor)
+        }
+    }
+
+    /// Queries the display the window is currently shown on.
+    pub fn query_display(&self) -> Result<DisplayInfo, String> {
+        let index = self.window.display_index()?;
+        let dpi = self
+            .video_subsystem
+            .display_dpi(index)
+            .ok()
+            .map(|(_diagonal, horizontal, _vertical)| horizontal)
+            .filter(|dpi| *dpi > 0.0);
+        Ok(DisplayInfo {
+            index,
+            name: self.video_subsystem.display_name(index).ok(),
+            usable_bounds: self.video_subsystem.display_usable_bounds(index)?,
+            refresh_rate: self
+                .video_subsystem
+                .current_display_mode(index)
+                .ok()
+                .filter(|mode| mode.refresh_rate > 0)
+                .map(|mode| mode.refresh_rate as u16),
+            dpi,
+            scale_factor: dpi.map_or(1.0, DisplayInfo::scale_factor_for_dpi),
+        })
+    }
+
+    /// Queries the display of the window and returns it if it differs from the previous one. If
+    /// the window is not visible on the display anymore, like after the display it was shown on
+    /// was disconnected, it is moved back onto the display.
+    pub(crate) fn update_display(&mut self) -> Option<DisplayInfo> {
+        let display = self
+            .query_display()
+            .map_err(|e| warn!("Failed to query the display of the window: {e}"))
+            .ok()?;
+        if self.display.as_ref() == Some(&display) {
+            return None;
+        }
+        if !self.window_maximized {
+            self.clamp_window_position(display.usable_bounds);
+        }
+        self.display = Some(display.clone());
+        Some(display)
+    }
+
+    /// Moves the window to the [`WindowPlacement`].
+    pub fn place_window(&mut self, placement: WindowPlacement) {
+        let display = match placement {
+            WindowPlacement::Undefined => return,
+            WindowPlacement::Centered { display } => display,
+            WindowPlacement::Positioned { display, .. } => display,
+        };
+        let bounds = match self.video_subsystem.display_usable_bounds(display) {
+            Ok(bounds) => bounds,
+            Err(e) => {
+                warn!("Display {display} is not available ({e}), using the primary display");
+                match self.video_subsystem.display_usable_bounds(0) {
+                    Ok(bounds) => bounds,
+                    Err(e) => return error!("Failed to query the primary display: {e}"),
+                }
+            }
+        };
+
+        let (width, height) = self.window.size();
+        let (x, y) = match placement {
+            WindowPlacement::Positioned { x, y, .. } => (bounds.x() + x, bounds.y() + y),
+            _ => (
+                bounds.x() + (bounds.width() as i32 - width as i32) / 2,
+                bounds.y() + (bounds.height() as i32 - height as i32) / 2,
+            ),
+        };
+        self.window
+            .set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+        self.clamp_window_position(bounds);
+    }
+
+    /// The current placement of the window on its display, to restore it with
+    /// [`SdlParts::place_window`] on the next start. [`None`] if the display could not be
+    /// queried.
+    pub fn window_placement(&self) -> Option<WindowPlacement> {
+        let display = self
+            .window
+            .display_index()
+            .map_err(|e| warn!("Failed to query the display of the window: {e}"))
+            .ok()?;
+        let bounds = self
+            .video_subsystem
+            .display_usable_bounds(display)
+            .map_err(|e| warn!("Failed to query the bounds of display {display}: {e}"))
+            .ok()?;
+        let (x, y) = self.window.position();
+        Some(WindowPlacement::Positioned {
+            display,
+            x: x - bounds.x(),
+            y: y - bounds.y(),
+        })
+    }
+
+    /// Stores the size and the placement of the window in the config, unless it is in
+    /// fullscreen, where both belong to the display instead.
+    pub fn update_window_config(&self, config: &mut EngineConfig) {
+        config.fullscreen = self.window_maximized;
+        if self.window_maximized {
+            return;
+        }
+        let (width, height) = self.window.size();
+        config.window_width = width;
+        config.window_height = height;
+        if let Some(placement) = self.window_placement() {
+            config.window_placement = placement;
+        }
+    }
+
+    fn clamp_window_position(&mut self, bounds: Rect) {
+        let (x, y) = self.window.position();
+        let (width, height) = self.window.size();
+        if bounds.has_intersection(Rect::new(x, y, width, height)) {
+            return;
+        }
+        let clamp = |pos: i32, size: u32, min: i32, available: u32| {
+            pos.min(min + available as i32 - size as i32).max(min)
+        };
+        let x = clamp(x, width, bounds.x(), bounds.width());
+        let y = clamp(y, height, bounds.y(), bounds.height());
+        info!("Moving the window back onto the display to {x}x{y}");
+        self.window
+            .set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+    }
+
+    fn update_window_icon(&mut self) {
+        let Some(image) = &self.window_icon_image else {
+            return;
+        };
+        let (width, height) = (image.width(), image.height());
+        let mut data = image.data().to_vec();
+        if let Some(badge) = &self.window_badge {
+            badge.draw(&mut data, width, height);
+        }
+
         let source = Surface::from_data(
             &mut data,
             width,
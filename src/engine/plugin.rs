@@ -0,0 +1,143 @@
+//! Extensions that install themselves into the [`Engine`], like a physics debug renderer, a
+//! console or a profiler, added with [`EngineBuilder::with_plugin`].
+//!
+//! ```no_run
+//! use hotrod::engine::builder::EngineBuilder;
+//! use hotrod::engine::event::EngineEvent;
+//! use hotrod::engine::plugin::EnginePlugin;
+//! use hotrod::engine::RenderStats;
+//!
+//! #[derive(Default)]
+//! struct SlowFrameLogger {
+//!     events: usize,
+//! }
+//!
+//! impl EnginePlugin for SlowFrameLogger {
+//!     fn name(&self) -> &str {
+//!         "slow-frame-logger"
+//!     }
+//!
+//!     fn on_event(&mut self, _event: &EngineEvent) {
+//!         self.events += 1;
+//!     }
+//!
+//!     fn after_frame(&mut self, stats: &RenderStats) {
+//!         if stats.duration.as_millis() > 20 {
+//!             println!("Frame {} was slow, {} events so far", stats.frame, self.events);
+//!         }
+//!     }
+//! }
+//!
+//! let engine = EngineBuilder::default()
+//!     .with_plugin(SlowFrameLogger::default())
+//!     .build()
+//!     .unwrap();
+//! let _logger = engine.plugin::<SlowFrameLogger>();
+//! ```
+//!
+//! [`Engine`]: crate::engine::Engine
+//! [`EngineBuilder::with_plugin`]: crate::engine::builder::EngineBuilder::with_plugin
+
+use crate::engine::event::EngineEvent;
+use crate::engine::system::commands::RecordedCommands;
+use crate::engine::system::vulkan::desc::WriteDescriptorSetOrigin;
+use crate::engine::system::vulkan::system::{InlineCommandBufferBuilder, VulkanSystem};
+use crate::engine::system::vulkan::Error;
+use crate::engine::{RenderContext, RenderStats};
+use std::any::Any;
+use std::borrow::Borrow;
+use std::time::Duration;
+
+/// The error a plugin can fail to build with.
+pub type PluginError = Box<dyn std::error::Error + Send + Sync>;
+
+/// An extension of the [`Engine`]. All hooks are optional, they are called in the order the
+/// plugins were added.
+///
+/// [`Engine`]: crate::engine::Engine
+pub trait EnginePlugin: Any {
+    /// The name of the plugin, for logs and error messages.
+    fn name(&self) -> &str;
+
+    /// Called once while the engine is built, before the default pipelines are created.
+    fn build(&mut self, context: &mut PluginBuildContext) -> Result<(), PluginError> {
+        let _ = context;
+        Ok(())
+    }
+
+    /// Called for every polled event, before the events are passed to the game.
+    fn on_event(&mut self, event: &EngineEvent) {
+        let _ = event;
+    }
+
+    /// Called at the start of every [`Engine::update`], before the game updates.
+    ///
+    /// [`Engine::update`]: crate::engine::Engine::update
+    fn before_frame(&mut self, frame: u64, delta: Duration) {
+        let _ = (frame, delta);
+    }
+
+    /// Called after the game updated the main egui context, see
+    /// [`BeforeRenderContext::update_egui`].
+    ///
+    /// [`BeforeRenderContext::update_egui`]: crate::engine::BeforeRenderContext::update_egui
+    #[cfg(feature = "ui-egui")]
+    fn update_egui(&mut self, ctx: &egui::Context) {
+        let _ = ctx;
+    }
+
+    /// The commands to render after the ones of the game and before egui, so overlays of
+    /// plugins are drawn on top of the game.
+    fn render(&mut self, context: RenderContext) -> Vec<RecordedCommands> {
+        let _ = context;
+        Vec::new()
+    }
+
+    /// Like [`EnginePlugin::render`], but for the [`RenderMode::Inline`], where the commands are
+    /// recorded directly into the primary command buffer of the frame.
+    ///
+    /// [`RenderMode::Inline`]: crate::engine::builder::RenderMode::Inline
+    fn render_inline(&mut self, context: RenderContext, builder: &mut InlineCommandBufferBuilder) {
+        let _ = (context, builder);
+    }
+
+    /// Called after a frame was rendered.
+    fn after_frame(&mut self, stats: &RenderStats) {
+        let _ = stats;
+    }
+}
+
+impl dyn EnginePlugin {
+    #[inline]
+    pub fn downcast_ref<P: EnginePlugin>(&self) -> Option<&P> {
+        (self as &dyn Any).downcast_ref()
+    }
+
+    #[inline]
+    pub fn downcast_mut<P: EnginePlugin>(&mut self) -> Option<&mut P> {
+        (self as &mut dyn Any).downcast_mut()
+    }
+}
+
+/// What a plugin can register while the engine is built, see [`EnginePlugin::build`].
+pub struct PluginBuildContext<'a> {
+    pub(crate) vulkan_system: &'a mut VulkanSystem,
+}
+
+impl PluginBuildContext<'_> {
+    /// The system to create pipelines for, e.g. with `MyPipeline::try_from(context.vulkan_system())`.
+    #[inline]
+    pub fn vulkan_system(&self) -> &VulkanSystem {
+        self.vulkan_system
+    }
+
+    /// Adds a descriptor binding that all pipelines created afterwards can use, see
+    /// [`VulkanSystem::insert_write_descriptor`].
+    #[inline]
+    pub fn insert_write_descriptor<W: WriteDescriptorSetOrigin>(
+        &mut self,
+        origin: impl Borrow<W>,
+    ) -> Result<(), Error> {
+        self.vulkan_system.insert_write_descriptor(origin)
+    }
+}
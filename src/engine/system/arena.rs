@@ -0,0 +1,213 @@
+//! A bump allocator for data that only lives for a single frame, like the vertices that are
+//! collected before they are copied into a vertex buffer.
+//!
+//! Allocating is a pointer increment within the current chunk and nothing is freed individually.
+//! [`FrameArena::reset`] frees everything at once and keeps the memory for the next frame, so
+//! after a few frames, no further calls into the global allocator are needed.
+
+use std::alloc::Layout;
+use std::cell::{Cell, RefCell};
+use std::ptr::NonNull;
+
+/// The alignment of every chunk and therefore the maximal alignment of allocated values
+const CHUNK_ALIGN: usize = 16;
+
+struct Chunk {
+    ptr: NonNull<u8>,
+    size: usize,
+}
+
+impl Chunk {
+    fn new(size: usize) -> Self {
+        let layout = Layout::from_size_align(size.max(CHUNK_ALIGN), CHUNK_ALIGN)
+            .expect("Invalid FrameArena chunk size");
+        // SAFETY: the size of the layout is never zero
+        let ptr = unsafe { std::alloc::alloc(layout) };
+        Self {
+            ptr: NonNull::new(ptr).unwrap_or_else(|| std::alloc::handle_alloc_error(layout)),
+            size: layout.size(),
+        }
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        // SAFETY: allocated in Chunk::new with the same layout
+        unsafe {
+            std::alloc::dealloc(
+                self.ptr.as_ptr(),
+                Layout::from_size_align_unchecked(self.size, CHUNK_ALIGN),
+            )
+        }
+    }
+}
+
+/// Owned by the [`VulkanSystem`] and reset at the beginning of each frame, accessible through
+/// [`RenderContext::frame_arena`]. Only [`Copy`] values can be allocated, as nothing is dropped.
+///
+/// [`VulkanSystem`]: crate::engine::system::vulkan::system::VulkanSystem
+/// [`RenderContext::frame_arena`]: crate::engine::system::vulkan::system::RenderContext::frame_arena
+pub struct FrameArena {
+    /// Only the last chunk is allocated from, the others are full
+    chunks: RefCell<Vec<Chunk>>,
+    /// The offset of the free memory in the last chunk
+    offset: Cell<usize>,
+    allocated: Cell<usize>,
+    min_chunk_size: usize,
+}
+
+// SAFETY: the chunks are owned exclusively by the arena
+unsafe impl Send for FrameArena {}
+
+impl Default for FrameArena {
+    #[inline]
+    fn default() -> Self {
+        Self::with_capacity(64 * 1024)
+    }
+}
+
+impl std::fmt::Debug for FrameArena {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameArena")
+            .field("allocated", &self.allocated_bytes())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+// the returned references point to disjoint memory, just like with bumpalo
+#[allow(clippy::mut_from_ref)]
+impl FrameArena {
+    pub fn with_capacity(bytes: usize) -> Self {
+        Self {
+            chunks: RefCell::new(vec![Chunk::new(bytes)]),
+            offset: Cell::new(0),
+            allocated: Cell::new(0),
+            min_chunk_size: bytes.max(CHUNK_ALIGN),
+        }
+    }
+
+    pub fn alloc<T: Copy>(&self, value: T) -> &mut T {
+        let ptr = self.alloc_array::<T>(1);
+        // SAFETY: the memory is valid for one T and not referenced by anything else
+        unsafe {
+            ptr.as_ptr().write(value);
+            &mut *ptr.as_ptr()
+        }
+    }
+
+    pub fn alloc_slice_copy<T: Copy>(&self, values: &[T]) -> &mut [T] {
+        let ptr = self.alloc_array::<T>(values.len());
+        // SAFETY: the memory is valid for values.len() Ts and not referenced by anything else
+        unsafe {
+            std::ptr::copy_nonoverlapping(values.as_ptr(), ptr.as_ptr(), values.len());
+            std::slice::from_raw_parts_mut(ptr.as_ptr(), values.len())
+        }
+    }
+
+    /// Allocates a slice for the items of an iterator with a known length.
+    pub fn alloc_slice_fill_iter<T: Copy, I>(&self, values: I) -> &mut [T]
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let values = values.into_iter();
+        let len = values.len();
+        let ptr = self.alloc_array::<T>(len);
+        let mut written = 0;
+        for value in values.take(len) {
+            // SAFETY: written < len
+            unsafe { ptr.as_ptr().add(written).write(value) };
+            written += 1;
+        }
+        // SAFETY: the first `written` Ts were initialized above
+        unsafe { std::slice::from_raw_parts_mut(ptr.as_ptr(), written) }
+    }
+
+    /// Allocates a slice for the items of an iterator of unknown length, like a
+    /// [`Iterator::flat_map`]. If the size hint is too small, the slice is moved to a larger
+    /// allocation, which wastes the smaller one until the next reset.
+    pub fn alloc_slice_from_iter<T: Copy>(&self, values: impl IntoIterator<Item = T>) -> &mut [T] {
+        let values = values.into_iter();
+        let mut capacity = values.size_hint().0.max(16);
+        let mut ptr = self.alloc_array::<T>(capacity);
+        let mut len = 0;
+        for value in values {
+            if len == capacity {
+                capacity *= 2;
+                let grown = self.alloc_array::<T>(capacity);
+                // SAFETY: both allocations are valid for len Ts and do not overlap
+                unsafe { std::ptr::copy_nonoverlapping(ptr.as_ptr(), grown.as_ptr(), len) };
+                ptr = grown;
+            }
+            // SAFETY: len < capacity
+            unsafe { ptr.as_ptr().add(len).write(value) };
+            len += 1;
+        }
+        // SAFETY: the first `len` Ts were initialized above
+        unsafe { std::slice::from_raw_parts_mut(ptr.as_ptr(), len) }
+    }
+
+    /// The amount of bytes allocated since the last reset.
+    #[inline]
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated.get()
+    }
+
+    /// The amount of bytes of all chunks.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.chunks.borrow().iter().map(|chunk| chunk.size).sum()
+    }
+
+    /// Frees all allocations. If more than one chunk was needed, they are replaced by a single
+    /// chunk large enough for all of them, so the next frame of the same size fits into it.
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+        if chunks.len() > 1 {
+            let capacity = chunks.iter().map(|chunk| chunk.size).sum();
+            chunks.clear();
+            chunks.push(Chunk::new(capacity));
+        }
+        self.offset.set(0);
+        self.allocated.set(0);
+    }
+
+    fn alloc_array<T>(&self, len: usize) -> NonNull<T> {
+        let layout = Layout::array::<T>(len).expect("FrameArena allocation too large");
+        if layout.size() == 0 {
+            return NonNull::dangling();
+        }
+        assert!(
+            layout.align() <= CHUNK_ALIGN,
+            "FrameArena does not support an alignment of {}",
+            layout.align()
+        );
+        self.alloc_layout(layout).cast()
+    }
+
+    fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
+        let mut chunks = self.chunks.borrow_mut();
+        self.allocated.set(self.allocated.get() + layout.size());
+
+        if let Some(chunk) = chunks.last() {
+            let start = self.offset.get().next_multiple_of(layout.align());
+            if start + layout.size() <= chunk.size {
+                self.offset.set(start + layout.size());
+                // SAFETY: start is within the chunk
+                return unsafe { NonNull::new_unchecked(chunk.ptr.as_ptr().add(start)) };
+            }
+        }
+
+        let previous = chunks.last().map_or(0, |chunk| chunk.size);
+        let chunk = Chunk::new(
+            self.min_chunk_size
+                .max(previous * 2)
+                .max(layout.size().next_multiple_of(CHUNK_ALIGN)),
+        );
+        let ptr = chunk.ptr;
+        chunks.push(chunk);
+        self.offset.set(layout.size());
+        ptr
+    }
+}
@@ -0,0 +1,473 @@
+//! Sound effects and music through SDL2_mixer. Sound effects are loaded completely into memory
+//! as [`SoundClip`]s and mixed on a fixed amount of channels, music is streamed from disk as
+//! [`MusicTrack`] with only one track playing at a time.
+//!
+//! ```no_run
+//! use hotrod::engine::system::audio::PlayOptions;
+//! use std::time::Duration;
+//!
+//! let mut engine = hotrod::engine::Engine::default();
+//! let audio = engine.audio_mut().expect("No audio device");
+//! let jump = audio.load_clip("sounds/jump.wav").unwrap();
+//! let theme = audio.load_music("music/theme.ogg").unwrap();
+//!
+//! audio.play_music(&theme, true, Duration::from_secs(2)).unwrap();
+//! let playback = audio
+//!     .play(&jump, PlayOptions::default().with_volume(0.8).with_pan(-0.5))
+//!     .unwrap();
+//! playback.fade_out(Duration::from_millis(200));
+//! ```
+
+use sdl2::libc::{c_int, c_void};
+use sdl2::mixer::{Channel, Chunk, InitFlag, Music, Sdl2MixerContext, MAX_VOLUME};
+use sdl2::sys::mixer;
+use sdl2::{AudioSubsystem, Sdl};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AudioError {
+    #[error("Failed to initialize the audio subsystem: {0}")]
+    Init(String),
+    #[error("Failed to open the audio device: {0}")]
+    Open(String),
+    #[error("Failed to load {0:?}: {1}")]
+    Load(PathBuf, String),
+    #[error("Failed to play: {0}")]
+    Play(String),
+    #[error("All channels are busy")]
+    NoFreeChannel,
+}
+
+/// The configuration of the audio device, see [`EngineBuilder::with_audio`].
+///
+/// [`EngineBuilder::with_audio`]: crate::engine::builder::EngineBuilder::with_audio
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AudioConfig {
+    pub frequency: i32,
+    /// The amount of samples mixed at once, smaller values reduce the latency but might cause
+    /// crackling on slow systems
+    pub chunk_size: i32,
+    /// The amount of sound effects that can play at the same time
+    pub channels: i32,
+}
+
+impl Default for AudioConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            frequency: 44_100,
+            chunk_size: 1024,
+            channels: 16,
+        }
+    }
+}
+
+impl AudioConfig {
+    #[inline]
+    pub fn with_frequency(mut self, frequency: i32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    #[inline]
+    pub fn with_chunk_size(mut self, chunk_size: i32) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    #[inline]
+    pub fn with_channels(mut self, channels: i32) -> Self {
+        self.channels = channels;
+        self
+    }
+}
+
+/// A sound effect that is decoded into memory, cheap to clone.
+#[derive(Clone)]
+pub struct SoundClip(Rc<Chunk>);
+
+/// A music track that is streamed while playing, cheap to clone.
+#[derive(Clone)]
+pub struct MusicTrack(Rc<Music<'static>>);
+
+/// How a [`SoundClip`] is played, see [`AudioSystem::play`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlayOptions {
+    /// `0.0..=1.0`
+    pub volume: f32,
+    /// `-1.0` is left, `0.0` is centered and `1.0` is right
+    pub pan: f32,
+    pub looping: bool,
+    pub fade_in: Duration,
+}
+
+impl Default for PlayOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pan: 0.0,
+            looping: false,
+            fade_in: Duration::ZERO,
+        }
+    }
+}
+
+impl PlayOptions {
+    #[inline]
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    #[inline]
+    pub fn with_pan(mut self, pan: f32) -> Self {
+        self.pan = pan;
+        self
+    }
+
+    #[inline]
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    #[inline]
+    pub fn with_fade_in(mut self, fade_in: Duration) -> Self {
+        self.fade_in = fade_in;
+        self
+    }
+}
+
+/// The channel a [`SoundClip`] is played on. The channel is reused for other clips once the
+/// clip finished, so the handle should not be kept for long.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Playback(Channel);
+
+impl Playback {
+    #[inline]
+    pub fn channel(self) -> i32 {
+        self.0 .0
+    }
+
+    #[inline]
+    pub fn is_playing(self) -> bool {
+        self.0.is_playing()
+    }
+
+    #[inline]
+    pub fn set_volume(self, volume: f32) {
+        self.0.set_volume(to_mixer_volume(volume));
+    }
+
+    pub fn set_pan(self, pan: f32) {
+        let (left, right) = pan_gains(pan);
+        if let Err(e) = self.0.set_panning(left, right) {
+            warn!(
+                "Failed to set the panning of channel {}: {e}",
+                self.channel()
+            );
+        }
+    }
+
+    /// Sets the gain of the left and the right speaker directly, see [`Playback::set_pan`].
+    pub fn set_gains(self, left: u8, right: u8) {
+        if let Err(e) = self.0.set_panning(left, right) {
+            warn!(
+                "Failed to set the panning of channel {}: {e}",
+                self.channel()
+            );
+        }
+    }
+
+    #[inline]
+    pub fn pause(self) {
+        self.0.pause();
+    }
+
+    #[inline]
+    pub fn resume(self) {
+        self.0.resume();
+    }
+
+    #[inline]
+    pub fn stop(self) {
+        self.0.halt();
+    }
+
+    #[inline]
+    pub fn fade_out(self, duration: Duration) {
+        self.0.fade_out(duration.as_millis() as i32);
+    }
+
+    fn start(self, clip: &SoundClip, options: PlayOptions) -> Result<Playback, AudioError> {
+        self.set_volume(options.volume);
+        self.set_pan(options.pan);
+
+        let loops = if options.looping { -1 } else { 0 };
+        let result = if options.fade_in.is_zero() {
+            self.0.play(&clip.0, loops)
+        } else {
+            self.0
+                .fade_in(&clip.0, loops, options.fade_in.as_millis() as i32)
+        };
+        result.map(Playback).map_err(AudioError::Play)
+    }
+}
+
+/// Produces the interleaved stereo samples of the music channel, see
+/// [`AudioSystem::set_music_hook`].
+pub type MusicHook = Box<dyn FnMut(&mut [i16]) + Send>;
+
+/// Owns the SDL2 audio subsystem and the mixer, see the [module documentation](self).
+pub struct AudioSystem {
+    frequency: i32,
+    channels: i32,
+    /// The first channels that are excluded from [`AudioSystem::play`]
+    reserved: i32,
+    /// Kept alive while it is streamed by the mixer
+    current_music: Option<MusicTrack>,
+    /// Boxed twice, so the mixer can be given a thin pointer to it
+    music_hook: Option<Box<MusicHook>>,
+    _mixer: Sdl2MixerContext,
+    _audio: AudioSubsystem,
+}
+
+impl AudioSystem {
+    pub fn new(context: &Sdl, config: AudioConfig) -> Result<Self, AudioError> {
+        let audio = context.audio().map_err(AudioError::Init)?;
+        sdl2::mixer::open_audio(
+            config.frequency,
+            sdl2::mixer::DEFAULT_FORMAT,
+            sdl2::mixer::DEFAULT_CHANNELS,
+            config.chunk_size,
+        )
+        .map_err(AudioError::Open)?;
+        let mixer = sdl2::mixer::init(InitFlag::OGG).map_err(AudioError::Init)?;
+        let channels = sdl2::mixer::allocate_channels(config.channels);
+        let frequency = sdl2::mixer::query_spec()
+            .map(|(frequency, _, _)| frequency)
+            .unwrap_or(config.frequency);
+        info!(
+            "SDL2 Mixer Version {} with {channels} channels",
+            sdl2::mixer::get_linked_version()
+        );
+        Ok(Self {
+            frequency,
+            channels,
+            reserved: 0,
+            current_music: None,
+            music_hook: None,
+            _mixer: mixer,
+            _audio: audio,
+        })
+    }
+
+    /// Loads a WAV or OGG file completely into memory.
+    pub fn load_clip(&self, path: impl AsRef<Path>) -> Result<SoundClip, AudioError> {
+        let path = path.as_ref();
+        Chunk::from_file(path)
+            .map(|chunk| SoundClip(Rc::new(chunk)))
+            .map_err(|e| AudioError::Load(path.to_path_buf(), e))
+    }
+
+    /// Opens a music file (OGG, WAV, ...) for streaming.
+    pub fn load_music(&self, path: impl AsRef<Path>) -> Result<MusicTrack, AudioError> {
+        let path = path.as_ref();
+        Music::from_file(path)
+            .map(|music| MusicTrack(Rc::new(music)))
+            .map_err(|e| AudioError::Load(path.to_path_buf(), e))
+    }
+
+    /// Plays the clip on a free channel that is not reserved, fails if all of them are busy.
+    pub fn play(&mut self, clip: &SoundClip, options: PlayOptions) -> Result<Playback, AudioError> {
+        let channel = (self.reserved..self.channels)
+            .map(Channel)
+            .find(|channel| !channel.is_playing())
+            .ok_or(AudioError::NoFreeChannel)?;
+        Playback(channel).start(clip, options)
+    }
+
+    /// The sample rate of the opened audio device, which might differ from the requested
+    /// [`AudioConfig::frequency`].
+    #[inline]
+    pub fn frequency(&self) -> i32 {
+        self.frequency
+    }
+
+    /// The amount of channels sound effects are mixed on.
+    #[inline]
+    pub fn channels(&self) -> i32 {
+        self.channels
+    }
+
+    /// Changes the amount of channels, the clips of removed channels are stopped.
+    #[inline]
+    pub fn set_channels(&mut self, channels: i32) {
+        self.channels = sdl2::mixer::allocate_channels(channels);
+        self.reserved = self.reserved.min(self.channels);
+    }
+
+    /// Excludes the first `count` channels from [`AudioSystem::play`], so they can be used
+    /// exclusively through [`AudioSystem::channel`], e.g. for dialogue. Returns the amount of
+    /// channels that are actually reserved.
+    #[inline]
+    pub fn reserve_channels(&mut self, count: i32) -> i32 {
+        self.reserved = sdl2::mixer::reserve_channels(count.clamp(0, self.channels));
+        self.reserved
+    }
+
+    /// The amount of channels excluded from [`AudioSystem::play`].
+    #[inline]
+    pub fn reserved_channels(&self) -> i32 {
+        self.reserved
+    }
+
+    /// Plays the clip on the given (reserved) channel, replacing its current clip.
+    pub fn play_on(
+        &mut self,
+        channel: i32,
+        clip: &SoundClip,
+        options: PlayOptions,
+    ) -> Result<Playback, AudioError> {
+        self.channel(channel).start(clip, options)
+    }
+
+    #[inline]
+    pub fn channel(&self, channel: i32) -> Playback {
+        Playback(Channel(channel))
+    }
+
+    /// The amount of channels that are currently playing.
+    #[inline]
+    pub fn playing_channels(&self) -> i32 {
+        sdl2::mixer::get_playing_channels_number()
+    }
+
+    #[inline]
+    pub fn pause_all(&mut self) {
+        Channel::all().pause();
+        Music::pause();
+    }
+
+    #[inline]
+    pub fn resume_all(&mut self) {
+        Channel::all().resume();
+        Music::resume();
+    }
+
+    #[inline]
+    pub fn stop_all(&mut self) {
+        Channel::all().halt();
+        self.stop_music(Duration::ZERO);
+    }
+
+    /// Replaces the current music, which is looped endlessly if `looping` is set.
+    pub fn play_music(
+        &mut self,
+        track: &MusicTrack,
+        looping: bool,
+        fade_in: Duration,
+    ) -> Result<(), AudioError> {
+        let loops = if looping { -1 } else { 1 };
+        if self.music_hook.is_some() {
+            self.set_music_hook(None);
+        }
+        track
+            .0
+            .fade_in(loops, fade_in.as_millis() as i32)
+            .map_err(AudioError::Play)?;
+        self.current_music = Some(track.clone());
+        Ok(())
+    }
+
+    /// Replaces the music with the samples of the hook, or removes the hook. The hook is called
+    /// on the audio thread of the mixer with interleaved stereo samples at
+    /// [`AudioSystem::frequency`], for example by [`MusicOutput::attach`].
+    ///
+    /// [`MusicOutput::attach`]: crate::support::music::MusicOutput::attach
+    pub fn set_music_hook(&mut self, hook: Option<MusicHook>) {
+        unsafe extern "C" fn mix(data: *mut c_void, stream: *mut u8, len: c_int) {
+            // SAFETY: the pointer is the boxed hook of the AudioSystem, which is only replaced
+            //         or dropped after it was unhooked, and the mixer opened the device with
+            //         16 bit samples
+            let hook = &mut *(data as *mut MusicHook);
+            let samples = std::slice::from_raw_parts_mut(stream as *mut i16, len as usize / 2);
+            hook(samples);
+        }
+
+        if hook.is_some() {
+            self.stop_music(Duration::ZERO);
+            self.current_music = None;
+        }
+        // SAFETY: the mixer locks the audio device while the hook is replaced
+        unsafe { mixer::Mix_HookMusic(None, std::ptr::null_mut()) };
+        self.music_hook = hook.map(Box::new);
+        if let Some(hook) = &mut self.music_hook {
+            let data = hook.as_mut() as *mut MusicHook as *mut c_void;
+            // SAFETY: see above
+            unsafe { mixer::Mix_HookMusic(Some(mix), data) };
+        }
+    }
+
+    pub fn stop_music(&mut self, fade_out: Duration) {
+        if fade_out.is_zero() {
+            Music::halt();
+        } else if let Err(e) = Music::fade_out(fade_out.as_millis() as i32) {
+            warn!("Failed to fade out the music: {e}");
+            Music::halt();
+        }
+    }
+
+    #[inline]
+    pub fn pause_music(&mut self) {
+        Music::pause();
+    }
+
+    #[inline]
+    pub fn resume_music(&mut self) {
+        Music::resume();
+    }
+
+    #[inline]
+    pub fn is_music_playing(&self) -> bool {
+        Music::is_playing() && !Music::is_paused()
+    }
+
+    /// `0.0..=1.0`
+    #[inline]
+    pub fn set_music_volume(&mut self, volume: f32) {
+        Music::set_volume(to_mixer_volume(volume));
+    }
+
+    #[inline]
+    pub fn music_volume(&self) -> f32 {
+        Music::get_volume() as f32 / MAX_VOLUME as f32
+    }
+}
+
+impl Drop for AudioSystem {
+    fn drop(&mut self) {
+        Channel::all().halt();
+        Music::halt();
+        self.current_music = None;
+        self.set_music_hook(None);
+        sdl2::mixer::close_audio();
+    }
+}
+
+#[inline]
+fn to_mixer_volume(volume: f32) -> i32 {
+    (volume.clamp(0.0, 1.0) * MAX_VOLUME as f32).round() as i32
+}
+
+/// The gains of the left and right speaker, the centered position plays at full volume on both.
+#[inline]
+fn pan_gains(pan: f32) -> (u8, u8) {
+    let pan = pan.clamp(-1.0, 1.0);
+    let left = (1.0 - pan).min(1.0);
+    let right = (1.0 + pan).min(1.0);
+    ((left * 255.0).round() as u8, (right * 255.0).round() as u8)
+}
@@ -0,0 +1,40 @@
+//! The seam between the engine and the graphics API. The handles of recorded commands and of
+//! textures ([`CommandRecorder`], [`RecordedCommands`], [`TextureId`] and [`TextureView`]) are
+//! generic over the [`RenderBackend`], so the APIs that produce and take them, like the
+//! [`BufferedCanvasLayer`] and the [`FontRenderer`], do not depend on `vulkano`.
+//!
+//! [`Vulkan`] is the only backend for now and the default of the handles. Code that records its
+//! own commands with `vulkano`, like custom pipelines, converts from and into the handles with
+//! the functions of [`Vulkan`].
+//!
+//! [`CommandRecorder`]: crate::engine::system::commands::CommandRecorder
+//! [`RecordedCommands`]: crate::engine::system::commands::RecordedCommands
+//! [`TextureId`]: crate::engine::system::vulkan::textures::TextureId
+//! [`TextureView`]: crate::engine::system::vulkan::textures::TextureView
+//! [`BufferedCanvasLayer`]: crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer
+//! [`FontRenderer`]: crate::engine::system::ttf::FontRenderer
+
+pub use crate::engine::system::vulkan::backend::Vulkan;
+
+/// The types and operations of a graphics API behind the crate-owned handles.
+pub trait RenderBackend: Sized + 'static {
+    /// Records the commands of a [`CommandRecorder`]
+    ///
+    /// [`CommandRecorder`]: crate::engine::system::commands::CommandRecorder
+    type Recorder;
+    /// The commands of [`RecordedCommands`], ready to be submitted
+    ///
+    /// [`RecordedCommands`]: crate::engine::system::commands::RecordedCommands
+    type Commands: Clone + Send + Sync;
+    /// The image behind a [`TextureId`] and what binds it to the pipelines, released once the
+    /// last handle is dropped
+    ///
+    /// [`TextureId`]: crate::engine::system::vulkan::textures::TextureId
+    type Texture: Send + Sync;
+    type Error: std::error::Error;
+
+    fn finish(recorder: Self::Recorder) -> Result<Self::Commands, Self::Error>;
+
+    /// The size of the image of the texture in pixels.
+    fn texture_extent(texture: &Self::Texture) -> [u32; 2];
+}
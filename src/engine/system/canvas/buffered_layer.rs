@@ -1,19 +1,45 @@
+use crate::engine::system::arena::FrameArena;
+use crate::engine::system::commands::{CommandRecorder, RecordedCommands};
+use crate::engine::system::viewport::ViewportRect;
 use crate::engine::system::vulkan::lines::{Line, Vertex2d};
 use crate::engine::system::vulkan::pipelines::VulkanPipelines;
 use crate::engine::system::vulkan::system::RenderContext;
 use crate::engine::system::vulkan::textured::{Textured, TexturedPipeline, Vertex2dUv};
-use crate::engine::system::vulkan::textures::TextureId;
-use crate::engine::system::vulkan::triangles::Triangles;
+use crate::engine::system::vulkan::textures::{TextureId, TextureRegistry, TextureView};
+use crate::engine::system::vulkan::triangles::{self, Triangles};
+use crate::engine::system::vulkan::world2d::entities::{
+    EntityInstanceData, World2dEntitiesPipeline,
+};
+use crate::engine::system::vulkan::world2d::terrain::{
+    InstanceData, TerrainChunk, World2dTerrainPipeline,
+};
 use crate::engine::system::vulkan::DrawError;
-use crate::engine::types::world2d::{Dim, Pos};
+use crate::engine::types::world2d::{Dim, Pos, Rect};
+use cgmath::{InnerSpace, Matrix3, Rad, SquareMatrix, Transform};
+use std::f32::consts::TAU;
 use std::sync::Arc;
-use vulkano::command_buffer::{AutoCommandBufferBuilder, SecondaryAutoCommandBuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::pipeline::graphics::viewport::Scissor;
 
 type Uv<T> = Pos<T>;
 
 pub struct BufferedCanvasLayer {
     color: [f32; 4],
     sink: ActionSink,
+    pool: CanvasGeometryPool,
+    /// Primitives entirely outside of this area are dropped
+    culling: Option<Rect<f32>>,
+    culled: usize,
+    /// Textures registered as part of an atlas are drawn with the atlas instead
+    texture_registry: Option<Arc<TextureRegistry<TexturedPipeline>>>,
+    /// The segments of a full circle, derived from the radius if [`None`]
+    circle_segments: Option<u32>,
+    /// Applied to the positions of all primitives, [`None`] for the identity
+    transform: Option<Matrix3<f32>>,
+    transform_stack: Vec<Option<Matrix3<f32>>>,
+    /// The area in pixels of the viewport that primitives are clipped to
+    clip: Option<Rect<f32>>,
+    clip_stack: Vec<Option<Rect<f32>>>,
 }
 
 impl Default for BufferedCanvasLayer {
@@ -21,22 +47,209 @@ impl Default for BufferedCanvasLayer {
         Self {
             color: [1.0, 1.0, 1.0, 1.0],
             sink: ActionSink::Buffer(Vec::default()),
+            pool: CanvasGeometryPool::default(),
+            culling: None,
+            culled: 0,
+            texture_registry: None,
+            circle_segments: None,
+            transform: None,
+            transform_stack: Vec::new(),
+            clip: None,
+            clip_stack: Vec::new(),
         }
     }
 }
 
 impl BufferedCanvasLayer {
-    pub fn new(
-        builder: AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
-        pipelines: Arc<VulkanPipelines>,
-    ) -> Self {
+    /// Draws the primitives into the `recorder` while they are added instead of buffering them
+    /// until [`BufferedCanvasLayer::flush`].
+    pub fn new(recorder: CommandRecorder, pipelines: Arc<VulkanPipelines>) -> Self {
         Self {
             color: [1.0, 1.0, 1.0, 1.0],
+            texture_registry: Some(Arc::clone(pipelines.texture.texture_registry())),
             sink: ActionSink::Commands {
                 current: None,
-                builder,
+                recorder,
                 pipelines,
             },
+            pool: CanvasGeometryPool::default(),
+            culling: None,
+            culled: 0,
+            circle_segments: None,
+            transform: None,
+            transform_stack: Vec::new(),
+            clip: None,
+            clip_stack: Vec::new(),
+        }
+    }
+
+    /// Reuses the vectors of the layer of a previous frame, see
+    /// [`BufferedCanvasLayer::flush_recycling`].
+    #[inline]
+    pub fn with_pool(mut self, pool: CanvasGeometryPool) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Textures registered in the [`TextureRegistry`] are replaced by their atlas, so that
+    /// consecutive primitives with different textures of the same atlas are merged into a
+    /// single draw call. Layers created with [`BufferedCanvasLayer::new`] use the registry of
+    /// the [`TexturedPipeline`].
+    #[inline]
+    pub fn with_texture_registry(
+        mut self,
+        registry: Arc<TextureRegistry<TexturedPipeline>>,
+    ) -> Self {
+        self.texture_registry = Some(registry);
+        self
+    }
+
+    /// Drops primitives that are entirely outside of the visible area before their vertices are
+    /// buffered, which is useful if the layer is fed with world-space data of which only a small
+    /// part is on screen. The area is in the same coordinates as the primitives.
+    #[inline]
+    pub fn with_culling(mut self, visible: Rect<f32>) -> Self {
+        self.set_culling(Some(visible));
+        self
+    }
+
+    #[inline]
+    pub fn set_culling(&mut self, visible: Option<Rect<f32>>) {
+        self.culling = visible;
+    }
+
+    /// The amount of primitives dropped by the culling so far.
+    #[inline]
+    pub fn culled(&self) -> usize {
+        self.culled
+    }
+
+    /// The amount of segments circles, ellipses and arcs are tessellated into per full turn. By
+    /// default, the amount is derived from the radius so that the deviation from the exact
+    /// curve stays below a quarter of a unit, which is a quarter pixel in screen coordinates.
+    #[inline]
+    pub fn with_circle_segments(mut self, segments: u32) -> Self {
+        self.set_circle_segments(Some(segments));
+        self
+    }
+
+    #[inline]
+    pub fn set_circle_segments(&mut self, segments: Option<u32>) {
+        self.circle_segments = segments.map(|segments| segments.max(3));
+    }
+
+    /// Saves the current transform, to be restored by [`BufferedCanvasLayer::pop_transform`].
+    #[inline]
+    pub fn push_transform(&mut self) {
+        self.transform_stack.push(self.transform);
+    }
+
+    /// Restores the transform of the matching [`BufferedCanvasLayer::push_transform`], returns
+    /// `false` if there was none.
+    #[inline]
+    pub fn pop_transform(&mut self) -> bool {
+        match self.transform_stack.pop() {
+            Some(transform) => {
+                self.transform = transform;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the following primitives by the offset, in the coordinates of the current transform.
+    #[inline]
+    pub fn translate<D: Into<Dim<f32>>>(&mut self, offset: D) {
+        self.apply_transform(Matrix3::from_translation(offset.into()));
+    }
+
+    /// Rotates the following primitives around the origin of the current transform. The angle
+    /// is in radians and increases clockwise on screen, like the angles of
+    /// [`BufferedCanvasLayer::draw_arc`].
+    #[inline]
+    pub fn rotate(&mut self, angle: f32) {
+        self.apply_transform(Matrix3::from_angle_z(Rad(angle)));
+    }
+
+    /// Scales the following primitives relative to the origin of the current transform.
+    #[inline]
+    pub fn scale<D: Into<Dim<f32>>>(&mut self, factors: D) {
+        let factors = factors.into();
+        self.apply_transform(Matrix3::from_nonuniform_scale(factors.x, factors.y));
+    }
+
+    /// Resets the current transform to the identity, the saved transforms are kept.
+    #[inline]
+    pub fn reset_transform(&mut self) {
+        self.transform = None;
+    }
+
+    /// The transform that is applied to the positions of the following primitives. It is applied
+    /// while buffering, so the culling (see [`BufferedCanvasLayer::with_culling`]) is in the
+    /// transformed coordinates. The world2d terrain and entities are not transformed.
+    #[inline]
+    pub fn transform(&self) -> Matrix3<f32> {
+        self.transform.unwrap_or_else(Matrix3::identity)
+    }
+
+    #[inline]
+    fn apply_transform(&mut self, local: Matrix3<f32>) {
+        self.transform = Some(match self.transform {
+            Some(transform) => transform * local,
+            None => local,
+        });
+    }
+
+    #[inline]
+    fn transform_pos(&self, pos: Pos<f32>) -> Pos<f32> {
+        match &self.transform {
+            Some(transform) => transform.transform_point(pos),
+            None => pos,
+        }
+    }
+
+    /// Clips the following primitives to the rectangle, for example for scrollable panels or
+    /// minimaps, until the matching [`BufferedCanvasLayer::pop_clip_rect`]. Nested clip rects
+    /// are intersected with the current one. The rectangle is in pixels of the viewport the layer
+    /// is drawn into (like the primitives), confined to it and not affected by the transform (see
+    /// [`BufferedCanvasLayer::push_transform`]).
+    pub fn push_clip_rect(&mut self, rect: Rect<f32>) {
+        self.clip_stack.push(self.clip);
+        let clip = match &self.clip {
+            Some(current) => intersect(current, &rect),
+            None => rect,
+        };
+        self.set_clip(Some(clip));
+    }
+
+    /// Restores the clip rect of the matching [`BufferedCanvasLayer::push_clip_rect`], returns
+    /// `false` if there was none.
+    pub fn pop_clip_rect(&mut self) -> bool {
+        match self.clip_stack.pop() {
+            Some(clip) => {
+                self.set_clip(clip);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The current clip rect, [`None`] if the primitives are not clipped.
+    #[inline]
+    pub fn clip_rect(&self) -> Option<Rect<f32>> {
+        self.clip
+    }
+
+    fn set_clip(&mut self, clip: Option<Rect<f32>>) {
+        if self.clip == clip {
+            return;
+        }
+        self.clip = clip;
+        if let Some(Action::Clip(previous)) = self.sink.action_mut() {
+            // no primitives were drawn with the previous clip rect
+            *previous = clip;
+        } else {
+            self.push_action(Action::Clip(clip));
         }
     }
 
@@ -62,36 +275,208 @@ impl BufferedCanvasLayer {
         ])
     }
 
+    /// Consecutive rects of the same color are merged into a single draw call.
     pub fn fill_rect<P: Into<Pos<f32>>, D: Into<Dim<f32>>>(&mut self, pos: P, dim: D) {
         let pos = pos.into();
         let dim = dim.into();
-        self.sink.append(Triangles {
-            vertices: [
-                pos,
-                pos + Dim::new(dim.x, 0.0),
-                pos + dim,
-                pos + dim,
-                pos + Dim::new(0.0, dim.y),
-                pos,
-            ]
-            .into_iter()
-            .map(|pos| crate::engine::system::vulkan::triangles::Vertex2d { pos: pos.into() })
-            .collect::<Vec<_>>(),
-            color: self.color,
-        });
+        let vertices = [
+            pos,
+            pos + Dim::new(dim.x, 0.0),
+            pos + dim,
+            pos + dim,
+            pos + Dim::new(0.0, dim.y),
+            pos,
+        ]
+        .map(|pos| triangles::Vertex2d { pos: pos.into() });
+        self.fill_triangles(&vertices);
+    }
+
+    /// Fills the rectangle with corners rounded by the radius, which is limited to half the
+    /// shorter side.
+    pub fn fill_rounded_rect<P: Into<Pos<f32>>, D: Into<Dim<f32>>>(
+        &mut self,
+        pos: P,
+        dim: D,
+        corner_radius: f32,
+    ) {
+        let pos = pos.into();
+        let dim = dim.into();
+        let radius = corner_radius.min(dim.x.abs().min(dim.y.abs()) * 0.5);
+        if radius <= 0.0 {
+            self.fill_rect(pos, dim);
+            return;
+        }
+
+        let radii = Dim::new(radius, radius);
+        let (left, right) = (pos.x + radius, pos.x + dim.x - radius);
+        let (top, bottom) = (pos.y + radius, pos.y + dim.y - radius);
+        let quarter = TAU / 4.0;
+        // clockwise on screen, starting at the left end of the top-left corner
+        let outline = [
+            (Pos::new(left, top), 2.0 * quarter),
+            (Pos::new(right, top), 3.0 * quarter),
+            (Pos::new(right, bottom), 0.0),
+            (Pos::new(left, bottom), quarter),
+        ]
+        .into_iter()
+        .flat_map(|(center, start)| self.arc_positions(center, radii, start, quarter))
+        .collect::<Vec<_>>();
+
+        let center = pos + dim * 0.5;
+        let vertices = outline
+            .iter()
+            .zip(outline.iter().cycle().skip(1))
+            .flat_map(|(a, b)| [center, *a, *b])
+            .map(|pos| triangles::Vertex2d { pos: pos.into() })
+            .collect::<Vec<_>>();
+        self.fill_triangles(&vertices);
+    }
+
+    /// Appends the triangle list to the current triangles action, merging it into the last
+    /// triangles of the same color.
+    fn fill_triangles(&mut self, vertices: &[triangles::Vertex2d]) {
+        let transformed;
+        let vertices = match self.transform {
+            Some(_) => {
+                transformed = vertices
+                    .iter()
+                    .map(|v| triangles::Vertex2d {
+                        pos: self.transform_pos(Pos::from(v.pos)).into(),
+                    })
+                    .collect::<Vec<_>>();
+                &transformed[..]
+            }
+            None => vertices,
+        };
+
+        if !self.is_visible(vertices.iter().map(|v| v.pos)) {
+            return;
+        }
+
+        let color = self.color;
+        if let Some(Action::Triangles(list)) = self.sink.action_mut() {
+            match list.last_mut() {
+                Some(last) if last.color == color => last.vertices.extend_from_slice(vertices),
+                _ => list.push(Triangles {
+                    vertices: self.pool.triangle_vertices(vertices),
+                    color,
+                }),
+            }
+        } else {
+            let mut list = CanvasGeometryPool::take(&mut self.pool.triangles);
+            list.push(Triangles {
+                vertices: self.pool.triangle_vertices(vertices),
+                color,
+            });
+            self.push_action(Action::Triangles(list));
+        }
     }
 
     pub fn draw_path<P: Into<Pos<f32>> + Copy>(&mut self, positions: &[P]) {
-        self.sink.append(Line {
-            vertices: positions
-                .iter()
-                .copied()
-                .map(|pos| Vertex2d {
-                    pos: pos.into().into(),
-                })
-                .collect(),
+        let mut vertices = CanvasGeometryPool::take(&mut self.pool.line_vertices);
+        vertices.extend(positions.iter().map(|pos| Vertex2d {
+            pos: self.transform_pos((*pos).into()).into(),
+        }));
+
+        if !self.is_visible(vertices.iter().map(|v| v.pos)) {
+            CanvasGeometryPool::give(&mut self.pool.line_vertices, vertices);
+            return;
+        }
+
+        let line = Line {
+            vertices,
             color: self.color,
+        };
+
+        if let Some(Action::Lines(list)) = self.sink.action_mut() {
+            // line strips cannot be merged
+            list.push(line);
+        } else {
+            let mut list = CanvasGeometryPool::take(&mut self.pool.lines);
+            list.push(line);
+            self.push_action(Action::Lines(list));
+        }
+    }
+
+    #[inline]
+    pub fn draw_circle<P: Into<Pos<f32>>>(&mut self, center: P, radius: f32) {
+        self.draw_ellipse(center, Dim::new(radius, radius));
+    }
+
+    /// Consecutive filled shapes of the same color are merged into a single draw call.
+    #[inline]
+    pub fn fill_circle<P: Into<Pos<f32>>>(&mut self, center: P, radius: f32) {
+        self.fill_ellipse(center, Dim::new(radius, radius));
+    }
+
+    #[inline]
+    pub fn draw_ellipse<P: Into<Pos<f32>>, D: Into<Dim<f32>>>(&mut self, center: P, radii: D) {
+        let positions = self.arc_positions(center.into(), radii.into(), 0.0, TAU);
+        self.draw_path(&positions);
+    }
+
+    #[inline]
+    pub fn fill_ellipse<P: Into<Pos<f32>>, D: Into<Dim<f32>>>(&mut self, center: P, radii: D) {
+        self.fill_pie_segment(center.into(), radii.into(), 0.0, TAU);
+    }
+
+    /// Draws the outline of the circle from the `start` angle for the `sweep` angle, both in
+    /// radians. Angles increase clockwise on screen, because the y axis points down, and `0.0`
+    /// points to the right.
+    #[inline]
+    pub fn draw_arc<P: Into<Pos<f32>>>(&mut self, center: P, radius: f32, start: f32, sweep: f32) {
+        let radii = Dim::new(radius, radius);
+        let positions = self.arc_positions(center.into(), radii, start, sweep);
+        self.draw_path(&positions);
+    }
+
+    /// Fills the slice of the circle that is enclosed by the arc (see
+    /// [`BufferedCanvasLayer::draw_arc`]) and the center.
+    #[inline]
+    pub fn fill_pie<P: Into<Pos<f32>>>(&mut self, center: P, radius: f32, start: f32, sweep: f32) {
+        self.fill_pie_segment(center.into(), Dim::new(radius, radius), start, sweep);
+    }
+
+    fn fill_pie_segment(&mut self, center: Pos<f32>, radii: Dim<f32>, start: f32, sweep: f32) {
+        let positions = self.arc_positions(center, radii, start, sweep);
+        let vertices = positions
+            .windows(2)
+            .flat_map(|edge| [center, edge[0], edge[1]])
+            .map(|pos| triangles::Vertex2d { pos: pos.into() })
+            .collect::<Vec<_>>();
+        self.fill_triangles(&vertices);
+    }
+
+    /// The positions along the arc, including both ends.
+    fn arc_positions(
+        &self,
+        center: Pos<f32>,
+        radii: Dim<f32>,
+        start: f32,
+        sweep: f32,
+    ) -> Vec<Pos<f32>> {
+        let sweep = sweep.clamp(-TAU, TAU);
+        let full_turn_segments = self.circle_segments.unwrap_or_else(|| {
+            // the arc is scaled by the transform after the tessellation
+            let scale = self.transform.map_or(1.0, |transform| {
+                transform
+                    .x
+                    .truncate()
+                    .magnitude()
+                    .max(transform.y.truncate().magnitude())
+            });
+            circle_segments_for_radius(radii.x.abs().max(radii.y.abs()) * scale)
         });
+        let segments = ((full_turn_segments as f32 * sweep.abs() / TAU).ceil() as usize).max(1);
+        (0..=segments)
+            .map(|i| {
+                let angle = start + sweep * (i as f32 / segments as f32);
+                Pos::new(
+                    center.x + radii.x * angle.cos(),
+                    center.y + radii.y * angle.sin(),
+                )
+            })
+            .collect()
     }
 
     #[inline]
@@ -117,57 +502,446 @@ impl BufferedCanvasLayer {
         );
     }
 
+    /// Draws the region of the texture stretched to the rectangle, but with the corners at their
+    /// original size and the edges only stretched along their side, so panels and buttons of any
+    /// size can share one image. The `margins` are the sizes of the corners in pixels of the
+    /// texture. If the rectangle is smaller than the opposing margins, they shrink proportionally.
+    pub fn draw_nine_slice<P: Into<Pos<f32>>, D: Into<Dim<f32>>>(
+        &mut self,
+        texture_view: &TextureView<TexturedPipeline>,
+        pos: P,
+        dim: D,
+        margins: NineSliceMargins,
+    ) {
+        let pos = pos.into();
+        let dim = dim.into();
+        let [width, height] = texture_view.extent();
+
+        let slices = |pos: f32, dim: f32, size: f32, start: f32, end: f32| {
+            let shrink = if start + end > dim.abs() {
+                dim.abs() / (start + end)
+            } else {
+                1.0
+            };
+            let size = size.max(f32::EPSILON);
+            (
+                [
+                    pos,
+                    pos + start * shrink * dim.signum(),
+                    pos + dim - end * shrink * dim.signum(),
+                    pos + dim,
+                ],
+                [0.0, start / size, 1.0 - end / size, 1.0],
+            )
+        };
+        let (xs, us) = slices(pos.x, dim.x, width, margins.left, margins.right);
+        let (ys, vs) = slices(pos.y, dim.y, height, margins.top, margins.bottom);
+
+        let vertices = (0..3)
+            .flat_map(|row| (0..3).map(move |column| (row, column)))
+            .filter(|&(row, column)| xs[column] != xs[column + 1] && ys[row] != ys[row + 1])
+            .flat_map(|(row, column)| {
+                let corner = |dx: usize, dy: usize| {
+                    (
+                        Pos::new(xs[column + dx], ys[row + dy]),
+                        Uv::from(texture_view.map_uv([us[column + dx], vs[row + dy]])),
+                    )
+                };
+                [
+                    corner(0, 0),
+                    corner(1, 0),
+                    corner(1, 1),
+                    corner(1, 1),
+                    corner(0, 1),
+                    corner(0, 0),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        self.draw_textured_triangles(vertices.into_iter(), texture_view.texture.clone());
+    }
+
+    /// Consecutive triangles with the same texture, or with textures of the same atlas (see
+    /// [`BufferedCanvasLayer::with_texture_registry`]), are merged into a single draw call.
     pub fn draw_textured_triangles<P: Into<Pos<f32>>, U: Into<Uv<f32>>>(
         &mut self,
         pos_uv: impl Iterator<Item = (P, U)>,
         texture: TextureId<TexturedPipeline>,
     ) {
-        self.sink.append(Textured {
-            vertices: pos_uv
-                .map(|(pos, uv)| {
-                    let pos = pos.into();
-                    let uv = uv.into();
-                    Vertex2dUv {
-                        pos: pos.into(),
-                        uv: uv.into(),
-                    }
-                })
-                .collect(),
-            texture,
+        let region = self
+            .texture_registry
+            .as_ref()
+            .and_then(|registry| registry.lookup(&texture));
+        let transform = self.transform;
+        let vertices = pos_uv.map(|(pos, uv)| {
+            let pos = match &transform {
+                Some(transform) => transform.transform_point(pos.into()),
+                None => pos.into(),
+            };
+            let uv = uv.into();
+            Vertex2dUv {
+                pos: pos.into(),
+                uv: match &region {
+                    Some(region) => region.map_uv(uv.into()),
+                    None => uv.into(),
+                },
+            }
+        });
+
+        let mut pooled = CanvasGeometryPool::take(&mut self.pool.textured_vertices);
+        pooled.extend(vertices);
+        let texture = match region {
+            Some(region) => region.atlas,
+            None => texture,
+        };
+
+        if !self.is_visible(pooled.iter().map(|v| v.pos)) {
+            CanvasGeometryPool::give(&mut self.pool.textured_vertices, pooled);
+            return;
+        }
+
+        if let Some(Action::TexturedTriangle(list)) = self.sink.action_mut() {
+            match list.last_mut() {
+                Some(last) if last.texture.ptr_eq(&texture) => {
+                    last.vertices.extend_from_slice(&pooled);
+                    CanvasGeometryPool::give(&mut self.pool.textured_vertices, pooled);
+                }
+                _ => list.push(Textured {
+                    vertices: pooled,
+                    texture,
+                }),
+            }
+        } else {
+            let mut list = CanvasGeometryPool::take(&mut self.pool.textured);
+            list.push(Textured {
+                vertices: pooled,
+                texture,
+            });
+            self.push_action(Action::TexturedTriangle(list));
+        }
+    }
+
+    /// Draws the tiles with the [`World2dTerrainPipeline`], in world coordinates. Consecutive
+    /// chunks with the same texture are merged into a single draw call. The culling (see
+    /// [`BufferedCanvasLayer::with_culling`]) drops the chunk as a whole.
+    pub fn draw_terrain(&mut self, chunk: &TerrainChunk) {
+        if chunk.tiles.is_empty() {
+            return;
+        }
+
+        let bounds = chunk.tiles.iter().flat_map(|tile| {
+            let [x, y] = tile.tile_pos;
+            [[x - 0.5, y - 0.5], [x + 0.5, y + 0.5]]
         });
+        if !self.is_visible(bounds) {
+            return;
+        }
+
+        if let Some(Action::Terrain(list)) = self.sink.action_mut() {
+            Instances::merge(
+                list,
+                &chunk.texture,
+                &chunk.tiles,
+                &mut self.pool.terrain_tiles,
+            );
+        } else {
+            let mut list = CanvasGeometryPool::take(&mut self.pool.terrain);
+            Instances::merge(
+                &mut list,
+                &chunk.texture,
+                &chunk.tiles,
+                &mut self.pool.terrain_tiles,
+            );
+            self.push_action(Action::Terrain(list));
+        }
+    }
+
+    /// Draws the entities with the [`World2dEntitiesPipeline`], in world coordinates.
+    /// Consecutive entities with the same texture are merged into a single draw call. The culling
+    /// (see [`BufferedCanvasLayer::with_culling`]) drops the slice as a whole.
+    pub fn draw_entities(
+        &mut self,
+        texture: &TextureId<World2dEntitiesPipeline>,
+        entities: &[EntityInstanceData],
+    ) {
+        if entities.is_empty() {
+            return;
+        }
+
+        let bounds = entities.iter().flat_map(|entity| {
+            let [x, y] = entity.entity_pos;
+            let half = entity.size * 0.5;
+            [[x - half, y - half], [x + half, y + half]]
+        });
+        if !self.is_visible(bounds) {
+            return;
+        }
+
+        if let Some(Action::Entities(list)) = self.sink.action_mut() {
+            Instances::merge(list, texture, entities, &mut self.pool.entity_instances);
+        } else {
+            let mut list = CanvasGeometryPool::take(&mut self.pool.entities);
+            Instances::merge(
+                &mut list,
+                texture,
+                entities,
+                &mut self.pool.entity_instances,
+            );
+            self.push_action(Action::Entities(list));
+        }
+    }
+
+    /// Whether the bounds of the positions overlap the culling area, counts the primitive as
+    /// culled otherwise.
+    fn is_visible(&mut self, positions: impl IntoIterator<Item = [f32; 2]>) -> bool {
+        let Some(visible) = &self.culling else {
+            return true;
+        };
+
+        let mut min = [f32::INFINITY; 2];
+        let mut max = [f32::NEG_INFINITY; 2];
+        for [x, y] in positions {
+            min = [min[0].min(x), min[1].min(y)];
+            max = [max[0].max(x), max[1].max(y)];
+        }
+
+        let outside = max[0] < visible.pos.x
+            || max[1] < visible.pos.y
+            || min[0] > visible.pos.x + visible.dim.x
+            || min[1] > visible.pos.y + visible.dim.y;
+
+        if outside {
+            self.culled += 1;
+        }
+        !outside
+    }
+
+    fn push_action(&mut self, action: Action) {
+        if let Some(flushed) = self.sink.push_action(action) {
+            self.pool.recycle(flushed);
+        }
     }
 
     #[must_use]
-    pub fn flush(
+    pub fn flush(self, ctx: &RenderContext, pipelines: &VulkanPipelines) -> RecordedCommands {
+        self.flush_recycling(ctx, pipelines).0
+    }
+
+    /// Like [`BufferedCanvasLayer::flush`], but also returns the vectors of the flushed geometry,
+    /// so that the layer of the next frame can reuse them with [`BufferedCanvasLayer::with_pool`].
+    #[must_use]
+    pub fn flush_recycling(
         self,
         ctx: &RenderContext,
         pipelines: &VulkanPipelines,
-    ) -> Arc<SecondaryAutoCommandBuffer> {
-        self.sink.flush(ctx, pipelines)
+    ) -> (RecordedCommands, CanvasGeometryPool) {
+        let Self { sink, mut pool, .. } = self;
+        let commands = sink.flush(ctx, pipelines, &mut pool);
+        (commands, pool)
     }
 }
 
+/// The vectors of flushed geometry, which are cleared and reused for the next primitives instead
+/// of allocating new ones.
+#[derive(Default)]
+pub struct CanvasGeometryPool {
+    line_vertices: Vec<Vec<Vertex2d>>,
+    triangle_vertices: Vec<Vec<triangles::Vertex2d>>,
+    textured_vertices: Vec<Vec<Vertex2dUv>>,
+    lines: Vec<Vec<Line>>,
+    triangles: Vec<Vec<Triangles>>,
+    textured: Vec<Vec<Textured>>,
+    terrain_tiles: Vec<Vec<InstanceData>>,
+    entity_instances: Vec<Vec<EntityInstanceData>>,
+    terrain: Vec<Vec<Instances<World2dTerrainPipeline, InstanceData>>>,
+    entities: Vec<Vec<Instances<World2dEntitiesPipeline, EntityInstanceData>>>,
+}
+
+impl CanvasGeometryPool {
+    /// Further vectors are dropped instead of being kept for later
+    const MAX_POOLED: usize = 1024;
+
+    #[inline]
+    fn take<T>(pool: &mut Vec<Vec<T>>) -> Vec<T> {
+        pool.pop().unwrap_or_default()
+    }
+
+    #[inline]
+    fn give<T>(pool: &mut Vec<Vec<T>>, mut vec: Vec<T>) {
+        if pool.len() < Self::MAX_POOLED && vec.capacity() > 0 {
+            vec.clear();
+            pool.push(vec);
+        }
+    }
+
+    #[inline]
+    fn triangle_vertices(&mut self, vertices: &[triangles::Vertex2d]) -> Vec<triangles::Vertex2d> {
+        let mut pooled = Self::take(&mut self.triangle_vertices);
+        pooled.extend_from_slice(vertices);
+        pooled
+    }
+
+    fn recycle(&mut self, action: Action) {
+        match action {
+            Action::Lines(mut lines) => {
+                for line in lines.drain(..) {
+                    Self::give(&mut self.line_vertices, line.vertices);
+                }
+                Self::give(&mut self.lines, lines);
+            }
+            Action::Triangles(mut triangles) => {
+                for triangles in triangles.drain(..) {
+                    Self::give(&mut self.triangle_vertices, triangles.vertices);
+                }
+                Self::give(&mut self.triangles, triangles);
+            }
+            Action::TexturedTriangle(mut textured) => {
+                for textured in textured.drain(..) {
+                    Self::give(&mut self.textured_vertices, textured.vertices);
+                }
+                Self::give(&mut self.textured, textured);
+            }
+            Action::Clip(_) => {}
+            Action::Terrain(mut terrain) => {
+                for chunk in terrain.drain(..) {
+                    Self::give(&mut self.terrain_tiles, chunk.instances);
+                }
+                Self::give(&mut self.terrain, terrain);
+            }
+            Action::Entities(mut entities) => {
+                for batch in entities.drain(..) {
+                    Self::give(&mut self.entity_instances, batch.instances);
+                }
+                Self::give(&mut self.entities, entities);
+            }
+        }
+    }
+}
+
+/// Instances of a world2d pipeline that share a texture.
+struct Instances<P, I> {
+    texture: TextureId<P>,
+    instances: Vec<I>,
+}
+
+impl<P, I: Copy> Instances<P, I> {
+    /// Appends the instances to the last entry of the list if it has the same texture.
+    fn merge(
+        list: &mut Vec<Self>,
+        texture: &TextureId<P>,
+        instances: &[I],
+        pool: &mut Vec<Vec<I>>,
+    ) {
+        match list.last_mut() {
+            Some(last) if last.texture.ptr_eq(texture) => {
+                last.instances.extend_from_slice(instances);
+            }
+            _ => {
+                let mut pooled = CanvasGeometryPool::take(pool);
+                pooled.extend_from_slice(instances);
+                list.push(Self {
+                    texture: texture.clone(),
+                    instances: pooled,
+                });
+            }
+        }
+    }
+}
+
+/// The sizes of the borders of a [`BufferedCanvasLayer::draw_nine_slice`] in pixels of the
+/// texture.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct NineSliceMargins {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl NineSliceMargins {
+    #[inline]
+    pub const fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    #[inline]
+    pub const fn uniform(margin: f32) -> Self {
+        Self::new(margin, margin, margin, margin)
+    }
+}
+
+/// The overlap of both rectangles, empty if they do not overlap.
+fn intersect(a: &Rect<f32>, b: &Rect<f32>) -> Rect<f32> {
+    let min = Pos::new(a.pos.x.max(b.pos.x), a.pos.y.max(b.pos.y));
+    let max = Pos::new(
+        (a.pos.x + a.dim.x).min(b.pos.x + b.dim.x).max(min.x),
+        (a.pos.y + a.dim.y).min(b.pos.y + b.dim.y).max(min.y),
+    );
+    Rect::new(min, max - min)
+}
+
+/// The scissor of the clip rect, which is relative to the viewport like the primitives. Without
+/// a clip rect, this is the scissor of the whole viewport. Without a viewport, the clip rect is
+/// relative to the render target and [`None`] is not clipped.
+fn clip_scissor(clip: Option<&Rect<f32>>, viewport: Option<ViewportRect>) -> Scissor {
+    let bounds = viewport.map(|viewport| {
+        Rect::new(
+            Pos::new(viewport.x as f32, viewport.y as f32),
+            Dim::new(viewport.width as f32, viewport.height as f32),
+        )
+    });
+    let clip = match (clip, &bounds) {
+        (Some(clip), Some(bounds)) => intersect(
+            bounds,
+            &Rect::new(clip.pos + Dim::new(bounds.pos.x, bounds.pos.y), clip.dim),
+        ),
+        (Some(clip), None) => *clip,
+        (None, _) => {
+            return viewport
+                .as_ref()
+                .map_or_else(Scissor::default, ViewportRect::scissor)
+        }
+    };
+    let min = [clip.pos.x.max(0.0), clip.pos.y.max(0.0)];
+    let max = [
+        (clip.pos.x + clip.dim.x).max(min[0]),
+        (clip.pos.y + clip.dim.y).max(min[1]),
+    ];
+    Scissor {
+        offset: [min[0].floor() as u32, min[1].floor() as u32],
+        extent: [
+            (max[0].ceil() - min[0].floor()) as u32,
+            (max[1].ceil() - min[1].floor()) as u32,
+        ],
+    }
+}
+
+/// The segments of a full circle so that the chords deviate at most a quarter of a unit from the
+/// circle with the given radius.
+fn circle_segments_for_radius(radius: f32) -> u32 {
+    const MAX_DEVIATION: f32 = 0.25;
+    if radius <= MAX_DEVIATION {
+        return 8;
+    }
+    let max_angle = 2.0 * (1.0 - MAX_DEVIATION / radius).acos();
+    ((TAU / max_angle).ceil() as u32).clamp(8, 512)
+}
+
 enum ActionSink {
     Buffer(Vec<Action>),
     Commands {
         current: Option<Action>,
-        builder: AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
+        recorder: CommandRecorder,
         pipelines: Arc<VulkanPipelines>,
     },
 }
 
 impl ActionSink {
-    pub fn append(&mut self, action: impl Into<Action>) {
-        let action = action.into();
-        if let Some(current) = self.action_mut() {
-            if let Some(action) = current.try_push(action) {
-                self.push_action(action);
-            }
-        } else {
-            self.push_action(action);
-        }
-    }
-
     pub fn action_mut(&mut self) -> Option<&mut Action> {
         match self {
             ActionSink::Buffer(buffer) => buffer.last_mut(),
@@ -175,19 +949,24 @@ impl ActionSink {
         }
     }
 
-    pub fn push_action(&mut self, action: Action) {
+    /// Returns the previous action if it was flushed into the command buffer.
+    pub fn push_action(&mut self, action: Action) -> Option<Action> {
         match self {
-            ActionSink::Buffer(buffer) => buffer.push(action),
+            ActionSink::Buffer(buffer) => {
+                buffer.push(action);
+                None
+            }
             ActionSink::Commands {
                 current,
-                builder,
+                recorder,
                 pipelines,
             } => {
-                if let Some(prev) = current.replace(action) {
-                    if let Err(e) = prev.flush(builder, pipelines) {
-                        error!("{e:?}");
-                    }
+                let prev = current.replace(action)?;
+                let viewport = recorder.viewport();
+                if let Err(e) = prev.flush(recorder.recorder_mut(), pipelines, None, viewport) {
+                    error!("{e:?}");
                 }
+                Some(prev)
             }
         }
     }
@@ -196,28 +975,38 @@ impl ActionSink {
         self,
         ctx: &RenderContext,
         pipelines: &VulkanPipelines,
-    ) -> Arc<SecondaryAutoCommandBuffer> {
+        pool: &mut CanvasGeometryPool,
+    ) -> RecordedCommands {
+        let arena = Some(ctx.frame_arena());
         match self {
             ActionSink::Buffer(buffer) => {
-                let mut builder = ctx.create_render_buffer_builder().unwrap();
+                let mut recorder = CommandRecorder::new(ctx).unwrap();
+                let viewport = recorder.viewport();
                 for action in buffer {
-                    if let Err(e) = action.flush(&mut builder, pipelines) {
+                    if let Err(e) =
+                        action.flush(recorder.recorder_mut(), pipelines, arena, viewport)
+                    {
                         error!("{e:?}");
                     }
+                    pool.recycle(action);
                 }
-                builder.build().unwrap()
+                recorder.finish().unwrap()
             }
             ActionSink::Commands {
                 current,
-                mut builder,
+                mut recorder,
                 pipelines,
             } => {
                 if let Some(action) = current {
-                    if let Err(e) = action.flush(&mut builder, &pipelines) {
+                    let viewport = recorder.viewport();
+                    if let Err(e) =
+                        action.flush(recorder.recorder_mut(), &pipelines, arena, viewport)
+                    {
                         error!("{e:?}");
                     }
+                    pool.recycle(action);
                 }
-                builder.build().unwrap()
+                recorder.finish().unwrap()
             }
         }
     }
@@ -227,54 +1016,53 @@ enum Action {
     Lines(Vec<Line>),
     Triangles(Vec<Triangles>),
     TexturedTriangle(Vec<Textured>),
+    Terrain(Vec<Instances<World2dTerrainPipeline, InstanceData>>),
+    Entities(Vec<Instances<World2dEntitiesPipeline, EntityInstanceData>>),
+    /// Sets the dynamic scissor of all following draws
+    Clip(Option<Rect<f32>>),
 }
 
 impl Action {
-    pub fn try_push(&mut self, other: Action) -> Option<Action> {
-        macro_rules! try_push {
-            ($($ty:ident, )+) => {
-                match (self, other) {
-                    $(
-                        (Action::$ty(dst), Action::$ty(src)) => {
-                            dst.extend(src.into_iter());
-                            None
-                        }
-                        (Action::$ty(_), other) => Some(other),
-                    )+
-                }
-            }
-        }
-
-        try_push!(Lines, Triangles, TexturedTriangle,)
-    }
-
+    /// Collects the vertices in the [`FrameArena`] if one is available, actions flushed while
+    /// drawing into the command buffer of a [`BufferedCanvasLayer::new`] have none. Clip rects
+    /// are confined to the `viewport`, if it is known.
     pub fn flush<L>(
-        self,
+        &self,
         builder: &mut AutoCommandBufferBuilder<L>,
         pipelines: &VulkanPipelines,
+        arena: Option<&FrameArena>,
+        viewport: Option<ViewportRect>,
     ) -> Result<(), DrawError> {
-        match self {
-            Action::Lines(lines) => pipelines.line.draw(builder, &lines),
-            Action::Triangles(triangles) => pipelines.triangles.draw(builder, &triangles),
-            Action::TexturedTriangle(textured) => pipelines.texture.draw(builder, &textured),
+        match (self, arena) {
+            (Action::Lines(lines), Some(arena)) => pipelines.line.draw_in(builder, arena, lines),
+            (Action::Lines(lines), None) => pipelines.line.draw(builder, lines),
+            (Action::Triangles(triangles), Some(arena)) => {
+                pipelines.triangles.draw_in(builder, arena, triangles)
+            }
+            (Action::Triangles(triangles), None) => pipelines.triangles.draw(builder, triangles),
+            (Action::TexturedTriangle(textured), Some(arena)) => {
+                pipelines.texture.draw_in(builder, arena, textured)
+            }
+            (Action::TexturedTriangle(textured), None) => pipelines.texture.draw(builder, textured),
+            (Action::Terrain(terrain), _) => terrain.iter().try_for_each(|chunk| {
+                pipelines.world2d_terrain.draw(
+                    builder,
+                    &chunk.texture,
+                    chunk.instances.iter().copied(),
+                )
+            }),
+            (Action::Entities(entities), _) => entities.iter().try_for_each(|batch| {
+                pipelines.world2d_entities.draw(
+                    builder,
+                    &batch.texture,
+                    batch.instances.iter().copied(),
+                )
+            }),
+            (Action::Clip(clip), _) => {
+                let scissor = clip_scissor(clip.as_ref(), viewport);
+                builder.set_scissor(0, [scissor].into_iter().collect())?;
+                Ok(())
+            }
         }
     }
 }
-
-impl From<Line> for Action {
-    fn from(value: Line) -> Self {
-        Action::Lines(vec![value])
-    }
-}
-
-impl From<Triangles> for Action {
-    fn from(value: Triangles) -> Self {
-        Action::Triangles(vec![value])
-    }
-}
-
-impl From<Textured> for Action {
-    fn from(value: Textured) -> Self {
-        Action::TexturedTriangle(vec![value])
-    }
-}
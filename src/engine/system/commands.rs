@@ -0,0 +1,74 @@
+//! Handles for recorded draw commands, so that the public APIs that produce them (like the
+//! [`BufferedCanvasLayer`]) do not depend on the types of the graphics backend, see the
+//! [`backend`](crate::engine::system::backend) module.
+//!
+//! [`BufferedCanvasLayer`]: crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer
+
+use crate::engine::system::backend::{RenderBackend, Vulkan};
+use crate::engine::system::viewport::ViewportRect;
+
+/// Records commands that are executed within the main render pass.
+pub struct CommandRecorder<B: RenderBackend = Vulkan> {
+    recorder: B::Recorder,
+    /// The viewport the commands render into, clip rects are confined to it
+    viewport: Option<ViewportRect>,
+}
+
+impl<B: RenderBackend> CommandRecorder<B> {
+    #[inline]
+    pub(crate) fn from_parts(recorder: B::Recorder, viewport: Option<ViewportRect>) -> Self {
+        Self { recorder, viewport }
+    }
+
+    #[inline]
+    pub(crate) fn recorder_mut(&mut self) -> &mut B::Recorder {
+        &mut self.recorder
+    }
+
+    /// The viewport the commands are recorded for, like the one of
+    /// [`RenderContext::create_viewport_buffer_builder`]. Without it, clip rects are relative to
+    /// the whole render target.
+    ///
+    /// [`RenderContext::create_viewport_buffer_builder`]: crate::engine::system::vulkan::system::RenderContext::create_viewport_buffer_builder
+    #[inline]
+    pub fn with_viewport(mut self, viewport: ViewportRect) -> Self {
+        self.viewport = Some(viewport);
+        self
+    }
+
+    #[inline]
+    pub fn viewport(&self) -> Option<ViewportRect> {
+        self.viewport
+    }
+
+    #[inline]
+    pub fn finish(self) -> Result<RecordedCommands<B>, B::Error> {
+        B::finish(self.recorder).map(RecordedCommands::from_inner)
+    }
+}
+
+/// Commands recorded by a [`CommandRecorder`], ready to be submitted.
+pub struct RecordedCommands<B: RenderBackend = Vulkan> {
+    commands: B::Commands,
+}
+
+impl<B: RenderBackend> Clone for RecordedCommands<B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            commands: self.commands.clone(),
+        }
+    }
+}
+
+impl<B: RenderBackend> RecordedCommands<B> {
+    #[inline]
+    pub(crate) fn from_inner(commands: B::Commands) -> Self {
+        Self { commands }
+    }
+
+    #[inline]
+    pub(crate) fn into_inner(self) -> B::Commands {
+        self.commands
+    }
+}
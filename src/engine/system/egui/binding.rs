@@ -11,6 +11,14 @@ use std::time::UNIX_EPOCH;
 
 pub(crate) struct Sdl2EguiMapping {
     input: RawInput,
+    /// To convert the pixels of sdl2 to points of egui
+    pixels_per_point: f32,
+    /// The pixels per window coordinate of sdl2 events, see [`SdlParts::drawable_scale`]
+    ///
+    /// [`SdlParts::drawable_scale`]: crate::engine::parts::sdl::SdlParts::drawable_scale
+    window_scale: f32,
+    /// Whether an IME composition is in progress, see [`Event::TextEditing`]
+    composing: bool,
 }
 
 impl Default for Sdl2EguiMapping {
@@ -24,6 +32,9 @@ impl Default for Sdl2EguiMapping {
                 focused: true,
                 ..Default::default()
             },
+            pixels_per_point: 1.0,
+            window_scale: 1.0,
+            composing: false,
         }
     }
 }
@@ -92,10 +103,10 @@ impl Sdl2EguiMapping {
 
     pub fn set_sdl2_view_area<I: Into<sdl2::rect::Rect>>(&mut self, area: I) {
         let area = area.into();
-        let x = area.x() as f32;
-        let y = area.y() as f32;
-        let w = area.width() as f32;
-        let h = area.height() as f32;
+        let x = area.x() as f32 / self.pixels_per_point;
+        let y = area.y() as f32 / self.pixels_per_point;
+        let w = area.width() as f32 / self.pixels_per_point;
+        let h = area.height() as f32 / self.pixels_per_point;
         self.input.screen_rect = Some(Rect {
             min: Pos2::new(x, y),
             max: Pos2::new(x + w, y + h),
@@ -110,6 +121,38 @@ impl Sdl2EguiMapping {
         self.on_current_viewport_mut(|viewport| viewport.fullscreen = Some(fullscreen));
     }
 
+    /// The scale factor of the display, which egui multiplies with its zoom factor.
+    pub fn set_native_pixels_per_point(&mut self, pixels_per_point: f32) {
+        self.on_current_viewport_mut(|viewport| {
+            viewport.native_pixels_per_point = Some(pixels_per_point)
+        });
+    }
+
+    /// The current [`egui::Context::pixels_per_point`], to convert the pixels of sdl2 events.
+    pub fn set_pixels_per_point(&mut self, pixels_per_point: f32) {
+        self.pixels_per_point = pixels_per_point;
+    }
+
+    /// The pixels per window coordinate, to convert the coordinates of sdl2 events to pixels.
+    pub fn set_window_scale(&mut self, window_scale: f32) {
+        self.window_scale = window_scale;
+    }
+
+    #[inline]
+    pub fn window_scale(&self) -> f32 {
+        self.window_scale
+    }
+
+    /// Forgets an unfinished IME composition, once no text field has the focus anymore.
+    pub fn reset_composition(&mut self) {
+        self.composing = false;
+    }
+
+    #[inline]
+    fn to_point(&self, x: i32, y: i32) -> Pos2 {
+        Pos2::new(x as f32, y as f32) * (self.window_scale / self.pixels_per_point)
+    }
+
     pub fn on_sdl2_event(&mut self, event: &Event) {
         match event {
             Event::KeyDown { keycode, .. } | Event::KeyUp { keycode, .. } => {
@@ -220,12 +263,39 @@ impl Sdl2EguiMapping {
                 });
             }
             Event::TextInput { text, .. } => {
-                self.input.events.push(egui::Event::Text(text.clone()));
+                if self.composing {
+                    // the IME committed the composition
+                    self.composing = false;
+                    self.input
+                        .events
+                        .push(egui::Event::CompositionEnd(text.clone()));
+                } else {
+                    self.input.events.push(egui::Event::Text(text.clone()));
+                }
+            }
+            Event::TextEditing { text, .. } => {
+                if text.is_empty() {
+                    // cancelled, or about to be committed with a TextInput event
+                    if self.composing {
+                        self.composing = false;
+                        self.input
+                            .events
+                            .push(egui::Event::CompositionEnd(String::new()));
+                    }
+                } else {
+                    if !self.composing {
+                        self.composing = true;
+                        self.input.events.push(egui::Event::CompositionStart);
+                    }
+                    self.input
+                        .events
+                        .push(egui::Event::CompositionUpdate(text.clone()));
+                }
             }
             Event::MouseMotion { x, y, .. } => self
                 .input
                 .events
-                .push(egui::Event::PointerMoved(Pos2::new(*x as f32, *y as f32))),
+                .push(egui::Event::PointerMoved(self.to_point(*x, *y))),
             Event::MouseButtonDown {
                 x, y, mouse_btn, ..
             }
@@ -239,7 +309,7 @@ impl Sdl2EguiMapping {
                     _ => return,
                 };
                 self.input.events.push(egui::Event::PointerButton {
-                    pos: Pos2::new(*x as f32, *y as f32),
+                    pos: self.to_point(*x, *y),
                     button,
                     pressed: matches!(event, Event::MouseButtonDown { .. }),
                     modifiers: self.input.modifiers,
@@ -9,11 +9,37 @@ mod binding;
 pub mod extensions;
 pub mod styling;
 
+/// Decides which sdl2 events are forwarded to an [`EguiSystem`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum EguiInputRouting {
+    /// All events are forwarded
+    #[default]
+    All,
+    /// Only events of the window with the given id are forwarded, see [`sdl2::video::Window::id`]
+    Window(u32),
+    /// No events are forwarded, they need to be passed to [`EguiSystem::on_sdl2_event`] manually
+    Manual,
+}
+
+impl EguiInputRouting {
+    #[inline]
+    pub fn accepts(&self, event: &Event) -> bool {
+        match self {
+            EguiInputRouting::All => true,
+            EguiInputRouting::Window(id) => event.get_window_id() == Some(*id),
+            EguiInputRouting::Manual => false,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct EguiSystem {
     context: Context,
     binding: Sdl2EguiMapping,
+    input_routing: EguiInputRouting,
     current_cursor: Option<CursorIcon>,
+    /// The area of the focused text field in points
+    text_input_rect: Option<egui::Rect>,
     pub(crate) width: f32,
     pub(crate) height: f32,
     /// [`TexturesDelta`] to upload next
@@ -33,6 +59,30 @@ impl EguiSystem {
         self.binding.on_sdl2_event(event)
     }
 
+    /// Forwards the event to [`EguiSystem::on_sdl2_event`] if the [`EguiInputRouting`] accepts it.
+    #[inline]
+    pub fn on_routed_sdl2_event(&mut self, event: &Event) {
+        if self.input_routing.accepts(event) {
+            self.on_sdl2_event(event)
+        }
+    }
+
+    #[inline]
+    pub fn input_routing(&self) -> EguiInputRouting {
+        self.input_routing
+    }
+
+    #[inline]
+    pub fn set_input_routing(&mut self, routing: EguiInputRouting) {
+        self.input_routing = routing;
+    }
+
+    #[inline]
+    pub fn with_input_routing(mut self, routing: EguiInputRouting) -> Self {
+        self.set_input_routing(routing);
+        self
+    }
+
     #[inline]
     pub fn set_target_frame_rate(&mut self, fps: u16) {
         self.binding.set_target_frame_rate(fps)
@@ -43,14 +93,51 @@ impl EguiSystem {
         self.binding.set_fullscreen(fullscreen)
     }
 
+    /// The scale factor of the display, which is multiplied with the zoom factor of the
+    /// [`Context`] to the [`EguiSystem::pixels_per_point`], see [`DisplayInfo::scale_factor`].
+    ///
+    /// [`DisplayInfo::scale_factor`]: crate::engine::parts::sdl::DisplayInfo::scale_factor
+    #[inline]
+    pub fn set_native_pixels_per_point(&mut self, pixels_per_point: f32) {
+        self.binding.set_native_pixels_per_point(pixels_per_point)
+    }
+
+    #[inline]
+    pub fn pixels_per_point(&self) -> f32 {
+        self.context.pixels_per_point()
+    }
+
+    /// The pixels per window coordinate, see [`SdlParts::drawable_scale`].
+    #[inline]
+    pub fn set_window_scale(&mut self, window_scale: f32) {
+        self.binding.set_window_scale(window_scale)
+    }
+
+    /// The area in pixels, [`EguiSystem`] stores its size in points.
     #[inline]
     pub fn set_sdl2_view_area<I: Into<sdl2::rect::Rect>>(&mut self, area: I) {
         let area = area.into();
-        self.width = area.width() as f32;
-        self.height = area.height() as f32;
+        let pixels_per_point = self.pixels_per_point();
+        self.width = area.width() as f32 / pixels_per_point;
+        self.height = area.height() as f32 / pixels_per_point;
+        self.binding.set_pixels_per_point(pixels_per_point);
         self.binding.set_sdl2_view_area(area);
     }
 
+    /// The area (in window coordinates) of the text field that has the focus and therefore wants
+    /// text input.
+    pub fn text_input_rect(&self) -> Option<sdl2::rect::Rect> {
+        self.text_input_rect.map(|rect| {
+            let rect = rect * (self.context.pixels_per_point() / self.binding.window_scale());
+            sdl2::rect::Rect::new(
+                rect.min.x as i32,
+                rect.min.y as i32,
+                rect.width().max(1.0) as u32,
+                rect.height().max(1.0) as u32,
+            )
+        })
+    }
+
     /// Updates the [`Context`]. This updates the state for calls to [`EguiPipeline::prepare`] and
     /// [`EguiPipeline::draw`].
     pub fn update(
@@ -93,6 +180,10 @@ impl EguiSystem {
             }
         }
 
+        self.text_input_rect = output.platform_output.ime.map(|ime| ime.rect);
+        if self.text_input_rect.is_none() {
+            self.binding.reset_composition();
+        }
         self.texture_delta = output.textures_delta;
         self.clipped_primitives = self
             .context
@@ -1,15 +1,24 @@
+use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
 pub struct FpsManager {
     target_duration: Duration,
     last_instant: Option<Instant>,
+    frame_times: VecDeque<Duration>,
+    history_len: usize,
+    vsync: bool,
 }
 
 impl FpsManager {
+    pub const DEFAULT_HISTORY_LEN: usize = 240;
+
     pub fn new(target_frame_rate: u16) -> Self {
         Self {
             target_duration: Self::target_duration(target_frame_rate),
             last_instant: None,
+            frame_times: VecDeque::with_capacity(Self::DEFAULT_HISTORY_LEN),
+            history_len: Self::DEFAULT_HISTORY_LEN,
+            vsync: false,
         }
     }
 
@@ -17,22 +26,239 @@ impl FpsManager {
         self.target_duration = Self::target_duration(target_frame_rate);
     }
 
+    /// The duration a frame should take at most to reach the target frame rate.
+    #[inline]
+    pub fn target_frame_duration(&self) -> Duration {
+        self.target_duration
+    }
+
+    /// Whether the presentation is paced by the vsync of the platform, like on mobile devices. In
+    /// that case, [`FpsManager::delay`] only records the frame times but does not sleep, because
+    /// sleeping on top of the vsync would cause missed frames.
+    #[inline]
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.vsync = vsync;
+    }
+
+    #[inline]
+    pub fn is_vsync(&self) -> bool {
+        self.vsync
+    }
+
+    /// The amount of frame times to keep for [`FpsManager::frame_times`] and
+    /// [`FpsManager::statistics`].
+    pub fn set_history_len(&mut self, history_len: usize) {
+        self.history_len = history_len;
+        while self.frame_times.len() > history_len {
+            self.frame_times.pop_front();
+        }
+    }
+
     pub fn delay(&mut self) -> Duration {
+        self.delay_paced(self.vsync)
+    }
+
+    /// Like [`FpsManager::delay`], but sleeps even with vsync. While nothing is presented, like
+    /// while rendering is suspended, there is no vsync to wait for and the loop would spin.
+    #[inline]
+    pub fn delay_without_present(&mut self) -> Duration {
+        self.delay_paced(false)
+    }
+
+    fn delay_paced(&mut self, paced_by_vsync: bool) -> Duration {
         let mut slept = Duration::ZERO;
         if let Some(before) = self.last_instant.take() {
             let duration = before.elapsed();
             let target_duration = self.target_duration;
-            if duration < target_duration {
+            if !paced_by_vsync && duration < target_duration {
                 slept = target_duration - duration;
                 std::thread::sleep(slept);
             }
+            self.record(duration);
         }
         self.last_instant = Some(Instant::now());
         slept
     }
 
+    fn record(&mut self, frame_time: Duration) {
+        if self.history_len == 0 {
+            return;
+        }
+        if self.frame_times.len() >= self.history_len {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    /// The durations of the most recent frames from the oldest to the newest, without the time
+    /// spent sleeping in [`FpsManager::delay`].
+    #[inline]
+    pub fn frame_times(&self) -> impl ExactSizeIterator<Item = Duration> + '_ {
+        self.frame_times.iter().copied()
+    }
+
+    /// The distribution of the durations of [`FpsManager::frame_times`].
+    pub fn statistics(&self) -> FrameStatistics {
+        let mut durations = self.frame_times.iter().copied().collect::<Vec<_>>();
+        if durations.is_empty() {
+            return FrameStatistics::default();
+        }
+
+        durations.sort_unstable();
+        let percentile = |p: usize| durations[(durations.len() - 1) * p / 100];
+        FrameStatistics {
+            min: durations[0],
+            average: durations.iter().sum::<Duration>() / durations.len() as u32,
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+            max: durations[durations.len() - 1],
+        }
+    }
+
     #[inline]
     fn target_duration(target_frame_rate: u16) -> Duration {
         Duration::from_secs_f32(1.0_f32 / (target_frame_rate as f32))
     }
 }
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct FrameStatistics {
+    pub min: Duration,
+    pub average: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// The accumulator of a game loop with a fixed-timestep simulation: the time of the rendered
+/// frames is collected and consumed in steps of a fixed duration, so the simulation behaves the
+/// same regardless of the frame rate. The remaining time is returned as
+/// [`FixedSteps::alpha`] to interpolate the rendered state between the previous and the current
+/// step. See [`Engine::run_fixed`] for a loop that uses it.
+///
+/// After a long frame (like a hitch or a breakpoint), at most
+/// [`FixedTimestep::max_steps_per_frame`] steps are run and the remaining time is dropped. Too
+/// slow steps would otherwise accumulate even more time to catch up with in the next frame.
+///
+/// [`Engine::run_fixed`]: crate::engine::Engine::run_fixed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedTimestep {
+    step: Duration,
+    accumulator: Duration,
+    max_steps_per_frame: u32,
+    /// The amount of steps run so far
+    ticks: u64,
+}
+
+impl FixedTimestep {
+    pub const DEFAULT_MAX_STEPS_PER_FRAME: u32 = 8;
+
+    /// Steps with the given rate per second.
+    #[inline]
+    pub fn new(update_rate: u16) -> Self {
+        Self::from_step(Duration::from_secs_f64(1.0 / f64::from(update_rate.max(1))))
+    }
+
+    pub fn from_step(step: Duration) -> Self {
+        Self {
+            step: step.max(Duration::from_nanos(1)),
+            accumulator: Duration::ZERO,
+            max_steps_per_frame: Self::DEFAULT_MAX_STEPS_PER_FRAME,
+            ticks: 0,
+        }
+    }
+
+    #[inline]
+    pub fn with_max_steps_per_frame(mut self, max_steps_per_frame: u32) -> Self {
+        self.max_steps_per_frame = max_steps_per_frame.max(1);
+        self
+    }
+
+    /// The duration of a single step.
+    #[inline]
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    #[inline]
+    pub fn max_steps_per_frame(&self) -> u32 {
+        self.max_steps_per_frame
+    }
+
+    /// The amount of steps run since the creation or the last [`FixedTimestep::reset`].
+    #[inline]
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Discards the accumulated time, for example after loading a level.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::ZERO;
+        self.ticks = 0;
+    }
+
+    /// Adds the duration of a frame and returns the steps to run for it.
+    pub fn advance(&mut self, delta: Duration) -> FixedSteps {
+        self.accumulator += delta;
+        let step = self.step.as_nanos();
+        let available = self.accumulator.as_nanos() / step;
+        let steps = available.min(u128::from(self.max_steps_per_frame)) as u32;
+
+        // the time of the skipped steps is dropped, only the fraction of a step is kept
+        let remainder = Duration::from_nanos((self.accumulator.as_nanos() % step) as u64);
+        let dropped = Duration::from_nanos(
+            ((available - u128::from(steps)) * step).min(u128::from(u64::MAX)) as u64,
+        );
+        self.accumulator = remainder;
+
+        let first_tick = self.ticks;
+        self.ticks += u64::from(steps);
+
+        FixedSteps {
+            first_tick,
+            steps,
+            step: self.step,
+            alpha: (remainder.as_secs_f64() / self.step.as_secs_f64()) as f32,
+            dropped,
+        }
+    }
+}
+
+/// A single step of a [`FixedTimestep`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FixedStep {
+    /// See [`FixedTimestep::ticks`]
+    pub tick: u64,
+    /// The duration of the step, the same for every step
+    pub delta: Duration,
+    /// Whether this is the first step of the frame, the only one with events, see
+    /// [`Engine::run_fixed`](crate::engine::Engine::run_fixed)
+    pub first_of_frame: bool,
+}
+
+/// The steps of a [`FixedTimestep`] to run for a frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FixedSteps {
+    /// The [`FixedTimestep::ticks`] of the first step
+    pub first_tick: u64,
+    /// The amount of steps to run, zero if the frame was shorter than a step
+    pub steps: u32,
+    /// The duration of each step
+    pub step: Duration,
+    /// How far the rendered frame is between the last and the next step, in `0.0..1.0`
+    pub alpha: f32,
+    /// The time that was skipped because more than [`FixedTimestep::max_steps_per_frame`] steps
+    /// would have been needed
+    pub dropped: Duration,
+}
+
+impl FixedSteps {
+    /// The ticks of the steps to run.
+    #[inline]
+    pub fn ticks(&self) -> std::ops::Range<u64> {
+        self.first_tick..self.first_tick + u64::from(self.steps)
+    }
+}
@@ -0,0 +1,270 @@
+use crate::engine::event::EngineEvent;
+use crate::engine::input::{
+    AxisBinding, AxisValue, ConflictResolution, InputBinding, InputMap, RebindOutcome,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+use sdl2::controller::Axis;
+use sdl2::event::{Event, WindowEvent};
+
+/// The inputs of the current frame, see [`ActionMap::update`].
+#[derive(Debug, Default, Clone)]
+struct InputState {
+    held: FxHashSet<InputBinding>,
+    just_pressed: FxHashSet<InputBinding>,
+    just_released: FxHashSet<InputBinding>,
+    /// The raw values of the axes, per instance id of the controller
+    axes: FxHashMap<(u32, Axis), i16>,
+}
+
+/// Binds keys, mouse buttons and controller buttons and axes to named actions (like `"jump"`
+/// or `"move_x"`) and tracks their state from frame to frame. The buttons are bound through an
+/// [`InputMap`], so the conflict handling and the capturing of the next input for rebinding are
+/// the same, but the state can be queried at any time during a frame.
+///
+/// The [`Engine`] owns one and feeds it the polled events at the start of every frame, so the
+/// bindings only need to be set up through [`Engine::action_map_mut`].
+///
+/// With the `serde-io` feature, the bindings can be (de)serialized so players can rebind their
+/// controls; the input state is not.
+///
+/// ```no_run
+/// use hotrod::engine::input::{AxisBinding, InputBinding};
+/// use hotrod::engine::system::input::ActionMap;
+/// use sdl2::controller::{Axis, Button};
+/// use sdl2::keyboard::Keycode;
+///
+/// let mut actions = ActionMap::default()
+///     .with_binding("jump", InputBinding::Key(Keycode::Space))
+///     .with_binding("jump", InputBinding::ControllerButton(Button::A))
+///     .with_axis(
+///         "move_x",
+///         AxisBinding::digital(InputBinding::Key(Keycode::A), InputBinding::Key(Keycode::D)),
+///     )
+///     .with_axis("move_x", AxisBinding::controller(Axis::LeftX));
+///
+/// # let events: Vec<hotrod::engine::event::EngineEvent> = Vec::new();
+/// actions.update(&events);
+/// if actions.just_pressed("jump") {
+///     // ...
+/// }
+/// let _speed = actions.axis_value("move_x").value() * 100.0;
+/// ```
+///
+/// [`InputMap`]: crate::engine::input::InputMap
+/// [`Engine`]: crate::engine::Engine
+/// [`Engine::action_map_mut`]: crate::engine::Engine::action_map_mut
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct ActionMap {
+    buttons: InputMap<String>,
+    axes: FxHashMap<String, Vec<AxisBinding>>,
+    #[cfg_attr(feature = "serde-io", serde(skip))]
+    state: InputState,
+    #[cfg_attr(feature = "serde-io", serde(skip))]
+    rebind_outcome: Option<RebindOutcome<String>>,
+}
+
+impl ActionMap {
+    #[inline]
+    pub fn with_binding(mut self, action: impl Into<String>, binding: InputBinding) -> Self {
+        self.bind(action, binding);
+        self
+    }
+
+    /// See [`InputMap::bind`]
+    #[inline]
+    pub fn bind(&mut self, action: impl Into<String>, binding: InputBinding) {
+        self.buttons.bind(action.into(), binding);
+    }
+
+    /// See [`InputMap::unbind`]
+    #[inline]
+    pub fn unbind(&mut self, action: &str, binding: InputBinding) -> bool {
+        self.buttons.unbind(action, binding)
+    }
+
+    /// Replaces all bindings of the action with the given one, as used when the player rebinds
+    /// a control. Other actions with the binding are handled by the `resolution`, see
+    /// [`InputMap::rebind`].
+    #[inline]
+    pub fn rebind(
+        &mut self,
+        action: impl Into<String>,
+        binding: InputBinding,
+        resolution: ConflictResolution,
+    ) -> RebindOutcome<String> {
+        self.buttons.rebind(action.into(), binding, resolution)
+    }
+
+    #[inline]
+    pub fn bindings(&self, action: &str) -> &[InputBinding] {
+        self.buttons.bindings(action)
+    }
+
+    /// The bindings of the buttons, for example to list the conflicts with
+    /// [`InputMap::all_conflicts`].
+    #[inline]
+    pub fn input_map(&self) -> &InputMap<String> {
+        &self.buttons
+    }
+
+    /// To rebind an action to the next input of the player with
+    /// [`InputMap::capture_next_input`]. The events are passed on by [`ActionMap::on_event`],
+    /// the result is available through [`ActionMap::take_rebind_outcome`].
+    #[inline]
+    pub fn input_map_mut(&mut self) -> &mut InputMap<String> {
+        &mut self.buttons
+    }
+
+    /// The result of the capture that completed or was rejected since the last call.
+    #[inline]
+    pub fn take_rebind_outcome(&mut self) -> Option<RebindOutcome<String>> {
+        self.rebind_outcome.take()
+    }
+
+    #[inline]
+    pub fn with_axis(mut self, action: impl Into<String>, binding: AxisBinding) -> Self {
+        self.bind_axis(action, binding);
+        self
+    }
+
+    /// Adds the binding to the axis action, unless it is already bound to it.
+    pub fn bind_axis(&mut self, action: impl Into<String>, binding: AxisBinding) {
+        let bindings = self.axes.entry(action.into()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    #[inline]
+    pub fn axis_bindings(&self, action: &str) -> &[AxisBinding] {
+        self.axes.get(action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Removes all bindings of the action, buttons and axes.
+    #[inline]
+    pub fn clear(&mut self, action: &str) {
+        self.buttons.clear(action);
+        self.axes.remove(action);
+    }
+
+    /// The names of all actions with at least one binding.
+    pub fn actions(&self) -> impl Iterator<Item = &str> + '_ {
+        let axes = self
+            .axes
+            .iter()
+            .filter(|(action, bindings)| {
+                !bindings.is_empty() && self.buttons.bindings(action.as_str()).is_empty()
+            })
+            .map(|(action, _)| action);
+        self.buttons.bound_actions().chain(axes).map(String::as_str)
+    }
+
+    /// Starts a new frame with the polled events, which resets the
+    /// [`ActionMap::just_pressed`] and [`ActionMap::just_released`] state of the previous frame.
+    pub fn update(&mut self, events: &[EngineEvent]) {
+        self.state.just_pressed.clear();
+        self.state.just_released.clear();
+        for event in events {
+            self.on_event(event);
+        }
+    }
+
+    /// Applies a single event to the state of the current frame, usually called through
+    /// [`ActionMap::update`]. Losing the focus releases all held inputs, see
+    /// [`ActionMap::reset`]. While the [`InputMap`] captures the next input, it does not
+    /// trigger any action.
+    pub fn on_event(&mut self, event: &Event) {
+        if self.buttons.capturing().is_some() {
+            if let Some(outcome) = self.buttons.on_event(event) {
+                self.rebind_outcome = Some(outcome);
+            }
+            return;
+        }
+
+        if let Event::Window {
+            win_event: WindowEvent::FocusLost,
+            ..
+        } = event
+        {
+            self.reset();
+        } else if let Event::ControllerAxisMotion {
+            which, axis, value, ..
+        } = event
+        {
+            self.state.axes.insert((*which, *axis), *value);
+        } else if let Event::ControllerDeviceRemoved { which, .. } = event {
+            self.state
+                .axes
+                .retain(|(controller, _), _| controller != which);
+        } else if let Some((binding, pressed)) = InputBinding::from_event(event) {
+            if pressed {
+                self.state.held.insert(binding);
+                self.state.just_pressed.insert(binding);
+            } else {
+                self.state.held.remove(&binding);
+                self.state.just_released.insert(binding);
+            }
+        }
+    }
+
+    /// Forgets all held inputs, for example when the release events will not arrive. This
+    /// happens automatically when the window loses the focus.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.state = InputState::default();
+    }
+
+    /// Whether any binding of the action is held down.
+    pub fn pressed(&self, action: &str) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| self.state.held.contains(binding))
+    }
+
+    /// Whether any binding of the action was pressed in the current frame.
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.bindings(action)
+            .iter()
+            .any(|binding| self.state.just_pressed.contains(binding))
+    }
+
+    /// Whether a binding of the action was released in the current frame and no other binding
+    /// of the action is still held down.
+    pub fn just_released(&self, action: &str) -> bool {
+        !self.pressed(action)
+            && self
+                .bindings(action)
+                .iter()
+                .any(|binding| self.state.just_released.contains(binding))
+    }
+
+    /// The value of the axis action. If multiple bindings (or the same axis of multiple
+    /// controllers) are deflected, the one with the largest magnitude wins, so a keyboard and a
+    /// controller can be used side by side.
+    pub fn axis_value(&self, action: &str) -> AxisValue {
+        self.axis_bindings(action)
+            .iter()
+            .map(|binding| match binding {
+                AxisBinding::Digital { negative, positive } => {
+                    let held = |binding: &InputBinding| {
+                        f32::from(u8::from(self.state.held.contains(binding)))
+                    };
+                    AxisValue::new(held(positive) - held(negative))
+                }
+                AxisBinding::Controller { axis, config } => self
+                    .state
+                    .axes
+                    .iter()
+                    .filter(|((_, a), _)| *a == axis.0)
+                    .map(|(_, value)| config.apply_raw(*value))
+                    .max_by(|a, b| a.value().abs().total_cmp(&b.value().abs()))
+                    .unwrap_or(AxisValue::NEUTRAL),
+            })
+            .max_by(|a, b| a.value().abs().total_cmp(&b.value().abs()))
+            .unwrap_or(AxisValue::NEUTRAL)
+    }
+}
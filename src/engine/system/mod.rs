@@ -1,7 +1,18 @@
+pub mod arena;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod backend;
 pub mod canvas;
+pub mod commands;
 #[cfg(feature = "ui-egui")]
 pub mod egui;
 pub mod fps;
+pub mod input;
+pub mod packet;
+pub mod resolution;
+#[cfg(feature = "steamworks")]
+pub mod steam;
+pub mod viewport;
 pub mod vulkan;
 
 #[cfg(feature = "ttf-sdl2")]
@@ -0,0 +1,547 @@
+//! A retained description of everything that is drawn in a frame, which is recorded into command
+//! buffers by the engine instead of by the game. Unlike the [`BufferedCanvasLayer`], a
+//! [`FramePacket`] holds no graphics resources: textures are referenced by [`TextureKey`]s,
+//! which are resolved through the [`PacketTextures`] when the packet is recorded. So a packet
+//! can be filled on any thread, kept across frames and (with the `serde-io` feature) serialized.
+//!
+//! ```no_run
+//! use hotrod::engine::system::packet::{FramePacket, PacketTextures, TextureKey};
+//! use hotrod::engine::types::world2d::{Dim, Pos};
+//!
+//! const PLAYER: TextureKey = TextureKey(0);
+//!
+//! let mut engine = hotrod::engine::Engine::default();
+//! let textures = PacketTextures::default(); // with_sprite(PLAYER, ...)
+//! let mut packet = FramePacket::default();
+//!
+//! engine.update(|context| {
+//!     packet.clear();
+//!     packet.set_draw_color([1.0, 0.0, 0.0, 1.0]);
+//!     packet.fill_rect(Pos::new(10.0, 10.0), Dim::new(100.0, 20.0));
+//!     packet.draw_sprite(PLAYER, Pos::new(50.0, 50.0), Dim::new(32.0, 32.0));
+//!     packet.draw_text("Score: 42", 24, [255, 255, 255, 255], Pos::new(10.0, 40.0));
+//!     context.render_packet(&packet, &textures).unwrap();
+//! });
+//! ```
+//!
+//! [`BufferedCanvasLayer`]: crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer
+
+use crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer;
+use crate::engine::system::commands::{CommandRecorder, RecordedCommands};
+#[cfg(feature = "ttf-font-renderer")]
+use crate::engine::system::ttf::TextTextures;
+use crate::engine::system::vulkan::textured::TexturedPipeline;
+use crate::engine::system::vulkan::textures::{TextureId, TextureView};
+use crate::engine::system::vulkan::world2d::entities::{
+    EntityInstanceData, World2dEntitiesPipeline,
+};
+use crate::engine::system::vulkan::world2d::terrain::{
+    InstanceData, TerrainChunk, World2dTerrainPipeline,
+};
+use crate::engine::system::vulkan::DrawError;
+use crate::engine::types::world2d::{Dim, Pos, Rect};
+use crate::engine::RenderContext;
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+
+/// Identifies a texture within a [`FramePacket`], see [`PacketTextures`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct TextureKey(pub u32);
+
+/// A single entry of a [`FramePacket`]. Most commands correspond to a method of the
+/// [`BufferedCanvasLayer`] they are replayed on, including the current draw color, transform and
+/// clip rect, which persist until they are changed by a later command.
+///
+/// [`BufferedCanvasLayer`]: crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum DrawCommand {
+    SetDrawColor([f32; 4]),
+    PushTransform,
+    PopTransform,
+    Translate(Dim<f32>),
+    /// In radians, clockwise on screen
+    Rotate(f32),
+    Scale(Dim<f32>),
+    /// In pixels of the render target
+    PushClipRect(Rect<f32>),
+    PopClipRect,
+    Line {
+        from: Pos<f32>,
+        to: Pos<f32>,
+    },
+    Path(Vec<Pos<f32>>),
+    Rect {
+        pos: Pos<f32>,
+        dim: Dim<f32>,
+        filled: bool,
+    },
+    Circle {
+        center: Pos<f32>,
+        radius: f32,
+        filled: bool,
+    },
+    /// The region of the texture between the texture coordinates, stretched to the rectangle
+    Sprite {
+        texture: TextureKey,
+        pos: Pos<f32>,
+        dim: Dim<f32>,
+        uv_min: [f32; 2],
+        uv_max: [f32; 2],
+    },
+    /// Rendered with the [`FontRenderer`] of the engine, the top left corner at the position
+    ///
+    /// [`FontRenderer`]: crate::engine::system::ttf::FontRenderer
+    Text {
+        text: String,
+        size: u16,
+        color: [u8; 4],
+        pos: Pos<f32>,
+    },
+    /// See [`BufferedCanvasLayer::draw_terrain`]
+    ///
+    /// [`BufferedCanvasLayer::draw_terrain`]: crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer::draw_terrain
+    Terrain {
+        texture: TextureKey,
+        tiles: Vec<InstanceData>,
+    },
+    /// See [`BufferedCanvasLayer::draw_entities`]
+    ///
+    /// [`BufferedCanvasLayer::draw_entities`]: crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer::draw_entities
+    Entities {
+        texture: TextureKey,
+        entities: Vec<EntityInstanceData>,
+    },
+    /// The output of the main egui context, see [`EguiPrimitives`]
+    #[cfg(feature = "ui-egui")]
+    Egui(EguiPrimitives),
+}
+
+/// The draws of a frame, in the order they are drawn, see the [module documentation](self).
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct FramePacket {
+    pub commands: Vec<DrawCommand>,
+}
+
+impl FramePacket {
+    #[inline]
+    pub fn push(&mut self, command: DrawCommand) {
+        self.commands.push(command);
+    }
+
+    /// Removes all commands but keeps the allocation, to fill the packet of the next frame.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    #[inline]
+    pub fn set_draw_color(&mut self, color: [f32; 4]) {
+        self.push(DrawCommand::SetDrawColor(color));
+    }
+
+    #[inline]
+    pub fn draw_line(&mut self, from: Pos<f32>, to: Pos<f32>) {
+        self.push(DrawCommand::Line { from, to });
+    }
+
+    #[inline]
+    pub fn draw_rect(&mut self, pos: Pos<f32>, dim: Dim<f32>) {
+        self.push(DrawCommand::Rect {
+            pos,
+            dim,
+            filled: false,
+        });
+    }
+
+    #[inline]
+    pub fn fill_rect(&mut self, pos: Pos<f32>, dim: Dim<f32>) {
+        self.push(DrawCommand::Rect {
+            pos,
+            dim,
+            filled: true,
+        });
+    }
+
+    #[inline]
+    pub fn fill_circle(&mut self, center: Pos<f32>, radius: f32) {
+        self.push(DrawCommand::Circle {
+            center,
+            radius,
+            filled: true,
+        });
+    }
+
+    /// The whole texture stretched to the rectangle.
+    #[inline]
+    pub fn draw_sprite(&mut self, texture: TextureKey, pos: Pos<f32>, dim: Dim<f32>) {
+        self.push(DrawCommand::Sprite {
+            texture,
+            pos,
+            dim,
+            uv_min: [0.0, 0.0],
+            uv_max: [1.0, 1.0],
+        });
+    }
+
+    #[inline]
+    pub fn draw_text(&mut self, text: impl Into<String>, size: u16, color: [u8; 4], pos: Pos<f32>) {
+        self.push(DrawCommand::Text {
+            text: text.into(),
+            size,
+            color,
+            pos,
+        });
+    }
+
+    #[inline]
+    pub fn draw_terrain(&mut self, texture: TextureKey, tiles: Vec<InstanceData>) {
+        self.push(DrawCommand::Terrain { texture, tiles });
+    }
+
+    #[inline]
+    pub fn draw_entities(&mut self, texture: TextureKey, entities: Vec<EntityInstanceData>) {
+        self.push(DrawCommand::Entities { texture, entities });
+    }
+
+    /// Records the commands of the packet. The draws are replayed on a [`BufferedCanvasLayer`],
+    /// which is flushed into a new command buffer whenever egui primitives are drawn in between.
+    /// Textures that are not in the [`PacketTextures`] are drawn with the missing placeholder,
+    /// terrain and entities without a texture are skipped.
+    pub fn record(
+        &self,
+        context: &mut RenderContext,
+        textures: &PacketTextures,
+    ) -> Result<Vec<RecordedCommands>, DrawError> {
+        let mut recorded = Vec::new();
+        let mut layer = Self::create_layer(context);
+
+        for command in &self.commands {
+            match command {
+                DrawCommand::SetDrawColor(color) => layer.set_draw_color(*color),
+                DrawCommand::PushTransform => layer.push_transform(),
+                DrawCommand::PopTransform => {
+                    layer.pop_transform();
+                }
+                DrawCommand::Translate(offset) => layer.translate(*offset),
+                DrawCommand::Rotate(angle) => layer.rotate(*angle),
+                DrawCommand::Scale(factors) => layer.scale(*factors),
+                DrawCommand::PushClipRect(rect) => layer.push_clip_rect(*rect),
+                DrawCommand::PopClipRect => {
+                    layer.pop_clip_rect();
+                }
+                DrawCommand::Line { from, to } => layer.draw_line(*from, *to),
+                DrawCommand::Path(positions) => layer.draw_path(positions),
+                DrawCommand::Rect { pos, dim, filled } => {
+                    if *filled {
+                        layer.fill_rect(*pos, *dim)
+                    } else {
+                        layer.draw_rect(*pos, *dim)
+                    }
+                }
+                DrawCommand::Circle {
+                    center,
+                    radius,
+                    filled,
+                } => {
+                    if *filled {
+                        layer.fill_circle(*center, *radius)
+                    } else {
+                        layer.draw_circle(*center, *radius)
+                    }
+                }
+                DrawCommand::Sprite {
+                    texture,
+                    pos,
+                    dim,
+                    uv_min,
+                    uv_max,
+                } => {
+                    let view = textures.sprites.get(texture).cloned().unwrap_or_else(|| {
+                        TextureView::new(context.pipelines.placeholders().missing().clone())
+                    });
+                    let [u0, v0] = view.map_uv(*uv_min);
+                    let [u1, v1] = view.map_uv(*uv_max);
+                    layer.draw_textured_triangles(
+                        [
+                            (*pos, Pos::new(u0, v0)),
+                            (*pos + Dim::new(dim.x, 0.0), Pos::new(u1, v0)),
+                            (*pos + *dim, Pos::new(u1, v1)),
+                            (*pos + *dim, Pos::new(u1, v1)),
+                            (*pos + Dim::new(0.0, dim.y), Pos::new(u0, v1)),
+                            (*pos, Pos::new(u0, v0)),
+                        ]
+                        .into_iter(),
+                        view.texture,
+                    );
+                }
+                #[cfg(feature = "ttf-font-renderer")]
+                DrawCommand::Text {
+                    text,
+                    size,
+                    color,
+                    pos,
+                } => {
+                    let textured = context.font_renderer.prepare_render(
+                        &TextTextures::new(context.pipelines, context.inner),
+                        text,
+                        *size,
+                        *color,
+                        pos.x,
+                        pos.y,
+                    );
+                    layer.draw_textured_triangles(
+                        textured
+                            .vertices
+                            .iter()
+                            .map(|vertex| (vertex.pos, vertex.uv)),
+                        textured.texture,
+                    );
+                }
+                #[cfg(not(feature = "ttf-font-renderer"))]
+                DrawCommand::Text { .. } => {}
+                DrawCommand::Terrain { texture, tiles } => {
+                    if let Some(texture) = textures.terrain.get(texture) {
+                        layer.draw_terrain(&TerrainChunk::new(texture.clone(), tiles.clone()));
+                    }
+                }
+                DrawCommand::Entities { texture, entities } => {
+                    if let Some(texture) = textures.entities.get(texture) {
+                        layer.draw_entities(texture, entities);
+                    }
+                }
+                #[cfg(feature = "ui-egui")]
+                DrawCommand::Egui(primitives) => {
+                    let flushed = core::mem::replace(&mut layer, Self::create_layer(context));
+                    recorded.push(flushed.flush(context.inner, context.pipelines));
+
+                    let mut recorder = CommandRecorder::new(context.inner)?;
+                    context.pipelines.egui.draw_primitives(
+                        recorder.recorder_mut(),
+                        primitives.size,
+                        primitives.pixels_per_point,
+                        &primitives.to_clipped_primitives(),
+                    )?;
+                    recorded.push(recorder.finish()?);
+                }
+            }
+        }
+
+        recorded.push(layer.flush(context.inner, context.pipelines));
+        Ok(recorded)
+    }
+
+    #[inline]
+    fn create_layer(context: &RenderContext) -> BufferedCanvasLayer {
+        BufferedCanvasLayer::default()
+            .with_texture_registry(Arc::clone(context.pipelines.texture.texture_registry()))
+    }
+}
+
+/// Resolves the [`TextureKey`]s of a [`FramePacket`] when it is recorded. The same key can refer
+/// to a different texture for sprites, terrain and entities.
+#[derive(Default)]
+pub struct PacketTextures {
+    sprites: FxHashMap<TextureKey, TextureView<TexturedPipeline>>,
+    terrain: FxHashMap<TextureKey, TextureId<World2dTerrainPipeline>>,
+    entities: FxHashMap<TextureKey, TextureId<World2dEntitiesPipeline>>,
+}
+
+impl PacketTextures {
+    /// The texture (or a region of it, like a sprite of an atlas) of [`DrawCommand::Sprite`]s.
+    #[inline]
+    pub fn with_sprite(
+        mut self,
+        key: TextureKey,
+        texture: impl Into<TextureView<TexturedPipeline>>,
+    ) -> Self {
+        self.insert_sprite(key, texture);
+        self
+    }
+
+    #[inline]
+    pub fn insert_sprite(
+        &mut self,
+        key: TextureKey,
+        texture: impl Into<TextureView<TexturedPipeline>>,
+    ) -> Option<TextureView<TexturedPipeline>> {
+        self.sprites.insert(key, texture.into())
+    }
+
+    #[inline]
+    pub fn insert_terrain(
+        &mut self,
+        key: TextureKey,
+        texture: TextureId<World2dTerrainPipeline>,
+    ) -> Option<TextureId<World2dTerrainPipeline>> {
+        self.terrain.insert(key, texture)
+    }
+
+    #[inline]
+    pub fn insert_entities(
+        &mut self,
+        key: TextureKey,
+        texture: TextureId<World2dEntitiesPipeline>,
+    ) -> Option<TextureId<World2dEntitiesPipeline>> {
+        self.entities.insert(key, texture)
+    }
+
+    /// Removes the textures of the key, so they can be released.
+    pub fn remove(&mut self, key: TextureKey) {
+        self.sprites.remove(&key);
+        self.terrain.remove(&key);
+        self.entities.remove(&key);
+    }
+}
+
+/// The meshes egui tessellated for a frame, captured with
+/// [`BeforeRenderContext::capture_egui`]. They are drawn with the textures of the main egui
+/// context, which must still be alive when the packet is recorded. The engine draws the main
+/// egui context on top of every frame anyway, capturing it is only needed for packets that are
+/// recorded elsewhere, like on a render thread. Paint callbacks are not captured.
+///
+/// [`BeforeRenderContext::capture_egui`]: crate::engine::BeforeRenderContext::capture_egui
+#[cfg(feature = "ui-egui")]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct EguiPrimitives {
+    /// The size of the screen in points
+    pub size: [f32; 2],
+    pub pixels_per_point: f32,
+    pub meshes: Vec<EguiMesh>,
+}
+
+#[cfg(feature = "ui-egui")]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct EguiMesh {
+    /// The minimum and maximum corner in points
+    pub clip_rect: [[f32; 2]; 2],
+    pub texture: EguiTexture,
+    pub indices: Vec<u32>,
+    pub vertices: Vec<EguiVertex>,
+}
+
+#[cfg(feature = "ui-egui")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum EguiTexture {
+    Managed(u64),
+    User(u64),
+}
+
+#[cfg(feature = "ui-egui")]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct EguiVertex {
+    pub pos: [f32; 2],
+    pub uv: [f32; 2],
+    /// Premultiplied by the alpha
+    pub color: [u8; 4],
+}
+
+#[cfg(feature = "ui-egui")]
+impl EguiPrimitives {
+    pub(crate) fn capture(egui: &crate::engine::system::egui::EguiSystem) -> Self {
+        use egui::epaint::Primitive;
+        Self {
+            size: [egui.width, egui.height],
+            pixels_per_point: egui.pixels_per_point(),
+            meshes: egui
+                .clipped_primitives
+                .iter()
+                .filter_map(|clipped| match &clipped.primitive {
+                    Primitive::Mesh(mesh) => Some(EguiMesh {
+                        clip_rect: [
+                            [clipped.clip_rect.min.x, clipped.clip_rect.min.y],
+                            [clipped.clip_rect.max.x, clipped.clip_rect.max.y],
+                        ],
+                        texture: match mesh.texture_id {
+                            egui::TextureId::Managed(id) => EguiTexture::Managed(id),
+                            egui::TextureId::User(id) => EguiTexture::User(id),
+                        },
+                        indices: mesh.indices.clone(),
+                        vertices: mesh
+                            .vertices
+                            .iter()
+                            .map(|vertex| EguiVertex {
+                                pos: [vertex.pos.x, vertex.pos.y],
+                                uv: [vertex.uv.x, vertex.uv.y],
+                                color: vertex.color.to_array(),
+                            })
+                            .collect(),
+                    }),
+                    Primitive::Callback(_) => None,
+                })
+                .collect(),
+        }
+    }
+
+    fn to_clipped_primitives(&self) -> Vec<egui::ClippedPrimitive> {
+        use egui::epaint::{Mesh, Primitive, Vertex};
+        use egui::{pos2, Color32, Rect as EguiRect};
+        self.meshes
+            .iter()
+            .map(|mesh| egui::ClippedPrimitive {
+                clip_rect: EguiRect::from_min_max(
+                    pos2(mesh.clip_rect[0][0], mesh.clip_rect[0][1]),
+                    pos2(mesh.clip_rect[1][0], mesh.clip_rect[1][1]),
+                ),
+                primitive: Primitive::Mesh(Mesh {
+                    indices: mesh.indices.clone(),
+                    vertices: mesh
+                        .vertices
+                        .iter()
+                        .map(|vertex| Vertex {
+                            pos: pos2(vertex.pos[0], vertex.pos[1]),
+                            uv: pos2(vertex.uv[0], vertex.uv[1]),
+                            color: Color32::from_rgba_premultiplied(
+                                vertex.color[0],
+                                vertex.color[1],
+                                vertex.color[2],
+                                vertex.color[3],
+                            ),
+                        })
+                        .collect(),
+                    texture_id: match mesh.texture {
+                        EguiTexture::Managed(id) => egui::TextureId::Managed(id),
+                        EguiTexture::User(id) => egui::TextureId::User(id),
+                    },
+                }),
+            })
+            .collect()
+    }
+}
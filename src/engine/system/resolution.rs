@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+/// Chooses the resolution the world is rendered at, relative to the window, from the time the
+/// previous frames took. If the frames take longer than the target frame time, the resolution is
+/// lowered step by step down to [`DynamicResolution::min_scale`] and raised again once there is
+/// enough headroom. The UI is not affected, see [`BeforeRenderContext::render_layered`].
+///
+/// The gap between the thresholds to lower and to raise the resolution (see
+/// [`DynamicResolution::with_thresholds`]) and the cooldown after each change keep the scale
+/// from oscillating between two steps. The [`Engine`] feeds the GPU time of the frames (see
+/// [`VulkanSystem::gpu_frame_time`]) and falls back to the frame time without the sleep of the
+/// frame limiter if the GPU does not support timestamps.
+///
+/// [`Engine`]: crate::engine::Engine
+/// [`VulkanSystem::gpu_frame_time`]: crate::engine::system::vulkan::system::VulkanSystem::gpu_frame_time
+/// [`BeforeRenderContext::render_layered`]: crate::engine::BeforeRenderContext::render_layered
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicResolution {
+    min_scale: f32,
+    max_scale: f32,
+    step: f32,
+    /// [`None`] to use the target frame time of the [`FpsManager`]
+    ///
+    /// [`FpsManager`]: crate::engine::system::fps::FpsManager
+    target_frame_time: Option<Duration>,
+    /// The share of the target frame time above which the resolution is lowered
+    lower_threshold: f32,
+    /// The share of the target frame time below which the resolution is raised
+    raise_threshold: f32,
+    /// The amount of frames to wait after a change before the next one
+    cooldown: u32,
+    scale: f32,
+    /// Replaces the scale chosen from the frame times, see [`DynamicResolution::set_override`]
+    override_scale: Option<f32>,
+    average_frame_time: Option<f32>,
+    frames_since_change: u32,
+}
+
+impl Default for DynamicResolution {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            min_scale: 0.5,
+            max_scale: 1.0,
+            step: 0.05,
+            target_frame_time: None,
+            lower_threshold: 0.95,
+            raise_threshold: 0.75,
+            cooldown: 30,
+            scale: 1.0,
+            override_scale: None,
+            average_frame_time: None,
+            frames_since_change: 0,
+        }
+    }
+}
+
+impl DynamicResolution {
+    /// The weight of the latest frame time for the moving average.
+    const SMOOTHING: f32 = 0.1;
+
+    /// Always renders the world at the given scale.
+    #[inline]
+    pub fn fixed(scale: f32) -> Self {
+        Self::default().with_scale_range(scale, scale)
+    }
+
+    #[inline]
+    pub fn with_scale_range(mut self, min_scale: f32, max_scale: f32) -> Self {
+        self.set_scale_range(min_scale, max_scale);
+        self
+    }
+
+    /// The scales are clamped to `0.1..=1.0`.
+    pub fn set_scale_range(&mut self, min_scale: f32, max_scale: f32) {
+        self.min_scale = min_scale.clamp(0.1, 1.0);
+        self.max_scale = max_scale.clamp(self.min_scale, 1.0);
+        self.scale = self.scale.clamp(self.min_scale, self.max_scale);
+    }
+
+    /// The amount the scale changes at once, `0.05` by default.
+    #[inline]
+    pub fn with_step(mut self, step: f32) -> Self {
+        self.step = step.max(0.01);
+        self
+    }
+
+    /// The frame time to stay below, the target frame time of the [`FpsManager`] by default.
+    ///
+    /// [`FpsManager`]: crate::engine::system::fps::FpsManager
+    #[inline]
+    pub fn with_target_frame_time(mut self, target: Duration) -> Self {
+        self.target_frame_time = Some(target);
+        self
+    }
+
+    /// The resolution is lowered while the average frame time exceeds `lower` times the target
+    /// frame time and raised while it is below `raise` times the target, `0.95` and `0.75` by
+    /// default. The `raise` threshold is clamped below the `lower` threshold.
+    #[inline]
+    pub fn with_thresholds(mut self, lower: f32, raise: f32) -> Self {
+        self.lower_threshold = lower.max(0.0);
+        self.raise_threshold = raise.clamp(0.0, self.lower_threshold);
+        self
+    }
+
+    /// The amount of frames to wait after the scale changed before changing it again, so the
+    /// frame times can settle.
+    #[inline]
+    pub fn with_cooldown(mut self, frames: u32) -> Self {
+        self.cooldown = frames;
+        self
+    }
+
+    #[inline]
+    pub fn min_scale(&self) -> f32 {
+        self.min_scale
+    }
+
+    #[inline]
+    pub fn max_scale(&self) -> f32 {
+        self.max_scale
+    }
+
+    #[inline]
+    pub fn target_frame_time(&self) -> Option<Duration> {
+        self.target_frame_time
+    }
+
+    /// The current scale of the world resolution, within
+    /// [`DynamicResolution::min_scale`]`..=`[`DynamicResolution::max_scale`] unless overridden.
+    #[inline]
+    pub fn scale(&self) -> f32 {
+        self.override_scale.unwrap_or(self.scale)
+    }
+
+    /// Renders the world at the given scale (clamped to `0.1..=1.0`) regardless of the frame
+    /// times, for example for a graphics setting or a benchmark. [`None`] hands the control
+    /// back to the frame times, starting from the scale before the override.
+    #[inline]
+    pub fn set_override(&mut self, scale: Option<f32>) {
+        self.override_scale = scale.map(|scale| scale.clamp(0.1, 1.0));
+    }
+
+    #[inline]
+    pub fn override_scale(&self) -> Option<f32> {
+        self.override_scale
+    }
+
+    /// The moving average of the recorded frame times.
+    #[inline]
+    pub fn average_frame_time(&self) -> Option<Duration> {
+        self.average_frame_time.map(Duration::from_secs_f32)
+    }
+
+    /// The size of the world for the given window size, at least one pixel.
+    #[inline]
+    pub fn scaled_extent(&self, width: u32, height: u32) -> [u32; 2] {
+        [
+            ((width as f32 * self.scale()).round() as u32).max(1),
+            ((height as f32 * self.scale()).round() as u32).max(1),
+        ]
+    }
+
+    /// Records the time the previous frame took (on the GPU or without the sleep of the frame
+    /// limiter) and adjusts the scale, returns whether it changed. While overridden, only the
+    /// average is updated.
+    pub fn update(&mut self, frame_time: Duration, default_target: Duration) -> bool {
+        let frame_time = frame_time.as_secs_f32();
+        let average = match self.average_frame_time {
+            Some(average) => average + (frame_time - average) * Self::SMOOTHING,
+            None => frame_time,
+        };
+        self.average_frame_time = Some(average);
+        self.frames_since_change = self.frames_since_change.saturating_add(1);
+
+        if self.override_scale.is_some() || self.frames_since_change < self.cooldown {
+            return false;
+        }
+
+        let target = self
+            .target_frame_time
+            .unwrap_or(default_target)
+            .as_secs_f32();
+        let scale = if average > target * self.lower_threshold {
+            (self.scale - self.step).max(self.min_scale)
+        } else if average < target * self.raise_threshold {
+            (self.scale + self.step).min(self.max_scale)
+        } else {
+            self.scale
+        };
+
+        if scale != self.scale {
+            self.scale = scale;
+            self.frames_since_change = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use steamworks::{AppId, CallbackHandle, Client, GameOverlayActivated, SingleClient};
+
+/// The connection to the Steam client, see [`EngineBuilder::with_steam_app_id`].
+///
+/// Steam is initialized by the engine before the vulkan instance is created, so that the overlay
+/// can hook into the presentation, and shut down after the window and the vulkan system are
+/// dropped. The callbacks are pumped once per frame at the beginning of [`Engine::update`].
+///
+/// [`EngineBuilder::with_steam_app_id`]: crate::engine::builder::EngineBuilder::with_steam_app_id
+/// [`Engine::update`]: crate::engine::Engine::update
+pub struct Steam {
+    client: Client,
+    single: SingleClient,
+    overlay_active: Arc<AtomicBool>,
+    _overlay_callback: CallbackHandle,
+    store_stats: bool,
+}
+
+impl Steam {
+    pub(crate) fn init(app_id: u32) -> Result<Self, Error> {
+        if steamworks::restart_app_if_necessary(AppId(app_id)) {
+            return Err(Error::RestartRequired);
+        }
+
+        let (client, single) = Client::init_app(AppId(app_id))?;
+        let overlay_active = Arc::new(AtomicBool::new(false));
+        let overlay_callback = client.register_callback({
+            let overlay_active = Arc::clone(&overlay_active);
+            move |overlay: GameOverlayActivated| {
+                overlay_active.store(overlay.active, Ordering::Relaxed);
+            }
+        });
+
+        info!("Steam initialized for app {app_id}");
+
+        Ok(Self {
+            client,
+            single,
+            overlay_active,
+            _overlay_callback: overlay_callback,
+            store_stats: false,
+        })
+    }
+
+    /// The underlying client for everything that is not wrapped.
+    #[inline]
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Pumps the callbacks and stores the changed stats, called once per frame by the engine.
+    pub(crate) fn update(&mut self) {
+        self.single.run_callbacks();
+        if std::mem::take(&mut self.store_stats) && self.client.user_stats().store_stats().is_err()
+        {
+            warn!("Failed to store the Steam stats");
+        }
+    }
+
+    /// Whether the overlay is shown. The game must keep rendering (the overlay is drawn on top
+    /// of the presented frames) but should pause and ignore input meanwhile.
+    #[inline]
+    pub fn is_overlay_active(&self) -> bool {
+        self.overlay_active.load(Ordering::Relaxed)
+    }
+
+    /// Opens the overlay with the given dialog, like `"friends"`, `"achievements"` or `"settings"`.
+    #[inline]
+    pub fn activate_overlay(&self, dialog: &str) {
+        self.client.friends().activate_game_overlay(dialog);
+    }
+
+    /// Unlocks the achievement with the given API name. The change is sent to Steam with the next
+    /// frame, which also shows the notification.
+    pub fn unlock_achievement(&mut self, name: &str) {
+        if self.client.user_stats().achievement(name).set().is_ok() {
+            self.store_stats = true;
+        } else {
+            warn!("Failed to unlock the unknown Steam achievement {name:?}");
+        }
+    }
+
+    /// Locks the achievement with the given API name again, mostly useful while testing.
+    pub fn clear_achievement(&mut self, name: &str) {
+        if self.client.user_stats().achievement(name).clear().is_ok() {
+            self.store_stats = true;
+        } else {
+            warn!("Failed to clear the unknown Steam achievement {name:?}");
+        }
+    }
+
+    /// Whether the achievement is unlocked, [`None`] if it is unknown.
+    #[inline]
+    pub fn is_achievement_unlocked(&self, name: &str) -> Option<bool> {
+        self.client.user_stats().achievement(name).get().ok()
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("The game was not launched through Steam and is being restarted by it, exit now")]
+    RestartRequired,
+    #[error("Failed to initialize Steam: {0}")]
+    InitError(#[from] steamworks::SteamAPIInitError),
+}
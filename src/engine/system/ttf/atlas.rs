@@ -0,0 +1,269 @@
+use crate::engine::system::vulkan::textured::TexturedPipeline;
+use crate::engine::system::vulkan::textures::{ImageSystem, TextureId, UploadPriority};
+use crate::engine::system::vulkan::UploadError;
+use std::sync::Arc;
+use vulkano::image::Image;
+
+/// The location of rendered text within a page of the [`TextAtlas`]. It is only valid as long as
+/// the generation of the page did not change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct AtlasSlot {
+    page: usize,
+    generation: u32,
+    pos: [u32; 2],
+    size: [u32; 2],
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    cursor: u32,
+}
+
+struct AtlasPage {
+    image: Arc<Image>,
+    texture: TextureId<TexturedPipeline>,
+    generation: u32,
+    shelves: Vec<Shelf>,
+    next_shelf_y: u32,
+    /// The frame in which a slot of this page was used the last time
+    last_used: u64,
+}
+
+impl AtlasPage {
+    /// The position of the padded allocation for content of the size.
+    fn allocate(&mut self, page_size: u32, size: [u32; 2]) -> Option<[u32; 2]> {
+        let [padded_width, padded_height] = TextAtlas::padded(size);
+
+        // the shelf that wastes the least height
+        let index = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| {
+                shelf.height >= padded_height && shelf.cursor + padded_width <= page_size
+            })
+            .min_by_key(|(_, shelf)| shelf.height - padded_height)
+            .map(|(index, _)| index);
+
+        let index = match index {
+            Some(index) => index,
+            None if self.next_shelf_y + padded_height <= page_size && padded_width <= page_size => {
+                self.shelves.push(Shelf {
+                    y: self.next_shelf_y,
+                    height: padded_height,
+                    cursor: 0,
+                });
+                self.next_shelf_y += padded_height;
+                self.shelves.len() - 1
+            }
+            None => return None,
+        };
+
+        let shelf = &mut self.shelves[index];
+        let pos = [shelf.cursor, shelf.y];
+        shelf.cursor += padded_width;
+        Some(pos)
+    }
+
+    fn clear(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        self.shelves.clear();
+        self.next_shelf_y = 0;
+    }
+}
+
+/// Packs rendered text into shared pages instead of creating a texture per text. A new page is
+/// allocated when all pages are full. Once the maximum amount of pages is reached, the page that
+/// was least recently used is cleared and reused: this increments its generation, which
+/// invalidates all [`AtlasSlot`]s pointing into it. Pages used in the current or the previous
+/// frame are never recycled, as texts drawn every frame are likely drawn later in this frame
+/// again, and would otherwise flicker while they are rendered anew.
+///
+/// Each slot is surrounded by a transparent border that is uploaded together with the text, so
+/// inserting only uploads the region of the new slot and neither the rest of the page nor the
+/// previous content of a recycled page can bleed into it.
+pub(crate) struct TextAtlas {
+    pages: Vec<AtlasPage>,
+    page_size: u32,
+    max_pages: usize,
+}
+
+impl TextAtlas {
+    /// The transparent border around each slot, so that linear sampling does not bleed into the
+    /// neighbours
+    const PADDING: u32 = 1;
+
+    pub(crate) fn new(page_size: u32, max_pages: usize) -> Self {
+        Self {
+            pages: Vec::default(),
+            page_size,
+            max_pages,
+        }
+    }
+
+    /// Whether text of the given size can be stored in the atlas at all.
+    #[inline]
+    pub(crate) fn fits(&self, size: [u32; 2]) -> bool {
+        let [width, height] = Self::padded(size);
+        self.max_pages > 0 && width <= self.page_size && height <= self.page_size
+    }
+
+    /// The size of the content with the border on all sides.
+    #[inline]
+    fn padded([width, height]: [u32; 2]) -> [u32; 2] {
+        [width + 2 * Self::PADDING, height + 2 * Self::PADDING]
+    }
+
+    /// The content surrounded by the transparent border.
+    fn pad(rgba: &[u8], [width, height]: [u32; 2]) -> Vec<u8> {
+        let [padded_width, padded_height] = Self::padded([width, height]);
+        let row = width as usize * 4;
+        let padded_row = padded_width as usize * 4;
+        let padding = Self::PADDING as usize * 4;
+        let mut padded = vec![0; padded_row * padded_height as usize];
+        for (y, source) in rgba.chunks_exact(row).take(height as usize).enumerate() {
+            let start = (y + Self::PADDING as usize) * padded_row + padding;
+            padded[start..start + row].copy_from_slice(source);
+        }
+        padded
+    }
+
+    #[inline]
+    pub(crate) fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Whether the slot still points to its content and marks the page as used in the frame.
+    pub(crate) fn touch(&mut self, slot: &AtlasSlot, frame: u64) -> bool {
+        match self.pages.get_mut(slot.page) {
+            Some(page) if page.generation == slot.generation => {
+                page.last_used = frame;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the upload of the content of the slot was not processed yet.
+    pub(crate) fn is_pending(&self, image_system: &ImageSystem, slot: &AtlasSlot) -> bool {
+        self.pages
+            .get(slot.page)
+            .is_some_and(|page| image_system.is_region_pending(&page.image, slot.pos, slot.size))
+    }
+
+    /// The texture of the page and the uv coordinates of the slot.
+    pub(crate) fn texture_and_uv(
+        &self,
+        slot: &AtlasSlot,
+    ) -> Option<(&TextureId<TexturedPipeline>, [f32; 2], [f32; 2])> {
+        let page = self.pages.get(slot.page)?;
+        let size = self.page_size as f32;
+        Some((
+            &page.texture,
+            [slot.pos[0] as f32 / size, slot.pos[1] as f32 / size],
+            [
+                (slot.pos[0] + slot.size[0]) as f32 / size,
+                (slot.pos[1] + slot.size[1]) as f32 / size,
+            ],
+        ))
+    }
+
+    /// Stores the rgba data in the atlas, growing it or recycling the least recently used page
+    /// if necessary. [`None`] if it does not fit, because all pages are in use in this or the
+    /// previous frame.
+    pub(crate) fn insert(
+        &mut self,
+        textured_pipeline: &TexturedPipeline,
+        image_system: &ImageSystem,
+        rgba: &[u8],
+        size: [u32; 2],
+        frame: u64,
+    ) -> Result<Option<AtlasSlot>, UploadError> {
+        if !self.fits(size) {
+            return Ok(None);
+        }
+
+        let page_size = self.page_size;
+        let found = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, page)| Some((index, page.allocate(page_size, size)?)));
+
+        let (index, pos) = match found {
+            Some(found) => found,
+            None if self.pages.len() < self.max_pages => {
+                let mut page = self.create_page(textured_pipeline, image_system)?;
+                let pos = page.allocate(page_size, size);
+                self.pages.push(page);
+                match pos {
+                    Some(pos) => (self.pages.len() - 1, pos),
+                    None => return Ok(None),
+                }
+            }
+            None => {
+                // the slots of a page used in this or the previous frame are still to be drawn
+                let Some((index, page)) = self
+                    .pages
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(_, page)| page.last_used.saturating_add(1) < frame)
+                    .min_by_key(|(_, page)| page.last_used)
+                else {
+                    return Ok(None);
+                };
+                debug!(
+                    "Text atlas is full, recycling page {index} (generation {})",
+                    page.generation
+                );
+                // the previous content is overwritten by the padded uploads of the new slots
+                page.clear();
+                match page.allocate(page_size, size) {
+                    Some(pos) => (index, pos),
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        let page = &mut self.pages[index];
+        page.last_used = frame;
+        image_system.enqueue_image_update_with_priority(
+            Arc::clone(&page.image),
+            Some((pos, Self::padded(size))),
+            Self::pad(rgba, size),
+            UploadPriority::High,
+        )?;
+
+        Ok(Some(AtlasSlot {
+            page: index,
+            generation: page.generation,
+            pos: [pos[0] + Self::PADDING, pos[1] + Self::PADDING],
+            size,
+        }))
+    }
+
+    fn create_page(
+        &self,
+        textured_pipeline: &TexturedPipeline,
+        image_system: &ImageSystem,
+    ) -> Result<AtlasPage, UploadError> {
+        let size = self.page_size;
+        // not uploaded as a whole, only the padded slots are ever sampled
+        let image = image_system.create_image(size, size)?;
+        let texture = textured_pipeline.prepare_texture(Arc::clone(&image))?;
+        debug!(
+            "Allocated text atlas page {} ({size}x{size})",
+            self.pages.len()
+        );
+        Ok(AtlasPage {
+            image,
+            texture,
+            generation: 0,
+            shelves: Vec::default(),
+            next_shelf_y: 0,
+            last_used: 0,
+        })
+    }
+}
@@ -0,0 +1,160 @@
+use std::ops::Range;
+
+/// The position of a cluster (a character including its combining marks) within a rendered line
+/// of text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterMetrics {
+    /// The bytes of the cluster in the text
+    pub bytes: Range<usize>,
+    /// The distance from the start of the line to the left edge of the cluster, in pixels
+    pub x: f32,
+    /// The horizontal advance of the cluster, including the kerning to the previous cluster
+    pub advance: f32,
+}
+
+/// The cluster boundaries and glyph advances of a single line of text, to place a caret, select
+/// ranges and hit-test clicks in custom text fields. All byte indices refer to the UTF-8 encoded
+/// text and positions between clusters are snapped to the nearest cluster boundary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextMetrics {
+    clusters: Vec<ClusterMetrics>,
+    width: f32,
+    height: f32,
+}
+
+impl TextMetrics {
+    /// Measures the text with a function returning the width of a prefix of the text, which
+    /// includes kerning and ligatures of the font.
+    pub fn measure(text: &str, height: f32, mut prefix_width: impl FnMut(&str) -> f32) -> Self {
+        let mut clusters = Vec::new();
+        let mut x = 0.0;
+        for bytes in cluster_ranges(text) {
+            let end_x = prefix_width(&text[..bytes.end]).max(x);
+            clusters.push(ClusterMetrics {
+                bytes,
+                x,
+                advance: end_x - x,
+            });
+            x = end_x;
+        }
+        Self {
+            clusters,
+            width: x,
+            height,
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn clusters(&self) -> &[ClusterMetrics] {
+        &self.clusters
+    }
+
+    /// The byte length of the measured text.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.clusters.last().map_or(0, |cluster| cluster.bytes.end)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.clusters.is_empty()
+    }
+
+    /// The index of the cluster containing the byte, or the amount of clusters for the end of
+    /// the text.
+    fn cluster_index(&self, byte: usize) -> usize {
+        self.clusters
+            .partition_point(|cluster| cluster.bytes.end <= byte)
+    }
+
+    /// The byte index of the cluster boundary at or before the given byte.
+    pub fn snap(&self, byte: usize) -> usize {
+        self.clusters
+            .get(self.cluster_index(byte))
+            .map_or(self.len(), |cluster| cluster.bytes.start)
+    }
+
+    /// The x position of a caret placed before the given byte.
+    pub fn caret_x(&self, byte: usize) -> f32 {
+        self.clusters
+            .get(self.cluster_index(byte))
+            .map_or(self.width, |cluster| cluster.x)
+    }
+
+    /// The byte index of the next cluster boundary, to move the caret to the right.
+    pub fn next_boundary(&self, byte: usize) -> usize {
+        self.clusters
+            .get(self.cluster_index(byte))
+            .map_or(self.len(), |cluster| cluster.bytes.end)
+    }
+
+    /// The byte index of the previous cluster boundary, to move the caret to the left.
+    pub fn prev_boundary(&self, byte: usize) -> usize {
+        let index = self.cluster_index(byte);
+        let index = match self.clusters.get(index) {
+            // the byte is in the middle of a cluster, the start of this cluster is the boundary
+            Some(cluster) if cluster.bytes.start < byte => index + 1,
+            _ => index,
+        };
+        index
+            .checked_sub(1)
+            .and_then(|index| self.clusters.get(index))
+            .map_or(0, |cluster| cluster.bytes.start)
+    }
+
+    /// The byte index of the cluster boundary closest to the x position, for example of a click.
+    pub fn hit_test(&self, x: f32) -> usize {
+        self.clusters
+            .iter()
+            .find(|cluster| x < cluster.x + cluster.advance * 0.5)
+            .map_or(self.len(), |cluster| cluster.bytes.start)
+    }
+
+    /// The horizontal extent of the selection between the two byte indices, in any order.
+    pub fn selection_x(&self, a: usize, b: usize) -> Range<f32> {
+        let (start, end) = (self.caret_x(a.min(b)), self.caret_x(a.max(b)));
+        start..end
+    }
+}
+
+/// Splits the text into clusters of a character and the following combining characters, zero
+/// width joiners and variation selectors. This is an approximation of extended grapheme clusters
+/// that covers accents and most emoji sequences.
+pub fn cluster_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut clusters: Vec<Range<usize>> = Vec::new();
+    let mut joined = false;
+    for (index, c) in text.char_indices() {
+        let end = index + c.len_utf8();
+        match clusters.last_mut() {
+            Some(last) if joined || extends_cluster(c) => last.end = end,
+            _ => clusters.push(index..end),
+        }
+        joined = c == '\u{200D}';
+    }
+    clusters
+}
+
+fn extends_cluster(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{200D}'
+            | '\u{1F3FB}'..='\u{1F3FF}'
+            | '\u{E0020}'..='\u{E007F}'
+    )
+}
@@ -1,5 +1,14 @@
-use crate::engine::system::vulkan::textured::{Textured, TexturedPipeline, Vertex2dUv};
-use crate::engine::system::vulkan::textures::{ImageSystem, TextureId};
+mod atlas;
+mod metrics;
+
+pub use metrics::*;
+
+use crate::engine::system::ttf::atlas::{AtlasSlot, TextAtlas};
+use crate::engine::system::vulkan::pipelines::VulkanPipelines;
+use crate::engine::system::vulkan::system::RenderContext;
+use crate::engine::system::vulkan::textured::{Textured, TexturedPipeline, TexturedQuad};
+use crate::engine::system::vulkan::textures::{ImageSystem, TextureId, UploadPriority};
+use crate::engine::system::vulkan::UploadError;
 use crossbeam::channel::Receiver;
 use crossbeam::channel::Sender;
 use crossbeam::queue::SegQueue;
@@ -11,149 +20,281 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
 
-type CacheUpdate = (String, Vec<u8>, u32, u32);
+/// The size and the color a text is rendered with
+type TextStyle = (u16, [u8; 4]);
+type CacheUpdate = (TextStyle, String, Vec<u8>, u32, u32);
+type MetricsUpdate = (String, u16, TextMetrics);
+
+enum TextContent {
+    /// The text was submitted to the render thread, but the result was not received yet
+    Pending,
+    Atlas(AtlasSlot),
+    /// The text is too large for the [`TextAtlas`] and has its own texture
+    Texture(TextureId<TexturedPipeline>),
+}
+
+struct CachedText {
+    content: TextContent,
+    width: f32,
+    height: f32,
+    counter: u8,
+}
+
+/// Where the [`FontRenderer`] creates the textures of the rendered texts, so that its API does not
+/// depend on the pipelines and the image uploads of the backend.
+#[derive(Copy, Clone)]
+pub struct TextTextures<'a> {
+    pipeline: &'a TexturedPipeline,
+    images: &'a ImageSystem,
+}
+
+impl<'a> TextTextures<'a> {
+    /// The textures of the [`TexturedPipeline`], uploaded with the frame of the `ctx`.
+    #[inline]
+    pub fn new(pipelines: &'a VulkanPipelines, ctx: &'a RenderContext) -> Self {
+        Self {
+            pipeline: &pipelines.texture,
+            images: ctx.image_system(),
+        }
+    }
+}
 
 pub struct FontRenderer {
-    dummy_image: Option<TextureId<TexturedPipeline>>,
-    cache: FxHashMap<String, (TextureId<TexturedPipeline>, f32, f32, u8)>,
+    /// The same text is rendered separately for every size and color
+    cache: FxHashMap<TextStyle, FxHashMap<String, CachedText>>,
+    metrics: FxHashMap<(String, u16), (Option<TextMetrics>, u8)>,
+    atlas: TextAtlas,
+    frame: u64,
     sender: Sender<FontRenderRequest>,
     update_queue: Arc<SegQueue<CacheUpdate>>,
+    metrics_queue: Arc<SegQueue<MetricsUpdate>>,
 }
 
 impl FontRenderer {
     const DUMMY_TEXTURE_WIDTH: u32 = 1;
     const DUMMY_TEXTURE_HEIGHT: u32 = 1;
-    const DUMMY_TEXTURE_RGBA: [u8; 4] = [0, 0, 0, 0];
     const DEFAULT_LAST_USED_COUNTER: u8 = 0;
+    const DEFAULT_ATLAS_PAGE_SIZE: u32 = 1024;
+    const DEFAULT_ATLAS_MAX_PAGES: usize = 4;
 
     pub fn new(ttf: Cow<'static, [u8]>) -> Self {
         let update_queue = Arc::default();
-        let sender = FontRendererThread::spawn(ttf, Arc::clone(&update_queue));
+        let metrics_queue = Arc::default();
+        let sender =
+            FontRendererThread::spawn(ttf, Arc::clone(&update_queue), Arc::clone(&metrics_queue));
 
         Self {
-            dummy_image: None,
             cache: FxHashMap::default(),
+            metrics: FxHashMap::default(),
+            atlas: TextAtlas::new(Self::DEFAULT_ATLAS_PAGE_SIZE, Self::DEFAULT_ATLAS_MAX_PAGES),
+            frame: 0,
             sender,
             update_queue,
+            metrics_queue,
         }
     }
 
+    /// The rendered texts are packed into atlas pages of `page_size` x `page_size` pixels. Once
+    /// `max_pages` are full, the least recently used page is cleared and the texts on it are
+    /// rendered again when they are used the next time. Texts that do not fit into a page get
+    /// a texture of their own.
+    #[inline]
+    pub fn with_atlas(mut self, page_size: u32, max_pages: usize) -> Self {
+        self.atlas = TextAtlas::new(page_size, max_pages);
+        self.cache.clear();
+        self
+    }
+
+    /// The amount of atlas pages that are currently allocated.
+    #[inline]
+    pub fn atlas_page_count(&self) -> usize {
+        self.atlas.page_count()
+    }
+
     pub fn on_frame_completed(&mut self) {
-        let mut remove = Vec::default();
-        for (key, (_, _, _, counter)) in self.cache.iter_mut() {
-            if *counter > 254 {
-                remove.push(key.clone());
-            } else {
-                *counter += 1;
-            }
+        self.frame += 1;
+        self.cache.retain(|_, texts| {
+            texts.retain(|_, cached| {
+                if cached.counter > 254 {
+                    false
+                } else {
+                    cached.counter += 1;
+                    true
+                }
+            });
+            !texts.is_empty()
+        });
+        self.metrics.retain(|_, (_, counter)| {
+            *counter = counter.saturating_add(1);
+            *counter < u8::MAX
+        });
+    }
+
+    /// The cluster boundaries and advances of the text when rendered with the given size. Like
+    /// the rendering itself, the text is measured by the render thread: until the result was
+    /// received, [`None`] is returned.
+    pub fn text_metrics(&mut self, text: &str, size: u16) -> Option<&TextMetrics> {
+        while let Some((text, size, metrics)) = self.metrics_queue.pop() {
+            self.metrics.insert(
+                (text, size),
+                (Some(metrics), Self::DEFAULT_LAST_USED_COUNTER),
+            );
         }
-        for key in remove {
-            self.cache.remove(&key);
+
+        let key = (text.to_string(), size);
+        if !self.metrics.contains_key(&key) {
+            if let Err(e) = self.sender.send(FontRenderRequest::Measure {
+                size,
+                text: text.to_string(),
+            }) {
+                error!("Failed to send FontRenderRequest: {e}");
+            }
         }
+
+        let (metrics, counter) = self
+            .metrics
+            .entry(key)
+            .or_insert((None, Self::DEFAULT_LAST_USED_COUNTER));
+        *counter = Self::DEFAULT_LAST_USED_COUNTER;
+        metrics.as_ref()
     }
 
     #[must_use]
-    #[instrument(level = "trace", skip(self, textured_pipeline, image_system))]
+    #[instrument(level = "trace", skip(self, textures))]
     pub fn prepare_render(
         &mut self,
-        textured_pipeline: &TexturedPipeline,
-        image_system: &ImageSystem,
+        textures: &TextTextures,
         text: &str,
         size: u16,
         color: [u8; 4],
         x: f32,
         y: f32,
     ) -> Textured {
+        let quad = self.prepare_quad(textures, text, size, color, [x, y]);
+        let [v0, v1, v2, v3] = quad.vertices;
+        Textured {
+            vertices: vec![v0, v1, v2, v2, v3, v0],
+            texture: quad.texture,
+        }
+    }
+
+    /// Like [`FontRenderer::prepare_render`], but as a [`TexturedQuad`] for
+    /// [`TexturedPipeline::draw_quads`], which draws consecutive texts on the same atlas page with
+    /// a single draw call.
+    #[must_use]
+    #[instrument(level = "trace", skip(self, textures))]
+    pub fn prepare_quad(
+        &mut self,
+        textures: &TextTextures,
+        text: &str,
+        size: u16,
+        color: [u8; 4],
+        pos: [f32; 2],
+    ) -> TexturedQuad {
+        let TextTextures {
+            pipeline: textured_pipeline,
+            images: image_system,
+        } = *textures;
         self.retrieve_threaded_updates(textured_pipeline, image_system);
 
-        let (texture, w, h) = match self.cache.get_mut(text) {
+        let dummy = || {
+            (
+                textured_pipeline.placeholders().transparent().clone(),
+                Self::DUMMY_TEXTURE_WIDTH as f32,
+                Self::DUMMY_TEXTURE_HEIGHT as f32,
+                [0.0, 0.0],
+                [1.0, 1.0],
+            )
+        };
+
+        let cached = self
+            .cache
+            .get_mut(&(size, color))
+            .and_then(|texts| texts.get_mut(text));
+        let (texture, w, h, [u0, v0], [u1, v1]) = match cached {
             // Fine, it already exists, just reset the counter
-            Some((texture_id, w, h, counter)) => {
-                *counter = Self::DEFAULT_LAST_USED_COUNTER;
-                (texture_id.clone(), *w, *h)
+            Some(cached) => {
+                cached.counter = Self::DEFAULT_LAST_USED_COUNTER;
+                match &cached.content {
+                    TextContent::Pending => dummy(),
+                    // the content is undefined until the upload was processed
+                    TextContent::Texture(texture)
+                        if image_system.is_upload_pending(texture.image()) =>
+                    {
+                        dummy()
+                    }
+                    TextContent::Texture(texture) => (
+                        texture.clone(),
+                        cached.width,
+                        cached.height,
+                        [0.0, 0.0],
+                        [1.0, 1.0],
+                    ),
+                    TextContent::Atlas(slot) if self.atlas.touch(slot, self.frame) => {
+                        match self.atlas.texture_and_uv(slot) {
+                            // only the region of the slot is uploaded, the rest of the page
+                            // stays visible meanwhile
+                            _ if self.atlas.is_pending(image_system, slot) => dummy(),
+                            Some((texture, uv0, uv1)) => {
+                                (texture.clone(), cached.width, cached.height, uv0, uv1)
+                            }
+                            None => dummy(),
+                        }
+                    }
+                    // The atlas page was recycled in the meantime, the text has to be rendered
+                    // again
+                    TextContent::Atlas(_) => {
+                        cached.content = TextContent::Pending;
+                        Self::request_render(&self.sender, text, size, color);
+                        dummy()
+                    }
+                }
             }
             // In this scenario, the text is submitted for rendering to the separate thread while
-            // this context continues on returning a `Textured` instance with a dummy texture.
+            // this context continues on returning a quad with a transparent
+            // placeholder texture.
             None => {
-                if let Err(e) = self.sender.send(FontRenderRequest {
-                    size,
-                    color,
-                    text: text.to_string(),
-                }) {
-                    error!("Failed to send FontRenderRequest: {e}");
-                }
-
-                let dummy_texture =
-                    self.get_or_create_dummy_texture(textured_pipeline, image_system);
-
-                self.cache.insert(
+                Self::request_render(&self.sender, text, size, color);
+                self.cache.entry((size, color)).or_default().insert(
                     text.to_string(),
-                    (
-                        dummy_texture.clone(),
-                        Self::DUMMY_TEXTURE_WIDTH as f32,
-                        Self::DUMMY_TEXTURE_HEIGHT as f32,
-                        Self::DEFAULT_LAST_USED_COUNTER,
-                    ),
+                    CachedText {
+                        content: TextContent::Pending,
+                        width: Self::DUMMY_TEXTURE_WIDTH as f32,
+                        height: Self::DUMMY_TEXTURE_HEIGHT as f32,
+                        counter: Self::DEFAULT_LAST_USED_COUNTER,
+                    },
                 );
-
-                (
-                    dummy_texture,
-                    Self::DUMMY_TEXTURE_WIDTH as f32,
-                    Self::DUMMY_TEXTURE_HEIGHT as f32,
-                )
+                dummy()
             }
         };
 
-        Textured {
-            vertices: vec![
-                Vertex2dUv {
-                    pos: [x, y],
-                    uv: [0.0, 0.0],
-                },
-                Vertex2dUv {
-                    pos: [x + w, y],
-                    uv: [1.0, 0.0],
-                },
-                Vertex2dUv {
-                    pos: [x + w, y + h],
-                    uv: [1.0, 1.0],
-                },
-                Vertex2dUv {
-                    pos: [x + w, y + h],
-                    uv: [1.0, 1.0],
-                },
-                Vertex2dUv {
-                    pos: [x, y + h],
-                    uv: [0.0, 1.0],
-                },
-                Vertex2dUv {
-                    pos: [x, y],
-                    uv: [0.0, 0.0],
-                },
-            ],
-            texture,
+        TexturedQuad::new(pos, [w, h], texture).with_uv([u0, v0], [u1, v1])
+    }
+
+    fn request_render(sender: &Sender<FontRenderRequest>, text: &str, size: u16, color: [u8; 4]) {
+        if let Err(e) = sender.send(FontRenderRequest::Render {
+            size,
+            color,
+            text: text.to_string(),
+        }) {
+            error!("Failed to send FontRenderRequest: {e}");
         }
     }
 
-    fn get_or_create_dummy_texture(
-        &mut self,
+    fn create_texture(
         textured_pipeline: &TexturedPipeline,
         image_system: &ImageSystem,
-    ) -> TextureId<TexturedPipeline> {
-        self.dummy_image.clone().unwrap_or_else(|| {
-            let image = image_system
-                .create_image_and_enqueue_upload(
-                    Self::DUMMY_TEXTURE_RGBA,
-                    Self::DUMMY_TEXTURE_WIDTH,
-                    Self::DUMMY_TEXTURE_HEIGHT,
-                )
-                .unwrap();
-
-            let texture = textured_pipeline.prepare_texture(image).unwrap();
-
-            self.dummy_image = Some(texture.clone());
-            texture
-        })
+        rgba: Vec<u8>,
+        [width, height]: [u32; 2],
+    ) -> Result<TextureId<TexturedPipeline>, UploadError> {
+        // text is expected to show up immediately, instead of the missing placeholder
+        let image = image_system.create_image_and_enqueue_upload_with_priority(
+            rgba,
+            width,
+            height,
+            UploadPriority::High,
+        )?;
+        Ok(textured_pipeline.prepare_texture(image)?)
     }
 
     fn retrieve_threaded_updates(
@@ -161,20 +302,61 @@ impl FontRenderer {
         textured_pipeline: &TexturedPipeline,
         image_system: &ImageSystem,
     ) {
-        while let Some((text, image_data, w, h)) = self.update_queue.pop() {
-            let image = image_system
-                .create_image_and_enqueue_upload(image_data, w, h)
-                .unwrap();
-            let texture = textured_pipeline.prepare_texture(image).unwrap();
-            self.cache.insert(text, (texture, w as f32, h as f32, 0));
+        while let Some((style, text, image_data, w, h)) = self.update_queue.pop() {
+            let slot = if self.atlas.fits([w, h]) {
+                match self.atlas.insert(
+                    textured_pipeline,
+                    image_system,
+                    &image_data,
+                    [w, h],
+                    self.frame,
+                ) {
+                    Ok(slot) => slot,
+                    Err(e) => {
+                        error!("Failed to insert text into the atlas: {e}");
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+            let content = match slot {
+                Some(slot) => TextContent::Atlas(slot),
+                // too large for the atlas or all of its pages are in use in this frame
+                None => {
+                    match Self::create_texture(textured_pipeline, image_system, image_data, [w, h])
+                    {
+                        Ok(texture) => TextContent::Texture(texture),
+                        Err(e) => {
+                            error!("Failed to upload the text: {e}");
+                            continue;
+                        }
+                    }
+                }
+            };
+            self.cache.entry(style).or_default().insert(
+                text,
+                CachedText {
+                    content,
+                    width: w as f32,
+                    height: h as f32,
+                    counter: Self::DEFAULT_LAST_USED_COUNTER,
+                },
+            );
         }
     }
 }
 
-struct FontRenderRequest {
-    size: u16,
-    color: [u8; 4],
-    text: String,
+enum FontRenderRequest {
+    Render {
+        size: u16,
+        color: [u8; 4],
+        text: String,
+    },
+    Measure {
+        size: u16,
+        text: String,
+    },
 }
 
 struct FontRendererThread<'a> {
@@ -183,12 +365,14 @@ struct FontRendererThread<'a> {
     fonts: FxHashMap<u16, Font<'a, 'a>>,
     receiver: Receiver<FontRenderRequest>,
     result_queue: Arc<SegQueue<CacheUpdate>>,
+    metrics_queue: Arc<SegQueue<MetricsUpdate>>,
 }
 
 impl<'a> FontRendererThread<'a> {
     pub fn spawn(
         ttf: Cow<'static, [u8]>,
         result_queue: Arc<SegQueue<CacheUpdate>>,
+        metrics_queue: Arc<SegQueue<MetricsUpdate>>,
     ) -> Sender<FontRenderRequest> {
         let (sender, receiver) = crossbeam::channel::unbounded();
         if let Err(e) = std::thread::Builder::new()
@@ -201,6 +385,7 @@ impl<'a> FontRendererThread<'a> {
                     fonts: HashMap::default(),
                     receiver,
                     result_queue,
+                    metrics_queue,
                 }
                 .run()
             })
@@ -212,12 +397,18 @@ impl<'a> FontRendererThread<'a> {
 
     fn run(mut self) {
         while let Ok(request) = self.receiver.recv() {
-            self.process_request(request.text, request.size, request.color);
+            match request {
+                FontRenderRequest::Render { size, color, text } => {
+                    self.process_request(text, size, color)
+                }
+                FontRenderRequest::Measure { size, text } => self.process_measure(text, size),
+            }
         }
     }
 
     #[instrument(level = "info", skip(self))]
-    fn process_request(&mut self, text: String, size: u16, [r, g, b, a]: [u8; 4]) {
+    fn process_request(&mut self, text: String, size: u16, color: [u8; 4]) {
+        let [r, g, b, a] = color;
         let font = self
             .fonts
             .entry(size)
@@ -231,7 +422,21 @@ impl<'a> FontRendererThread<'a> {
         let w = surface.width();
         let h = surface.height();
 
-        self.result_queue.push((text, data, w, h));
+        self.result_queue.push(((size, color), text, data, w, h));
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    fn process_measure(&mut self, text: String, size: u16) {
+        let font = self
+            .fonts
+            .entry(size)
+            .or_insert_with(|| Self::load_font_for_size(self.ctx, self.ttf, size));
+
+        let metrics = TextMetrics::measure(&text, font.height() as f32, |prefix| {
+            font.size_of(prefix).map_or(0.0, |(width, _)| width as f32)
+        });
+
+        self.metrics_queue.push((text, size, metrics));
     }
 
     #[instrument(level = "info", skip(ctx, data))]
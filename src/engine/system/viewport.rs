@@ -0,0 +1,292 @@
+use vulkano::command_buffer::ClearRect;
+use vulkano::pipeline::graphics::viewport::{Scissor, Viewport};
+
+/// Where the world is rendered within the window: an optional [`AspectRatio`] that is enforced
+/// with pillarbox or letterbox bars and a [`SplitScreen`] that divides the window into several
+/// viewports, which are letterboxed individually. See [`ViewportLayout::viewports`].
+///
+/// The bars are cleared with [`ViewportLayout::bar_color`], the viewports with the clear value
+/// of the [`VulkanSystem`]. The [`WindowSize`] uniform is the size of a single viewport, so
+/// pipelines render into a viewport as if it were the whole window. egui is drawn over the whole
+/// window regardless of the layout.
+///
+/// [`VulkanSystem`]: crate::engine::system::vulkan::system::VulkanSystem
+/// [`WindowSize`]: crate::engine::system::vulkan::desc::binding_101_window_size::WindowSize
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize),
+    serde(default)
+)]
+pub struct ViewportLayout {
+    /// [`None`] to fill the window (or the cell of the [`SplitScreen`])
+    pub aspect_ratio: Option<AspectRatio>,
+    pub split_screen: SplitScreen,
+    pub bar_color: [f32; 4],
+}
+
+impl Default for ViewportLayout {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            aspect_ratio: None,
+            split_screen: SplitScreen::Single,
+            bar_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl ViewportLayout {
+    /// A single viewport with the given aspect ratio.
+    #[inline]
+    pub fn letterboxed(aspect_ratio: AspectRatio) -> Self {
+        Self::default().with_aspect_ratio(Some(aspect_ratio))
+    }
+
+    #[inline]
+    pub fn with_aspect_ratio(mut self, aspect_ratio: Option<AspectRatio>) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    #[inline]
+    pub fn with_split_screen(mut self, split_screen: SplitScreen) -> Self {
+        self.split_screen = split_screen;
+        self
+    }
+
+    #[inline]
+    pub fn with_bar_color(mut self, rgba: [f32; 4]) -> Self {
+        self.bar_color = rgba;
+        self
+    }
+
+    /// Whether a single viewport covers the whole window, so there are no bars to draw.
+    #[inline]
+    pub fn is_fullscreen(&self) -> bool {
+        self.aspect_ratio.is_none() && self.split_screen.count() == 1
+    }
+
+    /// The viewports for a render target of the given size, in the order of the
+    /// [`SplitScreen`]. All viewports have the same size.
+    pub fn viewports(&self, extent: [u32; 2]) -> Vec<ViewportRect> {
+        self.split_screen
+            .cells(extent)
+            .into_iter()
+            .map(|cell| cell.letterboxed(self.aspect_ratio))
+            .collect()
+    }
+
+    /// The viewport at the position of the render target, [`None`] over the bars.
+    pub fn locate(&self, extent: [u32; 2], pos: [f32; 2]) -> Option<ViewportPos> {
+        self.viewports(extent)
+            .into_iter()
+            .enumerate()
+            .find_map(|(index, viewport)| {
+                viewport.to_local(pos).map(|pos| ViewportPos { index, pos })
+            })
+    }
+}
+
+/// The ratio of the width to the height, like `16:9`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct AspectRatio {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AspectRatio {
+    pub const RATIO_4_3: AspectRatio = AspectRatio::new(4, 3);
+    pub const RATIO_16_9: AspectRatio = AspectRatio::new(16, 9);
+    pub const RATIO_16_10: AspectRatio = AspectRatio::new(16, 10);
+    pub const RATIO_21_9: AspectRatio = AspectRatio::new(21, 9);
+
+    /// Zero is treated as one.
+    #[inline]
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self {
+            width: if width == 0 { 1 } else { width },
+            height: if height == 0 { 1 } else { height },
+        }
+    }
+
+    #[inline]
+    pub fn ratio(&self) -> f32 {
+        self.width.max(1) as f32 / self.height.max(1) as f32
+    }
+}
+
+/// How the window is divided into viewports for local multiplayer.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum SplitScreen {
+    #[default]
+    Single,
+    /// The given amount of viewports side by side, from left to right
+    Horizontal(u32),
+    /// The given amount of viewports above each other, from top to bottom
+    Vertical(u32),
+    /// The viewports row by row, from the top left to the bottom right
+    Grid { columns: u32, rows: u32 },
+}
+
+impl SplitScreen {
+    /// The columns and the rows, at least one each.
+    #[inline]
+    pub fn dimensions(&self) -> [u32; 2] {
+        match *self {
+            SplitScreen::Single => [1, 1],
+            SplitScreen::Horizontal(count) => [count.max(1), 1],
+            SplitScreen::Vertical(count) => [1, count.max(1)],
+            SplitScreen::Grid { columns, rows } => [columns.max(1), rows.max(1)],
+        }
+    }
+
+    /// The amount of viewports.
+    #[inline]
+    pub fn count(&self) -> u32 {
+        let [columns, rows] = self.dimensions();
+        columns * rows
+    }
+
+    /// The cells of the render target before letterboxing, the remainder of the division is
+    /// left to the last column and row.
+    pub fn cells(&self, [width, height]: [u32; 2]) -> Vec<ViewportRect> {
+        let [columns, rows] = self.dimensions();
+        let (cell_width, cell_height) = (width / columns, height / rows);
+        (0..rows)
+            .flat_map(|row| {
+                (0..columns).map(move |column| ViewportRect {
+                    x: column * cell_width,
+                    y: row * cell_height,
+                    width: cell_width.max(1),
+                    height: cell_height.max(1),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A rectangle of the render target in pixels.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ViewportRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ViewportRect {
+    /// The whole render target of the given size.
+    #[inline]
+    pub fn full([width, height]: [u32; 2]) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    #[inline]
+    pub fn extent(&self) -> [u32; 2] {
+        [self.width, self.height]
+    }
+
+    /// The largest rectangle of the aspect ratio centered within this one, this one without an
+    /// aspect ratio.
+    pub fn letterboxed(&self, aspect_ratio: Option<AspectRatio>) -> Self {
+        let Some(aspect_ratio) = aspect_ratio else {
+            return *self;
+        };
+        let ratio = aspect_ratio.ratio();
+        let (width, height) = if (self.width as f32) > self.height as f32 * ratio {
+            // pillarbox: bars left and right
+            ((self.height as f32 * ratio).round() as u32, self.height)
+        } else {
+            // letterbox: bars above and below
+            (self.width, (self.width as f32 / ratio).round() as u32)
+        };
+        let (width, height) = (width.clamp(1, self.width), height.clamp(1, self.height));
+        Self {
+            x: self.x + (self.width - width) / 2,
+            y: self.y + (self.height - height) / 2,
+            width,
+            height,
+        }
+    }
+
+    #[inline]
+    pub fn contains(&self, [x, y]: [f32; 2]) -> bool {
+        x >= self.x as f32
+            && y >= self.y as f32
+            && x < (self.x + self.width) as f32
+            && y < (self.y + self.height) as f32
+    }
+
+    /// The position of the render target relative to the top left corner of this viewport,
+    /// [`None`] if it is outside.
+    #[inline]
+    pub fn to_local(&self, pos: [f32; 2]) -> Option<[f32; 2]> {
+        self.contains(pos)
+            .then(|| [pos[0] - self.x as f32, pos[1] - self.y as f32])
+    }
+
+    /// Like [`ViewportRect::to_local`], but positions outside are moved onto the nearest edge,
+    /// for example for a cursor over the bars.
+    #[inline]
+    pub fn to_local_clamped(&self, pos: [f32; 2]) -> [f32; 2] {
+        [
+            (pos[0] - self.x as f32).clamp(0.0, self.width as f32),
+            (pos[1] - self.y as f32).clamp(0.0, self.height as f32),
+        ]
+    }
+
+    /// The position relative to this viewport as a position of the render target.
+    #[inline]
+    pub fn to_target(&self, local: [f32; 2]) -> [f32; 2] {
+        [local[0] + self.x as f32, local[1] + self.y as f32]
+    }
+
+    #[inline]
+    pub fn viewport(&self) -> Viewport {
+        Viewport {
+            offset: [self.x as f32, self.y as f32],
+            extent: [self.width as f32, self.height as f32],
+            depth_range: 0.0..=1.0,
+        }
+    }
+
+    #[inline]
+    pub fn scissor(&self) -> Scissor {
+        Scissor {
+            offset: [self.x, self.y],
+            extent: [self.width, self.height],
+        }
+    }
+
+    #[inline]
+    pub(crate) fn clear_rect(&self) -> ClearRect {
+        ClearRect {
+            offset: [self.x, self.y],
+            extent: [self.width, self.height],
+            array_layers: 0..1,
+        }
+    }
+}
+
+/// A position within the viewport with the index, see [`ViewportLayout::locate`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ViewportPos {
+    /// The index of the viewport in [`ViewportLayout::viewports`]
+    pub index: usize,
+    /// Relative to the top left corner of the viewport, in pixels
+    pub pos: [f32; 2],
+}
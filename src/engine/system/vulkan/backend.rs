@@ -0,0 +1,128 @@
+//! The [`Vulkan`] backend of the crate-owned handles, see the
+//! [`backend`](crate::engine::system::backend) module.
+
+use crate::engine::system::backend::RenderBackend;
+use crate::engine::system::commands::{CommandRecorder, RecordedCommands};
+use crate::engine::system::vulkan::destruction::DeferredDestructionQueue;
+use crate::engine::system::vulkan::system::RenderContext;
+use crate::engine::system::vulkan::DrawError;
+use std::sync::Arc;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, SecondaryAutoCommandBuffer};
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::image::Image;
+
+/// Records with `vulkano` into secondary command buffers that are executed within the main
+/// render pass of the [`VulkanSystem`].
+///
+/// [`VulkanSystem`]: crate::engine::system::vulkan::system::VulkanSystem
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Vulkan;
+
+impl RenderBackend for Vulkan {
+    type Recorder = AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>;
+    type Commands = Arc<SecondaryAutoCommandBuffer>;
+    type Texture = VulkanTexture;
+    type Error = DrawError;
+
+    #[inline]
+    fn finish(recorder: Self::Recorder) -> Result<Self::Commands, Self::Error> {
+        recorder
+            .build()
+            .map_err(DrawError::FailedToBuildCommandBuffer)
+    }
+
+    #[inline]
+    fn texture_extent(texture: &Self::Texture) -> [u32; 2] {
+        let [width, height, _] = texture.image.extent();
+        [width, height]
+    }
+}
+
+impl Vulkan {
+    /// Wraps a builder of [`RenderContext::create_render_buffer_builder`], or of
+    /// [`RenderContext::create_viewport_buffer_builder`] together with
+    /// [`CommandRecorder::with_viewport`].
+    #[inline]
+    pub fn recorder(
+        builder: AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
+    ) -> CommandRecorder {
+        CommandRecorder::from_parts(builder, None)
+    }
+
+    /// The builder of the recorder, to draw into it with the pipelines of this backend.
+    #[inline]
+    pub fn builder(
+        recorder: &mut CommandRecorder,
+    ) -> &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer> {
+        recorder.recorder_mut()
+    }
+
+    /// Wraps commands that were recorded with `vulkano` directly.
+    #[inline]
+    pub fn commands(commands: Arc<SecondaryAutoCommandBuffer>) -> RecordedCommands {
+        RecordedCommands::from_inner(commands)
+    }
+
+    /// The command buffer to submit with [`VulkanSystem::render`].
+    ///
+    /// [`VulkanSystem::render`]: crate::engine::system::vulkan::system::VulkanSystem::render
+    #[inline]
+    pub fn command_buffer(commands: RecordedCommands) -> Arc<SecondaryAutoCommandBuffer> {
+        commands.into_inner()
+    }
+}
+
+impl CommandRecorder {
+    /// Records into the first viewport of the frame, see
+    /// [`RenderContext::create_render_buffer_builder`].
+    #[inline]
+    pub fn new(ctx: &RenderContext) -> Result<Self, DrawError> {
+        let builder = ctx
+            .create_render_buffer_builder()
+            .map_err(DrawError::FailedToCreateCommandBuffer)?;
+        Ok(Self::from_parts(builder, Some(ctx.viewport_rect())))
+    }
+}
+
+/// The image and the descriptor of a texture, which are released through the
+/// [`DeferredDestructionQueue`] once the last [`TextureId`] is dropped.
+///
+/// [`TextureId`]: crate::engine::system::vulkan::textures::TextureId
+pub struct VulkanTexture {
+    image: Arc<Image>,
+    descriptor: Arc<PersistentDescriptorSet>,
+    deferred_destruction: Arc<DeferredDestructionQueue>,
+}
+
+impl VulkanTexture {
+    #[inline]
+    pub(crate) fn new(
+        image: Arc<Image>,
+        descriptor: Arc<PersistentDescriptorSet>,
+        deferred_destruction: Arc<DeferredDestructionQueue>,
+    ) -> Self {
+        Self {
+            image,
+            descriptor,
+            deferred_destruction,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn image(&self) -> &Arc<Image> {
+        &self.image
+    }
+
+    #[inline]
+    pub(crate) fn descriptor(&self) -> &Arc<PersistentDescriptorSet> {
+        &self.descriptor
+    }
+}
+
+impl Drop for VulkanTexture {
+    fn drop(&mut self) {
+        // the GPU might still be sampling the texture in a frame in flight
+        self.deferred_destruction
+            .enqueue((Arc::clone(&self.image), Arc::clone(&self.descriptor)));
+    }
+}
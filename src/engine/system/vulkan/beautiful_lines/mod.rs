@@ -1,4 +1,5 @@
 use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
 use crate::engine::system::vulkan::system::{GraphicsPipelineRenderPassInfo, VulkanSystem};
 use crate::engine::system::vulkan::wds::WriteDescriptorSetManager;
 use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError};
@@ -9,11 +10,8 @@ use vulkano::command_buffer::AutoCommandBufferBuilder;
 use vulkano::descriptor_set::PersistentDescriptorSet;
 use vulkano::device::{Device, Features};
 use vulkano::pipeline::cache::PipelineCache;
-use vulkano::pipeline::graphics::color_blend::{
-    AttachmentBlend, ColorBlendAttachmentState, ColorBlendState,
-};
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendAttachmentState};
 use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
-use vulkano::pipeline::graphics::multisample::MultisampleState;
 use vulkano::pipeline::graphics::rasterization::RasterizationState;
 use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition};
 use vulkano::pipeline::graphics::viewport::ViewportState;
@@ -100,18 +98,17 @@ impl BeautifulLinePipeline {
                 }),
                 viewport_state: Some(ViewportState::default()),
                 rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState {
-                    rasterization_samples: render_pass_info.rasterization_samples(),
-                    ..MultisampleState::default()
-                }),
-                color_blend_state: Some(ColorBlendState::with_attachment_states(
-                    render_pass_info.num_color_attachments(),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(render_pass_info.color_blend_state(
                     ColorBlendAttachmentState {
                         blend: Some(AttachmentBlend::alpha()),
                         ..ColorBlendAttachmentState::default()
                     },
                 )),
-                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                    .into_iter()
+                    .collect(),
+                depth_stencil_state: render_pass_info.depth_stencil_state(),
                 subpass: Some(render_pass_info.into_subpass_type()),
                 ..GraphicsPipelineCreateInfo::layout(layout)
             },
@@ -139,9 +136,16 @@ impl BeautifulLinePipeline {
         builder: &mut AutoCommandBufferBuilder<P>,
         lines: &[BeautifulLine],
     ) -> Result<(), DrawError> {
+        if !self
+            .buffers_manager
+            .draw_filter()
+            .admit(PipelineKind::BeautifulLine, lines.len())
+        {
+            return Ok(());
+        }
         let mut offset = 0;
 
-        let vertex_buffer = self.buffers_manager.create_vertex_buffer(
+        let vertex_buffer = self.buffers_manager.create_pooled_vertex_buffer(
             lines
                 .iter()
                 .flat_map(|l| l.vertices.iter().copied())
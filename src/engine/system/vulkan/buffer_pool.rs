@@ -0,0 +1,168 @@
+//! Vertex and index buffers for single draw calls, sub-allocated from a few large arenas instead
+//! of allocating a new buffer for every draw call, see
+//! [`BasicBuffersManager::create_pooled_vertex_buffer`].
+//!
+//! Unlike the regions of a [`StreamingBuffer`], an arena is not reused after a fixed amount of
+//! frames, but once none of its [`Subbuffer`]s is alive anymore. The subbuffers of a frame are
+//! kept alive by its command buffers, which the [`VulkanSystem`] drops once the fence of the frame
+//! signaled, so an arena is never written while the GPU might still read it.
+//!
+//! [`BasicBuffersManager::create_pooled_vertex_buffer`]: crate::engine::system::vulkan::buffers::BasicBuffersManager::create_pooled_vertex_buffer
+//! [`StreamingBuffer`]: crate::engine::system::vulkan::streaming::StreamingBuffer
+//! [`VulkanSystem`]: crate::engine::system::vulkan::system::VulkanSystem
+
+use bytemuck::Pod;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use vulkano::buffer::{AllocateBufferError, Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter};
+use vulkano::memory::MemoryPropertyFlags;
+use vulkano::Validated;
+
+pub struct BufferPool {
+    memo_allocator: Arc<dyn MemoryAllocator>,
+    usage: BufferUsage,
+    /// Larger slices get a buffer of their own
+    arena_size: u64,
+    state: Mutex<PoolState>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    /// The arena that is currently sub-allocated from
+    current: Option<Subbuffer<[u8]>>,
+    /// The amount of bytes of the current arena already handed out
+    offset: u64,
+    /// Arenas that are full, reused once their subbuffers were dropped
+    filled: VecDeque<Subbuffer<[u8]>>,
+}
+
+impl BufferPool {
+    /// The alignment of every slice, which satisfies all vertex and index types
+    const ALIGNMENT: u64 = 16;
+
+    pub(crate) fn new(
+        memo_allocator: Arc<dyn MemoryAllocator>,
+        usage: BufferUsage,
+        arena_size: u64,
+    ) -> Self {
+        Self {
+            memo_allocator,
+            usage,
+            arena_size: arena_size.next_multiple_of(Self::ALIGNMENT),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Writes the values into the free space of an arena and returns the slice they were
+    /// written to.
+    pub(crate) fn write<I, T: Pod + Send + Sync>(
+        &self,
+        values: I,
+    ) -> Result<Subbuffer<[T]>, Validated<AllocateBufferError>>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let values = values.into_iter();
+        let size = (values.len() * core::mem::size_of::<T>()) as u64;
+        if size == 0
+            || size > self.arena_size
+            || core::mem::align_of::<T>() as u64 > Self::ALIGNMENT
+        {
+            return Buffer::from_iter(
+                Arc::clone(&self.memo_allocator),
+                self.buffer_create_info(),
+                Self::allocation_create_info(),
+                values,
+            );
+        }
+
+        let slice = {
+            let mut state = self.state.lock().unwrap();
+            let start = state.offset.next_multiple_of(Self::ALIGNMENT);
+            let start = match &state.current {
+                Some(arena) if start + size <= arena.size() => start,
+                _ => {
+                    self.next_arena(&mut state)?;
+                    0
+                }
+            };
+            state.offset = start + size;
+            state
+                .current
+                .as_ref()
+                .expect("An arena was just assigned")
+                .clone()
+                .slice(start..start + size)
+        }
+        .reinterpret::<[T]>();
+
+        slice
+            .write()
+            .expect("The free space of an arena is not in use")
+            .iter_mut()
+            .zip(values)
+            .for_each(|(target, value)| *target = value);
+        Ok(slice)
+    }
+
+    /// The amount of bytes allocated for all arenas.
+    pub(crate) fn allocated_bytes(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        state
+            .current
+            .iter()
+            .chain(state.filled.iter())
+            .map(Subbuffer::size)
+            .sum()
+    }
+
+    /// Retires the current arena and continues with the oldest arena that is no longer in use,
+    /// or with a new one if all of them are.
+    fn next_arena(&self, state: &mut PoolState) -> Result<(), Validated<AllocateBufferError>> {
+        if let Some(current) = state.current.take() {
+            state.filled.push_back(current);
+        }
+        let arena = match state
+            .filled
+            .iter()
+            .position(|arena| Arc::strong_count(arena.buffer()) == 1)
+        {
+            Some(index) => state
+                .filled
+                .remove(index)
+                .expect("The index was just found"),
+            None => Buffer::new_slice::<u8>(
+                Arc::clone(&self.memo_allocator),
+                self.buffer_create_info(),
+                Self::allocation_create_info(),
+                self.arena_size,
+            )?,
+        };
+        state.current = Some(arena);
+        state.offset = 0;
+        Ok(())
+    }
+
+    #[inline]
+    fn buffer_create_info(&self) -> BufferCreateInfo {
+        BufferCreateInfo {
+            usage: self.usage,
+            ..BufferCreateInfo::default()
+        }
+    }
+
+    #[inline]
+    fn allocation_create_info() -> AllocationCreateInfo {
+        AllocationCreateInfo {
+            // host coherent memory does not need to be flushed after writing into it
+            memory_type_filter: MemoryTypeFilter {
+                required_flags: MemoryPropertyFlags::HOST_VISIBLE
+                    | MemoryPropertyFlags::HOST_COHERENT,
+                ..MemoryTypeFilter::PREFER_DEVICE | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE
+            },
+            ..AllocationCreateInfo::default()
+        }
+    }
+}
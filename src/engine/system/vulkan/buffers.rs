@@ -1,43 +1,111 @@
+use crate::engine::system::vulkan::buffer_pool::BufferPool;
+use crate::engine::system::vulkan::draw_filter::DrawFilter;
 use bytemuck::Pod;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use vulkano::buffer::{AllocateBufferError, Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::buffer::{
+    AllocateBufferError, Buffer, BufferCreateInfo, BufferUsage, IndexBuffer, Subbuffer,
+};
 use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter};
 use vulkano::Validated;
 
 pub struct BasicBuffersManager {
     pub(crate) memo_allocator: Arc<dyn MemoryAllocator>,
+    frame: AtomicU64,
+    draw_filter: DrawFilter,
+    vertex_pool: BufferPool,
+    index_pool: BufferPool,
 }
 
 impl BasicBuffersManager {
+    /// The size of the arenas the pooled vertex buffers are sub-allocated from
+    const VERTEX_ARENA_SIZE: u64 = 1024 * 1024;
+    /// The size of the arenas the pooled index buffers are sub-allocated from
+    const INDEX_ARENA_SIZE: u64 = 256 * 1024;
+
     #[inline]
-    pub fn new(memo_allocator: impl MemoryAllocator) -> Self {
+    pub fn new(memo_allocator: Arc<dyn MemoryAllocator>) -> Self {
         Self {
-            memo_allocator: Arc::new(memo_allocator),
+            vertex_pool: BufferPool::new(
+                Arc::clone(&memo_allocator),
+                BufferUsage::VERTEX_BUFFER,
+                Self::VERTEX_ARENA_SIZE,
+            ),
+            index_pool: BufferPool::new(
+                Arc::clone(&memo_allocator),
+                BufferUsage::INDEX_BUFFER,
+                Self::INDEX_ARENA_SIZE,
+            ),
+            memo_allocator,
+            frame: AtomicU64::new(0),
+            draw_filter: DrawFilter::default(),
         }
     }
 
+    /// The number of the frame currently being recorded, used by the
+    /// [`StreamingBuffer`](crate::engine::system::vulkan::streaming::StreamingBuffer)s to select
+    /// their region.
     #[inline]
+    pub fn frame(&self) -> u64 {
+        self.frame.load(Ordering::Acquire)
+    }
+
+    /// Called by the [`VulkanSystem`](crate::engine::system::vulkan::system::VulkanSystem) at the
+    /// beginning of each frame.
+    #[inline]
+    pub(crate) fn next_frame(&self) {
+        self.frame.fetch_add(1, Ordering::AcqRel);
+        self.draw_filter.next_frame();
+    }
+
+    /// Consulted by all pipelines sharing this manager before they record a draw.
+    #[inline]
+    pub fn draw_filter(&self) -> &DrawFilter {
+        &self.draw_filter
+    }
+
+    /// Creates a buffer with 16-bit indices if all indices fit into them, which halves the
+    /// index bandwidth of typical 2d meshes, and with 32-bit indices otherwise.
     pub fn create_index_buffer<I>(
         &self,
         indices: I,
+    ) -> Result<IndexBuffer, Validated<AllocateBufferError>>
+    where
+        I: IntoIterator<Item = u32>,
+        I::IntoIter: ExactSizeIterator + Clone,
+    {
+        let indices = indices.into_iter();
+        if indices.clone().all(|index| index <= u32::from(u16::MAX)) {
+            Ok(IndexBuffer::U16(self.create_index_buffer_u16(
+                indices.map(|index| index as u16),
+            )?))
+        } else {
+            Ok(IndexBuffer::U32(self.create_index_buffer_u32(indices)?))
+        }
+    }
+
+    #[inline]
+    pub fn create_index_buffer_u16<I>(
+        &self,
+        indices: I,
+    ) -> Result<Subbuffer<[u16]>, Validated<AllocateBufferError>>
+    where
+        I: IntoIterator<Item = u16>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.create_buffer(BufferUsage::INDEX_BUFFER, indices)
+    }
+
+    #[inline]
+    pub fn create_index_buffer_u32<I>(
+        &self,
+        indices: I,
     ) -> Result<Subbuffer<[u32]>, Validated<AllocateBufferError>>
     where
         I: IntoIterator<Item = u32>,
         I::IntoIter: ExactSizeIterator,
     {
-        Buffer::from_iter(
-            Arc::clone(&self.memo_allocator),
-            BufferCreateInfo {
-                usage: BufferUsage::INDEX_BUFFER,
-                ..BufferCreateInfo::default()
-            },
-            AllocationCreateInfo {
-                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
-                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                ..AllocationCreateInfo::default()
-            },
-            indices,
-        )
+        self.create_buffer(BufferUsage::INDEX_BUFFER, indices)
     }
 
     #[inline]
@@ -45,6 +113,61 @@ impl BasicBuffersManager {
         &self,
         vertices: I,
     ) -> Result<Subbuffer<[T]>, Validated<AllocateBufferError>>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.create_buffer(BufferUsage::VERTEX_BUFFER, vertices)
+    }
+
+    /// Like [`BasicBuffersManager::create_vertex_buffer`], but sub-allocated from the arenas of a
+    /// pool, which are reused once the GPU finished reading them. For vertices that are only
+    /// used by the draw calls of the current frame, see the [`buffer_pool`] module.
+    ///
+    /// [`buffer_pool`]: crate::engine::system::vulkan::buffer_pool
+    #[inline]
+    pub fn create_pooled_vertex_buffer<I, T: Send + Sync + Pod>(
+        &self,
+        vertices: I,
+    ) -> Result<Subbuffer<[T]>, Validated<AllocateBufferError>>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.vertex_pool.write(vertices)
+    }
+
+    /// Like [`BasicBuffersManager::create_index_buffer`], but sub-allocated like
+    /// [`BasicBuffersManager::create_pooled_vertex_buffer`].
+    pub fn create_pooled_index_buffer<I>(
+        &self,
+        indices: I,
+    ) -> Result<IndexBuffer, Validated<AllocateBufferError>>
+    where
+        I: IntoIterator<Item = u32>,
+        I::IntoIter: ExactSizeIterator + Clone,
+    {
+        let indices = indices.into_iter();
+        if indices.clone().all(|index| index <= u32::from(u16::MAX)) {
+            Ok(IndexBuffer::U16(
+                self.index_pool.write(indices.map(|index| index as u16))?,
+            ))
+        } else {
+            Ok(IndexBuffer::U32(self.index_pool.write(indices)?))
+        }
+    }
+
+    /// The amount of bytes allocated for the arenas of the pooled vertex and index buffers.
+    #[inline]
+    pub fn pooled_bytes(&self) -> u64 {
+        self.vertex_pool.allocated_bytes() + self.index_pool.allocated_bytes()
+    }
+
+    fn create_buffer<I, T: Send + Sync + Pod>(
+        &self,
+        usage: BufferUsage,
+        values: I,
+    ) -> Result<Subbuffer<[T]>, Validated<AllocateBufferError>>
     where
         I: IntoIterator<Item = T>,
         I::IntoIter: ExactSizeIterator,
@@ -52,7 +175,7 @@ impl BasicBuffersManager {
         Buffer::from_iter(
             Arc::clone(&self.memo_allocator),
             BufferCreateInfo {
-                usage: BufferUsage::VERTEX_BUFFER,
+                usage,
                 ..BufferCreateInfo::default()
             },
             AllocationCreateInfo {
@@ -60,7 +183,7 @@ impl BasicBuffersManager {
                     | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
                 ..AllocationCreateInfo::default()
             },
-            vertices,
+            values,
         )
     }
 }
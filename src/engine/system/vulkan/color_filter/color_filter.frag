@@ -0,0 +1,18 @@
+#version 450
+
+layout(location = 0) in vec2 in_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(binding = 0, set = 0) uniform sampler2D frame;
+layout(push_constant) uniform ColorMatrix { vec4 rows[3]; } color_matrix;
+
+void main() {
+    vec4 color = texture(frame, in_uv);
+    vec3 rgb = vec3(
+        dot(color_matrix.rows[0].rgb, color.rgb),
+        dot(color_matrix.rows[1].rgb, color.rgb),
+        dot(color_matrix.rows[2].rgb, color.rgb)
+    );
+    out_color = vec4(clamp(rgb, 0.0, 1.0), color.a);
+}
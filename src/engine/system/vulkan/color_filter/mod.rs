@@ -0,0 +1,301 @@
+use crate::engine::system::vulkan::system::GraphicsPipelineRenderPassInfo;
+use crate::engine::system::vulkan::textures::ImageSamplerMode;
+use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError};
+use crate::shader_from_path;
+use std::sync::Arc;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CopyImageInfo, RenderingAttachmentInfo, RenderingInfo,
+};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::image::sampler::Sampler;
+use vulkano::image::view::ImageView;
+use vulkano::image::{Image, ImageCreateInfo, ImageType, ImageUsage};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator};
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::graphics::color_blend::ColorBlendAttachmentState;
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::rasterization::RasterizationState;
+use vulkano::pipeline::graphics::vertex_input::VertexInputState;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
+use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
+use vulkano::pipeline::{
+    DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+    PipelineShaderStageCreateInfo,
+};
+use vulkano::render_pass::{AttachmentLoadOp, AttachmentStoreOp};
+use vulkano::shader::EntryPoint;
+
+/// The kinds of color vision deficiency a [`ColorFilter`] can simulate or compensate for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum ColorBlindness {
+    /// Missing red cones
+    Protanopia,
+    /// Missing green cones, the most common one
+    Deuteranopia,
+    /// Missing blue cones
+    Tritanopia,
+}
+
+impl ColorBlindness {
+    /// The simulation matrices of Machado et al. (2009) for a severity of `1.0`, for linear RGB.
+    #[rustfmt::skip]
+    pub fn simulation_matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            ColorBlindness::Protanopia => [
+                [ 0.152286,  1.052583, -0.204868],
+                [ 0.114503,  0.786281,  0.099216],
+                [-0.003882, -0.048116,  1.051998],
+            ],
+            ColorBlindness::Deuteranopia => [
+                [ 0.367322,  0.860646, -0.227968],
+                [ 0.280085,  0.672501,  0.047413],
+                [-0.011820,  0.042940,  0.968881],
+            ],
+            ColorBlindness::Tritanopia => [
+                [ 1.255528, -0.076749, -0.178779],
+                [-0.078411,  0.930809,  0.147602],
+                [ 0.004733,  0.691367,  0.303900],
+            ],
+        }
+    }
+}
+
+/// A filter that is applied to the whole frame after everything (including egui) was drawn, see
+/// [`VulkanSystem::set_color_filter`]. Developers can check whether their game is still playable
+/// with a [`ColorFilter::Simulate`] filter, players can enable a [`ColorFilter::Daltonize`]
+/// filter as an accessibility mode.
+///
+/// [`VulkanSystem::set_color_filter`]: crate::engine::system::vulkan::system::VulkanSystem::set_color_filter
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub enum ColorFilter {
+    /// Shows the frame like someone with the color vision deficiency sees it
+    Simulate(ColorBlindness),
+    /// Shifts the colors that cannot be told apart with the color vision deficiency towards
+    /// colors that can (Fidaner et al.)
+    Daltonize(ColorBlindness),
+}
+
+impl ColorFilter {
+    /// Shifts the error between the original and the simulated color from red towards green and
+    /// blue.
+    const DALTONIZE_SHIFT: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]];
+
+    /// The matrix that is multiplied with the linear RGB of each pixel. The daltonization
+    /// `c + shift * (c - simulate * c)` is linear as well and is therefore merged into a single
+    /// matrix.
+    pub fn matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            ColorFilter::Simulate(blindness) => blindness.simulation_matrix(),
+            ColorFilter::Daltonize(blindness) => {
+                let simulation = blindness.simulation_matrix();
+                let mut matrix = [[0.0; 3]; 3];
+                for (row, values) in matrix.iter_mut().enumerate() {
+                    for (column, value) in values.iter_mut().enumerate() {
+                        let identity = |r: usize, c: usize| if r == c { 1.0 } else { 0.0 };
+                        *value = identity(row, column)
+                            + (0..3)
+                                .map(|k| {
+                                    Self::DALTONIZE_SHIFT[row][k]
+                                        * (identity(k, column) - simulation[k][column])
+                                })
+                                .sum::<f32>();
+                    }
+                }
+                matrix
+            }
+        }
+    }
+}
+
+/// Applies a [`ColorFilter`] to the rendered image: the image is copied into a sampled image,
+/// which is drawn back through the color matrix with a single fullscreen triangle. The rendered
+/// image therefore needs [`ImageUsage::TRANSFER_SRC`].
+pub(crate) struct ColorFilterPass {
+    pipeline: Arc<GraphicsPipeline>,
+    sampler: Arc<Sampler>,
+    desc_allocator: Arc<StandardDescriptorSetAllocator>,
+    memory_allocator: Arc<dyn MemoryAllocator>,
+    /// The copy of the rendered image, recreated when its size changes
+    source: Option<(Arc<Image>, Arc<PersistentDescriptorSet>)>,
+}
+
+impl ColorFilterPass {
+    pub(crate) fn new(
+        device: Arc<Device>,
+        format: Format,
+        cache: Option<Arc<PipelineCache>>,
+        desc_allocator: Arc<StandardDescriptorSetAllocator>,
+        memory_allocator: Arc<dyn MemoryAllocator>,
+    ) -> Result<Self, PipelineCreateError> {
+        Ok(Self {
+            pipeline: Self::create_pipeline(
+                Arc::clone(&device),
+                GraphicsPipelineRenderPassInfo::dynamic_rendering([format]),
+                cache,
+            )?,
+            sampler: ImageSamplerMode::PixelPerfect.create_texture_sampler(device)?,
+            desc_allocator,
+            memory_allocator,
+            source: None,
+        })
+    }
+
+    fn create_pipeline(
+        device: Arc<Device>,
+        render_pass_info: GraphicsPipelineRenderPassInfo,
+        cache: Option<Arc<PipelineCache>>,
+    ) -> Result<Arc<GraphicsPipeline>, PipelineCreateError> {
+        let vs = Self::load_vertex_shader(Arc::clone(&device))?;
+        let fs = Self::load_fragment_shader(Arc::clone(&device))?;
+
+        let stages = [
+            PipelineShaderStageCreateInfo::new(vs),
+            PipelineShaderStageCreateInfo::new(fs),
+        ];
+
+        let layout = PipelineLayout::new(
+            Arc::clone(&device),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+                .into_pipeline_layout_create_info(Arc::clone(&device))?,
+        )?;
+
+        Ok(GraphicsPipeline::new(
+            Arc::clone(&device),
+            cache,
+            GraphicsPipelineCreateInfo {
+                stages: stages.into_iter().collect(),
+                vertex_input_state: Some(VertexInputState::default()),
+                input_assembly_state: Some(InputAssemblyState {
+                    topology: PrimitiveTopology::TriangleList,
+                    ..InputAssemblyState::default()
+                }),
+                viewport_state: Some(ViewportState::default()),
+                rasterization_state: Some(RasterizationState::default()),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(
+                    render_pass_info.color_blend_state(ColorBlendAttachmentState::default()),
+                ),
+                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                subpass: Some(render_pass_info.into_subpass_type()),
+                ..GraphicsPipelineCreateInfo::layout(layout)
+            },
+        )?)
+    }
+
+    fn load_vertex_shader(device: Arc<Device>) -> Result<EntryPoint, ShaderLoadError> {
+        shader_from_path!(
+            device,
+            "vertex",
+            "src/engine/system/vulkan/color_filter/color_filter.vert"
+        )
+    }
+
+    fn load_fragment_shader(device: Arc<Device>) -> Result<EntryPoint, ShaderLoadError> {
+        shader_from_path!(
+            device,
+            "fragment",
+            "src/engine/system/vulkan/color_filter/color_filter.frag"
+        )
+    }
+
+    /// Filters the `image`, which must not be inside a render pass.
+    pub(crate) fn record<P>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        image: &Arc<Image>,
+        filter: ColorFilter,
+    ) -> Result<(), DrawError> {
+        let (source, descriptor_set) = self.source_for(image)?;
+        builder.copy_image(CopyImageInfo::images(Arc::clone(image), source))?;
+
+        let [width, height, _] = image.extent();
+        let matrix = filter.matrix().map(|[r, g, b]| [r, g, b, 0.0]);
+        builder
+            .begin_rendering(RenderingInfo {
+                color_attachments: vec![Some(RenderingAttachmentInfo {
+                    // every pixel is overwritten
+                    load_op: AttachmentLoadOp::DontCare,
+                    store_op: AttachmentStoreOp::Store,
+                    ..RenderingAttachmentInfo::image_view(
+                        ImageView::new_default(Arc::clone(image))
+                            .map_err(DrawError::FailedToPrepareTheColorFilter)?,
+                    )
+                })],
+                ..RenderingInfo::default()
+            })?
+            .set_viewport(
+                0,
+                [Viewport {
+                    offset: [0.0, 0.0],
+                    extent: [width as f32, height as f32],
+                    depth_range: 0.0..=1.0,
+                }]
+                .into_iter()
+                .collect(),
+            )?
+            .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                Arc::clone(self.pipeline.layout()),
+                0,
+                descriptor_set,
+            )?
+            .push_constants(Arc::clone(self.pipeline.layout()), 0, matrix)?
+            .draw(3, 1, 0, 0)?
+            .end_rendering()?;
+        Ok(())
+    }
+
+    fn source_for(
+        &mut self,
+        image: &Image,
+    ) -> Result<(Arc<Image>, Arc<PersistentDescriptorSet>), DrawError> {
+        if let Some((source, descriptor_set)) = &self.source {
+            if source.extent() == image.extent() && source.format() == image.format() {
+                return Ok((Arc::clone(source), Arc::clone(descriptor_set)));
+            }
+        }
+
+        let source = Image::new(
+            Arc::clone(&self.memory_allocator),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: image.format(),
+                extent: image.extent(),
+                usage: ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST,
+                ..ImageCreateInfo::default()
+            },
+            AllocationCreateInfo::default(),
+        )
+        .map_err(DrawError::FailedToCreateTheColorFilterImage)?;
+        let descriptor_set = ImageView::new_default(Arc::clone(&source))
+            .and_then(|view| {
+                PersistentDescriptorSet::new(
+                    &self.desc_allocator,
+                    Arc::clone(&self.pipeline.layout().set_layouts()[0]),
+                    [WriteDescriptorSet::image_view_sampler(
+                        0,
+                        view,
+                        Arc::clone(&self.sampler),
+                    )],
+                    [],
+                )
+            })
+            .map_err(DrawError::FailedToPrepareTheColorFilter)?;
+
+        self.source = Some((Arc::clone(&source), Arc::clone(&descriptor_set)));
+        Ok((source, descriptor_set))
+    }
+}
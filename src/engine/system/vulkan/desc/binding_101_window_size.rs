@@ -1,24 +1,35 @@
 use crate::engine::system::vulkan::desc::WriteDescriptorSetOrigin;
 use crate::engine::system::vulkan::system::VulkanSystem;
 
+/// The size of the image that is rendered into in pixels (of a single viewport with a
+/// [`ViewportLayout`], see [`VulkanSystem::viewport_extent`]) and the pixels per point of the
+/// window (see [`VulkanSystem::pixels_per_point`]), for shaders that scale with the UI:
+///
+/// ```glsl
+/// layout(binding = 101) uniform WindowProperties { vec2 screen_size; float pixels_per_point; } window;
+/// ```
+///
+/// [`ViewportLayout`]: crate::engine::system::viewport::ViewportLayout
 pub struct WindowSize {
     width: f32,
     height: f32,
+    pixels_per_point: f32,
 }
 
 impl From<&VulkanSystem> for WindowSize {
     fn from(vs: &VulkanSystem) -> Self {
-        let [width, height] = vs.swapchain().image_extent();
+        let [width, height] = vs.viewport_extent();
         Self {
             width: width as f32,
             height: height as f32,
+            pixels_per_point: vs.pixels_per_point(),
         }
     }
 }
 
 impl WriteDescriptorSetOrigin for WindowSize {
     type BufferContents = f32;
-    type Data = <[f32; 2] as IntoIterator>::IntoIter;
+    type Data = <[f32; 3] as IntoIterator>::IntoIter;
 
     #[inline]
     fn binding(&self) -> u32 {
@@ -27,6 +38,6 @@ impl WriteDescriptorSetOrigin for WindowSize {
 
     #[inline]
     fn data(&self) -> Self::Data {
-        [self.width, self.height].into_iter()
+        [self.width, self.height, self.pixels_per_point].into_iter()
     }
 }
@@ -0,0 +1,45 @@
+use crate::engine::system::vulkan::desc::WriteDescriptorSetOrigin;
+use crate::engine::system::vulkan::system::VulkanSystem;
+
+/// The time of the current frame, updated by the [`VulkanSystem`] at the beginning of every
+/// frame. Shaders can use it for animations without push constants:
+///
+/// ```glsl
+/// layout(binding = 102) uniform FrameTime {
+///     float elapsed;
+///     float delta;
+///     uint frame;
+/// } frame_time;
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct FrameTime {
+    /// The seconds since the [`VulkanSystem`] was created
+    pub elapsed: f32,
+    /// The seconds since the previous frame, zero for the first frame
+    pub delta: f32,
+    /// The number of the frame, wraps around after [`u32::MAX`] frames
+    pub frame: u32,
+}
+
+impl From<&VulkanSystem> for FrameTime {
+    #[inline]
+    fn from(vs: &VulkanSystem) -> Self {
+        vs.frame_time()
+    }
+}
+
+impl WriteDescriptorSetOrigin for FrameTime {
+    type BufferContents = f32;
+    // the frame is reinterpreted as uint by the shader, padded to 16 bytes for the std140 layout
+    type Data = <[f32; 4] as IntoIterator>::IntoIter;
+
+    #[inline]
+    fn binding(&self) -> u32 {
+        102
+    }
+
+    #[inline]
+    fn data(&self) -> Self::Data {
+        [self.elapsed, self.delta, f32::from_bits(self.frame), 0.0].into_iter()
+    }
+}
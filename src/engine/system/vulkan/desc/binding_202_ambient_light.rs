@@ -0,0 +1,56 @@
+use crate::engine::system::vulkan::desc::WriteDescriptorSetOrigin;
+use crate::engine::system::vulkan::system::VulkanSystem;
+
+/// The color all world2d pipelines multiply into their output, for example to tint the world
+/// for the time of day. White leaves the output unchanged.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AmbientLight {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl Default for AmbientLight {
+    #[inline]
+    fn default() -> Self {
+        Self::WHITE
+    }
+}
+
+impl AmbientLight {
+    pub const WHITE: Self = Self {
+        r: 1.0,
+        g: 1.0,
+        b: 1.0,
+    };
+}
+
+impl From<[f32; 3]> for AmbientLight {
+    #[inline]
+    fn from([r, g, b]: [f32; 3]) -> Self {
+        Self { r, g, b }
+    }
+}
+
+impl From<&VulkanSystem> for AmbientLight {
+    #[inline]
+    fn from(vs: &VulkanSystem) -> Self {
+        vs.ambient_light()
+    }
+}
+
+impl WriteDescriptorSetOrigin for AmbientLight {
+    type BufferContents = f32;
+    // padded to a vec4 for the std140 layout
+    type Data = <[f32; 4] as IntoIterator>::IntoIter;
+
+    #[inline]
+    fn binding(&self) -> u32 {
+        202
+    }
+
+    #[inline]
+    fn data(&self) -> Self::Data {
+        [self.r, self.g, self.b, 1.0].into_iter()
+    }
+}
@@ -4,12 +4,12 @@ use vulkano::buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage};
 use vulkano::command_buffer::allocator::CommandBufferAllocator;
 use vulkano::command_buffer::AutoCommandBufferBuilder;
 use vulkano::descriptor_set::{WriteDescriptorSet, WriteDescriptorSetElements};
-use vulkano::memory::allocator::{
-    AllocationCreateInfo, GenericMemoryAllocator, MemoryTypeFilter, Suballocator,
-};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter};
 
 pub mod binding_101_window_size;
+pub mod binding_102_frame_time;
 pub mod binding_201_world_2d_view;
+pub mod binding_202_ambient_light;
 
 pub trait WriteDescriptorSetOrigin {
     type BufferContents: BufferContents;
@@ -19,9 +19,9 @@ pub trait WriteDescriptorSetOrigin {
 
     fn data(&self) -> Self::Data;
 
-    fn create_descriptor_set<T: Suballocator + Send + 'static>(
+    fn create_descriptor_set(
         &self,
-        memory_allocator: Arc<GenericMemoryAllocator<T>>,
+        memory_allocator: Arc<dyn MemoryAllocator>,
     ) -> Result<WriteDescriptorSet, Error> {
         Ok(WriteDescriptorSet::buffer(
             self.binding(),
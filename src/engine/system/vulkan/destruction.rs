@@ -0,0 +1,65 @@
+use crossbeam::queue::SegQueue;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use vulkano::sync::future::FenceSignalFuture;
+use vulkano::sync::GpuFuture;
+
+type Resource = Box<dyn Send + Sync>;
+
+/// Signalled once the GPU completed the commands of a submitted frame (and of all frames that
+/// were submitted before it).
+pub(crate) type FrameFence = Arc<FenceSignalFuture<Box<dyn GpuFuture + Send + Sync>>>;
+
+/// Keeps released resources alive until the frames that might still be using them on the GPU
+/// have completed.
+#[derive(Default)]
+pub struct DeferredDestructionQueue {
+    released: SegQueue<Resource>,
+    /// The resources released before the submission of the frame of the fence, in the order of
+    /// the submissions
+    in_flight: Mutex<VecDeque<(FrameFence, Vec<Resource>)>>,
+}
+
+impl DeferredDestructionQueue {
+    /// Enqueues the given resource for destruction once all frames that were submitted until
+    /// then have been completed, independent of how many frames are in flight.
+    #[inline]
+    pub fn enqueue(&self, resource: impl Send + Sync + 'static) {
+        self.released.push(Box::new(resource));
+    }
+
+    /// The amount of resources that are waiting for their destruction.
+    pub fn len(&self) -> usize {
+        self.released.len()
+            + self
+                .in_flight
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, resources)| resources.len())
+                .sum::<usize>()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The resources released until now are destroyed once the fence of the submitted frame is
+    /// signalled. If the submission failed, they are kept for the next submitted frame.
+    pub(crate) fn on_frame_submitted(&self, fence: FrameFence) {
+        let released = std::iter::from_fn(|| self.released.pop()).collect::<Vec<_>>();
+        self.in_flight.lock().unwrap().push_back((fence, released));
+    }
+
+    /// Drops the resources of the frames whose fences are signalled.
+    pub(crate) fn on_frame_completed(&self) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        while in_flight
+            .front()
+            .is_some_and(|(fence, _)| fence.is_signaled().unwrap_or(false))
+        {
+            drop(in_flight.pop_front());
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Switches to skip the draws of single pipelines and a recorder for the draws of a frame, for
+//! performance experiments at runtime, like comparing the frame times with and without the
+//! [`BeautifulLinePipeline`].
+//!
+//! ```no_run
+//! use hotrod::engine::system::vulkan::draw_filter::PipelineKind;
+//!
+//! let engine = hotrod::engine::Engine::default();
+//! let filter = engine.draw_filter();
+//! filter.set_enabled(PipelineKind::BeautifulLine, false);
+//! filter.record_next_frame();
+//!
+//! // ... render two frames
+//!
+//! for draw in filter.take_recording().unwrap_or_default() {
+//!     println!("{}: {} items, skipped={}", draw.pipeline, draw.items, draw.skipped);
+//! }
+//! ```
+//!
+//! [`BeautifulLinePipeline`]: crate::engine::system::vulkan::beautiful_lines::BeautifulLinePipeline
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+
+/// The pipelines of the engine, see [`VulkanPipelines`].
+///
+/// [`VulkanPipelines`]: crate::engine::system::vulkan::pipelines::VulkanPipelines
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PipelineKind {
+    Line,
+    Textured,
+    Triangles,
+    BeautifulLine,
+    World2dTerrain,
+    World2dDecals,
+    World2dEntities,
+    GlowingBalls,
+    Trail,
+    SdfShapes,
+    Egui,
+}
+
+impl PipelineKind {
+    pub const ALL: [PipelineKind; 11] = [
+        PipelineKind::Line,
+        PipelineKind::Textured,
+        PipelineKind::Triangles,
+        PipelineKind::BeautifulLine,
+        PipelineKind::World2dTerrain,
+        PipelineKind::World2dDecals,
+        PipelineKind::World2dEntities,
+        PipelineKind::GlowingBalls,
+        PipelineKind::Trail,
+        PipelineKind::SdfShapes,
+        PipelineKind::Egui,
+    ];
+
+    /// The name for logs and the dev console, like `"beautiful_line"`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            PipelineKind::Line => "line",
+            PipelineKind::Textured => "textured",
+            PipelineKind::Triangles => "triangles",
+            PipelineKind::BeautifulLine => "beautiful_line",
+            PipelineKind::World2dTerrain => "world2d_terrain",
+            PipelineKind::World2dDecals => "world2d_decals",
+            PipelineKind::World2dEntities => "world2d_entities",
+            PipelineKind::GlowingBalls => "glowing_balls",
+            PipelineKind::Trail => "trail",
+            PipelineKind::SdfShapes => "sdf_shapes",
+            PipelineKind::Egui => "egui",
+        }
+    }
+
+    #[inline]
+    const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+impl Display for PipelineKind {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Unknown pipeline: {0}")]
+pub struct UnknownPipelineError(pub String);
+
+impl FromStr for PipelineKind {
+    type Err = UnknownPipelineError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownPipelineError(s.to_string()))
+    }
+}
+
+/// A draw call (or a batch of draw calls) of a pipeline.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DrawRecord {
+    pub pipeline: PipelineKind,
+    /// The amount of primitives, instances or vertices passed to the draw method
+    pub items: usize,
+    /// Whether the draw was skipped because the pipeline is disabled
+    pub skipped: bool,
+}
+
+#[derive(Debug, Default)]
+enum RecordingState {
+    #[default]
+    Idle,
+    /// Starts with the next frame
+    Armed,
+    Active(Vec<DrawRecord>),
+}
+
+/// Shared by all pipelines of a [`VulkanSystem`] through the [`BasicBuffersManager`], see the
+/// [module documentation](self).
+///
+/// [`VulkanSystem`]: crate::engine::system::vulkan::system::VulkanSystem
+/// [`BasicBuffersManager`]: crate::engine::system::vulkan::buffers::BasicBuffersManager
+#[derive(Debug, Default)]
+pub struct DrawFilter {
+    /// The bits of the disabled [`PipelineKind`]s
+    disabled: AtomicU32,
+    /// Whether a recording is active, to skip the lock otherwise
+    recording: AtomicBool,
+    state: Mutex<RecordingState>,
+    finished: Mutex<Option<Vec<DrawRecord>>>,
+}
+
+impl DrawFilter {
+    #[inline]
+    pub fn is_enabled(&self, pipeline: PipelineKind) -> bool {
+        self.disabled.load(Ordering::Relaxed) & pipeline.bit() == 0
+    }
+
+    /// Disabled pipelines skip their draws without recording any commands.
+    #[inline]
+    pub fn set_enabled(&self, pipeline: PipelineKind, enabled: bool) {
+        if enabled {
+            self.disabled.fetch_and(!pipeline.bit(), Ordering::Relaxed);
+        } else {
+            self.disabled.fetch_or(pipeline.bit(), Ordering::Relaxed);
+        }
+    }
+
+    #[inline]
+    pub fn enable_all(&self) {
+        self.disabled.store(0, Ordering::Relaxed);
+    }
+
+    /// Records the draws of all pipelines during the next frame, from the start of one
+    /// [`VulkanSystem::render`] to the start of the following one. The result is available
+    /// through [`DrawFilter::take_recording`] afterward.
+    ///
+    /// [`VulkanSystem::render`]: crate::engine::system::vulkan::system::VulkanSystem::render
+    pub fn record_next_frame(&self) {
+        let mut state = self.state.lock().unwrap();
+        if matches!(*state, RecordingState::Idle) {
+            *state = RecordingState::Armed;
+        }
+    }
+
+    /// The draws of the last finished recording.
+    pub fn take_recording(&self) -> Option<Vec<DrawRecord>> {
+        self.finished.lock().unwrap().take()
+    }
+
+    /// Called by every pipeline before recording a draw, returns whether to record it.
+    pub fn admit(&self, pipeline: PipelineKind, items: usize) -> bool {
+        let enabled = self.is_enabled(pipeline);
+        if self.recording.load(Ordering::Relaxed) {
+            if let RecordingState::Active(draws) = &mut *self.state.lock().unwrap() {
+                draws.push(DrawRecord {
+                    pipeline,
+                    items,
+                    skipped: !enabled,
+                });
+            }
+        }
+        enabled
+    }
+
+    /// Finishes the active recording and starts an armed one.
+    pub(crate) fn next_frame(&self) {
+        let mut state = self.state.lock().unwrap();
+        match core::mem::take(&mut *state) {
+            RecordingState::Idle => {}
+            RecordingState::Armed => {
+                *state = RecordingState::Active(Vec::new());
+                self.recording.store(true, Ordering::Relaxed);
+            }
+            RecordingState::Active(draws) => {
+                self.recording.store(false, Ordering::Relaxed);
+                *self.finished.lock().unwrap() = Some(draws);
+            }
+        }
+    }
+}
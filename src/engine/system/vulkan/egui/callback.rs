@@ -0,0 +1,119 @@
+use crate::engine::system::vulkan::system::InlineCommandBufferBuilder;
+use crate::engine::system::vulkan::DrawError;
+use egui::{PaintCallback, Rect};
+use std::sync::Arc;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, SecondaryAutoCommandBuffer,
+};
+
+/// The command buffer an [`EguiPaintCallback`] records into, which depends on the
+/// [`RenderMode`] of the engine.
+///
+/// [`RenderMode`]: crate::engine::builder::RenderMode
+pub enum EguiCallbackBuilder<'a> {
+    Secondary(&'a mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>),
+    Inline(&'a mut InlineCommandBufferBuilder),
+}
+
+/// The command buffers an [`EguiPaintCallback`] can record into.
+pub trait EguiCallbackCommandBuffer: Sized {
+    fn callback_builder(builder: &mut AutoCommandBufferBuilder<Self>) -> EguiCallbackBuilder<'_>;
+}
+
+impl EguiCallbackCommandBuffer for SecondaryAutoCommandBuffer {
+    #[inline]
+    fn callback_builder(builder: &mut AutoCommandBufferBuilder<Self>) -> EguiCallbackBuilder<'_> {
+        EguiCallbackBuilder::Secondary(builder)
+    }
+}
+
+impl EguiCallbackCommandBuffer for PrimaryAutoCommandBuffer {
+    #[inline]
+    fn callback_builder(builder: &mut AutoCommandBufferBuilder<Self>) -> EguiCallbackBuilder<'_> {
+        EguiCallbackBuilder::Inline(builder)
+    }
+}
+
+/// Where an [`EguiPaintCallback`] draws. When it is called, the viewport is already set to the
+/// [`EguiCallbackInfo::viewport`] and the scissor to the [`EguiCallbackInfo::clip_rect`], so
+/// drawing in normalized device coordinates fills the area of the widget.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EguiCallbackInfo {
+    /// The rect of the [`PaintCallback`] in pixels
+    pub viewport: Rect,
+    /// The visible part of the [`EguiCallbackInfo::viewport`] in pixels
+    pub clip_rect: Rect,
+    pub pixels_per_point: f32,
+    /// The size of the render target in pixels
+    pub screen_size: [u32; 2],
+}
+
+type PaintFn =
+    dyn Fn(&EguiCallbackInfo, EguiCallbackBuilder) -> Result<(), DrawError> + Send + Sync;
+
+/// Custom rendering inside an egui widget, like a viewport of the world that is drawn with the
+/// world2d pipelines. The callback is called while the commands of egui are recorded, in the
+/// render pass of the frame, so it can only record draw commands. The pipeline and the buffers
+/// of egui are bound again afterward.
+///
+/// ```no_run
+/// use hotrod::engine::system::vulkan::egui::{EguiCallbackBuilder, EguiPaintCallback};
+/// use hotrod::ui::egui;
+///
+/// fn world_viewport(ui: &mut egui::Ui) {
+///     let (rect, _) = ui.allocate_exact_size(egui::vec2(320.0, 240.0), egui::Sense::hover());
+///     ui.painter().add(EguiPaintCallback::shape(rect, |_info, builder| {
+///         match builder {
+///             EguiCallbackBuilder::Secondary(builder) => { /* draw the world */ }
+///             EguiCallbackBuilder::Inline(builder) => { /* draw the world */ }
+///         }
+///         Ok(())
+///     }));
+/// }
+/// ```
+///
+/// Callbacks are not drawn into an [`EguiOffscreenTarget`] and are not captured into a
+/// [`FramePacket`].
+///
+/// [`EguiOffscreenTarget`]: super::EguiOffscreenTarget
+/// [`FramePacket`]: crate::engine::system::packet::FramePacket
+pub struct EguiPaintCallback {
+    paint: Box<PaintFn>,
+}
+
+impl EguiPaintCallback {
+    pub fn new(
+        paint: impl Fn(&EguiCallbackInfo, EguiCallbackBuilder) -> Result<(), DrawError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            paint: Box::new(paint),
+        }
+    }
+
+    /// The [`PaintCallback`] to add to an [`egui::Painter`], which draws with the `paint`
+    /// callback into the `rect`.
+    pub fn shape(
+        rect: Rect,
+        paint: impl Fn(&EguiCallbackInfo, EguiCallbackBuilder) -> Result<(), DrawError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> PaintCallback {
+        PaintCallback {
+            rect,
+            callback: Arc::new(Self::new(paint)),
+        }
+    }
+
+    #[inline]
+    pub fn paint(
+        &self,
+        info: &EguiCallbackInfo,
+        builder: EguiCallbackBuilder,
+    ) -> Result<(), DrawError> {
+        (self.paint)(info, builder)
+    }
+}
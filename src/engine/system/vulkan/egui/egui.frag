@@ -7,6 +7,9 @@ layout(location = 0) out vec4 out_color;
 
 layout(binding = 0, set = 0) uniform sampler2D font_texture;
 
+// Whether the render target expects gamma encoded colors (see EguiOutputColorSpace)
+layout(constant_id = 0) const bool GAMMA_OUTPUT = false;
+
 // 0-255 sRGB  from  0-1 linear
 vec3 srgb_from_linear(vec3 rgb) {
     bvec3 cutoff = lessThan(rgb, vec3(0.0031308));
@@ -26,8 +29,10 @@ vec4 gamma_from_linear_rgba(vec4 linear_rgba) {
 }
 
 void main() {
-    // The texture is set up with `SRGB8_ALPHA8` !?!?!?!
-    // out_color = in_color * gamma_from_linear_rgba(texture(font_texture, in_uv));
-    
-    out_color = in_color * texture(font_texture, in_uv);
+    vec4 linear_color = in_color * texture(font_texture, in_uv);
+    if (GAMMA_OUTPUT) {
+        out_color = gamma_from_linear_rgba(linear_color);
+    } else {
+        out_color = linear_color;
+    }
 }
\ No newline at end of file
@@ -1,15 +1,19 @@
 use crate::engine::system::egui::EguiSystem;
+use crate::engine::system::viewport::ViewportRect;
 use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
+use crate::engine::system::vulkan::streaming::StreamingBuffer;
 use crate::engine::system::vulkan::system::{GraphicsPipelineRenderPassInfo, VulkanSystem};
 use crate::engine::system::vulkan::textures::{
-    ImageSamplerMode, ImageSystem, TextureId, TextureManager,
+    ImageSamplerMode, ImageSystem, TextureId, TextureManager, UploadPriority,
 };
+use crate::engine::system::vulkan::utils::DescriptorSetBinding;
 use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError, UploadError};
 use crate::shader_from_path;
 use bytemuck::{Pod, Zeroable};
 use egui::{
-    ClippedPrimitive, Color32, ImageData, Rect, TextureId as EguiTextureId, TextureOptions,
-    TexturesDelta,
+    ClippedPrimitive, Color32, ImageData, Pos2, Rect, TextureId as EguiTextureId, TextureOptions,
+    TexturesDelta, Vec2,
 };
 use nohash_hasher::NoHashHasher;
 use std::collections::HashMap;
@@ -17,33 +21,119 @@ use std::hash::BuildHasherDefault;
 use std::ops::DerefMut;
 use std::sync::{Arc, RwLock};
 use vulkano::buffer::AllocateBufferError;
-use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, RenderingAttachmentInfo, RenderingInfo};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
 use vulkano::device::{Device, Queue};
 use vulkano::image::sampler::{Filter, Sampler, SamplerCreateInfo, SamplerMipmapMode};
+use vulkano::image::view::ImageView;
 use vulkano::image::{AllocateImageError, Image};
 use vulkano::pipeline::cache::PipelineCache;
 use vulkano::pipeline::graphics::color_blend::{
-    AttachmentBlend, ColorBlendAttachmentState, ColorBlendState,
+    AttachmentBlend, BlendFactor, BlendOp, ColorBlendAttachmentState,
 };
 use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
-use vulkano::pipeline::graphics::multisample::MultisampleState;
 use vulkano::pipeline::graphics::rasterization::RasterizationState;
 use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition};
-use vulkano::pipeline::graphics::viewport::{Scissor, ViewportState};
+use vulkano::pipeline::graphics::viewport::{Scissor, Viewport, ViewportState};
 use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
 use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
 use vulkano::pipeline::{
-    DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
-    PipelineShaderStageCreateInfo,
+    DynamicState, GraphicsPipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
 };
-use vulkano::shader::EntryPoint;
+use vulkano::render_pass::{AttachmentLoadOp, AttachmentStoreOp};
+use vulkano::shader::{EntryPoint, SpecializationConstant};
 use vulkano::{Validated, VulkanError};
 
 use crate::ui::egui::epaint::{ImageDelta, Primitive};
 use crate::ui::egui::{TextureFilter, TextureWrapMode};
 
+mod callback;
+mod offscreen;
+pub use callback::*;
+pub use offscreen::*;
+
 type TextureSamplers = HashMap<TextureOptions, Arc<Sampler>>;
 
+/// Records an [`EguiPaintCallback`], [`None`] where callbacks are not drawn
+type PaintCallbackFn<P> = fn(
+    &mut AutoCommandBufferBuilder<P>,
+    &EguiPaintCallback,
+    &EguiCallbackInfo,
+) -> Result<(), DrawError>;
+
+/// A mesh or a callback of the [`ClippedPrimitive`]s, in the order they are drawn
+enum DrawItem<'a> {
+    /// The index of the mesh
+    Mesh(usize),
+    /// The rect of the [`egui::PaintCallback`]
+    Callback(Rect, &'a EguiPaintCallback),
+}
+
+/// The `constant_id` of `GAMMA_OUTPUT` in `egui.frag`
+const FRAGMENT_GAMMA_OUTPUT_CONSTANT_ID: u32 = 0;
+
+/// How the output of egui is blended onto the render target.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum EguiBlendMode {
+    /// Blends with straight (non-premultiplied) alpha.
+    #[default]
+    Alpha,
+    /// Blends with premultiplied alpha, as egui's reference painters do.
+    PremultipliedAlpha,
+}
+
+impl EguiBlendMode {
+    fn attachment_blend(self) -> AttachmentBlend {
+        match self {
+            EguiBlendMode::Alpha => AttachmentBlend::alpha(),
+            EguiBlendMode::PremultipliedAlpha => AttachmentBlend {
+                src_color_blend_factor: BlendFactor::One,
+                dst_color_blend_factor: BlendFactor::OneMinusSrcAlpha,
+                color_blend_op: BlendOp::Add,
+                src_alpha_blend_factor: BlendFactor::OneMinusDstAlpha,
+                dst_alpha_blend_factor: BlendFactor::One,
+                alpha_blend_op: BlendOp::Add,
+            },
+        }
+    }
+}
+
+/// The color space the fragment shader writes its colors in.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum EguiOutputColorSpace {
+    /// For sRGB render targets, which apply the gamma encoding themselves.
+    #[default]
+    Linear,
+    /// For linear (UNORM) render targets, the shader applies the gamma encoding.
+    Gamma,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct EguiPipelineOptions {
+    pub blend_mode: EguiBlendMode,
+    pub output_color_space: EguiOutputColorSpace,
+}
+
+impl EguiPipelineOptions {
+    #[inline]
+    pub fn with_blend_mode(mut self, blend_mode: EguiBlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    #[inline]
+    pub fn with_output_color_space(mut self, color_space: EguiOutputColorSpace) -> Self {
+        self.output_color_space = color_space;
+        self
+    }
+}
+
+/// The textures of a single [`egui::Context`]. The [`EguiTextureId`]s are only unique within
+/// their context, so every context needs its own instance.
+#[derive(Default)]
+pub struct EguiTextures(RwLock<Inner>);
+
+#[derive(Default)]
 struct Inner {
     pub textures:
         HashMap<IdWrapper, TextureId<EguiPipeline>, BuildHasherDefault<NoHashHasher<u64>>>,
@@ -55,43 +145,74 @@ struct Inner {
 pub struct EguiPipeline {
     pub queue: Arc<Queue>,
     pub pipeline: Arc<GraphicsPipeline>,
-    buffers_manager: Arc<BasicBuffersManager>,
+    offscreen_pipeline: Arc<GraphicsPipeline>,
+    vertices: StreamingBuffer<AdapterVertex>,
+    indices: StreamingBuffer<u32>,
     image_system: Arc<ImageSystem>,
     texture_manager: TextureManager<Self, 0>,
-    inner: RwLock<Inner>,
+    inner: EguiTextures,
     device: Arc<Device>,
+    buffers_manager: Arc<BasicBuffersManager>,
 }
 
 impl TryFrom<&VulkanSystem> for EguiPipeline {
     type Error = PipelineCreateError;
 
+    #[inline]
     fn try_from(vs: &VulkanSystem) -> Result<Self, Self::Error> {
+        Self::with_options(vs, EguiPipelineOptions::default())
+    }
+}
+
+impl EguiPipeline {
+    /// Like [`EguiPipeline::try_from`], but with the given options instead of the defaults.
+    pub fn with_options(
+        vs: &VulkanSystem,
+        options: EguiPipelineOptions,
+    ) -> Result<Self, PipelineCreateError> {
         Self::new(
-            Arc::clone(vs.device()),
             Arc::clone(vs.queue()),
             vs.graphics_pipeline_render_pass_info(),
             vs.pipeline_cache().map(Arc::clone),
+            options,
             Arc::clone(vs.basic_buffers_manager()),
             Arc::clone(vs.image_system()),
+            Arc::clone(vs.descriptor_set_allocator()),
         )
     }
-}
 
-impl EguiPipeline {
     pub fn new(
-        device: Arc<Device>,
         queue: Arc<Queue>,
         render_pass_info: GraphicsPipelineRenderPassInfo,
         cache: Option<Arc<PipelineCache>>,
+        options: EguiPipelineOptions,
         buffers_manager: Arc<BasicBuffersManager>,
         image_system: Arc<ImageSystem>,
+        desc_allocator: Arc<StandardDescriptorSetAllocator>,
     ) -> Result<Self, PipelineCreateError> {
-        let pipeline = Self::create_pipeline(Arc::clone(&device), render_pass_info, cache)?;
-        let texture_manager =
-            TextureManager::basic(Arc::clone(&device), &pipeline, ImageSamplerMode::Linear)?;
+        let device = Arc::clone(queue.device());
+        let pipeline = Self::create_pipeline(
+            Arc::clone(&device),
+            render_pass_info,
+            cache.clone(),
+            options,
+        )?;
+        let offscreen_pipeline = Self::create_pipeline(
+            Arc::clone(&device),
+            GraphicsPipelineRenderPassInfo::dynamic_rendering([ImageSystem::RENDER_TARGET_FORMAT]),
+            cache,
+            options,
+        )?;
+        let texture_manager = TextureManager::basic(
+            Arc::clone(&device),
+            &pipeline,
+            ImageSamplerMode::Linear,
+            desc_allocator,
+            Arc::clone(image_system.deferred_destruction()),
+        )?;
         Ok(Self {
             queue,
-            inner: RwLock::new(Inner {
+            inner: EguiTextures(RwLock::new(Inner {
                 textures: HashMap::default(),
                 textures_to_free: Vec::default(),
                 images: HashMap::default(),
@@ -105,12 +226,15 @@ impl EguiPipeline {
                 )]
                 .into_iter()
                 .collect::<HashMap<_, _>>(),
-            }),
+            })),
             device,
+            vertices: StreamingBuffer::vertices(Arc::clone(&buffers_manager), 16 * 1024),
+            indices: StreamingBuffer::indices(Arc::clone(&buffers_manager), 32 * 1024),
             buffers_manager,
             image_system,
             texture_manager,
             pipeline,
+            offscreen_pipeline,
         })
     }
 
@@ -118,9 +242,25 @@ impl EguiPipeline {
         device: Arc<Device>,
         render_pass_info: GraphicsPipelineRenderPassInfo,
         cache: Option<Arc<PipelineCache>>,
+        options: EguiPipelineOptions,
     ) -> Result<Arc<GraphicsPipeline>, PipelineCreateError> {
         let vs = Self::load_vertex_shader(Arc::clone(&device))?;
         let fs = Self::load_fragment_shader(Arc::clone(&device))?;
+        let fs = fs
+            .module()
+            .base_module()
+            .specialize(
+                [(
+                    FRAGMENT_GAMMA_OUTPUT_CONSTANT_ID,
+                    SpecializationConstant::Bool(
+                        options.output_color_space == EguiOutputColorSpace::Gamma,
+                    ),
+                )]
+                .into_iter()
+                .collect(),
+            )?
+            .entry_point("main")
+            .ok_or(ShaderLoadError::MissingEntryPoint("fragment", "main"))?;
 
         let vertex_input_state =
             AdapterVertex::per_vertex().definition(&vs.info().input_interface)?;
@@ -148,24 +288,14 @@ impl EguiPipeline {
                 }),
                 viewport_state: Some(ViewportState::default()), // Some(ViewportState::viewport_dynamic_scissor_dynamic(1)),
                 rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState {
-                    rasterization_samples: render_pass_info.rasterization_samples(),
-                    ..MultisampleState::default()
-                }),
-                color_blend_state: Some(ColorBlendState::with_attachment_states(
-                    render_pass_info.num_color_attachments(),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(render_pass_info.color_blend_state(
                     ColorBlendAttachmentState {
-                        // was before - erroneous?
-                        // .src_color_blend_factor = BlendFactor::One;
-                        // .dst_color_blend_factor = BlendFactor::OneMinusSrcAlpha;
-                        // .color_blend_op = BlendOp::Add;
-                        // .src_alpha_blend_factor = BlendFactor::OneMinusDstAlpha;
-                        // .dst_alpha_blend_factor = BlendFactor::One;
-                        // .alpha_blend_op = BlendOp::Add;
-                        blend: Some(AttachmentBlend::alpha()),
+                        blend: Some(options.blend_mode.attachment_blend()),
                         ..ColorBlendAttachmentState::default()
                     },
                 )),
+                depth_stencil_state: render_pass_info.depth_stencil_state(),
                 subpass: Some(render_pass_info.into_subpass_type()),
                 dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
                     .into_iter()
@@ -214,108 +344,345 @@ impl EguiPipeline {
 
     #[inline]
     pub fn prepare(&self, egui: &EguiSystem) -> Result<(), UploadError> {
-        self.update_textures(&egui.texture_delta)
+        self.prepare_with(egui, &self.inner)
     }
 
+    /// Like [`EguiPipeline::prepare`] but for an [`EguiSystem`] other than the main one, which
+    /// brings its own [`EguiTextures`].
     #[inline]
-    pub fn draw<P>(
+    pub fn prepare_with(
+        &self,
+        egui: &EguiSystem,
+        textures: &EguiTextures,
+    ) -> Result<(), UploadError> {
+        self.update_textures(textures, &egui.texture_delta)
+    }
+
+    #[inline]
+    pub fn draw<P: EguiCallbackCommandBuffer>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        egui: &EguiSystem,
+    ) -> Result<(), DrawError> {
+        self.draw_with(builder, egui, &self.inner)
+    }
+
+    /// Like [`EguiPipeline::draw`] but for an [`EguiSystem`] other than the main one, see
+    /// [`EguiPipeline::prepare_with`].
+    #[inline]
+    pub fn draw_with<P: EguiCallbackCommandBuffer>(
         &self,
         builder: &mut AutoCommandBufferBuilder<P>,
         egui: &EguiSystem,
+        textures: &EguiTextures,
+    ) -> Result<(), DrawError> {
+        self.draw_internal(
+            builder,
+            &self.pipeline,
+            textures,
+            [egui.width, egui.height],
+            egui.pixels_per_point(),
+            &egui.clipped_primitives,
+            Some(Self::paint_callback),
+        )
+    }
+
+    /// Draws primitives that were captured from the main [`EguiSystem`] earlier, like the ones
+    /// of a [`FramePacket`], with the textures of the main context.
+    ///
+    /// [`FramePacket`]: crate::engine::system::packet::FramePacket
+    pub(crate) fn draw_primitives<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        size: [f32; 2],
+        pixels_per_point: f32,
+        clipped_primitives: &[ClippedPrimitive],
     ) -> Result<(), DrawError> {
-        self.draw_internal(builder, egui.width, egui.height, &egui.clipped_primitives)
+        self.draw_internal(
+            builder,
+            &self.pipeline,
+            &self.inner,
+            size,
+            pixels_per_point,
+            clipped_primitives,
+            None,
+        )
     }
 
+    /// Creates a new [`EguiOffscreenTarget`] with its own [`egui::Context`]. The size of its image
+    /// is the `logical_size` (in points) multiplied by `pixels_per_point`.
+    pub fn create_offscreen_target(
+        &self,
+        logical_size: [f32; 2],
+        pixels_per_point: f32,
+    ) -> Result<EguiOffscreenTarget, UploadError> {
+        let [width, height] = logical_size.map(|v| ((v * pixels_per_point).ceil() as u32).max(1));
+        let image = self
+            .image_system
+            .create_render_target_image(width, height)?;
+        Ok(EguiOffscreenTarget::new(
+            logical_size,
+            pixels_per_point,
+            ImageView::new_default(image)?,
+        ))
+    }
+
+    /// Uploads the textures the [`EguiOffscreenTarget`] requested in its last update.
+    #[inline]
+    pub fn prepare_offscreen(&self, target: &mut EguiOffscreenTarget) -> Result<(), UploadError> {
+        let delta = core::mem::take(&mut target.texture_delta);
+        self.update_textures(&target.textures, &delta)
+    }
+
+    /// Renders the ui of the [`EguiOffscreenTarget`] into its image. This begins its own
+    /// rendering, so the `builder` must not be inside a render pass, see
+    /// [`RenderContext::create_preparation_buffer_builder`].
+    ///
+    /// [`RenderContext::create_preparation_buffer_builder`]: crate::engine::system::vulkan::system::RenderContext::create_preparation_buffer_builder
+    pub fn draw_offscreen<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        target: &EguiOffscreenTarget,
+    ) -> Result<(), DrawError> {
+        let [width, height] = target.extent();
+        builder
+            .begin_rendering(RenderingInfo {
+                color_attachments: vec![Some(RenderingAttachmentInfo {
+                    load_op: AttachmentLoadOp::Clear,
+                    store_op: AttachmentStoreOp::Store,
+                    clear_value: Some([0.0, 0.0, 0.0, 0.0].into()),
+                    ..RenderingAttachmentInfo::image_view(Arc::clone(&target.image_view))
+                })],
+                ..RenderingInfo::default()
+            })?
+            .set_viewport(
+                0,
+                [Viewport {
+                    offset: [0.0, 0.0],
+                    extent: [width as f32, height as f32],
+                    depth_range: 0.0..=1.0,
+                }]
+                .into_iter()
+                .collect(),
+            )?;
+
+        self.draw_internal(
+            builder,
+            &self.offscreen_pipeline,
+            &target.textures,
+            target.logical_size,
+            target.pixels_per_point,
+            &target.clipped_primitives,
+            None,
+        )?;
+
+        builder.end_rendering()?;
+        Ok(())
+    }
+
+    #[inline]
+    fn paint_callback<P: EguiCallbackCommandBuffer>(
+        builder: &mut AutoCommandBufferBuilder<P>,
+        callback: &EguiPaintCallback,
+        info: &EguiCallbackInfo,
+    ) -> Result<(), DrawError> {
+        callback.paint(info, P::callback_builder(builder))
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn draw_internal<P>(
         &self,
         builder: &mut AutoCommandBufferBuilder<P>,
-        width: f32,
-        height: f32,
+        pipeline: &Arc<GraphicsPipeline>,
+        textures: &EguiTextures,
+        [width, height]: [f32; 2],
+        pixels_per_point: f32,
         clipped_primitives: &[ClippedPrimitive],
+        paint_callback: Option<PaintCallbackFn<P>>,
     ) -> Result<(), DrawError> {
-        let mut vertices = Vec::<AdapterVertex>::with_capacity(clipped_primitives.len() * 4);
-        let mut indices = Vec::<u32>::with_capacity(clipped_primitives.len() * 6);
-        let mut clip_rects = Vec::<Rect>::with_capacity(clipped_primitives.len());
-        let mut texture_ids = Vec::<EguiTextureId>::with_capacity(clipped_primitives.len());
+        if !self
+            .buffers_manager
+            .draw_filter()
+            .admit(PipelineKind::Egui, clipped_primitives.len())
+        {
+            return Ok(());
+        }
+        let mut meshes = Vec::with_capacity(clipped_primitives.len());
+        let mut draws = Vec::<(Rect, DrawItem)>::with_capacity(clipped_primitives.len());
         let mut offsets = Vec::<(usize, usize)>::with_capacity(clipped_primitives.len());
+        let (mut vertex_count, mut index_count) = (0, 0);
 
         for clipped in clipped_primitives {
-            let mesh = match &clipped.primitive {
-                Primitive::Mesh(mesh) => mesh,
-                Primitive::Callback(_) => {
-                    dbg!("NOT YET SUPPORTED", &clipped.primitive);
-                    continue;
+            match &clipped.primitive {
+                Primitive::Mesh(mesh) => {
+                    if mesh.vertices.is_empty() || mesh.indices.is_empty() {
+                        continue;
+                    }
+
+                    draws.push((clipped.clip_rect, DrawItem::Mesh(meshes.len())));
+                    offsets.push((vertex_count, index_count));
+
+                    vertex_count += mesh.vertices.len();
+                    index_count += mesh.indices.len();
+                    meshes.push(mesh);
+                }
+                Primitive::Callback(callback) => {
+                    match callback.callback.downcast_ref::<EguiPaintCallback>() {
+                        Some(paint) if paint_callback.is_some() => draws
+                            .push((clipped.clip_rect, DrawItem::Callback(callback.rect, paint))),
+                        Some(_) => {}
+                        None => warn!("Unsupported egui PaintCallback, use an EguiPaintCallback"),
+                    }
                 }
-            };
-
-            if mesh.vertices.is_empty() || mesh.indices.is_empty() {
-                continue;
             }
-
-            offsets.push((vertices.len(), indices.len()));
-            texture_ids.push(mesh.texture_id);
-
-            mesh.vertices.iter().for_each(|v| vertices.push(v.into()));
-            mesh.indices.iter().for_each(|i| indices.push(*i));
-            clip_rects.push(clipped.clip_rect);
         }
 
-        if clip_rects.is_empty() {
+        if draws.is_empty() {
             // nothing to do
             return Ok(());
         }
 
-        offsets.push((vertices.len(), indices.len()));
+        offsets.push((vertex_count, index_count));
 
-        let vertex_buffer = self.buffers_manager.create_vertex_buffer(vertices)?;
-        let index_buffer = self.buffers_manager.create_index_buffer(indices)?;
-
-        builder
-            //.next_subpass(SubpassContents::Inline)?
-            .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
-            .bind_index_buffer(index_buffer)?
-            .bind_vertex_buffers(0, vertex_buffer)?
-            .push_constants(Arc::clone(&self.pipeline.layout()), 0, [width, height])?;
-
-        let inner = self.inner.read().unwrap();
-        for (index, rect) in clip_rects.into_iter().enumerate() {
-            let (offset_vertex, offset_index) = offsets[index];
-            let (_offset_vertex_end, offset_index_end) = offsets[index + 1];
-
-            if let Some(texture) = inner.textures.get(&IdWrapper::from(texture_ids[index])) {
+        // written directly into the mapped memory, without collecting them into a Vec first
+        let buffers = if meshes.is_empty() {
+            None
+        } else {
+            let vertex_buffer = self.vertices.write_with(vertex_count, |vertices| {
+                meshes
+                    .iter()
+                    .flat_map(|mesh| mesh.vertices.iter())
+                    .zip(vertices)
+                    .for_each(|(vertex, target)| *target = vertex.into());
+            })?;
+            let index_buffer = self.indices.write_with(index_count, |indices| {
+                meshes
+                    .iter()
+                    .flat_map(|mesh| mesh.indices.iter())
+                    .zip(indices)
+                    .for_each(|(index, target)| *target = *index);
+            })?;
+            Some((vertex_buffer, index_buffer))
+        };
+
+        let bind = |builder: &mut AutoCommandBufferBuilder<P>| -> Result<(), DrawError> {
+            if let Some((vertex_buffer, index_buffer)) = &buffers {
                 builder
-                    .set_scissor(
+                    //.next_subpass(SubpassContents::Inline)?
+                    .bind_pipeline_graphics(Arc::clone(pipeline))?
+                    .bind_index_buffer(index_buffer.clone())?
+                    .bind_vertex_buffers(0, vertex_buffer.clone())?
+                    .push_constants(Arc::clone(&pipeline.layout()), 0, [width, height])?;
+            }
+            Ok(())
+        };
+        bind(builder)?;
+
+        let screen = Rect::from_min_size(Pos2::ZERO, Vec2::new(width, height) * pixels_per_point);
+        let inner = textures.0.read().unwrap();
+        let mut binding = DescriptorSetBinding::new(pipeline.layout());
+        for (rect, item) in draws {
+            let index = match item {
+                DrawItem::Mesh(index) => index,
+                DrawItem::Callback(viewport, paint) => {
+                    let viewport = viewport * pixels_per_point;
+                    let clip_rect = (rect * pixels_per_point).intersect(screen);
+                    if clip_rect.width() < 1.0 || clip_rect.height() < 1.0 {
+                        continue;
+                    }
+                    builder
+                        .set_viewport(
+                            0,
+                            [Viewport {
+                                offset: viewport.min.into(),
+                                extent: viewport.size().into(),
+                                depth_range: 0.0..=1.0,
+                            }]
+                            .into_iter()
+                            .collect(),
+                        )?
+                        .set_scissor(
+                            0,
+                            [Scissor {
+                                offset: [clip_rect.min.x as u32, clip_rect.min.y as u32],
+                                extent: [clip_rect.width() as u32, clip_rect.height() as u32],
+                            }]
+                            .into_iter()
+                            .collect(),
+                        )?;
+                    if let Some(paint_callback) = paint_callback {
+                        paint_callback(
+                            builder,
+                            paint,
+                            &EguiCallbackInfo {
+                                viewport,
+                                clip_rect,
+                                pixels_per_point,
+                                screen_size: [screen.width() as u32, screen.height() as u32],
+                            },
+                        )?;
+                    }
+                    builder.set_viewport(
                         0,
-                        [Scissor {
-                            offset: [rect.min.x as u32, rect.min.y as u32],
-                            extent: [rect.width() as u32, rect.height() as u32],
+                        [Viewport {
+                            offset: [0.0, 0.0],
+                            extent: screen.size().into(),
+                            depth_range: 0.0..=1.0,
                         }]
                         .into_iter()
                         .collect(),
-                    )?
-                    .bind_descriptor_sets(
-                        PipelineBindPoint::Graphics,
-                        Arc::clone(&self.pipeline.layout()),
-                        0,
-                        Arc::clone(texture.descriptor()),
-                    )?
-                    .draw_indexed(
-                        (offset_index_end - offset_index) as u32,
-                        1,
-                        offset_index as u32,
-                        offset_vertex as i32,
-                        0,
                     )?;
+                    // the callback might have bound anything
+                    bind(builder)?;
+                    binding = DescriptorSetBinding::new(pipeline.layout());
+                    continue;
+                }
+            };
+
+            let (offset_vertex, offset_index) = offsets[index];
+            let (_offset_vertex_end, offset_index_end) = offsets[index + 1];
+
+            if let Some(texture) = inner
+                .textures
+                .get(&IdWrapper::from(meshes[index].texture_id))
+                .filter(|texture| !self.image_system.is_upload_pending(texture.image()))
+            {
+                // the scissor is not clamped to the viewport by vulkan
+                let rect = (rect * pixels_per_point).intersect(screen);
+                if rect.width() < 1.0 || rect.height() < 1.0 {
+                    continue;
+                }
+                builder.set_scissor(
+                    0,
+                    [Scissor {
+                        offset: [rect.min.x as u32, rect.min.y as u32],
+                        extent: [rect.width() as u32, rect.height() as u32],
+                    }]
+                    .into_iter()
+                    .collect(),
+                )?;
+                binding.bind(builder, texture.descriptor())?;
+                builder.draw_indexed(
+                    (offset_index_end - offset_index) as u32,
+                    1,
+                    offset_index as u32,
+                    offset_vertex as i32,
+                    0,
+                )?;
             }
         }
 
+        // the scissor is dynamic for the other pipelines as well, egui covers the whole viewport
+        let viewport = ViewportRect::full([screen.width() as u32, screen.height() as u32]);
+        builder.set_scissor(0, [viewport.scissor()].into_iter().collect())?;
+
         drop(inner);
-        self.free_textures();
+        Self::free_textures(textures);
         Ok(())
     }
 
-    fn free_textures(&self) {
-        let mut inner = self.inner.write().unwrap();
+    fn free_textures(textures: &EguiTextures) {
+        let mut inner = textures.0.write().unwrap();
         let inner = inner.deref_mut();
         for texture in inner.textures_to_free.drain(..).map(IdWrapper::from) {
             inner.textures.remove(&texture);
@@ -323,8 +690,12 @@ impl EguiPipeline {
         }
     }
 
-    fn update_textures(&self, textures_delta: &TexturesDelta) -> Result<(), UploadError> {
-        let mut inner = self.inner.write().unwrap();
+    fn update_textures(
+        &self,
+        textures: &EguiTextures,
+        textures_delta: &TexturesDelta,
+    ) -> Result<(), UploadError> {
+        let mut inner = textures.0.write().unwrap();
         inner
             .textures_to_free
             .extend(textures_delta.free.iter().copied());
@@ -383,7 +754,8 @@ impl EguiPipeline {
         image: Arc<Image>,
         delta: &ImageDelta,
     ) -> Result<(), Validated<AllocateBufferError>> {
-        self.image_system.enqueue_image_update(
+        // the ui should never wait on the upload of other textures
+        self.image_system.enqueue_image_update_with_priority(
             image,
             delta.pos.map(|[x, y]| {
                 (
@@ -402,6 +774,7 @@ impl EguiPipeline {
                     .flat_map(|c| c.to_array())
                     .collect::<Vec<_>>(),
             },
+            UploadPriority::High,
         )?;
 
         Ok(())
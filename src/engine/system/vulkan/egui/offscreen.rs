@@ -0,0 +1,95 @@
+use super::EguiTextures;
+use crate::ui::egui::ClippedPrimitive;
+use egui::{Context, Pos2, RawInput, Rect, TexturesDelta, Vec2};
+use std::sync::Arc;
+use vulkano::image::view::ImageView;
+use vulkano::image::Image;
+
+/// An egui ui that is rendered into an image instead of the swapchain, for example for in-game
+/// computer screens or portraits. Every target has its own [`Context`] and thereby its own
+/// state, input and textures. Create it with [`EguiPipeline::create_offscreen_target`].
+///
+/// The [`EguiOffscreenTarget::image`] can be shown like any other image, e.g. with
+/// [`TexturedPipeline::prepare_texture`].
+///
+/// [`EguiPipeline::create_offscreen_target`]: super::EguiPipeline::create_offscreen_target
+/// [`TexturedPipeline::prepare_texture`]: crate::engine::system::vulkan::textured::TexturedPipeline::prepare_texture
+pub struct EguiOffscreenTarget {
+    context: Context,
+    pub(super) logical_size: [f32; 2],
+    pub(super) pixels_per_point: f32,
+    pub(super) image_view: Arc<ImageView>,
+    pub(super) textures: EguiTextures,
+    /// [`TexturesDelta`] to upload next, accumulated over all updates since the last upload
+    pub(super) texture_delta: TexturesDelta,
+    /// [`ClippedPrimitive`] to render next
+    pub(super) clipped_primitives: Vec<ClippedPrimitive>,
+}
+
+impl EguiOffscreenTarget {
+    pub(super) fn new(
+        logical_size: [f32; 2],
+        pixels_per_point: f32,
+        image_view: Arc<ImageView>,
+    ) -> Self {
+        let context = Context::default();
+        context.set_pixels_per_point(pixels_per_point);
+        Self {
+            context,
+            logical_size,
+            pixels_per_point,
+            image_view,
+            textures: EguiTextures::default(),
+            texture_delta: TexturesDelta::default(),
+            clipped_primitives: Vec::default(),
+        }
+    }
+
+    #[inline]
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// The image the ui is rendered into.
+    #[inline]
+    pub fn image(&self) -> &Arc<Image> {
+        self.image_view.image()
+    }
+
+    /// The size in points, as seen by the ui.
+    #[inline]
+    pub fn logical_size(&self) -> [f32; 2] {
+        self.logical_size
+    }
+
+    #[inline]
+    pub fn pixels_per_point(&self) -> f32 {
+        self.pixels_per_point
+    }
+
+    /// The size of the [`EguiOffscreenTarget::image`] in pixels.
+    #[inline]
+    pub fn extent(&self) -> [u32; 2] {
+        let [width, height, _] = self.image_view.image().extent();
+        [width, height]
+    }
+
+    /// Runs the ui for the next frame. Because the target is not bound to the window, the `input`
+    /// must be provided by the caller, with pointer positions in points relative to the top left
+    /// corner of the target. The `screen_rect` of the `input` is overwritten.
+    pub fn update(&mut self, mut input: RawInput, ui: impl FnOnce(&Context)) {
+        input.screen_rect = Some(Rect::from_min_size(
+            Pos2::ZERO,
+            Vec2::from(self.logical_size),
+        ));
+
+        let output = self.context.run(input, |ctx| {
+            ui(ctx);
+        });
+
+        self.texture_delta.append(output.textures_delta);
+        self.clipped_primitives = self
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+    }
+}
@@ -1,36 +1,31 @@
 use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
 use crate::engine::system::vulkan::system::{GraphicsPipelineRenderPassInfo, VulkanSystem};
+use crate::engine::system::vulkan::utils::quad::QuadInstancer;
 use crate::engine::system::vulkan::wds::WriteDescriptorSetManager;
 use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError};
 use crate::shader_from_path;
 use bytemuck::{Pod, Zeroable};
 use std::sync::Arc;
-use vulkano::buffer::{IndexBuffer, Subbuffer};
 use vulkano::command_buffer::AutoCommandBufferBuilder;
 use vulkano::descriptor_set::PersistentDescriptorSet;
 use vulkano::device::Device;
 use vulkano::pipeline::cache::PipelineCache;
-use vulkano::pipeline::graphics::color_blend::{
-    AttachmentBlend, ColorBlendAttachmentState, ColorBlendState,
-};
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendAttachmentState};
 use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
-use vulkano::pipeline::graphics::multisample::MultisampleState;
 use vulkano::pipeline::graphics::rasterization::RasterizationState;
-use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition};
+use vulkano::pipeline::graphics::vertex_input::Vertex;
 use vulkano::pipeline::graphics::viewport::ViewportState;
 use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
 use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
 use vulkano::pipeline::{
-    DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
-    PipelineShaderStageCreateInfo,
+    DynamicState, GraphicsPipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
 };
 use vulkano::shader::EntryPoint;
 
 pub struct GlowingBallsPipeline {
     pipeline: Arc<GraphicsPipeline>,
-    buffers_manager: Arc<BasicBuffersManager>,
-    quad_index_buffer: IndexBuffer,
-    quad_vertex_buffer: Subbuffer<[Vertex2d]>,
+    quads: QuadInstancer,
     descriptor_set: Arc<PersistentDescriptorSet>,
 }
 
@@ -59,21 +54,10 @@ impl GlowingBallsPipeline {
     ) -> Result<Self, PipelineCreateError> {
         let pipeline = Self::create_pipeline(Arc::clone(&device), render_pass_info, cache)?;
         Ok(Self {
-            quad_index_buffer: buffers_manager
-                .create_index_buffer([0, 1, 2, 2, 3, 0])?
-                .into(),
-            quad_vertex_buffer: buffers_manager
-                .create_vertex_buffer(vec![
-                    Vertex2d { pos: [-0.5, -0.5] },
-                    Vertex2d { pos: [0.5, -0.5] },
-                    Vertex2d { pos: [0.5, 0.5] },
-                    Vertex2d { pos: [-0.5, 0.5] },
-                ])?
-                .into(),
+            quads: QuadInstancer::new(buffers_manager, PipelineKind::GlowingBalls)?,
             descriptor_set: write_descriptors
                 .create_persistent_descriptor_set(&pipeline.layout().set_layouts()[0])?,
             pipeline,
-            buffers_manager,
         })
     }
 
@@ -85,8 +69,7 @@ impl GlowingBallsPipeline {
         let vs = Self::load_vertex_shader(Arc::clone(&device))?;
         let fs = Self::load_fragment_shader(Arc::clone(&device))?;
 
-        let vertex_input_state = [Vertex2d::per_vertex(), GlowingBall::per_instance()]
-            .definition(&vs.info().input_interface)?;
+        let vertex_input_state = QuadInstancer::vertex_input_state::<GlowingBall>(&vs)?;
 
         let stages = [
             PipelineShaderStageCreateInfo::new(vs),
@@ -111,18 +94,17 @@ impl GlowingBallsPipeline {
                 }),
                 viewport_state: Some(ViewportState::default()),
                 rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState {
-                    rasterization_samples: render_pass_info.rasterization_samples(),
-                    ..MultisampleState::default()
-                }),
-                color_blend_state: Some(ColorBlendState::with_attachment_states(
-                    render_pass_info.num_color_attachments(),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(render_pass_info.color_blend_state(
                     ColorBlendAttachmentState {
                         blend: Some(AttachmentBlend::alpha()),
                         ..ColorBlendAttachmentState::default()
                     },
                 )),
-                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                    .into_iter()
+                    .collect(),
+                depth_stencil_state: render_pass_info.depth_stencil_state(),
                 subpass: Some(render_pass_info.into_subpass_type()),
                 ..GraphicsPipelineCreateInfo::layout(layout)
             },
@@ -154,38 +136,15 @@ impl GlowingBallsPipeline {
         I: IntoIterator<Item = GlowingBall>,
         I::IntoIter: ExactSizeIterator,
     {
-        let vertex_buffer = self.buffers_manager.create_vertex_buffer(balls)?;
-        let instance_count = vertex_buffer.len() as u32;
-
-        builder
-            .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                Arc::clone(&self.pipeline.layout()),
-                0,
-                Arc::clone(&self.descriptor_set),
-            )?
-            .bind_index_buffer(self.quad_index_buffer.clone())?
-            .bind_vertex_buffers(
-                0,
-                [
-                    self.quad_vertex_buffer.as_bytes().clone(),
-                    vertex_buffer.into_bytes(),
-                ],
-            )?
-            .draw_indexed(6, instance_count, 0, 0, 0)?;
-
-        Ok(())
+        self.quads.draw(
+            builder,
+            &self.pipeline,
+            Arc::clone(&self.descriptor_set),
+            balls,
+        )
     }
 }
 
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
-pub struct Vertex2d {
-    #[format(R32G32_SFLOAT)]
-    pos: [f32; 2],
-}
-
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
 pub struct GlowingBall {
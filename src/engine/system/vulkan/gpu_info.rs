@@ -0,0 +1,155 @@
+use crate::engine::system::vulkan::system::VulkanSystem;
+use std::fmt::{Display, Formatter};
+use vulkano::device::physical::PhysicalDeviceType;
+use vulkano::device::{DeviceExtensions, Features};
+use vulkano::format::Format;
+use vulkano::memory::MemoryHeapFlags;
+use vulkano::swapchain::ColorSpace;
+use vulkano::{DeviceSize, Version};
+
+/// Information about the GPU and driver chosen by the [`VulkanSystem`], to be shown in an
+/// about or diagnostics screen and in logs. The [`Display`] implementation formats it as one
+/// `key: value` pair per line.
+#[derive(Debug, Clone)]
+pub struct GpuInfo {
+    pub device_name: String,
+    pub device_type: PhysicalDeviceType,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub driver_name: Option<String>,
+    pub driver_info: Option<String>,
+    /// The raw driver version, its encoding depends on the vendor, see
+    /// [`GpuInfo::driver_version`]
+    pub driver_version_raw: u32,
+    pub api_version: Version,
+    pub enabled_extensions: DeviceExtensions,
+    pub enabled_features: Features,
+    /// The format and color space of the swapchain images, [`None`] for a headless system
+    pub swapchain_format: Option<(Format, ColorSpace)>,
+    /// All formats and color spaces supported by the surface of the window, empty for a
+    /// headless system
+    pub surface_formats: Vec<(Format, ColorSpace)>,
+    pub memory_heaps: Vec<GpuMemoryHeap>,
+}
+
+impl GpuInfo {
+    const VENDOR_ID_NVIDIA: u32 = 0x10DE;
+    const VENDOR_ID_INTEL: u32 = 0x8086;
+
+    pub fn new(vulkan: &VulkanSystem) -> Self {
+        let device = vulkan.device();
+        let physical = device.physical_device();
+        let properties = physical.properties();
+        let swapchain = vulkan.swapchain();
+
+        let surface_formats = swapchain
+            .map(|swapchain| {
+                physical
+                    .surface_formats(swapchain.surface(), Default::default())
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to retrieve the surface formats: {e}");
+                        Vec::new()
+                    })
+            })
+            .unwrap_or_default();
+
+        Self {
+            device_name: properties.device_name.clone(),
+            device_type: properties.device_type,
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            driver_name: properties.driver_name.clone(),
+            driver_info: properties.driver_info.clone(),
+            driver_version_raw: properties.driver_version,
+            api_version: device.api_version(),
+            enabled_extensions: *device.enabled_extensions(),
+            enabled_features: *device.enabled_features(),
+            swapchain_format: swapchain
+                .map(|swapchain| (swapchain.image_format(), swapchain.image_color_space())),
+            surface_formats,
+            memory_heaps: physical
+                .memory_properties()
+                .memory_heaps
+                .iter()
+                .map(|heap| GpuMemoryHeap {
+                    size: heap.size,
+                    device_local: heap.flags.intersects(MemoryHeapFlags::DEVICE_LOCAL),
+                })
+                .collect(),
+        }
+    }
+
+    /// The driver version decoded according to the conventions of the vendor.
+    pub fn driver_version(&self) -> String {
+        let v = self.driver_version_raw;
+        match self.vendor_id {
+            Self::VENDOR_ID_NVIDIA => format!(
+                "{}.{}.{}.{}",
+                v >> 22,
+                (v >> 14) & 0xFF,
+                (v >> 6) & 0xFF,
+                v & 0x3F
+            ),
+            Self::VENDOR_ID_INTEL if cfg!(windows) => format!("{}.{}", v >> 14, v & 0x3FFF),
+            _ => format!("{}.{}.{}", v >> 22, (v >> 12) & 0x3FF, v & 0xFFF),
+        }
+    }
+
+    /// The total size of the heaps that are local to the device, the video memory on discrete
+    /// GPUs.
+    #[inline]
+    pub fn device_local_memory(&self) -> DeviceSize {
+        self.memory_heaps
+            .iter()
+            .filter(|heap| heap.device_local)
+            .map(|heap| heap.size)
+            .sum()
+    }
+}
+
+impl Display for GpuInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "GPU: {} ({:?})", self.device_name, self.device_type)?;
+        writeln!(
+            f,
+            "Vendor/Device: 0x{:04x}/0x{:04x}",
+            self.vendor_id, self.device_id
+        )?;
+        writeln!(
+            f,
+            "Driver: {} {} ({})",
+            self.driver_name.as_deref().unwrap_or("unknown"),
+            self.driver_version(),
+            self.driver_info.as_deref().unwrap_or_default(),
+        )?;
+        writeln!(f, "Vulkan API: {}", self.api_version)?;
+        match self.swapchain_format {
+            Some((format, color_space)) => {
+                writeln!(f, "Swapchain format: {format:?} {color_space:?}")?
+            }
+            None => writeln!(f, "Swapchain format: headless")?,
+        }
+        writeln!(f, "Surface formats: {:?}", self.surface_formats)?;
+        for (index, heap) in self.memory_heaps.iter().enumerate() {
+            writeln!(
+                f,
+                "Memory heap {index}: {} MiB{}",
+                heap.size / (1024 * 1024),
+                if heap.device_local {
+                    " (device local)"
+                } else {
+                    ""
+                }
+            )?;
+        }
+        writeln!(f, "Enabled extensions: {:?}", self.enabled_extensions)?;
+        writeln!(f, "Enabled features: {:?}", self.enabled_features)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GpuMemoryHeap {
+    pub size: DeviceSize,
+    /// Whether the heap is local to the device, otherwise it is host memory visible to the device
+    pub device_local: bool,
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+use std::time::Duration;
+use vulkano::command_buffer::allocator::CommandBufferAllocator;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::device::{DeviceOwned, Queue};
+use vulkano::query::{QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType};
+use vulkano::sync::PipelineStage;
+
+/// Measures how long the GPU takes to execute the commands of a frame with a pair of timestamp
+/// queries per frame. The results are read when the queries are reused a few frames later, so
+/// reading them never stalls the CPU.
+pub(crate) struct GpuTimer {
+    query_pool: Arc<QueryPool>,
+    /// Nanoseconds per timestamp tick
+    period: f64,
+    /// The bits of the timestamps that are valid
+    mask: u64,
+    /// Whether the queries of a slot were written but not yet read
+    pending: [bool; Self::SLOTS as usize],
+    next: u32,
+    latest: Option<Duration>,
+}
+
+impl GpuTimer {
+    /// More than the frames that can be in flight at once
+    const SLOTS: u32 = 4;
+
+    /// [`None`] if the queue does not support timestamps.
+    pub(crate) fn new(queue: &Queue) -> Option<Self> {
+        let device = queue.device();
+        let valid_bits = device.physical_device().queue_family_properties()
+            [queue.queue_family_index() as usize]
+            .timestamp_valid_bits?;
+        let period = device.physical_device().properties().timestamp_period;
+
+        let query_pool = QueryPool::new(
+            Arc::clone(device),
+            QueryPoolCreateInfo {
+                query_count: Self::SLOTS * 2,
+                ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+            },
+        )
+        .map_err(|e| warn!("Failed to create the timestamp queries, no GPU frame times: {e}"))
+        .ok()?;
+
+        Some(Self {
+            query_pool,
+            period: f64::from(period),
+            mask: u64::MAX.checked_shr(64 - valid_bits).unwrap_or(u64::MAX),
+            pending: [false; Self::SLOTS as usize],
+            next: 0,
+            latest: None,
+        })
+    }
+
+    /// The time the GPU took for the most recent frame whose results are available.
+    #[inline]
+    pub(crate) fn latest(&self) -> Option<Duration> {
+        self.latest
+    }
+
+    /// Reads the results of the next slot and writes the first timestamp into it. Returns the
+    /// slot to pass to [`GpuTimer::end`], or [`None`] if the previous queries of the slot are
+    /// still in flight and must not be reset yet. Must be called outside a render pass.
+    pub(crate) fn begin<L, A: CommandBufferAllocator>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+    ) -> Option<u32> {
+        let slot = self.next;
+        let queries = slot * 2..slot * 2 + 2;
+
+        if self.pending[slot as usize] {
+            let mut timestamps = [0u64; 2];
+            match self.query_pool.get_results(
+                queries.clone(),
+                &mut timestamps,
+                QueryResultFlags::empty(),
+            ) {
+                Ok(true) => {
+                    self.pending[slot as usize] = false;
+                    let ticks = timestamps[1].wrapping_sub(timestamps[0]) & self.mask;
+                    self.latest = Some(Duration::from_nanos((ticks as f64 * self.period) as u64));
+                }
+                Ok(false) => return None,
+                Err(e) => {
+                    warn!("Failed to read the timestamp queries: {e}");
+                    return None;
+                }
+            }
+        }
+
+        // SAFETY: the queries of the slot are either unused or their results were read above,
+        //         so they are not used by a command buffer in flight
+        let written = unsafe {
+            builder
+                .reset_query_pool(Arc::clone(&self.query_pool), queries.clone())
+                .and_then(|builder| {
+                    builder.write_timestamp(
+                        Arc::clone(&self.query_pool),
+                        queries.start,
+                        PipelineStage::TopOfPipe,
+                    )
+                })
+        };
+
+        match written {
+            Ok(_) => {
+                self.next = (slot + 1) % Self::SLOTS;
+                Some(slot)
+            }
+            Err(e) => {
+                warn!("Failed to write the timestamp query: {e}");
+                None
+            }
+        }
+    }
+
+    /// Writes the second timestamp into the slot once all previous commands completed.
+    pub(crate) fn end<L, A: CommandBufferAllocator>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+        slot: u32,
+    ) {
+        // SAFETY: the query was reset in GpuTimer::begin
+        let written = unsafe {
+            builder.write_timestamp(
+                Arc::clone(&self.query_pool),
+                slot * 2 + 1,
+                PipelineStage::BottomOfPipe,
+            )
+        };
+        match written {
+            Ok(_) => self.pending[slot as usize] = true,
+            Err(e) => warn!("Failed to write the timestamp query: {e}"),
+        }
+    }
+
+    /// The command buffer with the queries of the slot was not submitted, so the results will
+    /// never become available.
+    #[inline]
+    pub(crate) fn discard(&mut self, slot: u32) {
+        self.pending[slot as usize] = false;
+    }
+}
@@ -1,21 +1,22 @@
+use crate::engine::system::arena::FrameArena;
 use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
+use crate::engine::system::vulkan::streaming::StreamingBuffer;
 use crate::engine::system::vulkan::system::{GraphicsPipelineRenderPassInfo, VulkanSystem};
 use crate::engine::system::vulkan::wds::WriteDescriptorSetManager;
 use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError};
 use crate::shader_from_path;
 use bytemuck::{Pod, Zeroable};
 use std::sync::Arc;
+use vulkano::buffer::Subbuffer;
 use vulkano::command_buffer::AutoCommandBufferBuilder;
 use vulkano::descriptor_set::PersistentDescriptorSet;
 use vulkano::device::{Device, Features};
 use vulkano::pipeline::cache::PipelineCache;
-use vulkano::pipeline::graphics::color_blend::{
-    AttachmentBlend, ColorBlendAttachmentState, ColorBlendState,
-};
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendAttachmentState};
 use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
-use vulkano::pipeline::graphics::multisample::MultisampleState;
 use vulkano::pipeline::graphics::rasterization::RasterizationState;
-use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition};
+use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition, VertexInputState};
 use vulkano::pipeline::graphics::viewport::ViewportState;
 use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
 use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
@@ -28,7 +29,13 @@ use vulkano::shader::EntryPoint;
 pub struct LinePipeline {
     pipeline: Arc<GraphicsPipeline>,
     buffers_manager: Arc<BasicBuffersManager>,
+    vertices: StreamingBuffer<Vertex2d>,
     descriptor_set: Arc<PersistentDescriptorSet>,
+    /// Draws [`LineSegment`]s as instances of a single line
+    segment_pipeline: Arc<GraphicsPipeline>,
+    segment_descriptor_set: Arc<PersistentDescriptorSet>,
+    segment_ends: Subbuffer<[SegmentEnd]>,
+    segments: StreamingBuffer<LineSegment>,
 }
 
 impl TryFrom<&VulkanSystem> for LinePipeline {
@@ -59,11 +66,40 @@ impl LinePipeline {
         write_descriptors: &WriteDescriptorSetManager,
         buffers_manager: Arc<BasicBuffersManager>,
     ) -> Result<Self, PipelineCreateError> {
-        let pipeline = Self::create_pipeline(Arc::clone(&device), render_pass_info, cache)?;
+        let vs = Self::load_vertex_shader(Arc::clone(&device))?;
+        let vertex_input_state = Vertex2d::per_vertex().definition(&vs.info().input_interface)?;
+        let pipeline = Self::create_pipeline(
+            Arc::clone(&device),
+            render_pass_info.clone(),
+            cache.clone(),
+            vs,
+            vertex_input_state,
+            PrimitiveTopology::LineStrip,
+        )?;
+
+        let vs = Self::load_segments_vertex_shader(Arc::clone(&device))?;
+        let vertex_input_state = [SegmentEnd::per_vertex(), LineSegment::per_instance()]
+            .definition(&vs.info().input_interface)?;
+        let segment_pipeline = Self::create_pipeline(
+            Arc::clone(&device),
+            render_pass_info,
+            cache,
+            vs,
+            vertex_input_state,
+            PrimitiveTopology::LineList,
+        )?;
+
         Ok(Self {
             descriptor_set: write_descriptors
                 .create_persistent_descriptor_set(&pipeline.layout().set_layouts()[0])?,
+            segment_descriptor_set: write_descriptors
+                .create_persistent_descriptor_set(&segment_pipeline.layout().set_layouts()[0])?,
+            segment_ends: buffers_manager
+                .create_vertex_buffer([SegmentEnd { end: 0.0 }, SegmentEnd { end: 1.0 }])?,
+            vertices: StreamingBuffer::vertices(Arc::clone(&buffers_manager), 4 * 1024),
+            segments: StreamingBuffer::vertices(Arc::clone(&buffers_manager), 1024),
             pipeline,
+            segment_pipeline,
             buffers_manager,
         })
     }
@@ -72,12 +108,12 @@ impl LinePipeline {
         device: Arc<Device>,
         render_pass_info: GraphicsPipelineRenderPassInfo,
         cache: Option<Arc<PipelineCache>>,
+        vs: EntryPoint,
+        vertex_input_state: VertexInputState,
+        topology: PrimitiveTopology,
     ) -> Result<Arc<GraphicsPipeline>, PipelineCreateError> {
-        let vs = Self::load_vertex_shader(Arc::clone(&device))?;
         let fs = Self::load_fragment_shader(Arc::clone(&device))?;
 
-        let vertex_input_state = Vertex2d::per_vertex().definition(&vs.info().input_interface)?;
-
         let stages = [
             PipelineShaderStageCreateInfo::new(vs),
             PipelineShaderStageCreateInfo::new(fs),
@@ -96,23 +132,22 @@ impl LinePipeline {
                 stages: stages.into_iter().collect(),
                 vertex_input_state: Some(vertex_input_state),
                 input_assembly_state: Some(InputAssemblyState {
-                    topology: PrimitiveTopology::LineStrip,
+                    topology,
                     ..InputAssemblyState::default()
                 }),
                 viewport_state: Some(ViewportState::default()),
                 rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState {
-                    rasterization_samples: render_pass_info.rasterization_samples(),
-                    ..MultisampleState::default()
-                }),
-                color_blend_state: Some(ColorBlendState::with_attachment_states(
-                    render_pass_info.num_color_attachments(),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(render_pass_info.color_blend_state(
                     ColorBlendAttachmentState {
                         blend: Some(AttachmentBlend::alpha()),
                         ..ColorBlendAttachmentState::default()
                     },
                 )),
-                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                    .into_iter()
+                    .collect(),
+                depth_stencil_state: render_pass_info.depth_stencil_state(),
                 subpass: Some(render_pass_info.into_subpass_type()),
                 ..GraphicsPipelineCreateInfo::layout(layout)
             },
@@ -127,6 +162,14 @@ impl LinePipeline {
         )
     }
 
+    fn load_segments_vertex_shader(device: Arc<Device>) -> Result<EntryPoint, ShaderLoadError> {
+        shader_from_path!(
+            device,
+            "vertex",
+            "src/engine/system/vulkan/lines/segments.vert"
+        )
+    }
+
     fn load_fragment_shader(device: Arc<Device>) -> Result<EntryPoint, ShaderLoadError> {
         shader_from_path!(
             device,
@@ -140,13 +183,45 @@ impl LinePipeline {
         builder: &mut AutoCommandBufferBuilder<P>,
         lines: &[Line],
     ) -> Result<(), DrawError> {
-        let mut offset = 0;
-        let vertex_buffer = self.buffers_manager.create_vertex_buffer(
+        self.record(builder, lines)
+    }
+
+    /// Same as [`LinePipeline::draw`], which writes the vertices directly into a
+    /// [`StreamingBuffer`], the [`FrameArena`] is not needed anymore.
+    pub fn draw_in<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        _arena: &FrameArena,
+        lines: &[Line],
+    ) -> Result<(), DrawError> {
+        self.record(builder, lines)
+    }
+
+    fn record<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        lines: &[Line],
+    ) -> Result<(), DrawError> {
+        if !self
+            .buffers_manager
+            .draw_filter()
+            .admit(PipelineKind::Line, lines.len())
+        {
+            return Ok(());
+        }
+        let vertex_count = lines.iter().map(|l| l.vertices.len()).sum::<usize>();
+        if vertex_count == 0 {
+            return Ok(());
+        }
+        // written directly into the mapped memory, without collecting them into a Vec first
+        let vertex_buffer = self.vertices.write_with(vertex_count, |vertices| {
             lines
                 .iter()
-                .flat_map(|l| l.vertices.iter().copied())
-                .collect::<Vec<_>>(),
-        )?;
+                .flat_map(|l| l.vertices.iter())
+                .zip(vertices)
+                .for_each(|(vertex, target)| *target = *vertex);
+        })?;
+        let mut offset = 0;
 
         builder
             .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
@@ -158,20 +233,78 @@ impl LinePipeline {
                 Arc::clone(&self.descriptor_set),
             )?;
 
+        let mut color = None;
         for line in lines {
-            builder
-                .push_constants(
-                    Arc::clone(&self.pipeline.layout()),
-                    0,
-                    [line.color[0], line.color[1], line.color[2], line.color[3]],
-                )?
-                .draw(line.vertices.len() as u32, 1, offset, 0)?;
+            // push constants stay valid between draw calls
+            if color != Some(line.color) {
+                builder.push_constants(Arc::clone(self.pipeline.layout()), 0, line.color)?;
+                color = Some(line.color);
+            }
+            builder.draw(line.vertices.len() as u32, 1, offset, 0)?;
 
             offset += line.vertices.len() as u32;
         }
 
         Ok(())
     }
+
+    /// Draws all segments with a single instanced draw call, which is much cheaper than one draw
+    /// call per [`Line`] for debug overlays with thousands of segments.
+    pub fn draw_segments<P, I>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        segments: I,
+    ) -> Result<(), DrawError>
+    where
+        I: IntoIterator<Item = LineSegment>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let segments = segments.into_iter();
+        if segments.len() == 0 {
+            return Ok(());
+        }
+        if !self
+            .buffers_manager
+            .draw_filter()
+            .admit(PipelineKind::Line, segments.len())
+        {
+            return Ok(());
+        }
+
+        let instance_buffer = self.segments.write(segments)?;
+        let instance_count = instance_buffer.len() as u32;
+
+        builder
+            .bind_pipeline_graphics(Arc::clone(&self.segment_pipeline))?
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                Arc::clone(self.segment_pipeline.layout()),
+                0,
+                Arc::clone(&self.segment_descriptor_set),
+            )?
+            .bind_vertex_buffers(
+                0,
+                [
+                    self.segment_ends.as_bytes().clone(),
+                    instance_buffer.into_bytes(),
+                ],
+            )?
+            .draw(2, instance_count, 0, 0)?;
+
+        Ok(())
+    }
+
+    /// Like [`LinePipeline::draw`], but splits the lines into [`LineSegment`]s in the
+    /// [`FrameArena`] and draws them with [`LinePipeline::draw_segments`].
+    pub fn draw_batched<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        arena: &FrameArena,
+        lines: &[Line],
+    ) -> Result<(), DrawError> {
+        let segments = arena.alloc_slice_from_iter(lines.iter().flat_map(Line::segments));
+        self.draw_segments(builder, segments.iter().copied())
+    }
 }
 
 #[repr(C)]
@@ -185,3 +318,39 @@ pub struct Line {
     pub vertices: Vec<Vertex2d>,
     pub color: [f32; 4],
 }
+
+impl Line {
+    /// The segments between the consecutive vertices of this line strip.
+    pub fn segments(&self) -> impl Iterator<Item = LineSegment> + '_ {
+        self.vertices
+            .windows(2)
+            .map(|pair| LineSegment::new(pair[0].pos, pair[1].pos, self.color))
+    }
+}
+
+/// A single line from one point to another, see [`LinePipeline::draw_segments`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
+pub struct LineSegment {
+    #[format(R32G32_SFLOAT)]
+    pub from: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    pub to: [f32; 2],
+    #[format(R32G32B32A32_SFLOAT)]
+    pub color: [f32; 4],
+}
+
+impl LineSegment {
+    #[inline]
+    pub fn new(from: [f32; 2], to: [f32; 2], color: [f32; 4]) -> Self {
+        Self { from, to, color }
+    }
+}
+
+/// Whether the vertex is the start (`0.0`) or the end (`1.0`) of a [`LineSegment`]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
+struct SegmentEnd {
+    #[format(R32_SFLOAT)]
+    end: f32,
+}
@@ -0,0 +1,26 @@
+#version 450
+
+// per vertex data, 0.0 for the start and 1.0 for the end of the segment
+layout(location = 0) in float end;
+
+// per instance data
+layout(location = 1) in vec2 from;
+layout(location = 2) in vec2 to;
+layout(location = 3) in vec4 color;
+
+layout(location = 0) out vec4 out_color;
+
+layout(binding = 101) uniform WindowProperties { vec2 screen_size; } window;
+
+void main() {
+    vec2 pos = mix(from, to, end);
+
+    gl_Position = vec4(
+    2.0 * pos.x / window.screen_size.x - 1.0,
+    2.0 * pos.y / window.screen_size.y - 1.0,
+    0.0,
+    1.0
+    );
+
+    out_color = color;
+}
@@ -0,0 +1,198 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use vulkano::device::{Device, DeviceOwned};
+use vulkano::memory::allocator::{
+    AllocationCreateInfo, AllocationType, DeviceLayout, MemoryAlloc, MemoryAllocator,
+    MemoryAllocatorError, MemoryTypeFilter, StandardMemoryAllocator,
+};
+use vulkano::memory::{DedicatedAllocation, ExternalMemoryHandleTypes, MemoryRequirements};
+use vulkano::DeviceSize;
+
+/// The kind of resource an allocation was made for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MemoryUsage {
+    /// Buffers and linear images
+    Linear,
+    /// Optimal images
+    NonLinear,
+    /// Either linear or non-linear
+    Unknown,
+    /// Allocations with their own block of device memory
+    Dedicated,
+}
+
+impl MemoryUsage {
+    #[inline]
+    const fn index(self) -> usize {
+        match self {
+            MemoryUsage::Linear => 0,
+            MemoryUsage::NonLinear => 1,
+            MemoryUsage::Unknown => 2,
+            MemoryUsage::Dedicated => 3,
+        }
+    }
+
+    fn of(allocation: &MemoryAlloc) -> (Self, DeviceSize) {
+        match &allocation.suballocation {
+            Some(suballocation) => (
+                match suballocation.allocation_type {
+                    AllocationType::Linear => MemoryUsage::Linear,
+                    AllocationType::NonLinear => MemoryUsage::NonLinear,
+                    AllocationType::Unknown => MemoryUsage::Unknown,
+                },
+                suballocation.size,
+            ),
+            None => (
+                MemoryUsage::Dedicated,
+                allocation.device_memory.allocation_size(),
+            ),
+        }
+    }
+}
+
+/// A snapshot of the statistics of the [`InstrumentedMemoryAllocator`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The amount of allocations since the creation of the allocator
+    pub allocations: u64,
+    /// The amount of deallocations since the creation of the allocator
+    pub deallocations: u64,
+    /// The amount of currently allocated bytes per [`MemoryUsage`]
+    bytes: [DeviceSize; 4],
+}
+
+impl MemoryStats {
+    /// The amount of allocations that have not been deallocated yet.
+    #[inline]
+    pub fn live_allocations(&self) -> u64 {
+        self.allocations.saturating_sub(self.deallocations)
+    }
+
+    /// The amount of currently allocated bytes for the given [`MemoryUsage`].
+    #[inline]
+    pub fn bytes(&self, usage: MemoryUsage) -> DeviceSize {
+        self.bytes[usage.index()]
+    }
+
+    /// The amount of currently allocated bytes for all [`MemoryUsage`]s.
+    #[inline]
+    pub fn bytes_total(&self) -> DeviceSize {
+        self.bytes.iter().sum()
+    }
+}
+
+/// Wraps the [`StandardMemoryAllocator`] to collect [`MemoryStats`]. The [`VulkanSystem`] owns a
+/// single instance which is shared by all its sub-systems and pipelines.
+///
+/// [`VulkanSystem`]: crate::engine::system::vulkan::system::VulkanSystem
+pub struct InstrumentedMemoryAllocator {
+    inner: StandardMemoryAllocator,
+    allocations: AtomicU64,
+    deallocations: AtomicU64,
+    bytes: [AtomicU64; 4],
+}
+
+impl InstrumentedMemoryAllocator {
+    pub fn new_default(device: Arc<Device>) -> Self {
+        Self {
+            inner: StandardMemoryAllocator::new_default(device),
+            allocations: AtomicU64::default(),
+            deallocations: AtomicU64::default(),
+            bytes: Default::default(),
+        }
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        MemoryStats {
+            allocations: self.allocations.load(Ordering::Relaxed),
+            deallocations: self.deallocations.load(Ordering::Relaxed),
+            bytes: [0, 1, 2, 3].map(|index| self.bytes[index].load(Ordering::Relaxed)),
+        }
+    }
+
+    fn on_allocated(
+        &self,
+        result: Result<MemoryAlloc, MemoryAllocatorError>,
+    ) -> Result<MemoryAlloc, MemoryAllocatorError> {
+        if let Ok(allocation) = &result {
+            let (usage, size) = MemoryUsage::of(allocation);
+            self.allocations.fetch_add(1, Ordering::Relaxed);
+            self.bytes[usage.index()].fetch_add(size, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn on_deallocate(&self, allocation: &MemoryAlloc) {
+        let (usage, size) = MemoryUsage::of(allocation);
+        self.deallocations.fetch_add(1, Ordering::Relaxed);
+        self.bytes[usage.index()].fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+unsafe impl MemoryAllocator for InstrumentedMemoryAllocator {
+    #[inline]
+    fn find_memory_type_index(
+        &self,
+        memory_type_bits: u32,
+        filter: MemoryTypeFilter,
+    ) -> Option<u32> {
+        self.inner.find_memory_type_index(memory_type_bits, filter)
+    }
+
+    fn allocate_from_type(
+        &self,
+        memory_type_index: u32,
+        layout: DeviceLayout,
+        allocation_type: AllocationType,
+        never_allocate: bool,
+    ) -> Result<MemoryAlloc, MemoryAllocatorError> {
+        self.on_allocated(self.inner.allocate_from_type(
+            memory_type_index,
+            layout,
+            allocation_type,
+            never_allocate,
+        ))
+    }
+
+    fn allocate(
+        &self,
+        requirements: MemoryRequirements,
+        allocation_type: AllocationType,
+        create_info: AllocationCreateInfo,
+        dedicated_allocation: Option<DedicatedAllocation<'_>>,
+    ) -> Result<MemoryAlloc, MemoryAllocatorError> {
+        self.on_allocated(self.inner.allocate(
+            requirements,
+            allocation_type,
+            create_info,
+            dedicated_allocation,
+        ))
+    }
+
+    fn allocate_dedicated(
+        &self,
+        memory_type_index: u32,
+        allocation_size: DeviceSize,
+        dedicated_allocation: Option<DedicatedAllocation<'_>>,
+        export_handle_types: ExternalMemoryHandleTypes,
+    ) -> Result<MemoryAlloc, MemoryAllocatorError> {
+        self.on_allocated(self.inner.allocate_dedicated(
+            memory_type_index,
+            allocation_size,
+            dedicated_allocation,
+            export_handle_types,
+        ))
+    }
+
+    unsafe fn deallocate(&self, allocation: MemoryAlloc) {
+        self.on_deallocate(&allocation);
+        self.inner.deallocate(allocation)
+    }
+}
+
+unsafe impl DeviceOwned for InstrumentedMemoryAllocator {
+    #[inline]
+    fn device(&self) -> &Arc<Device> {
+        self.inner.device()
+    }
+}
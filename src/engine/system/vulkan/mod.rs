@@ -1,21 +1,39 @@
+use crate::engine::builder::RenderMode;
 use vulkano::buffer::AllocateBufferError;
-use vulkano::image::AllocateImageError;
+use vulkano::command_buffer::CommandBufferExecError;
+use vulkano::format::Format;
+use vulkano::image::{AllocateImageError, ImageUsage};
 use vulkano::pipeline::layout::IntoPipelineLayoutCreateInfoError;
+use vulkano::sync::HostAccessError;
 use vulkano::{Validated, ValidationError, VulkanError};
 
 pub mod desc;
 pub mod utils;
 
+pub mod backend;
 pub mod beautiful_lines;
+pub mod buffer_pool;
 pub mod buffers;
+pub mod color_filter;
+pub mod destruction;
+pub mod draw_filter;
 #[cfg(feature = "ui-egui")]
 pub mod egui;
 pub mod glowing_balls;
+pub mod gpu_info;
+mod gpu_timer;
 pub mod lines;
+pub mod memory;
+pub mod pipeline_cache;
 pub mod pipelines;
+pub mod render_target;
+mod rendering;
+pub mod sdf_shapes;
+pub mod streaming;
 pub mod system;
 pub mod textured;
 pub mod textures;
+pub mod trails;
 pub mod triangles;
 pub mod wds;
 #[cfg(feature = "world2d")]
@@ -37,8 +55,8 @@ pub enum Error {
     FailedToRetrieveSurfaceCapabilities(Validated<VulkanError>),
     #[error("Failed to retrieve surface formats: {0:?}")]
     FailedToRetrieveSurfaceFormats(Validated<VulkanError>),
-    #[error("Failed to create framebuffers: {0:?}")]
-    FailedToCreateFramebuffers(Validated<VulkanError>),
+    #[error("Failed to create framebuffers: {0}")]
+    FailedToCreateFramebuffers(AttachmentError),
     #[error("Failed to create render pass: {0:?}")]
     RenderPassCreationError(#[from] PipelineCreateError),
     #[error("Failed to allocate Buffer of WriteDescriptor for binding {1}: {0:?}")]
@@ -47,6 +65,16 @@ pub enum Error {
     FailedToUpdateWriteDescriptorBuffer(Box<ValidationError>, u32),
     #[error("Failed to create a (secondary) command buffer: {0:?} ")]
     FailedToCreateCommandBuffer(Validated<VulkanError>),
+    #[error("Failed to create the placeholder images: {0}")]
+    FailedToCreatePlaceholderImages(UploadError),
+    #[error("Failed to create the image of the headless system: {0}")]
+    FailedToCreateHeadlessImage(Validated<AllocateImageError>),
+    #[error("The format of the surface changed from {0:?} to {1:?}")]
+    SurfaceFormatChanged(Format, Format),
+    #[error("Failed to create the intermediate images: {0}")]
+    FailedToCreateIntermediateImages(Validated<AllocateImageError>),
+    #[error("The image usage {0:?} is not supported for the format {1:?}")]
+    UnsupportedImageUsage(ImageUsage, Format),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -58,9 +86,15 @@ pub enum DrawError {
     #[error("Failed to allocate buffer: {0}")]
     BufferAllocateError(#[from] Validated<AllocateBufferError>),
     #[error("Failed to re-create the framebuffers: {0}")]
-    FailedToRecreateTheFramebuffers(Validated<VulkanError>),
+    FailedToRecreateTheFramebuffers(AttachmentError),
+    #[error("Failed to re-create the image of the headless system: {0}")]
+    FailedToRecreateTheHeadlessImage(Validated<AllocateImageError>),
+    #[error("Failed to re-create the intermediate images: {0}")]
+    FailedToRecreateTheIntermediateImages(Validated<AllocateImageError>),
     // #[error("Failed to execute the pipeline: {0}")]
     // PipelineExecutionError(#[from] Validated<VulkanError>),
+    #[error("Failed to create a command buffer: {0}")]
+    FailedToCreateCommandBuffer(Error),
     #[error("Failed to build command buffer: {0}")]
     FailedToBuildCommandBuffer(Validated<VulkanError>),
     #[error("Failed to acquire the next swapchain image: {0}")]
@@ -69,6 +103,53 @@ pub enum DrawError {
     /// is for another reason not presented to the user.
     #[error("Acquiring the next swapchain image ran into the presentation timeout")]
     AcquiringSwapchainImageReachedTimeout,
+    /// The swapchain no longer matches the surface, like after a resize. It is recreated for the
+    /// next frame.
+    #[error("The swapchain is out of date")]
+    SwapchainOutOfDate,
+    #[error("The engine was built for the render mode {0:?}")]
+    RenderModeMismatch(RenderMode),
+    #[error("Failed to create the image of the color filter: {0}")]
+    FailedToCreateTheColorFilterImage(Validated<AllocateImageError>),
+    #[error("Failed to prepare the color filter: {0}")]
+    FailedToPrepareTheColorFilter(Validated<VulkanError>),
+    #[error("The texture was not prepared by the pipeline it is drawn with")]
+    ForeignTexture,
+}
+
+impl DrawError {
+    /// Whether only this frame failed and the following frames are expected to render again,
+    /// like while the window is minimized or after a resize.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            DrawError::AcquiringSwapchainImageReachedTimeout
+                | DrawError::SwapchainOutOfDate
+                | DrawError::FailedToAcquireSwapchainImage(
+                    VulkanError::Timeout | VulkanError::NotReady | VulkanError::OutOfDate
+                )
+        )
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReadbackError {
+    #[error("Only the image of a headless system can be read back")]
+    NotHeadless,
+    #[error("The rendered images do not support TRANSFER_SRC")]
+    MissingTransferSource,
+    #[error("The rendered images have the unsupported format {0:?}")]
+    UnsupportedFormat(Format),
+    #[error("Vulkan Error: {0}")]
+    VulkanError(#[from] Validated<VulkanError>),
+    #[error("Validation Error: {0}")]
+    ValidationError(#[from] Box<ValidationError>),
+    #[error("Failed to allocate buffer: {0}")]
+    BufferAllocateError(#[from] Validated<AllocateBufferError>),
+    #[error("Failed to execute the copy command: {0}")]
+    CommandBufferExecError(#[from] CommandBufferExecError),
+    #[error("Failed to read the buffer: {0}")]
+    HostAccessError(#[from] HostAccessError),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -79,6 +160,17 @@ pub enum UploadError {
     ImageError(#[from] Validated<AllocateImageError>),
     #[error("Failed to allocate buffer: {0}")]
     BufferAllocateError(#[from] Validated<AllocateBufferError>),
+    #[error("Failed to create the attachments: {0}")]
+    AttachmentError(#[from] AttachmentError),
+}
+
+/// Creating the framebuffers or the transient attachments of a pass failed.
+#[derive(thiserror::Error, Debug)]
+pub enum AttachmentError {
+    #[error("Vulkan Error: {0}")]
+    VulkanError(#[from] Validated<VulkanError>),
+    #[error("Failed to allocate the image: {0}")]
+    ImageError(#[from] Validated<AllocateImageError>),
 }
 
 #[derive(thiserror::Error, Debug)]
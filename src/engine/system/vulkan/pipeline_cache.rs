@@ -0,0 +1,111 @@
+//! Persists the compiled pipelines between runs, see [`VulkanSystem::with_pipeline_cache_file`].
+//! Without the cache, the driver compiles every pipeline from scratch on each start, which shows
+//! as stutter whenever a pipeline is created for the first time.
+//!
+//! [`VulkanSystem::with_pipeline_cache_file`]: crate::engine::system::vulkan::system::VulkanSystem::with_pipeline_cache_file
+
+use std::path::Path;
+use std::sync::Arc;
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::device::Device;
+use vulkano::pipeline::cache::{PipelineCache, PipelineCacheCreateInfo};
+use vulkano::{Validated, VulkanError};
+
+/// The size of `VkPipelineCacheHeaderVersionOne`.
+const HEADER_SIZE: usize = 32;
+/// `VK_PIPELINE_CACHE_HEADER_VERSION_ONE`
+const HEADER_VERSION_ONE: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PipelineCacheError {
+    #[error("Vulkan Error: {0}")]
+    VulkanError(#[from] VulkanError),
+    #[error("Failed to create the pipeline cache: {0}")]
+    FailedToCreatePipelineCache(Validated<VulkanError>),
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Creates a pipeline cache with the data of the file. The data is ignored (with a warning) if
+/// the file is missing, was written for another device or driver, or is corrupted, so the cache
+/// starts empty instead.
+pub fn load_pipeline_cache(
+    device: &Arc<Device>,
+    path: &Path,
+) -> Result<Arc<PipelineCache>, PipelineCacheError> {
+    let initial_data = match std::fs::read(path) {
+        Ok(data) if is_compatible(&data, device.physical_device()) => {
+            debug!(
+                "Loaded {} bytes of pipeline cache from {}",
+                data.len(),
+                path.display()
+            );
+            data
+        }
+        Ok(_) => {
+            warn!(
+                "Ignoring the pipeline cache {}, it was written for another device or driver",
+                path.display()
+            );
+            Vec::new()
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            warn!("Failed to read the pipeline cache {}: {e}", path.display());
+            Vec::new()
+        }
+    };
+
+    // SAFETY: the header was checked against the device, the driver validates the rest
+    unsafe {
+        PipelineCache::new(
+            Arc::clone(device),
+            PipelineCacheCreateInfo {
+                initial_data,
+                ..PipelineCacheCreateInfo::default()
+            },
+        )
+    }
+    .map_err(PipelineCacheError::FailedToCreatePipelineCache)
+}
+
+/// Writes the data of the cache into the file, whose directory is created if needed. The data
+/// is written into a temporary file first, so an interrupted write does not leave a truncated
+/// cache behind.
+pub fn save_pipeline_cache(cache: &PipelineCache, path: &Path) -> Result<(), PipelineCacheError> {
+    let data = cache.get_data()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, &data)?;
+    std::fs::rename(&temp, path)?;
+    debug!(
+        "Saved {} bytes of pipeline cache to {}",
+        data.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Whether the header of the data matches the device, as the data is otherwise passed to the
+/// driver unchecked.
+fn is_compatible(data: &[u8], physical_device: &PhysicalDevice) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+    let read_u32 = |offset: usize| {
+        u32::from_ne_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    };
+    let properties = physical_device.properties();
+    read_u32(0) as usize >= HEADER_SIZE
+        && read_u32(4) == HEADER_VERSION_ONE
+        && read_u32(8) == properties.vendor_id
+        && read_u32(12) == properties.device_id
+        && data[16..32] == properties.pipeline_cache_uuid
+}
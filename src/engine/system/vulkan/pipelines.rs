@@ -1,9 +1,13 @@
 use crate::engine::system::vulkan::beautiful_lines::BeautifulLinePipeline;
 use crate::engine::system::vulkan::glowing_balls::GlowingBallsPipeline;
 use crate::engine::system::vulkan::lines::LinePipeline;
+use crate::engine::system::vulkan::sdf_shapes::SdfShapePipeline;
 use crate::engine::system::vulkan::system::VulkanSystem;
 use crate::engine::system::vulkan::textured::TexturedPipeline;
+use crate::engine::system::vulkan::textures::PlaceholderTextures;
+use crate::engine::system::vulkan::trails::TrailPipeline;
 use crate::engine::system::vulkan::triangles::TrianglesPipeline;
+use crate::engine::system::vulkan::world2d::decals::World2dDecalsPipeline;
 use crate::engine::system::vulkan::world2d::entities::World2dEntitiesPipeline;
 use crate::engine::system::vulkan::world2d::terrain::World2dTerrainPipeline;
 use crate::engine::system::vulkan::PipelineCreateError;
@@ -14,8 +18,11 @@ pub struct VulkanPipelines {
     pub triangles: TrianglesPipeline,
     pub beautiful_line: BeautifulLinePipeline,
     pub world2d_terrain: World2dTerrainPipeline,
+    pub world2d_decals: World2dDecalsPipeline,
     pub world2d_entities: World2dEntitiesPipeline,
     pub glowing_balls: GlowingBallsPipeline,
+    pub trail: TrailPipeline,
+    pub sdf_shapes: SdfShapePipeline,
     #[cfg(feature = "ui-egui")]
     pub egui: crate::engine::system::vulkan::egui::EguiPipeline,
 }
@@ -23,17 +30,52 @@ pub struct VulkanPipelines {
 impl TryFrom<&VulkanSystem> for VulkanPipelines {
     type Error = PipelineCreateError;
 
+    #[inline]
     fn try_from(vs: &VulkanSystem) -> Result<Self, Self::Error> {
+        #[cfg(feature = "ui-egui")]
+        {
+            Self::new(vs, Default::default())
+        }
+        #[cfg(not(feature = "ui-egui"))]
+        {
+            Self::new(vs)
+        }
+    }
+}
+
+impl VulkanPipelines {
+    /// Like [`VulkanPipelines::try_from`], but creates the [`EguiPipeline`] with the given
+    /// options, see [`EngineBuilder::with_egui_options`].
+    ///
+    /// [`EguiPipeline`]: crate::engine::system::vulkan::egui::EguiPipeline
+    /// [`EngineBuilder::with_egui_options`]: crate::engine::builder::EngineBuilder::with_egui_options
+    pub fn new(
+        vs: &VulkanSystem,
+        #[cfg(feature = "ui-egui")]
+        egui_options: crate::engine::system::vulkan::egui::EguiPipelineOptions,
+    ) -> Result<Self, PipelineCreateError> {
         Ok(Self {
             line: LinePipeline::try_from(vs)?,
             texture: TexturedPipeline::try_from(vs)?,
             triangles: TrianglesPipeline::try_from(vs)?,
             beautiful_line: BeautifulLinePipeline::try_from(vs)?,
             world2d_terrain: World2dTerrainPipeline::try_from(vs)?,
+            world2d_decals: World2dDecalsPipeline::try_from(vs)?,
             world2d_entities: World2dEntitiesPipeline::try_from(vs)?,
             glowing_balls: GlowingBallsPipeline::try_from(vs)?,
+            trail: TrailPipeline::try_from(vs)?,
+            sdf_shapes: SdfShapePipeline::try_from(vs)?,
             #[cfg(feature = "ui-egui")]
-            egui: crate::engine::system::vulkan::egui::EguiPipeline::try_from(vs)?,
+            egui: crate::engine::system::vulkan::egui::EguiPipeline::with_options(
+                vs,
+                egui_options,
+            )?,
         })
     }
+
+    /// The [`PlaceholderTextures`] of the [`TexturedPipeline`], which is used by the canvas layers.
+    #[inline]
+    pub fn placeholders(&self) -> &PlaceholderTextures<TexturedPipeline> {
+        self.texture.placeholders()
+    }
 }
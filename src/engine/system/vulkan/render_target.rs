@@ -0,0 +1,167 @@
+use crate::engine::system::vulkan::rendering::{RenderingAttachments, RenderingFormat};
+use crate::engine::system::vulkan::textured::TexturedPipeline;
+use crate::engine::system::vulkan::textures::TextureId;
+use std::sync::Arc;
+use vulkano::command_buffer::allocator::CommandBufferAllocator;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, ClearValue, RenderPassBeginInfo, RenderingInfo,
+    SecondaryCommandBufferAbstract, SubpassBeginInfo, SubpassContents, SubpassEndInfo,
+};
+use vulkano::image::Image;
+use vulkano::render_pass::{Framebuffer, RenderPass};
+use vulkano::{Validated, ValidationError, VulkanError};
+
+/// An offscreen image that is rendered into like the frames of the [`VulkanSystem`] (with its
+/// render pass or with dynamic rendering), so the same pipelines can draw into it, for example
+/// for a minimap, a mirror or post effects.
+/// Create it with [`VulkanSystem::create_render_target`].
+///
+/// The commands for the target are recorded with
+/// [`RenderContext::create_render_target_buffer_builder`] and submitted with
+/// [`RenderContext::render_into`]. All targets are rendered before the main render pass, so the
+/// result can be sampled within the same frame, e.g. after turning it into a texture with
+/// [`RenderTarget::texture`].
+///
+/// [`VulkanSystem`]: crate::engine::system::vulkan::system::VulkanSystem
+/// [`VulkanSystem::create_render_target`]: crate::engine::system::vulkan::system::VulkanSystem::create_render_target
+/// [`RenderContext::create_render_target_buffer_builder`]: crate::engine::system::vulkan::system::RenderContext::create_render_target_buffer_builder
+/// [`RenderContext::render_into`]: crate::engine::system::vulkan::system::RenderContext::render_into
+pub struct RenderTarget {
+    /// The resolved image, which can be sampled
+    pub(crate) image: Arc<Image>,
+    pub(crate) attachments: RenderTargetAttachments,
+    pub(crate) clear_values: Vec<Option<ClearValue>>,
+}
+
+pub(crate) enum RenderTargetAttachments {
+    Framebuffer {
+        render_pass: Arc<RenderPass>,
+        framebuffer: Arc<Framebuffer>,
+    },
+    Rendering {
+        format: RenderingFormat,
+        attachments: RenderingAttachments,
+    },
+}
+
+impl RenderTarget {
+    /// The image the target is rendered into.
+    #[inline]
+    pub fn image(&self) -> &Arc<Image> {
+        &self.image
+    }
+
+    /// [`None`] if the [`VulkanSystem`] uses dynamic rendering.
+    ///
+    /// [`VulkanSystem`]: crate::engine::system::vulkan::system::VulkanSystem
+    #[inline]
+    pub fn framebuffer(&self) -> Option<&Arc<Framebuffer>> {
+        match &self.attachments {
+            RenderTargetAttachments::Framebuffer { framebuffer, .. } => Some(framebuffer),
+            RenderTargetAttachments::Rendering { .. } => None,
+        }
+    }
+
+    /// The size of the [`RenderTarget::image`] in pixels.
+    #[inline]
+    pub fn extent(&self) -> [u32; 2] {
+        let [width, height, _] = self.image.extent();
+        [width, height]
+    }
+
+    /// The color the image is cleared with before rendering into it, transparent by default.
+    #[inline]
+    pub fn with_clear_value(mut self, rgba: [f32; 4]) -> Self {
+        self.set_clear_value(rgba);
+        self
+    }
+
+    #[inline]
+    pub fn set_clear_value(&mut self, rgba: [f32; 4]) {
+        self.clear_values[0] = Some(rgba.into());
+    }
+
+    /// How the rendering into the target begins.
+    pub(crate) fn pass_begin(&self) -> PassBegin {
+        match &self.attachments {
+            RenderTargetAttachments::Framebuffer { framebuffer, .. } => {
+                PassBegin::RenderPass(RenderPassBeginInfo {
+                    clear_values: self.clear_values.clone(),
+                    ..RenderPassBeginInfo::framebuffer(Arc::clone(framebuffer))
+                })
+            }
+            RenderTargetAttachments::Rendering { attachments, .. } => {
+                PassBegin::Rendering(attachments.rendering_info(self.clear_values[0].clone()))
+            }
+        }
+    }
+
+    /// A texture of the [`RenderTarget::image`] for the [`TexturedPipeline`]. The texture shows
+    /// the current content of the image, so it only has to be created once.
+    #[inline]
+    pub fn texture(
+        &self,
+        pipeline: &TexturedPipeline,
+    ) -> Result<TextureId<TexturedPipeline>, Validated<VulkanError>> {
+        pipeline.prepare_texture(Arc::clone(&self.image))
+    }
+}
+
+/// The commands to render into a [`RenderTarget`], collected by the render context.
+pub(crate) struct RenderTargetPass {
+    pub(crate) begin: PassBegin,
+    pub(crate) commands: Vec<Arc<dyn SecondaryCommandBufferAbstract>>,
+}
+
+/// A render pass of the classic frame path or a dynamic rendering.
+pub(crate) enum PassBegin {
+    RenderPass(RenderPassBeginInfo),
+    Rendering(RenderingInfo),
+}
+
+impl PassBegin {
+    /// Begins the pass for either the execution of secondary command buffers or for commands
+    /// that are recorded inline.
+    pub(crate) fn begin<L, A: CommandBufferAllocator>(
+        self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+        contents: SubpassContents,
+    ) -> Result<PassEnd, Box<ValidationError>> {
+        match self {
+            PassBegin::RenderPass(info) => {
+                builder.begin_render_pass(
+                    info,
+                    SubpassBeginInfo {
+                        contents,
+                        ..SubpassBeginInfo::default()
+                    },
+                )?;
+                Ok(PassEnd::RenderPass)
+            }
+            PassBegin::Rendering(info) => {
+                builder.begin_rendering(RenderingInfo { contents, ..info })?;
+                Ok(PassEnd::Rendering)
+            }
+        }
+    }
+}
+
+/// Ends a pass that was started with [`PassBegin::begin`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum PassEnd {
+    RenderPass,
+    Rendering,
+}
+
+impl PassEnd {
+    pub(crate) fn end<L, A: CommandBufferAllocator>(
+        self,
+        builder: &mut AutoCommandBufferBuilder<L, A>,
+    ) -> Result<(), Box<ValidationError>> {
+        match self {
+            PassEnd::RenderPass => builder.end_render_pass(SubpassEndInfo::default())?,
+            PassEnd::Rendering => builder.end_rendering()?,
+        };
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+//! The attachments for dynamic rendering (`VK_KHR_dynamic_rendering`), which replace the render
+//! pass and the framebuffers of the classic frame path, see
+//! [`VulkanSystem::with_dynamic_rendering`].
+//!
+//! [`VulkanSystem::with_dynamic_rendering`]: crate::engine::system::vulkan::system::VulkanSystem::with_dynamic_rendering
+
+use crate::engine::system::vulkan::utils::pipeline::RenderPassLayout;
+use crate::engine::system::vulkan::AttachmentError;
+use std::sync::Arc;
+use vulkano::command_buffer::{
+    ClearValue, CommandBufferInheritanceRenderingInfo, RenderingAttachmentInfo,
+    RenderingAttachmentResolveInfo, RenderingInfo,
+};
+use vulkano::format::Format;
+use vulkano::image::view::ImageView;
+use vulkano::image::{Image, ImageCreateInfo, ImageType, ImageUsage, SampleCount};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator};
+use vulkano::render_pass::{AttachmentLoadOp, AttachmentStoreOp};
+
+/// The formats of the attachments, which the pipelines and the secondary command buffers are
+/// created for.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RenderingFormat {
+    pub(crate) color: Format,
+    pub(crate) samples: SampleCount,
+    pub(crate) depth: Option<Format>,
+}
+
+impl From<RenderPassLayout> for RenderingFormat {
+    #[inline]
+    fn from(layout: RenderPassLayout) -> Self {
+        Self {
+            color: layout.color_format,
+            samples: layout.samples,
+            depth: layout.depth_format,
+        }
+    }
+}
+
+impl RenderingFormat {
+    pub(crate) fn inheritance_info(&self) -> CommandBufferInheritanceRenderingInfo {
+        CommandBufferInheritanceRenderingInfo {
+            color_attachment_formats: vec![Some(self.color)],
+            depth_attachment_format: self.depth,
+            rasterization_samples: self.samples,
+            ..CommandBufferInheritanceRenderingInfo::default()
+        }
+    }
+}
+
+/// The image views of a rendering, the counterpart of a framebuffer. Unlike a framebuffer, the
+/// target can be exchanged every frame, so only the transient multisampled color and depth
+/// attachments depend on the size.
+#[derive(Clone)]
+pub(crate) struct RenderingAttachments {
+    /// Rendered into and resolved into the `target`, [`None`] without multisampling
+    multisampled: Option<Arc<ImageView>>,
+    depth: Option<Arc<ImageView>>,
+    target: Arc<ImageView>,
+}
+
+impl RenderingAttachments {
+    pub(crate) fn new(
+        allocator: &Arc<dyn MemoryAllocator>,
+        image: Arc<Image>,
+        format: &RenderingFormat,
+    ) -> Result<Self, AttachmentError> {
+        let extent = image.extent();
+        let multisampled = (format.samples != SampleCount::Sample1)
+            .then(|| {
+                create_transient_attachment(
+                    allocator,
+                    image.format(),
+                    extent,
+                    format.samples,
+                    ImageUsage::COLOR_ATTACHMENT,
+                )
+            })
+            .transpose()?;
+        let depth = format
+            .depth
+            .map(|depth_format| {
+                create_transient_attachment(
+                    allocator,
+                    depth_format,
+                    extent,
+                    format.samples,
+                    ImageUsage::DEPTH_STENCIL_ATTACHMENT,
+                )
+            })
+            .transpose()?;
+        Ok(Self {
+            multisampled,
+            depth,
+            target: ImageView::new_default(image)?,
+        })
+    }
+
+    /// Reuses the transient attachments for another image of the same size, like the next
+    /// swapchain image. Returns [`None`] if the size differs.
+    pub(crate) fn with_target(&self, image: Arc<Image>) -> Option<Result<Self, AttachmentError>> {
+        if image.extent() != self.target.image().extent() {
+            return None;
+        }
+        Some(
+            ImageView::new_default(image)
+                .map(|target| Self {
+                    multisampled: self.multisampled.clone(),
+                    depth: self.depth.clone(),
+                    target,
+                })
+                .map_err(AttachmentError::from),
+        )
+    }
+
+    #[inline]
+    pub(crate) fn extent(&self) -> [u32; 2] {
+        let [width, height, _] = self.target.image().extent();
+        [width, height]
+    }
+
+    /// Clears the color attachment with the clear value and the depth to the far plane, like
+    /// the render pass of the classic path.
+    pub(crate) fn rendering_info(&self, clear_value: Option<ClearValue>) -> RenderingInfo {
+        let color = match &self.multisampled {
+            Some(multisampled) => RenderingAttachmentInfo {
+                load_op: AttachmentLoadOp::Clear,
+                store_op: AttachmentStoreOp::DontCare,
+                clear_value,
+                resolve_info: Some(RenderingAttachmentResolveInfo::image_view(Arc::clone(
+                    &self.target,
+                ))),
+                ..RenderingAttachmentInfo::image_view(Arc::clone(multisampled))
+            },
+            None => RenderingAttachmentInfo {
+                load_op: AttachmentLoadOp::Clear,
+                store_op: AttachmentStoreOp::Store,
+                clear_value,
+                ..RenderingAttachmentInfo::image_view(Arc::clone(&self.target))
+            },
+        };
+        RenderingInfo {
+            color_attachments: vec![Some(color)],
+            depth_attachment: self.depth.as_ref().map(|depth| RenderingAttachmentInfo {
+                load_op: AttachmentLoadOp::Clear,
+                store_op: AttachmentStoreOp::DontCare,
+                clear_value: Some(ClearValue::Depth(1.0)),
+                ..RenderingAttachmentInfo::image_view(Arc::clone(depth))
+            }),
+            ..RenderingInfo::default()
+        }
+    }
+}
+
+/// A multisampled color or a depth attachment, which is only used during the rendering.
+pub(crate) fn create_transient_attachment(
+    allocator: &Arc<dyn MemoryAllocator>,
+    format: Format,
+    extent: [u32; 3],
+    samples: SampleCount,
+    usage: ImageUsage,
+) -> Result<Arc<ImageView>, AttachmentError> {
+    let image = Image::new(
+        Arc::clone(allocator),
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format,
+            extent,
+            usage: usage | ImageUsage::TRANSIENT_ATTACHMENT,
+            samples,
+            ..Default::default()
+        },
+        AllocationCreateInfo::default(),
+    )?;
+    Ok(ImageView::new_default(image)?)
+}
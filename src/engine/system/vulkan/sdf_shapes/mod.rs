@@ -0,0 +1,232 @@
+use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
+use crate::engine::system::vulkan::streaming::StreamingBuffer;
+use crate::engine::system::vulkan::system::{GraphicsPipelineRenderPassInfo, VulkanSystem};
+use crate::engine::system::vulkan::utils::quad::QuadInstancer;
+use crate::engine::system::vulkan::wds::WriteDescriptorSetManager;
+use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError};
+use crate::shader_from_path;
+use bytemuck::{Pod, Zeroable};
+use std::sync::Arc;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Device;
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendAttachmentState};
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::rasterization::RasterizationState;
+use vulkano::pipeline::graphics::vertex_input::Vertex;
+use vulkano::pipeline::graphics::viewport::ViewportState;
+use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
+use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
+use vulkano::pipeline::{
+    DynamicState, GraphicsPipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
+};
+use vulkano::shader::EntryPoint;
+
+/// Renders circles, capsules and boxes with rounded corners in screen coordinates. Each shape is
+/// a single quad, the outline is computed analytically from a signed distance field in the
+/// fragment shader and is therefore anti-aliased at any size.
+pub struct SdfShapePipeline {
+    pipeline: Arc<GraphicsPipeline>,
+    quads: QuadInstancer,
+    instances: StreamingBuffer<SdfShape>,
+    descriptor_set: Arc<PersistentDescriptorSet>,
+}
+
+impl TryFrom<&VulkanSystem> for SdfShapePipeline {
+    type Error = PipelineCreateError;
+
+    #[inline]
+    fn try_from(vs: &VulkanSystem) -> Result<Self, Self::Error> {
+        Self::new(
+            Arc::clone(vs.device()),
+            vs.graphics_pipeline_render_pass_info(),
+            vs.pipeline_cache().map(Arc::clone),
+            vs.write_descriptor_set_manager(),
+            Arc::clone(vs.basic_buffers_manager()),
+        )
+    }
+}
+
+impl SdfShapePipeline {
+    pub fn new(
+        device: Arc<Device>,
+        render_pass_info: GraphicsPipelineRenderPassInfo,
+        cache: Option<Arc<PipelineCache>>,
+        write_descriptors: &WriteDescriptorSetManager,
+        buffers_manager: Arc<BasicBuffersManager>,
+    ) -> Result<Self, PipelineCreateError> {
+        let pipeline = Self::create_pipeline(Arc::clone(&device), render_pass_info, cache)?;
+        Ok(Self {
+            instances: StreamingBuffer::vertices(Arc::clone(&buffers_manager), 1024),
+            quads: QuadInstancer::new(buffers_manager, PipelineKind::SdfShapes)?,
+            descriptor_set: write_descriptors
+                .create_persistent_descriptor_set(&pipeline.layout().set_layouts()[0])?,
+            pipeline,
+        })
+    }
+
+    fn create_pipeline(
+        device: Arc<Device>,
+        render_pass_info: GraphicsPipelineRenderPassInfo,
+        cache: Option<Arc<PipelineCache>>,
+    ) -> Result<Arc<GraphicsPipeline>, PipelineCreateError> {
+        let vs = Self::load_vertex_shader(Arc::clone(&device))?;
+        let fs = Self::load_fragment_shader(Arc::clone(&device))?;
+
+        let vertex_input_state = QuadInstancer::vertex_input_state::<SdfShape>(&vs)?;
+
+        let stages = [
+            PipelineShaderStageCreateInfo::new(vs),
+            PipelineShaderStageCreateInfo::new(fs),
+        ];
+
+        let layout = PipelineLayout::new(
+            Arc::clone(&device),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+                .into_pipeline_layout_create_info(Arc::clone(&device))?,
+        )?;
+
+        Ok(GraphicsPipeline::new(
+            Arc::clone(&device),
+            cache,
+            GraphicsPipelineCreateInfo {
+                stages: stages.into_iter().collect(),
+                vertex_input_state: Some(vertex_input_state),
+                input_assembly_state: Some(InputAssemblyState {
+                    topology: PrimitiveTopology::TriangleList,
+                    ..InputAssemblyState::default()
+                }),
+                viewport_state: Some(ViewportState::default()),
+                rasterization_state: Some(RasterizationState::default()),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(render_pass_info.color_blend_state(
+                    ColorBlendAttachmentState {
+                        blend: Some(AttachmentBlend::alpha()),
+                        ..ColorBlendAttachmentState::default()
+                    },
+                )),
+                dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                    .into_iter()
+                    .collect(),
+                depth_stencil_state: render_pass_info.depth_stencil_state(),
+                subpass: Some(render_pass_info.into_subpass_type()),
+                ..GraphicsPipelineCreateInfo::layout(layout)
+            },
+        )?)
+    }
+
+    fn load_vertex_shader(device: Arc<Device>) -> Result<EntryPoint, ShaderLoadError> {
+        shader_from_path!(
+            device,
+            "vertex",
+            "src/engine/system/vulkan/sdf_shapes/sdf_shapes.vert"
+        )
+    }
+
+    fn load_fragment_shader(device: Arc<Device>) -> Result<EntryPoint, ShaderLoadError> {
+        shader_from_path!(
+            device,
+            "fragment",
+            "src/engine/system/vulkan/sdf_shapes/sdf_shapes.frag"
+        )
+    }
+
+    pub fn draw<P, I>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        shapes: I,
+    ) -> Result<(), DrawError>
+    where
+        I: IntoIterator<Item = SdfShape>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.quads.draw_streamed(
+            builder,
+            &self.pipeline,
+            Arc::clone(&self.descriptor_set),
+            shapes,
+            &self.instances,
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum SdfShapeKind {
+    Circle = 0,
+    /// A box whose shorter sides are half circles
+    Capsule = 1,
+    RoundedBox = 2,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
+pub struct SdfShape {
+    /// The center of the shape in screen coordinates
+    #[format(R32G32_SFLOAT)]
+    pub center: [f32; 2],
+    /// The width and height of the shape, the diameter for circles
+    #[format(R32G32_SFLOAT)]
+    pub size: [f32; 2],
+    /// See [`SdfShapeKind`]
+    #[format(R32_UINT)]
+    pub kind: u32,
+    /// Only used by [`SdfShapeKind::RoundedBox`]
+    #[format(R32_SFLOAT)]
+    pub corner_radius: f32,
+    /// The width of the border, which is drawn inside of the shape
+    #[format(R32_SFLOAT)]
+    pub border_width: f32,
+    #[format(R32G32B32A32_SFLOAT)]
+    pub fill_color: [f32; 4],
+    #[format(R32G32B32A32_SFLOAT)]
+    pub border_color: [f32; 4],
+}
+
+impl SdfShape {
+    #[inline]
+    pub fn new(kind: SdfShapeKind, center: [f32; 2], size: [f32; 2]) -> Self {
+        Self {
+            center,
+            size,
+            kind: kind as u32,
+            corner_radius: 0.0,
+            border_width: 0.0,
+            fill_color: [1.0, 1.0, 1.0, 1.0],
+            border_color: [0.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    #[inline]
+    pub fn circle(center: [f32; 2], radius: f32) -> Self {
+        Self::new(SdfShapeKind::Circle, center, [radius * 2.0, radius * 2.0])
+    }
+
+    #[inline]
+    pub fn capsule(center: [f32; 2], size: [f32; 2]) -> Self {
+        Self::new(SdfShapeKind::Capsule, center, size)
+    }
+
+    #[inline]
+    pub fn rounded_box(center: [f32; 2], size: [f32; 2], corner_radius: f32) -> Self {
+        Self {
+            corner_radius,
+            ..Self::new(SdfShapeKind::RoundedBox, center, size)
+        }
+    }
+
+    #[inline]
+    pub fn with_fill_color(mut self, color: [f32; 4]) -> Self {
+        self.fill_color = color;
+        self
+    }
+
+    #[inline]
+    pub fn with_border(mut self, width: f32, color: [f32; 4]) -> Self {
+        self.border_width = width;
+        self.border_color = color;
+        self
+    }
+}
@@ -0,0 +1,47 @@
+#version 450
+
+layout(location = 0) in vec2 in_local;
+layout(location = 1) in vec2 in_half_size;
+layout(location = 2) flat in uint in_kind;
+layout(location = 3) in float in_corner_radius;
+layout(location = 4) in float in_border_width;
+layout(location = 5) in vec4 in_fill_color;
+layout(location = 6) in vec4 in_border_color;
+
+layout(location = 0) out vec4 out_color;
+
+const uint KIND_CIRCLE = 0;
+const uint KIND_CAPSULE = 1;
+const uint KIND_ROUNDED_BOX = 2;
+
+float sd_rounded_box(vec2 p, vec2 half_size, float radius) {
+    radius = min(radius, min(half_size.x, half_size.y));
+    vec2 q = abs(p) - half_size + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+void main() {
+    float distance;
+    if (in_kind == KIND_CIRCLE) {
+        distance = length(in_local) - min(in_half_size.x, in_half_size.y);
+    } else if (in_kind == KIND_CAPSULE) {
+        distance = sd_rounded_box(in_local, in_half_size, min(in_half_size.x, in_half_size.y));
+    } else {
+        distance = sd_rounded_box(in_local, in_half_size, in_corner_radius);
+    }
+
+    // the width of a pixel in the distance field, for resolution independent anti-aliasing
+    float aa = max(fwidth(distance), 0.0001) * 0.5;
+
+    float coverage = 1.0 - smoothstep(-aa, aa, distance);
+    float border = in_border_width > 0.0
+        ? smoothstep(-in_border_width - aa, -in_border_width + aa, distance)
+        : 0.0;
+
+    out_color = mix(in_fill_color, in_border_color, border);
+    out_color.a *= coverage;
+
+    if (out_color.a <= 0.001) {
+        discard;
+    }
+}
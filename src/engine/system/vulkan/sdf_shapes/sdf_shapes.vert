@@ -0,0 +1,46 @@
+#version 450
+
+// per vertex data
+layout(location = 0) in vec2 pos;
+
+// per instance data
+layout(location = 1) in vec2 center;
+layout(location = 2) in vec2 size;
+layout(location = 3) in uint kind;
+layout(location = 4) in float corner_radius;
+layout(location = 5) in float border_width;
+layout(location = 6) in vec4 fill_color;
+layout(location = 7) in vec4 border_color;
+
+layout(binding = 101) uniform WindowProperties { vec2 screen_size; } window;
+
+layout(location = 0) out vec2 out_local;
+layout(location = 1) out vec2 out_half_size;
+layout(location = 2) flat out uint out_kind;
+layout(location = 3) out float out_corner_radius;
+layout(location = 4) out float out_border_width;
+layout(location = 5) out vec4 out_fill_color;
+layout(location = 6) out vec4 out_border_color;
+
+// one pixel of margin on each side, so the anti-aliased edge is not cut off
+const float AA_MARGIN = 2.0;
+
+void main() {
+    vec2 local = pos * (size + AA_MARGIN);
+    vec2 screen = center + local;
+
+    gl_Position = vec4(
+    2.0 * screen.x / window.screen_size.x - 1.0,
+    2.0 * screen.y / window.screen_size.y - 1.0,
+    0.0,
+    1.0
+    );
+
+    out_local = local;
+    out_half_size = size * 0.5;
+    out_kind = kind;
+    out_corner_radius = corner_radius;
+    out_border_width = border_width;
+    out_fill_color = fill_color;
+    out_border_color = border_color;
+}
@@ -0,0 +1,172 @@
+//! Buffers for geometry that changes every frame, like the meshes of egui or the canvas.
+//!
+//! Instead of allocating a new buffer for every draw call, a [`StreamingBuffer`] owns one
+//! persistently mapped, host coherent region per frame in flight and sub-allocates from the
+//! region of the current frame. The values are written directly into the mapped memory, without
+//! collecting them into a temporary [`Vec`] first. A region is only written again after
+//! [`STREAMING_FRAMES`] frames, when the GPU has finished reading it.
+
+use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use bytemuck::Pod;
+use std::sync::{Arc, Mutex};
+use vulkano::buffer::{AllocateBufferError, Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryTypeFilter};
+use vulkano::memory::MemoryPropertyFlags;
+use vulkano::Validated;
+
+/// The amount of frames that can be recorded while the GPU is still reading the regions of the
+/// previous ones.
+pub const STREAMING_FRAMES: usize = 3;
+
+pub struct StreamingBuffer<T> {
+    buffers_manager: Arc<BasicBuffersManager>,
+    usage: BufferUsage,
+    state: Mutex<StreamingState<T>>,
+}
+
+struct StreamingState<T> {
+    /// The regions are allocated lazily and grow when a frame needs more values than before
+    regions: [Option<Subbuffer<[T]>>; STREAMING_FRAMES],
+    /// The minimal length of newly allocated regions
+    capacity: u64,
+    /// The frame the `offset` belongs to
+    frame: u64,
+    /// The amount of values already written into the region of the current frame
+    offset: u64,
+}
+
+impl<T: Pod + Send + Sync> StreamingBuffer<T> {
+    /// Creates a buffer for vertices, which allocates regions for at least `capacity` vertices.
+    #[inline]
+    pub fn vertices(buffers_manager: Arc<BasicBuffersManager>, capacity: usize) -> Self {
+        Self::new(buffers_manager, BufferUsage::VERTEX_BUFFER, capacity)
+    }
+
+    /// Creates a buffer for indices, which allocates regions for at least `capacity` indices.
+    #[inline]
+    pub fn indices(buffers_manager: Arc<BasicBuffersManager>, capacity: usize) -> Self {
+        Self::new(buffers_manager, BufferUsage::INDEX_BUFFER, capacity)
+    }
+
+    pub fn new(
+        buffers_manager: Arc<BasicBuffersManager>,
+        usage: BufferUsage,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            buffers_manager,
+            usage,
+            state: Mutex::new(StreamingState {
+                regions: Default::default(),
+                capacity: capacity.max(1) as u64,
+                frame: 0,
+                offset: 0,
+            }),
+        }
+    }
+
+    /// Writes the values into the region of the current frame and returns the slice they were
+    /// written to. The slice must not be used after [`STREAMING_FRAMES`] frames.
+    pub fn write<I>(&self, values: I) -> Result<Subbuffer<[T]>, Validated<AllocateBufferError>>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let values = values.into_iter();
+        self.write_with(values.len(), |slice| {
+            slice
+                .iter_mut()
+                .zip(values)
+                .for_each(|(target, value)| *target = value)
+        })
+    }
+
+    /// Reserves `len` values in the region of the current frame and lets `fill` write them. The
+    /// slice passed to `fill` is not initialized and must be overwritten completely.
+    ///
+    /// # Panics
+    ///
+    /// If `len` is zero, as there are no empty [`Subbuffer`]s.
+    pub fn write_with(
+        &self,
+        len: usize,
+        fill: impl FnOnce(&mut [T]),
+    ) -> Result<Subbuffer<[T]>, Validated<AllocateBufferError>> {
+        assert_ne!(0, len, "Cannot write zero values into a StreamingBuffer");
+        let len = len as u64;
+
+        let mut state = self.state.lock().unwrap();
+        let frame = self.buffers_manager.frame();
+        if state.frame != frame {
+            state.frame = frame;
+            state.offset = 0;
+        }
+
+        let index = (frame % STREAMING_FRAMES as u64) as usize;
+        let region = match &state.regions[index] {
+            Some(region) if state.offset + len <= region.len() => region.clone(),
+            region => {
+                // slices of the previous region that were already handed out keep it alive
+                let capacity = match region {
+                    Some(region) => (region.len() * 2).max(len),
+                    None => state.capacity.max(len),
+                };
+                let region = self.allocate(capacity)?;
+                state.capacity = state.capacity.max(capacity);
+                state.regions[index] = Some(region.clone());
+                state.offset = 0;
+                region
+            }
+        };
+
+        let slice = region.slice(state.offset..state.offset + len);
+        state.offset += len;
+        drop(state);
+
+        match slice.write() {
+            Ok(mut guard) => {
+                fill(&mut guard);
+                drop(guard);
+                Ok(slice)
+            }
+            Err(_) => {
+                // the GPU is more than STREAMING_FRAMES behind, do not wait for it
+                let slice = self.allocate(len)?;
+                fill(&mut slice.write().expect("A new buffer is not in use"));
+                Ok(slice)
+            }
+        }
+    }
+
+    /// The amount of bytes allocated for all regions.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.state
+            .lock()
+            .unwrap()
+            .regions
+            .iter()
+            .flatten()
+            .map(Subbuffer::size)
+            .sum()
+    }
+
+    fn allocate(&self, len: u64) -> Result<Subbuffer<[T]>, Validated<AllocateBufferError>> {
+        Buffer::new_slice(
+            Arc::clone(&self.buffers_manager.memo_allocator),
+            BufferCreateInfo {
+                usage: self.usage,
+                ..BufferCreateInfo::default()
+            },
+            AllocationCreateInfo {
+                // host coherent memory does not need to be flushed after writing into it
+                memory_type_filter: MemoryTypeFilter {
+                    required_flags: MemoryPropertyFlags::HOST_VISIBLE
+                        | MemoryPropertyFlags::HOST_COHERENT,
+                    ..MemoryTypeFilter::PREFER_DEVICE | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE
+                },
+                ..AllocationCreateInfo::default()
+            },
+            len,
+        )
+    }
+}
@@ -1,23 +1,47 @@
+use crate::engine::system::arena::FrameArena;
+use crate::engine::system::viewport::{ViewportLayout, ViewportRect};
 use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::color_filter::{ColorFilter, ColorFilterPass};
 use crate::engine::system::vulkan::desc::binding_101_window_size::WindowSize;
+use crate::engine::system::vulkan::desc::binding_102_frame_time::FrameTime;
 use crate::engine::system::vulkan::desc::binding_201_world_2d_view::World2dView;
+use crate::engine::system::vulkan::desc::binding_202_ambient_light::AmbientLight;
 use crate::engine::system::vulkan::desc::WriteDescriptorSetOrigin;
-use crate::engine::system::vulkan::textures::ImageSystem;
-use crate::engine::system::vulkan::utils::pipeline::single_pass_render_pass_from_image_format;
+use crate::engine::system::vulkan::gpu_info::GpuInfo;
+use crate::engine::system::vulkan::gpu_timer::GpuTimer;
+use crate::engine::system::vulkan::memory::{InstrumentedMemoryAllocator, MemoryStats};
+use crate::engine::system::vulkan::pipeline_cache::{
+    load_pipeline_cache, save_pipeline_cache, PipelineCacheError,
+};
+use crate::engine::system::vulkan::render_target::{
+    PassBegin, RenderTarget, RenderTargetAttachments, RenderTargetPass,
+};
+use crate::engine::system::vulkan::rendering::{
+    create_transient_attachment, RenderingAttachments, RenderingFormat,
+};
+use crate::engine::system::vulkan::textures::{ImageSystem, PlaceholderImages, UploadBudget};
+use crate::engine::system::vulkan::utils::pipeline::RenderPassLayout;
 use crate::engine::system::vulkan::wds::WriteDescriptorSetManager;
-use crate::engine::system::vulkan::{DrawError, Error};
+use crate::engine::system::vulkan::{
+    AttachmentError, DrawError, Error, ReadbackError, UploadError,
+};
+use crate::support::image::RawRgbaImage;
 use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
 use vulkano::command_buffer::allocator::{
     CommandBufferAllocator, StandardCommandBufferAllocator,
     StandardCommandBufferAllocatorCreateInfo,
 };
 use vulkano::command_buffer::{
-    AutoCommandBufferBuilder, CommandBufferInheritanceInfo, CommandBufferInheritanceRenderPassInfo,
-    CommandBufferInheritanceRenderPassType, CommandBufferUsage, RenderPassBeginInfo,
-    SecondaryAutoCommandBuffer, SecondaryCommandBufferAbstract, SubpassBeginInfo, SubpassContents,
-    SubpassEndInfo,
+    AutoCommandBufferBuilder, ClearAttachment, ClearValue, CommandBufferInheritanceInfo,
+    CommandBufferInheritanceRenderPassInfo, CommandBufferInheritanceRenderPassType,
+    CommandBufferUsage, CopyImageInfo, CopyImageToBufferInfo, PrimaryAutoCommandBuffer,
+    RenderPassBeginInfo, SecondaryAutoCommandBuffer, SecondaryCommandBufferAbstract,
+    SubpassContents,
 };
 use vulkano::descriptor_set::allocator::{
     StandardDescriptorSetAllocator, StandardDescriptorSetAllocatorCreateInfo,
@@ -27,54 +51,176 @@ use vulkano::device::physical::{PhysicalDevice, PhysicalDeviceType};
 use vulkano::device::{
     Device, DeviceCreateInfo, DeviceExtensions, Features, Queue, QueueCreateInfo, QueueFlags,
 };
-use vulkano::format::Format;
+use vulkano::format::{Format, FormatFeatures};
 use vulkano::image::view::ImageView;
-use vulkano::image::{Image, ImageCreateInfo, ImageType, ImageUsage, SampleCount};
-use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator, StandardMemoryAllocator};
-use vulkano::pipeline::cache::PipelineCache;
-use vulkano::pipeline::graphics::subpass::PipelineSubpassType;
-use vulkano::pipeline::graphics::viewport::Viewport;
+use vulkano::image::{
+    AllocateImageError, Image, ImageCreateInfo, ImageType, ImageUsage, SampleCount,
+};
+use vulkano::instance::Instance;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter};
+use vulkano::pipeline::cache::{PipelineCache, PipelineCacheCreateInfo};
+use vulkano::pipeline::graphics::color_blend::{ColorBlendAttachmentState, ColorBlendState};
+use vulkano::pipeline::graphics::depth_stencil::{CompareOp, DepthState, DepthStencilState};
+use vulkano::pipeline::graphics::multisample::MultisampleState;
+use vulkano::pipeline::graphics::subpass::{PipelineRenderingCreateInfo, PipelineSubpassType};
 use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
 use vulkano::swapchain::{
-    acquire_next_image, Surface, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo,
+    acquire_next_image, ColorSpace, Surface, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo,
 };
-use vulkano::sync::GpuFuture;
-use vulkano::{Validated, Version, VulkanError};
+use vulkano::sync::{GpuFuture, Sharing};
+use vulkano::{DeviceSize, Validated, ValidationError, Version, VulkanError};
 
 pub struct VulkanSystem {
     device: Arc<Device>,
     queue: Arc<Queue>,
+    /// The queue the swapchain images are presented on, the same as `queue` if the graphics
+    /// queue family supports presenting (or the system is headless)
+    present_queue: Arc<Queue>,
     render_pass: Arc<RenderPass>,
-    swapchain: Arc<Swapchain>,
+    /// [`None`] for a headless system, see [`VulkanSystem::new_headless`]
+    swapchain: Option<Arc<Swapchain>>,
+    /// The images of the swapchain or the single image of a headless system
     swapchain_images: Vec<Arc<Image>>,
+    /// Rendered into instead of the `swapchain_images` if those do not support the requested
+    /// `image_usage` and copied into them afterward, empty otherwise
+    intermediate_images: Vec<Arc<Image>>,
+    /// The additional usage of the images that are rendered into, see
+    /// [`VulkanSystem::image_usage`]
+    image_usage: ImageUsage,
+    /// Empty with dynamic rendering, see [`VulkanSystem::with_dynamic_rendering`]
     swapchain_framebuffers: Vec<Arc<Framebuffer>>,
+    /// [`Some`] if the frames are rendered with dynamic rendering instead of the `render_pass`
+    rendering_format: Option<RenderingFormat>,
+    /// The attachments of the previous frame, whose transient images are reused while the size
+    /// does not change
+    rendering_attachments: Option<RenderingAttachments>,
     recreate_swapchain: bool,
     swapchain_is_new: bool,
-    previous_frame_end: Option<Box<dyn GpuFuture>>,
+    /// The extent of the released swapchain while suspended, see [`VulkanSystem::suspend`]
+    suspended_extent: Option<[u32; 2]>,
+    ambient_light: AmbientLight,
+    ambient_light_changed: bool,
+    /// See [`VulkanSystem::pixels_per_point`]
+    pixels_per_point: f32,
+    pixels_per_point_changed: bool,
+    frame_time: FrameTime,
+    /// When the system was created, see [`FrameTime::elapsed`]
+    started: Instant,
+    /// When the previous frame started rendering, see [`FrameTime::delta`]
+    previous_render: Option<Instant>,
+    previous_frame_end: Option<Box<dyn GpuFuture + Send + Sync>>,
     write_descriptors: Arc<WriteDescriptorSetManager>,
     cmd_allocator: StandardCommandBufferAllocator,
+    memory_allocator: Arc<InstrumentedMemoryAllocator>,
     image_system: Arc<ImageSystem>,
+    placeholder_images: PlaceholderImages,
     basic_buffers_manager: Arc<BasicBuffersManager>,
     clear_value_rgba: [f32; 4],
+    /// See [`VulkanSystem::set_viewport_layout`]
+    viewport_layout: ViewportLayout,
+    viewport_layout_changed: bool,
     samples: SampleCount,
+    /// The format of the optional depth attachment, see [`VulkanSystem::depth_format`]
+    depth_format: Option<Format>,
+    upload_budget: UploadBudget,
+    frame_arena: FrameArena,
+    /// [`None`] if the graphics queue does not support timestamps
+    gpu_timer: Option<GpuTimer>,
+    color_filter: Option<ColorFilter>,
+    /// Created with the first frame that is filtered
+    color_filter_pass: Option<ColorFilterPass>,
+    /// See [`VulkanSystem::set_fixed_frame_delta`]
+    fixed_frame_delta: Option<Duration>,
+    /// See [`VulkanSystem::request_frame_capture`]
+    capture_requested: bool,
+    /// The read back of a captured frame, until [`VulkanSystem::take_frame_capture`]
+    frame_capture: Option<FrameCapture>,
+    /// See [`VulkanSystem::capture_next_frame`]
+    capture_callbacks: Vec<FrameCaptureCallback>,
+    /// Shared by all pipelines, [`None`] if it could not be created
+    pipeline_cache: Option<Arc<PipelineCache>>,
+    /// See [`VulkanSystem::with_pipeline_cache_file`]
+    pipeline_cache_file: Option<PathBuf>,
+}
+
+type FrameCaptureCallback = Box<dyn FnOnce(Result<&RawRgbaImage, &ReadbackError>) + Send>;
+
+/// The buffer a rendered frame is copied into, see [`VulkanSystem::request_frame_capture`].
+struct FrameCapture {
+    buffer: Subbuffer<[u8]>,
+    extent: [u32; 2],
+    format: Format,
 }
 
 impl VulkanSystem {
+    /// The format of the image a headless system renders into.
+    pub const HEADLESS_FORMAT: Format = Format::R8G8B8A8_SRGB;
+
     pub fn new(
         surface: Arc<Surface>,
         width: u32,
         height: u32,
         features: Features,
         samples: SampleCount,
+        depth_format: Option<Format>,
+        image_usage: ImageUsage,
+    ) -> Result<Self, Error> {
+        let instance = Arc::clone(surface.instance());
+        Self::create(
+            instance,
+            Some(surface),
+            width,
+            height,
+            features,
+            samples,
+            depth_format,
+            image_usage,
+        )
+    }
+
+    /// Creates a system without a window that renders into a single image of the given size in
+    /// [`VulkanSystem::HEADLESS_FORMAT`], which can be read back with
+    /// [`VulkanSystem::read_pixels`]. This allows rendering on machines without a display, like
+    /// CI machines with a software rasterizer such as lavapipe.
+    pub fn new_headless(
+        instance: Arc<Instance>,
+        width: u32,
+        height: u32,
+        features: Features,
+        samples: SampleCount,
+        depth_format: Option<Format>,
+        image_usage: ImageUsage,
+    ) -> Result<Self, Error> {
+        Self::create(
+            instance,
+            None,
+            width,
+            height,
+            features,
+            samples,
+            depth_format,
+            image_usage,
+        )
+    }
+
+    fn create(
+        instance: Arc<Instance>,
+        surface: Option<Arc<Surface>>,
+        width: u32,
+        height: u32,
+        features: Features,
+        samples: SampleCount,
+        depth_format: Option<Format>,
+        image_usage: ImageUsage,
     ) -> Result<Self, Error> {
         let mut device_extensions = DeviceExtensions {
-            khr_swapchain: true,
+            khr_swapchain: surface.is_some(),
             khr_dynamic_rendering: true,
             ..DeviceExtensions::empty()
         };
 
-        let (physical_device, queue_family_index) =
-            choose_physical_device(&surface, &mut device_extensions)?;
+        let (physical_device, queue_families) =
+            choose_physical_device(&instance, surface.as_deref(), &mut device_extensions)?;
 
         let (device, mut queues) = Device::new(
             physical_device,
@@ -84,33 +230,87 @@ impl VulkanSystem {
                     dynamic_rendering: true,
                     ..Features::empty()
                 } | features,
-                queue_create_infos: vec![QueueCreateInfo {
-                    queue_family_index,
-
-                    ..Default::default()
-                }],
+                queue_create_infos: queue_families
+                    .distinct()
+                    .map(|queue_family_index| QueueCreateInfo {
+                        queue_family_index,
+                        ..Default::default()
+                    })
+                    .collect(),
                 ..Default::default()
             },
         )
         .map_err(Error::DeviceInitializationFailed)?;
 
-        let (swapchain, swapchain_images) =
-            create_swapchain(&device, &surface, [width, height], samples)?;
-        let render_pass = single_pass_render_pass_from_image_format(
-            Arc::clone(&device),
-            swapchain.image_format(),
-            samples,
-        )
-        .map_err(Error::FailedToCreateFramebuffers)?;
+        let queue = queues.next().expect("Promised queue is not present");
+        let present_queue = if queue_families.is_shared() {
+            Arc::clone(&queue)
+        } else {
+            queues
+                .next()
+                .expect("Promised present queue is not present")
+        };
+
+        let memory_allocator = Arc::new(InstrumentedMemoryAllocator::new_default(Arc::clone(
+            &device,
+        )));
+
+        let (swapchain, swapchain_images) = match &surface {
+            Some(surface) => {
+                let (swapchain, images) = create_swapchain(
+                    &device,
+                    surface,
+                    [width, height],
+                    samples,
+                    queue_families,
+                    image_usage,
+                )?;
+                (Some(swapchain), images)
+            }
+            None => (
+                None,
+                create_headless_images(&memory_allocator, [width, height], image_usage)
+                    .map_err(Error::FailedToCreateHeadlessImage)?,
+            ),
+        };
+        check_format_supports_usage(&device, swapchain_images[0].format(), image_usage)?;
+        let intermediate_images =
+            create_intermediate_images(&memory_allocator, &swapchain_images, image_usage)
+                .map_err(Error::FailedToCreateIntermediateImages)?;
+        let layout = RenderPassLayout::new(swapchain_images[0].format())
+            .with_samples(samples)
+            .with_depth_format(depth_format)
+            .supported_by(&device);
+        if layout.samples != samples {
+            warn!(
+                "The device does not support {samples:?} for the render pass, falling back to {:?}",
+                layout.samples
+            );
+        }
+        let samples = layout.samples;
+        let render_pass = layout
+            .create_render_pass(Arc::clone(&device))
+            .map_err(|e| Error::FailedToCreateFramebuffers(e.into()))?;
 
         let basic_buffers_manager = Arc::new(BasicBuffersManager::new(
-            StandardMemoryAllocator::new_default(Arc::clone(&device)),
+            Arc::clone(&memory_allocator) as Arc<dyn MemoryAllocator>,
         ));
 
+        let image_system = Arc::new(ImageSystem::new(
+            Arc::clone(&memory_allocator) as Arc<dyn MemoryAllocator>
+        )?);
+        let placeholder_images = PlaceholderImages::create(&image_system)
+            .map_err(Error::FailedToCreatePlaceholderImages)?;
+
+        // SAFETY: without initial data, there is nothing the driver could misinterpret
+        let pipeline_cache =
+            unsafe { PipelineCache::new(Arc::clone(&device), PipelineCacheCreateInfo::default()) }
+                .map_err(|e| warn!("Failed to create the pipeline cache: {e}"))
+                .ok();
+
         Self {
-            image_system: Arc::new(ImageSystem::new(StandardMemoryAllocator::new_default(
-                Arc::clone(&device),
-            ))?),
+            image_system,
+            placeholder_images,
             cmd_allocator: StandardCommandBufferAllocator::new(
                 Arc::clone(&device),
                 StandardCommandBufferAllocatorCreateInfo {
@@ -119,31 +319,60 @@ impl VulkanSystem {
                     ..StandardCommandBufferAllocatorCreateInfo::default()
                 },
             ),
-            queue: queues.next().expect("Promised queue is not present"),
+            gpu_timer: GpuTimer::new(&queue),
+            queue,
+            present_queue,
             recreate_swapchain: false,
             swapchain_is_new: false,
-            previous_frame_end: Some(vulkano::sync::now(Arc::clone(&device)).boxed()),
+            suspended_extent: None,
+            ambient_light: AmbientLight::default(),
+            ambient_light_changed: false,
+            pixels_per_point: 1.0,
+            pixels_per_point_changed: false,
+            frame_time: FrameTime::default(),
+            started: Instant::now(),
+            previous_render: None,
+            previous_frame_end: Some(vulkano::sync::now(Arc::clone(&device)).boxed_send_sync()),
             swapchain_framebuffers: create_framebuffers(
                 &basic_buffers_manager.memo_allocator,
-                &swapchain_images,
+                render_images(&swapchain_images, &intermediate_images),
                 &render_pass,
                 samples,
+                depth_format,
             )
             .map_err(Error::FailedToCreateFramebuffers)?,
+            rendering_format: None,
+            rendering_attachments: None,
             swapchain,
             swapchain_images,
+            intermediate_images,
+            image_usage,
             render_pass,
             write_descriptors: Arc::new(WriteDescriptorSetManager::new(
                 Arc::new(StandardDescriptorSetAllocator::new(
                     Arc::clone(&device),
                     StandardDescriptorSetAllocatorCreateInfo::default(),
                 )),
-                Arc::new(StandardMemoryAllocator::new_default(Arc::clone(&device))),
+                Arc::clone(&memory_allocator) as Arc<dyn MemoryAllocator>,
             )),
+            memory_allocator,
             device,
             clear_value_rgba: [0.0, 0.5, 1.0, 1.0], // blue-ish value
+            viewport_layout: ViewportLayout::default(),
+            viewport_layout_changed: false,
             basic_buffers_manager,
             samples,
+            depth_format,
+            upload_budget: UploadBudget::default(),
+            frame_arena: FrameArena::default(),
+            color_filter: None,
+            color_filter_pass: None,
+            fixed_frame_delta: None,
+            capture_requested: false,
+            frame_capture: None,
+            capture_callbacks: Vec::new(),
+            pipeline_cache,
+            pipeline_cache_file: None,
         }
         .with_write_descriptors_initialized()
     }
@@ -154,6 +383,42 @@ impl VulkanSystem {
         Ok(self)
     }
 
+    /// Renders the frames and the [`RenderTarget`]s with dynamic rendering
+    /// (`VK_KHR_dynamic_rendering`) instead of a render pass and framebuffers. The attachments
+    /// are described per frame, so a resize only recreates the transient multisampled and depth
+    /// images, if there are any, and offscreen targets need no framebuffers. Pipelines have to be
+    /// created afterward, because they are created for either of both (see
+    /// [`VulkanSystem::graphics_pipeline_render_pass_info`]).
+    pub fn with_dynamic_rendering(mut self) -> Self {
+        self.rendering_format = Some(RenderingFormat::from(self.render_pass_layout()));
+        self.swapchain_framebuffers.clear();
+        self
+    }
+
+    /// Whether the frames are rendered with dynamic rendering, see
+    /// [`VulkanSystem::with_dynamic_rendering`].
+    #[inline]
+    pub fn uses_dynamic_rendering(&self) -> bool {
+        self.rendering_format.is_some()
+    }
+
+    /// No framebuffers are needed with dynamic rendering.
+    fn create_swapchain_framebuffers(
+        &self,
+        images: &[Arc<Image>],
+    ) -> Result<Vec<Arc<Framebuffer>>, AttachmentError> {
+        if self.uses_dynamic_rendering() {
+            return Ok(Vec::new());
+        }
+        create_framebuffers(
+            &self.basic_buffers_manager.memo_allocator,
+            images,
+            &self.render_pass,
+            self.samples,
+            self.depth_format,
+        )
+    }
+
     fn init_write_descriptors(&mut self) -> Result<(), Error> {
         // clone to not re-create allocators
         let mut write_descriptor = WriteDescriptorSetManager::new(
@@ -163,6 +428,8 @@ impl VulkanSystem {
 
         write_descriptor.insert(WindowSize::from(&*self))?;
         write_descriptor.insert(World2dView::from(&*self))?;
+        write_descriptor.insert(AmbientLight::from(&*self))?;
+        write_descriptor.insert(FrameTime::from(&*self))?;
 
         self.write_descriptors = Arc::new(write_descriptor);
         Ok(())
@@ -174,6 +441,8 @@ impl VulkanSystem {
     ) -> Result<(), Error> {
         self.write_descriptors
             .update(cmds, WindowSize::from(self))?;
+        self.write_descriptors
+            .update(cmds, AmbientLight::from(self))?;
 
         Ok(())
     }
@@ -183,14 +452,166 @@ impl VulkanSystem {
         &self.device
     }
 
+    /// The queue all commands are executed on.
     #[inline]
     pub fn queue(&self) -> &Arc<Queue> {
         &self.queue
     }
 
+    /// The queue the swapchain images are presented on. Some devices do not support presenting
+    /// on the graphics queue family, on all others this is the [`VulkanSystem::queue`].
+    #[inline]
+    pub fn present_queue(&self) -> &Arc<Queue> {
+        &self.present_queue
+    }
+
+    #[inline]
+    fn queue_families(&self) -> QueueFamilies {
+        QueueFamilies {
+            graphics: self.queue.queue_family_index(),
+            present: self.present_queue.queue_family_index(),
+        }
+    }
+
+    /// [`None`] for a headless system.
+    #[inline]
+    pub fn swapchain(&self) -> Option<&Swapchain> {
+        self.swapchain.as_deref()
+    }
+
+    #[inline]
+    pub fn is_headless(&self) -> bool {
+        self.swapchain.is_none() && self.suspended_extent.is_none()
+    }
+
+    /// Whether the swapchain is released, see [`VulkanSystem::suspend`].
+    #[inline]
+    pub fn is_suspended(&self) -> bool {
+        self.suspended_extent.is_some()
+    }
+
+    /// The size of the images that are rendered into. While suspended, the size of the released
+    /// swapchain.
     #[inline]
-    pub fn swapchain(&self) -> &Swapchain {
-        &self.swapchain
+    pub fn image_extent(&self) -> [u32; 2] {
+        match self.swapchain_images.first() {
+            Some(image) => {
+                let [width, height, _] = image.extent();
+                [width, height]
+            }
+            None => self.suspended_extent.unwrap_or_default(),
+        }
+    }
+
+    #[inline]
+    pub fn image_format(&self) -> Format {
+        self.render_pass.attachments()[0].format
+    }
+
+    /// The color space the swapchain images are presented in, [`None`] for a headless system.
+    #[inline]
+    pub fn image_color_space(&self) -> Option<ColorSpace> {
+        self.swapchain
+            .as_ref()
+            .map(|swapchain| swapchain.image_color_space())
+    }
+
+    /// The amount of swapchain images, which is `1` for a headless system and `0` while
+    /// suspended.
+    #[inline]
+    pub fn image_count(&self) -> usize {
+        self.swapchain_images.len()
+    }
+
+    /// The additional usage of the images that are rendered into, see
+    /// [`RenderContext::target_image`]. If the swapchain images do not support it, the frames are
+    /// rendered into intermediate images with the usage, which are copied into the swapchain
+    /// images afterward.
+    #[inline]
+    pub fn image_usage(&self) -> ImageUsage {
+        self.image_usage
+    }
+
+    /// Whether the frames are rendered into intermediate images, see
+    /// [`VulkanSystem::image_usage`].
+    #[inline]
+    pub fn uses_intermediate_images(&self) -> bool {
+        !self.intermediate_images.is_empty()
+    }
+
+    /// The amount of samples per pixel of the main render target. This is the sample count
+    /// requested with [`EngineBuilder::with_msaa`], lowered to what the device supports.
+    ///
+    /// [`EngineBuilder::with_msaa`]: crate::engine::builder::EngineBuilder::with_msaa
+    #[inline]
+    pub fn samples(&self) -> SampleCount {
+        self.samples
+    }
+
+    /// The attachments of the render pass or of the dynamic rendering of the frames and of the
+    /// [`RenderTarget`]s. Pipelines should be created with
+    /// [`VulkanSystem::graphics_pipeline_render_pass_info`], which is derived from this.
+    #[inline]
+    pub fn render_pass_layout(&self) -> RenderPassLayout {
+        RenderPassLayout::new(self.image_format())
+            .with_samples(self.samples)
+            .with_depth_format(self.depth_format)
+    }
+
+    /// The format of the depth attachment of the main render target, [`None`] if it has none.
+    /// Pipelines can query this with [`GraphicsPipelineRenderPassInfo::has_depth_attachment`].
+    #[inline]
+    pub fn depth_format(&self) -> Option<Format> {
+        self.depth_format
+    }
+
+    /// Creates an offscreen [`RenderTarget`] of the given size, which is compatible to the render
+    /// pass of this system and thereby to all pipelines created for it.
+    pub fn create_render_target(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderTarget, UploadError> {
+        let image = Image::new(
+            Arc::clone(&self.memory_allocator) as Arc<dyn MemoryAllocator>,
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: self.image_format(),
+                extent: [width.max(1), height.max(1), 1],
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED,
+                ..ImageCreateInfo::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..AllocationCreateInfo::default()
+            },
+        )?;
+        let attachments = match &self.rendering_format {
+            Some(format) => RenderTargetAttachments::Rendering {
+                format: format.clone(),
+                attachments: RenderingAttachments::new(
+                    &self.basic_buffers_manager.memo_allocator,
+                    Arc::clone(&image),
+                    format,
+                )?,
+            },
+            None => RenderTargetAttachments::Framebuffer {
+                render_pass: Arc::clone(&self.render_pass),
+                framebuffer: create_framebuffers(
+                    &self.basic_buffers_manager.memo_allocator,
+                    core::slice::from_ref(&image),
+                    &self.render_pass,
+                    self.samples,
+                    self.depth_format,
+                )?
+                .remove(0),
+            },
+        };
+        Ok(RenderTarget {
+            image,
+            attachments,
+            clear_values: clear_values([0.0; 4], self.samples, self.depth_format),
+        })
     }
 
     #[inline]
@@ -198,14 +619,50 @@ impl VulkanSystem {
         &self.render_pass
     }
 
+    /// What the pipelines are created for, the render pass or the formats of the dynamic
+    /// rendering, see [`VulkanSystem::with_dynamic_rendering`].
     pub fn graphics_pipeline_render_pass_info(&self) -> GraphicsPipelineRenderPassInfo {
-        GraphicsPipelineRenderPassInfo(Arc::clone(&self.render_pass))
+        match &self.rendering_format {
+            Some(_) => {
+                GraphicsPipelineRenderPassInfo::dynamic_rendering_layout(&self.render_pass_layout())
+            }
+            None => GraphicsPipelineRenderPassInfo::from_render_pass(Arc::clone(&self.render_pass)),
+        }
     }
 
+    /// The cache all pipelines of the engine are created with, see
+    /// [`VulkanSystem::with_pipeline_cache_file`].
     #[inline]
     pub fn pipeline_cache(&self) -> Option<&Arc<PipelineCache>> {
-        info!("NO PipelineCache configured!");
-        None
+        self.pipeline_cache.as_ref()
+    }
+
+    /// Loads the [`VulkanSystem::pipeline_cache`] from the file, so that pipelines that were
+    /// compiled in a previous run are not compiled again. The cache is written back into the file
+    /// with [`VulkanSystem::save_pipeline_cache`]. A missing or incompatible file (like after a
+    /// driver update) starts with an empty cache. Pipelines have to be created afterward.
+    pub fn with_pipeline_cache_file(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        match load_pipeline_cache(&self.device, &path) {
+            Ok(cache) => self.pipeline_cache = Some(cache),
+            Err(e) => error!("Failed to load the pipeline cache {}: {e}", path.display()),
+        }
+        self.pipeline_cache_file = Some(path);
+        self
+    }
+
+    #[inline]
+    pub fn pipeline_cache_file(&self) -> Option<&Path> {
+        self.pipeline_cache_file.as_deref()
+    }
+
+    /// Writes the [`VulkanSystem::pipeline_cache`] into the file of
+    /// [`VulkanSystem::with_pipeline_cache_file`], does nothing without a file.
+    pub fn save_pipeline_cache(&self) -> Result<(), PipelineCacheError> {
+        match (&self.pipeline_cache, &self.pipeline_cache_file) {
+            (Some(cache), Some(path)) => save_pipeline_cache(cache, path),
+            _ => Ok(()),
+        }
     }
 
     #[inline]
@@ -213,6 +670,34 @@ impl VulkanSystem {
         &self.image_system
     }
 
+    /// The allocator shared by all buffers, images and descriptors of this system.
+    #[inline]
+    pub fn memory_allocator(&self) -> &Arc<InstrumentedMemoryAllocator> {
+        &self.memory_allocator
+    }
+
+    /// See [`GpuInfo`], this queries the device each time it is called.
+    #[inline]
+    pub fn gpu_info(&self) -> GpuInfo {
+        GpuInfo::new(self)
+    }
+
+    #[inline]
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.memory_allocator.stats()
+    }
+
+    /// The descriptor set allocator shared by all pipelines of this system.
+    #[inline]
+    pub fn descriptor_set_allocator(&self) -> &Arc<StandardDescriptorSetAllocator> {
+        self.write_descriptors.descriptor_set_allocator()
+    }
+
+    #[inline]
+    pub fn placeholder_images(&self) -> &PlaceholderImages {
+        &self.placeholder_images
+    }
+
     #[inline]
     pub fn write_descriptor_set_manager(&self) -> &Arc<WriteDescriptorSetManager> {
         &self.write_descriptors
@@ -228,6 +713,74 @@ impl VulkanSystem {
         self.recreate_swapchain = true;
     }
 
+    /// Waits for the GPU to become idle and releases the swapchain and its framebuffers, so the
+    /// surface of the window can be destroyed. This is required when a mobile app is sent into the
+    /// background, on Android the native window is destroyed afterwards. Until
+    /// [`VulkanSystem::resume`] is called, [`VulkanSystem::render`] does nothing. Headless systems
+    /// cannot be suspended.
+    pub fn suspend(&mut self) {
+        if self.swapchain.is_none() {
+            return;
+        }
+
+        self.suspended_extent = Some(self.image_extent());
+        if let Some(mut previous) = self.previous_frame_end.take() {
+            previous.cleanup_finished();
+        }
+        for queue in [&self.queue, &self.present_queue] {
+            if let Err(e) = queue.with(|mut queue| queue.wait_idle()) {
+                error!("Failed to wait for the queue before suspending: {e}");
+            }
+        }
+        self.swapchain_framebuffers.clear();
+        self.rendering_attachments = None;
+        self.intermediate_images.clear();
+        self.swapchain_images.clear();
+        self.swapchain = None;
+        info!("Released the swapchain");
+    }
+
+    /// Creates a new swapchain for the (new) surface of the window after
+    /// [`VulkanSystem::suspend`]. The surface has to provide the same format as before, because
+    /// all pipelines are bound to the render pass.
+    pub fn resume(&mut self, surface: Arc<Surface>, width: u32, height: u32) -> Result<(), Error> {
+        if self.suspended_extent.is_none() {
+            return Ok(());
+        }
+
+        let (swapchain, images) = create_swapchain(
+            &self.device,
+            &surface,
+            [width, height],
+            self.samples,
+            self.queue_families(),
+            self.image_usage,
+        )?;
+        if images[0].format() != self.image_format() {
+            return Err(Error::SurfaceFormatChanged(
+                self.image_format(),
+                images[0].format(),
+            ));
+        }
+
+        let intermediate_images =
+            create_intermediate_images(&self.memory_allocator, &images, self.image_usage)
+                .map_err(Error::FailedToCreateIntermediateImages)?;
+        self.swapchain_framebuffers = self
+            .create_swapchain_framebuffers(render_images(&images, &intermediate_images))
+            .map_err(Error::FailedToCreateFramebuffers)?;
+        self.swapchain = Some(swapchain);
+        self.swapchain_images = images;
+        self.intermediate_images = intermediate_images;
+        self.swapchain_is_new = true;
+        self.recreate_swapchain = false;
+        self.suspended_extent = None;
+        self.previous_frame_end =
+            Some(vulkano::sync::now(Arc::clone(&self.device)).boxed_send_sync());
+        info!("Recreated the swapchain");
+        Ok(())
+    }
+
     #[inline]
     pub fn clear_value(&self) -> [f32; 4] {
         self.clear_value_rgba
@@ -238,6 +791,132 @@ impl VulkanSystem {
         self.clear_value_rgba = rgba;
     }
 
+    #[inline]
+    pub fn viewport_layout(&self) -> &ViewportLayout {
+        &self.viewport_layout
+    }
+
+    /// Renders the frames into the viewports of the layout instead of the whole image, see
+    /// [`ViewportLayout`]. The secondary command buffers of
+    /// [`RenderContext::create_render_buffer_builder`] render into the first viewport, those of
+    /// [`RenderContext::create_viewport_buffer_builder`] into the given one.
+    #[inline]
+    pub fn set_viewport_layout(&mut self, layout: ViewportLayout) {
+        if self.viewport_layout != layout {
+            self.viewport_layout = layout;
+            self.viewport_layout_changed = true;
+        }
+    }
+
+    /// The color the main pass is cleared with: the bar color if there are bars, the viewports
+    /// are cleared with the clear value afterward.
+    #[inline]
+    fn pass_clear_value(&self) -> [f32; 4] {
+        if self.viewport_layout.is_fullscreen() {
+            self.clear_value_rgba
+        } else {
+            self.viewport_layout.bar_color
+        }
+    }
+
+    /// The viewports of the [`VulkanSystem::viewport_layout`] for the current
+    /// [`VulkanSystem::image_extent`].
+    #[inline]
+    pub fn viewports(&self) -> Vec<ViewportRect> {
+        self.viewport_layout.viewports(self.image_extent())
+    }
+
+    /// The size of a single viewport, the [`VulkanSystem::image_extent`] without a
+    /// [`ViewportLayout`]. This is the size of the [`WindowSize`] uniform.
+    #[inline]
+    pub fn viewport_extent(&self) -> [u32; 2] {
+        self.viewports()
+            .first()
+            .map_or_else(|| self.image_extent(), ViewportRect::extent)
+    }
+
+    #[inline]
+    pub fn ambient_light(&self) -> AmbientLight {
+        self.ambient_light
+    }
+
+    /// The uniform buffer is updated at the beginning of the next frame.
+    #[inline]
+    pub fn set_ambient_light(&mut self, light: impl Into<AmbientLight>) {
+        let light = light.into();
+        if self.ambient_light != light {
+            self.ambient_light = light;
+            self.ambient_light_changed = true;
+        }
+    }
+
+    /// The pixels per point of the window, which is passed to the shaders together with the
+    /// window size, see [`WindowSize`]. The images are always rendered at the native resolution.
+    #[inline]
+    pub fn pixels_per_point(&self) -> f32 {
+        self.pixels_per_point
+    }
+
+    /// The uniform buffer is updated at the beginning of the next frame.
+    #[inline]
+    pub fn set_pixels_per_point(&mut self, pixels_per_point: f32) {
+        if self.pixels_per_point != pixels_per_point {
+            self.pixels_per_point = pixels_per_point;
+            self.pixels_per_point_changed = true;
+        }
+    }
+
+    #[inline]
+    pub fn color_filter(&self) -> Option<ColorFilter> {
+        self.color_filter
+    }
+
+    /// Applies the [`ColorFilter`] to every frame from the next one on, after everything was
+    /// drawn, or disables it with [`None`]. The images that are rendered into need
+    /// [`ImageUsage::TRANSFER_SRC`] (see [`VulkanSystem::image_usage`]), otherwise the filter is
+    /// disabled again with a warning. The colors are filtered as stored in the image, which is
+    /// linear RGB for the `SRGB` formats of the swapchain.
+    #[inline]
+    pub fn set_color_filter(&mut self, filter: Option<ColorFilter>) {
+        self.color_filter = filter;
+    }
+
+    /// Adds a descriptor binding that is kept up to date by the caller with
+    /// [`RenderContext::update_write_descriptor_set`]. Pipelines that were created before keep
+    /// the previous bindings, so this should happen before the pipelines that use it are created.
+    pub fn insert_write_descriptor<W: WriteDescriptorSetOrigin>(
+        &mut self,
+        origin: impl Borrow<W>,
+    ) -> Result<(), Error> {
+        Arc::make_mut(&mut self.write_descriptors).insert(origin)
+    }
+
+    /// The time of the frame that is currently or was last rendered, also available to shaders
+    /// at binding `102`, see [`FrameTime`].
+    #[inline]
+    pub fn frame_time(&self) -> FrameTime {
+        self.frame_time
+    }
+
+    #[inline]
+    pub fn upload_budget(&self) -> UploadBudget {
+        self.upload_budget
+    }
+
+    /// The time the GPU took to execute the commands of a recent frame, measured with
+    /// timestamp queries. The value lags a few frames behind and is [`None`] until the first
+    /// measurement is available or if the GPU does not support timestamps.
+    #[inline]
+    pub fn gpu_frame_time(&self) -> Option<Duration> {
+        self.gpu_timer.as_ref().and_then(GpuTimer::latest)
+    }
+
+    /// Limits the amount of image uploads processed per frame, see [`UploadBudget`].
+    #[inline]
+    pub fn set_upload_budget(&mut self, budget: UploadBudget) {
+        self.upload_budget = budget;
+    }
+
     // TODO just for demo
     pub fn render<F1>(
         &mut self,
@@ -248,46 +927,177 @@ impl VulkanSystem {
     where
         F1: FnOnce(&RenderContext) -> Vec<Arc<SecondaryAutoCommandBuffer>>,
     {
+        self.render_frame(
+            width,
+            height,
+            FrameRecorder::<F1, InlineCallback>::Secondary(render_callback),
+        )
+    }
+
+    /// Like [`VulkanSystem::render`], but the callback records its draw commands directly into
+    /// the primary command buffer, inside the main render pass of the frame, instead of into
+    /// secondary command buffers. The viewport and the scissor are already set to the whole
+    /// image. Commands that are not allowed inside a render pass, like uploads, cannot be
+    /// recorded. The callback is recorded into a primary command buffer of its own, so the
+    /// commands for [`RenderContext::render_into`] are still rendered before the main render pass
+    /// and can be sampled in the same frame.
+    pub fn render_inline<F1>(
+        &mut self,
+        width: u32,
+        height: u32,
+        render_callback: F1,
+    ) -> Result<(), DrawError>
+    where
+        F1: FnOnce(&RenderContext, &mut InlineCommandBufferBuilder),
+    {
+        self.render_inline_layered(width, height, None, |context, _, builder| {
+            render_callback(context, builder)
+        })
+    }
+
+    /// Like [`VulkanSystem::render_inline`], but with a `world_target` the callback additionally
+    /// gets a second primary command buffer, inside a render pass of its own on the whole
+    /// `world_target`. It is executed before the main render pass, so the world rendered into it
+    /// can be sampled in the main render pass of the same frame. This is the inline counterpart
+    /// to rendering the world of a dynamic resolution with [`RenderContext::with_render_target`].
+    pub fn render_inline_layered<F1>(
+        &mut self,
+        width: u32,
+        height: u32,
+        world_target: Option<&RenderTarget>,
+        render_callback: F1,
+    ) -> Result<(), DrawError>
+    where
+        F1: FnOnce(
+            &RenderContext,
+            Option<&mut InlineCommandBufferBuilder>,
+            &mut InlineCommandBufferBuilder,
+        ),
+    {
+        self.render_frame(
+            width,
+            height,
+            FrameRecorder::<SecondaryCallback, F1>::Inline {
+                world_target,
+                callback: render_callback,
+            },
+        )
+    }
+
+    fn render_frame<S, I>(
+        &mut self,
+        width: u32,
+        height: u32,
+        recorder: FrameRecorder<S, I>,
+    ) -> Result<(), DrawError>
+    where
+        S: FnOnce(&RenderContext) -> Vec<Arc<SecondaryAutoCommandBuffer>>,
+        I: FnOnce(
+            &RenderContext,
+            Option<&mut InlineCommandBufferBuilder>,
+            &mut InlineCommandBufferBuilder,
+        ),
+    {
+        if self.is_suspended() {
+            return Ok(());
+        }
+
+        if self.frame_capture.is_some() && !self.capture_callbacks.is_empty() {
+            // the error was passed to the callbacks already
+            let _ = self.take_frame_capture();
+        }
+
+        // the previous frame was presented, its transient data is no longer referenced
+        self.frame_arena.reset();
+        self.basic_buffers_manager.next_frame();
+
+        let now = Instant::now();
+        let delta = self
+            .previous_render
+            .replace(now)
+            .map(|previous| now.duration_since(previous));
+        let (elapsed, delta) = match (self.fixed_frame_delta, delta) {
+            (Some(fixed), Some(_)) => (self.frame_time.elapsed + fixed.as_secs_f32(), Some(fixed)),
+            (Some(_), None) => (self.frame_time.elapsed, None),
+            (None, _) => (now.duration_since(self.started).as_secs_f32(), delta),
+        };
+        self.frame_time = FrameTime {
+            elapsed,
+            delta: delta.unwrap_or_default().as_secs_f32(),
+            frame: match delta {
+                Some(_) => self.frame_time.frame.wrapping_add(1),
+                None => 0,
+            },
+        };
+
         if core::mem::take(&mut self.recreate_swapchain) {
-            match self.swapchain.recreate(SwapchainCreateInfo {
-                image_extent: [width, height],
-                ..self.swapchain.create_info()
-            }) {
-                Ok((new_swapchain, new_image)) => {
-                    self.swapchain = new_swapchain;
-                    self.swapchain_images = new_image;
-                    self.swapchain_framebuffers = create_framebuffers(
-                        &self.basic_buffers_manager.memo_allocator,
-                        &self.swapchain_images,
-                        &self.render_pass,
-                        self.samples,
-                    )
-                    .map_err(DrawError::FailedToRecreateTheFramebuffers)?;
-                    self.swapchain_is_new = true;
-                }
-                Err(e) => {
-                    error!("{e}");
-                    // try again
-                    self.recreate_swapchain = true;
-                    return Ok(());
-                    // panic!()
-                }
-            }
+            self.swapchain_images = match &self.swapchain {
+                Some(swapchain) => match swapchain.recreate(SwapchainCreateInfo {
+                    image_extent: [width, height],
+                    ..swapchain.create_info()
+                }) {
+                    Ok((new_swapchain, new_image)) => {
+                        self.swapchain = Some(new_swapchain);
+                        new_image
+                    }
+                    Err(e) => {
+                        error!("{e}");
+                        // try again
+                        self.recreate_swapchain = true;
+                        return Ok(());
+                        // panic!()
+                    }
+                },
+                None => create_headless_images(
+                    &self.memory_allocator,
+                    [width, height],
+                    self.image_usage,
+                )
+                .map_err(DrawError::FailedToRecreateTheHeadlessImage)?,
+            };
+            self.intermediate_images = create_intermediate_images(
+                &self.memory_allocator,
+                &self.swapchain_images,
+                self.image_usage,
+            )
+            .map_err(DrawError::FailedToRecreateTheIntermediateImages)?;
+            self.swapchain_framebuffers = self
+                .create_swapchain_framebuffers(render_images(
+                    &self.swapchain_images,
+                    &self.intermediate_images,
+                ))
+                .map_err(DrawError::FailedToRecreateTheFramebuffers)?;
+            self.swapchain_is_new = true;
         }
 
-        let (swapchain_image_index, suboptimal, acquire_future) =
-            match acquire_next_image(Arc::clone(&self.swapchain), Some(Duration::from_secs(1))) {
-                Ok(ok) => Ok(ok),
-                Err(Validated::Error(VulkanError::Timeout)) => {
-                    return Err(DrawError::AcquiringSwapchainImageReachedTimeout)
+        let (swapchain_image_index, acquire_future) = match &self.swapchain {
+            Some(swapchain) => {
+                let (swapchain_image_index, suboptimal, acquire_future) =
+                    match acquire_next_image(Arc::clone(swapchain), Some(Duration::from_secs(1))) {
+                        Ok(ok) => ok,
+                        Err(Validated::Error(VulkanError::Timeout)) => {
+                            return Err(DrawError::AcquiringSwapchainImageReachedTimeout)
+                        }
+                        Err(Validated::Error(VulkanError::OutOfDate)) => {
+                            self.recreate_swapchain = true;
+                            return Err(DrawError::SwapchainOutOfDate);
+                        }
+                        Err(Validated::Error(e)) => {
+                            return Err(DrawError::FailedToAcquireSwapchainImage(e))
+                        }
+                        Err(Validated::ValidationError(e)) => {
+                            return Err(DrawError::ValidationError(e))
+                        }
+                    };
+
+                if suboptimal {
+                    self.recreate_swapchain = true;
                 }
-                e => e,
-            }
-            .unwrap();
 
-        if suboptimal {
-            self.recreate_swapchain = true;
-        }
+                (swapchain_image_index, Some(acquire_future))
+            }
+            None => (0, None),
+        };
 
         let mut primary = AutoCommandBufferBuilder::primary(
             &self.cmd_allocator,
@@ -296,31 +1106,98 @@ impl VulkanSystem {
         )
         .unwrap();
 
+        let timer_slot = self
+            .gpu_timer
+            .as_mut()
+            .and_then(|timer| timer.begin(&mut primary));
+
+        let target_image = &render_images(&self.swapchain_images, &self.intermediate_images)
+            [swapchain_image_index as usize];
+        let (target, main_pass) = match &self.rendering_format {
+            Some(format) => {
+                // only the transient attachments depend on the size, the target changes per frame
+                let attachments = self
+                    .rendering_attachments
+                    .as_ref()
+                    .and_then(|attachments| attachments.with_target(Arc::clone(target_image)))
+                    .unwrap_or_else(|| {
+                        RenderingAttachments::new(
+                            &self.basic_buffers_manager.memo_allocator,
+                            Arc::clone(target_image),
+                            format,
+                        )
+                    })
+                    .map_err(DrawError::FailedToRecreateTheFramebuffers)?;
+                let main_pass = PassBegin::Rendering(
+                    attachments.rendering_info(Some(self.pass_clear_value().into())),
+                );
+                let target = PassTarget::Rendering(format, attachments.extent());
+                self.rendering_attachments = Some(attachments);
+                (target, main_pass)
+            }
+            None => {
+                let framebuffer = &self.swapchain_framebuffers[swapchain_image_index as usize];
+                let main_pass = PassBegin::RenderPass(RenderPassBeginInfo {
+                    clear_values: clear_values(
+                        self.pass_clear_value(),
+                        self.samples,
+                        self.depth_format,
+                    ),
+                    ..RenderPassBeginInfo::framebuffer(Arc::clone(framebuffer))
+                });
+                (
+                    PassTarget::RenderPass(&self.render_pass, framebuffer),
+                    main_pass,
+                )
+            }
+        };
+
+        let render_target_passes = RefCell::default();
+        let viewports = self.viewports();
         let context = RenderContext {
             queue_family_index: self.queue.queue_family_index(),
-            renderpass: &self.render_pass,
-            swapchain_framebuffer: &self.swapchain_framebuffers[swapchain_image_index as usize],
+            target,
             command_buffer_allocator: &self.cmd_allocator,
             write_descriptor_set_manager: &self.write_descriptors,
             image_system: &self.image_system,
+            frame_arena: &self.frame_arena,
+            render_target_passes: &render_target_passes,
+            target_image,
+            viewports: &viewports,
         };
 
         let mut prepare_commands: Vec<Arc<dyn SecondaryCommandBufferAbstract>> = Vec::new();
-        let mut render_commands: Vec<Arc<dyn SecondaryCommandBufferAbstract>> = Vec::new();
+        // submitted in order before the primary command buffer
+        let mut preceding_commands: Vec<Arc<PrimaryAutoCommandBuffer>> = Vec::new();
 
-        acquire_future
-            .wait(Some(Duration::from_secs(10)))
-            .map_err(DrawError::FailedToAcquireSwapchainImage)?;
+        if let Some(acquire_future) = &acquire_future {
+            acquire_future
+                .wait(Some(Duration::from_secs(10)))
+                .map_err(DrawError::FailedToAcquireSwapchainImage)?;
+        }
         if let Some(previous) = self.previous_frame_end.as_mut() {
             previous.cleanup_finished();
         }
+        self.image_system
+            .deferred_destruction()
+            .on_frame_completed();
 
-        if core::mem::take(&mut self.swapchain_is_new) {
+        {
             let mut buffer = context
                 .create_preparation_buffer_builder()
                 .expect("Failed to create preparation command buffer for descriptor updates");
-            self.update_write_descriptor_sets(&mut buffer)
-                .expect("Failed to update write descriptor sets");
+            if core::mem::take(&mut self.swapchain_is_new)
+                | core::mem::take(&mut self.ambient_light_changed)
+                | core::mem::take(&mut self.pixels_per_point_changed)
+                | core::mem::take(&mut self.viewport_layout_changed)
+            {
+                self.update_write_descriptor_sets(&mut buffer)
+                    .expect("Failed to update write descriptor sets");
+            }
+            // changes every frame
+            self.write_descriptors
+                .update(&mut buffer, FrameTime::from(&*self))
+                .expect("Failed to update the frame time descriptor");
             prepare_commands.push(
                 buffer
                     .build()
@@ -328,17 +1205,16 @@ impl VulkanSystem {
             );
         }
 
-        let callback_commands = render_callback(&context);
-
         // collect all enqueued requests from other systems and insert it before the commands of
-        // the callback.
+        // the callback. This happens before invoking the callback, so that it can tell whether an
+        // image is going to be uploaded in this frame or was deferred by the upload budget.
         // TODO might need to extend to more systems in the future
         if self.image_system.has_upload_info_enqueued() {
             let mut buffer = context
                 .create_preparation_buffer_builder()
                 .expect("Failed to create preparation command buffer system updates");
 
-            while let Some(upload_request) = self.image_system.next_upload_info() {
+            for upload_request in self.image_system.next_upload_infos(&self.upload_budget) {
                 if let Err(e) = buffer.copy_buffer_to_image(upload_request) {
                     error!("Failed to enqueue copy_buffer_to_image-cmd: {e}");
                 }
@@ -351,78 +1227,196 @@ impl VulkanSystem {
             )
         }
 
-        for command in callback_commands {
-            if command.inheritance_info().render_pass.is_none() {
-                prepare_commands.push(command);
-            } else {
-                render_commands.push(command);
+        match recorder {
+            FrameRecorder::Secondary(render_callback) => {
+                let mut render_commands: Vec<Arc<dyn SecondaryCommandBufferAbstract>> = Vec::new();
+                if !self.viewport_layout.is_fullscreen() {
+                    let mut builder = context
+                        .create_render_buffer_builder()
+                        .expect("Failed to create command buffer for clearing the viewports");
+                    clear_viewports(&mut builder, &viewports, self.clear_value_rgba)?;
+                    render_commands.push(
+                        builder
+                            .build()
+                            .map_err(DrawError::FailedToBuildCommandBuffer)?,
+                    );
+                }
+                for command in render_callback(&context) {
+                    if command.inheritance_info().render_pass.is_none() {
+                        prepare_commands.push(command);
+                    } else {
+                        render_commands.push(command);
+                    }
+                }
+
+                if let Err(e) = primary.execute_commands_from_vec(prepare_commands) {
+                    error!("Failed to execute preparation commands: {e:?}");
+                }
+
+                execute_render_target_passes(&mut primary, render_target_passes.take())?;
+
+                let end =
+                    main_pass.begin(&mut primary, SubpassContents::SecondaryCommandBuffers)?;
+                if let Err(e) = primary.execute_commands_from_vec(render_commands) {
+                    error!("Failed to execute rendering commands: {e:?}");
+                }
+                end.end(&mut primary)?;
+            }
+            FrameRecorder::Inline {
+                world_target,
+                callback,
+            } => {
+                // the callback records into primary command buffers of its own, so that the
+                // passes it requests with render_into can be submitted before them
+                let create_primary = || {
+                    AutoCommandBufferBuilder::primary(
+                        &self.cmd_allocator,
+                        self.queue.queue_family_index(),
+                        CommandBufferUsage::OneTimeSubmit,
+                    )
+                    .map_err(|e| {
+                        DrawError::FailedToCreateCommandBuffer(Error::FailedToCreateCommandBuffer(
+                            e,
+                        ))
+                    })
+                };
+
+                let mut world = match world_target {
+                    Some(world_target) => {
+                        let mut builder = create_primary()?;
+                        let end = world_target
+                            .pass_begin()
+                            .begin(&mut builder, SubpassContents::Inline)?;
+                        set_full_viewport(&mut builder, world_target.extent())?;
+                        Some((builder, end))
+                    }
+                    None => None,
+                };
+
+                let mut main = create_primary()?;
+                let end = main_pass.begin(&mut main, SubpassContents::Inline)?;
+                if self.viewport_layout.is_fullscreen() {
+                    set_full_viewport(&mut main, context.target.extent())?;
+                } else {
+                    clear_viewports(&mut main, &viewports, self.clear_value_rgba)?;
+                    set_viewport_rect(&mut main, viewports[0])?;
+                }
+                callback(
+                    &context,
+                    world.as_mut().map(|(builder, _)| builder),
+                    &mut main,
+                );
+                end.end(&mut main)?;
+                let world = match world {
+                    Some((mut builder, end)) => {
+                        end.end(&mut builder)?;
+                        Some(
+                            builder
+                                .build()
+                                .map_err(DrawError::FailedToBuildCommandBuffer)?,
+                        )
+                    }
+                    None => None,
+                };
+
+                if let Err(e) = primary.execute_commands_from_vec(prepare_commands) {
+                    error!("Failed to execute preparation commands: {e:?}");
+                }
+
+                execute_render_target_passes(&mut primary, render_target_passes.take())?;
+
+                // the post-processing below is recorded after the main render pass
+                preceding_commands.push(
+                    core::mem::replace(&mut primary, main)
+                        .build()
+                        .map_err(DrawError::FailedToBuildCommandBuffer)?,
+                );
+                preceding_commands.extend(world);
             }
         }
 
-        if let Err(e) = primary.execute_commands_from_vec(prepare_commands) {
-            error!("Failed to execute preparation commands: {e:?}");
+        if let Some(filter) = self.color_filter {
+            let image = Arc::clone(
+                &render_images(&self.swapchain_images, &self.intermediate_images)
+                    [swapchain_image_index as usize],
+            );
+            self.record_color_filter(&mut primary, &image, filter);
         }
 
-        primary
-            .begin_render_pass(
-                RenderPassBeginInfo {
-                    clear_values: if self.samples == SampleCount::Sample1 {
-                        vec![Some(self.clear_value_rgba.into())]
-                    } else {
-                        vec![Some(self.clear_value_rgba.into()), None]
-                    },
-                    // clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into())],
-                    ..RenderPassBeginInfo::framebuffer(Arc::clone(
-                        &self.swapchain_framebuffers[swapchain_image_index as usize],
-                    ))
-                },
-                SubpassBeginInfo {
-                    contents: SubpassContents::SecondaryCommandBuffers,
-                    ..SubpassBeginInfo::default()
-                },
-            )?
-            .set_viewport(
-                0,
-                [Viewport {
-                    offset: [0.0, 0.0],
-                    extent: [
-                        self.swapchain_images[0].extent()[0] as f32,
-                        self.swapchain_images[0].extent()[1] as f32,
-                    ],
-                    depth_range: 0.0..=1.0,
-                }]
-                .into_iter()
-                .collect(),
-            )?;
+        if let Some(intermediate) = self.intermediate_images.get(swapchain_image_index as usize) {
+            primary.copy_image(CopyImageInfo::images(
+                Arc::clone(intermediate),
+                Arc::clone(&self.swapchain_images[swapchain_image_index as usize]),
+            ))?;
+        }
+
+        if core::mem::take(&mut self.capture_requested) {
+            let image = Arc::clone(
+                &render_images(&self.swapchain_images, &self.intermediate_images)
+                    [swapchain_image_index as usize],
+            );
+            if let Err(e) = self.record_frame_capture(&mut primary, image) {
+                error!("Failed to capture the frame: {e}");
+                self.deliver_frame_capture(Err(&e));
+            }
+        }
 
-        if let Err(e) = primary.execute_commands_from_vec(render_commands) {
-            error!("Failed to execute rendering commands: {e:?}");
+        if let (Some(timer), Some(slot)) = (&mut self.gpu_timer, timer_slot) {
+            timer.end(&mut primary, slot);
         }
 
-        primary.end_render_pass(SubpassEndInfo::default())?;
         let command_buffer = primary
             .build()
             .map_err(DrawError::FailedToBuildCommandBuffer)?;
 
-        let future = self
+        let mut future = self
             .previous_frame_end
             .take()
-            .unwrap_or_else(|| vulkano::sync::now(Arc::clone(&self.device)).boxed())
-            .join(acquire_future)
+            .unwrap_or_else(|| vulkano::sync::now(Arc::clone(&self.device)).boxed_send_sync());
+
+        if let Some(acquire_future) = acquire_future {
+            future = future.join(acquire_future).boxed_send_sync();
+        }
+
+        for preceding in preceding_commands {
+            future = future
+                .then_execute(Arc::clone(&self.queue), preceding)
+                .unwrap()
+                .boxed_send_sync();
+        }
+
+        let future = future
             .then_execute(Arc::clone(&self.queue), command_buffer)
-            .unwrap()
-            .then_swapchain_present(
-                Arc::clone(&self.queue),
-                SwapchainPresentInfo::swapchain_image_index(
-                    Arc::clone(&self.swapchain),
-                    swapchain_image_index,
-                ),
-            )
-            .then_signal_fence_and_flush();
+            .unwrap();
+
+        // a separate present queue has to wait for the rendering on the graphics queue
+        let future = if self.queue_families().is_shared() {
+            future.boxed_send_sync()
+        } else {
+            future.then_signal_semaphore().boxed_send_sync()
+        };
+
+        let future = match &self.swapchain {
+            Some(swapchain) => future
+                .then_swapchain_present(
+                    Arc::clone(&self.present_queue),
+                    SwapchainPresentInfo::swapchain_image_index(
+                        Arc::clone(swapchain),
+                        swapchain_image_index,
+                    ),
+                )
+                .boxed_send_sync(),
+            None => future.boxed_send_sync(),
+        }
+        .then_signal_fence_and_flush();
 
         match future {
             Ok(future) => {
-                self.previous_frame_end = Some(future.boxed());
+                let fence = Arc::new(future);
+                self.image_system
+                    .deferred_destruction()
+                    .on_frame_submitted(Arc::clone(&fence));
+                self.previous_frame_end = Some(fence.boxed_send_sync());
             }
             Err(e) => {
                 match e {
@@ -432,20 +1426,282 @@ impl VulkanSystem {
                 }
                 self.recreate_swapchain = true;
                 self.previous_frame_end =
-                    Some(vulkano::sync::now(Arc::clone(&self.device)).boxed());
+                    Some(vulkano::sync::now(Arc::clone(&self.device)).boxed_send_sync());
+                if let (Some(timer), Some(slot)) = (&mut self.gpu_timer, timer_slot) {
+                    timer.discard(slot);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Disables the [`ColorFilter`] if it cannot be applied, so the error is only logged once.
+    fn record_color_filter(
+        &mut self,
+        builder: &mut InlineCommandBufferBuilder,
+        image: &Arc<Image>,
+        filter: ColorFilter,
+    ) {
+        if !image.usage().intersects(ImageUsage::TRANSFER_SRC) {
+            warn!("Disabling the color filter, the rendered images do not support TRANSFER_SRC");
+            self.color_filter = None;
+            return;
+        }
+        if self.color_filter_pass.is_none() {
+            match ColorFilterPass::new(
+                Arc::clone(&self.device),
+                image.format(),
+                self.pipeline_cache().map(Arc::clone),
+                Arc::clone(self.write_descriptors.descriptor_set_allocator()),
+                Arc::clone(&self.memory_allocator) as Arc<dyn MemoryAllocator>,
+            ) {
+                Ok(pass) => self.color_filter_pass = Some(pass),
+                Err(e) => {
+                    error!("Disabling the color filter, failed to create its pipeline: {e}");
+                    self.color_filter = None;
+                    return;
+                }
+            }
+        }
+        if let Some(pass) = &mut self.color_filter_pass {
+            if let Err(e) = pass.record(builder, image, filter) {
+                error!("Disabling the color filter: {e}");
+                self.color_filter = None;
             }
         }
+    }
+
+    /// Renders every following frame as if exactly `delta` passed since the previous one, so the
+    /// [`FrameTime`] of the shaders advances at a fixed rate regardless of the wall clock, for
+    /// example while capturing a frame sequence. [`None`] continues with the wall clock from the
+    /// current [`FrameTime::elapsed`] on.
+    pub fn set_fixed_frame_delta(&mut self, delta: Option<Duration>) {
+        if self.fixed_frame_delta.is_some() && delta.is_none() {
+            self.started = Instant::now()
+                .checked_sub(Duration::from_secs_f32(self.frame_time.elapsed))
+                .unwrap_or(self.started);
+        }
+        self.fixed_frame_delta = delta;
+    }
+
+    #[inline]
+    pub fn fixed_frame_delta(&self) -> Option<Duration> {
+        self.fixed_frame_delta
+    }
+
+    /// Copies the next rendered frame into a host visible buffer, after everything (including
+    /// the [`ColorFilter`]) was drawn, see [`VulkanSystem::take_frame_capture`]. The images that
+    /// are rendered into need [`ImageUsage::TRANSFER_SRC`], see [`VulkanSystem::image_usage`].
+    #[inline]
+    pub fn request_frame_capture(&mut self) {
+        self.capture_requested = true;
+    }
+
+    fn record_frame_capture(
+        &mut self,
+        builder: &mut InlineCommandBufferBuilder,
+        image: Arc<Image>,
+    ) -> Result<(), ReadbackError> {
+        if !image.usage().intersects(ImageUsage::TRANSFER_SRC) {
+            return Err(ReadbackError::MissingTransferSource);
+        }
+        let format = image.format();
+        if !matches!(
+            format,
+            Format::R8G8B8A8_SRGB
+                | Format::R8G8B8A8_UNORM
+                | Format::B8G8R8A8_SRGB
+                | Format::B8G8R8A8_UNORM
+        ) {
+            return Err(ReadbackError::UnsupportedFormat(format));
+        }
 
+        let [width, height, _] = image.extent();
+        let buffer = Buffer::new_slice::<u8>(
+            Arc::clone(&self.memory_allocator) as Arc<dyn MemoryAllocator>,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..BufferCreateInfo::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..AllocationCreateInfo::default()
+            },
+            DeviceSize::from(width) * DeviceSize::from(height) * 4,
+        )?;
+        builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(image, buffer.clone()))?;
+        self.frame_capture = Some(FrameCapture {
+            buffer,
+            extent: [width, height],
+            format,
+        });
         Ok(())
     }
+
+    /// Calls `f` with the next rendered frame as RGBA, for example for screenshots of bug
+    /// reports or a photo mode. The frame is read back once the GPU finished it, at the latest
+    /// when rendering the frame after it. See [`VulkanSystem::request_frame_capture`] for the
+    /// requirements.
+    pub fn capture_next_frame(
+        &mut self,
+        f: impl FnOnce(Result<&RawRgbaImage, &ReadbackError>) + Send + 'static,
+    ) {
+        self.capture_callbacks.push(Box::new(f));
+        self.request_frame_capture();
+    }
+
+    fn deliver_frame_capture(&mut self, result: Result<&RawRgbaImage, &ReadbackError>) {
+        for callback in self.capture_callbacks.drain(..) {
+            callback(result);
+        }
+    }
+
+    /// The frame captured with [`VulkanSystem::request_frame_capture`] as RGBA, [`None`] if no
+    /// frame was captured since the last call. Waits for the frame to be rendered. The frame is
+    /// passed to the callbacks of [`VulkanSystem::capture_next_frame`] as well.
+    pub fn take_frame_capture(&mut self) -> Result<Option<RawRgbaImage>, ReadbackError> {
+        let Some(capture) = self.frame_capture.take() else {
+            return Ok(None);
+        };
+        let result = self.read_frame_capture(capture);
+        self.deliver_frame_capture(result.as_ref());
+        result.map(Some)
+    }
+
+    fn read_frame_capture(&mut self, capture: FrameCapture) -> Result<RawRgbaImage, ReadbackError> {
+        if let Some(previous) = self.previous_frame_end.take() {
+            previous.then_signal_fence_and_flush()?.wait(None)?;
+        }
+        self.previous_frame_end =
+            Some(vulkano::sync::now(Arc::clone(&self.device)).boxed_send_sync());
+
+        let mut data = capture.buffer.read()?.to_vec();
+        if matches!(
+            capture.format,
+            Format::B8G8R8A8_SRGB | Format::B8G8R8A8_UNORM
+        ) {
+            data.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
+        }
+        let [width, height] = capture.extent;
+        Ok(RawRgbaImage::new(data, width, height))
+    }
+
+    /// Copies the content of the image of a headless system after waiting for the last frame to
+    /// be rendered. The pixels are in [`VulkanSystem::HEADLESS_FORMAT`].
+    pub fn read_pixels(&mut self) -> Result<RawRgbaImage, ReadbackError> {
+        if !self.is_headless() {
+            return Err(ReadbackError::NotHeadless);
+        }
+
+        let image = Arc::clone(&self.swapchain_images[0]);
+        let [width, height] = self.image_extent();
+        let buffer = Buffer::new_slice::<u8>(
+            Arc::clone(&self.memory_allocator) as Arc<dyn MemoryAllocator>,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..BufferCreateInfo::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..AllocationCreateInfo::default()
+            },
+            DeviceSize::from(width) * DeviceSize::from(height) * 4,
+        )?;
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.cmd_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(image, buffer.clone()))?;
+        let command_buffer = builder.build()?;
+
+        self.previous_frame_end
+            .take()
+            .unwrap_or_else(|| vulkano::sync::now(Arc::clone(&self.device)).boxed_send_sync())
+            .then_execute(Arc::clone(&self.queue), command_buffer)?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+        self.previous_frame_end =
+            Some(vulkano::sync::now(Arc::clone(&self.device)).boxed_send_sync());
+
+        let data = buffer.read()?.to_vec();
+        Ok(RawRgbaImage::new(data, width, height))
+    }
+}
+
+impl Drop for VulkanSystem {
+    fn drop(&mut self) {
+        if let Err(e) = self.save_pipeline_cache() {
+            error!("Failed to save the pipeline cache: {e}");
+        }
+    }
+}
+
+/// The queue families chosen by [`choose_physical_device`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct QueueFamilies {
+    graphics: u32,
+    present: u32,
+}
+
+impl QueueFamilies {
+    /// Whether the graphics queue family is used for presenting as well.
+    #[inline]
+    fn is_shared(self) -> bool {
+        self.graphics == self.present
+    }
+
+    /// The indices of the queue families, without duplicates, the graphics family first.
+    #[inline]
+    fn distinct(self) -> impl Iterator<Item = u32> {
+        std::iter::once(self.graphics).chain(Some(self.present).filter(|_| !self.is_shared()))
+    }
+
+    /// Prefers a queue family that supports both, graphics and presenting to the surface. Some
+    /// devices only support presenting on a family without graphics capabilities, then two
+    /// distinct families are used. Without surface, the graphics family is used for both.
+    fn choose(physical_device: &PhysicalDevice, surface: Option<&Surface>) -> Option<Self> {
+        let families = physical_device.queue_family_properties();
+        let supports_present = |index: usize| {
+            surface.map_or(true, |surface| {
+                physical_device
+                    .surface_support(index as u32, surface)
+                    .unwrap_or(false)
+            })
+        };
+        let graphics = families
+            .iter()
+            .enumerate()
+            .inspect(|(i, q)| info!("Queue({i}) = {q:?}"))
+            .filter(|(_, q)| q.queue_flags.contains(QueueFlags::GRAPHICS))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        if let Some(shared) = graphics.iter().copied().find(|i| supports_present(*i)) {
+            return Some(Self {
+                graphics: shared as u32,
+                present: shared as u32,
+            });
+        }
+
+        let present = (0..families.len()).find(|i| supports_present(*i))?;
+        Some(Self {
+            graphics: *graphics.first()? as u32,
+            present: present as u32,
+        })
+    }
 }
 
 fn choose_physical_device(
-    surface: &Surface,
+    instance: &Arc<Instance>,
+    surface: Option<&Surface>,
     device_extensions: &mut DeviceExtensions,
-) -> Result<(Arc<PhysicalDevice>, u32), Error> {
-    surface
-        .instance()
+) -> Result<(Arc<PhysicalDevice>, QueueFamilies), Error> {
+    instance
         .enumerate_physical_devices()
         .map_err(Error::FailedToEnumeratePhysicalDevices)?
         .filter(|p| {
@@ -475,17 +1731,7 @@ fn choose_physical_device(
             }
             satisfies_req_device_extensions
         })
-        .filter_map(|p| {
-            p.queue_family_properties()
-                .iter()
-                .enumerate()
-                .position(|(i, q)| {
-                    info!("Queue({i}) = {q:?}");
-                    q.queue_flags.contains(QueueFlags::GRAPHICS)
-                        && p.surface_support(i as u32, &surface).unwrap_or(false)
-                })
-                .map(|i| (p, i as u32))
-        })
+        .filter_map(|p| QueueFamilies::choose(&p, surface).map(|families| (p, families)))
         .min_by_key(|(p, _)| match p.properties().device_type {
             PhysicalDeviceType::DiscreteGpu => 0,
             PhysicalDeviceType::IntegratedGpu => 1,
@@ -494,10 +1740,12 @@ fn choose_physical_device(
             PhysicalDeviceType::Other => 4,
             _ => 5,
         })
-        .map(|(p, i)| {
+        .map(|(p, families)| {
             info!(
-                "Chosen physical device {} and with queue family index {i} and v{:?}",
+                "Chosen physical device {} with the graphics queue family index {} and the present queue family index {} and v{:?}",
                 p.properties().device_name,
+                families.graphics,
+                families.present,
                 p.api_version()
             );
 
@@ -506,7 +1754,7 @@ fn choose_physical_device(
             device_extensions.khr_dynamic_rendering = true;
             // }
 
-            (p, i)
+            (p, families)
         })
         .ok_or(Error::NoSatisfyingPhysicalDevicePresent)
 }
@@ -516,6 +1764,8 @@ fn create_swapchain(
     surface: &Arc<Surface>,
     image_extent: [u32; 2],
     samples: SampleCount,
+    queue_families: QueueFamilies,
+    image_usage: ImageUsage,
 ) -> Result<(Arc<Swapchain>, Vec<Arc<Image>>), Error> {
     let surface_capabilities = device
         .physical_device()
@@ -539,6 +1789,24 @@ fn create_swapchain(
         .expect("Did not find a suitable color space")
         .0;
 
+    let mut usage = if samples == SampleCount::Sample1 {
+        ImageUsage::COLOR_ATTACHMENT
+    } else {
+        ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_DST
+    };
+    if surface_capabilities
+        .supported_usage_flags
+        .contains(image_usage)
+    {
+        usage |= image_usage;
+    } else {
+        // the intermediate images are copied into the swapchain images
+        warn!(
+            "The swapchain images do not support {image_usage:?}, rendering into intermediate images"
+        );
+        usage |= ImageUsage::TRANSFER_DST;
+    }
+
     Swapchain::new(
         Arc::clone(&device),
         Arc::clone(&surface),
@@ -546,10 +1814,12 @@ fn create_swapchain(
             min_image_count: surface_capabilities.min_image_count,
             image_format,
             image_extent,
-            image_usage: if samples == SampleCount::Sample1 {
-                ImageUsage::COLOR_ATTACHMENT
+            image_usage: usage,
+            // without ownership transfers between the graphics and present queue families
+            image_sharing: if queue_families.is_shared() {
+                Sharing::Exclusive
             } else {
-                ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_DST
+                Sharing::Concurrent(queue_families.distinct().collect())
             },
             composite_alpha: surface_capabilities
                 .supported_composite_alpha
@@ -562,58 +1832,278 @@ fn create_swapchain(
     .map_err(Error::SwapchainInitializationFailed)
 }
 
+fn create_headless_images(
+    allocator: &Arc<InstrumentedMemoryAllocator>,
+    [width, height]: [u32; 2],
+    image_usage: ImageUsage,
+) -> Result<Vec<Arc<Image>>, Validated<AllocateImageError>> {
+    Ok(vec![Image::new(
+        Arc::clone(allocator) as Arc<dyn MemoryAllocator>,
+        ImageCreateInfo {
+            image_type: ImageType::Dim2d,
+            format: VulkanSystem::HEADLESS_FORMAT,
+            extent: [width.max(1), height.max(1), 1],
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC | image_usage,
+            ..ImageCreateInfo::default()
+        },
+        AllocationCreateInfo::default(),
+    )?])
+}
+
+/// Creates an image of the same format and size for each image without the requested usage,
+/// returns no images if all images support the usage already.
+fn create_intermediate_images(
+    allocator: &Arc<InstrumentedMemoryAllocator>,
+    images: &[Arc<Image>],
+    image_usage: ImageUsage,
+) -> Result<Vec<Arc<Image>>, Validated<AllocateImageError>> {
+    if images
+        .iter()
+        .all(|image| image.usage().contains(image_usage))
+    {
+        return Ok(Vec::new());
+    }
+    images
+        .iter()
+        .map(|image| {
+            Image::new(
+                Arc::clone(allocator) as Arc<dyn MemoryAllocator>,
+                ImageCreateInfo {
+                    image_type: ImageType::Dim2d,
+                    format: image.format(),
+                    extent: image.extent(),
+                    usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC | image_usage,
+                    ..ImageCreateInfo::default()
+                },
+                AllocationCreateInfo::default(),
+            )
+        })
+        .collect()
+}
+
+/// The intermediate images if there are any, the swapchain images otherwise.
+#[inline]
+fn render_images<'a>(
+    swapchain_images: &'a [Arc<Image>],
+    intermediate_images: &'a [Arc<Image>],
+) -> &'a [Arc<Image>] {
+    if intermediate_images.is_empty() {
+        swapchain_images
+    } else {
+        intermediate_images
+    }
+}
+
+/// Whether images of the format can be created with the usage at all, for example `SRGB` formats
+/// usually do not support [`ImageUsage::STORAGE`].
+fn check_format_supports_usage(
+    device: &Device,
+    format: Format,
+    image_usage: ImageUsage,
+) -> Result<(), Error> {
+    let features = device
+        .physical_device()
+        .format_properties(format)
+        .map_or(FormatFeatures::empty(), |properties| {
+            properties.optimal_tiling_features
+        });
+    let supported = [
+        (ImageUsage::TRANSFER_SRC, FormatFeatures::TRANSFER_SRC),
+        (ImageUsage::SAMPLED, FormatFeatures::SAMPLED_IMAGE),
+        (ImageUsage::STORAGE, FormatFeatures::STORAGE_IMAGE),
+    ]
+    .into_iter()
+    .all(|(usage, feature)| !image_usage.intersects(usage) || features.intersects(feature));
+    if supported {
+        Ok(())
+    } else {
+        Err(Error::UnsupportedImageUsage(image_usage, format))
+    }
+}
+
+fn clear_values(
+    rgba: [f32; 4],
+    samples: SampleCount,
+    depth_format: Option<Format>,
+) -> Vec<Option<ClearValue>> {
+    let mut clear_values = if samples == SampleCount::Sample1 {
+        vec![Some(rgba.into())]
+    } else {
+        vec![Some(rgba.into()), None]
+    };
+    if depth_format.is_some() {
+        // the far plane, so that anything drawn passes the depth test
+        clear_values.push(Some(ClearValue::Depth(1.0)));
+    }
+    clear_values
+}
+
 fn create_framebuffers(
     allocator: &Arc<dyn MemoryAllocator>,
     images: &[Arc<Image>],
     render_pass: &Arc<RenderPass>,
     sample_count: SampleCount,
-) -> Result<Vec<Arc<Framebuffer>>, Validated<VulkanError>> {
+    depth_format: Option<Format>,
+) -> Result<Vec<Arc<Framebuffer>>, AttachmentError> {
     images
         .iter()
         .map(|image| {
+            let mut attachments = if sample_count == SampleCount::Sample1 {
+                vec![ImageView::new_default(Arc::clone(image))?]
+            } else {
+                vec![
+                    create_transient_attachment(
+                        allocator,
+                        image.format(),
+                        image.extent(),
+                        sample_count,
+                        ImageUsage::COLOR_ATTACHMENT,
+                    )?,
+                    ImageView::new_default(Arc::clone(image))?,
+                ]
+            };
+            if let Some(depth_format) = depth_format {
+                attachments.push(create_transient_attachment(
+                    allocator,
+                    depth_format,
+                    image.extent(),
+                    sample_count,
+                    ImageUsage::DEPTH_STENCIL_ATTACHMENT,
+                )?);
+            }
             Framebuffer::new(
                 Arc::clone(&render_pass),
-                if sample_count == SampleCount::Sample1 {
-                    FramebufferCreateInfo {
-                        attachments: vec![ImageView::new_default(Arc::clone(image))?],
-                        ..FramebufferCreateInfo::default()
-                    }
-                } else {
-                    FramebufferCreateInfo {
-                        attachments: vec![
-                            ImageView::new_default(
-                                Image::new(
-                                    Arc::clone(&allocator),
-                                    ImageCreateInfo {
-                                        image_type: ImageType::Dim2d,
-                                        format: image.format(),
-                                        extent: image.extent(),
-                                        usage: ImageUsage::COLOR_ATTACHMENT
-                                            | ImageUsage::TRANSIENT_ATTACHMENT,
-                                        samples: sample_count,
-                                        ..Default::default()
-                                    },
-                                    AllocationCreateInfo::default(),
-                                )
-                                .unwrap(),
-                            )?,
-                            ImageView::new_default(Arc::clone(image))?,
-                        ],
-                        ..FramebufferCreateInfo::default()
-                    }
+                FramebufferCreateInfo {
+                    attachments,
+                    ..FramebufferCreateInfo::default()
                 },
             )
+            .map_err(AttachmentError::from)
         })
         .collect::<Result<Vec<_>, _>>()
 }
 
+/// Sets the viewport and the scissor to the whole extent. All pipelines have a dynamic scissor, see
+/// [`BufferedCanvasLayer::push_clip_rect`].
+///
+/// [`BufferedCanvasLayer::push_clip_rect`]: crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer::push_clip_rect
+#[inline]
+fn set_full_viewport<L, A: CommandBufferAllocator>(
+    builder: &mut AutoCommandBufferBuilder<L, A>,
+    extent: [u32; 2],
+) -> Result<(), Box<ValidationError>> {
+    set_viewport_rect(builder, ViewportRect::full(extent))
+}
+
+/// Sets the viewport and the scissor to the rectangle, see [`ViewportLayout`].
+fn set_viewport_rect<L, A: CommandBufferAllocator>(
+    builder: &mut AutoCommandBufferBuilder<L, A>,
+    rect: ViewportRect,
+) -> Result<(), Box<ValidationError>> {
+    builder
+        .set_viewport(0, [rect.viewport()].into_iter().collect())?
+        .set_scissor(0, [rect.scissor()].into_iter().collect())?;
+    Ok(())
+}
+
+/// Clears the viewports with the clear value, the remaining bars keep the
+/// [`ViewportLayout::bar_color`] the pass was cleared with.
+fn clear_viewports<L, A: CommandBufferAllocator>(
+    builder: &mut AutoCommandBufferBuilder<L, A>,
+    viewports: &[ViewportRect],
+    rgba: [f32; 4],
+) -> Result<(), Box<ValidationError>> {
+    builder.clear_attachments(
+        [ClearAttachment::Color {
+            color_attachment: 0,
+            clear_value: rgba.into(),
+        }]
+        .into_iter()
+        .collect(),
+        viewports.iter().map(ViewportRect::clear_rect).collect(),
+    )?;
+    Ok(())
+}
+
+/// Renders into the [`RenderTarget`]s, see [`RenderContext::render_into`].
+fn execute_render_target_passes<L, A: CommandBufferAllocator>(
+    builder: &mut AutoCommandBufferBuilder<L, A>,
+    passes: Vec<RenderTargetPass>,
+) -> Result<(), Box<ValidationError>> {
+    for pass in passes {
+        let end = pass
+            .begin
+            .begin(builder, SubpassContents::SecondaryCommandBuffers)?;
+        if let Err(e) = builder.execute_commands_from_vec(pass.commands) {
+            error!("Failed to execute the commands of a render target: {e:?}");
+        }
+        end.end(builder)?;
+    }
+    Ok(())
+}
+
+/// How the commands of a frame are recorded, see [`VulkanSystem::render`] and
+/// [`VulkanSystem::render_inline`].
+enum FrameRecorder<'a, S, I> {
+    Secondary(S),
+    Inline {
+        /// Rendered into by the callback in a render pass of its own before the main render pass
+        world_target: Option<&'a RenderTarget>,
+        callback: I,
+    },
+}
+
+type SecondaryCallback = fn(&RenderContext) -> Vec<Arc<SecondaryAutoCommandBuffer>>;
+type InlineCallback =
+    fn(&RenderContext, Option<&mut InlineCommandBufferBuilder>, &mut InlineCommandBufferBuilder);
+
+/// The primary command buffer of [`VulkanSystem::render_inline`].
+pub type InlineCommandBufferBuilder = AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>;
+
+/// What the secondary command buffers of a [`RenderContext`] render into.
+#[derive(Copy, Clone)]
+enum PassTarget<'a> {
+    RenderPass(&'a Arc<RenderPass>, &'a Arc<Framebuffer>),
+    /// The formats and the extent of the attachments
+    Rendering(&'a RenderingFormat, [u32; 2]),
+}
+
+impl<'a> PassTarget<'a> {
+    fn extent(&self) -> [u32; 2] {
+        match self {
+            PassTarget::RenderPass(_, framebuffer) => {
+                [framebuffer.extent()[0], framebuffer.extent()[1]]
+            }
+            PassTarget::Rendering(_, extent) => *extent,
+        }
+    }
+
+    fn of(target: &'a RenderTarget) -> Self {
+        match &target.attachments {
+            RenderTargetAttachments::Framebuffer {
+                render_pass,
+                framebuffer,
+            } => PassTarget::RenderPass(render_pass, framebuffer),
+            RenderTargetAttachments::Rendering {
+                format,
+                attachments,
+            } => PassTarget::Rendering(format, attachments.extent()),
+        }
+    }
+}
+
 pub struct RenderContext<'a> {
     queue_family_index: u32,
-    renderpass: &'a Arc<RenderPass>,
-    swapchain_framebuffer: &'a Arc<Framebuffer>,
+    target: PassTarget<'a>,
     command_buffer_allocator: &'a StandardCommandBufferAllocator,
     write_descriptor_set_manager: &'a WriteDescriptorSetManager,
     image_system: &'a ImageSystem,
+    frame_arena: &'a FrameArena,
+    /// Rendered before the main render pass, see [`RenderContext::render_into`]
+    render_target_passes: &'a RefCell<Vec<RenderTargetPass>>,
+    target_image: &'a Arc<Image>,
+    /// Empty for a [`RenderTarget`], see [`VulkanSystem::viewports`]
+    viewports: &'a [ViewportRect],
 }
 
 impl<'a> RenderContext<'a> {
@@ -634,41 +2124,122 @@ impl<'a> RenderContext<'a> {
         .map_err(Error::FailedToCreateCommandBuffer)
     }
 
+    /// The commands render into the first of the [`RenderContext::viewports`], which is the
+    /// whole image without a [`ViewportLayout`].
     pub fn create_render_buffer_builder(
         &self,
     ) -> Result<AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>, Error> {
+        self.create_render_buffer_builder_for(self.target, self.viewports.first().copied())
+    }
+
+    /// Like [`RenderContext::create_render_buffer_builder`], but the commands render into the
+    /// viewport with the given index, for example for the second player of a split screen.
+    /// [`None`] if there is no such viewport.
+    pub fn create_viewport_buffer_builder(
+        &self,
+        index: usize,
+    ) -> Option<Result<AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>, Error>> {
+        let viewport = *self.viewports.get(index)?;
+        Some(self.create_render_buffer_builder_for(self.target, Some(viewport)))
+    }
+
+    /// The viewports of the [`VulkanSystem::viewport_layout`] in this frame, empty when
+    /// rendering into a [`RenderTarget`].
+    #[inline]
+    pub fn viewports(&self) -> &[ViewportRect] {
+        self.viewports
+    }
+
+    /// The viewport the command buffers of [`RenderContext::create_render_buffer_builder`]
+    /// render into, the first viewport or the whole target.
+    #[inline]
+    pub fn viewport_rect(&self) -> ViewportRect {
+        self.viewports
+            .first()
+            .copied()
+            .unwrap_or_else(|| ViewportRect::full(self.target.extent()))
+    }
+
+    /// Like [`RenderContext::create_render_buffer_builder`], but the commands render into the
+    /// [`RenderTarget`]. Submit them with [`RenderContext::render_into`] instead of returning
+    /// them from the render callback.
+    #[inline]
+    pub fn create_render_target_buffer_builder(
+        &self,
+        target: &RenderTarget,
+    ) -> Result<AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>, Error> {
+        self.create_render_buffer_builder_for(PassTarget::of(target), None)
+    }
+
+    /// Renders the commands into the [`RenderTarget`] before the main render pass of this frame.
+    /// Every call begins a new render pass, which clears the target first.
+    pub fn render_into(
+        &self,
+        target: &RenderTarget,
+        commands: impl IntoIterator<Item = Arc<dyn SecondaryCommandBufferAbstract>>,
+    ) {
+        self.render_target_passes
+            .borrow_mut()
+            .push(RenderTargetPass {
+                begin: target.pass_begin(),
+                commands: commands.into_iter().collect(),
+            });
+    }
+
+    /// A context whose [`RenderContext::create_render_buffer_builder`] renders into the
+    /// [`RenderTarget`] instead of the swapchain image, so code that renders into the main render
+    /// pass can be redirected without changes. The commands have to be submitted with
+    /// [`RenderContext::render_into`].
+    pub fn with_render_target<'t>(&'t self, target: &'t RenderTarget) -> RenderContext<'t> {
+        RenderContext {
+            queue_family_index: self.queue_family_index,
+            target: PassTarget::of(target),
+            command_buffer_allocator: self.command_buffer_allocator,
+            write_descriptor_set_manager: self.write_descriptor_set_manager,
+            image_system: self.image_system,
+            frame_arena: self.frame_arena,
+            render_target_passes: self.render_target_passes,
+            target_image: &target.image,
+            viewports: &[],
+        }
+    }
+
+    /// Renders into the whole target without a `viewport`.
+    fn create_render_buffer_builder_for(
+        &self,
+        target: PassTarget,
+        viewport: Option<ViewportRect>,
+    ) -> Result<AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>, Error> {
+        let render_pass = match target {
+            PassTarget::RenderPass(renderpass, framebuffer) => {
+                CommandBufferInheritanceRenderPassType::BeginRenderPass(
+                    CommandBufferInheritanceRenderPassInfo {
+                        subpass: Subpass::from(Arc::clone(renderpass), 0).unwrap(),
+                        framebuffer: Some(Arc::clone(framebuffer)),
+                    },
+                )
+            }
+            PassTarget::Rendering(format, _) => {
+                CommandBufferInheritanceRenderPassType::BeginRendering(format.inheritance_info())
+            }
+        };
         let mut secondary = AutoCommandBufferBuilder::secondary(
             self.command_buffer_allocator,
             self.queue_family_index,
             CommandBufferUsage::OneTimeSubmit,
             CommandBufferInheritanceInfo {
-                render_pass: Some(CommandBufferInheritanceRenderPassType::BeginRenderPass(
-                    CommandBufferInheritanceRenderPassInfo {
-                        subpass: Subpass::from(Arc::clone(&self.renderpass), 0).unwrap(),
-                        framebuffer: Some(Arc::clone(&self.swapchain_framebuffer)),
-                    },
-                )),
+                render_pass: Some(render_pass),
                 occlusion_query: None,
                 query_statistics_flags: Default::default(),
                 ..CommandBufferInheritanceInfo::default()
             },
         )
         .map_err(Error::FailedToCreateCommandBuffer)?;
-        secondary
-            .set_viewport(
-                0,
-                [Viewport {
-                    offset: [0.0, 0.0],
-                    extent: [
-                        self.swapchain_framebuffer.extent()[0] as f32,
-                        self.swapchain_framebuffer.extent()[1] as f32,
-                    ],
-                    depth_range: 0.0..=1.0,
-                }]
-                .into_iter()
-                .collect(),
-            )
-            .expect("Using the Swapchain extents should never fail");
+        set_viewport_rect(
+            &mut secondary,
+            viewport.unwrap_or_else(|| ViewportRect::full(target.extent())),
+        )
+        .expect("Using the Swapchain extents should never fail");
         Ok(secondary)
     }
 
@@ -689,39 +2260,158 @@ impl<'a> RenderContext<'a> {
     pub fn image_system(&self) -> &ImageSystem {
         self.image_system
     }
+
+    /// The image the main render pass renders into in this frame, with the additional
+    /// [`VulkanSystem::image_usage`].
+    #[inline]
+    pub fn target_image(&self) -> &Arc<Image> {
+        self.target_image
+    }
+
+    /// Allocations for data that is only needed while preparing this frame, see [`FrameArena`].
+    #[inline]
+    pub fn frame_arena(&self) -> &FrameArena {
+        self.frame_arena
+    }
 }
 
+/// Where a graphics pipeline renders into, so that every pipeline derives the amount of color
+/// attachments, the samples and the depth state from the same source instead of assuming them.
+#[derive(Clone)]
+pub struct GraphicsPipelineRenderPassInfo(RenderPassInfo);
+
 #[derive(Clone)]
-pub struct GraphicsPipelineRenderPassInfo(Arc<RenderPass>);
+enum RenderPassInfo {
+    /// The first subpass of the render pass
+    RenderPass(Arc<RenderPass>),
+    /// Dynamic rendering without a render pass, see [`AutoCommandBufferBuilder::begin_rendering`]
+    Rendering(PipelineRenderingCreateInfo, SampleCount),
+}
 
 impl GraphicsPipelineRenderPassInfo {
+    /// For pipelines that render with dynamic rendering into single-sampled color attachments of
+    /// the given formats, without depth.
+    pub fn dynamic_rendering(color_attachment_formats: impl IntoIterator<Item = Format>) -> Self {
+        Self(RenderPassInfo::Rendering(
+            PipelineRenderingCreateInfo {
+                color_attachment_formats: color_attachment_formats.into_iter().map(Some).collect(),
+                ..PipelineRenderingCreateInfo::default()
+            },
+            SampleCount::Sample1,
+        ))
+    }
+
+    /// For pipelines that render with dynamic rendering into the attachments of the layout,
+    /// including its samples and its depth attachment.
+    pub fn dynamic_rendering_layout(layout: &RenderPassLayout) -> Self {
+        Self(RenderPassInfo::Rendering(
+            layout.pipeline_rendering_info(),
+            layout.samples,
+        ))
+    }
+
+    /// For pipelines that render into the first subpass of the render pass, which should be
+    /// created with [`RenderPassLayout::create_render_pass`] so the samples match.
     #[inline]
-    pub fn render_pass(&self) -> &Arc<RenderPass> {
-        &self.0
+    pub fn from_render_pass(render_pass: Arc<RenderPass>) -> Self {
+        Self(RenderPassInfo::RenderPass(render_pass))
     }
 
+    /// [`None`] for [`GraphicsPipelineRenderPassInfo::dynamic_rendering`].
     #[inline]
-    pub fn into_subpass(self) -> Subpass {
-        Subpass::from(self.0, 0).expect("There must always be at least one subpass")
+    pub fn render_pass(&self) -> Option<&Arc<RenderPass>> {
+        match &self.0 {
+            RenderPassInfo::RenderPass(render_pass) => Some(render_pass),
+            RenderPassInfo::Rendering(..) => None,
+        }
     }
 
     #[inline]
     pub fn into_subpass_type(self) -> PipelineSubpassType {
-        self.into_subpass().into()
+        match self.0 {
+            RenderPassInfo::RenderPass(render_pass) => Subpass::from(render_pass, 0)
+                .expect("There must always be at least one subpass")
+                .into(),
+            RenderPassInfo::Rendering(info, _) => info.into(),
+        }
     }
 
     #[inline]
-    fn subpass(&self) -> Subpass {
-        Subpass::from(Arc::clone(&self.0), 0).expect("There must always be at least one subpass")
+    fn subpass(&self) -> Option<Subpass> {
+        self.render_pass().map(|render_pass| {
+            Subpass::from(Arc::clone(render_pass), 0)
+                .expect("There must always be at least one subpass")
+        })
     }
 
     #[inline]
     pub fn rasterization_samples(&self) -> SampleCount {
-        self.subpass().num_samples().unwrap_or(SampleCount::Sample1)
+        match &self.0 {
+            RenderPassInfo::RenderPass(_) => self
+                .subpass()
+                .and_then(|subpass| subpass.num_samples())
+                .unwrap_or(SampleCount::Sample1),
+            RenderPassInfo::Rendering(_, samples) => *samples,
+        }
     }
 
     #[inline]
     pub fn num_color_attachments(&self) -> u32 {
-        self.subpass().num_color_attachments()
+        match &self.0 {
+            RenderPassInfo::RenderPass(_) => self
+                .subpass()
+                .map_or(0, |subpass| subpass.num_color_attachments()),
+            RenderPassInfo::Rendering(info, _) => info.color_attachment_formats.len() as u32,
+        }
+    }
+
+    /// Multisampling with the [`GraphicsPipelineRenderPassInfo::rasterization_samples`].
+    #[inline]
+    pub fn multisample_state(&self) -> MultisampleState {
+        MultisampleState {
+            rasterization_samples: self.rasterization_samples(),
+            ..MultisampleState::default()
+        }
+    }
+
+    /// The given blending for every color attachment.
+    #[inline]
+    pub fn color_blend_state(&self, attachment: ColorBlendAttachmentState) -> ColorBlendState {
+        ColorBlendState::with_attachment_states(self.num_color_attachments(), attachment)
+    }
+
+    #[inline]
+    pub fn has_depth_attachment(&self) -> bool {
+        match &self.0 {
+            RenderPassInfo::RenderPass(_) => self
+                .subpass()
+                .is_some_and(|subpass| subpass.subpass_desc().depth_stencil_attachment.is_some()),
+            RenderPassInfo::Rendering(info, _) => info.depth_attachment_format.is_some(),
+        }
+    }
+
+    /// The depth state for pipelines that are drawn in the order they are recorded. A render
+    /// pass with a depth attachment requires a depth state, but this one neither tests nor writes
+    /// the depth, so the pipeline behaves as without a depth attachment.
+    #[inline]
+    pub fn depth_stencil_state(&self) -> Option<DepthStencilState> {
+        self.has_depth_attachment().then(DepthStencilState::default)
+    }
+
+    /// The depth state for pipelines that want z-ordering: fragments are only drawn if their
+    /// depth is less or equal to the depth already drawn, so fragments of the same depth are still
+    /// drawn in the order they are recorded. [`None`] without a depth attachment, see
+    /// [`EngineBuilder::with_depth_buffer`].
+    ///
+    /// [`EngineBuilder::with_depth_buffer`]: crate::engine::builder::EngineBuilder::with_depth_buffer
+    #[inline]
+    pub fn depth_tested_stencil_state(&self) -> Option<DepthStencilState> {
+        self.has_depth_attachment().then(|| DepthStencilState {
+            depth: Some(DepthState {
+                write_enable: true,
+                compare_op: CompareOp::LessOrEqual,
+            }),
+            ..DepthStencilState::default()
+        })
     }
 }
@@ -1,6 +1,14 @@
+use crate::engine::system::arena::FrameArena;
 use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
+use crate::engine::system::vulkan::streaming::StreamingBuffer;
 use crate::engine::system::vulkan::system::{GraphicsPipelineRenderPassInfo, VulkanSystem};
-use crate::engine::system::vulkan::textures::{ImageSamplerMode, TextureId, TextureManager};
+use crate::engine::system::vulkan::textures::{
+    ImageSamplerMode, ImageSystem, PlaceholderImages, PlaceholderTextures, TextureId,
+    TextureManager, TextureRegistry,
+};
+use crate::engine::system::vulkan::utils::quad::QuadIndexBuffer;
+use crate::engine::system::vulkan::utils::DescriptorSetBinding;
 use crate::engine::system::vulkan::wds::WriteDescriptorSetManager;
 use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError};
 use crate::shader_from_path;
@@ -10,19 +18,15 @@ use vulkano::command_buffer::AutoCommandBufferBuilder;
 use vulkano::device::{Device, Features};
 use vulkano::image::Image;
 use vulkano::pipeline::cache::PipelineCache;
-use vulkano::pipeline::graphics::color_blend::{
-    AttachmentBlend, ColorBlendAttachmentState, ColorBlendState,
-};
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendAttachmentState};
 use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
-use vulkano::pipeline::graphics::multisample::MultisampleState;
 use vulkano::pipeline::graphics::rasterization::RasterizationState;
 use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition};
 use vulkano::pipeline::graphics::viewport::ViewportState;
 use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
 use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
 use vulkano::pipeline::{
-    DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
-    PipelineShaderStageCreateInfo,
+    DynamicState, GraphicsPipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
 };
 use vulkano::shader::EntryPoint;
 use vulkano::{Validated, VulkanError};
@@ -32,7 +36,13 @@ pub struct TexturedPipeline {
     pipeline: Arc<GraphicsPipeline>,
     write_descriptors: Arc<WriteDescriptorSetManager>,
     texture_manager: TextureManager<Self, 0>,
+    image_system: Arc<ImageSystem>,
+    placeholders: PlaceholderTextures<Self>,
+    texture_registry: Arc<TextureRegistry<Self>>,
     buffers_manager: Arc<BasicBuffersManager>,
+    vertices: StreamingBuffer<Vertex2dUv>,
+    indices: StreamingBuffer<u32>,
+    quad_indices: QuadIndexBuffer,
 }
 
 impl TryFrom<&VulkanSystem> for TexturedPipeline {
@@ -45,6 +55,8 @@ impl TryFrom<&VulkanSystem> for TexturedPipeline {
             vs.pipeline_cache().map(Arc::clone),
             Arc::clone(vs.write_descriptor_set_manager()),
             Arc::clone(vs.basic_buffers_manager()),
+            Arc::clone(vs.image_system()),
+            vs.placeholder_images(),
         )
     }
 }
@@ -61,12 +73,33 @@ impl TexturedPipeline {
         cache: Option<Arc<PipelineCache>>,
         write_descriptors: Arc<WriteDescriptorSetManager>,
         buffers_manager: Arc<BasicBuffersManager>,
+        image_system: Arc<ImageSystem>,
+        placeholder_images: &PlaceholderImages,
     ) -> Result<Self, PipelineCreateError> {
         let pipeline = Self::create_pipeline(Arc::clone(&device), render_pass_info, cache)?;
+        let texture_manager = TextureManager::basic(
+            device,
+            &pipeline,
+            ImageSamplerMode::Linear,
+            Arc::clone(write_descriptors.descriptor_set_allocator()),
+            Arc::clone(image_system.deferred_destruction()),
+        )?;
+        let placeholders = PlaceholderTextures::prepare(placeholder_images, |image| {
+            texture_manager.prepare_texture(
+                image,
+                write_descriptors.get_required_descriptors(&pipeline.layout().set_layouts()[0]),
+            )
+        })?;
         Ok(Self {
+            quad_indices: QuadIndexBuffer::new(Arc::clone(&buffers_manager), 1024)?,
+            vertices: StreamingBuffer::vertices(Arc::clone(&buffers_manager), 4 * 1024),
+            indices: StreamingBuffer::indices(Arc::clone(&buffers_manager), 4 * 1024),
             buffers_manager,
             write_descriptors,
-            texture_manager: TextureManager::basic(device, &pipeline, ImageSamplerMode::Linear)?,
+            texture_manager,
+            image_system,
+            placeholders,
+            texture_registry: Arc::default(),
             pipeline,
         })
     }
@@ -104,18 +137,17 @@ impl TexturedPipeline {
                 }),
                 viewport_state: Some(ViewportState::default()),
                 rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState {
-                    rasterization_samples: render_pass_info.rasterization_samples(),
-                    ..MultisampleState::default()
-                }),
-                color_blend_state: Some(ColorBlendState::with_attachment_states(
-                    render_pass_info.num_color_attachments(),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(render_pass_info.color_blend_state(
                     ColorBlendAttachmentState {
                         blend: Some(AttachmentBlend::alpha()),
                         ..ColorBlendAttachmentState::default()
                     },
                 )),
-                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                    .into_iter()
+                    .collect(),
+                depth_stencil_state: render_pass_info.depth_stencil_state(),
                 subpass: Some(render_pass_info.into_subpass_type()),
                 ..GraphicsPipelineCreateInfo::layout(layout)
             },
@@ -143,28 +175,56 @@ impl TexturedPipeline {
         builder: &mut AutoCommandBufferBuilder<P>,
         textured: &[Textured],
     ) -> Result<(), DrawError> {
-        let mut offset = 0;
-        let vertex_buffer = self.buffers_manager.create_vertex_buffer(
+        self.record(builder, textured)
+    }
+
+    /// Same as [`TexturedPipeline::draw`], which writes the vertices directly into a
+    /// [`StreamingBuffer`], the [`FrameArena`] is not needed anymore.
+    pub fn draw_in<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        _arena: &FrameArena,
+        textured: &[Textured],
+    ) -> Result<(), DrawError> {
+        self.record(builder, textured)
+    }
+
+    fn record<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        textured: &[Textured],
+    ) -> Result<(), DrawError> {
+        if !self
+            .buffers_manager
+            .draw_filter()
+            .admit(PipelineKind::Textured, textured.len())
+        {
+            return Ok(());
+        }
+        let vertex_count = textured.iter().map(|l| l.vertices.len()).sum::<usize>();
+        if vertex_count == 0 {
+            return Ok(());
+        }
+        // written directly into the mapped memory, without collecting them into a Vec first
+        let vertex_buffer = self.vertices.write_with(vertex_count, |vertices| {
             textured
                 .iter()
-                .flat_map(|l| l.vertices.iter().copied())
-                .collect::<Vec<_>>(),
-        )?;
+                .flat_map(|l| l.vertices.iter())
+                .zip(vertices)
+                .for_each(|(vertex, target)| *target = *vertex);
+        })?;
+        let mut offset = 0;
 
         builder
             .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
             .bind_vertex_buffers(0, vertex_buffer)?;
 
+        let mut binding = DescriptorSetBinding::new(self.pipeline.layout());
+
         for textured in textured {
-            if self.texture_manager.is_origin_of(&textured.texture) {
-                builder
-                    .bind_descriptor_sets(
-                        PipelineBindPoint::Graphics,
-                        Arc::clone(&self.pipeline.layout()),
-                        0,
-                        Arc::clone(&textured.texture.0.descriptor),
-                    )?
-                    .draw(textured.vertices.len() as u32, 1, offset, 0)?;
+            if let Some(texture) = self.resolve_texture(&textured.texture) {
+                binding.bind(builder, texture.descriptor())?;
+                builder.draw(textured.vertices.len() as u32, 1, offset, 0)?;
             }
 
             offset += textured.vertices.len() as u32;
@@ -178,40 +238,56 @@ impl TexturedPipeline {
         builder: &mut AutoCommandBufferBuilder<P>,
         textured: &[TexturedIndexed],
     ) -> Result<(), DrawError> {
+        if !self
+            .buffers_manager
+            .draw_filter()
+            .admit(PipelineKind::Textured, textured.len())
+        {
+            return Ok(());
+        }
         let mut offset_vertices = 0;
         let mut offset_indices = 0;
 
-        let vertex_buffer = self.buffers_manager.create_vertex_buffer(
+        let vertex_count = textured.iter().map(|l| l.vertices.len()).sum::<usize>();
+        let index_count = textured.iter().map(|l| l.indices.len() * 3).sum::<usize>();
+        if vertex_count == 0 || index_count == 0 {
+            return Ok(());
+        }
+
+        let vertex_buffer = self.vertices.write_with(vertex_count, |vertices| {
             textured
                 .iter()
-                .flat_map(|l| l.vertices.iter().copied())
-                .collect::<Vec<_>>(),
-        )?;
+                .flat_map(|l| l.vertices.iter())
+                .zip(vertices)
+                .for_each(|(vertex, target)| *target = *vertex);
+        })?;
 
-        let index_buffer = self.buffers_manager.create_index_buffer(
+        let index_buffer = self.indices.write_with(index_count, |indices| {
             textured
                 .iter()
-                .flat_map(|l| l.indices.iter().flat_map(|i| i.into_iter()).copied())
-                .collect::<Vec<_>>(),
-        )?;
+                .flat_map(|l| l.indices.iter().flatten())
+                .zip(indices)
+                .for_each(|(index, target)| *target = *index);
+        })?;
 
         builder
             .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
             .bind_index_buffer(index_buffer)?
             .bind_vertex_buffers(0, vertex_buffer)?;
 
+        let mut binding = DescriptorSetBinding::new(self.pipeline.layout());
+
         for textured in textured {
+            // the indices are relative to the vertices of each item, see vertex_offset below
+            debug_assert!(
+                textured.is_valid(),
+                "An index of a TexturedIndexed is out of bounds of its vertices"
+            );
             let index_count = textured.indices.len() as u32 * 3;
 
-            if self.texture_manager.is_origin_of(&textured.texture) {
-                builder
-                    .bind_descriptor_sets(
-                        PipelineBindPoint::Graphics,
-                        Arc::clone(&self.pipeline.layout()),
-                        0,
-                        Arc::clone(&textured.texture.0.descriptor),
-                    )?
-                    .draw_indexed(index_count, 1, offset_indices, offset_vertices, 0)?;
+            if let Some(texture) = self.resolve_texture(&textured.texture) {
+                binding.bind(builder, texture.descriptor())?;
+                builder.draw_indexed(index_count, 1, offset_indices, offset_vertices, 0)?;
             }
 
             offset_vertices += textured.vertices.len() as i32;
@@ -221,6 +297,76 @@ impl TexturedPipeline {
         Ok(())
     }
 
+    /// Draws quads with the shared [`QuadIndexBuffer`], so no indices are uploaded per call.
+    /// Consecutive quads with the same texture are drawn with a single draw call.
+    pub fn draw_quads<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        quads: &[TexturedQuad],
+    ) -> Result<(), DrawError> {
+        if !self
+            .buffers_manager
+            .draw_filter()
+            .admit(PipelineKind::Textured, quads.len())
+        {
+            return Ok(());
+        }
+        if quads.is_empty() {
+            return Ok(());
+        }
+
+        let index_buffer = self.quad_indices.for_quads(quads.len())?;
+        let vertex_buffer = self
+            .vertices
+            .write((0..quads.len() * 4).map(|index| quads[index / 4].vertices[index % 4]))?;
+
+        builder
+            .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
+            .bind_index_buffer(index_buffer)?
+            .bind_vertex_buffers(0, vertex_buffer)?;
+
+        let mut binding = DescriptorSetBinding::new(self.pipeline.layout());
+
+        let mut first = 0;
+        for batch in quads.chunk_by(|a, b| a.texture.ptr_eq(&b.texture)) {
+            if let Some(texture) = self.resolve_texture(&batch[0].texture) {
+                binding.bind(builder, texture.descriptor())?;
+                builder.draw_indexed(batch.len() as u32 * 6, 1, first as u32 * 6, 0, 0)?;
+            }
+            first += batch.len();
+        }
+
+        Ok(())
+    }
+
+    /// Textures whose upload was deferred by the [`UploadBudget`] are replaced by the
+    /// [`PlaceholderTextures::missing`] texture until the upload has been processed, as their
+    /// content is undefined until then.
+    ///
+    /// [`UploadBudget`]: crate::engine::system::vulkan::textures::UploadBudget
+    #[inline]
+    fn resolve_texture<'a>(&'a self, texture: &'a TextureId<Self>) -> Option<&'a TextureId<Self>> {
+        if self.texture_manager.is_origin_of(texture) {
+            self.placeholders.resolve(&self.image_system, texture)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn placeholders(&self) -> &PlaceholderTextures<Self> {
+        &self.placeholders
+    }
+
+    /// The atlas regions that the [`BufferedCanvasLayer`] uses to batch textures of the same
+    /// atlas.
+    ///
+    /// [`BufferedCanvasLayer`]: crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer
+    #[inline]
+    pub fn texture_registry(&self) -> &Arc<TextureRegistry<Self>> {
+        &self.texture_registry
+    }
+
     pub fn prepare_texture(
         &self,
         image: Arc<Image>,
@@ -252,3 +398,68 @@ pub struct TexturedIndexed {
     pub indices: Vec<[u32; 3]>,
     pub texture: TextureId<TexturedPipeline>,
 }
+
+impl TexturedIndexed {
+    /// Whether all indices refer to vertices of this item, as they are relative to its first
+    /// vertex.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.indices
+            .iter()
+            .flatten()
+            .all(|index| (*index as usize) < self.vertices.len())
+    }
+}
+
+/// A textured rectangle for [`TexturedPipeline::draw_quads`].
+pub struct TexturedQuad {
+    /// The corners in clockwise or counterclockwise order
+    pub vertices: [Vertex2dUv; 4],
+    pub texture: TextureId<TexturedPipeline>,
+}
+
+impl TexturedQuad {
+    /// An axis-aligned rectangle showing the whole texture.
+    pub fn new(pos: [f32; 2], dim: [f32; 2], texture: TextureId<TexturedPipeline>) -> Self {
+        let [x, y] = pos;
+        let [w, h] = dim;
+        Self {
+            vertices: [
+                Vertex2dUv {
+                    pos: [x, y],
+                    uv: [0.0, 0.0],
+                },
+                Vertex2dUv {
+                    pos: [x + w, y],
+                    uv: [1.0, 0.0],
+                },
+                Vertex2dUv {
+                    pos: [x + w, y + h],
+                    uv: [1.0, 1.0],
+                },
+                Vertex2dUv {
+                    pos: [x, y + h],
+                    uv: [0.0, 1.0],
+                },
+            ],
+            texture,
+        }
+    }
+
+    /// The quad moved by the offset.
+    pub fn translated(mut self, offset: [f32; 2]) -> Self {
+        for vertex in &mut self.vertices {
+            vertex.pos = [vertex.pos[0] + offset[0], vertex.pos[1] + offset[1]];
+        }
+        self
+    }
+
+    /// Shows only the given region of the texture, in normalized coordinates.
+    pub fn with_uv(mut self, uv_min: [f32; 2], uv_max: [f32; 2]) -> Self {
+        self.vertices[0].uv = uv_min;
+        self.vertices[1].uv = [uv_max[0], uv_min[1]];
+        self.vertices[2].uv = uv_max;
+        self.vertices[3].uv = [uv_min[0], uv_max[1]];
+        self
+    }
+}
@@ -0,0 +1,300 @@
+use crate::engine::system::vulkan::textured::TexturedPipeline;
+use crate::engine::system::vulkan::textures::{ImageSystem, TextureId, TextureView};
+use crate::engine::system::vulkan::UploadError;
+use crate::support::image::RawRgbaImage;
+use rustc_hash::FxHashMap;
+use std::cmp::Reverse;
+use std::hash::Hash;
+use vulkano::{Validated, VulkanError};
+
+#[derive(thiserror::Error, Debug)]
+pub enum AtlasError {
+    #[error("There are no images to pack")]
+    Empty,
+    #[error("The data of image {0} does not match its size")]
+    InvalidImage(usize),
+    #[error("The images do not fit into an atlas of {0}x{0} pixels")]
+    TooLarge(u32),
+    #[error("Failed to upload the atlas: {0}")]
+    UploadError(#[from] UploadError),
+    #[error("Failed to create the texture of the atlas: {0}")]
+    VulkanError(#[from] Validated<VulkanError>),
+}
+
+struct AtlasEntry<K> {
+    key: K,
+    image: RawRgbaImage,
+    /// Registered as region of the atlas in the [`TexturedPipeline::texture_registry`]
+    texture: Option<TextureId<TexturedPipeline>>,
+}
+
+/// Packs decoded images at runtime into a single texture, so that sprites of different images
+/// can be drawn in a single batch and with a single descriptor set.
+///
+/// ```no_run
+/// use hotrod::engine::system::vulkan::textures::{TextureAtlas, TextureAtlasBuilder};
+/// use hotrod::engine::RenderContext;
+/// use hotrod::support::image::RawRgbaImage;
+///
+/// fn load_sprites(context: &RenderContext) -> TextureAtlas<&'static str> {
+///     TextureAtlasBuilder::default()
+///         .with_image("player", RawRgbaImage::new(vec![255; 32 * 32 * 4], 32, 32))
+///         .with_image("coin", RawRgbaImage::new(vec![255; 16 * 16 * 4], 16, 16))
+///         .build(context.inner.image_system(), &context.pipelines.texture)
+///         .unwrap()
+/// }
+/// ```
+pub struct TextureAtlasBuilder<K> {
+    entries: Vec<AtlasEntry<K>>,
+    padding: u32,
+    max_extent: u32,
+}
+
+impl<K> Default for TextureAtlasBuilder<K> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            entries: Vec::default(),
+            padding: 1,
+            max_extent: 4096,
+        }
+    }
+}
+
+impl<K: Eq + Hash> TextureAtlasBuilder<K> {
+    #[inline]
+    pub fn with_image(mut self, key: K, image: RawRgbaImage) -> Self {
+        self.add(key, image);
+        self
+    }
+
+    #[inline]
+    pub fn add(&mut self, key: K, image: RawRgbaImage) {
+        self.entries.push(AtlasEntry {
+            key,
+            image,
+            texture: None,
+        });
+    }
+
+    /// Like [`TextureAtlasBuilder::add`], but the `texture` showing the same image is registered
+    /// as region of the atlas in the [`TexturedPipeline::texture_registry`], so the
+    /// [`BufferedCanvasLayer`] batches it with the other images of the atlas.
+    ///
+    /// [`BufferedCanvasLayer`]: crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer
+    #[inline]
+    pub fn add_texture(
+        &mut self,
+        key: K,
+        image: RawRgbaImage,
+        texture: TextureId<TexturedPipeline>,
+    ) {
+        self.entries.push(AtlasEntry {
+            key,
+            image,
+            texture: Some(texture),
+        });
+    }
+
+    /// The pixels between two images, which are filled with the edges of the images so that
+    /// linear filtering does not blend in the neighbours, `1` by default.
+    #[inline]
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// The maximal width and height of the atlas, `4096` by default, which all devices support.
+    #[inline]
+    pub fn with_max_extent(mut self, max_extent: u32) -> Self {
+        self.max_extent = max_extent;
+        self
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Packs the images into the smallest power of two atlas they fit into and enqueues its
+    /// upload. The images are placed row by row, the tallest first.
+    pub fn build(
+        self,
+        image_system: &ImageSystem,
+        pipeline: &TexturedPipeline,
+    ) -> Result<TextureAtlas<K>, AtlasError> {
+        if self.entries.is_empty() {
+            return Err(AtlasError::Empty);
+        }
+        if let Some(index) = self.entries.iter().position(|entry| {
+            entry.image.data().len()
+                != entry.image.width() as usize * entry.image.height() as usize * 4
+        }) {
+            return Err(AtlasError::InvalidImage(index));
+        }
+
+        let cells = self
+            .entries
+            .iter()
+            .map(|entry| {
+                [
+                    entry.image.width() + 2 * self.padding,
+                    entry.image.height() + 2 * self.padding,
+                ]
+            })
+            .collect::<Vec<_>>();
+        let area = cells
+            .iter()
+            .map(|[width, height]| u64::from(*width) * u64::from(*height))
+            .sum::<u64>();
+        let mut extent = [
+            cells.iter().map(|[width, _]| *width).max().unwrap_or(1),
+            cells.iter().map(|[_, height]| *height).max().unwrap_or(1),
+        ]
+        .map(|min| {
+            min.max((area as f64).sqrt().ceil() as u32)
+                .next_power_of_two()
+        });
+
+        let positions = loop {
+            if extent[0] > self.max_extent || extent[1] > self.max_extent {
+                return Err(AtlasError::TooLarge(self.max_extent));
+            }
+            match pack(&cells, extent) {
+                Some(positions) => break positions,
+                None if extent[0] <= extent[1] => extent[0] *= 2,
+                None => extent[1] *= 2,
+            }
+        };
+
+        let [width, height] = extent;
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        for (entry, [x, y]) in self.entries.iter().zip(&positions) {
+            blit_extruded(
+                &mut pixels,
+                width,
+                &entry.image,
+                [x + self.padding, y + self.padding],
+                self.padding,
+            );
+        }
+
+        let image = image_system.create_image_and_enqueue_upload(pixels, width, height)?;
+        let texture = pipeline.prepare_texture(image)?;
+
+        let views = self
+            .entries
+            .into_iter()
+            .zip(positions)
+            .map(|(entry, [x, y])| {
+                let view = TextureView::from_pixels(
+                    texture.clone(),
+                    [x + self.padding, y + self.padding],
+                    [entry.image.width(), entry.image.height()],
+                );
+                if let Some(original) = &entry.texture {
+                    pipeline.texture_registry().register(
+                        original,
+                        texture.clone(),
+                        view.uv_min,
+                        view.uv_max,
+                    );
+                }
+                (entry.key, view)
+            })
+            .collect();
+
+        Ok(TextureAtlas { texture, views })
+    }
+}
+
+/// The texture of a [`TextureAtlasBuilder`] and the regions of its images.
+pub struct TextureAtlas<K> {
+    texture: TextureId<TexturedPipeline>,
+    views: FxHashMap<K, TextureView<TexturedPipeline>>,
+}
+
+impl<K: Eq + Hash> TextureAtlas<K> {
+    #[inline]
+    pub fn texture(&self) -> &TextureId<TexturedPipeline> {
+        &self.texture
+    }
+
+    /// The region of the image that was added with the given key.
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<&TextureView<TexturedPipeline>> {
+        self.views.get(key)
+    }
+
+    #[inline]
+    pub fn views(&self) -> impl Iterator<Item = (&K, &TextureView<TexturedPipeline>)> {
+        self.views.iter()
+    }
+
+    #[inline]
+    pub fn into_views(self) -> FxHashMap<K, TextureView<TexturedPipeline>> {
+        self.views
+    }
+}
+
+/// Places the rectangles on shelves from top to bottom, the tallest first, and returns their
+/// positions in the order of `sizes`, [`None`] if they do not fit.
+fn pack(sizes: &[[u32; 2]], [width, height]: [u32; 2]) -> Option<Vec<[u32; 2]>> {
+    let mut order = (0..sizes.len()).collect::<Vec<_>>();
+    order.sort_by_key(|index| Reverse(sizes[*index][1]));
+
+    let mut positions = vec![[0, 0]; sizes.len()];
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for index in order {
+        let [w, h] = sizes[index];
+        if x + w > width {
+            x = 0;
+            y += shelf_height;
+            shelf_height = 0;
+        }
+        if x + w > width || y + h > height {
+            return None;
+        }
+        positions[index] = [x, y];
+        x += w;
+        shelf_height = shelf_height.max(h);
+    }
+    Some(positions)
+}
+
+/// Copies the image to the position and repeats its outermost pixels `padding` times around it.
+fn blit_extruded(
+    pixels: &mut [u8],
+    atlas_width: u32,
+    image: &RawRgbaImage,
+    [x, y]: [u32; 2],
+    padding: u32,
+) {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    if width == 0 || height == 0 {
+        return;
+    }
+    let padding = padding as usize;
+    let data = image.data();
+    for row in 0..height + 2 * padding {
+        let source_row = row.saturating_sub(padding).min(height - 1);
+        let source = &data[source_row * width * 4..(source_row + 1) * width * 4];
+        let start =
+            ((y as usize + row - padding) * atlas_width as usize + x as usize - padding) * 4;
+        let target = &mut pixels[start..start + (width + 2 * padding) * 4];
+
+        let (left, rest) = target.split_at_mut(padding * 4);
+        let (center, right) = rest.split_at_mut(width * 4);
+        center.copy_from_slice(source);
+        left.chunks_exact_mut(4)
+            .for_each(|pixel| pixel.copy_from_slice(&source[..4]));
+        right
+            .chunks_exact_mut(4)
+            .for_each(|pixel| pixel.copy_from_slice(&source[source.len() - 4..]));
+    }
+}
@@ -1,49 +1,364 @@
+use crate::engine::system::vulkan::destruction::DeferredDestructionQueue;
 use crate::engine::system::vulkan::{PipelineCreateError, UploadError};
-use crossbeam::queue::SegQueue;
-use std::sync::Arc;
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use vulkano::buffer::{AllocateBufferError, Buffer, BufferCreateInfo, BufferUsage};
 use vulkano::command_buffer::CopyBufferToImageInfo;
 use vulkano::format::Format;
 use vulkano::image::{AllocateImageError, Image, ImageCreateInfo, ImageType, ImageUsage};
 use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryTypeFilter};
-use vulkano::Validated;
+use vulkano::{DeviceSize, Handle, Validated, VulkanObject};
+
+/// The order in which enqueued uploads are processed. All uploads of a higher priority are
+/// processed before any upload of a lower priority. Uploads of the same priority are processed in
+/// the order they were enqueued. The uploads of a single image are always processed in the order
+/// they were enqueued: enqueuing an upload with a higher priority raises the priority of the
+/// older uploads of the same image as well.
+#[derive(Debug, Default, Copy, Clone, PartialOrd, PartialEq, Ord, Eq, Hash)]
+pub enum UploadPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl UploadPriority {
+    #[inline]
+    const fn index(self) -> usize {
+        match self {
+            UploadPriority::High => 0,
+            UploadPriority::Normal => 1,
+            UploadPriority::Low => 2,
+        }
+    }
+}
+
+/// Limits the amount of enqueued uploads that are processed within a single frame, the remaining
+/// uploads are deferred to the following frames. To always make progress, the first upload of a
+/// frame is processed even if it alone exceeds the budget.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct UploadBudget {
+    pub max_bytes_per_frame: Option<DeviceSize>,
+    pub max_duration_per_frame: Option<Duration>,
+}
+
+impl UploadBudget {
+    pub const UNLIMITED: Self = Self {
+        max_bytes_per_frame: None,
+        max_duration_per_frame: None,
+    };
+
+    #[inline]
+    pub const fn with_max_bytes_per_frame(mut self, bytes: DeviceSize) -> Self {
+        self.max_bytes_per_frame = Some(bytes);
+        self
+    }
+
+    #[inline]
+    pub const fn with_max_duration_per_frame(mut self, duration: Duration) -> Self {
+        self.max_duration_per_frame = Some(duration);
+        self
+    }
+
+    #[inline]
+    fn allows(&self, bytes: DeviceSize, start: Instant) -> bool {
+        self.max_bytes_per_frame.map_or(true, |max| bytes <= max)
+            && self
+                .max_duration_per_frame
+                .map_or(true, |max| start.elapsed() < max)
+    }
+}
+
+impl Default for UploadBudget {
+    #[inline]
+    fn default() -> Self {
+        Self::UNLIMITED
+    }
+}
+
+/// The progress of the uploads enqueued since the upload queue was empty the last time.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct UploadProgress {
+    pub uploads_done: usize,
+    pub uploads_total: usize,
+    pub bytes_done: DeviceSize,
+    pub bytes_total: DeviceSize,
+}
+
+impl UploadProgress {
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.uploads_done >= self.uploads_total
+    }
+
+    /// The share of the processed bytes, `1.0` if there is nothing to upload.
+    #[inline]
+    pub fn fraction(&self) -> f32 {
+        if self.bytes_total == 0 {
+            1.0
+        } else {
+            self.bytes_done as f32 / self.bytes_total as f32
+        }
+    }
+}
+
+/// The enqueued uploads of a single image, which are processed in the order they were enqueued
+/// so that an older upload never overwrites a newer one.
+struct ImageUploads {
+    /// The highest priority of the uploads, all of them are processed with it
+    priority: UploadPriority,
+    /// Identifies the entry in the queue of the priority, all other entries of the image are stale
+    ticket: u64,
+    uploads: VecDeque<CopyBufferToImageInfo>,
+}
+
+impl ImageUploads {
+    /// Whether an upload that replaces the whole content is enqueued, so the content is undefined
+    /// or outdated until then.
+    fn has_full_upload(&self) -> bool {
+        self.uploads.iter().any(|upload| {
+            let region = &upload.regions[0];
+            let extent = upload.dst_image.extent();
+            region.image_offset[..2] == [0, 0] && region.image_extent[..2] == extent[..2]
+        })
+    }
+
+    /// Whether an enqueued upload overlaps the region.
+    fn overlaps(&self, [x, y]: [u32; 2], [width, height]: [u32; 2]) -> bool {
+        self.uploads.iter().any(|upload| {
+            let region = &upload.regions[0];
+            let [rx, ry, _] = region.image_offset;
+            let [rw, rh, _] = region.image_extent;
+            rx < x + width && x < rx + rw && ry < y + height && y < ry + rh
+        })
+    }
+}
+
+#[derive(Default)]
+struct UploadQueues {
+    /// The (raw) handles of the images with enqueued uploads and the tickets of the entries, per
+    /// priority. An entry is stale if its ticket is not the one of the [`ImageUploads`], because
+    /// they were raised to a higher priority or were processed already. The ticket also tells
+    /// the entries apart if the image is enqueued again later or its handle is reused.
+    queues: [VecDeque<(u64, u64)>; 3],
+    images: FxHashMap<u64, ImageUploads>,
+    next_ticket: u64,
+    progress: UploadProgress,
+}
+
+impl UploadQueues {
+    fn push(&mut self, priority: UploadPriority, upload: CopyBufferToImageInfo) {
+        if self.images.is_empty() {
+            self.progress = UploadProgress::default();
+        }
+        self.progress.uploads_total += 1;
+        self.progress.bytes_total += upload.src_buffer.size();
+
+        let handle = upload.dst_image.handle().as_raw();
+        let ticket = self.next_ticket;
+        match self.images.get_mut(&handle) {
+            Some(image) => {
+                image.uploads.push_back(upload);
+                if priority < image.priority {
+                    // the older uploads are raised along, so they still come first
+                    image.priority = priority;
+                    image.ticket = ticket;
+                    self.next_ticket += 1;
+                    self.queues[priority.index()].push_back((handle, ticket));
+                }
+            }
+            None => {
+                self.images.insert(
+                    handle,
+                    ImageUploads {
+                        priority,
+                        ticket,
+                        uploads: VecDeque::from([upload]),
+                    },
+                );
+                self.next_ticket += 1;
+                self.queues[priority.index()].push_back((handle, ticket));
+            }
+        }
+    }
+
+    fn pop_within(
+        &mut self,
+        budget: &UploadBudget,
+        bytes: &mut DeviceSize,
+        start: Instant,
+    ) -> Option<CopyBufferToImageInfo> {
+        let (priority, handle) = self.next_image()?;
+        let image = self.images.get_mut(&handle)?;
+        let size = image.uploads.front()?.src_buffer.size();
+
+        if *bytes > 0 && !budget.allows(*bytes + size, start) {
+            return None;
+        }
+
+        let upload = image.uploads.pop_front()?;
+        if image.uploads.is_empty() {
+            self.images.remove(&handle);
+            self.queues[priority.index()].pop_front();
+        }
+
+        *bytes += size;
+        self.progress.uploads_done += 1;
+        self.progress.bytes_done += size;
+        Some(upload)
+    }
+
+    /// The priority and the handle of the image whose uploads are processed next, stale entries
+    /// are dropped on the way.
+    fn next_image(&mut self) -> Option<(UploadPriority, u64)> {
+        for priority in [
+            UploadPriority::High,
+            UploadPriority::Normal,
+            UploadPriority::Low,
+        ] {
+            let queue = &mut self.queues[priority.index()];
+            while let Some((handle, ticket)) = queue.front().copied() {
+                if self
+                    .images
+                    .get(&handle)
+                    .is_some_and(|image| image.ticket == ticket)
+                {
+                    return Some((priority, handle));
+                }
+                queue.pop_front();
+            }
+        }
+        None
+    }
+
+    #[inline]
+    fn pending_uploads(&self) -> usize {
+        self.images.values().map(|image| image.uploads.len()).sum()
+    }
+}
 
 pub struct ImageSystem {
     memo_allocator: Arc<dyn MemoryAllocator>,
-    upload_queue: SegQueue<CopyBufferToImageInfo>,
+    upload_queues: Mutex<UploadQueues>,
+    /// The amount of enqueued uploads, so that checking for pending uploads while drawing does
+    /// not need to lock the [`UploadQueues`] unless there are any
+    pending_uploads: AtomicUsize,
+    deferred_destruction: Arc<DeferredDestructionQueue>,
 }
 
 impl ImageSystem {
-    pub fn new(memo_allocator: impl MemoryAllocator) -> Result<Self, PipelineCreateError> {
+    pub const RENDER_TARGET_FORMAT: Format = Format::R8G8B8A8_SRGB;
+
+    pub fn new(memo_allocator: Arc<dyn MemoryAllocator>) -> Result<Self, PipelineCreateError> {
         Ok(Self {
-            memo_allocator: Arc::new(memo_allocator),
-            upload_queue: Default::default(),
+            memo_allocator,
+            upload_queues: Mutex::default(),
+            pending_uploads: AtomicUsize::new(0),
+            deferred_destruction: Arc::default(),
         })
     }
 
+    /// The [`DeferredDestructionQueue`] through which released images (and their descriptors)
+    /// are destroyed.
+    #[inline]
+    pub fn deferred_destruction(&self) -> &Arc<DeferredDestructionQueue> {
+        &self.deferred_destruction
+    }
+
     /// Whether there are [`CopyBufferToImageInfo`]-requests enqueued.
+    #[inline]
     pub(crate) fn has_upload_info_enqueued(&self) -> bool {
-        !self.upload_queue.is_empty()
+        self.pending_uploads.load(Ordering::Acquire) > 0
     }
 
-    /// Retrieves enqueued [`CopyBufferToImageInfo`]-requests.
-    pub(crate) fn next_upload_info(&self) -> Option<CopyBufferToImageInfo> {
-        self.upload_queue.pop()
+    /// Retrieves the enqueued [`CopyBufferToImageInfo`]-requests which fit into the given
+    /// [`UploadBudget`] for this frame, ordered by their [`UploadPriority`].
+    pub(crate) fn next_upload_infos(&self, budget: &UploadBudget) -> Vec<CopyBufferToImageInfo> {
+        let start = Instant::now();
+        let mut bytes = 0;
+        let mut queues = self.upload_queues.lock().unwrap();
+        let uploads =
+            std::iter::from_fn(|| queues.pop_within(budget, &mut bytes, start)).collect::<Vec<_>>();
+        self.pending_uploads
+            .store(queues.pending_uploads(), Ordering::Release);
+        uploads
+    }
+
+    /// Whether an upload of the whole content of the given [`Image`] is enqueued and was not
+    /// processed yet. Until then, the content of the [`Image`] is undefined (or outdated) and
+    /// should not be sampled. Updates of a region only are not considered, see
+    /// [`ImageSystem::is_region_pending`].
+    pub fn is_upload_pending(&self, image: &Image) -> bool {
+        self.has_upload_info_enqueued()
+            && self
+                .upload_queues
+                .lock()
+                .unwrap()
+                .images
+                .get(&image.handle().as_raw())
+                .is_some_and(ImageUploads::has_full_upload)
+    }
+
+    /// Whether an enqueued upload of the given [`Image`] that was not processed yet overlaps the
+    /// region, so that the region still shows its previous content.
+    pub fn is_region_pending(&self, image: &Image, offset: [u32; 2], extent: [u32; 2]) -> bool {
+        self.has_upload_info_enqueued()
+            && self
+                .upload_queues
+                .lock()
+                .unwrap()
+                .images
+                .get(&image.handle().as_raw())
+                .is_some_and(|uploads| uploads.overlaps(offset, extent))
+    }
+
+    /// The progress of the enqueued uploads, for example to show a loading screen while textures
+    /// are streamed in with an [`UploadBudget`].
+    pub fn upload_progress(&self) -> UploadProgress {
+        self.upload_queues.lock().unwrap().progress
     }
 
     /// Creates a new [`Image`] and enqueues an upload-request the given `rgba`-data as content.
+    #[inline]
     pub fn create_image_and_enqueue_upload<I>(
         &self,
         rgba: I,
         width: u32,
         height: u32,
     ) -> Result<Arc<Image>, UploadError>
+    where
+        I: IntoIterator<Item = u8>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.create_image_and_enqueue_upload_with_priority(
+            rgba,
+            width,
+            height,
+            UploadPriority::default(),
+        )
+    }
+
+    /// Like [`ImageSystem::create_image_and_enqueue_upload`] but with the given [`UploadPriority`].
+    pub fn create_image_and_enqueue_upload_with_priority<I>(
+        &self,
+        rgba: I,
+        width: u32,
+        height: u32,
+        priority: UploadPriority,
+    ) -> Result<Arc<Image>, UploadError>
     where
         I: IntoIterator<Item = u8>,
         I::IntoIter: ExactSizeIterator,
     {
         let image = self.create_image(width, height)?;
-        self.enqueue_image_upload(Arc::clone(&image), rgba)?;
+        self.enqueue_image_upload_with_priority(Arc::clone(&image), rgba, priority)?;
         Ok(image)
     }
 
@@ -69,6 +384,30 @@ impl ImageSystem {
         )
     }
 
+    /// Creates an image that can be rendered into and then be sampled like any other texture.
+    /// Its format is [`ImageSystem::RENDER_TARGET_FORMAT`].
+    #[inline]
+    pub fn create_render_target_image(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> Result<Arc<Image>, Validated<AllocateImageError>> {
+        Image::new(
+            Arc::clone(&self.memo_allocator),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Self::RENDER_TARGET_FORMAT,
+                extent: [width, height, 1],
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED,
+                ..ImageCreateInfo::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..AllocationCreateInfo::default()
+            },
+        )
+    }
+
     #[inline]
     pub fn enqueue_image_upload<I>(
         &self,
@@ -79,11 +418,31 @@ impl ImageSystem {
         I: IntoIterator<Item = u8>,
         I::IntoIter: ExactSizeIterator,
     {
-        self.upload_queue
-            .push(self.create_copy_buffer_to_image_image(image, rgba)?);
+        self.enqueue_image_upload_with_priority(image, rgba, UploadPriority::default())
+    }
+
+    #[inline]
+    pub fn enqueue_image_upload_with_priority<I>(
+        &self,
+        image: Arc<Image>,
+        rgba: I,
+        priority: UploadPriority,
+    ) -> Result<(), Validated<AllocateBufferError>>
+    where
+        I: IntoIterator<Item = u8>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let copy_info = self.create_copy_buffer_to_image_image(image, rgba)?;
+        self.push_upload(priority, copy_info);
         Ok(())
     }
 
+    fn push_upload(&self, priority: UploadPriority, upload: CopyBufferToImageInfo) {
+        let mut queues = self.upload_queues.lock().unwrap();
+        queues.push(priority, upload);
+        self.pending_uploads.fetch_add(1, Ordering::Release);
+    }
+
     fn create_copy_buffer_to_image_image<I>(
         &self,
         image: Arc<Image>,
@@ -111,6 +470,7 @@ impl ImageSystem {
         ))
     }
 
+    #[inline]
     pub fn enqueue_image_update<I>(
         &self,
         image: Arc<Image>,
@@ -121,18 +481,30 @@ impl ImageSystem {
         I: IntoIterator<Item = u8>,
         I::IntoIter: ExactSizeIterator,
     {
-        self.upload_queue.push({
-            let mut copy_info = self.create_copy_buffer_to_image_image(image, rgba)?;
-
-            if let Some(([x, y], [width, height])) = region {
-                copy_info.regions[0].image_offset[0] = x;
-                copy_info.regions[0].image_offset[1] = y;
-                copy_info.regions[0].image_extent[0] = width;
-                copy_info.regions[0].image_extent[1] = height;
-            }
+        self.enqueue_image_update_with_priority(image, region, rgba, UploadPriority::default())
+    }
+
+    pub fn enqueue_image_update_with_priority<I>(
+        &self,
+        image: Arc<Image>,
+        region: Option<([u32; 2], [u32; 2])>,
+        rgba: I,
+        priority: UploadPriority,
+    ) -> Result<(), Validated<AllocateBufferError>>
+    where
+        I: IntoIterator<Item = u8>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let mut copy_info = self.create_copy_buffer_to_image_image(image, rgba)?;
+
+        if let Some(([x, y], [width, height])) = region {
+            copy_info.regions[0].image_offset[0] = x;
+            copy_info.regions[0].image_offset[1] = y;
+            copy_info.regions[0].image_extent[0] = width;
+            copy_info.regions[0].image_extent[1] = height;
+        }
 
-            copy_info
-        });
+        self.push_upload(priority, copy_info);
 
         Ok(())
     }
@@ -0,0 +1,267 @@
+use crate::engine::system::vulkan::textures::{
+    ImageSystem, PlaceholderTextures, TextureId, UploadPriority,
+};
+use crate::support::image::RawRgbaImage;
+use crossbeam::channel::{Receiver, Sender};
+use crossbeam::queue::SegQueue;
+use rustc_hash::FxHashMap;
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use vulkano::image::Image;
+use vulkano::{Validated, VulkanError};
+
+#[derive(thiserror::Error, Debug)]
+pub enum TextureLoadError {
+    #[error("Failed to read the image: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to decode the image: {0}")]
+    Decode(String),
+}
+
+/// Decodes the encoded bytes of an image, like a PNG file, into RGBA pixels. The engine does not
+/// depend on a specific codec, the decoder of the game is plugged in here. With the `image`
+/// feature, [`decode_png`] is available as default.
+pub type TextureDecoder =
+    dyn Fn(&[u8]) -> Result<RawRgbaImage, TextureLoadError> + Send + Sync + 'static;
+
+/// Decodes a PNG file with the `image` crate, the default decoder of
+/// [`TextureLoader::new_png`].
+#[cfg(feature = "image")]
+pub fn decode_png(bytes: &[u8]) -> Result<RawRgbaImage, TextureLoadError> {
+    let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)
+        .map_err(|e| TextureLoadError::Decode(e.to_string()))?
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    Ok(RawRgbaImage::new(image.into_raw(), width, height))
+}
+
+enum TextureSource {
+    File(PathBuf),
+    Bytes(Cow<'static, [u8]>),
+}
+
+struct LoadRequest {
+    id: u64,
+    source: TextureSource,
+}
+
+type LoadResult = (u64, Result<RawRgbaImage, TextureLoadError>);
+
+/// A texture that is loaded by a [`TextureLoader`]. Until it is ready (or if loading failed), the
+/// handle resolves to a placeholder texture, so it can be drawn right away.
+pub struct TextureHandle<T> {
+    /// [`None`] if loading failed
+    state: Arc<OnceLock<Option<TextureId<T>>>>,
+}
+
+impl<T> Clone for TextureHandle<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<T> TextureHandle<T> {
+    /// The loaded texture, [`None`] while it is loaded or if loading failed.
+    #[inline]
+    pub fn texture(&self) -> Option<&TextureId<T>> {
+        self.state.get().and_then(Option::as_ref)
+    }
+
+    /// Whether the image was decoded and its upload enqueued.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.texture().is_some()
+    }
+
+    #[inline]
+    pub fn is_failed(&self) -> bool {
+        matches!(self.state.get(), Some(None))
+    }
+
+    /// The loaded texture or the [`PlaceholderTextures::missing`] texture if it is not ready.
+    #[inline]
+    pub fn resolve<'a>(&'a self, placeholders: &'a PlaceholderTextures<T>) -> &'a TextureId<T> {
+        self.texture().unwrap_or_else(|| placeholders.missing())
+    }
+}
+
+/// Decodes images on worker threads, so that loading large textures does not cause frame hitches
+/// on the render thread. The decoded images are turned into textures in
+/// [`TextureLoader::poll`], which creates their images and enqueues their uploads through the
+/// [`ImageSystem`].
+///
+/// ```no_run
+/// # #[cfg(feature = "image")]
+/// # fn main() {
+/// use hotrod::engine::system::vulkan::textured::TexturedPipeline;
+/// use hotrod::engine::system::vulkan::textures::{TextureLoader, UploadPriority};
+/// use hotrod::engine::RenderContext;
+///
+/// let mut loader = TextureLoader::<TexturedPipeline>::new_png(2);
+/// let player = loader.load_file("sprites/player.png");
+///
+/// // every frame
+/// fn draw(context: &RenderContext, loader: &mut TextureLoader<TexturedPipeline>) {
+///     loader.poll(context.inner.image_system(), UploadPriority::default(), |image| {
+///         context.pipelines.texture.prepare_texture(image)
+///     });
+/// }
+/// # }
+/// # #[cfg(not(feature = "image"))]
+/// # fn main() {}
+/// ```
+pub struct TextureLoader<T> {
+    sender: Sender<LoadRequest>,
+    results: Arc<SegQueue<LoadResult>>,
+    pending: FxHashMap<u64, TextureHandle<T>>,
+    next_id: u64,
+}
+
+impl<T> TextureLoader<T> {
+    /// Spawns the given amount of worker threads (at least one), which decode the images with
+    /// the `decoder`. The threads stop once the loader is dropped.
+    pub fn new(
+        threads: usize,
+        decoder: impl Fn(&[u8]) -> Result<RawRgbaImage, TextureLoadError> + Send + Sync + 'static,
+    ) -> Self {
+        let (sender, receiver) = crossbeam::channel::unbounded();
+        let results = Arc::<SegQueue<LoadResult>>::default();
+        let decoder = Arc::new(decoder) as Arc<TextureDecoder>;
+
+        for index in 0..threads.max(1) {
+            let receiver = receiver.clone();
+            let results = Arc::clone(&results);
+            let decoder = Arc::clone(&decoder);
+            if let Err(e) = std::thread::Builder::new()
+                .name(format!("TextureLoaderThread-{index}"))
+                .spawn(move || TextureLoaderThread::run(&receiver, &results, &*decoder))
+            {
+                error!("Failed to start TextureLoader Thread: {e}");
+            }
+        }
+
+        Self {
+            sender,
+            results,
+            pending: FxHashMap::default(),
+            next_id: 0,
+        }
+    }
+
+    /// Like [`TextureLoader::new`], but decodes PNG files with [`decode_png`].
+    #[cfg(feature = "image")]
+    #[inline]
+    pub fn new_png(threads: usize) -> Self {
+        Self::new(threads, decode_png)
+    }
+
+    /// Reads and decodes the file on a worker thread.
+    #[inline]
+    pub fn load_file(&mut self, path: impl Into<PathBuf>) -> TextureHandle<T> {
+        self.request(TextureSource::File(path.into()))
+    }
+
+    /// Decodes the already read bytes, like an embedded asset, on a worker thread.
+    #[inline]
+    pub fn load_bytes(&mut self, bytes: impl Into<Cow<'static, [u8]>>) -> TextureHandle<T> {
+        self.request(TextureSource::Bytes(bytes.into()))
+    }
+
+    /// The amount of textures that are not ready yet.
+    #[inline]
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn request(&mut self, source: TextureSource) -> TextureHandle<T> {
+        let handle = TextureHandle {
+            state: Arc::default(),
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+
+        if let Err(e) = self.sender.send(LoadRequest { id, source }) {
+            error!("Failed to send texture LoadRequest: {e}");
+            let _ = handle.state.set(None);
+        } else {
+            self.pending.insert(id, handle.clone());
+        }
+        handle
+    }
+
+    /// Creates the textures of the images that were decoded since the last call and enqueues
+    /// their uploads with the given [`UploadPriority`]. Must be called regularly, like once per
+    /// frame, on the thread that owns the pipeline of the textures.
+    pub fn poll(
+        &mut self,
+        image_system: &ImageSystem,
+        priority: UploadPriority,
+        mut prepare_texture: impl FnMut(Arc<Image>) -> Result<TextureId<T>, Validated<VulkanError>>,
+    ) {
+        while let Some((id, result)) = self.results.pop() {
+            let Some(handle) = self.pending.remove(&id) else {
+                continue;
+            };
+
+            let texture = result
+                .map_err(|e| error!("Failed to load texture: {e}"))
+                .ok()
+                .and_then(|image| {
+                    image_system
+                        .create_image_and_enqueue_upload_with_priority(
+                            image.data().iter().copied(),
+                            image.width(),
+                            image.height(),
+                            priority,
+                        )
+                        .map_err(|e| error!("Failed to upload loaded texture: {e}"))
+                        .ok()
+                })
+                .and_then(|image| {
+                    prepare_texture(image)
+                        .map_err(|e| error!("Failed to prepare loaded texture: {e}"))
+                        .ok()
+                });
+
+            let _ = handle.state.set(texture);
+        }
+    }
+}
+
+struct TextureLoaderThread;
+
+impl TextureLoaderThread {
+    fn run(
+        receiver: &Receiver<LoadRequest>,
+        results: &SegQueue<LoadResult>,
+        decoder: &TextureDecoder,
+    ) {
+        while let Ok(LoadRequest { id, source }) = receiver.recv() {
+            results.push((id, Self::load(source, decoder)));
+        }
+    }
+
+    #[instrument(level = "info", skip_all)]
+    fn load(
+        source: TextureSource,
+        decoder: &TextureDecoder,
+    ) -> Result<RawRgbaImage, TextureLoadError> {
+        let image = match source {
+            TextureSource::File(path) => decoder(&std::fs::read(path)?)?,
+            TextureSource::Bytes(bytes) => decoder(&bytes)?,
+        };
+        if image.data().len() != image.width() as usize * image.height() as usize * 4 {
+            return Err(TextureLoadError::Decode(format!(
+                "{} bytes do not match an image of {}x{} pixels",
+                image.data().len(),
+                image.width(),
+                image.height()
+            )));
+        }
+        Ok(image)
+    }
+}
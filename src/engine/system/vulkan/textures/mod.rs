@@ -1,7 +1,15 @@
+mod atlas;
 mod image;
+mod loader;
+mod placeholder;
+mod registry;
 mod sampler;
 mod texture;
 
+pub use atlas::*;
 pub use image::*;
+pub use loader::*;
+pub use placeholder::*;
+pub use registry::*;
 pub use sampler::*;
 pub use texture::*;
@@ -0,0 +1,101 @@
+use crate::engine::system::vulkan::textures::{ImageSystem, TextureId, UploadPriority};
+use crate::engine::system::vulkan::UploadError;
+use std::sync::Arc;
+use vulkano::image::Image;
+use vulkano::{Validated, VulkanError};
+
+/// The [`Image`]s of the [`PlaceholderTextures`], shared between all pipelines.
+pub struct PlaceholderImages {
+    white: Arc<Image>,
+    missing: Arc<Image>,
+    transparent: Arc<Image>,
+}
+
+impl PlaceholderImages {
+    const MISSING_SIZE: u32 = 64;
+    const MISSING_CELL_SIZE: u32 = 8;
+    const MISSING_RGBA: [[u8; 4]; 2] = [[255, 0, 255, 255], [0, 0, 0, 255]];
+
+    pub fn create(image_system: &ImageSystem) -> Result<Self, UploadError> {
+        Ok(Self {
+            white: image_system.create_image_and_enqueue_upload_with_priority(
+                [255, 255, 255, 255],
+                1,
+                1,
+                UploadPriority::High,
+            )?,
+            missing: image_system.create_image_and_enqueue_upload_with_priority(
+                Self::missing_rgba(),
+                Self::MISSING_SIZE,
+                Self::MISSING_SIZE,
+                UploadPriority::High,
+            )?,
+            transparent: image_system.create_image_and_enqueue_upload_with_priority(
+                [0, 0, 0, 0],
+                1,
+                1,
+                UploadPriority::High,
+            )?,
+        })
+    }
+
+    fn missing_rgba() -> Vec<u8> {
+        (0..Self::MISSING_SIZE)
+            .flat_map(|y| (0..Self::MISSING_SIZE).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                let cell = (x / Self::MISSING_CELL_SIZE) + (y / Self::MISSING_CELL_SIZE);
+                Self::MISSING_RGBA[cell as usize % 2]
+            })
+            .collect()
+    }
+}
+
+/// Textures to draw instead of the actual texture, if the latter is not (yet) available.
+pub struct PlaceholderTextures<T> {
+    white: TextureId<T>,
+    missing: TextureId<T>,
+    transparent: TextureId<T>,
+}
+
+impl<T> PlaceholderTextures<T> {
+    pub fn prepare(
+        images: &PlaceholderImages,
+        mut prepare_texture: impl FnMut(Arc<Image>) -> Result<TextureId<T>, Validated<VulkanError>>,
+    ) -> Result<Self, Validated<VulkanError>> {
+        Ok(Self {
+            white: prepare_texture(Arc::clone(&images.white))?,
+            missing: prepare_texture(Arc::clone(&images.missing))?,
+            transparent: prepare_texture(Arc::clone(&images.transparent))?,
+        })
+    }
+
+    /// A single white pixel, useful to draw solid colors through a textured pipeline.
+    #[inline]
+    pub fn white(&self) -> &TextureId<T> {
+        &self.white
+    }
+
+    /// A magenta and black checkerboard, drawn instead of textures that are not uploaded yet.
+    #[inline]
+    pub fn missing(&self) -> &TextureId<T> {
+        &self.missing
+    }
+
+    /// A single transparent pixel.
+    #[inline]
+    pub fn transparent(&self) -> &TextureId<T> {
+        &self.transparent
+    }
+
+    /// Returns the given texture if its content is available, the [`PlaceholderTextures::missing`]
+    /// texture otherwise. Returns [`None`] if neither can be sampled yet.
+    pub fn resolve<'a>(
+        &'a self,
+        image_system: &ImageSystem,
+        texture: &'a TextureId<T>,
+    ) -> Option<&'a TextureId<T>> {
+        [texture, &self.missing]
+            .into_iter()
+            .find(|texture| !image_system.is_upload_pending(texture.image()))
+    }
+}
@@ -0,0 +1,138 @@
+use crate::engine::system::vulkan::textures::{TextureId, WeakTextureId};
+use crate::engine::types::handle::{Handle, HandleMap};
+use rustc_hash::FxHashMap;
+use std::sync::RwLock;
+
+/// The part of an atlas texture that another [`TextureId`] refers to, in normalized texture
+/// coordinates.
+pub struct AtlasRegion<T> {
+    pub atlas: TextureId<T>,
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+impl<T> Clone for AtlasRegion<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            atlas: self.atlas.clone(),
+            uv_min: self.uv_min,
+            uv_max: self.uv_max,
+        }
+    }
+}
+
+impl<T> AtlasRegion<T> {
+    /// Maps texture coordinates of the original texture into the atlas.
+    #[inline]
+    pub fn map_uv(&self, [u, v]: [f32; 2]) -> [f32; 2] {
+        [
+            self.uv_min[0] + u * (self.uv_max[0] - self.uv_min[0]),
+            self.uv_min[1] + v * (self.uv_max[1] - self.uv_min[1]),
+        ]
+    }
+}
+
+/// Remembers which textures are also available as a region of an atlas texture, so that
+/// primitives with different textures of the same atlas can be drawn in a single batch.
+///
+/// The registry does not keep the registered textures alive. Entries of released textures are
+/// ignored and dropped by [`TextureRegistry::retain_alive`], which the engine calls once per
+/// frame.
+pub struct TextureRegistry<T> {
+    entries: RwLock<Entries<T>>,
+}
+
+struct Entries<T> {
+    regions: HandleMap<AtlasRegion<T>>,
+    /// By the address of the registered texture
+    textures: FxHashMap<usize, (WeakTextureId<T>, Handle<AtlasRegion<T>>)>,
+}
+
+impl<T> Default for TextureRegistry<T> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            entries: RwLock::new(Entries {
+                regions: HandleMap::default(),
+                textures: FxHashMap::default(),
+            }),
+        }
+    }
+}
+
+impl<T> TextureRegistry<T> {
+    /// Registers `texture` as the region between `uv_min` and `uv_max` of the `atlas`, replacing
+    /// a previous registration of the texture. The handle becomes stale once the texture is
+    /// unregistered or released.
+    pub fn register(
+        &self,
+        texture: &TextureId<T>,
+        atlas: TextureId<T>,
+        uv_min: [f32; 2],
+        uv_max: [f32; 2],
+    ) -> Handle<AtlasRegion<T>> {
+        let mut entries = self.entries.write().unwrap();
+        let handle = entries.regions.insert(AtlasRegion {
+            atlas,
+            uv_min,
+            uv_max,
+        });
+        if let Some((_, previous)) = entries
+            .textures
+            .insert(Self::key(texture), (texture.downgrade(), handle))
+        {
+            entries.regions.remove(previous);
+        }
+        handle
+    }
+
+    pub fn unregister(&self, texture: &TextureId<T>) -> Option<AtlasRegion<T>> {
+        let mut entries = self.entries.write().unwrap();
+        let (_, handle) = entries.textures.remove(&Self::key(texture))?;
+        entries.regions.remove(handle)
+    }
+
+    /// The atlas region of the texture, if one was registered.
+    pub fn lookup(&self, texture: &TextureId<T>) -> Option<AtlasRegion<T>> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .textures
+            .get(&Self::key(texture))
+            .filter(|(weak, _)| !weak.is_released())
+            .and_then(|(_, handle)| entries.regions.get(*handle))
+            .cloned()
+    }
+
+    /// The region of the handle returned by [`TextureRegistry::register`], [`None`] once it is
+    /// stale.
+    #[inline]
+    pub fn region(&self, handle: Handle<AtlasRegion<T>>) -> Option<AtlasRegion<T>> {
+        self.entries.read().unwrap().regions.get(handle).cloned()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().unwrap().regions.is_empty()
+    }
+
+    /// Drops the entries of released textures, which also releases their atlas unless it is
+    /// referenced elsewhere.
+    pub fn retain_alive(&self) {
+        let mut entries = self.entries.write().unwrap();
+        let Entries { regions, textures } = &mut *entries;
+        textures.retain(|_, (weak, handle)| {
+            let alive = !weak.is_released();
+            if !alive {
+                regions.remove(*handle);
+            }
+            alive
+        });
+    }
+
+    /// The address stays unique as long as the [`WeakTextureId`] of the entry exists.
+    #[inline]
+    fn key(texture: &TextureId<T>) -> usize {
+        texture.key()
+    }
+}
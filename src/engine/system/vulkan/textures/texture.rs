@@ -1,10 +1,11 @@
+use crate::engine::system::backend::{RenderBackend, Vulkan};
+use crate::engine::system::vulkan::backend::VulkanTexture;
+use crate::engine::system::vulkan::destruction::DeferredDestructionQueue;
 use crate::engine::system::vulkan::textures::ImageSamplerMode;
 use crate::engine::system::vulkan::PipelineCreateError;
 use std::marker::PhantomData;
-use std::sync::Arc;
-use vulkano::descriptor_set::allocator::{
-    StandardDescriptorSetAllocator, StandardDescriptorSetAllocatorCreateInfo,
-};
+use std::sync::{Arc, Weak};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
 use vulkano::descriptor_set::layout::DescriptorSetLayout;
 use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
 use vulkano::device::Device;
@@ -17,8 +18,9 @@ use vulkano::{Validated, VulkanError};
 pub struct TextureManager<T, const BINDING: u32> {
     sampler: Arc<Sampler>,
     desc_layout: Arc<DescriptorSetLayout>,
-    desc_allocator: StandardDescriptorSetAllocator,
+    desc_allocator: Arc<StandardDescriptorSetAllocator>,
     origin_marker: Arc<()>,
+    deferred_destruction: Arc<DeferredDestructionQueue>,
     _t: PhantomData<T>,
 }
 
@@ -28,27 +30,29 @@ impl<T, const BINDING: u32> TextureManager<T, BINDING> {
         device: Arc<Device>,
         pipeline: &GraphicsPipeline,
         mode: ImageSamplerMode,
+        desc_allocator: Arc<StandardDescriptorSetAllocator>,
+        deferred_destruction: Arc<DeferredDestructionQueue>,
     ) -> Result<Self, PipelineCreateError> {
         Ok(Self::new(
-            mode.create_texture_sampler(Arc::clone(&device))?,
+            mode.create_texture_sampler(device)?,
             Arc::clone(&pipeline.layout().set_layouts()[0]),
-            StandardDescriptorSetAllocator::new(
-                device,
-                StandardDescriptorSetAllocatorCreateInfo::default(),
-            ),
+            desc_allocator,
+            deferred_destruction,
         ))
     }
 
     pub fn new(
         sampler: Arc<Sampler>,
         desc_layout: Arc<DescriptorSetLayout>,
-        desc_allocator: StandardDescriptorSetAllocator,
+        desc_allocator: Arc<StandardDescriptorSetAllocator>,
+        deferred_destruction: Arc<DeferredDestructionQueue>,
     ) -> Self {
         Self {
             sampler,
             desc_layout,
             desc_allocator,
             origin_marker: Arc::new(()),
+            deferred_destruction,
             _t: PhantomData::default(),
         }
     }
@@ -73,12 +77,18 @@ impl<T, const BINDING: u32> TextureManager<T, BINDING> {
         sampler: Arc<Sampler>,
         descriptors: impl Iterator<Item = WriteDescriptorSet>,
     ) -> Result<TextureId<T>, Validated<VulkanError>> {
-        Ok(TextureId(Arc::new(TextureInner {
-            origin: Arc::clone(&self.origin_marker),
-            _image: Arc::clone(&image),
-            descriptor: self.create_image_desc(image, sampler, descriptors)?,
-            _t: Default::default(),
-        })))
+        let descriptor = self.create_image_desc(Arc::clone(&image), sampler, descriptors)?;
+        Ok(TextureId {
+            inner: Arc::new(TextureInner {
+                origin: Arc::clone(&self.origin_marker),
+                texture: VulkanTexture::new(
+                    image,
+                    descriptor,
+                    Arc::clone(&self.deferred_destruction),
+                ),
+                _t: Default::default(),
+            }),
+        })
     }
 
     fn create_image_desc(
@@ -107,32 +117,166 @@ impl<T, const BINDING: u32> TextureManager<T, BINDING> {
     }
 }
 
-pub struct TextureId<T>(pub Arc<TextureInner<T>>);
+/// A reference-counted handle to a texture of the [`RenderBackend`]. The resources of the
+/// texture are released once the last [`TextureId`] is dropped, for [`Vulkan`] through the
+/// [`DeferredDestructionQueue`].
+pub struct TextureId<T, B: RenderBackend = Vulkan> {
+    inner: Arc<TextureInner<T, B>>,
+}
 
-impl<T> Clone for TextureId<T> {
+impl<T, B: RenderBackend> Clone for TextureId<T, B> {
     #[inline]
     fn clone(&self) -> Self {
-        Self(Arc::clone(&self.0))
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
     }
 }
 
-impl<T> TextureId<T> {
+impl<T, B: RenderBackend> TextureId<T, B> {
     #[inline]
     pub fn originates_from(&self, origin: &Arc<()>) -> bool {
-        Arc::ptr_eq(&self.0.origin, origin)
+        Arc::ptr_eq(&self.inner.origin, origin)
+    }
+
+    /// Whether both handles refer to the same texture.
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// The size of the image of the texture in pixels.
+    #[inline]
+    pub fn extent(&self) -> [u32; 2] {
+        B::texture_extent(&self.inner.texture)
+    }
+
+    /// Creates a [`WeakTextureId`] which does not keep the texture alive, useful for caches.
+    #[inline]
+    pub fn downgrade(&self) -> WeakTextureId<T, B> {
+        WeakTextureId(Arc::downgrade(&self.inner))
+    }
+
+    /// Identifies the texture as long as it is alive.
+    #[inline]
+    pub(crate) fn key(&self) -> usize {
+        Arc::as_ptr(&self.inner) as usize
     }
 }
 
 impl<T> TextureId<T> {
     #[inline]
-    pub fn descriptor(&self) -> &Arc<PersistentDescriptorSet> {
-        &self.0.descriptor
+    pub(crate) fn descriptor(&self) -> &Arc<PersistentDescriptorSet> {
+        self.inner.texture.descriptor()
+    }
+
+    #[inline]
+    pub(crate) fn image(&self) -> &Arc<Image> {
+        self.inner.texture.image()
+    }
+}
+
+/// A region of a texture in normalized texture coordinates, like a sprite of a sprite sheet or
+/// a panel of a UI skin.
+pub struct TextureView<T, B: RenderBackend = Vulkan> {
+    pub texture: TextureId<T, B>,
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+impl<T, B: RenderBackend> Clone for TextureView<T, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            texture: self.texture.clone(),
+            uv_min: self.uv_min,
+            uv_max: self.uv_max,
+        }
+    }
+}
+
+impl<T, B: RenderBackend> From<TextureId<T, B>> for TextureView<T, B> {
+    #[inline]
+    fn from(texture: TextureId<T, B>) -> Self {
+        Self::new(texture)
+    }
+}
+
+impl<T, B: RenderBackend> TextureView<T, B> {
+    /// The whole texture.
+    #[inline]
+    pub fn new(texture: TextureId<T, B>) -> Self {
+        Self {
+            texture,
+            uv_min: [0.0, 0.0],
+            uv_max: [1.0, 1.0],
+        }
+    }
+
+    #[inline]
+    pub fn with_uv(mut self, uv_min: [f32; 2], uv_max: [f32; 2]) -> Self {
+        self.uv_min = uv_min;
+        self.uv_max = uv_max;
+        self
+    }
+
+    /// The region of the given position and size in pixels of the texture.
+    pub fn from_pixels(texture: TextureId<T, B>, pos: [u32; 2], dim: [u32; 2]) -> Self {
+        let [width, height] = texture.extent().map(|v| v.max(1) as f32);
+        Self {
+            uv_min: [pos[0] as f32 / width, pos[1] as f32 / height],
+            uv_max: [
+                (pos[0] + dim[0]) as f32 / width,
+                (pos[1] + dim[1]) as f32 / height,
+            ],
+            texture,
+        }
+    }
+
+    /// The size of the region in pixels of the texture.
+    #[inline]
+    pub fn extent(&self) -> [f32; 2] {
+        let [width, height] = self.texture.extent();
+        [
+            (self.uv_max[0] - self.uv_min[0]) * width as f32,
+            (self.uv_max[1] - self.uv_min[1]) * height as f32,
+        ]
+    }
+
+    /// Maps texture coordinates relative to the region into coordinates of the texture.
+    #[inline]
+    pub fn map_uv(&self, [u, v]: [f32; 2]) -> [f32; 2] {
+        [
+            self.uv_min[0] + u * (self.uv_max[0] - self.uv_min[0]),
+            self.uv_min[1] + v * (self.uv_max[1] - self.uv_min[1]),
+        ]
+    }
+}
+
+pub struct WeakTextureId<T, B: RenderBackend = Vulkan>(Weak<TextureInner<T, B>>);
+
+impl<T, B: RenderBackend> Clone for WeakTextureId<T, B> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(Weak::clone(&self.0))
+    }
+}
+
+impl<T, B: RenderBackend> WeakTextureId<T, B> {
+    /// Returns the [`TextureId`] if the texture has not been released yet.
+    #[inline]
+    pub fn upgrade(&self) -> Option<TextureId<T, B>> {
+        self.0.upgrade().map(|inner| TextureId { inner })
+    }
+
+    #[inline]
+    pub fn is_released(&self) -> bool {
+        self.0.strong_count() == 0
     }
 }
 
-pub struct TextureInner<T> {
-    pub origin: Arc<()>,
-    pub _image: Arc<Image>,
-    pub descriptor: Arc<PersistentDescriptorSet>,
+struct TextureInner<T, B: RenderBackend> {
+    origin: Arc<()>,
+    texture: B::Texture,
     _t: PhantomData<T>,
 }
@@ -0,0 +1,318 @@
+use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
+use crate::engine::system::vulkan::system::{GraphicsPipelineRenderPassInfo, VulkanSystem};
+use crate::engine::system::vulkan::wds::WriteDescriptorSetManager;
+use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError};
+use crate::shader_from_path;
+use bytemuck::{Pod, Zeroable};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::{Device, Features};
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendAttachmentState};
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::rasterization::RasterizationState;
+use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition};
+use vulkano::pipeline::graphics::viewport::ViewportState;
+use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
+use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
+use vulkano::pipeline::{
+    DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+    PipelineShaderStageCreateInfo,
+};
+use vulkano::shader::EntryPoint;
+
+/// Renders [`Trail`]s as triangle strips, which are generated on the CPU. The edges of the
+/// ribbons are faded out like the lines of the
+/// [`BeautifulLinePipeline`](crate::engine::system::vulkan::beautiful_lines::BeautifulLinePipeline),
+/// but without depending on the `wide_lines` feature.
+pub struct TrailPipeline {
+    pipeline: Arc<GraphicsPipeline>,
+    descriptor_set: Arc<PersistentDescriptorSet>,
+    buffers_manager: Arc<BasicBuffersManager>,
+}
+
+impl TryFrom<&VulkanSystem> for TrailPipeline {
+    type Error = PipelineCreateError;
+
+    #[inline]
+    fn try_from(vs: &VulkanSystem) -> Result<Self, Self::Error> {
+        Self::new(
+            Arc::clone(vs.device()),
+            vs.graphics_pipeline_render_pass_info(),
+            vs.pipeline_cache().map(Arc::clone),
+            vs.write_descriptor_set_manager(),
+            Arc::clone(vs.basic_buffers_manager()),
+        )
+    }
+}
+
+impl TrailPipeline {
+    pub const REQUIRED_FEATURES: Features = Features {
+        dynamic_rendering: true,
+        ..Features::empty()
+    };
+
+    pub fn new(
+        device: Arc<Device>,
+        render_pass_info: GraphicsPipelineRenderPassInfo,
+        cache: Option<Arc<PipelineCache>>,
+        write_descriptors: &WriteDescriptorSetManager,
+        buffers_manager: Arc<BasicBuffersManager>,
+    ) -> Result<Self, PipelineCreateError> {
+        let pipeline = Self::create_pipeline(Arc::clone(&device), render_pass_info, cache)?;
+        Ok(Self {
+            buffers_manager,
+            descriptor_set: write_descriptors
+                .create_persistent_descriptor_set(&pipeline.layout().set_layouts()[0])?,
+            pipeline,
+        })
+    }
+
+    fn create_pipeline(
+        device: Arc<Device>,
+        render_pass_info: GraphicsPipelineRenderPassInfo,
+        cache: Option<Arc<PipelineCache>>,
+    ) -> Result<Arc<GraphicsPipeline>, PipelineCreateError> {
+        let vs = Self::load_vertex_shader(Arc::clone(&device))?;
+        let fs = Self::load_fragment_shader(Arc::clone(&device))?;
+        let vertex_input_state =
+            TrailVertex::per_vertex().definition(&vs.info().input_interface)?;
+
+        let stages = [
+            PipelineShaderStageCreateInfo::new(vs),
+            PipelineShaderStageCreateInfo::new(fs),
+        ];
+
+        let layout = PipelineLayout::new(
+            Arc::clone(&device),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+                .into_pipeline_layout_create_info(Arc::clone(&device))?,
+        )?;
+
+        Ok(GraphicsPipeline::new(
+            Arc::clone(&device),
+            cache,
+            GraphicsPipelineCreateInfo {
+                stages: stages.into_iter().collect(),
+                vertex_input_state: Some(vertex_input_state),
+                input_assembly_state: Some(InputAssemblyState {
+                    topology: PrimitiveTopology::TriangleStrip,
+                    ..InputAssemblyState::default()
+                }),
+                viewport_state: Some(ViewportState::default()),
+                rasterization_state: Some(RasterizationState::default()),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(render_pass_info.color_blend_state(
+                    ColorBlendAttachmentState {
+                        blend: Some(AttachmentBlend::alpha()),
+                        ..ColorBlendAttachmentState::default()
+                    },
+                )),
+                dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                    .into_iter()
+                    .collect(),
+                depth_stencil_state: render_pass_info.depth_stencil_state(),
+                subpass: Some(render_pass_info.into_subpass_type()),
+                ..GraphicsPipelineCreateInfo::layout(layout)
+            },
+        )?)
+    }
+
+    fn load_vertex_shader(device: Arc<Device>) -> Result<EntryPoint, ShaderLoadError> {
+        shader_from_path!(
+            device,
+            "vertex",
+            "src/engine/system/vulkan/trails/trails.vert"
+        )
+    }
+
+    fn load_fragment_shader(device: Arc<Device>) -> Result<EntryPoint, ShaderLoadError> {
+        shader_from_path!(
+            device,
+            "fragment",
+            "src/engine/system/vulkan/trails/trails.frag"
+        )
+    }
+
+    pub fn draw<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        trails: &[Trail],
+    ) -> Result<(), DrawError> {
+        if !self
+            .buffers_manager
+            .draw_filter()
+            .admit(PipelineKind::Trail, trails.len())
+        {
+            return Ok(());
+        }
+        let mut vertices = Vec::new();
+        let mut strips = Vec::with_capacity(trails.len());
+
+        for trail in trails {
+            let offset = vertices.len() as u32;
+            trail.append_vertices(&mut vertices);
+            let count = vertices.len() as u32 - offset;
+            if count > 0 {
+                strips.push((offset, count));
+            }
+        }
+
+        if strips.is_empty() {
+            return Ok(());
+        }
+
+        let vertex_buffer = self.buffers_manager.create_pooled_vertex_buffer(vertices)?;
+
+        builder
+            .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
+            .bind_vertex_buffers(0, vertex_buffer)?
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                Arc::clone(&self.pipeline.layout()),
+                0,
+                Arc::clone(&self.descriptor_set),
+            )?;
+
+        for (offset, count) in strips {
+            builder.draw(count, 1, offset, 0)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
+pub struct TrailVertex {
+    #[format(R32G32_SFLOAT)]
+    pub pos: [f32; 2],
+    #[format(R32G32B32A32_SFLOAT)]
+    pub color: [f32; 4],
+    /// `-1.0` on the one edge of the ribbon, `1.0` on the other
+    #[format(R32_SFLOAT)]
+    pub across: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TrailPoint {
+    pub pos: [f32; 2],
+    pub width: f32,
+    pub color: [f32; 4],
+}
+
+/// The most recent positions of a moving object, the oldest position is removed once the
+/// capacity is reached. Towards the oldest position, the ribbon fades out and tapers to a point.
+#[derive(Debug, Clone)]
+pub struct Trail {
+    points: VecDeque<TrailPoint>,
+    capacity: usize,
+    fade: bool,
+    taper: bool,
+}
+
+impl Trail {
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            points: VecDeque::with_capacity(capacity),
+            capacity,
+            fade: true,
+            taper: true,
+        }
+    }
+
+    /// Whether the alpha decreases towards the oldest position.
+    #[inline]
+    pub fn with_fade(mut self, fade: bool) -> Self {
+        self.fade = fade;
+        self
+    }
+
+    /// Whether the width decreases towards the oldest position.
+    #[inline]
+    pub fn with_taper(mut self, taper: bool) -> Self {
+        self.taper = taper;
+        self
+    }
+
+    #[inline]
+    pub fn push(&mut self, point: TrailPoint) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.points.len() >= self.capacity {
+            self.points.pop_front();
+        }
+        self.points.push_back(point);
+    }
+
+    /// Removes the oldest position, for example to let the trail shrink once the object stopped.
+    #[inline]
+    pub fn pop_oldest(&mut self) -> Option<TrailPoint> {
+        self.points.pop_front()
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The points from the oldest to the most recent.
+    #[inline]
+    pub fn points(&self) -> impl Iterator<Item = &TrailPoint> {
+        self.points.iter()
+    }
+
+    fn append_vertices(&self, vertices: &mut Vec<TrailVertex>) {
+        let len = self.points.len();
+        if len < 2 {
+            return;
+        }
+
+        for (index, point) in self.points.iter().enumerate() {
+            // the direction of the ribbon at this point, averaged over both neighbours
+            let prev = self.points[index.saturating_sub(1)].pos;
+            let next = self.points[(index + 1).min(len - 1)].pos;
+            let (dx, dy) = (next[0] - prev[0], next[1] - prev[1]);
+            let length = (dx * dx + dy * dy).sqrt();
+            let normal = if length > f32::EPSILON {
+                [-dy / length, dx / length]
+            } else {
+                [0.0, 0.0]
+            };
+
+            // 0.0 for the oldest point, 1.0 for the most recent
+            let age = index as f32 / (len - 1) as f32;
+            let half_width = point.width * 0.5 * if self.taper { age } else { 1.0 };
+            let mut color = point.color;
+            if self.fade {
+                color[3] *= age;
+            }
+
+            for side in [-1.0f32, 1.0] {
+                vertices.push(TrailVertex {
+                    pos: [
+                        point.pos[0] + normal[0] * half_width * side,
+                        point.pos[1] + normal[1] * half_width * side,
+                    ],
+                    color,
+                    across: side,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+#version 450
+
+layout(location = 0) in vec4 in_color;
+layout(location = 1) in float in_across;
+
+layout(location = 0) out vec4 out_color;
+
+// like the beautiful lines, the core is solid and the edges fade out
+const float RATIO = 0.65;
+
+void main() {
+    vec4 color = in_color;
+    float dist = abs(in_across);
+
+    if (dist > RATIO) {
+        color.a *= 1.0 - (dist - RATIO) / (1.0 - RATIO);
+    }
+
+    out_color = color;
+}
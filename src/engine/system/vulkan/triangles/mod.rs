@@ -1,4 +1,7 @@
+use crate::engine::system::arena::FrameArena;
 use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
+use crate::engine::system::vulkan::streaming::StreamingBuffer;
 use crate::engine::system::vulkan::system::{GraphicsPipelineRenderPassInfo, VulkanSystem};
 use crate::engine::system::vulkan::wds::WriteDescriptorSetManager;
 use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError};
@@ -9,11 +12,8 @@ use vulkano::command_buffer::AutoCommandBufferBuilder;
 use vulkano::descriptor_set::PersistentDescriptorSet;
 use vulkano::device::{Device, Features};
 use vulkano::pipeline::cache::PipelineCache;
-use vulkano::pipeline::graphics::color_blend::{
-    AttachmentBlend, ColorBlendAttachmentState, ColorBlendState,
-};
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendAttachmentState};
 use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
-use vulkano::pipeline::graphics::multisample::MultisampleState;
 use vulkano::pipeline::graphics::rasterization::RasterizationState;
 use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition};
 use vulkano::pipeline::graphics::viewport::ViewportState;
@@ -29,6 +29,8 @@ use vulkano::shader::EntryPoint;
 pub struct TrianglesPipeline {
     pipeline: Arc<GraphicsPipeline>,
     buffers_manager: Arc<BasicBuffersManager>,
+    vertices: StreamingBuffer<Vertex2d>,
+    indices: StreamingBuffer<u32>,
     descriptor_set: Arc<PersistentDescriptorSet>,
 }
 
@@ -64,6 +66,8 @@ impl TrianglesPipeline {
         Ok(Self {
             descriptor_set: write_descriptors
                 .create_persistent_descriptor_set(&pipeline.layout().set_layouts()[0])?,
+            vertices: StreamingBuffer::vertices(Arc::clone(&buffers_manager), 4 * 1024),
+            indices: StreamingBuffer::indices(Arc::clone(&buffers_manager), 8 * 1024),
             pipeline,
             buffers_manager,
         })
@@ -102,18 +106,17 @@ impl TrianglesPipeline {
                 }),
                 viewport_state: Some(ViewportState::default()),
                 rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState {
-                    rasterization_samples: render_pass_info.rasterization_samples(),
-                    ..MultisampleState::default()
-                }),
-                color_blend_state: Some(ColorBlendState::with_attachment_states(
-                    render_pass_info.num_color_attachments(),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(render_pass_info.color_blend_state(
                     ColorBlendAttachmentState {
                         blend: Some(AttachmentBlend::alpha()),
                         ..ColorBlendAttachmentState::default()
                     },
                 )),
-                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                    .into_iter()
+                    .collect(),
+                depth_stencil_state: render_pass_info.depth_stencil_state(),
                 subpass: Some(render_pass_info.into_subpass_type()),
                 ..GraphicsPipelineCreateInfo::layout(layout)
             },
@@ -141,14 +144,45 @@ impl TrianglesPipeline {
         builder: &mut AutoCommandBufferBuilder<P>,
         triangles: &[Triangles],
     ) -> Result<(), DrawError> {
-        let mut offset = 0;
+        self.record(builder, triangles)
+    }
+
+    /// Same as [`TrianglesPipeline::draw`], which writes the vertices directly into a
+    /// [`StreamingBuffer`], the [`FrameArena`] is not needed anymore.
+    pub fn draw_in<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        _arena: &FrameArena,
+        triangles: &[Triangles],
+    ) -> Result<(), DrawError> {
+        self.record(builder, triangles)
+    }
 
-        let vertex_buffer = self.buffers_manager.create_vertex_buffer(
+    fn record<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        triangles: &[Triangles],
+    ) -> Result<(), DrawError> {
+        if !self
+            .buffers_manager
+            .draw_filter()
+            .admit(PipelineKind::Triangles, triangles.len())
+        {
+            return Ok(());
+        }
+        let vertex_count = triangles.iter().map(|l| l.vertices.len()).sum::<usize>();
+        if vertex_count == 0 {
+            return Ok(());
+        }
+        // written directly into the mapped memory, without collecting them into a Vec first
+        let vertex_buffer = self.vertices.write_with(vertex_count, |vertices| {
             triangles
                 .iter()
-                .flat_map(|l| l.vertices.iter().copied())
-                .collect::<Vec<_>>(),
-        )?;
+                .flat_map(|l| l.vertices.iter())
+                .zip(vertices)
+                .for_each(|(vertex, target)| *target = *vertex);
+        })?;
+        let mut offset = 0;
 
         builder
             .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
@@ -160,19 +194,14 @@ impl TrianglesPipeline {
                 Arc::clone(&self.descriptor_set),
             )?;
 
+        let mut color = None;
         for triangles in triangles {
-            builder
-                .push_constants(
-                    Arc::clone(&self.pipeline.layout()),
-                    0,
-                    [
-                        triangles.color[0],
-                        triangles.color[1],
-                        triangles.color[2],
-                        triangles.color[3],
-                    ],
-                )?
-                .draw(triangles.vertices.len() as u32, 1, offset, 0)?;
+            // push constants stay valid between draw calls
+            if color != Some(triangles.color) {
+                builder.push_constants(Arc::clone(self.pipeline.layout()), 0, triangles.color)?;
+                color = Some(triangles.color);
+            }
+            builder.draw(triangles.vertices.len() as u32, 1, offset, 0)?;
             offset += triangles.vertices.len() as u32;
         }
 
@@ -184,22 +213,37 @@ impl TrianglesPipeline {
         builder: &mut AutoCommandBufferBuilder<P>,
         triangles: &[TrianglesIndexed],
     ) -> Result<(), DrawError> {
+        if !self
+            .buffers_manager
+            .draw_filter()
+            .admit(PipelineKind::Triangles, triangles.len())
+        {
+            return Ok(());
+        }
         let mut offset_vertices = 0;
         let mut offset_indices = 0;
 
-        let vertex_buffer = self.buffers_manager.create_vertex_buffer(
+        let vertex_count = triangles.iter().map(|l| l.vertices.len()).sum::<usize>();
+        let index_count = triangles.iter().map(|l| l.indices.len() * 3).sum::<usize>();
+        if vertex_count == 0 || index_count == 0 {
+            return Ok(());
+        }
+
+        let vertex_buffer = self.vertices.write_with(vertex_count, |vertices| {
             triangles
                 .iter()
-                .flat_map(|l| l.vertices.iter().copied())
-                .collect::<Vec<_>>(),
-        )?;
+                .flat_map(|l| l.vertices.iter())
+                .zip(vertices)
+                .for_each(|(vertex, target)| *target = *vertex);
+        })?;
 
-        let index_buffer = self.buffers_manager.create_index_buffer(
+        let index_buffer = self.indices.write_with(index_count, |indices| {
             triangles
                 .iter()
-                .flat_map(|l| l.indices.iter().flat_map(|i| i.into_iter()).copied())
-                .collect::<Vec<_>>(),
-        )?;
+                .flat_map(|l| l.indices.iter().flatten())
+                .zip(indices)
+                .for_each(|(index, target)| *target = *index);
+        })?;
 
         builder
             .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
@@ -212,24 +256,24 @@ impl TrianglesPipeline {
                 Arc::clone(&self.descriptor_set),
             )?;
 
+        let mut color = None;
         for triangles in triangles {
+            // the indices are relative to the vertices of each item, see vertex_offset below
+            debug_assert!(
+                triangles.is_valid(),
+                "An index of a TrianglesIndexed is out of bounds of its vertices"
+            );
             let index_count = triangles.indices.len() as u32 * 3;
 
-            builder
-                .push_constants(
-                    Arc::clone(&self.pipeline.layout()),
-                    0,
-                    [
-                        triangles.color[0],
-                        triangles.color[1],
-                        triangles.color[2],
-                        triangles.color[3],
-                    ],
-                )?
-                .draw_indexed(index_count, 1, offset_indices, offset_vertices, 0)?;
+            // push constants stay valid between draw calls
+            if color != Some(triangles.color) {
+                builder.push_constants(Arc::clone(self.pipeline.layout()), 0, triangles.color)?;
+                color = Some(triangles.color);
+            }
+            builder.draw_indexed(index_count, 1, offset_indices, offset_vertices, 0)?;
 
             offset_vertices += triangles.vertices.len() as i32;
-            offset_indices += index_count as u32;
+            offset_indices += index_count;
         }
 
         Ok(())
@@ -253,3 +297,15 @@ pub struct TrianglesIndexed {
     pub indices: Vec<[u32; 3]>,
     pub color: [f32; 4],
 }
+
+impl TrianglesIndexed {
+    /// Whether all indices refer to vertices of this item, as they are relative to its first
+    /// vertex.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.indices
+            .iter()
+            .flatten()
+            .all(|index| (*index as usize) < self.vertices.len())
+    }
+}
@@ -1,4 +1,49 @@
 pub mod pipeline;
+pub mod quad;
+
+use std::sync::Arc;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::pipeline::{PipelineBindPoint, PipelineLayout};
+use vulkano::ValidationError;
+
+/// Binds descriptor sets at set `0` of a graphics pipeline, but skips the set that is already
+/// bound. Consecutive draw calls with the same texture neither record another bind command nor
+/// touch the reference counts of the layout and the descriptor set.
+pub struct DescriptorSetBinding<'a> {
+    layout: &'a Arc<PipelineLayout>,
+    bound: Option<&'a Arc<PersistentDescriptorSet>>,
+}
+
+impl<'a> DescriptorSetBinding<'a> {
+    #[inline]
+    pub fn new(layout: &'a Arc<PipelineLayout>) -> Self {
+        Self {
+            layout,
+            bound: None,
+        }
+    }
+
+    pub fn bind<P>(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        descriptor_set: &'a Arc<PersistentDescriptorSet>,
+    ) -> Result<(), Box<ValidationError>> {
+        if !self
+            .bound
+            .is_some_and(|bound| Arc::ptr_eq(bound, descriptor_set))
+        {
+            builder.bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                Arc::clone(self.layout),
+                0,
+                Arc::clone(descriptor_set),
+            )?;
+            self.bound = Some(descriptor_set);
+        }
+        Ok(())
+    }
+}
 
 #[macro_export]
 macro_rules! shader_from_path {
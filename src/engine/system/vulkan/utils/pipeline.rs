@@ -2,53 +2,213 @@ use std::sync::Arc;
 use vulkano::device::Device;
 use vulkano::format::Format;
 use vulkano::image::SampleCount;
+use vulkano::pipeline::graphics::subpass::PipelineRenderingCreateInfo;
 use vulkano::render_pass::RenderPass;
 use vulkano::{Validated, VulkanError};
 
+/// The attachments of the forward render pass, from which both the render pass of the classic
+/// frame path and the formats of dynamic rendering are derived, so that the pipelines, the
+/// framebuffers and the rendering attachments always agree on the samples.
+///
+/// With multisampling, the pass renders into a transient multisampled color attachment, which is
+/// resolved into the single-sampled target at the end of the pass (the `color_resolve`
+/// attachment of the render pass or the resolve image of the rendering). The depth attachment
+/// has the samples of the color attachment. The resolved target is what is presented, copied or
+/// sampled, so nothing downstream has to know about the samples.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RenderPassLayout {
+    pub color_format: Format,
+    pub samples: SampleCount,
+    pub depth_format: Option<Format>,
+}
+
+impl RenderPassLayout {
+    /// A single-sampled color attachment without depth.
+    #[inline]
+    pub fn new(color_format: Format) -> Self {
+        Self {
+            color_format,
+            samples: SampleCount::Sample1,
+            depth_format: None,
+        }
+    }
+
+    #[inline]
+    pub fn with_samples(mut self, samples: SampleCount) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    #[inline]
+    pub fn with_depth_format(mut self, depth_format: Option<Format>) -> Self {
+        self.depth_format = depth_format;
+        self
+    }
+
+    /// Whether the color attachment is resolved into the target.
+    #[inline]
+    pub fn is_multisampled(&self) -> bool {
+        self.samples != SampleCount::Sample1
+    }
+
+    /// The highest amount of samples up to [`RenderPassLayout::samples`] that the device supports
+    /// for the color and the depth attachments of a framebuffer.
+    pub fn supported_samples(&self, device: &Device) -> SampleCount {
+        let properties = device.physical_device().properties();
+        let mut supported = properties.framebuffer_color_sample_counts;
+        if self.depth_format.is_some() {
+            supported &= properties.framebuffer_depth_sample_counts;
+        }
+        [
+            SampleCount::Sample64,
+            SampleCount::Sample32,
+            SampleCount::Sample16,
+            SampleCount::Sample8,
+            SampleCount::Sample4,
+            SampleCount::Sample2,
+        ]
+        .into_iter()
+        .filter(|samples| u32::from(*samples) <= u32::from(self.samples))
+        .find(|samples| supported.contains_enum(*samples))
+        .unwrap_or(SampleCount::Sample1)
+    }
+
+    /// The layout with the [`RenderPassLayout::supported_samples`].
+    #[inline]
+    pub fn supported_by(self, device: &Device) -> Self {
+        Self {
+            samples: self.supported_samples(device),
+            ..self
+        }
+    }
+
+    /// The formats for pipelines that render with dynamic rendering into this layout.
+    pub fn pipeline_rendering_info(&self) -> PipelineRenderingCreateInfo {
+        PipelineRenderingCreateInfo {
+            color_attachment_formats: vec![Some(self.color_format)],
+            depth_attachment_format: self.depth_format,
+            ..PipelineRenderingCreateInfo::default()
+        }
+    }
+
+    /// Creates a render pass with a single subpass. The attachments are in the order of the
+    /// framebuffers: the multisampled color attachment (if any), the resolved color attachment
+    /// and the depth attachment (if any).
+    pub fn create_render_pass(
+        &self,
+        device: Arc<Device>,
+    ) -> Result<Arc<RenderPass>, Validated<VulkanError>> {
+        let Self {
+            color_format: image_format,
+            samples,
+            depth_format,
+        } = *self;
+        match (samples == SampleCount::Sample1, depth_format) {
+            (true, None) => vulkano::single_pass_renderpass!(
+                device,
+                attachments: {
+                    color: {
+                        format: image_format,
+                        samples: 1,
+                        load_op: Clear,
+                        store_op: Store,
+                    },
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {},
+                }
+            ),
+            (true, Some(depth_format)) => vulkano::single_pass_renderpass!(
+                device,
+                attachments: {
+                    color: {
+                        format: image_format,
+                        samples: 1,
+                        load_op: Clear,
+                        store_op: Store,
+                    },
+                    depth: {
+                        format: depth_format,
+                        samples: 1,
+                        load_op: Clear,
+                        store_op: DontCare,
+                    },
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {depth},
+                }
+            ),
+            (false, None) => vulkano::single_pass_renderpass!(
+                device,
+                attachments: {
+                    intermediary: {
+                        format: image_format,
+                        // This has to match the image definition.
+                        samples: samples,
+                        load_op: Clear,
+                        store_op: DontCare,
+                    },
+                    color: {
+                        format: image_format,
+                        samples: 1,
+                        load_op: DontCare,
+                        store_op: Store,
+                    },
+                },
+                pass: {
+                    color: [intermediary],
+                    color_resolve: [color],
+                    depth_stencil: {},
+                }
+            ),
+            (false, Some(depth_format)) => vulkano::single_pass_renderpass!(
+                device,
+                attachments: {
+                    intermediary: {
+                        format: image_format,
+                        // This has to match the image definition.
+                        samples: samples,
+                        load_op: Clear,
+                        store_op: DontCare,
+                    },
+                    color: {
+                        format: image_format,
+                        samples: 1,
+                        load_op: DontCare,
+                        store_op: Store,
+                    },
+                    depth: {
+                        format: depth_format,
+                        // This has to match the samples of the intermediary.
+                        samples: samples,
+                        load_op: Clear,
+                        store_op: DontCare,
+                    },
+                },
+                pass: {
+                    color: [intermediary],
+                    color_resolve: [color],
+                    depth_stencil: {depth},
+                }
+            ),
+        }
+    }
+}
+
+/// Creates a render pass with a single subpass that renders into a color attachment of the given
+/// format. With a `depth_format`, a depth attachment follows the color attachments. See
+/// [`RenderPassLayout::create_render_pass`].
+#[inline]
 pub fn single_pass_render_pass_from_image_format(
     device: Arc<Device>,
     image_format: Format,
     samples: SampleCount,
+    depth_format: Option<Format>,
 ) -> Result<Arc<RenderPass>, Validated<VulkanError>> {
-    if samples == SampleCount::Sample1 {
-        vulkano::single_pass_renderpass!(
-            device,
-            attachments: {
-                color: {
-                    format: image_format,
-                    samples: 1,
-                    load_op: Clear,
-                    store_op: Store,
-                },
-            },
-            pass: {
-                color: [color],
-                depth_stencil: {},
-            }
-        )
-    } else {
-        vulkano::single_pass_renderpass!(
-            device,
-            attachments: {
-                intermediary: {
-                    format: image_format,
-                    // This has to match the image definition.
-                    samples: samples,
-                    load_op: Clear,
-                    store_op: DontCare,
-                },
-                color: {
-                    format: image_format,
-                    samples: 1,
-                    load_op: DontCare,
-                    store_op: Store,
-                },
-            },
-            pass: {
-                color: [intermediary],
-                color_resolve: [color],
-                depth_stencil: {},
-            }
-        )
-    }
+    RenderPassLayout::new(image_format)
+        .with_samples(samples)
+        .with_depth_format(depth_format)
+        .create_render_pass(device)
 }
@@ -0,0 +1,204 @@
+use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
+use crate::engine::system::vulkan::streaming::StreamingBuffer;
+use crate::engine::system::vulkan::DrawError;
+use bytemuck::{Pod, Zeroable};
+use std::sync::{Arc, Mutex};
+use vulkano::buffer::{AllocateBufferError, IndexBuffer, Subbuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition, VertexInputState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::shader::EntryPoint;
+use vulkano::{Validated, ValidationError};
+
+/// The indices of the two triangles of a quad, with the corners in clockwise or counterclockwise
+/// order.
+pub const QUAD_INDICES: [u32; 6] = [0, 1, 2, 2, 3, 0];
+
+/// Owns the buffers of a unit quad (centered at the origin) and draws it once per instance.
+/// Pipelines that render billboards only need to provide the per-instance data, the shaders
+/// receive the corners of the quad as `vec2 pos` input.
+///
+/// The draws are admitted through the [`DrawFilter`] as the [`PipelineKind`] of the pipeline
+/// that owns the instancer, so the pipelines themselves do not need to check it.
+///
+/// [`DrawFilter`]: crate::engine::system::vulkan::draw_filter::DrawFilter
+pub struct QuadInstancer {
+    buffers_manager: Arc<BasicBuffersManager>,
+    kind: PipelineKind,
+    index_buffer: IndexBuffer,
+    vertex_buffer: Subbuffer<[QuadVertex]>,
+}
+
+impl QuadInstancer {
+    const INDICES: [u32; 6] = QUAD_INDICES;
+
+    pub fn new(
+        buffers_manager: Arc<BasicBuffersManager>,
+        kind: PipelineKind,
+    ) -> Result<Self, Validated<AllocateBufferError>> {
+        Ok(Self {
+            index_buffer: buffers_manager.create_index_buffer(Self::INDICES)?,
+            vertex_buffer: buffers_manager.create_vertex_buffer([
+                QuadVertex { pos: [-0.5, -0.5] },
+                QuadVertex { pos: [0.5, -0.5] },
+                QuadVertex { pos: [0.5, 0.5] },
+                QuadVertex { pos: [-0.5, 0.5] },
+            ])?,
+            buffers_manager,
+            kind,
+        })
+    }
+
+    /// The vertex input of a pipeline with the quad at binding `0` and the instances of type
+    /// `I` at binding `1`.
+    pub fn vertex_input_state<I: Vertex>(
+        vs: &EntryPoint,
+    ) -> Result<VertexInputState, Box<ValidationError>> {
+        [QuadVertex::per_vertex(), I::per_instance()].definition(&vs.info().input_interface)
+    }
+
+    /// Binds the pipeline and the descriptor set and draws the quad for each instance, unless
+    /// the [`PipelineKind`] is disabled.
+    pub fn draw<P, I, T>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        pipeline: &Arc<GraphicsPipeline>,
+        descriptor_set: Arc<PersistentDescriptorSet>,
+        instances: I,
+    ) -> Result<(), DrawError>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+        T: Vertex + Pod + Send + Sync,
+    {
+        self.draw_with(builder, pipeline, descriptor_set, instances, |instances| {
+            self.buffers_manager.create_pooled_vertex_buffer(instances)
+        })
+    }
+
+    /// Like [`QuadInstancer::draw`], but writes the instances into the [`StreamingBuffer`] of
+    /// the pipeline, for instances that change every frame.
+    pub fn draw_streamed<P, I, T>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        pipeline: &Arc<GraphicsPipeline>,
+        descriptor_set: Arc<PersistentDescriptorSet>,
+        instances: I,
+        streaming: &StreamingBuffer<T>,
+    ) -> Result<(), DrawError>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+        T: Vertex + Pod + Send + Sync,
+    {
+        self.draw_with(builder, pipeline, descriptor_set, instances, |instances| {
+            streaming.write(instances)
+        })
+    }
+
+    fn draw_with<P, I, T>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        pipeline: &Arc<GraphicsPipeline>,
+        descriptor_set: Arc<PersistentDescriptorSet>,
+        instances: I,
+        upload: impl FnOnce(I::IntoIter) -> Result<Subbuffer<[T]>, Validated<AllocateBufferError>>,
+    ) -> Result<(), DrawError>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+        T: Vertex + Pod + Send + Sync,
+    {
+        let instances = instances.into_iter();
+        if !self
+            .buffers_manager
+            .draw_filter()
+            .admit(self.kind, instances.len())
+            || instances.len() == 0
+        {
+            return Ok(());
+        }
+        let instance_buffer = upload(instances)?;
+        let instance_count = instance_buffer.len() as u32;
+
+        builder
+            .bind_pipeline_graphics(Arc::clone(pipeline))?
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                Arc::clone(pipeline.layout()),
+                0,
+                descriptor_set,
+            )?
+            .bind_index_buffer(self.index_buffer.clone())?
+            .bind_vertex_buffers(
+                0,
+                [
+                    self.vertex_buffer.as_bytes().clone(),
+                    instance_buffer.into_bytes(),
+                ],
+            )?
+            .draw_indexed(Self::INDICES.len() as u32, instance_count, 0, 0, 0)?;
+
+        Ok(())
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
+pub struct QuadVertex {
+    #[format(R32G32_SFLOAT)]
+    pub pos: [f32; 2],
+}
+
+/// An index buffer with the [`QUAD_INDICES`] repeated for consecutive quads, each with four
+/// vertices. It is shared between all draw calls and only reallocated when more quads are drawn
+/// at once than ever before.
+pub struct QuadIndexBuffer {
+    buffers_manager: Arc<BasicBuffersManager>,
+    /// The buffer and the amount of quads it has indices for
+    indices: Mutex<(IndexBuffer, usize)>,
+}
+
+impl QuadIndexBuffer {
+    pub fn new(
+        buffers_manager: Arc<BasicBuffersManager>,
+        quads: usize,
+    ) -> Result<Self, Validated<AllocateBufferError>> {
+        let quads = quads.max(1);
+        Ok(Self {
+            indices: Mutex::new((Self::create(&buffers_manager, quads)?, quads)),
+            buffers_manager,
+        })
+    }
+
+    /// An index buffer for at least the given amount of quads. Command buffers that still use a
+    /// smaller buffer keep it alive until they are executed. The indices are 16-bit for up to
+    /// 16384 quads and 32-bit beyond.
+    pub fn for_quads(&self, quads: usize) -> Result<IndexBuffer, Validated<AllocateBufferError>> {
+        let mut indices = self.indices.lock().unwrap();
+        if indices.1 < quads {
+            let quads = quads.next_power_of_two();
+            *indices = (Self::create(&self.buffers_manager, quads)?, quads);
+        }
+        Ok(indices.0.clone())
+    }
+
+    /// The amount of quads the current buffer has indices for.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.indices.lock().unwrap().1
+    }
+
+    fn create(
+        buffers_manager: &BasicBuffersManager,
+        quads: usize,
+    ) -> Result<IndexBuffer, Validated<AllocateBufferError>> {
+        let len = (quads * QUAD_INDICES.len()) as u32;
+        buffers_manager.create_index_buffer((0..len).map(|index| {
+            let quad = index / QUAD_INDICES.len() as u32;
+            quad * 4 + QUAD_INDICES[(index % QUAD_INDICES.len() as u32) as usize]
+        }))
+    }
+}
@@ -8,19 +8,20 @@ use vulkano::command_buffer::AutoCommandBufferBuilder;
 use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
 use vulkano::descriptor_set::layout::DescriptorSetLayout;
 use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
-use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::memory::allocator::MemoryAllocator;
 use vulkano::{Validated, VulkanError};
 
+#[derive(Clone)]
 pub struct WriteDescriptorSetManager {
     desc_allocator: Arc<StandardDescriptorSetAllocator>,
-    memo_allocator: Arc<StandardMemoryAllocator>,
+    memo_allocator: Arc<dyn MemoryAllocator>,
     write_descriptor_sets: HashMap<u32, WriteDescriptorSet, nohash_hasher::BuildNoHashHasher<u32>>,
 }
 
 impl WriteDescriptorSetManager {
     pub fn new(
         desc_allocator: Arc<StandardDescriptorSetAllocator>,
-        memo_allocator: Arc<StandardMemoryAllocator>,
+        memo_allocator: Arc<dyn MemoryAllocator>,
     ) -> Self {
         Self {
             desc_allocator,
@@ -35,7 +36,7 @@ impl WriteDescriptorSetManager {
     }
 
     #[inline]
-    pub fn memory_allocator(&self) -> &Arc<StandardMemoryAllocator> {
+    pub fn memory_allocator(&self) -> &Arc<dyn MemoryAllocator> {
         &self.memo_allocator
     }
 
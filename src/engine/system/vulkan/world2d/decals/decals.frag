@@ -0,0 +1,14 @@
+#version 450
+
+layout(location = 0) in vec2 in_uv;
+layout(location = 1) in vec4 in_color;
+
+layout(location = 0) out vec4 out_color;
+
+layout(binding = 0, set = 0) uniform sampler2D bound_texture;
+layout(binding = 202) uniform AmbientLight { vec4 color; } ambient;
+
+void main() {
+    vec4 color = texture(bound_texture, in_uv) * in_color;
+    out_color = vec4(color.rgb * ambient.color.rgb, color.a);
+}
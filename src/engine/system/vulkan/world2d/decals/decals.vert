@@ -0,0 +1,35 @@
+#version 450
+
+// per vertex data
+layout(location = 0) in vec2 pos;
+
+// per instance data
+layout(location = 1) in vec2 decal_pos;
+layout(location = 2) in vec2 uv0;
+layout(location = 3) in vec2 uv1;
+layout(location = 4) in vec2 size;
+layout(location = 5) in float rotation;
+layout(location = 6) in vec4 color;
+
+layout(binding = 101) uniform WindowProperties { vec2 screen_size; } window;
+layout(binding = 201) uniform WorldView2d { vec2 position; float zoom; } view;
+
+layout(location = 0) out vec2 out_uv;
+layout(location = 1) out vec4 out_color;
+
+void main() {
+    vec2 local = pos * size;
+    float c = cos(rotation);
+    float s = sin(rotation);
+    vec2 rotated = vec2(c * local.x - s * local.y, s * local.x + c * local.y);
+
+    gl_Position = vec4(
+    2.0 * ((view.zoom * (rotated.x + decal_pos.x - view.position.x)) / window.screen_size.x),
+    2.0 * ((view.zoom * (rotated.y + decal_pos.y - view.position.y)) / window.screen_size.y),
+    0.0,
+    1.0
+    );
+
+    out_uv = mix(uv0, uv1, pos + 0.5);
+    out_color = color;
+}
@@ -0,0 +1,347 @@
+use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
+use crate::engine::system::vulkan::system::{GraphicsPipelineRenderPassInfo, VulkanSystem};
+use crate::engine::system::vulkan::textures::{
+    ImageSamplerMode, ImageSystem, PlaceholderImages, PlaceholderTextures, TextureId,
+    TextureManager,
+};
+use crate::engine::system::vulkan::utils::quad::QuadInstancer;
+use crate::engine::system::vulkan::wds::WriteDescriptorSetManager;
+use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError};
+use crate::shader_from_path;
+use bytemuck::{Pod, Zeroable};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::device::Device;
+use vulkano::image::Image;
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendAttachmentState};
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::rasterization::RasterizationState;
+use vulkano::pipeline::graphics::vertex_input::Vertex;
+use vulkano::pipeline::graphics::viewport::ViewportState;
+use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
+use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
+use vulkano::pipeline::{
+    DynamicState, GraphicsPipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
+};
+use vulkano::shader::EntryPoint;
+use vulkano::{Validated, VulkanError};
+
+/// This pipeline is used to stamp decals (scorch marks, blood, footprints) onto the terrain of 2d
+/// worlds. It is meant to be drawn after the terrain and before the entities, the decals
+/// themselves are managed by a [`DecalRing`].
+pub struct World2dDecalsPipeline {
+    pipeline: Arc<GraphicsPipeline>,
+    quads: QuadInstancer,
+    write_descriptors: Arc<WriteDescriptorSetManager>,
+    texture_manager: TextureManager<Self, 0>,
+    image_system: Arc<ImageSystem>,
+    placeholders: PlaceholderTextures<Self>,
+}
+
+impl TryFrom<&VulkanSystem> for World2dDecalsPipeline {
+    type Error = PipelineCreateError;
+
+    #[inline]
+    fn try_from(vs: &VulkanSystem) -> Result<Self, Self::Error> {
+        Self::new(
+            Arc::clone(vs.device()),
+            vs.graphics_pipeline_render_pass_info(),
+            vs.pipeline_cache().map(Arc::clone),
+            Arc::clone(vs.write_descriptor_set_manager()),
+            Arc::clone(vs.basic_buffers_manager()),
+            Arc::clone(vs.image_system()),
+            vs.placeholder_images(),
+        )
+    }
+}
+
+impl World2dDecalsPipeline {
+    pub fn new(
+        device: Arc<Device>,
+        render_pass_info: GraphicsPipelineRenderPassInfo,
+        cache: Option<Arc<PipelineCache>>,
+        write_descriptors: Arc<WriteDescriptorSetManager>,
+        buffers_manager: Arc<BasicBuffersManager>,
+        image_system: Arc<ImageSystem>,
+        placeholder_images: &PlaceholderImages,
+    ) -> Result<Self, PipelineCreateError> {
+        let pipeline = Self::create_pipeline(Arc::clone(&device), render_pass_info, cache)?;
+        let texture_manager = TextureManager::basic(
+            device,
+            &pipeline,
+            ImageSamplerMode::PixelPerfect,
+            Arc::clone(write_descriptors.descriptor_set_allocator()),
+            Arc::clone(image_system.deferred_destruction()),
+        )?;
+        let placeholders = PlaceholderTextures::prepare(placeholder_images, |image| {
+            texture_manager.prepare_texture(
+                image,
+                write_descriptors.get_required_descriptors(&pipeline.layout().set_layouts()[0]),
+            )
+        })?;
+        Ok(Self {
+            quads: QuadInstancer::new(buffers_manager, PipelineKind::World2dDecals)?,
+            write_descriptors,
+            texture_manager,
+            image_system,
+            placeholders,
+            pipeline,
+        })
+    }
+
+    fn create_pipeline(
+        device: Arc<Device>,
+        render_pass_info: GraphicsPipelineRenderPassInfo,
+        cache: Option<Arc<PipelineCache>>,
+    ) -> Result<Arc<GraphicsPipeline>, PipelineCreateError> {
+        let vs = Self::load_vertex_shader(Arc::clone(&device))?;
+        let fs = Self::load_fragment_shader(Arc::clone(&device))?;
+
+        let vertex_input_state = QuadInstancer::vertex_input_state::<DecalInstanceData>(&vs)?;
+
+        let stages = [
+            PipelineShaderStageCreateInfo::new(vs),
+            PipelineShaderStageCreateInfo::new(fs),
+        ];
+
+        let layout = PipelineLayout::new(
+            Arc::clone(&device),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+                .into_pipeline_layout_create_info(Arc::clone(&device))?,
+        )?;
+
+        Ok(GraphicsPipeline::new(
+            Arc::clone(&device),
+            cache,
+            GraphicsPipelineCreateInfo {
+                stages: stages.into_iter().collect(),
+                vertex_input_state: Some(vertex_input_state),
+                input_assembly_state: Some(InputAssemblyState {
+                    topology: PrimitiveTopology::TriangleList,
+                    ..InputAssemblyState::default()
+                }),
+                viewport_state: Some(ViewportState::default()),
+                rasterization_state: Some(RasterizationState::default()),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(render_pass_info.color_blend_state(
+                    ColorBlendAttachmentState {
+                        blend: Some(AttachmentBlend::alpha()),
+                        ..ColorBlendAttachmentState::default()
+                    },
+                )),
+                dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                    .into_iter()
+                    .collect(),
+                depth_stencil_state: render_pass_info.depth_stencil_state(),
+                subpass: Some(render_pass_info.into_subpass_type()),
+                ..GraphicsPipelineCreateInfo::layout(layout)
+            },
+        )?)
+    }
+
+    fn load_vertex_shader(device: Arc<Device>) -> Result<EntryPoint, ShaderLoadError> {
+        shader_from_path!(
+            device,
+            "vertex",
+            "src/engine/system/vulkan/world2d/decals/decals.vert"
+        )
+    }
+
+    fn load_fragment_shader(device: Arc<Device>) -> Result<EntryPoint, ShaderLoadError> {
+        shader_from_path!(
+            device,
+            "fragment",
+            "src/engine/system/vulkan/world2d/decals/decals.frag"
+        )
+    }
+
+    pub fn draw<P, I>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        texture: &TextureId<Self>,
+        decals: I,
+    ) -> Result<(), DrawError>
+    where
+        I: IntoIterator<Item = DecalInstanceData>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        if self.texture_manager.is_origin_of(texture) {
+            // the content is undefined until a deferred upload was processed
+            let Some(texture) = self.placeholders.resolve(&self.image_system, texture) else {
+                return Ok(());
+            };
+
+            self.quads.draw(
+                builder,
+                &self.pipeline,
+                Arc::clone(texture.descriptor()),
+                decals,
+            )
+        } else {
+            Err(DrawError::ForeignTexture)
+        }
+    }
+
+    #[inline]
+    pub fn placeholders(&self) -> &PlaceholderTextures<Self> {
+        &self.placeholders
+    }
+
+    pub fn prepare_texture(
+        &self,
+        image: Arc<Image>,
+    ) -> Result<TextureId<Self>, Validated<VulkanError>> {
+        self.texture_manager.prepare_texture(
+            image,
+            self.write_descriptors
+                .get_required_descriptors(&self.pipeline.layout().set_layouts()[0]),
+        )
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
+pub struct DecalInstanceData {
+    #[format(R32G32_SFLOAT)]
+    pub decal_pos: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    pub uv0: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    pub uv1: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    pub size: [f32; 2],
+    /// In radians
+    #[format(R32_SFLOAT)]
+    pub rotation: f32,
+    /// Multiplied with the color of the texture
+    #[format(R32G32B32A32_SFLOAT)]
+    pub color: [f32; 4],
+}
+
+/// A decal stamped onto the terrain at a world position.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Decal {
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+    /// In radians
+    pub rotation: f32,
+    pub uv0: [f32; 2],
+    pub uv1: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// Keeps the most recent decals. Once the capacity is reached, stamping a new decal replaces the
+/// oldest one, so that the cost of drawing the decals is bounded. Decals fade out over the
+/// `fade` duration before reaching their `lifetime`, or before being replaced once the ring is
+/// nearly full.
+#[derive(Debug, Clone)]
+pub struct DecalRing {
+    decals: VecDeque<(Decal, Duration)>,
+    capacity: usize,
+    lifetime: Option<Duration>,
+    fade: Duration,
+}
+
+impl DecalRing {
+    /// Decals are kept until they are replaced by newer ones.
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            decals: VecDeque::with_capacity(capacity),
+            capacity,
+            lifetime: None,
+            fade: Duration::from_secs(1),
+        }
+    }
+
+    /// Removes decals once they reach the given age.
+    #[inline]
+    pub fn with_lifetime(mut self, lifetime: Duration) -> Self {
+        self.lifetime = Some(lifetime);
+        self
+    }
+
+    #[inline]
+    pub fn with_fade(mut self, fade: Duration) -> Self {
+        self.fade = fade;
+        self
+    }
+
+    #[inline]
+    pub fn stamp(&mut self, decal: Decal) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.decals.len() >= self.capacity {
+            self.decals.pop_front();
+        }
+        self.decals.push_back((decal, Duration::ZERO));
+    }
+
+    /// Ages all decals and removes the expired ones.
+    pub fn advance(&mut self, delta: Duration) {
+        for (_, age) in &mut self.decals {
+            *age += delta;
+        }
+        if let Some(lifetime) = self.lifetime {
+            // the oldest decals are at the front
+            while self.decals.front().is_some_and(|(_, age)| *age >= lifetime) {
+                self.decals.pop_front();
+            }
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.decals.clear();
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.decals.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.decals.is_empty()
+    }
+
+    /// The instances to draw with the [`World2dDecalsPipeline`], from the oldest to the most
+    /// recent decal so that newer decals are drawn on top.
+    pub fn instances(&self) -> impl ExactSizeIterator<Item = DecalInstanceData> + '_ {
+        let fade = self.fade.as_secs_f32();
+        let len = self.decals.len();
+        self.decals
+            .iter()
+            .enumerate()
+            .map(move |(index, (decal, age))| {
+                let mut alpha = 1.0_f32;
+                if fade > 0.0 {
+                    if let Some(lifetime) = self.lifetime {
+                        let remaining = lifetime.saturating_sub(*age).as_secs_f32();
+                        alpha = alpha.min(remaining / fade);
+                    }
+                    // fade out the oldest decals of a full ring, one fade step per newer decal
+                    if len == self.capacity {
+                        let fade_slots = (self.capacity / 10).max(1);
+                        alpha = alpha.min((index + 1) as f32 / fade_slots as f32);
+                    }
+                }
+
+                let mut color = decal.color;
+                color[3] *= alpha.clamp(0.0, 1.0);
+                DecalInstanceData {
+                    decal_pos: decal.pos,
+                    uv0: decal.uv0,
+                    uv1: decal.uv1,
+                    size: decal.size,
+                    rotation: decal.rotation,
+                    color,
+                }
+            })
+    }
+}
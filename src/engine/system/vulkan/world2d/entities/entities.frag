@@ -1,11 +1,57 @@
 #version 450
 
 layout(location = 0) in vec2 in_uv;
+layout(location = 1) flat in vec4 in_uv_bounds;
+layout(location = 2) flat in vec4 in_outline_color;
+layout(location = 3) flat in vec2 in_outline_step;
+layout(location = 4) flat in float in_outline_width;
 
 layout(location = 0) out vec4 out_color;
 
 layout(binding = 0, set = 0) uniform sampler2D bound_texture;
+layout(binding = 202) uniform AmbientLight { vec4 color; } ambient;
+
+const int OUTLINE_DIRECTIONS = 16;
+const float TAU = 6.28318530718;
+
+// the alpha of the sprite, the neighbours in an atlas are not part of it
+float sprite_alpha(vec2 uv) {
+    if (any(lessThan(uv, in_uv_bounds.xy)) || any(greaterThan(uv, in_uv_bounds.zw))) {
+        return 0.0;
+    }
+    return texture(bound_texture, uv).a;
+}
 
 void main() {
-    out_color = texture(bound_texture, in_uv);
-}
\ No newline at end of file
+    if (in_outline_width <= 0.0) {
+        vec4 color = texture(bound_texture, in_uv);
+        out_color = vec4(color.rgb * ambient.color.rgb, color.a);
+        return;
+    }
+
+    vec4 color = vec4(0.0);
+    if (all(greaterThanEqual(in_uv, in_uv_bounds.xy)) && all(lessThanEqual(in_uv, in_uv_bounds.zw))) {
+        color = texture(bound_texture, in_uv);
+    }
+
+    // the silhouette expanded by the outline width: the nearest opaque pixel on rings around
+    // the fragment, one pixel apart
+    float outline = 0.0;
+    if (color.a < 1.0) {
+        for (float distance = 1.0; distance <= in_outline_width + 0.5 && outline < 1.0; distance += 1.0) {
+            float radius = min(distance, in_outline_width);
+            for (int i = 0; i < OUTLINE_DIRECTIONS; ++i) {
+                float angle = TAU * float(i) / float(OUTLINE_DIRECTIONS);
+                vec2 offset = vec2(cos(angle), sin(angle)) * radius * in_outline_step;
+                outline = max(outline, sprite_alpha(in_uv + offset));
+            }
+        }
+    }
+
+    vec4 sprite = vec4(color.rgb * ambient.color.rgb, color.a);
+    vec4 border = vec4(in_outline_color.rgb, in_outline_color.a * outline);
+    float alpha = sprite.a + border.a * (1.0 - sprite.a);
+    out_color = alpha > 0.0
+        ? vec4((sprite.rgb * sprite.a + border.rgb * border.a * (1.0 - sprite.a)) / alpha, alpha)
+        : vec4(0.0);
+}
@@ -8,20 +8,42 @@ layout(location = 1) in vec2 entity_pos;
 layout(location = 2) in vec2 uv0;
 layout(location = 3) in vec2 uv1;
 layout(location = 4) in float size;
+layout(location = 5) in vec4 outline_color;
+layout(location = 6) in float outline_width;
 
 layout(binding = 101) uniform WindowProperties { vec2 screen_size; } window;
 layout(binding = 201) uniform WorldView2d { vec2 position; float zoom; } view;
 
+// keep in sync with EntityInstanceData::MAX_OUTLINE_WIDTH
+const float MAX_OUTLINE_WIDTH = 8.0;
+
 layout(location = 0) out vec2 out_uv;
+layout(location = 1) flat out vec4 out_uv_bounds;
+layout(location = 2) flat out vec4 out_outline_color;
+layout(location = 3) flat out vec2 out_outline_step;
+layout(location = 4) flat out float out_outline_width;
 
 void main() {
+    float width = clamp(outline_width, 0.0, MAX_OUTLINE_WIDTH);
+    if (outline_color.a <= 0.0) {
+        width = 0.0;
+    }
+
+    // grow the quad by the outline on each side, the uv coordinates are extrapolated accordingly
+    float pixels = max(view.zoom * size, 1.0);
+    vec2 expanded = pos * ((pixels + 2.0 * width) / pixels);
+
     gl_Position = vec4(
-    2.0 * (((view.zoom * ((pos.x * size) + entity_pos.x - view.position.x))) / window.screen_size.x),
-    2.0 * (((view.zoom * ((pos.y * size) + entity_pos.y - view.position.y))) / window.screen_size.y),
+    2.0 * (((view.zoom * ((expanded.x * size) + entity_pos.x - view.position.x))) / window.screen_size.x),
+    2.0 * (((view.zoom * ((expanded.y * size) + entity_pos.y - view.position.y))) / window.screen_size.y),
     0.0,
     1.0
     );
 
 
-    out_uv = mix(uv0, uv1, pos + 0.5);
-}
\ No newline at end of file
+    out_uv = mix(uv0, uv1, expanded + 0.5);
+    out_uv_bounds = vec4(min(uv0, uv1), max(uv0, uv1));
+    out_outline_color = outline_color;
+    out_outline_step = (uv1 - uv0) / pixels;
+    out_outline_width = width;
+}
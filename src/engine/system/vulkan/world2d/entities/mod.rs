@@ -1,29 +1,29 @@
 use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
 use crate::engine::system::vulkan::system::{GraphicsPipelineRenderPassInfo, VulkanSystem};
-use crate::engine::system::vulkan::textures::{ImageSamplerMode, TextureId, TextureManager};
+use crate::engine::system::vulkan::textures::{
+    ImageSamplerMode, ImageSystem, PlaceholderImages, PlaceholderTextures, TextureId,
+    TextureManager,
+};
+use crate::engine::system::vulkan::utils::quad::QuadInstancer;
 use crate::engine::system::vulkan::wds::WriteDescriptorSetManager;
 use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError};
 use crate::shader_from_path;
 use bytemuck::{Pod, Zeroable};
 use std::sync::Arc;
-use vulkano::buffer::{IndexBuffer, Subbuffer};
 use vulkano::command_buffer::AutoCommandBufferBuilder;
 use vulkano::device::Device;
 use vulkano::image::Image;
 use vulkano::pipeline::cache::PipelineCache;
-use vulkano::pipeline::graphics::color_blend::{
-    AttachmentBlend, ColorBlendAttachmentState, ColorBlendState,
-};
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendAttachmentState};
 use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
-use vulkano::pipeline::graphics::multisample::MultisampleState;
 use vulkano::pipeline::graphics::rasterization::RasterizationState;
-use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition};
+use vulkano::pipeline::graphics::vertex_input::Vertex;
 use vulkano::pipeline::graphics::viewport::ViewportState;
 use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
 use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
 use vulkano::pipeline::{
-    DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
-    PipelineShaderStageCreateInfo,
+    DynamicState, GraphicsPipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
 };
 use vulkano::shader::EntryPoint;
 use vulkano::{Validated, VulkanError};
@@ -33,11 +33,11 @@ use vulkano::{Validated, VulkanError};
 #[derive()]
 pub struct World2dEntitiesPipeline {
     pipeline: Arc<GraphicsPipeline>,
-    buffers_manager: Arc<BasicBuffersManager>,
-    quad_index_buffer: IndexBuffer,
-    quad_vertex_buffer: Subbuffer<[Vertex2d]>,
+    quads: QuadInstancer,
     write_descriptors: Arc<WriteDescriptorSetManager>,
     texture_manager: TextureManager<Self, 0>,
+    image_system: Arc<ImageSystem>,
+    placeholders: PlaceholderTextures<Self>,
 }
 
 impl TryFrom<&VulkanSystem> for World2dEntitiesPipeline {
@@ -51,6 +51,8 @@ impl TryFrom<&VulkanSystem> for World2dEntitiesPipeline {
             vs.pipeline_cache().map(Arc::clone),
             Arc::clone(vs.write_descriptor_set_manager()),
             Arc::clone(vs.basic_buffers_manager()),
+            Arc::clone(vs.image_system()),
+            vs.placeholder_images(),
         )
     }
 }
@@ -62,27 +64,29 @@ impl World2dEntitiesPipeline {
         cache: Option<Arc<PipelineCache>>,
         write_descriptors: Arc<WriteDescriptorSetManager>,
         buffers_manager: Arc<BasicBuffersManager>,
+        image_system: Arc<ImageSystem>,
+        placeholder_images: &PlaceholderImages,
     ) -> Result<Self, PipelineCreateError> {
         let pipeline = Self::create_pipeline(Arc::clone(&device), render_pass_info, cache)?;
+        let texture_manager = TextureManager::basic(
+            device,
+            &pipeline,
+            ImageSamplerMode::PixelPerfect,
+            Arc::clone(write_descriptors.descriptor_set_allocator()),
+            Arc::clone(image_system.deferred_destruction()),
+        )?;
+        let placeholders = PlaceholderTextures::prepare(placeholder_images, |image| {
+            texture_manager.prepare_texture(
+                image,
+                write_descriptors.get_required_descriptors(&pipeline.layout().set_layouts()[0]),
+            )
+        })?;
         Ok(Self {
-            quad_index_buffer: buffers_manager
-                .create_index_buffer([0, 1, 2, 2, 3, 0])?
-                .into(),
-            quad_vertex_buffer: buffers_manager
-                .create_vertex_buffer(vec![
-                    Vertex2d { pos: [-0.5, -0.5] },
-                    Vertex2d { pos: [0.5, -0.5] },
-                    Vertex2d { pos: [0.5, 0.5] },
-                    Vertex2d { pos: [-0.5, 0.5] },
-                ])?
-                .into(),
+            quads: QuadInstancer::new(buffers_manager, PipelineKind::World2dEntities)?,
             write_descriptors,
-            buffers_manager,
-            texture_manager: TextureManager::basic(
-                device,
-                &pipeline,
-                ImageSamplerMode::PixelPerfect,
-            )?,
+            texture_manager,
+            image_system,
+            placeholders,
             pipeline,
         })
     }
@@ -95,8 +99,7 @@ impl World2dEntitiesPipeline {
         let vs = Self::load_vertex_shader(Arc::clone(&device))?;
         let fs = Self::load_fragment_shader(Arc::clone(&device))?;
 
-        let vertex_input_state = [Vertex2d::per_vertex(), EntityInstanceData::per_instance()]
-            .definition(&vs.info().input_interface)?;
+        let vertex_input_state = QuadInstancer::vertex_input_state::<EntityInstanceData>(&vs)?;
 
         let stages = [
             PipelineShaderStageCreateInfo::new(vs),
@@ -121,18 +124,17 @@ impl World2dEntitiesPipeline {
                 }),
                 viewport_state: Some(ViewportState::default()),
                 rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState {
-                    rasterization_samples: render_pass_info.rasterization_samples(),
-                    ..MultisampleState::default()
-                }),
-                color_blend_state: Some(ColorBlendState::with_attachment_states(
-                    render_pass_info.num_color_attachments(),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(render_pass_info.color_blend_state(
                     ColorBlendAttachmentState {
                         blend: Some(AttachmentBlend::alpha()),
                         ..ColorBlendAttachmentState::default()
                     },
                 )),
-                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                    .into_iter()
+                    .collect(),
+                depth_stencil_state: render_pass_info.depth_stencil_state(),
                 subpass: Some(render_pass_info.into_subpass_type()),
                 ..GraphicsPipelineCreateInfo::layout(layout)
             },
@@ -166,33 +168,27 @@ impl World2dEntitiesPipeline {
         I::IntoIter: ExactSizeIterator,
     {
         if self.texture_manager.is_origin_of(texture) {
-            let vertex_buffer = self.buffers_manager.create_vertex_buffer(tiles)?;
-            let instance_count = vertex_buffer.len() as u32;
-
-            builder
-                .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
-                .bind_descriptor_sets(
-                    PipelineBindPoint::Graphics,
-                    Arc::clone(&self.pipeline.layout()),
-                    0,
-                    Arc::clone(&texture.0.descriptor),
-                )?
-                .bind_index_buffer(self.quad_index_buffer.clone())?
-                .bind_vertex_buffers(
-                    0,
-                    [
-                        self.quad_vertex_buffer.as_bytes().clone(),
-                        vertex_buffer.into_bytes(),
-                    ],
-                )?
-                .draw_indexed(6, instance_count, 0, 0, 0)?;
-
-            Ok(())
+            // the content is undefined until a deferred upload was processed
+            let Some(texture) = self.placeholders.resolve(&self.image_system, texture) else {
+                return Ok(());
+            };
+
+            self.quads.draw(
+                builder,
+                &self.pipeline,
+                Arc::clone(texture.descriptor()),
+                tiles,
+            )
         } else {
             todo!()
         }
     }
 
+    #[inline]
+    pub fn placeholders(&self) -> &PlaceholderTextures<Self> {
+        &self.placeholders
+    }
+
     pub fn prepare_texture(
         &self,
         image: Arc<Image>,
@@ -206,14 +202,11 @@ impl World2dEntitiesPipeline {
 }
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
-pub struct Vertex2d {
-    #[format(R32G32_SFLOAT)]
-    pos: [f32; 2],
-}
-
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
+#[derive(Debug, Clone, Copy, PartialEq, Zeroable, Pod, Vertex)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
 pub struct EntityInstanceData {
     #[format(R32G32_SFLOAT)]
     pub entity_pos: [f32; 2],
@@ -223,4 +216,48 @@ pub struct EntityInstanceData {
     pub uv1: [f32; 2],
     #[format(R32_SFLOAT)]
     pub size: f32,
+    /// The color of the outline, which is not affected by the ambient light
+    #[format(R32G32B32A32_SFLOAT)]
+    pub outline_color: [f32; 4],
+    /// The width of the outline around the silhouette of the entity in pixels, `0.0` to draw
+    /// no outline
+    #[format(R32_SFLOAT)]
+    pub outline_width: f32,
+}
+
+impl EntityInstanceData {
+    /// The maximum [`EntityInstanceData::outline_width`], wider outlines are clamped
+    pub const MAX_OUTLINE_WIDTH: f32 = 8.0;
+
+    #[inline]
+    pub fn new(entity_pos: [f32; 2], uv0: [f32; 2], uv1: [f32; 2], size: f32) -> Self {
+        Self {
+            entity_pos,
+            uv0,
+            uv1,
+            size,
+            outline_color: [0.0; 4],
+            outline_width: 0.0,
+        }
+    }
+
+    /// Outlines the silhouette of the entity (the non-transparent pixels of its texture), like to
+    /// highlight a selected or hovered unit.
+    #[inline]
+    pub fn with_outline(mut self, color: [f32; 4], width: f32) -> Self {
+        self.outline_color = color;
+        self.outline_width = width;
+        self
+    }
+
+    #[inline]
+    pub fn without_outline(mut self) -> Self {
+        self.outline_width = 0.0;
+        self
+    }
+
+    #[inline]
+    pub fn has_outline(&self) -> bool {
+        self.outline_width > 0.0 && self.outline_color[3] > 0.0
+    }
 }
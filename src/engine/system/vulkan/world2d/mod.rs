@@ -1,2 +1,3 @@
+pub mod decals;
 pub mod entities;
 pub mod terrain;
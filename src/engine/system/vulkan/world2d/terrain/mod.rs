@@ -1,29 +1,29 @@
 use crate::engine::system::vulkan::buffers::BasicBuffersManager;
+use crate::engine::system::vulkan::draw_filter::PipelineKind;
 use crate::engine::system::vulkan::system::{GraphicsPipelineRenderPassInfo, VulkanSystem};
-use crate::engine::system::vulkan::textures::{ImageSamplerMode, TextureId, TextureManager};
+use crate::engine::system::vulkan::textures::{
+    ImageSamplerMode, ImageSystem, PlaceholderImages, PlaceholderTextures, TextureId,
+    TextureManager,
+};
+use crate::engine::system::vulkan::utils::quad::QuadInstancer;
 use crate::engine::system::vulkan::wds::WriteDescriptorSetManager;
 use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ShaderLoadError};
 use crate::shader_from_path;
 use bytemuck::{Pod, Zeroable};
 use std::sync::Arc;
-use vulkano::buffer::{IndexBuffer, Subbuffer};
 use vulkano::command_buffer::AutoCommandBufferBuilder;
 use vulkano::device::Device;
 use vulkano::image::Image;
 use vulkano::pipeline::cache::PipelineCache;
-use vulkano::pipeline::graphics::color_blend::{
-    AttachmentBlend, ColorBlendAttachmentState, ColorBlendState,
-};
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, ColorBlendAttachmentState};
 use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
-use vulkano::pipeline::graphics::multisample::MultisampleState;
 use vulkano::pipeline::graphics::rasterization::RasterizationState;
-use vulkano::pipeline::graphics::vertex_input::{Vertex, VertexDefinition};
+use vulkano::pipeline::graphics::vertex_input::Vertex;
 use vulkano::pipeline::graphics::viewport::ViewportState;
 use vulkano::pipeline::graphics::GraphicsPipelineCreateInfo;
 use vulkano::pipeline::layout::PipelineDescriptorSetLayoutCreateInfo;
 use vulkano::pipeline::{
-    DynamicState, GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
-    PipelineShaderStageCreateInfo,
+    DynamicState, GraphicsPipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
 };
 use vulkano::shader::EntryPoint;
 use vulkano::{Validated, VulkanError};
@@ -34,11 +34,11 @@ use vulkano::{Validated, VulkanError};
 #[derive()]
 pub struct World2dTerrainPipeline {
     pipeline: Arc<GraphicsPipeline>,
-    buffers_manager: Arc<BasicBuffersManager>,
-    quad_index_buffer: IndexBuffer,
-    quad_vertex_buffer: Subbuffer<[Vertex2d]>,
+    quads: QuadInstancer,
     write_descriptors: Arc<WriteDescriptorSetManager>,
     texture_manager: TextureManager<Self, 0>,
+    image_system: Arc<ImageSystem>,
+    placeholders: PlaceholderTextures<Self>,
 }
 
 impl TryFrom<&VulkanSystem> for World2dTerrainPipeline {
@@ -52,6 +52,8 @@ impl TryFrom<&VulkanSystem> for World2dTerrainPipeline {
             vs.pipeline_cache().map(Arc::clone),
             Arc::clone(vs.write_descriptor_set_manager()),
             Arc::clone(vs.basic_buffers_manager()),
+            Arc::clone(vs.image_system()),
+            vs.placeholder_images(),
         )
     }
 }
@@ -63,27 +65,29 @@ impl World2dTerrainPipeline {
         cache: Option<Arc<PipelineCache>>,
         write_descriptors: Arc<WriteDescriptorSetManager>,
         buffers_manager: Arc<BasicBuffersManager>,
+        image_system: Arc<ImageSystem>,
+        placeholder_images: &PlaceholderImages,
     ) -> Result<Self, PipelineCreateError> {
         let pipeline = Self::create_pipeline(Arc::clone(&device), render_pass_info, cache)?;
+        let texture_manager = TextureManager::basic(
+            device,
+            &pipeline,
+            ImageSamplerMode::PixelPerfect,
+            Arc::clone(write_descriptors.descriptor_set_allocator()),
+            Arc::clone(image_system.deferred_destruction()),
+        )?;
+        let placeholders = PlaceholderTextures::prepare(placeholder_images, |image| {
+            texture_manager.prepare_texture(
+                image,
+                write_descriptors.get_required_descriptors(&pipeline.layout().set_layouts()[0]),
+            )
+        })?;
         Ok(Self {
-            quad_index_buffer: buffers_manager
-                .create_index_buffer([0, 1, 2, 2, 3, 0])?
-                .into(),
-            quad_vertex_buffer: buffers_manager
-                .create_vertex_buffer(vec![
-                    Vertex2d { pos: [-0.5, -0.5] },
-                    Vertex2d { pos: [0.5, -0.5] },
-                    Vertex2d { pos: [0.5, 0.5] },
-                    Vertex2d { pos: [-0.5, 0.5] },
-                ])?
-                .into(),
+            quads: QuadInstancer::new(buffers_manager, PipelineKind::World2dTerrain)?,
             write_descriptors,
-            buffers_manager,
-            texture_manager: TextureManager::basic(
-                device,
-                &pipeline,
-                ImageSamplerMode::PixelPerfect,
-            )?,
+            texture_manager,
+            image_system,
+            placeholders,
             pipeline,
         })
     }
@@ -96,8 +100,7 @@ impl World2dTerrainPipeline {
         let vs = Self::load_vertex_shader(Arc::clone(&device))?;
         let fs = Self::load_fragment_shader(Arc::clone(&device))?;
 
-        let vertex_input_state = [Vertex2d::per_vertex(), InstanceData::per_instance()]
-            .definition(&vs.info().input_interface)?;
+        let vertex_input_state = QuadInstancer::vertex_input_state::<InstanceData>(&vs)?;
 
         let stages = [
             PipelineShaderStageCreateInfo::new(vs),
@@ -122,18 +125,17 @@ impl World2dTerrainPipeline {
                 }),
                 viewport_state: Some(ViewportState::default()),
                 rasterization_state: Some(RasterizationState::default()),
-                multisample_state: Some(MultisampleState {
-                    rasterization_samples: render_pass_info.rasterization_samples(),
-                    ..MultisampleState::default()
-                }),
-                color_blend_state: Some(ColorBlendState::with_attachment_states(
-                    render_pass_info.num_color_attachments(),
+                multisample_state: Some(render_pass_info.multisample_state()),
+                color_blend_state: Some(render_pass_info.color_blend_state(
                     ColorBlendAttachmentState {
                         blend: Some(AttachmentBlend::alpha()),
                         ..ColorBlendAttachmentState::default()
                     },
                 )),
-                dynamic_state: [DynamicState::Viewport].into_iter().collect(),
+                dynamic_state: [DynamicState::Viewport, DynamicState::Scissor]
+                    .into_iter()
+                    .collect(),
+                depth_stencil_state: render_pass_info.depth_stencil_state(),
                 subpass: Some(render_pass_info.into_subpass_type()),
                 ..GraphicsPipelineCreateInfo::layout(layout)
             },
@@ -167,33 +169,27 @@ impl World2dTerrainPipeline {
         I::IntoIter: ExactSizeIterator,
     {
         if self.texture_manager.is_origin_of(texture) {
-            let vertex_buffer = self.buffers_manager.create_vertex_buffer(tiles)?;
-            let instance_count = vertex_buffer.len() as u32;
-
-            builder
-                .bind_pipeline_graphics(Arc::clone(&self.pipeline))?
-                .bind_descriptor_sets(
-                    PipelineBindPoint::Graphics,
-                    Arc::clone(&self.pipeline.layout()),
-                    0,
-                    Arc::clone(&texture.0.descriptor),
-                )?
-                .bind_index_buffer(self.quad_index_buffer.clone())?
-                .bind_vertex_buffers(
-                    0,
-                    [
-                        self.quad_vertex_buffer.as_bytes().clone(),
-                        vertex_buffer.into_bytes(),
-                    ],
-                )?
-                .draw_indexed(6, instance_count, 0, 0, 0)?;
-
-            Ok(())
+            // the content is undefined until a deferred upload was processed
+            let Some(texture) = self.placeholders.resolve(&self.image_system, texture) else {
+                return Ok(());
+            };
+
+            self.quads.draw(
+                builder,
+                &self.pipeline,
+                Arc::clone(texture.descriptor()),
+                tiles,
+            )
         } else {
             todo!()
         }
     }
 
+    #[inline]
+    pub fn placeholders(&self) -> &PlaceholderTextures<Self> {
+        &self.placeholders
+    }
+
     pub fn prepare_texture(
         &self,
         image: Arc<Image>,
@@ -207,14 +203,11 @@ impl World2dTerrainPipeline {
 }
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
-pub struct Vertex2d {
-    #[format(R32G32_SFLOAT)]
-    pos: [f32; 2],
-}
-
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Zeroable, Pod, Vertex)]
+#[derive(Debug, Clone, Copy, PartialEq, Zeroable, Pod, Vertex)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
 pub struct InstanceData {
     #[format(R32G32_SFLOAT)]
     pub tile_pos: [f32; 2],
@@ -225,3 +218,20 @@ pub struct InstanceData {
     #[format(R32_SFLOAT)]
     pub shading: f32,
 }
+
+/// The tiles of a part of the terrain that share a texture (usually an atlas of all tile types),
+/// to be drawn at once, for example through [`BufferedCanvasLayer::draw_terrain`].
+///
+/// [`BufferedCanvasLayer::draw_terrain`]: crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer::draw_terrain
+#[derive(Clone)]
+pub struct TerrainChunk {
+    pub texture: TextureId<World2dTerrainPipeline>,
+    pub tiles: Vec<InstanceData>,
+}
+
+impl TerrainChunk {
+    #[inline]
+    pub fn new(texture: TextureId<World2dTerrainPipeline>, tiles: Vec<InstanceData>) -> Self {
+        Self { texture, tiles }
+    }
+}
@@ -6,8 +6,9 @@ layout(location = 1) in float shading;
 layout(location = 0) out vec4 out_color;
 
 layout(binding = 0, set = 0) uniform sampler2D bound_texture;
+layout(binding = 202) uniform AmbientLight { vec4 color; } ambient;
 
 void main() {
     vec4 color = texture(bound_texture, in_uv);
-    out_color = vec4(color.rgb * (1.0 - shading), color.a);
+    out_color = vec4(color.rgb * (1.0 - shading) * ambient.color.rgb, color.a);
 }
\ No newline at end of file
@@ -0,0 +1,717 @@
+//! Splits the [`Engine`] into an update thread and a render thread, to improve the frame pacing
+//! of games whose update takes a considerable amount of the frame time: while the render thread
+//! records and submits the commands of a frame, the update thread already polls the SDL events
+//! and updates the game for the next frame.
+//!
+//! The threads exchange two frame packets of a game defined type through channels: the update
+//! thread fills one packet with everything needed to render a frame (like the positions of the
+//! sprites), while the render thread renders from the other one. Once rendered, a packet is sent
+//! back to be filled again. The update thread waits for a packet if the render thread falls
+//! behind by more than one frame.
+//!
+//! ```no_run
+//! use hotrod::engine::threaded::ThreadedEngine;
+//! use hotrod::engine::Engine;
+//!
+//! #[derive(Default)]
+//! struct Frame {
+//!     player: [f32; 2],
+//! }
+//!
+//! let mut engine: ThreadedEngine<Frame> = Engine::default()
+//!     .into_threaded(|context, frame: &mut Frame| {
+//!         // record the commands of the frame with the `context.pipelines`
+//!         let _ = (context, frame.player);
+//!         Vec::new()
+//!     })
+//!     .unwrap();
+//!
+//! loop {
+//!     engine
+//!         .update(|context| {
+//!             context.packet.player[0] += context.delta.as_secs_f32();
+//!         })
+//!         .unwrap();
+//!     engine.delay();
+//! }
+//! ```
+//!
+//! The render thread owns the [`VulkanSystem`] and always records the frames into secondary
+//! command buffers. The events, the input, the plugins and the window are handled exactly like
+//! by the [`Engine`], the render thread draws the [`VirtualCursor`] on top of the commands of the
+//! `renderer` and shows the ambient light within the flash limit of the
+//! [`AccessibilityOptions`]. Egui, the dynamic resolution and the rendering of plugins are not
+//! available on this path: the egui contexts of the [`Engine`] are dropped by
+//! [`Engine::into_threaded`], plugins still receive the events and the [`RenderStats`].
+//!
+//! [`VulkanSystem`]: crate::engine::system::vulkan::system::VulkanSystem
+
+use crate::engine::accessibility::AccessibilityOptions;
+use crate::engine::builder::EngineConfig;
+use crate::engine::event::EngineEvent;
+use crate::engine::input::{Haptics, TextInput, VirtualCursor};
+use crate::engine::parts::sdl::{DisplayInfo, WindowBadge, WindowPlacement};
+use crate::engine::plugin::EnginePlugin;
+use crate::engine::system::commands::RecordedCommands;
+use crate::engine::system::fps::FpsManager;
+use crate::engine::system::input::ActionMap;
+use crate::engine::system::vulkan::desc::binding_202_ambient_light::AmbientLight;
+use crate::engine::system::vulkan::pipelines::VulkanPipelines;
+use crate::engine::system::vulkan::system::VulkanSystem;
+use crate::engine::system::vulkan::textured::TexturedQuad;
+use crate::engine::system::vulkan::DrawError;
+use crate::engine::update::{Lifecycle, UpdateState};
+use crate::engine::{Engine, Error, RenderContext, RenderResponse, RenderStats};
+use crate::support::image::RawRgbaImage;
+use crate::support::world2d::spatial_audio::SpatialAudio;
+use crossbeam::channel::{Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use vulkano::instance::Instance;
+use vulkano::swapchain::Surface;
+
+/// The frame packets that are exchanged between the threads
+const PACKETS: usize = 2;
+
+enum RenderCommand<P> {
+    Frame(FrameSubmission<P>),
+    RecreateSwapchain,
+    SetPixelsPerPoint(f32),
+    SetAmbientLight(AmbientLight),
+    Suspend,
+    Resume {
+        surface: Arc<Surface>,
+        width: u32,
+        height: u32,
+    },
+    Shutdown,
+}
+
+struct FrameSubmission<P> {
+    packet: P,
+    frame: u64,
+    delta: Duration,
+    elapsed: Duration,
+    width: u32,
+    height: u32,
+    /// The [`VirtualCursor`] in pixels of the window, drawn on top of the frame
+    cursor: Option<TexturedQuad>,
+}
+
+/// The update thread half of an [`Engine`] that was split with [`Engine::into_threaded`], see the
+/// [module documentation](self).
+pub struct ThreadedEngine<P> {
+    commands: Sender<RenderCommand<P>>,
+    /// The packets that were rendered and can be filled again
+    free_packets: Receiver<P>,
+    stats: Receiver<RenderStats>,
+    /// Joined before the window is dropped
+    render_thread: Option<JoinHandle<()>>,
+    /// To create a new surface in [`ThreadedEngine::resume`]
+    instance: Arc<Instance>,
+    /// Shared with the render thread, for the placeholder texture of the [`VirtualCursor`]
+    pipelines: Arc<VulkanPipelines>,
+    suspended: bool,
+    /// Shared with the [`Engine`], drop last for the window and the steam client
+    state: UpdateState,
+}
+
+impl<P: Default + Send + 'static> ThreadedEngine<P> {
+    /// See [`Engine::into_threaded`]
+    pub(crate) fn spawn<R>(engine: Engine, renderer: R) -> Result<Self, Error>
+    where
+        R: FnMut(RenderContext, &mut P) -> Vec<RecordedCommands> + Send + 'static,
+    {
+        let (command_sender, command_receiver) = crossbeam::channel::unbounded();
+        let (packet_sender, packet_receiver) = crossbeam::channel::bounded(PACKETS);
+        let (stats_sender, stats_receiver) = crossbeam::channel::unbounded();
+
+        for _ in 0..PACKETS {
+            let _ = packet_sender.send(P::default());
+        }
+
+        #[cfg(feature = "ui-egui")]
+        if !engine.egui_contexts.is_empty() {
+            warn!(
+                "Dropping {} egui contexts, egui is not rendered by the threaded engine",
+                engine.egui_contexts.len()
+            );
+        }
+
+        let instance = Arc::clone(engine.vulkan_system.device().instance());
+        let pipelines = Arc::clone(&engine.vulkan_pipelines);
+        let render_thread = RenderThread {
+            vulkan_system: engine.vulkan_system,
+            pipelines: engine.vulkan_pipelines,
+            #[cfg(feature = "ttf-font-renderer")]
+            font_renderer: engine.font_renderer,
+            renderer,
+            commands: command_receiver,
+            free_packets: packet_sender,
+            stats: stats_sender,
+        };
+
+        let render_thread = std::thread::Builder::new()
+            .name("RenderThread".to_string())
+            .spawn(move || render_thread.run())
+            .map_err(Error::RenderThreadSpawnError)?;
+
+        Ok(Self {
+            commands: command_sender,
+            free_packets: packet_receiver,
+            stats: stats_receiver,
+            render_thread: Some(render_thread),
+            instance,
+            pipelines,
+            suspended: false,
+            state: engine.state,
+        })
+    }
+
+    /// Like [`Engine::update`], but the frame is not rendered within the callback: the
+    /// [`ThreadedUpdateContext::packet`] is sent to the render thread once the callback returns.
+    /// Waits for a free packet if the render thread is more than one frame behind.
+    pub fn update<T>(
+        &mut self,
+        f: impl FnOnce(ThreadedUpdateContext<P>) -> T,
+    ) -> Result<RenderResponse<T>, Error> {
+        let start = Instant::now();
+        self.receive_render_stats();
+        let events = self.poll_events();
+        let (width, height) = self.state.sdl.window.vulkan_drawable_size();
+        let (delta, elapsed) = self.state.advance_clock(start);
+
+        if let Some(light) = self.state.before_frame(delta) {
+            self.send(RenderCommand::SetAmbientLight(light));
+        }
+
+        let mut packet = self
+            .free_packets
+            .recv()
+            .map_err(|_| Error::RenderThreadTerminated)?;
+
+        let frame = self.state.frame;
+        let data = f(ThreadedUpdateContext {
+            engine: self,
+            packet: &mut packet,
+            events,
+            frame,
+            delta,
+            elapsed,
+            width,
+            height,
+            start,
+        });
+
+        let cursor = self.state.virtual_cursor.as_ref().and_then(|cursor| {
+            cursor.quad(
+                self.pipelines.texture.placeholders(),
+                self.state.sdl.drawable_scale(),
+            )
+        });
+
+        self.commands
+            .send(RenderCommand::Frame(FrameSubmission {
+                packet,
+                frame,
+                delta,
+                elapsed,
+                width,
+                height,
+                cursor,
+            }))
+            .map_err(|_| Error::RenderThreadTerminated)?;
+
+        self.state.after_frame();
+
+        Ok(RenderResponse {
+            data,
+            start,
+            duration: start.elapsed(),
+        })
+    }
+}
+
+impl<P> ThreadedEngine<P> {
+    fn poll_events(&mut self) -> Vec<EngineEvent> {
+        let polled = self.state.poll_events();
+
+        for lifecycle in &polled.lifecycle {
+            match lifecycle {
+                Lifecycle::Suspend => self.suspend(),
+                Lifecycle::Resume => {
+                    if let Err(e) = self.resume() {
+                        error!("Failed to resume rendering: {e}");
+                    }
+                }
+            }
+        }
+
+        if polled.toggle_fullscreen {
+            self.set_fullscreen(!self.state.sdl.window_maximized);
+        }
+        if polled.resized {
+            self.send(RenderCommand::RecreateSwapchain);
+        }
+        if let Some(refresh_rate) = polled.refresh_rate {
+            self.set_fps(refresh_rate);
+        }
+        if polled.display_changed {
+            self.send(RenderCommand::SetPixelsPerPoint(
+                self.state.sdl.pixels_per_point(),
+            ));
+        }
+
+        polled.events
+    }
+
+    /// Passes the [`RenderStats`] of the frames the render thread finished since the last call
+    /// to the plugins.
+    fn receive_render_stats(&mut self) {
+        for stats in self.stats.try_iter() {
+            self.state.on_render_stats(stats);
+        }
+    }
+
+    #[inline]
+    fn send(&self, command: RenderCommand<P>) {
+        if self.commands.send(command).is_err() {
+            error!("Failed to send a command to the terminated render thread");
+        }
+    }
+
+    /// See [`Engine::suspend`], the render thread waits for the GPU before releasing the
+    /// swapchain.
+    pub fn suspend(&mut self) {
+        self.suspended = true;
+        self.send(RenderCommand::Suspend);
+        self.state.pause_audio();
+    }
+
+    /// See [`Engine::resume`]
+    pub fn resume(&mut self) -> Result<(), Error> {
+        if !self.suspended {
+            return Ok(());
+        }
+
+        // SAFETY: the window outlives the render thread, see `ThreadedEngine::drop`
+        let surface =
+            unsafe { Surface::from_window_ref(Arc::clone(&self.instance), &self.state.sdl.window) }
+                .map_err(|e| Error::SdlCreateVulkanSurfaceError(e.to_string()))?;
+        let (width, height) = self.state.sdl.window.vulkan_drawable_size();
+        self.send(RenderCommand::Resume {
+            surface,
+            width,
+            height,
+        });
+        self.suspended = false;
+        self.state.resume_audio();
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// See [`Engine::is_terminating`]
+    #[inline]
+    pub fn is_terminating(&self) -> bool {
+        self.state.terminating
+    }
+
+    /// The number of the frame that is updated next.
+    #[inline]
+    pub fn frame(&self) -> u64 {
+        self.state.frame
+    }
+
+    /// The [`RenderStats`] of the last frame the render thread finished, which lags behind the
+    /// [`ThreadedEngine::frame`] by one or two frames.
+    #[inline]
+    pub fn render_stats(&self) -> RenderStats {
+        self.state.render_stats
+    }
+
+    #[inline]
+    pub fn plugin<T: EnginePlugin>(&self) -> Option<&T> {
+        self.state.plugin()
+    }
+
+    #[inline]
+    pub fn plugin_mut<T: EnginePlugin>(&mut self) -> Option<&mut T> {
+        self.state.plugin_mut()
+    }
+
+    #[inline]
+    pub fn haptics(&self) -> &Haptics {
+        &self.state.haptics
+    }
+
+    #[inline]
+    pub fn haptics_mut(&mut self) -> &mut Haptics {
+        &mut self.state.haptics
+    }
+
+    /// See [`Engine::action_map`]
+    #[inline]
+    pub fn action_map(&self) -> &ActionMap {
+        &self.state.action_map
+    }
+
+    #[inline]
+    pub fn action_map_mut(&mut self) -> &mut ActionMap {
+        &mut self.state.action_map
+    }
+
+    /// See [`Engine::spatial_audio`]
+    #[inline]
+    pub fn spatial_audio(&self) -> &SpatialAudio {
+        &self.state.spatial_audio
+    }
+
+    #[inline]
+    pub fn spatial_audio_mut(&mut self) -> &mut SpatialAudio {
+        &mut self.state.spatial_audio
+    }
+
+    #[inline]
+    pub fn text_input(&self) -> &TextInput {
+        &self.state.text_input
+    }
+
+    /// See [`EngineBuilder::with_virtual_cursor`]
+    ///
+    /// [`EngineBuilder::with_virtual_cursor`]: crate::engine::builder::EngineBuilder::with_virtual_cursor
+    #[inline]
+    pub fn virtual_cursor(&self) -> Option<&VirtualCursor> {
+        self.state.virtual_cursor.as_ref()
+    }
+
+    #[inline]
+    pub fn virtual_cursor_mut(&mut self) -> Option<&mut VirtualCursor> {
+        self.state.virtual_cursor.as_mut()
+    }
+
+    #[inline]
+    #[cfg(feature = "audio")]
+    pub fn audio_mut(&mut self) -> Option<&mut crate::engine::system::audio::AudioSystem> {
+        self.state.audio.as_mut()
+    }
+
+    #[inline]
+    #[cfg(feature = "steamworks")]
+    pub fn steam_mut(&mut self) -> Option<&mut crate::engine::system::steam::Steam> {
+        self.state.steam.as_mut()
+    }
+
+    #[inline]
+    pub fn set_fps(&mut self, fps: u16) {
+        self.state.framerate_manager.set_target_frame_rate(fps);
+    }
+
+    /// See [`Engine::display`]
+    #[inline]
+    pub fn display(&self) -> Option<&DisplayInfo> {
+        self.state.sdl.display.as_ref()
+    }
+
+    /// See [`Engine::window_placement`]
+    #[inline]
+    pub fn window_placement(&self) -> Option<WindowPlacement> {
+        self.state.sdl.window_placement()
+    }
+
+    /// See [`Engine::set_window_placement`]
+    #[inline]
+    pub fn set_window_placement(&mut self, placement: WindowPlacement) {
+        self.state.sdl.place_window(placement);
+    }
+
+    /// See [`Engine::update_window_config`]
+    #[inline]
+    pub fn update_window_config(&self, config: &mut EngineConfig) {
+        self.state.sdl.update_window_config(config);
+    }
+
+    /// See [`Engine::pixels_per_point`]
+    #[inline]
+    pub fn pixels_per_point(&self) -> f32 {
+        self.state.sdl.pixels_per_point()
+    }
+
+    /// See [`Engine::accessibility`]
+    #[inline]
+    pub fn accessibility(&self) -> &AccessibilityOptions {
+        &self.state.accessibility
+    }
+
+    /// See [`Engine::set_accessibility`]
+    pub fn set_accessibility(&mut self, options: AccessibilityOptions) {
+        if let Some(light) = self.state.set_accessibility(options) {
+            self.send(RenderCommand::SetAmbientLight(light));
+        }
+    }
+
+    /// See [`Engine::ambient_light`]
+    #[inline]
+    pub fn ambient_light(&self) -> AmbientLight {
+        self.state.ambient_light
+    }
+
+    /// See [`Engine::set_ambient_light`]
+    pub fn set_ambient_light(&mut self, light: impl Into<AmbientLight>) {
+        let light = self.state.set_ambient_light(light.into());
+        self.send(RenderCommand::SetAmbientLight(light));
+    }
+
+    /// See [`Engine::set_window_icon`]
+    #[inline]
+    pub fn set_window_icon(&mut self, icon: impl Into<RawRgbaImage>) {
+        self.state.sdl.set_window_icon(icon.into());
+    }
+
+    /// See [`Engine::set_window_badge`]
+    #[inline]
+    pub fn set_window_badge(&mut self, badge: Option<WindowBadge>) {
+        self.state.sdl.set_window_badge(badge);
+    }
+
+    /// See [`Engine::set_fullscreen`]
+    #[inline]
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.state.set_fullscreen(fullscreen);
+    }
+
+    /// The frame time statistics of the update thread collected in [`ThreadedEngine::delay`].
+    #[inline]
+    pub fn fps_manager(&self) -> &FpsManager {
+        &self.state.framerate_manager
+    }
+
+    #[inline]
+    pub fn delay(&mut self) -> Duration {
+        if self.suspended {
+            self.state.framerate_manager.delay_without_present()
+        } else {
+            self.state.framerate_manager.delay()
+        }
+    }
+}
+
+impl<P> Drop for ThreadedEngine<P> {
+    fn drop(&mut self) {
+        // the surface and swapchain must be destroyed before the window
+        self.send(RenderCommand::Shutdown);
+        if let Some(render_thread) = self.render_thread.take() {
+            if render_thread.join().is_err() {
+                error!("The render thread panicked");
+            }
+        }
+    }
+}
+
+pub struct ThreadedUpdateContext<'a, P> {
+    engine: &'a mut ThreadedEngine<P>,
+    /// Sent to the render thread after the update, still contains the content it was rendered
+    /// with two frames ago
+    pub packet: &'a mut P,
+    pub events: Vec<EngineEvent>,
+    /// See [`ThreadedEngine::frame`]
+    pub frame: u64,
+    /// See [`BeforeRenderContext::delta`]
+    ///
+    /// [`BeforeRenderContext::delta`]: crate::engine::BeforeRenderContext::delta
+    pub delta: Duration,
+    /// See [`BeforeRenderContext::elapsed`]
+    ///
+    /// [`BeforeRenderContext::elapsed`]: crate::engine::BeforeRenderContext::elapsed
+    pub elapsed: Duration,
+    pub width: u32,
+    pub height: u32,
+    pub start: Instant,
+}
+
+impl<'a, P> ThreadedUpdateContext<'a, P> {
+    #[inline]
+    pub fn haptics(&mut self) -> &mut Haptics {
+        &mut self.engine.state.haptics
+    }
+
+    /// See [`Engine::action_map_mut`]
+    #[inline]
+    pub fn action_map(&mut self) -> &mut ActionMap {
+        &mut self.engine.state.action_map
+    }
+
+    /// See [`Engine::spatial_audio_mut`]
+    #[inline]
+    pub fn spatial_audio(&mut self) -> &mut SpatialAudio {
+        &mut self.engine.state.spatial_audio
+    }
+
+    /// See [`ThreadedEngine::virtual_cursor_mut`]
+    #[inline]
+    pub fn virtual_cursor(&mut self) -> Option<&mut VirtualCursor> {
+        self.engine.virtual_cursor_mut()
+    }
+
+    /// See [`ThreadedEngine::plugin_mut`]
+    #[inline]
+    pub fn plugin<T: EnginePlugin>(&mut self) -> Option<&mut T> {
+        self.engine.plugin_mut()
+    }
+
+    /// See [`TextInput::start`]
+    #[inline]
+    pub fn start_text_input(&mut self, rect: Option<sdl2::rect::Rect>) {
+        self.engine.state.text_input.start(rect);
+    }
+
+    /// See [`TextInput::stop`]
+    #[inline]
+    pub fn stop_text_input(&mut self) {
+        self.engine.state.text_input.stop();
+    }
+
+    /// See [`Engine::accessibility`]
+    #[inline]
+    pub fn accessibility(&self) -> &AccessibilityOptions {
+        self.engine.accessibility()
+    }
+
+    /// See [`Engine::set_ambient_light`]
+    #[inline]
+    pub fn set_ambient_light(&mut self, light: impl Into<AmbientLight>) {
+        self.engine.set_ambient_light(light);
+    }
+
+    #[inline]
+    #[cfg(feature = "audio")]
+    pub fn audio(&mut self) -> Option<&mut crate::engine::system::audio::AudioSystem> {
+        self.engine.audio_mut()
+    }
+
+    #[inline]
+    #[cfg(feature = "steamworks")]
+    pub fn steam(&mut self) -> Option<&mut crate::engine::system::steam::Steam> {
+        self.engine.steam_mut()
+    }
+}
+
+/// Owns everything needed to record and submit the frames.
+struct RenderThread<P, R> {
+    vulkan_system: VulkanSystem,
+    pipelines: Arc<VulkanPipelines>,
+    #[cfg(feature = "ttf-font-renderer")]
+    font_renderer: crate::engine::system::ttf::FontRenderer,
+    renderer: R,
+    commands: Receiver<RenderCommand<P>>,
+    free_packets: Sender<P>,
+    stats: Sender<RenderStats>,
+}
+
+impl<P, R> RenderThread<P, R>
+where
+    R: FnMut(RenderContext, &mut P) -> Vec<RecordedCommands>,
+{
+    fn run(mut self) {
+        while let Ok(command) = self.commands.recv() {
+            match command {
+                RenderCommand::Frame(submission) => self.render(submission),
+                RenderCommand::RecreateSwapchain => self.vulkan_system.recreate_swapchain(),
+                RenderCommand::SetPixelsPerPoint(pixels_per_point) => {
+                    self.vulkan_system.set_pixels_per_point(pixels_per_point)
+                }
+                RenderCommand::SetAmbientLight(light) => {
+                    self.vulkan_system.set_ambient_light(light)
+                }
+                RenderCommand::Suspend => self.vulkan_system.suspend(),
+                RenderCommand::Resume {
+                    surface,
+                    width,
+                    height,
+                } => {
+                    if let Err(e) = self.vulkan_system.resume(surface, width, height) {
+                        error!("Failed to resume rendering: {e}");
+                    }
+                }
+                RenderCommand::Shutdown => break,
+            }
+        }
+    }
+
+    fn render(&mut self, submission: FrameSubmission<P>) {
+        let FrameSubmission {
+            mut packet,
+            frame,
+            delta,
+            elapsed,
+            width,
+            height,
+            cursor,
+        } = submission;
+
+        let start = Instant::now();
+        let mut command_buffers = 0;
+        let result = self.vulkan_system.render(width, height, |render_context| {
+            let mut commands = (self.renderer)(
+                RenderContext {
+                    inner: render_context,
+                    pipelines: &self.pipelines,
+                    frame,
+                    delta,
+                    elapsed,
+                    width,
+                    height,
+                    #[cfg(feature = "ttf-font-renderer")]
+                    font_renderer: &mut self.font_renderer,
+                },
+                &mut packet,
+            )
+            .into_iter()
+            .map(RecordedCommands::into_inner)
+            .collect::<Vec<_>>();
+
+            if let Some(quad) = cursor {
+                // the cursor is in window coordinates, but drawn into the viewport
+                let viewport = render_context.viewport_rect();
+                let quad = quad.translated([-(viewport.x as f32), -(viewport.y as f32)]);
+                let cursor = render_context
+                    .create_render_buffer_builder()
+                    .map_err(DrawError::FailedToCreateCommandBuffer)
+                    .and_then(|mut builder| {
+                        super::draw_virtual_cursor(&mut builder, &self.pipelines, quad);
+                        builder
+                            .build()
+                            .map_err(DrawError::FailedToBuildCommandBuffer)
+                    });
+                match cursor {
+                    Ok(command) => commands.push(command),
+                    Err(e) => error!("Failed to record the virtual cursor of frame {frame}: {e}"),
+                }
+            }
+
+            command_buffers = commands.len();
+            commands
+        });
+
+        if let Err(e) = result {
+            error!("Failed to render frame {frame}: {e}");
+        }
+
+        #[cfg(feature = "ttf-font-renderer")]
+        self.font_renderer.on_frame_completed();
+        // releases the atlases of textures that were dropped
+        self.pipelines.texture.texture_registry().retain_alive();
+
+        let _ = self.stats.send(RenderStats {
+            frame,
+            duration: start.elapsed(),
+            command_buffers,
+            memory: self.vulkan_system.memory_stats(),
+        });
+        let _ = self.free_packets.send(packet);
+    }
+}
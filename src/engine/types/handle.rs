@@ -0,0 +1,291 @@
+//! Generational handles: a [`Handle`] is an index into a slot together with the generation of
+//! the slot at the time it was handed out. Removing a value increments the generation of its
+//! slot, so a stale handle is detected when the slot is reused instead of silently pointing at
+//! the new value.
+//!
+//! [`HandleMap`] stores values behind handles, [`HandleAllocator`] only hands out unique ids for
+//! values that are stored elsewhere.
+
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+/// Refers to a value of type `T` in a [`HandleMap`] or to an id of a [`HandleAllocator`]. The
+/// type parameter prevents mixing up handles of different maps.
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    #[inline]
+    const fn new(index: u32, generation: u32) -> Self {
+        Self {
+            index,
+            generation,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The index of the slot, which is reused after the value was removed.
+    #[inline]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The generation of the slot when this handle was handed out.
+    #[inline]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Both values in a single `u64`, for example to pass the handle through an FFI or a
+    /// scripting boundary. See [`Handle::from_bits`].
+    #[inline]
+    pub fn to_bits(&self) -> u64 {
+        (u64::from(self.generation) << 32) | u64::from(self.index)
+    }
+
+    #[inline]
+    pub fn from_bits(bits: u64) -> Self {
+        Self::new(bits as u32, (bits >> 32) as u32)
+    }
+
+    #[inline]
+    fn cast<U>(self) -> Handle<U> {
+        Handle::new(self.index, self.generation)
+    }
+}
+
+// implemented manually, so that T does not need to implement the traits as well
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> Clone for Handle<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> PartialOrd for Handle<T> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Handle<T> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.index, self.generation).cmp(&(other.index, other.generation))
+    }
+}
+
+impl<T> Hash for Handle<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bits().hash(state);
+    }
+}
+
+impl<T> Debug for Handle<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Stores values behind generational [`Handle`]s. Inserting and removing values is `O(1)`, the
+/// slots of removed values are reused.
+#[derive(Debug, Clone)]
+pub struct HandleMap<T> {
+    slots: Vec<Slot<T>>,
+    /// The indices of the empty slots that can be reused
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl<T> Default for HandleMap<T> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            slots: Vec::default(),
+            free: Vec::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<T> HandleMap<T> {
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slots: Vec::with_capacity(capacity),
+            ..Self::default()
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> Handle<T> {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.value = Some(value);
+            Handle::new(index, slot.generation)
+        } else {
+            let index = u32::try_from(self.slots.len()).expect("Too many handles");
+            self.slots.push(Slot {
+                generation: 0,
+                value: Some(value),
+            });
+            Handle::new(index, 0)
+        }
+    }
+
+    /// Removes the value, [`None`] if the handle is stale.
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        self.len -= 1;
+        // a slot whose generation is exhausted is retired, so its handles are never ambiguous
+        if let Some(generation) = slot.generation.checked_add(1) {
+            slot.generation = generation;
+            self.free.push(handle.index);
+        }
+        Some(value)
+    }
+
+    /// Whether the handle refers to a value of this map, `false` once it was removed.
+    #[inline]
+    pub fn contains(&self, handle: Handle<T>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    #[inline]
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all values, every handle handed out so far becomes stale.
+    pub fn clear(&mut self) {
+        for index in 0..self.slots.len() as u32 {
+            self.remove(Handle::new(index, self.slots[index as usize].generation));
+        }
+    }
+
+    /// The values and their handles in the order of their slots.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> + '_ {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let value = slot.value.as_ref()?;
+            Some((Handle::new(index as u32, slot.generation), value))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle<T>, &mut T)> + '_ {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let value = slot.value.as_mut()?;
+                Some((Handle::new(index as u32, slot.generation), value))
+            })
+    }
+
+    #[inline]
+    pub fn values(&self) -> impl Iterator<Item = &T> + '_ {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+
+    #[inline]
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+}
+
+/// Hands out unique [`Handle`]s for values that are stored elsewhere, like in a
+/// [`FxHashMap`](rustc_hash::FxHashMap) or on the GPU, and tells whether a handle was freed
+/// since.
+#[derive(Debug, Clone)]
+pub struct HandleAllocator<T> {
+    handles: HandleMap<()>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for HandleAllocator<T> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            handles: HandleMap::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> HandleAllocator<T> {
+    #[inline]
+    pub fn allocate(&mut self) -> Handle<T> {
+        self.handles.insert(()).cast()
+    }
+
+    /// Frees the handle, so its slot can be reused. `false` if it was already stale.
+    #[inline]
+    pub fn free(&mut self, handle: Handle<T>) -> bool {
+        self.handles.remove(handle.cast()).is_some()
+    }
+
+    /// Whether the handle was allocated and not freed since.
+    #[inline]
+    pub fn is_alive(&self, handle: Handle<T>) -> bool {
+        self.handles.contains(handle.cast())
+    }
+
+    /// The amount of handles that are alive.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+}
@@ -1,8 +1,14 @@
+pub mod handle;
+
 pub mod world2d {
     pub type Pos<T> = cgmath::Point2<T>;
     pub type Dim<T> = cgmath::Vector2<T>;
 
     #[derive(Debug, Copy, Clone, PartialEq)]
+    #[cfg_attr(
+        feature = "serde-io",
+        derive(serde_derive::Serialize, serde_derive::Deserialize)
+    )]
     pub struct Rect<T> {
         pub pos: Pos<T>,
         pub dim: Dim<T>,
@@ -0,0 +1,319 @@
+//! The state of the thread that polls the events and updates the game, which the [`Engine`] and
+//! the [`ThreadedEngine`] share. Both only differ in how they react to the [`PolledEvents`] with
+//! their renderer: the [`Engine`] calls the [`VulkanSystem`] directly, while the
+//! [`ThreadedEngine`] sends the changes to its render thread.
+//!
+//! [`Engine`]: crate::engine::Engine
+//! [`ThreadedEngine`]: crate::engine::threaded::ThreadedEngine
+//! [`VulkanSystem`]: crate::engine::system::vulkan::system::VulkanSystem
+
+use crate::engine::accessibility::{AccessibilityOptions, FlashLimiter};
+use crate::engine::event::EngineEvent;
+use crate::engine::input::{Haptics, TextInput, VirtualCursor};
+use crate::engine::parts::sdl::{DisplayInfo, SdlParts};
+use crate::engine::plugin::EnginePlugin;
+use crate::engine::system::fps::FpsManager;
+use crate::engine::system::input::ActionMap;
+use crate::engine::system::vulkan::desc::binding_202_ambient_light::AmbientLight;
+use crate::engine::RenderStats;
+use crate::support::world2d::spatial_audio::SpatialAudio;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::video::FullscreenType;
+use std::time::{Duration, Instant};
+
+pub(crate) struct UpdateState {
+    /// [`None`] if disabled or no audio device could be opened, drop before the sdl context
+    #[cfg(feature = "audio")]
+    pub(crate) audio: Option<crate::engine::system::audio::AudioSystem>,
+    // drop after the vulkan system! (last is fine, too)
+    pub(crate) sdl: SdlParts,
+    pub(crate) framerate_manager: FpsManager,
+    pub(crate) haptics: Haptics,
+    /// Fed the polled events at the start of every frame, see [`Engine::action_map_mut`]
+    ///
+    /// [`Engine::action_map_mut`]: crate::engine::Engine::action_map_mut
+    pub(crate) action_map: ActionMap,
+    /// Updated at the end of every frame, see [`Engine::spatial_audio_mut`]
+    ///
+    /// [`Engine::spatial_audio_mut`]: crate::engine::Engine::spatial_audio_mut
+    pub(crate) spatial_audio: SpatialAudio,
+    pub(crate) text_input: TextInput,
+    pub(crate) frame: u64,
+    /// Set once the platform announced the termination of the app, see
+    /// [`Engine::is_terminating`]
+    ///
+    /// [`Engine::is_terminating`]: crate::engine::Engine::is_terminating
+    pub(crate) terminating: bool,
+    /// When the engine was created, see [`BeforeRenderContext::elapsed`]
+    ///
+    /// [`BeforeRenderContext::elapsed`]: crate::engine::BeforeRenderContext::elapsed
+    pub(crate) started: Instant,
+    /// When the previous update started, see [`BeforeRenderContext::delta`]
+    ///
+    /// [`BeforeRenderContext::delta`]: crate::engine::BeforeRenderContext::delta
+    pub(crate) previous_update: Option<Instant>,
+    pub(crate) render_stats: RenderStats,
+    /// See [`EngineBuilder::with_plugin`]
+    ///
+    /// [`EngineBuilder::with_plugin`]: crate::engine::builder::EngineBuilder::with_plugin
+    pub(crate) plugins: Vec<Box<dyn EnginePlugin>>,
+    /// See [`EngineBuilder::with_virtual_cursor`]
+    ///
+    /// [`EngineBuilder::with_virtual_cursor`]: crate::engine::builder::EngineBuilder::with_virtual_cursor
+    pub(crate) virtual_cursor: Option<VirtualCursor>,
+    /// See [`EngineBuilder::with_accessibility`]
+    ///
+    /// [`EngineBuilder::with_accessibility`]: crate::engine::builder::EngineBuilder::with_accessibility
+    pub(crate) accessibility: AccessibilityOptions,
+    /// The light set by the game, which is approached through the `flash_limiter`
+    pub(crate) ambient_light: AmbientLight,
+    pub(crate) flash_limiter: FlashLimiter,
+    /// Shut down after everything else is dropped
+    #[cfg(feature = "steamworks")]
+    pub(crate) steam: Option<crate::engine::system::steam::Steam>,
+}
+
+/// The events of a frame and what the renderer has to adapt to.
+pub(crate) struct PolledEvents {
+    pub(crate) events: Vec<EngineEvent>,
+    /// The window was resized, the swapchain has to be recreated
+    pub(crate) resized: bool,
+    /// The window might be shown on another display or with another scale, the pixels per point
+    /// have to be updated
+    pub(crate) display_changed: bool,
+    /// The refresh rate of the new display to pace the frames with, see
+    /// [`UpdateState::on_display_changed`]
+    pub(crate) refresh_rate: Option<u16>,
+    /// In the order of the events
+    pub(crate) lifecycle: Vec<Lifecycle>,
+    /// F11 was released, the fullscreen mode has to be toggled
+    pub(crate) toggle_fullscreen: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Lifecycle {
+    /// The app entered the background or is terminating, rendering has to be suspended
+    Suspend,
+    /// The app entered the foreground again
+    Resume,
+}
+
+impl UpdateState {
+    /// Polls the events of the frame and passes them to the input, the [`VirtualCursor`] and
+    /// the plugins.
+    pub(crate) fn poll_events(&mut self) -> PolledEvents {
+        #[cfg(feature = "steamworks")]
+        if let Some(steam) = &mut self.steam {
+            steam.update();
+        }
+
+        let mut toggle_fullscreen = false;
+        let mut display_event = None;
+        let mut resized = false;
+        let mut lifecycle = Vec::new();
+        let mut events = self.sdl.event_pump.poll_iter().collect::<Vec<_>>();
+        let now = Instant::now();
+        let ticks_now = self.sdl.timer.ticks();
+
+        if let Some(cursor) = &mut self.virtual_cursor {
+            for event in &events {
+                cursor.on_sdl2_event(event);
+            }
+            let (width, height) = self.sdl.window.size();
+            events.extend(cursor.update(now, ticks_now, self.sdl.window.id(), [width, height]));
+        }
+
+        for (index, event) in events.iter().enumerate() {
+            self.haptics.on_sdl2_event(event);
+
+            match event {
+                Event::Window {
+                    win_event: WindowEvent::Resized(..) | WindowEvent::SizeChanged(..),
+                    ..
+                } => {
+                    resized = true;
+                    display_event = Some(index);
+                }
+                Event::Window {
+                    win_event: WindowEvent::DisplayChanged(..),
+                    ..
+                }
+                | Event::Display { .. } => display_event = Some(index),
+                Event::AppWillEnterBackground { .. } => lifecycle.push(Lifecycle::Suspend),
+                Event::AppDidEnterForeground { .. } => lifecycle.push(Lifecycle::Resume),
+                Event::AppTerminating { .. } => {
+                    warn!("The platform is terminating the app");
+                    self.terminating = true;
+                    lifecycle.push(Lifecycle::Suspend);
+                }
+                Event::KeyUp {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
+                } => toggle_fullscreen = true,
+                _ => {}
+            }
+        }
+
+        let mut events = events
+            .into_iter()
+            .map(|event| EngineEvent::new(event, self.frame, now, ticks_now))
+            .collect::<Vec<_>>();
+
+        let mut refresh_rate = None;
+        if let Some(index) = display_event {
+            if let Some(display) = self.sdl.update_display() {
+                refresh_rate = self.on_display_changed(&display);
+                events[index].display_changed = Some(display);
+            }
+        }
+
+        for plugin in &mut self.plugins {
+            for event in &events {
+                plugin.on_event(event);
+            }
+        }
+
+        self.haptics.update(Instant::now());
+        self.action_map.update(&events);
+
+        PolledEvents {
+            events,
+            resized,
+            // a resize can move the window to a display with another scale
+            display_changed: display_event.is_some(),
+            refresh_rate,
+            lifecycle,
+            toggle_fullscreen,
+        }
+    }
+
+    /// Logs the display and returns its refresh rate if the frames are paced by vsync.
+    pub(crate) fn on_display_changed(&self, display: &DisplayInfo) -> Option<u16> {
+        info!(
+            "Window is shown on display {} {:?} with {:?} DPI at {:?} Hz",
+            display.index, display.name, display.dpi, display.refresh_rate
+        );
+        display
+            .refresh_rate
+            .filter(|_| self.framerate_manager.is_vsync())
+    }
+
+    /// The time since the previous update and since the engine was created.
+    pub(crate) fn advance_clock(&mut self, start: Instant) -> (Duration, Duration) {
+        let delta = self
+            .previous_update
+            .replace(start)
+            .map(|previous| start.duration_since(previous))
+            .unwrap_or_default();
+        (delta, start.duration_since(self.started))
+    }
+
+    /// Passes the start of the frame to the plugins and returns the ambient light to show while
+    /// [`AccessibilityOptions::limit_flashes`] is enabled.
+    pub(crate) fn before_frame(&mut self, delta: Duration) -> Option<AmbientLight> {
+        for plugin in &mut self.plugins {
+            plugin.before_frame(self.frame, delta);
+        }
+        self.accessibility
+            .limit_flashes
+            .then(|| self.limit_ambient_light(delta))
+    }
+
+    pub(crate) fn after_frame(&mut self) {
+        self.frame += 1;
+        #[cfg(feature = "audio")]
+        self.spatial_audio.on_frame_completed(self.audio.as_ref());
+        #[cfg(not(feature = "audio"))]
+        self.spatial_audio.on_frame_completed();
+    }
+
+    /// Records the [`RenderStats`] of a rendered frame and passes them to the plugins.
+    pub(crate) fn on_render_stats(&mut self, stats: RenderStats) {
+        self.render_stats = stats;
+        crate::engine::crash::record_render_stats(stats);
+        for plugin in &mut self.plugins {
+            plugin.after_frame(&stats);
+        }
+    }
+
+    /// Sets the light and returns the one to show right away.
+    pub(crate) fn set_ambient_light(&mut self, light: AmbientLight) -> AmbientLight {
+        self.ambient_light = light;
+        if self.accessibility.limit_flashes {
+            self.limit_ambient_light(Duration::ZERO)
+        } else {
+            self.reset_ambient_light()
+        }
+    }
+
+    /// Returns the light to show if it changes with the options.
+    pub(crate) fn set_accessibility(
+        &mut self,
+        options: AccessibilityOptions,
+    ) -> Option<AmbientLight> {
+        let was_limiting_flashes = self.accessibility.limit_flashes;
+        self.accessibility = options;
+        (was_limiting_flashes && !self.accessibility.limit_flashes)
+            .then(|| self.reset_ambient_light())
+    }
+
+    /// Shows the ambient light at once, the flash limit continues from it.
+    fn reset_ambient_light(&mut self) -> AmbientLight {
+        let AmbientLight { r, g, b } = self.ambient_light;
+        self.flash_limiter.reset([r, g, b]);
+        self.ambient_light
+    }
+
+    /// Moves the shown light towards the ambient light within the flash limit.
+    fn limit_ambient_light(&mut self, delta: Duration) -> AmbientLight {
+        let AmbientLight { r, g, b } = self.ambient_light;
+        AmbientLight::from(
+            self.flash_limiter
+                .limit([r, g, b], delta, &self.accessibility),
+        )
+    }
+
+    pub(crate) fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.sdl.window_maximized = fullscreen;
+        if self.sdl.window_maximized {
+            self.sdl.window.maximize();
+            if let Err(e) = self.sdl.window.set_fullscreen(FullscreenType::True) {
+                error!("Enabling fullscreen failed: {e}");
+            }
+        } else {
+            if let Err(e) = self.sdl.window.set_fullscreen(FullscreenType::Off) {
+                error!("Disabling fullscreen failed: {e}");
+            }
+            self.sdl.window.restore();
+        }
+        self.sdl.window.set_bordered(!self.sdl.window_maximized);
+    }
+
+    pub(crate) fn pause_audio(&mut self) {
+        #[cfg(feature = "audio")]
+        if let Some(audio) = &mut self.audio {
+            audio.pause_all();
+        }
+    }
+
+    pub(crate) fn resume_audio(&mut self) {
+        #[cfg(feature = "audio")]
+        if let Some(audio) = &mut self.audio {
+            audio.resume_all();
+        }
+    }
+
+    pub(crate) fn plugin<P: EnginePlugin>(&self) -> Option<&P> {
+        self.plugins
+            .iter()
+            .find_map(|plugin| plugin.downcast_ref::<P>())
+    }
+
+    pub(crate) fn plugin_mut<P: EnginePlugin>(&mut self) -> Option<&mut P> {
+        self.plugins
+            .iter_mut()
+            .find_map(|plugin| plugin.downcast_mut::<P>())
+    }
+}
@@ -0,0 +1,452 @@
+//! Golden-image tests: canned scenes are rendered by a headless [`VulkanSystem`], read back and
+//! compared against reference images. This catches shader and blending regressions of the
+//! pipelines and also works on CI machines without a GPU by using a software rasterizer such as
+//! lavapipe.
+//!
+//! A missing reference image fails the check, so a scene cannot pass by accident. To write the
+//! reference images of new scenes or to update all of them after an intentional change, run with
+//! [`GoldenHarness::with_bless`] or set the `HOTROD_BLESS` environment variable to `1`.
+
+use crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer;
+use crate::engine::system::commands::{CommandRecorder, RecordedCommands};
+use crate::engine::system::vulkan::beautiful_lines::{self, BeautifulLine, BeautifulLinePipeline};
+use crate::engine::system::vulkan::glowing_balls::GlowingBall;
+use crate::engine::system::vulkan::lines::{self, Line};
+use crate::engine::system::vulkan::pipelines::VulkanPipelines;
+use crate::engine::system::vulkan::system::{RenderContext, VulkanSystem};
+use crate::engine::system::vulkan::textured::{Textured, Vertex2dUv};
+use crate::engine::system::vulkan::triangles::{self, Triangles, TrianglesIndexed};
+use crate::engine::system::vulkan::{DrawError, PipelineCreateError, ReadbackError};
+use crate::engine::types::world2d::{Dim, Pos};
+use crate::support::image::RawRgbaImage;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use vulkano::image::{ImageUsage, SampleCount};
+use vulkano::instance::{Instance, InstanceCreateInfo};
+use vulkano::{LoadingError, Validated, VulkanError, VulkanLibrary};
+
+/// The scenes rendered by [`GoldenHarness::check_all`], each covers a single pipeline.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GoldenScene {
+    Lines,
+    BeautifulLines,
+    Triangles,
+    TrianglesIndexed,
+    Textured,
+    GlowingBalls,
+    Canvas,
+}
+
+impl GoldenScene {
+    pub const ALL: [GoldenScene; 7] = [
+        GoldenScene::Lines,
+        GoldenScene::BeautifulLines,
+        GoldenScene::Triangles,
+        GoldenScene::TrianglesIndexed,
+        GoldenScene::Textured,
+        GoldenScene::GlowingBalls,
+        GoldenScene::Canvas,
+    ];
+
+    /// The file name of the reference image, without extension.
+    pub fn name(self) -> &'static str {
+        match self {
+            GoldenScene::Lines => "lines",
+            GoldenScene::BeautifulLines => "beautiful_lines",
+            GoldenScene::Triangles => "triangles",
+            GoldenScene::TrianglesIndexed => "triangles_indexed",
+            GoldenScene::Textured => "textured",
+            GoldenScene::GlowingBalls => "glowing_balls",
+            GoldenScene::Canvas => "canvas",
+        }
+    }
+
+    pub fn draw(
+        self,
+        context: &RenderContext,
+        pipelines: &VulkanPipelines,
+    ) -> Result<RecordedCommands, Error> {
+        let mut recorder = CommandRecorder::new(context)?;
+        let builder = recorder.recorder_mut();
+        match self {
+            GoldenScene::Lines => pipelines.line.draw(
+                builder,
+                &[Line {
+                    vertices: [[16.0, 16.0], [240.0, 128.0], [16.0, 240.0]]
+                        .into_iter()
+                        .map(|pos| lines::Vertex2d { pos })
+                        .collect(),
+                    color: [1.0, 0.5, 0.0, 1.0],
+                }],
+            )?,
+            GoldenScene::BeautifulLines => pipelines.beautiful_line.draw(
+                builder,
+                &[BeautifulLine {
+                    vertices: vec![
+                        beautiful_lines::Vertex2d {
+                            pos: [32.0, 32.0],
+                            color: [0.0, 1.0, 1.0, 1.0],
+                        },
+                        beautiful_lines::Vertex2d {
+                            pos: [224.0, 64.0],
+                            color: [1.0, 1.0, 0.0, 1.0],
+                        },
+                        beautiful_lines::Vertex2d {
+                            pos: [128.0, 224.0],
+                            color: [1.0, 0.0, 1.0, 0.5],
+                        },
+                    ],
+                    width: 24.0,
+                }],
+            )?,
+            GoldenScene::Triangles => pipelines.triangles.draw(
+                builder,
+                &[Triangles {
+                    vertices: [[32.0, 224.0], [128.0, 32.0], [224.0, 224.0]]
+                        .into_iter()
+                        .map(|pos| triangles::Vertex2d { pos })
+                        .collect(),
+                    color: [1.0, 1.0, 0.0, 1.0],
+                }],
+            )?,
+            GoldenScene::TrianglesIndexed => pipelines.triangles.draw_indexed(
+                builder,
+                &[TrianglesIndexed {
+                    vertices: [[64.0, 64.0], [192.0, 64.0], [192.0, 192.0], [64.0, 192.0]]
+                        .into_iter()
+                        .map(|pos| triangles::Vertex2d { pos })
+                        .collect(),
+                    indices: vec![[0, 1, 2], [2, 3, 0]],
+                    color: [0.0, 0.0, 1.0, 0.5],
+                }],
+            )?,
+            GoldenScene::Textured => pipelines.texture.draw(
+                builder,
+                &[Textured {
+                    vertices: [
+                        ([32.0, 32.0], [0.0, 0.0]),
+                        ([224.0, 32.0], [1.0, 0.0]),
+                        ([224.0, 224.0], [1.0, 1.0]),
+                        ([224.0, 224.0], [1.0, 1.0]),
+                        ([32.0, 224.0], [0.0, 1.0]),
+                        ([32.0, 32.0], [0.0, 0.0]),
+                    ]
+                    .into_iter()
+                    .map(|(pos, uv)| Vertex2dUv { pos, uv })
+                    .collect(),
+                    texture: pipelines.placeholders().missing().clone(),
+                }],
+            )?,
+            GoldenScene::GlowingBalls => pipelines.glowing_balls.draw(
+                builder,
+                [
+                    GlowingBall {
+                        pos: [96.0, 96.0],
+                        color: [1.0, 0.25, 0.0, 1.0],
+                        radius: 32.0,
+                        corona: 16.0,
+                        late_alpha: 0.5,
+                    },
+                    GlowingBall {
+                        pos: [160.0, 160.0],
+                        color: [0.0, 0.5, 1.0, 1.0],
+                        radius: 24.0,
+                        corona: 32.0,
+                        late_alpha: 0.25,
+                    },
+                ],
+            )?,
+            GoldenScene::Canvas => {
+                let mut layer = BufferedCanvasLayer::default();
+                layer.set_draw_color([1.0, 0.0, 0.0, 1.0]);
+                layer.draw_rect(Pos::new(16.0, 16.0), Dim::new(96.0, 96.0));
+                layer.set_draw_color([0.0, 1.0, 0.0, 0.5]);
+                layer.fill_rect(Pos::new(64.0, 64.0), Dim::new(128.0, 128.0));
+                layer.set_draw_color([0.0, 0.0, 1.0, 1.0]);
+                layer.draw_path(&[[32.0, 224.0], [128.0, 160.0], [224.0, 224.0]]);
+                return Ok(layer.flush(context, pipelines));
+            }
+        }
+
+        Ok(recorder.finish()?)
+    }
+}
+
+/// How much a rendered image may differ from its reference, because rasterization rules and
+/// precision differ slightly between drivers.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GoldenTolerance {
+    /// The difference of a channel up to which a pixel is considered equal
+    pub channel: u8,
+    /// The ratio (`0..=1`) of pixels that may exceed the channel tolerance
+    pub mismatched_ratio: f32,
+}
+
+impl Default for GoldenTolerance {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            channel: 2,
+            mismatched_ratio: 0.001,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GoldenComparison {
+    pub mismatched_pixels: usize,
+    pub total_pixels: usize,
+    /// The largest difference of a channel
+    pub max_difference: u8,
+}
+
+impl GoldenComparison {
+    pub fn new(actual: &[u8], reference: &[u8], tolerance: &GoldenTolerance) -> Self {
+        let mut mismatched_pixels = 0;
+        let mut max_difference = 0;
+        for (a, r) in actual.chunks_exact(4).zip(reference.chunks_exact(4)) {
+            let difference = a.iter().zip(r).map(|(a, r)| a.abs_diff(*r)).max();
+            let difference = difference.unwrap_or_default();
+            max_difference = max_difference.max(difference);
+            if difference > tolerance.channel {
+                mismatched_pixels += 1;
+            }
+        }
+        Self {
+            mismatched_pixels,
+            total_pixels: actual.len() / 4,
+            max_difference,
+        }
+    }
+
+    #[inline]
+    pub fn passes(&self, tolerance: &GoldenTolerance) -> bool {
+        self.mismatched_pixels as f32 <= self.total_pixels as f32 * tolerance.mismatched_ratio
+    }
+}
+
+/// The result of a successful [`GoldenHarness::check`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GoldenOutcome {
+    /// The rendered image matches the reference within the tolerance
+    Matched(GoldenComparison),
+    /// Blessing is enabled, the reference image was (over-) written
+    Blessed,
+}
+
+/// Renders scenes with a headless [`VulkanSystem`] of [`GoldenHarness::SIZE`] and compares
+/// them with the reference images in a directory.
+pub struct GoldenHarness {
+    vulkan: VulkanSystem,
+    pipelines: Arc<VulkanPipelines>,
+    reference_dir: PathBuf,
+    tolerance: GoldenTolerance,
+    bless: bool,
+}
+
+impl GoldenHarness {
+    pub const SIZE: [u32; 2] = [256, 256];
+    pub const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+    pub fn new(reference_dir: impl Into<PathBuf>) -> Result<Self, Error> {
+        let instance = Instance::new(
+            VulkanLibrary::new()?,
+            InstanceCreateInfo::application_from_cargo_toml(),
+        )?;
+        let mut vulkan = VulkanSystem::new_headless(
+            instance,
+            Self::SIZE[0],
+            Self::SIZE[1],
+            BeautifulLinePipeline::REQUIRED_FEATURES,
+            SampleCount::Sample1,
+            None,
+            ImageUsage::empty(),
+        )?;
+        vulkan.set_clear_value(Self::CLEAR_COLOR);
+
+        Ok(Self {
+            pipelines: Arc::new(VulkanPipelines::try_from(&vulkan)?),
+            vulkan,
+            reference_dir: reference_dir.into(),
+            tolerance: GoldenTolerance::default(),
+            bless: std::env::var("HOTROD_BLESS").is_ok_and(|value| value == "1"),
+        })
+    }
+
+    #[inline]
+    pub fn with_tolerance(mut self, tolerance: GoldenTolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Whether to overwrite the reference images instead of comparing against them.
+    #[inline]
+    pub fn with_bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        self
+    }
+
+    #[inline]
+    pub fn vulkan_system(&self) -> &VulkanSystem {
+        &self.vulkan
+    }
+
+    #[inline]
+    pub fn pipelines(&self) -> &Arc<VulkanPipelines> {
+        &self.pipelines
+    }
+
+    /// Renders a single frame and reads it back. Pending texture uploads are completed before,
+    /// so that no placeholder shows up instead of a texture.
+    pub fn render(
+        &mut self,
+        f: impl FnOnce(&RenderContext, &VulkanPipelines) -> Result<Vec<RecordedCommands>, Error>,
+    ) -> Result<RawRgbaImage, Error> {
+        let [width, height] = Self::SIZE;
+        while self.vulkan.image_system().has_upload_info_enqueued() {
+            self.vulkan.render(width, height, |_| Vec::new())?;
+        }
+
+        let pipelines = Arc::clone(&self.pipelines);
+        let mut result = Ok(());
+        self.vulkan
+            .render(width, height, |context| match f(context, &pipelines) {
+                Ok(commands) => commands
+                    .into_iter()
+                    .map(RecordedCommands::into_inner)
+                    .collect(),
+                Err(e) => {
+                    result = Err(e);
+                    Vec::new()
+                }
+            })?;
+        result?;
+
+        Ok(self.vulkan.read_pixels()?)
+    }
+
+    /// Renders the scene and compares it with the reference image `<name>.png`. On a mismatch,
+    /// the rendered image and a difference image are written next to the reference as
+    /// `<name>.actual.png` and `<name>.diff.png`.
+    pub fn check(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(&RenderContext, &VulkanPipelines) -> Result<Vec<RecordedCommands>, Error>,
+    ) -> Result<GoldenOutcome, Error> {
+        let actual = self.render(f)?;
+        let reference_path = self.reference_dir.join(format!("{name}.png"));
+
+        if self.bless {
+            info!("Writing golden image {}", reference_path.display());
+            std::fs::create_dir_all(&self.reference_dir)?;
+            save_png(&reference_path, &actual)?;
+            return Ok(GoldenOutcome::Blessed);
+        }
+
+        if !reference_path.exists() {
+            return Err(Error::MissingReference {
+                name: name.to_string(),
+                path: reference_path,
+            });
+        }
+
+        let reference = image::open(&reference_path)?.to_rgba8();
+        if reference.dimensions() != (actual.width(), actual.height()) {
+            return Err(Error::SizeMismatch {
+                name: name.to_string(),
+                reference: reference.dimensions(),
+                actual: (actual.width(), actual.height()),
+            });
+        }
+
+        let comparison = GoldenComparison::new(actual.data(), reference.as_raw(), &self.tolerance);
+        if comparison.passes(&self.tolerance) {
+            return Ok(GoldenOutcome::Matched(comparison));
+        }
+
+        let diff = actual
+            .data()
+            .chunks_exact(4)
+            .zip(reference.as_raw().chunks_exact(4))
+            .flat_map(|(a, r)| {
+                let difference = a.iter().zip(r).map(|(a, r)| a.abs_diff(*r)).max();
+                if difference.unwrap_or_default() > self.tolerance.channel {
+                    [255, 0, 255, 255]
+                } else {
+                    [r[0] / 4, r[1] / 4, r[2] / 4, 255]
+                }
+            })
+            .collect::<Vec<u8>>();
+
+        save_png(
+            &self.reference_dir.join(format!("{name}.actual.png")),
+            &actual,
+        )?;
+        save_png(
+            &self.reference_dir.join(format!("{name}.diff.png")),
+            &RawRgbaImage::new(diff, actual.width(), actual.height()),
+        )?;
+
+        Err(Error::Mismatch {
+            name: name.to_string(),
+            comparison,
+        })
+    }
+
+    /// Checks all [`GoldenScene`]s, continuing after failures.
+    pub fn check_all(&mut self) -> Vec<(GoldenScene, Result<GoldenOutcome, Error>)> {
+        GoldenScene::ALL
+            .into_iter()
+            .map(|scene| {
+                let result = self.check(scene.name(), |context, pipelines| {
+                    Ok(vec![scene.draw(context, pipelines)?])
+                });
+                (scene, result)
+            })
+            .collect()
+    }
+}
+
+fn save_png(path: &Path, image: &RawRgbaImage) -> Result<(), Error> {
+    image::save_buffer(
+        path,
+        image.data(),
+        image.width(),
+        image.height(),
+        image::ColorType::Rgba8,
+    )?;
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to load the vulkan library: {0}")]
+    VulkanLibraryLoadingError(#[from] LoadingError),
+    #[error("Validated Vulkan Error: {0}")]
+    ValidatedVulkanError(#[from] Validated<VulkanError>),
+    #[error("Vulkan System Error: {0}")]
+    VulkanSystemError(#[from] crate::engine::system::vulkan::Error),
+    #[error("Failed to create a Vulkan System Pipeline: {0}")]
+    PipelineCreateError(#[from] PipelineCreateError),
+    #[error("Failed to render: {0}")]
+    DrawError(#[from] DrawError),
+    #[error("Failed to read back the rendered image: {0}")]
+    ReadbackError(#[from] ReadbackError),
+    #[error("Failed to load or save an image: {0}")]
+    ImageError(#[from] image::ImageError),
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("There is no reference image of {name} at {path:?}, bless to write it")]
+    MissingReference { name: String, path: PathBuf },
+    #[error("The reference image of {name} has the size {reference:?} instead of {actual:?}")]
+    SizeMismatch {
+        name: String,
+        reference: (u32, u32),
+        actual: (u32, u32),
+    },
+    #[error("The rendered image of {name} differs from its reference: {comparison:?}")]
+    Mismatch {
+        name: String,
+        comparison: GoldenComparison,
+    },
+}
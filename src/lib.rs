@@ -10,7 +10,11 @@ pub use sdl2;
 pub use thiserror;
 pub use vulkano;
 
+#[cfg(feature = "bench")]
+pub mod bench;
 pub mod engine;
+#[cfg(feature = "golden")]
+pub mod golden;
 pub mod hint;
 pub mod support;
 pub mod ui;
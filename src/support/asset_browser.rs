@@ -0,0 +1,262 @@
+//! An egui panel listing the loaded assets of a game, to debug asset pipelines.
+//!
+//! The engine does not keep track of which assets a game has loaded, therefore the game passes
+//! its assets as [`AssetEntry`]s every frame and applies the returned [`AssetAction`]s itself.
+
+use crate::engine::system::vulkan::textures::TextureId;
+use egui::{Color32, Image, Rect, RichText, TextureId as EguiTextureId, Ui, Vec2};
+use std::borrow::Cow;
+use vulkano::DeviceSize;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    Texture,
+    SpriteSheet,
+    Font,
+}
+
+impl AssetKind {
+    const ALL: [AssetKind; 3] = [AssetKind::Texture, AssetKind::SpriteSheet, AssetKind::Font];
+
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        match self {
+            AssetKind::Texture => "Texture",
+            AssetKind::SpriteSheet => "Sprite sheet",
+            AssetKind::Font => "Font",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetEntry {
+    pub name: Cow<'static, str>,
+    pub kind: AssetKind,
+    /// The memory used by the asset, in bytes
+    pub memory: DeviceSize,
+    /// An egui texture and the uv coordinates to show as thumbnail
+    pub thumbnail: Option<(EguiTextureId, Rect)>,
+    /// Whether the asset can be reloaded from its source
+    pub reloadable: bool,
+    /// Additional information, like the dimensions or the source path
+    pub details: Option<String>,
+}
+
+impl AssetEntry {
+    #[inline]
+    pub fn new(name: impl Into<Cow<'static, str>>, kind: AssetKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            memory: 0,
+            thumbnail: None,
+            reloadable: false,
+            details: None,
+        }
+    }
+
+    /// A [`AssetKind::Texture`] with the memory and dimensions of the image of the texture.
+    pub fn texture<T>(name: impl Into<Cow<'static, str>>, texture: &TextureId<T>) -> Self {
+        let image = texture.image();
+        let [width, height, _] = image.extent();
+        Self::new(name, AssetKind::Texture)
+            .with_memory(
+                image
+                    .memory_requirements()
+                    .iter()
+                    .map(|requirements| requirements.layout.size())
+                    .sum(),
+            )
+            .with_details(format!("{width}x{height} {:?}", image.format()))
+    }
+
+    #[inline]
+    pub fn with_memory(mut self, memory: DeviceSize) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    #[inline]
+    pub fn with_thumbnail(mut self, texture: EguiTextureId, uv: Rect) -> Self {
+        self.thumbnail = Some((texture, uv));
+        self
+    }
+
+    #[inline]
+    pub fn with_reloadable(mut self, reloadable: bool) -> Self {
+        self.reloadable = reloadable;
+        self
+    }
+
+    #[inline]
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+}
+
+/// What the user requested for the asset with the given name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AssetAction {
+    Reload(Cow<'static, str>),
+    Unload(Cow<'static, str>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AssetSort {
+    Name,
+    Memory,
+}
+
+pub struct AssetBrowser {
+    filter: String,
+    hidden_kinds: Vec<AssetKind>,
+    sort: AssetSort,
+    thumbnail_size: f32,
+}
+
+impl Default for AssetBrowser {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            filter: String::default(),
+            hidden_kinds: Vec::default(),
+            sort: AssetSort::Name,
+            thumbnail_size: 48.0,
+        }
+    }
+}
+
+impl AssetBrowser {
+    #[inline]
+    pub fn with_thumbnail_size(mut self, size: f32) -> Self {
+        self.thumbnail_size = size;
+        self
+    }
+
+    /// Shows the panel in a window, which can be closed through `open`.
+    pub fn show_window(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        entries: &[AssetEntry],
+    ) -> Vec<AssetAction> {
+        let mut actions = Vec::new();
+        egui::Window::new("Assets")
+            .open(open)
+            .default_size([420.0, 480.0])
+            .show(ctx, |ui| actions = self.show(ui, entries));
+        actions
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, entries: &[AssetEntry]) -> Vec<AssetAction> {
+        self.show_toolbar(ui);
+
+        let mut visible = entries
+            .iter()
+            .filter(|entry| !self.hidden_kinds.contains(&entry.kind))
+            .filter(|entry| {
+                self.filter.is_empty()
+                    || entry
+                        .name
+                        .to_lowercase()
+                        .contains(&self.filter.to_lowercase())
+            })
+            .collect::<Vec<_>>();
+
+        match self.sort {
+            AssetSort::Name => visible.sort_by(|a, b| a.name.cmp(&b.name)),
+            AssetSort::Memory => visible.sort_by(|a, b| b.memory.cmp(&a.memory)),
+        }
+
+        ui.label(format!(
+            "{} of {} assets, {}",
+            visible.len(),
+            entries.len(),
+            format_bytes(visible.iter().map(|entry| entry.memory).sum())
+        ));
+        ui.separator();
+
+        let mut actions = Vec::new();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for entry in visible {
+                ui.horizontal(|ui| {
+                    let size = Vec2::splat(self.thumbnail_size);
+                    match entry.thumbnail {
+                        Some((texture, uv)) => {
+                            ui.add(Image::new((texture, size)).uv(uv));
+                        }
+                        None => {
+                            ui.allocate_space(size);
+                        }
+                    }
+
+                    ui.vertical(|ui| {
+                        ui.label(RichText::new(entry.name.as_ref()).strong());
+                        ui.label(
+                            RichText::new(format!(
+                                "{}, {}",
+                                entry.kind.name(),
+                                format_bytes(entry.memory)
+                            ))
+                            .color(Color32::GRAY),
+                        );
+                        if let Some(details) = &entry.details {
+                            ui.label(RichText::new(details).small().color(Color32::GRAY));
+                        }
+                    });
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("Unload").clicked() {
+                            actions.push(AssetAction::Unload(entry.name.clone()));
+                        }
+                        if ui
+                            .add_enabled(entry.reloadable, egui::Button::new("Reload"))
+                            .clicked()
+                        {
+                            actions.push(AssetAction::Reload(entry.name.clone()));
+                        }
+                    });
+                });
+                ui.separator();
+            }
+        });
+        actions
+    }
+
+    fn show_toolbar(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Filter");
+            ui.text_edit_singleline(&mut self.filter);
+            ui.selectable_value(&mut self.sort, AssetSort::Name, "Name");
+            ui.selectable_value(&mut self.sort, AssetSort::Memory, "Memory");
+        });
+        ui.horizontal(|ui| {
+            for kind in AssetKind::ALL {
+                let mut shown = !self.hidden_kinds.contains(&kind);
+                if ui.checkbox(&mut shown, kind.name()).changed() {
+                    if shown {
+                        self.hidden_kinds.retain(|hidden| *hidden != kind);
+                    } else {
+                        self.hidden_kinds.push(kind);
+                    }
+                }
+            }
+        });
+    }
+}
+
+fn format_bytes(bytes: DeviceSize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
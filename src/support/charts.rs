@@ -0,0 +1,286 @@
+//! Simple charts for telemetry overlays and in-game dashboards. All charts are drawn into a
+//! [`BufferedCanvasLayer`] in screen coordinates, with the y-axis pointing downwards.
+//!
+//! ```no_run
+//! # use hotrod::engine::system::canvas::buffered_layer::BufferedCanvasLayer;
+//! # use hotrod::engine::types::world2d::{Dim, Pos, Rect};
+//! use hotrod::support::charts::{self, ChartStyle};
+//!
+//! # let mut layer = BufferedCanvasLayer::default();
+//! # let frame_times = [16.6, 16.7, 18.2, 16.5];
+//! let style = ChartStyle::default();
+//! let area = Rect::new(Pos::new(10.0, 10.0), Dim::new(200.0, 80.0));
+//! charts::line_chart(&mut layer, area, &[&frame_times], None, &style);
+//! charts::gauge(&mut layer, Pos::new(300.0, 50.0), 40.0, 0.7, 0.0..=1.0, &style);
+//! ```
+
+use crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer;
+use crate::engine::types::world2d::{Dim, Pos, Rect};
+use std::f32::consts::{PI, TAU};
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartStyle {
+    pub axis_color: [f32; 4],
+    pub grid_color: [f32; 4],
+    /// The colors of the bars and series, repeated if there are more bars or series
+    pub colors: Vec<[f32; 4]>,
+    /// The amount of intervals between the ticks of the value axis
+    pub ticks: usize,
+    pub tick_length: f32,
+    /// The space between two bars, relative to the width of a bar
+    pub bar_spacing: f32,
+    /// The amount of line segments of a full circle
+    pub circle_segments: usize,
+}
+
+impl Default for ChartStyle {
+    fn default() -> Self {
+        Self {
+            axis_color: [0.8, 0.8, 0.8, 1.0],
+            grid_color: [0.8, 0.8, 0.8, 0.2],
+            colors: vec![
+                [0.30, 0.65, 1.00, 1.0],
+                [1.00, 0.55, 0.20, 1.0],
+                [0.35, 0.85, 0.40, 1.0],
+                [0.95, 0.30, 0.35, 1.0],
+                [0.75, 0.50, 0.95, 1.0],
+            ],
+            ticks: 4,
+            tick_length: 4.0,
+            bar_spacing: 0.25,
+            circle_segments: 64,
+        }
+    }
+}
+
+impl ChartStyle {
+    #[inline]
+    pub fn color(&self, index: usize) -> [f32; 4] {
+        if self.colors.is_empty() {
+            self.axis_color
+        } else {
+            self.colors[index % self.colors.len()]
+        }
+    }
+}
+
+/// Draws a bar for every value. Without a `range`, the range is derived from the values and
+/// always includes zero, which is where the bars start.
+pub fn bar_chart(
+    layer: &mut BufferedCanvasLayer,
+    rect: Rect<f32>,
+    values: &[f32],
+    range: Option<RangeInclusive<f32>>,
+    style: &ChartStyle,
+) {
+    let range = range.unwrap_or_else(|| value_range(values.iter().copied(), true));
+    draw_axes(layer, rect, &range, style);
+    if values.is_empty() {
+        return;
+    }
+
+    let slot = rect.dim.x / values.len() as f32;
+    let width = slot / (1.0 + style.bar_spacing.max(0.0));
+    let zero = value_to_y(rect, &range, 0.0);
+    for (index, value) in values.iter().enumerate() {
+        let y = value_to_y(rect, &range, *value);
+        layer.set_draw_color(style.color(index));
+        layer.fill_rect(
+            Pos::new(
+                rect.pos.x + slot * index as f32 + (slot - width) * 0.5,
+                zero.min(y),
+            ),
+            Dim::new(width, (zero - y).abs()),
+        );
+    }
+}
+
+/// Draws every series as a line across the full width of the rect. Without a `range`, the range
+/// is derived from the values of all series.
+pub fn line_chart(
+    layer: &mut BufferedCanvasLayer,
+    rect: Rect<f32>,
+    series: &[&[f32]],
+    range: Option<RangeInclusive<f32>>,
+    style: &ChartStyle,
+) {
+    let range = range.unwrap_or_else(|| {
+        value_range(
+            series.iter().flat_map(|values| values.iter().copied()),
+            false,
+        )
+    });
+    draw_axes(layer, rect, &range, style);
+
+    for (index, values) in series.iter().enumerate() {
+        if values.len() < 2 {
+            continue;
+        }
+        let step = rect.dim.x / (values.len() - 1) as f32;
+        let path = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                Pos::new(
+                    rect.pos.x + step * i as f32,
+                    value_to_y(rect, &range, *value),
+                )
+            })
+            .collect::<Vec<_>>();
+        layer.set_draw_color(style.color(index));
+        layer.draw_path(&path);
+    }
+}
+
+/// Draws a radial gauge: the scale covers three quarters of a circle, starting at the bottom
+/// left, and a needle points at the value.
+pub fn gauge(
+    layer: &mut BufferedCanvasLayer,
+    center: Pos<f32>,
+    radius: f32,
+    value: f32,
+    range: RangeInclusive<f32>,
+    style: &ChartStyle,
+) {
+    const START: f32 = PI * 0.75;
+    const SWEEP: f32 = PI * 1.5;
+
+    let progress = normalize(&range, value).clamp(0.0, 1.0);
+    let angle_of = |progress: f32| START + SWEEP * progress;
+    let point = |angle: f32, radius: f32| center + Dim::new(angle.cos(), angle.sin()) * radius;
+    let segments = ((style.circle_segments as f32 * 0.75) as usize).max(2);
+
+    layer.set_draw_color(style.grid_color);
+    layer.draw_path(&arc(center, radius, START, SWEEP, segments));
+
+    layer.set_draw_color(style.color(0));
+    let filled = ((segments as f32 * progress).ceil() as usize).max(1);
+    layer.draw_path(&arc(center, radius, START, SWEEP * progress, filled));
+
+    layer.set_draw_color(style.axis_color);
+    for tick in 0..=style.ticks.max(1) {
+        let angle = angle_of(tick as f32 / style.ticks.max(1) as f32);
+        layer.draw_line(
+            point(angle, radius),
+            point(angle, radius - style.tick_length),
+        );
+    }
+    layer.draw_line(center, point(angle_of(progress), radius * 0.85));
+}
+
+/// Draws a radar chart with one axis per value, the first axis points upwards. The values are
+/// relative to `max`.
+pub fn radar_chart(
+    layer: &mut BufferedCanvasLayer,
+    center: Pos<f32>,
+    radius: f32,
+    values: &[f32],
+    max: f32,
+    style: &ChartStyle,
+) {
+    if values.len() < 3 {
+        return;
+    }
+
+    let corner = |index: usize, radius: f32| {
+        let angle = -PI * 0.5 + TAU * index as f32 / values.len() as f32;
+        center + Dim::new(angle.cos(), angle.sin()) * radius
+    };
+    let polygon = |radius: &dyn Fn(usize) -> f32| {
+        (0..=values.len())
+            .map(|index| corner(index % values.len(), radius(index % values.len())))
+            .collect::<Vec<_>>()
+    };
+
+    layer.set_draw_color(style.grid_color);
+    for ring in 1..=style.ticks.max(1) {
+        let ring_radius = radius * ring as f32 / style.ticks.max(1) as f32;
+        layer.draw_path(&polygon(&|_| ring_radius));
+    }
+
+    layer.set_draw_color(style.axis_color);
+    for index in 0..values.len() {
+        layer.draw_line(center, corner(index, radius));
+    }
+
+    layer.set_draw_color(style.color(0));
+    let scale = if max > 0.0 { radius / max } else { 0.0 };
+    layer.draw_path(&polygon(&|index| {
+        (values[index] * scale).clamp(0.0, radius)
+    }));
+}
+
+fn draw_axes(
+    layer: &mut BufferedCanvasLayer,
+    rect: Rect<f32>,
+    range: &RangeInclusive<f32>,
+    style: &ChartStyle,
+) {
+    let left = rect.pos.x;
+    let right = rect.pos.x + rect.dim.x;
+    let ticks = style.ticks.max(1);
+
+    for tick in 0..=ticks {
+        let value = range.start() + (range.end() - range.start()) * tick as f32 / ticks as f32;
+        let y = value_to_y(rect, range, value);
+        layer.set_draw_color(style.grid_color);
+        layer.draw_line(Pos::new(left, y), Pos::new(right, y));
+        layer.set_draw_color(style.axis_color);
+        layer.draw_line(Pos::new(left - style.tick_length, y), Pos::new(left, y));
+    }
+
+    layer.set_draw_color(style.axis_color);
+    let baseline = value_to_y(rect, range, 0.0_f32.clamp(*range.start(), *range.end()));
+    layer.draw_line(
+        Pos::new(left, rect.pos.y),
+        Pos::new(left, rect.pos.y + rect.dim.y),
+    );
+    layer.draw_line(Pos::new(left, baseline), Pos::new(right, baseline));
+}
+
+fn arc(center: Pos<f32>, radius: f32, start: f32, sweep: f32, segments: usize) -> Vec<Pos<f32>> {
+    (0..=segments)
+        .map(|segment| {
+            let angle = start + sweep * segment as f32 / segments as f32;
+            center + Dim::new(angle.cos(), angle.sin()) * radius
+        })
+        .collect()
+}
+
+/// The range of the values, widened if all values are equal.
+fn value_range(values: impl Iterator<Item = f32>, include_zero: bool) -> RangeInclusive<f32> {
+    let initial = if include_zero {
+        (0.0, 0.0)
+    } else {
+        (f32::INFINITY, f32::NEG_INFINITY)
+    };
+    let (min, max) = values
+        .filter(|value| value.is_finite())
+        .fold(initial, |(min, max), value| {
+            (min.min(value), max.max(value))
+        });
+    if min > max {
+        0.0..=1.0
+    } else if min == max {
+        min - 0.5..=max + 0.5
+    } else {
+        min..=max
+    }
+}
+
+#[inline]
+fn normalize(range: &RangeInclusive<f32>, value: f32) -> f32 {
+    let span = range.end() - range.start();
+    if span == 0.0 {
+        0.0
+    } else {
+        (value - range.start()) / span
+    }
+}
+
+/// The y coordinate of the value, the start of the range is at the bottom of the rect.
+#[inline]
+fn value_to_y(rect: Rect<f32>, range: &RangeInclusive<f32>, value: f32) -> f32 {
+    rect.pos.y + rect.dim.y * (1.0 - normalize(range, value).clamp(0.0, 1.0))
+}
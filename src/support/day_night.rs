@@ -0,0 +1,124 @@
+//! A day/night cycle that derives the [`AmbientLight`] from the game time.
+//!
+//! ```no_run
+//! # use hotrod::engine::Engine;
+//! # use std::time::Duration;
+//! use hotrod::support::day_night::DayNightCycle;
+//!
+//! # let mut engine = Engine::default();
+//! # let game_time = Duration::ZERO;
+//! // one in-game day lasts 20 real minutes
+//! let cycle = DayNightCycle::default().with_day_length(Duration::from_secs(20 * 60));
+//! engine.set_ambient_light(cycle.ambient_light(game_time));
+//! ```
+
+use crate::engine::system::vulkan::desc::binding_202_ambient_light::AmbientLight;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayNightCycle {
+    /// The colors at the given hour (`0.0..24.0`), sorted by the hour
+    gradient: Vec<(f32, [f32; 3])>,
+    day_length: Duration,
+    /// The hour at a game time of zero
+    start_hour: f32,
+}
+
+impl Default for DayNightCycle {
+    fn default() -> Self {
+        Self::new([
+            (0.0, [0.25, 0.3, 0.5]),
+            (5.0, [0.3, 0.32, 0.5]),
+            (7.0, [1.0, 0.75, 0.6]),
+            (10.0, [1.0, 1.0, 1.0]),
+            (17.0, [1.0, 1.0, 0.95]),
+            (19.5, [1.0, 0.6, 0.45]),
+            (21.5, [0.35, 0.35, 0.6]),
+        ])
+    }
+}
+
+impl DayNightCycle {
+    pub const HOURS_PER_DAY: f32 = 24.0;
+
+    /// The gradient is sorted by the hour and interpolated linearly, it wraps around from the
+    /// last to the first color at midnight.
+    pub fn new(gradient: impl IntoIterator<Item = (f32, [f32; 3])>) -> Self {
+        let mut gradient = gradient
+            .into_iter()
+            .map(|(hour, color)| (hour.rem_euclid(Self::HOURS_PER_DAY), color))
+            .collect::<Vec<_>>();
+        gradient.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            gradient,
+            day_length: Duration::from_secs(24 * 60),
+            start_hour: 8.0,
+        }
+    }
+
+    /// How long a full cycle of 24 in-game hours lasts in game time.
+    #[inline]
+    pub fn with_day_length(mut self, day_length: Duration) -> Self {
+        self.day_length = day_length;
+        self
+    }
+
+    /// The in-game hour at a game time of zero.
+    #[inline]
+    pub fn with_start_hour(mut self, hour: f32) -> Self {
+        self.start_hour = hour.rem_euclid(Self::HOURS_PER_DAY);
+        self
+    }
+
+    #[inline]
+    pub fn day_length(&self) -> Duration {
+        self.day_length
+    }
+
+    /// The in-game hour (`0.0..24.0`) at the given game time.
+    pub fn hour_at(&self, game_time: Duration) -> f32 {
+        let day_length = self.day_length.as_secs_f64();
+        if day_length <= 0.0 {
+            return self.start_hour;
+        }
+        let progress = (game_time.as_secs_f64() / day_length).fract() as f32;
+        (self.start_hour + progress * Self::HOURS_PER_DAY).rem_euclid(Self::HOURS_PER_DAY)
+    }
+
+    /// The interpolated color of the gradient at the given in-game hour.
+    pub fn color_at_hour(&self, hour: f32) -> [f32; 3] {
+        let hour = hour.rem_euclid(Self::HOURS_PER_DAY);
+        let (Some(first), Some(last)) = (self.gradient.first(), self.gradient.last()) else {
+            return [1.0, 1.0, 1.0];
+        };
+
+        // the neighbouring colors, wrapping around midnight
+        let next = self
+            .gradient
+            .iter()
+            .position(|(h, _)| *h > hour)
+            .unwrap_or(self.gradient.len());
+        let (from, to) = match next {
+            0 => ((last.0 - Self::HOURS_PER_DAY, last.1), *first),
+            n if n == self.gradient.len() => (*last, (first.0 + Self::HOURS_PER_DAY, first.1)),
+            n => (self.gradient[n - 1], self.gradient[n]),
+        };
+
+        let span = to.0 - from.0;
+        let t = if span > 0.0 {
+            (hour - from.0) / span
+        } else {
+            0.0
+        };
+        [
+            from.1[0] + (to.1[0] - from.1[0]) * t,
+            from.1[1] + (to.1[1] - from.1[1]) * t,
+            from.1[2] + (to.1[2] - from.1[2]) * t,
+        ]
+    }
+
+    #[inline]
+    pub fn ambient_light(&self, game_time: Duration) -> AmbientLight {
+        AmbientLight::from(self.color_at_hour(self.hour_at(game_time)))
+    }
+}
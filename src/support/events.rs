@@ -0,0 +1,241 @@
+//! A typed publish/subscribe bus, so that gameplay systems can communicate without holding
+//! references to each other.
+//!
+//! Events are kept in two buffers: those published in the current frame and those of the
+//! previous frame. [`EventBus::update`] has to be called once per frame and drops the events of
+//! the previous frame, so every reader that is polled once per frame sees every event exactly
+//! once, regardless of whether it is polled before or after the publisher.
+//!
+//! ```no_run
+//! use hotrod::support::events::{EventBus, EventReader};
+//!
+//! struct DamageDealt {
+//!     target: u32,
+//!     amount: f32,
+//! }
+//!
+//! let mut bus = EventBus::default();
+//! let mut reader = EventReader::<DamageDealt>::default();
+//!
+//! // in the game loop
+//! bus.publish(DamageDealt { target: 7, amount: 12.5 });
+//! for damage in reader.iter(&bus) {
+//!     println!("{} took {} damage", damage.target, damage.amount);
+//! }
+//! bus.update();
+//! ```
+
+use rustc_hash::FxHashMap;
+use std::any::{Any, TypeId};
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+
+/// Implemented for every type that can be sent over the [`EventBus`].
+pub trait Event: Send + Sync + 'static {}
+
+impl<T: Send + Sync + 'static> Event for T {}
+
+/// The double buffered events of a single type, events are identified by a running number.
+struct Channel<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    /// The number of the first event in `previous`
+    previous_start: u64,
+    /// The number of the first event in `current`
+    current_start: u64,
+}
+
+impl<T> Default for Channel<T> {
+    fn default() -> Self {
+        Self {
+            previous: Vec::default(),
+            current: Vec::default(),
+            previous_start: 0,
+            current_start: 0,
+        }
+    }
+}
+
+impl<T> Channel<T> {
+    #[inline]
+    fn end(&self) -> u64 {
+        self.current_start + self.current.len() as u64
+    }
+}
+
+trait ErasedChannel: Send + Sync {
+    fn update(&mut self);
+
+    fn clear(&mut self);
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Event> ErasedChannel for Channel<T> {
+    fn update(&mut self) {
+        // reuses the allocation of the dropped events for the next frame
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+        self.previous_start = self.current_start;
+        self.current_start += self.previous.len() as u64;
+    }
+
+    fn clear(&mut self) {
+        let end = self.end();
+        self.previous.clear();
+        self.current.clear();
+        self.previous_start = end;
+        self.current_start = end;
+    }
+
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[inline]
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    channels: FxHashMap<TypeId, Box<dyn ErasedChannel>>,
+}
+
+impl Debug for EventBus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field("channels", &self.channels.len())
+            .finish()
+    }
+}
+
+impl EventBus {
+    pub fn publish<T: Event>(&mut self, event: T) {
+        self.channel_mut::<T>().current.push(event);
+    }
+
+    pub fn publish_all<T: Event>(&mut self, events: impl IntoIterator<Item = T>) {
+        self.channel_mut::<T>().current.extend(events);
+    }
+
+    /// A reader that only sees events published from now on.
+    pub fn reader<T: Event>(&self) -> EventReader<T> {
+        EventReader {
+            next: self.channel::<T>().map_or(0, Channel::end),
+            _type: PhantomData,
+        }
+    }
+
+    /// Whether events of the given type were published in this or the previous frame.
+    pub fn has_events<T: Event>(&self) -> bool {
+        self.channel::<T>()
+            .is_some_and(|c| !c.previous.is_empty() || !c.current.is_empty())
+    }
+
+    /// Drops the events of the previous frame, must be called once per frame.
+    pub fn update(&mut self) {
+        for channel in self.channels.values_mut() {
+            channel.update();
+        }
+    }
+
+    /// Drops all events, for example when loading another level.
+    pub fn clear(&mut self) {
+        for channel in self.channels.values_mut() {
+            channel.clear();
+        }
+    }
+
+    fn channel<T: Event>(&self) -> Option<&Channel<T>> {
+        self.channels
+            .get(&TypeId::of::<T>())
+            .and_then(|channel| channel.as_any().downcast_ref())
+    }
+
+    fn channel_mut<T: Event>(&mut self) -> &mut Channel<T> {
+        self.channels
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::<Channel<T>>::default())
+            .as_any_mut()
+            .downcast_mut()
+            .expect("Channel registered for another type")
+    }
+}
+
+/// Remembers which events of a type were already read. Events that are not read within the frame
+/// they were published in or the frame after are missed.
+pub struct EventReader<T> {
+    next: u64,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventReader<T> {
+    /// A reader that sees all events that are still buffered.
+    fn default() -> Self {
+        Self {
+            next: 0,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for EventReader<T> {
+    fn clone(&self) -> Self {
+        Self {
+            next: self.next,
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T> Debug for EventReader<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventReader")
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+impl<T: Event> EventReader<T> {
+    /// The events that were published since the last call, in the order of publication.
+    pub fn iter<'a>(&mut self, bus: &'a EventBus) -> impl Iterator<Item = &'a T> + 'a {
+        let (previous, current): (&[T], &[T]) = match bus.channel::<T>() {
+            Some(channel) => {
+                let previous_skip = self.next.saturating_sub(channel.previous_start) as usize;
+                let current_skip = self.next.saturating_sub(channel.current_start) as usize;
+                self.next = channel.end();
+                (
+                    channel.previous.get(previous_skip..).unwrap_or_default(),
+                    channel.current.get(current_skip..).unwrap_or_default(),
+                )
+            }
+            None => (&[], &[]),
+        };
+        previous.iter().chain(current.iter())
+    }
+
+    /// The amount of events that [`EventReader::iter`] would return.
+    pub fn len(&self, bus: &EventBus) -> usize {
+        bus.channel::<T>().map_or(0, |channel| {
+            let start = self.next.max(channel.previous_start);
+            channel.end().saturating_sub(start) as usize
+        })
+    }
+
+    #[inline]
+    pub fn is_empty(&self, bus: &EventBus) -> bool {
+        self.len(bus) == 0
+    }
+
+    /// Marks all events as read.
+    pub fn clear(&mut self, bus: &EventBus) {
+        if let Some(channel) = bus.channel::<T>() {
+            self.next = channel.end();
+        }
+    }
+}
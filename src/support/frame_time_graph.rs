@@ -0,0 +1,257 @@
+//! A graph of the most recent frame times of the [`FpsManager`], for quick performance checks
+//! during development. Every frame is a bar, frames that missed the target frame duration are
+//! highlighted and the 50th, 95th and 99th percentile are marked by horizontal lines.
+//!
+//! By default, the vertical axis is histogram-equalized (see [`FrameTimeScale::Equalized`]): the
+//! height of a bar is the share of the recent frames that took at most as long, so small
+//! variations between many similar frames are visible while hitches stay at the top. With
+//! [`FrameTimeScale::Linear`], the height is proportional to the duration instead.
+
+use crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer;
+use crate::engine::system::fps::{FpsManager, FrameStatistics};
+use crate::engine::types::world2d::{Dim, Pos, Rect};
+use std::time::Duration;
+
+/// How the frame times are mapped onto the vertical axis of a [`FrameTimeGraph`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum FrameTimeScale {
+    /// Proportional to the duration, up to the 99th percentile (but at least the
+    /// [`FrameTimeGraph::with_min_scale`]) instead of the maximum, so a single hitch does not
+    /// flatten the rest of the graph. Bars above the scale are clipped.
+    Linear,
+    /// Remapped through the cumulative distribution of the recent frame times, so the
+    /// percentile markers are at 50%, 95% and 99% of the height and the target band shows the
+    /// share of frames that met the target.
+    #[default]
+    Equalized,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameTimeGraph {
+    background_color: [f32; 4],
+    target_band_color: [f32; 4],
+    frame_color: [f32; 4],
+    slow_frame_color: [f32; 4],
+    percentile_colors: [[f32; 4]; 3],
+    scale: FrameTimeScale,
+    /// The minimal duration shown, relative to the target frame duration
+    min_scale: f32,
+}
+
+impl Default for FrameTimeGraph {
+    fn default() -> Self {
+        Self {
+            background_color: [0.0, 0.0, 0.0, 0.5],
+            target_band_color: [0.2, 0.8, 0.3, 0.15],
+            frame_color: [0.3, 0.8, 0.4, 0.9],
+            slow_frame_color: [1.0, 0.3, 0.25, 0.9],
+            percentile_colors: [
+                [1.0, 1.0, 1.0, 0.6],
+                [1.0, 0.8, 0.2, 0.8],
+                [1.0, 0.4, 0.2, 0.8],
+            ],
+            scale: FrameTimeScale::default(),
+            min_scale: 2.0,
+        }
+    }
+}
+
+impl FrameTimeGraph {
+    #[inline]
+    pub fn with_background_color(mut self, color: [f32; 4]) -> Self {
+        self.background_color = color;
+        self
+    }
+
+    /// The color of the band between zero and the target frame duration.
+    #[inline]
+    pub fn with_target_band_color(mut self, color: [f32; 4]) -> Self {
+        self.target_band_color = color;
+        self
+    }
+
+    #[inline]
+    pub fn with_frame_colors(mut self, frame: [f32; 4], slow_frame: [f32; 4]) -> Self {
+        self.frame_color = frame;
+        self.slow_frame_color = slow_frame;
+        self
+    }
+
+    /// The colors of the 50th, 95th and 99th percentile markers.
+    #[inline]
+    pub fn with_percentile_colors(mut self, colors: [[f32; 4]; 3]) -> Self {
+        self.percentile_colors = colors;
+        self
+    }
+
+    #[inline]
+    pub fn with_scale(mut self, scale: FrameTimeScale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// With the [`FrameTimeScale::Linear`] scale, the graph always shows at least this multiple
+    /// of the target frame duration.
+    #[inline]
+    pub fn with_min_scale(mut self, min_scale: f32) -> Self {
+        self.min_scale = min_scale;
+        self
+    }
+
+    fn heights(&self, target: Duration, statistics: &FrameStatistics, fps: &FpsManager) -> Heights {
+        match self.scale {
+            FrameTimeScale::Equalized if fps.frame_times().len() > 0 => {
+                let mut sorted = fps.frame_times().collect::<Vec<_>>();
+                sorted.sort_unstable();
+                Heights::Equalized(sorted)
+            }
+            FrameTimeScale::Linear | FrameTimeScale::Equalized => Heights::Linear(
+                (target.as_secs_f32() * self.min_scale)
+                    .max(statistics.p99.as_secs_f32() * 1.1)
+                    .max(f32::EPSILON),
+            ),
+        }
+    }
+
+    /// Draws the graph into the rect, the layer is expected to draw in screen coordinates.
+    pub fn draw(&self, layer: &mut BufferedCanvasLayer, rect: Rect<f32>, fps: &FpsManager) {
+        let target = fps.target_frame_duration();
+        let statistics = fps.statistics();
+        let heights = self.heights(target, &statistics, fps);
+        let height_of = |duration: Duration| rect.dim.y * heights.of(duration);
+        let bottom = rect.pos.y + rect.dim.y;
+
+        layer.set_draw_color(self.background_color);
+        layer.fill_rect(rect.pos, rect.dim);
+
+        let band = height_of(target);
+        layer.set_draw_color(self.target_band_color);
+        layer.fill_rect(
+            Pos::new(rect.pos.x, bottom - band),
+            Dim::new(rect.dim.x, band),
+        );
+
+        let frames = fps.frame_times();
+        let count = frames.len();
+        if count > 0 {
+            let width = rect.dim.x / count as f32;
+            for (index, frame_time) in frames.enumerate() {
+                let height = height_of(frame_time);
+                layer.set_draw_color(if frame_time > target {
+                    self.slow_frame_color
+                } else {
+                    self.frame_color
+                });
+                layer.fill_rect(
+                    Pos::new(rect.pos.x + width * index as f32, bottom - height),
+                    Dim::new(width, height),
+                );
+            }
+        }
+
+        for (duration, color) in percentiles(&statistics).zip(self.percentile_colors) {
+            let y = bottom - height_of(duration);
+            layer.set_draw_color(color);
+            layer.draw_line(
+                Pos::new(rect.pos.x, y),
+                Pos::new(rect.pos.x + rect.dim.x, y),
+            );
+        }
+    }
+
+    /// Shows the graph with the given size and the percentiles as text below.
+    #[cfg(feature = "ui-egui")]
+    pub fn show(&self, ui: &mut egui::Ui, size: egui::Vec2, fps: &FpsManager) -> egui::Response {
+        use egui::{Color32, Pos2, Rect, Rounding, Stroke};
+
+        let color = |[r, g, b, a]: [f32; 4]| {
+            Color32::from_rgba_unmultiplied(
+                (r * 255.0) as u8,
+                (g * 255.0) as u8,
+                (b * 255.0) as u8,
+                (a * 255.0) as u8,
+            )
+        };
+
+        let target = fps.target_frame_duration();
+        let statistics = fps.statistics();
+        let heights = self.heights(target, &statistics, fps);
+
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let y_of = |duration: Duration| rect.bottom() - rect.height() * heights.of(duration);
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, Rounding::ZERO, color(self.background_color));
+        painter.rect_filled(
+            Rect::from_min_max(Pos2::new(rect.left(), y_of(target)), rect.right_bottom()),
+            Rounding::ZERO,
+            color(self.target_band_color),
+        );
+
+        let frames = fps.frame_times();
+        let count = frames.len();
+        if count > 0 {
+            let width = rect.width() / count as f32;
+            for (index, frame_time) in frames.enumerate() {
+                let left = rect.left() + width * index as f32;
+                painter.rect_filled(
+                    Rect::from_min_max(
+                        Pos2::new(left, y_of(frame_time)),
+                        Pos2::new(left + width, rect.bottom()),
+                    ),
+                    Rounding::ZERO,
+                    color(if frame_time > target {
+                        self.slow_frame_color
+                    } else {
+                        self.frame_color
+                    }),
+                );
+            }
+        }
+
+        for (duration, marker) in percentiles(&statistics).zip(self.percentile_colors) {
+            let y = y_of(duration);
+            painter.line_segment(
+                [Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)],
+                Stroke::new(1.0, color(marker)),
+            );
+        }
+
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        ui.label(format!(
+            "p50 {:.1} ms, p95 {:.1} ms, p99 {:.1} ms, max {:.1} ms (target {:.1} ms)",
+            millis(statistics.p50),
+            millis(statistics.p95),
+            millis(statistics.p99),
+            millis(statistics.max),
+            millis(target),
+        ));
+        response
+    }
+}
+
+/// Maps durations onto the vertical axis, see [`FrameTimeScale`].
+enum Heights {
+    /// The duration at the top of the graph, in seconds
+    Linear(f32),
+    /// The recent frame times in ascending order, never empty
+    Equalized(Vec<Duration>),
+}
+
+impl Heights {
+    /// The height of the duration, from `0.0` at the bottom to `1.0` at the top.
+    fn of(&self, duration: Duration) -> f32 {
+        match self {
+            Heights::Linear(scale) => (duration.as_secs_f32() / scale).clamp(0.0, 1.0),
+            Heights::Equalized(sorted) => {
+                let at_most = sorted.partition_point(|frame_time| *frame_time <= duration);
+                at_most as f32 / sorted.len() as f32
+            }
+        }
+    }
+}
+
+#[inline]
+fn percentiles(statistics: &FrameStatistics) -> impl Iterator<Item = Duration> {
+    [statistics.p50, statistics.p95, statistics.p99].into_iter()
+}
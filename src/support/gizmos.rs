@@ -0,0 +1,394 @@
+//! Translate, scale and rotate handles for world2d entities, the building block for in-engine
+//! level editors. A [`Gizmo`] is drawn in screen space at the position of the selected entity,
+//! so its size does not change with the zoom of the [`Map2dView`]. Dragging a handle emits
+//! [`TransformDelta`]s that the editor applies to the entity.
+//!
+//! ```no_run
+//! # use hotrod::support::gizmos::{Gizmo, GizmoMode, Transform2d};
+//! # use hotrod::support::world2d::view::Map2dView;
+//! # let view = Map2dView::new(800, 600);
+//! # let events: Vec<sdl2::event::Event> = Vec::new();
+//! let mut gizmo = Gizmo::new(GizmoMode::Translate);
+//! let mut transform = Transform2d::default();
+//! for event in &events {
+//!     if let Some(delta) = gizmo.handle_event(&view, transform.position, event) {
+//!         transform.apply(delta);
+//!     }
+//! }
+//! ```
+
+use crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer;
+use crate::engine::types::world2d::{Dim, Pos};
+use crate::support::world2d::view::Map2dView;
+use cgmath::InnerSpace;
+use sdl2::event::Event;
+use sdl2::mouse::MouseButton;
+use std::f32::consts::{PI, TAU};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GizmoMode {
+    Translate,
+    Scale,
+    Rotate,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GizmoHandle {
+    TranslateX,
+    TranslateY,
+    TranslateFree,
+    ScaleX,
+    ScaleY,
+    ScaleUniform,
+    Rotate,
+}
+
+/// The position, scale and rotation (in radians, clockwise on the screen) of an entity.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform2d {
+    pub position: Pos<f32>,
+    pub scale: Dim<f32>,
+    pub rotation: f32,
+}
+
+impl Default for Transform2d {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            position: Pos::new(0.0, 0.0),
+            scale: Dim::new(1.0, 1.0),
+            rotation: 0.0,
+        }
+    }
+}
+
+impl Transform2d {
+    #[inline]
+    pub fn apply(&mut self, delta: TransformDelta) {
+        self.position += delta.translation;
+        self.scale.x *= delta.scale.x;
+        self.scale.y *= delta.scale.y;
+        self.rotation = (self.rotation + delta.rotation).rem_euclid(TAU);
+    }
+}
+
+/// The change since the previous delta of the same drag, so the deltas of a drag can be applied
+/// one after another.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TransformDelta {
+    /// In world units
+    pub translation: Dim<f32>,
+    /// The factor to multiply the current scale with
+    pub scale: Dim<f32>,
+    /// In radians
+    pub rotation: f32,
+}
+
+impl Default for TransformDelta {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            translation: Dim::new(0.0, 0.0),
+            scale: Dim::new(1.0, 1.0),
+            rotation: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Drag {
+    handle: GizmoHandle,
+    /// The previous mouse position, in screen coordinates
+    last: Pos<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Gizmo {
+    mode: GizmoMode,
+    size: f32,
+    tolerance: f32,
+    hovered: Option<GizmoHandle>,
+    drag: Option<Drag>,
+}
+
+impl Gizmo {
+    const COLOR_X: [f32; 4] = [0.9, 0.2, 0.2, 1.0];
+    const COLOR_Y: [f32; 4] = [0.2, 0.9, 0.2, 1.0];
+    const COLOR_FREE: [f32; 4] = [0.3, 0.5, 1.0, 1.0];
+    const COLOR_ACTIVE: [f32; 4] = [1.0, 0.9, 0.1, 1.0];
+    const CIRCLE_SEGMENTS: usize = 48;
+    /// Below this distance from the origin (in pixels), scale and rotate drags are ignored
+    /// because the result would jump wildly.
+    const MIN_DRAG_DISTANCE: f32 = 2.0;
+
+    #[inline]
+    pub fn new(mode: GizmoMode) -> Self {
+        Self {
+            mode,
+            size: 80.0,
+            tolerance: 6.0,
+            hovered: None,
+            drag: None,
+        }
+    }
+
+    /// The length of the handles, in pixels.
+    #[inline]
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// How far away from a handle the mouse may be to still grab it, in pixels.
+    #[inline]
+    pub fn with_tolerance(mut self, tolerance: f32) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    #[inline]
+    pub fn mode(&self) -> GizmoMode {
+        self.mode
+    }
+
+    /// Changing the mode cancels the current drag.
+    #[inline]
+    pub fn set_mode(&mut self, mode: GizmoMode) {
+        self.mode = mode;
+        self.drag = None;
+        self.hovered = None;
+    }
+
+    #[inline]
+    pub fn hovered(&self) -> Option<GizmoHandle> {
+        self.hovered
+    }
+
+    #[inline]
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Whether the events are consumed by the gizmo and should not be processed further, for
+    /// example to not deselect the entity or pan the view.
+    #[inline]
+    pub fn wants_mouse_input(&self) -> bool {
+        self.drag.is_some() || self.hovered.is_some()
+    }
+
+    /// The handle at the given screen position for an entity at the world position `origin`.
+    pub fn hit_test(
+        &self,
+        view: &Map2dView,
+        origin: Pos<f32>,
+        mouse: Pos<f32>,
+    ) -> Option<GizmoHandle> {
+        let center = view.position_world_to_screen(origin);
+        let offset = mouse - center;
+        let size = self.size;
+        let tolerance = self.tolerance;
+
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                let (free, x, y) = match self.mode {
+                    GizmoMode::Translate => (
+                        GizmoHandle::TranslateFree,
+                        GizmoHandle::TranslateX,
+                        GizmoHandle::TranslateY,
+                    ),
+                    _ => (
+                        GizmoHandle::ScaleUniform,
+                        GizmoHandle::ScaleX,
+                        GizmoHandle::ScaleY,
+                    ),
+                };
+                let free_size = size * 0.25;
+                if (0.0..=free_size).contains(&offset.x) && (0.0..=free_size).contains(&offset.y) {
+                    Some(free)
+                } else if offset.y.abs() <= tolerance
+                    && (0.0..=size + tolerance).contains(&offset.x)
+                {
+                    Some(x)
+                } else if offset.x.abs() <= tolerance
+                    && (0.0..=size + tolerance).contains(&offset.y)
+                {
+                    Some(y)
+                } else {
+                    None
+                }
+            }
+            GizmoMode::Rotate => {
+                if (offset.magnitude() - size).abs() <= tolerance {
+                    Some(GizmoHandle::Rotate)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Updates the hovered handle and the current drag, returns the [`TransformDelta`] if the
+    /// entity at the world position `origin` was dragged.
+    pub fn handle_event(
+        &mut self,
+        view: &Map2dView,
+        origin: Pos<f32>,
+        event: &Event,
+    ) -> Option<TransformDelta> {
+        match *event {
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                let mouse = Pos::new(x as f32, y as f32);
+                self.drag = self.hit_test(view, origin, mouse).map(|handle| Drag {
+                    handle,
+                    last: mouse,
+                });
+                None
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => {
+                self.drag = None;
+                None
+            }
+            Event::MouseMotion { x, y, .. } => {
+                let mouse = Pos::new(x as f32, y as f32);
+                match self.drag {
+                    Some(drag) => {
+                        self.hovered = Some(drag.handle);
+                        let delta = Self::drag_delta(view, origin, drag, mouse);
+                        if delta.is_some() {
+                            self.drag = Some(Drag {
+                                last: mouse,
+                                ..drag
+                            });
+                        }
+                        delta
+                    }
+                    None => {
+                        self.hovered = self.hit_test(view, origin, mouse);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn drag_delta(
+        view: &Map2dView,
+        origin: Pos<f32>,
+        drag: Drag,
+        mouse: Pos<f32>,
+    ) -> Option<TransformDelta> {
+        let center = view.position_world_to_screen(origin);
+        let last = drag.last - center;
+        let current = mouse - center;
+        let zoom = view.zoom();
+
+        let scale_factor = |last: f32, current: f32| {
+            if last.abs() < Self::MIN_DRAG_DISTANCE || current.abs() < Self::MIN_DRAG_DISTANCE {
+                None
+            } else {
+                Some(current / last)
+            }
+        };
+
+        let mut delta = TransformDelta::default();
+        match drag.handle {
+            GizmoHandle::TranslateX => delta.translation.x = (mouse.x - drag.last.x) / zoom,
+            GizmoHandle::TranslateY => delta.translation.y = (mouse.y - drag.last.y) / zoom,
+            GizmoHandle::TranslateFree => delta.translation = (mouse - drag.last) / zoom,
+            GizmoHandle::ScaleX => delta.scale.x = scale_factor(last.x, current.x)?,
+            GizmoHandle::ScaleY => delta.scale.y = scale_factor(last.y, current.y)?,
+            GizmoHandle::ScaleUniform => {
+                let factor = scale_factor(last.magnitude(), current.magnitude())?;
+                delta.scale = Dim::new(factor, factor);
+            }
+            GizmoHandle::Rotate => {
+                if last.magnitude() < Self::MIN_DRAG_DISTANCE
+                    || current.magnitude() < Self::MIN_DRAG_DISTANCE
+                {
+                    return None;
+                }
+                let angle = current.y.atan2(current.x) - last.y.atan2(last.x);
+                // the shortest rotation, in -PI..PI
+                delta.rotation = (angle + PI).rem_euclid(TAU) - PI;
+            }
+        }
+        Some(delta)
+    }
+
+    /// Draws the handles of the current mode for an entity at the world position `origin`, the
+    /// layer is expected to draw in screen coordinates.
+    pub fn draw(&self, layer: &mut BufferedCanvasLayer, view: &Map2dView, origin: Pos<f32>) {
+        let center = view.position_world_to_screen(origin);
+        let size = self.size;
+        let active = self.drag.map(|drag| drag.handle).or(self.hovered);
+        let color = |handle: GizmoHandle, color: [f32; 4]| {
+            if active == Some(handle) {
+                Self::COLOR_ACTIVE
+            } else {
+                color
+            }
+        };
+
+        match self.mode {
+            GizmoMode::Translate => {
+                let head = size * 0.15;
+                layer.set_draw_color(color(GizmoHandle::TranslateX, Self::COLOR_X));
+                layer.draw_line(center, center + Dim::new(size, 0.0));
+                layer.draw_path(&[
+                    center + Dim::new(size - head, -head * 0.5),
+                    center + Dim::new(size, 0.0),
+                    center + Dim::new(size - head, head * 0.5),
+                ]);
+
+                layer.set_draw_color(color(GizmoHandle::TranslateY, Self::COLOR_Y));
+                layer.draw_line(center, center + Dim::new(0.0, size));
+                layer.draw_path(&[
+                    center + Dim::new(-head * 0.5, size - head),
+                    center + Dim::new(0.0, size),
+                    center + Dim::new(head * 0.5, size - head),
+                ]);
+
+                layer.set_draw_color(color(GizmoHandle::TranslateFree, Self::COLOR_FREE));
+                layer.draw_rect(center, Dim::new(size * 0.25, size * 0.25));
+            }
+            GizmoMode::Scale => {
+                let knob = size * 0.1;
+                layer.set_draw_color(color(GizmoHandle::ScaleX, Self::COLOR_X));
+                layer.draw_line(center, center + Dim::new(size, 0.0));
+                layer.fill_rect(
+                    center + Dim::new(size - knob * 0.5, -knob * 0.5),
+                    Dim::new(knob, knob),
+                );
+
+                layer.set_draw_color(color(GizmoHandle::ScaleY, Self::COLOR_Y));
+                layer.draw_line(center, center + Dim::new(0.0, size));
+                layer.fill_rect(
+                    center + Dim::new(-knob * 0.5, size - knob * 0.5),
+                    Dim::new(knob, knob),
+                );
+
+                layer.set_draw_color(color(GizmoHandle::ScaleUniform, Self::COLOR_FREE));
+                layer.fill_rect(center, Dim::new(size * 0.25, size * 0.25));
+            }
+            GizmoMode::Rotate => {
+                layer.set_draw_color(color(GizmoHandle::Rotate, Self::COLOR_FREE));
+                let circle = (0..=Self::CIRCLE_SEGMENTS)
+                    .map(|index| {
+                        let angle = index as f32 * TAU / Self::CIRCLE_SEGMENTS as f32;
+                        center + Dim::new(angle.cos(), angle.sin()) * size
+                    })
+                    .collect::<Vec<_>>();
+                layer.draw_path(&circle);
+            }
+        }
+    }
+}
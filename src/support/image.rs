@@ -35,4 +35,23 @@ impl RawRgbaImage {
     pub fn destruct(self) -> (Cow<'static, [u8]>, u32, u32) {
         (self.data, self.width, self.height)
     }
+
+    /// [`None`] if the data does not match the size.
+    #[cfg(feature = "image")]
+    pub fn to_dynamic_image(&self) -> Option<image::DynamicImage> {
+        image::RgbaImage::from_raw(self.width, self.height, self.data.to_vec())
+            .map(image::DynamicImage::ImageRgba8)
+    }
+
+    /// Encodes the image in the format of the file extension, like `.png`.
+    #[cfg(feature = "image")]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), image::ImageError> {
+        image::save_buffer(
+            path,
+            self.data(),
+            self.width,
+            self.height,
+            image::ColorType::Rgba8,
+        )
+    }
 }
@@ -0,0 +1,266 @@
+//! Aggregates the progress of loading assets, which may happen on other threads, so that a
+//! loading screen can be shown until all assets are ready.
+//!
+//! ```no_run
+//! # use hotrod::engine::Engine;
+//! use hotrod::support::loading::{LoadingProgress, LoadingScreen};
+//!
+//! # let engine = Engine::default();
+//! let progress = LoadingProgress::default();
+//! let reporter = progress.clone();
+//! std::thread::spawn(move || {
+//!     let item = reporter.begin("terrain.png", 4096);
+//!     // ... load the file
+//!     reporter.finish(item);
+//! });
+//!
+//! // every frame
+//! let status = progress.poll().with_uploads(engine.texture_upload_progress());
+//! if !status.is_done() {
+//!     // LoadingScreen::default().draw(&mut layer, (width, height), &status);
+//! }
+//! ```
+
+use crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer;
+use crate::engine::system::vulkan::textures::UploadProgress;
+use crate::engine::types::world2d::{Dim, Pos};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Identifies an item started with [`LoadingProgress::begin`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LoadingItem(u64);
+
+#[derive(Debug)]
+struct LoadingState {
+    next_id: u64,
+    /// The items that are currently loaded, in the order they were started
+    current: Vec<(LoadingItem, String, u64)>,
+    items_done: usize,
+    items_total: usize,
+    bytes_done: u64,
+    bytes_total: u64,
+}
+
+/// A cheaply cloneable handle through which loaders report their progress.
+#[derive(Debug, Clone)]
+pub struct LoadingProgress {
+    state: Arc<Mutex<LoadingState>>,
+}
+
+impl Default for LoadingProgress {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(LoadingState {
+                next_id: 0,
+                current: Vec::default(),
+                items_done: 0,
+                items_total: 0,
+                bytes_done: 0,
+                bytes_total: 0,
+            })),
+        }
+    }
+}
+
+impl LoadingProgress {
+    /// Announces items that will be started later, so the total is known from the beginning.
+    pub fn expect(&self, items: usize, bytes: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.items_total += items;
+        state.bytes_total += bytes;
+    }
+
+    /// Starts loading an item with the given size in bytes (`0` if unknown).
+    pub fn begin(&self, name: impl Into<String>, bytes: u64) -> LoadingItem {
+        let mut state = self.state.lock().unwrap();
+        let item = LoadingItem(state.next_id);
+        state.next_id += 1;
+        state.items_total += 1;
+        state.bytes_total += bytes;
+        state.current.push((item, name.into(), bytes));
+        item
+    }
+
+    /// Starts loading an item that was announced with [`LoadingProgress::expect`] before.
+    pub fn begin_expected(&self, name: impl Into<String>, bytes: u64) -> LoadingItem {
+        let mut state = self.state.lock().unwrap();
+        let item = LoadingItem(state.next_id);
+        state.next_id += 1;
+        state.current.push((item, name.into(), bytes));
+        item
+    }
+
+    pub fn finish(&self, item: LoadingItem) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(index) = state.current.iter().position(|(i, ..)| *i == item) {
+            let (_, _, bytes) = state.current.remove(index);
+            state.items_done += 1;
+            state.bytes_done += bytes;
+        }
+    }
+
+    /// Forgets all finished items, for example before loading the next level.
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        let (items, bytes) = (
+            state.current.len(),
+            state.current.iter().map(|(.., bytes)| bytes).sum(),
+        );
+        state.items_done = 0;
+        state.items_total = items;
+        state.bytes_done = 0;
+        state.bytes_total = bytes;
+    }
+
+    /// A snapshot of the current progress.
+    pub fn poll(&self) -> LoadingStatus {
+        let state = self.state.lock().unwrap();
+        LoadingStatus {
+            items_done: state.items_done,
+            items_total: state.items_total,
+            bytes_done: state.bytes_done,
+            bytes_total: state.bytes_total,
+            current: state.current.first().map(|(_, name, _)| name.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LoadingStatus {
+    pub items_done: usize,
+    pub items_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// The name of the item that is loaded the longest
+    pub current: Option<String>,
+}
+
+impl LoadingStatus {
+    /// Adds the texture uploads, which follow after the images were loaded.
+    pub fn with_uploads(mut self, uploads: UploadProgress) -> Self {
+        self.items_done += uploads.uploads_done;
+        self.items_total += uploads.uploads_total;
+        self.bytes_done += uploads.bytes_done;
+        self.bytes_total += uploads.bytes_total;
+        if self.current.is_none() && !uploads.is_done() {
+            self.current = Some(String::from("Uploading textures"));
+        }
+        self
+    }
+
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.items_done >= self.items_total
+    }
+
+    /// The share of the loaded bytes, or of the loaded items if the sizes are unknown.
+    pub fn fraction(&self) -> f32 {
+        if self.bytes_total > 0 {
+            self.bytes_done as f32 / self.bytes_total as f32
+        } else if self.items_total > 0 {
+            self.items_done as f32 / self.items_total as f32
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Draws a progress bar and a spinner in the center of the screen.
+#[derive(Debug, Clone)]
+pub struct LoadingScreen {
+    background_color: [f32; 4],
+    bar_color: [f32; 4],
+    bar_background_color: [f32; 4],
+    bar_size: Dim<f32>,
+    started: Instant,
+}
+
+impl Default for LoadingScreen {
+    fn default() -> Self {
+        Self {
+            background_color: [0.05, 0.05, 0.07, 1.0],
+            bar_color: [0.3, 0.65, 1.0, 1.0],
+            bar_background_color: [1.0, 1.0, 1.0, 0.1],
+            bar_size: Dim::new(320.0, 8.0),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl LoadingScreen {
+    #[inline]
+    pub fn with_background_color(mut self, color: [f32; 4]) -> Self {
+        self.background_color = color;
+        self
+    }
+
+    #[inline]
+    pub fn with_bar_colors(mut self, bar: [f32; 4], background: [f32; 4]) -> Self {
+        self.bar_color = bar;
+        self.bar_background_color = background;
+        self
+    }
+
+    #[inline]
+    pub fn with_bar_size(mut self, width: f32, height: f32) -> Self {
+        self.bar_size = Dim::new(width, height);
+        self
+    }
+
+    /// Draws the loading screen, the layer is expected to draw in screen coordinates.
+    pub fn draw(
+        &self,
+        layer: &mut BufferedCanvasLayer,
+        (width, height): (u32, u32),
+        status: &LoadingStatus,
+    ) {
+        let (width, height) = (width as f32, height as f32);
+        layer.set_draw_color(self.background_color);
+        layer.fill_rect(Pos::new(0.0, 0.0), Dim::new(width, height));
+
+        let pos = Pos::new(
+            (width - self.bar_size.x) * 0.5,
+            (height - self.bar_size.y) * 0.5,
+        );
+        layer.set_draw_color(self.bar_background_color);
+        layer.fill_rect(pos, self.bar_size);
+        layer.set_draw_color(self.bar_color);
+        layer.fill_rect(
+            pos,
+            Dim::new(
+                self.bar_size.x * status.fraction().clamp(0.0, 1.0),
+                self.bar_size.y,
+            ),
+        );
+
+        // a rotating arc above the bar, to show that the game did not freeze
+        let center = Pos::new(width * 0.5, pos.y - self.bar_size.y * 4.0);
+        let radius = self.bar_size.y * 2.0;
+        let start = self.started.elapsed().as_secs_f32() * std::f32::consts::TAU;
+        let arc = (0..=16)
+            .map(|segment| {
+                let angle = start + std::f32::consts::PI * 1.5 * segment as f32 / 16.0;
+                center + Dim::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect::<Vec<_>>();
+        layer.draw_path(&arc);
+    }
+
+    /// Shows the name of the current item and the amount of loaded items, the canvas layer has no
+    /// text support.
+    #[cfg(feature = "ui-egui")]
+    pub fn show_details(&self, ctx: &egui::Context, status: &LoadingStatus) {
+        egui::Area::new("hotrod-loading-screen")
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, self.bar_size.y * 3.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.label(format!("{} / {}", status.items_done, status.items_total));
+                    if let Some(current) = &status.current {
+                        ui.label(current);
+                    }
+                });
+            });
+    }
+}
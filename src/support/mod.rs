@@ -1,4 +1,23 @@
+#[cfg(feature = "editor")]
+pub mod asset_browser;
+pub mod charts;
+pub mod day_night;
+pub mod events;
+pub mod frame_time_graph;
+pub mod gizmos;
 pub mod image;
 pub mod interpolated;
+pub mod loading;
+pub mod music;
+pub mod noise;
+pub mod rng;
+#[cfg(feature = "scripting")]
+pub mod scripting;
 pub mod sprite_sheet;
+#[cfg(feature = "editor")]
+pub mod tile_map_editor;
+pub mod tweak;
+pub mod weather;
 pub mod world2d;
+#[cfg(feature = "ui-egui")]
+pub mod world_ui;
@@ -0,0 +1,797 @@
+//! Streams long music tracks from disk: a worker thread decodes the current track in chunks and
+//! mixes crossfades between the tracks of a playlist, so only a few chunks of audio are kept in
+//! memory at any time.
+//!
+//! OGG Vorbis tracks are decoded with the `music-ogg` feature and MP3 tracks with the `music-mp3`
+//! feature, see [`open_track`]. Other codecs are plugged in by implementing [`TrackDecoder`].
+//! Tracks are resampled to the sample rate of the output. The mixed audio is pulled from the
+//! [`MusicOutput`], which is either streamed through the music channel of the
+//! [`AudioSystem`](crate::engine::system::audio::AudioSystem) or used as SDL2 audio callback.
+//!
+//! ```no_run
+//! # use hotrod::support::music::{MusicConfig, MusicPlayer};
+//! # use std::path::PathBuf;
+//! # #[cfg(feature = "audio")]
+//! # fn main() {
+//! let mut engine = hotrod::engine::Engine::default();
+//! let audio = engine.audio_mut().expect("No audio device");
+//!
+//! let config = MusicConfig::default().with_sample_rate(audio.frequency() as u32);
+//! let (player, output) = MusicPlayer::spawn(config, hotrod::support::music::open_track);
+//! player.set_playlist(vec![PathBuf::from("music/title.ogg"), PathBuf::from("music/level1.ogg")]);
+//! player.play(0);
+//!
+//! output.attach(audio);
+//! # }
+//! # #[cfg(not(feature = "audio"))]
+//! # fn main() {}
+//! ```
+
+use crossbeam::channel::{Receiver, Sender, TryRecvError};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(thiserror::Error, Debug)]
+pub enum MusicError {
+    #[error("Failed to read the track: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to decode the track: {0}")]
+    Decode(String),
+    #[error("No decoder for the track {0:?}")]
+    UnsupportedFormat(PathBuf),
+}
+
+/// Decodes a track chunk by chunk, implemented for the codecs the game uses.
+pub trait TrackDecoder: Send {
+    fn sample_rate(&self) -> u32;
+
+    fn channels(&self) -> u16;
+
+    /// Appends the next chunk of interleaved samples to `out` and returns `false` once the end of
+    /// the track is reached.
+    fn decode_next(&mut self, out: &mut Vec<f32>) -> Result<bool, MusicError>;
+
+    /// The number of frames that are not decoded yet, if known. Without it, the next track starts
+    /// after the end of this one instead of being crossfaded.
+    #[inline]
+    fn remaining_frames(&self) -> Option<u64> {
+        None
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MusicConfig {
+    /// The sample rate of the output, tracks with another sample rate are resampled
+    pub sample_rate: u32,
+    /// The amount of stereo frames that are mixed at once
+    pub chunk_frames: usize,
+    /// How many mixed chunks are buffered ahead of the output
+    pub buffered_chunks: usize,
+    pub crossfade: Duration,
+    pub volume: f32,
+    /// Whether the playlist starts over after its last track
+    pub repeat: bool,
+}
+
+impl Default for MusicConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44_100,
+            chunk_frames: 2048,
+            buffered_chunks: 4,
+            crossfade: Duration::from_secs(3),
+            volume: 1.0,
+            repeat: true,
+        }
+    }
+}
+
+impl MusicConfig {
+    #[inline]
+    pub fn with_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    #[inline]
+    pub fn with_buffer(mut self, chunk_frames: usize, buffered_chunks: usize) -> Self {
+        self.chunk_frames = chunk_frames.max(1);
+        self.buffered_chunks = buffered_chunks.max(1);
+        self
+    }
+
+    #[inline]
+    pub fn with_crossfade(mut self, crossfade: Duration) -> Self {
+        self.crossfade = crossfade;
+        self
+    }
+
+    #[inline]
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume.clamp(0.0, 1.0);
+        self
+    }
+
+    #[inline]
+    pub fn with_repeat(mut self, repeat: bool) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    #[inline]
+    fn crossfade_frames(&self) -> u64 {
+        (self.crossfade.as_secs_f64() * f64::from(self.sample_rate)) as u64
+    }
+}
+
+enum MusicCommand {
+    SetPlaylist(Vec<PathBuf>),
+    Enqueue(PathBuf),
+    Play(usize),
+    Next,
+    Previous,
+    Stop,
+    SetCrossfade(Duration),
+    SetVolume(f32),
+    SetRepeat(bool),
+}
+
+/// Controls the playlist of the worker thread, dropping all clones stops the worker.
+#[derive(Debug, Clone)]
+pub struct MusicPlayer {
+    commands: Sender<MusicCommand>,
+    current: Arc<Mutex<Option<usize>>>,
+}
+
+impl MusicPlayer {
+    /// Starts the worker thread, which opens the tracks of the playlist through `opener`.
+    pub fn spawn(
+        config: MusicConfig,
+        opener: impl FnMut(&Path) -> Result<Box<dyn TrackDecoder>, MusicError> + Send + 'static,
+    ) -> (Self, MusicOutput) {
+        let (commands, command_receiver) = crossbeam::channel::unbounded();
+        let (chunk_sender, chunks) = crossbeam::channel::bounded(config.buffered_chunks.max(1));
+        let current = Arc::new(Mutex::new(None));
+
+        let worker = MusicWorker {
+            opener: Box::new(opener),
+            commands: command_receiver,
+            chunks: chunk_sender,
+            current_status: Arc::clone(&current),
+            config,
+            playlist: Vec::default(),
+            current: None,
+            fade: None,
+        };
+
+        if let Err(e) = std::thread::Builder::new()
+            .name("MusicWorkerThread".to_string())
+            .spawn(move || worker.run())
+        {
+            error!("Failed to start MusicWorker Thread: {e}");
+        }
+
+        (
+            Self { commands, current },
+            MusicOutput {
+                chunks,
+                pending: Vec::default(),
+                cursor: 0,
+            },
+        )
+    }
+
+    /// Replaces the playlist, the current track keeps playing until it ends.
+    #[inline]
+    pub fn set_playlist(&self, tracks: Vec<PathBuf>) {
+        self.send(MusicCommand::SetPlaylist(tracks));
+    }
+
+    #[inline]
+    pub fn enqueue(&self, track: impl Into<PathBuf>) {
+        self.send(MusicCommand::Enqueue(track.into()));
+    }
+
+    /// Crossfades from the current track to the track at the given index of the playlist.
+    #[inline]
+    pub fn play(&self, index: usize) {
+        self.send(MusicCommand::Play(index));
+    }
+
+    #[inline]
+    pub fn next(&self) {
+        self.send(MusicCommand::Next);
+    }
+
+    #[inline]
+    pub fn previous(&self) {
+        self.send(MusicCommand::Previous);
+    }
+
+    /// Fades out the current track.
+    #[inline]
+    pub fn stop(&self) {
+        self.send(MusicCommand::Stop);
+    }
+
+    #[inline]
+    pub fn set_crossfade(&self, crossfade: Duration) {
+        self.send(MusicCommand::SetCrossfade(crossfade));
+    }
+
+    #[inline]
+    pub fn set_volume(&self, volume: f32) {
+        self.send(MusicCommand::SetVolume(volume.clamp(0.0, 1.0)));
+    }
+
+    #[inline]
+    pub fn set_repeat(&self, repeat: bool) {
+        self.send(MusicCommand::SetRepeat(repeat));
+    }
+
+    /// The index of the track that is currently mixed, lags behind the output by the buffered
+    /// chunks.
+    #[inline]
+    pub fn current_track(&self) -> Option<usize> {
+        *self.current.lock().unwrap()
+    }
+
+    fn send(&self, command: MusicCommand) {
+        if self.commands.send(command).is_err() {
+            warn!("The MusicWorker Thread is no longer running");
+        }
+    }
+}
+
+/// Provides the mixed, interleaved stereo samples of the [`MusicPlayer`].
+#[derive(Debug)]
+pub struct MusicOutput {
+    chunks: Receiver<Vec<f32>>,
+    pending: Vec<f32>,
+    cursor: usize,
+}
+
+impl MusicOutput {
+    /// Fills `out` with interleaved stereo samples and returns how many were available, the rest
+    /// is filled with silence.
+    #[inline]
+    pub fn fill(&mut self, out: &mut [f32]) -> usize {
+        self.fill_converted(out, 0.0, |sample| sample)
+    }
+
+    /// Like [`MusicOutput::fill`], but with signed 16 bit samples.
+    #[inline]
+    pub fn fill_i16(&mut self, out: &mut [i16]) -> usize {
+        self.fill_converted(out, 0, |sample| {
+            (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16
+        })
+    }
+
+    /// Streams the music through the music channel of the [`AudioSystem`], replacing the
+    /// current music. The sample rate of the [`MusicConfig`] should match
+    /// [`AudioSystem::frequency`].
+    ///
+    /// [`AudioSystem`]: crate::engine::system::audio::AudioSystem
+    /// [`AudioSystem::frequency`]: crate::engine::system::audio::AudioSystem::frequency
+    #[cfg(feature = "audio")]
+    pub fn attach(mut self, audio: &mut crate::engine::system::audio::AudioSystem) {
+        audio.set_music_hook(Some(Box::new(move |out| {
+            self.fill_i16(out);
+        })));
+    }
+
+    fn fill_converted<T: Copy>(
+        &mut self,
+        out: &mut [T],
+        silence: T,
+        convert: impl Fn(f32) -> T,
+    ) -> usize {
+        let mut written = 0;
+        while written < out.len() {
+            if self.cursor >= self.pending.len() {
+                match self.chunks.try_recv() {
+                    Ok(chunk) => {
+                        self.pending = chunk;
+                        self.cursor = 0;
+                    }
+                    Err(_) => break,
+                }
+            }
+            let len = (self.pending.len() - self.cursor).min(out.len() - written);
+            out[written..written + len]
+                .iter_mut()
+                .zip(&self.pending[self.cursor..self.cursor + len])
+                .for_each(|(out, sample)| *out = convert(*sample));
+            self.cursor += len;
+            written += len;
+        }
+        out[written..].fill(silence);
+        written
+    }
+}
+
+impl sdl2::audio::AudioCallback for MusicOutput {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.fill(out);
+    }
+}
+
+/// A track that is being decoded.
+struct Voice {
+    index: usize,
+    decoder: Box<dyn TrackDecoder>,
+    channels: usize,
+    samples: Vec<f32>,
+    cursor: usize,
+    ended: bool,
+    /// The source frames per output frame
+    step: f64,
+    /// The position between the two source frames of the window
+    phase: f64,
+    /// The source frames that are linearly interpolated while resampling
+    window: Option<[[f32; 2]; 2]>,
+}
+
+impl Voice {
+    fn new(index: usize, decoder: Box<dyn TrackDecoder>, sample_rate: u32) -> Self {
+        Self {
+            index,
+            channels: usize::from(decoder.channels()).max(1),
+            step: f64::from(decoder.sample_rate().max(1)) / f64::from(sample_rate.max(1)),
+            decoder,
+            samples: Vec::default(),
+            cursor: 0,
+            ended: false,
+            phase: 0.0,
+            window: None,
+        }
+    }
+
+    /// The next frame at the sample rate of the output.
+    fn next_frame(&mut self) -> Option<[f32; 2]> {
+        if self.step == 1.0 {
+            return self.next_source_frame();
+        }
+
+        let [a, b] = match self.window {
+            Some(window) => window,
+            None => {
+                let a = self.next_source_frame()?;
+                let b = self.next_source_frame().unwrap_or(a);
+                [a, b]
+            }
+        };
+        let t = self.phase as f32;
+        let frame = [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+
+        let mut window = Some([a, b]);
+        self.phase += self.step;
+        while self.phase >= 1.0 {
+            self.phase -= 1.0;
+            window = window.and_then(|[_, b]| Some([b, self.next_source_frame()?]));
+        }
+        self.window = window;
+        if window.is_none() {
+            // the end of the track was reached, stay there
+            self.ended = true;
+            self.samples.clear();
+            self.cursor = 0;
+        }
+        Some(frame)
+    }
+
+    fn next_source_frame(&mut self) -> Option<[f32; 2]> {
+        while self.cursor + self.channels > self.samples.len() {
+            if self.ended {
+                return None;
+            }
+            self.samples.drain(..self.cursor);
+            self.cursor = 0;
+            match self.decoder.decode_next(&mut self.samples) {
+                Ok(true) => {}
+                Ok(false) => self.ended = true,
+                Err(e) => {
+                    error!("Failed to decode music track {}: {e}", self.index);
+                    self.ended = true;
+                }
+            }
+        }
+
+        let frame = &self.samples[self.cursor..self.cursor + self.channels];
+        self.cursor += self.channels;
+        Some(match frame {
+            [mono] => [*mono, *mono],
+            [left, right, ..] => [*left, *right],
+            [] => [0.0, 0.0],
+        })
+    }
+
+    /// The remaining frames at the sample rate of the output.
+    fn remaining_frames(&self) -> Option<u64> {
+        let buffered = (self.samples.len() - self.cursor) / self.channels;
+        self.decoder
+            .remaining_frames()
+            .map(|remaining| ((remaining + buffered as u64) as f64 / self.step) as u64)
+    }
+}
+
+struct Fade {
+    /// The track that is faded out, if any
+    out: Option<Voice>,
+    /// The gain of the track that is faded out at the start of the fade
+    out_gain: f32,
+    position: u64,
+    length: u64,
+}
+
+impl Fade {
+    /// The gain of the track that is faded out and of the track that is faded in, with constant
+    /// power so that the loudness does not drop in the middle.
+    #[inline]
+    fn gains(&self) -> (f32, f32) {
+        let angle =
+            (self.position as f32 / self.length as f32).min(1.0) * std::f32::consts::FRAC_PI_2;
+        (self.out_gain * angle.cos(), angle.sin())
+    }
+}
+
+type TrackOpener = Box<dyn FnMut(&Path) -> Result<Box<dyn TrackDecoder>, MusicError> + Send>;
+
+struct MusicWorker {
+    opener: TrackOpener,
+    commands: Receiver<MusicCommand>,
+    chunks: Sender<Vec<f32>>,
+    current_status: Arc<Mutex<Option<usize>>>,
+    config: MusicConfig,
+    playlist: Vec<PathBuf>,
+    current: Option<Voice>,
+    fade: Option<Fade>,
+}
+
+impl MusicWorker {
+    fn run(mut self) {
+        loop {
+            loop {
+                // nothing to mix, wait for the next command
+                let command = if self.current.is_none() && self.fade.is_none() {
+                    match self.commands.recv() {
+                        Ok(command) => command,
+                        Err(_) => return,
+                    }
+                } else {
+                    match self.commands.try_recv() {
+                        Ok(command) => command,
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => return,
+                    }
+                };
+                self.apply(command);
+            }
+
+            let chunk = self.mix_chunk();
+            *self.current_status.lock().unwrap() = self.current.as_ref().map(|v| v.index);
+
+            // blocks while enough chunks are buffered
+            if self.chunks.send(chunk).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn apply(&mut self, command: MusicCommand) {
+        match command {
+            MusicCommand::SetPlaylist(tracks) => self.playlist = tracks,
+            MusicCommand::Enqueue(track) => self.playlist.push(track),
+            MusicCommand::Play(index) => self.transition_to(Some(index)),
+            MusicCommand::Next => {
+                let next = match &self.current {
+                    Some(current) => self.following(current.index),
+                    None => (!self.playlist.is_empty()).then_some(0),
+                };
+                self.transition_to(next);
+            }
+            MusicCommand::Previous => {
+                let previous = self.current.as_ref().and_then(|current| {
+                    if current.index > 0 {
+                        Some(current.index - 1)
+                    } else if self.config.repeat {
+                        self.playlist.len().checked_sub(1)
+                    } else {
+                        None
+                    }
+                });
+                self.transition_to(previous);
+            }
+            MusicCommand::Stop => self.transition_to(None),
+            MusicCommand::SetCrossfade(crossfade) => self.config.crossfade = crossfade,
+            MusicCommand::SetVolume(volume) => self.config.volume = volume,
+            MusicCommand::SetRepeat(repeat) => self.config.repeat = repeat,
+        }
+    }
+
+    /// The track after the given one, if the playlist does not end there.
+    fn following(&self, index: usize) -> Option<usize> {
+        if index + 1 < self.playlist.len() {
+            Some(index + 1)
+        } else if self.config.repeat && !self.playlist.is_empty() {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// Opens the track at the given index, or the first one after it that can be opened.
+    fn open(&mut self, mut index: usize) -> Option<Voice> {
+        for _ in 0..self.playlist.len() {
+            let path = self.playlist.get(index)?;
+            match (self.opener)(path) {
+                Ok(decoder) => {
+                    if decoder.sample_rate() != self.config.sample_rate {
+                        debug!(
+                            "Resampling the music track {path:?} from {}Hz to {}Hz",
+                            decoder.sample_rate(),
+                            self.config.sample_rate
+                        );
+                    }
+                    return Some(Voice::new(index, decoder, self.config.sample_rate));
+                }
+                Err(e) => {
+                    error!("Failed to open the music track {path:?}: {e}");
+                    index = self.following(index)?;
+                }
+            }
+        }
+        None
+    }
+
+    /// Crossfades from the current track to the given one, or fades out if there is none. An
+    /// ongoing crossfade is interrupted: the track that was faded in is faded out from its
+    /// current gain, the track that was faded out is stopped.
+    fn transition_to(&mut self, index: Option<usize>) {
+        let next = index.and_then(|index| self.open(index));
+        let length = self.config.crossfade_frames();
+        let previous = std::mem::replace(&mut self.current, next);
+        let fade = self.fade.take();
+        let (out, out_gain) = match (previous, fade) {
+            (Some(previous), Some(fade)) => (Some(previous), fade.gains().1),
+            (Some(previous), None) => (Some(previous), 1.0),
+            (None, Some(fade)) => {
+                let gain = fade.gains().0;
+                (fade.out, gain)
+            }
+            (None, None) => (None, 1.0),
+        };
+        self.fade = if length > 0 && out.is_some() {
+            Some(Fade {
+                out,
+                out_gain,
+                position: 0,
+                length,
+            })
+        } else {
+            None
+        };
+    }
+
+    /// Starts crossfading to the next track, once the current one is about to end.
+    fn begin_crossfade_if_ending(&mut self) {
+        let length = self.config.crossfade_frames();
+        if self.fade.is_some() || length == 0 {
+            return;
+        }
+        let Some((index, remaining)) = self
+            .current
+            .as_ref()
+            .and_then(|current| Some((current.index, current.remaining_frames()?)))
+        else {
+            return;
+        };
+        if remaining > length {
+            return;
+        }
+        if let Some(next) = self.following(index).and_then(|i| self.open(i)) {
+            self.fade = Some(Fade {
+                out: self.current.replace(next),
+                out_gain: 1.0,
+                position: 0,
+                length: remaining.max(1),
+            });
+        }
+    }
+
+    fn mix_chunk(&mut self) -> Vec<f32> {
+        let mut chunk = Vec::with_capacity(self.config.chunk_frames * 2);
+        for _ in 0..self.config.chunk_frames {
+            self.begin_crossfade_if_ending();
+
+            let (gain_out, gain_in) = self.fade.as_ref().map_or((0.0, 1.0), Fade::gains);
+            let mut frame = [0.0_f32; 2];
+
+            if let Some(current) = &mut self.current {
+                if let Some([left, right]) = current.next_frame() {
+                    frame[0] += left * gain_in;
+                    frame[1] += right * gain_in;
+                } else {
+                    // the end was not known in advance, continue without a crossfade
+                    let index = current.index;
+                    self.current = self.following(index).and_then(|i| self.open(i));
+                }
+            }
+
+            if let Some(fade) = &mut self.fade {
+                if let Some([left, right]) = fade.out.as_mut().and_then(Voice::next_frame) {
+                    frame[0] += left * gain_out;
+                    frame[1] += right * gain_out;
+                }
+                fade.position += 1;
+                if fade.position >= fade.length {
+                    self.fade = None;
+                }
+            }
+
+            chunk.push(frame[0] * self.config.volume);
+            chunk.push(frame[1] * self.config.volume);
+        }
+        chunk
+    }
+}
+
+/// Opens the track with the decoder for its file extension, can directly be passed to
+/// [`MusicPlayer::spawn`]. Fails with [`MusicError::UnsupportedFormat`] if the feature of the
+/// codec is not enabled.
+pub fn open_track(path: &Path) -> Result<Box<dyn TrackDecoder>, MusicError> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        #[cfg(feature = "music-ogg")]
+        Some("ogg" | "oga") => Ok(Box::new(OggDecoder::open(path)?)),
+        #[cfg(feature = "music-mp3")]
+        Some("mp3") => Ok(Box::new(Mp3Decoder::open(path)?)),
+        _ => Err(MusicError::UnsupportedFormat(path.to_path_buf())),
+    }
+}
+
+/// Decodes OGG Vorbis tracks, see [`open_track`].
+#[cfg(feature = "music-ogg")]
+pub struct OggDecoder {
+    reader: lewton::inside_ogg::OggStreamReader<std::io::BufReader<std::fs::File>>,
+    /// The granule position of the last page, which is the length of the track in frames
+    total_frames: Option<u64>,
+    decoded_frames: u64,
+}
+
+#[cfg(feature = "music-ogg")]
+impl OggDecoder {
+    pub fn open(path: &Path) -> Result<Self, MusicError> {
+        let total_frames = Self::read_total_frames(path)?;
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let reader = lewton::inside_ogg::OggStreamReader::new(file)
+            .map_err(|e| MusicError::Decode(e.to_string()))?;
+        Ok(Self {
+            reader,
+            total_frames,
+            decoded_frames: 0,
+        })
+    }
+
+    /// Reads the granule position of the last page, which is at the end of the file.
+    fn read_total_frames(path: &Path) -> Result<Option<u64>, MusicError> {
+        use std::io::{Read, Seek, SeekFrom};
+        // a page is at most 65307 bytes long
+        const TAIL: u64 = 65_536;
+        let mut file = std::fs::File::open(path)?;
+        let len = file.seek(SeekFrom::End(0))?;
+        file.seek(SeekFrom::Start(len.saturating_sub(TAIL)))?;
+        let mut tail = Vec::with_capacity(TAIL as usize);
+        file.read_to_end(&mut tail)?;
+
+        Ok(tail
+            .windows(4)
+            .rposition(|window| window == b"OggS")
+            .and_then(|start| tail.get(start + 6..start + 14))
+            .map(|granule| u64::from_le_bytes(granule.try_into().unwrap()))
+            // -1 is used for pages without a finished packet
+            .filter(|granule| *granule != u64::MAX))
+    }
+}
+
+#[cfg(feature = "music-ogg")]
+impl TrackDecoder for OggDecoder {
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.reader.ident_hdr.audio_sample_rate
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        u16::from(self.reader.ident_hdr.audio_channels)
+    }
+
+    fn decode_next(&mut self, out: &mut Vec<f32>) -> Result<bool, MusicError> {
+        match self.reader.read_dec_packet_itl() {
+            Ok(Some(samples)) => {
+                let channels = usize::from(self.reader.ident_hdr.audio_channels).max(1);
+                self.decoded_frames += (samples.len() / channels) as u64;
+                out.extend(samples.into_iter().map(i16_to_f32));
+                Ok(true)
+            }
+            Ok(None) => Ok(false),
+            Err(e) => Err(MusicError::Decode(e.to_string())),
+        }
+    }
+
+    #[inline]
+    fn remaining_frames(&self) -> Option<u64> {
+        self.total_frames
+            .map(|total| total.saturating_sub(self.decoded_frames))
+    }
+}
+
+/// Decodes MP3 tracks, see [`open_track`]. The length of MP3 tracks is not known in advance, so
+/// the next track starts after the end without a crossfade.
+#[cfg(feature = "music-mp3")]
+pub struct Mp3Decoder {
+    decoder: minimp3::Decoder<std::io::BufReader<std::fs::File>>,
+    sample_rate: u32,
+    channels: u16,
+    /// The first frame, which is decoded while opening to learn the format
+    first: Option<Vec<i16>>,
+}
+
+#[cfg(feature = "music-mp3")]
+impl Mp3Decoder {
+    pub fn open(path: &Path) -> Result<Self, MusicError> {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut decoder = minimp3::Decoder::new(file);
+        let frame = decoder
+            .next_frame()
+            .map_err(|e| MusicError::Decode(e.to_string()))?;
+        Ok(Self {
+            decoder,
+            sample_rate: frame.sample_rate.max(0) as u32,
+            channels: frame.channels as u16,
+            first: Some(frame.data),
+        })
+    }
+}
+
+#[cfg(feature = "music-mp3")]
+impl TrackDecoder for Mp3Decoder {
+    #[inline]
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[inline]
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn decode_next(&mut self, out: &mut Vec<f32>) -> Result<bool, MusicError> {
+        if let Some(first) = self.first.take() {
+            out.extend(first.into_iter().map(i16_to_f32));
+            return Ok(true);
+        }
+        match self.decoder.next_frame() {
+            Ok(frame) => {
+                out.extend(frame.data.into_iter().map(i16_to_f32));
+                Ok(true)
+            }
+            Err(minimp3::Error::Eof) => Ok(false),
+            // garbage between frames, like ID3 tags, is skipped
+            Err(minimp3::Error::SkippedData) => Ok(true),
+            Err(e) => Err(MusicError::Decode(e.to_string())),
+        }
+    }
+}
+
+#[cfg(any(feature = "music-ogg", feature = "music-mp3"))]
+#[inline]
+fn i16_to_f32(sample: i16) -> f32 {
+    f32::from(sample) / 32_768.0
+}
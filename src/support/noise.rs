@@ -0,0 +1,358 @@
+//! Deterministic 2d noise for terrain generation, distortion effects and procedural clouds.
+//!
+//! The generators are sampled at arbitrary positions with [`Noise::sample`], or into a
+//! [`NoiseGrid`] that can be uploaded as a texture:
+//!
+//! ```no_run
+//! use hotrod::support::noise::{Fbm, NoiseGrid, Perlin};
+//! # fn upload(image_system: &hotrod::engine::system::vulkan::textures::ImageSystem) {
+//! let clouds = Fbm::new(Perlin::new(42)).with_octaves(5);
+//! let mut grid = NoiseGrid::generate(&clouds, 256, 256, 1.0 / 64.0);
+//! grid.normalize();
+//! let image = grid.create_image(image_system).unwrap();
+//! # }
+//! ```
+
+use crate::engine::system::vulkan::textures::ImageSystem;
+use crate::engine::system::vulkan::UploadError;
+use crate::support::rng::Rng;
+use std::sync::Arc;
+use vulkano::image::Image;
+
+pub trait Noise {
+    fn sample(&self, x: f32, y: f32) -> f32;
+}
+
+impl<N: Noise + ?Sized> Noise for &N {
+    #[inline]
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        N::sample(self, x, y)
+    }
+}
+
+/// The permutation table shared by [`Perlin`] and [`Simplex`], duplicated to avoid wrapping the
+/// indices.
+#[derive(Clone)]
+struct Permutation([u8; 512]);
+
+impl Permutation {
+    fn new(seed: u64) -> Self {
+        let mut values = std::array::from_fn::<u8, 256, _>(|index| index as u8);
+        Rng::from_seed(seed).shuffle(&mut values);
+        Self(std::array::from_fn(|index| values[index & 255]))
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> usize {
+        usize::from(self.0[index])
+    }
+
+    /// The dot product of one of eight gradients with the offset to the corner.
+    #[inline]
+    fn gradient(hash: usize, x: f32, y: f32) -> f32 {
+        match hash & 7 {
+            0 => x + y,
+            1 => -x + y,
+            2 => x - y,
+            3 => -x - y,
+            4 => x,
+            5 => -x,
+            6 => y,
+            _ => -y,
+        }
+    }
+}
+
+/// Classic gradient noise with values roughly in `-1.0..=1.0`, zero at integer positions.
+#[derive(Clone)]
+pub struct Perlin {
+    permutation: Permutation,
+}
+
+impl Perlin {
+    #[inline]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            permutation: Permutation::new(seed),
+        }
+    }
+
+    #[inline]
+    fn fade(t: f32) -> f32 {
+        t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+    }
+
+    #[inline]
+    fn lerp(t: f32, a: f32, b: f32) -> f32 {
+        a + t * (b - a)
+    }
+}
+
+impl Noise for Perlin {
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let (x_floor, y_floor) = (x.floor(), y.floor());
+        let xi = (x_floor as i32 & 255) as usize;
+        let yi = (y_floor as i32 & 255) as usize;
+        let (x, y) = (x - x_floor, y - y_floor);
+        let (u, v) = (Self::fade(x), Self::fade(y));
+
+        let p = &self.permutation;
+        let a = p.get(xi);
+        let b = p.get(xi + 1);
+
+        Self::lerp(
+            v,
+            Self::lerp(
+                u,
+                Permutation::gradient(p.get(a + yi), x, y),
+                Permutation::gradient(p.get(b + yi), x - 1.0, y),
+            ),
+            Self::lerp(
+                u,
+                Permutation::gradient(p.get(a + yi + 1), x, y - 1.0),
+                Permutation::gradient(p.get(b + yi + 1), x - 1.0, y - 1.0),
+            ),
+        )
+    }
+}
+
+/// Gradient noise on a triangular grid with values roughly in `-1.0..=1.0`. It has fewer
+/// directional artifacts than [`Perlin`] noise.
+#[derive(Clone)]
+pub struct Simplex {
+    permutation: Permutation,
+}
+
+impl Simplex {
+    /// `(sqrt(3) - 1) / 2`, skews the input space onto the triangular grid
+    const F2: f32 = 0.366_025_42;
+    /// `(3 - sqrt(3)) / 6`, unskews the triangular grid into the input space
+    const G2: f32 = 0.211_324_87;
+
+    #[inline]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            permutation: Permutation::new(seed),
+        }
+    }
+
+    #[inline]
+    fn corner(hash: usize, x: f32, y: f32) -> f32 {
+        let t = 0.5 - x * x - y * y;
+        if t < 0.0 {
+            0.0
+        } else {
+            t * t * t * t * Permutation::gradient(hash, x, y)
+        }
+    }
+}
+
+impl Noise for Simplex {
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let skew = (x + y) * Self::F2;
+        let (i, j) = ((x + skew).floor(), (y + skew).floor());
+        let unskew = (i + j) * Self::G2;
+        let (x0, y0) = (x - (i - unskew), y - (j - unskew));
+
+        // the triangle of the position within the skewed cell
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let (x1, y1) = (x0 - i1 as f32 + Self::G2, y0 - j1 as f32 + Self::G2);
+        let (x2, y2) = (x0 - 1.0 + 2.0 * Self::G2, y0 - 1.0 + 2.0 * Self::G2);
+
+        let p = &self.permutation;
+        let ii = (i as i32 & 255) as usize;
+        let jj = (j as i32 & 255) as usize;
+
+        70.0 * (Self::corner(p.get(ii + p.get(jj)), x0, y0)
+            + Self::corner(p.get(ii + i1 + p.get(jj + j1)), x1, y1)
+            + Self::corner(p.get(ii + 1 + p.get(jj + 1)), x2, y2))
+    }
+}
+
+/// Cellular noise: the distance to the nearest of randomly placed feature points, one per unit
+/// cell. The values are in `0.0..1.0` and zero at the feature points.
+#[derive(Debug, Clone)]
+pub struct Worley {
+    seed: u64,
+}
+
+impl Worley {
+    #[inline]
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// The position of the feature point within the cell, splitmix64 of the cell and seed.
+    fn feature_point(&self, x: i32, y: i32) -> (f32, f32) {
+        let mut hash = self.seed
+            ^ u64::from(x as u32).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ u64::from(y as u32).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        hash ^= hash >> 31;
+        let unit = |bits: u64| (bits & 0xFF_FFFF) as f32 / (1u32 << 24) as f32;
+        (unit(hash), unit(hash >> 32))
+    }
+}
+
+impl Noise for Worley {
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let (cell_x, cell_y) = (x.floor() as i32, y.floor() as i32);
+        let mut nearest = f32::MAX;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (cx, cy) = (cell_x + dx, cell_y + dy);
+                let (px, py) = self.feature_point(cx, cy);
+                let (ox, oy) = (cx as f32 + px - x, cy as f32 + py - y);
+                nearest = nearest.min(ox * ox + oy * oy);
+            }
+        }
+        nearest.sqrt().min(1.0)
+    }
+}
+
+/// Fractal Brownian motion: sums multiple octaves of the noise with increasing frequency and
+/// decreasing amplitude for more detail. The result is in the same range as the noise.
+#[derive(Debug, Clone)]
+pub struct Fbm<N> {
+    noise: N,
+    octaves: u32,
+    lacunarity: f32,
+    gain: f32,
+}
+
+impl<N: Noise> Fbm<N> {
+    #[inline]
+    pub fn new(noise: N) -> Self {
+        Self {
+            noise,
+            octaves: 4,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+
+    #[inline]
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    /// The factor the frequency is multiplied with for each octave.
+    #[inline]
+    pub fn with_lacunarity(mut self, lacunarity: f32) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    /// The factor the amplitude is multiplied with for each octave.
+    #[inline]
+    pub fn with_gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+}
+
+impl<N: Noise> Noise for Fbm<N> {
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let mut sum = 0.0;
+        let mut total_amplitude = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        for _ in 0..self.octaves {
+            sum += amplitude * self.noise.sample(x * frequency, y * frequency);
+            total_amplitude += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+        sum / total_amplitude
+    }
+}
+
+/// Noise sampled into a row-major grid of values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoiseGrid {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+impl NoiseGrid {
+    /// Samples the noise at `(x * frequency, y * frequency)` for every cell of the grid.
+    pub fn generate(noise: &impl Noise, width: u32, height: u32, frequency: f32) -> Self {
+        Self {
+            width,
+            height,
+            values: (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| noise.sample(x as f32 * frequency, y as f32 * frequency))
+                .collect(),
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    #[inline]
+    pub fn values_mut(&mut self) -> &mut [f32] {
+        &mut self.values
+    }
+
+    #[inline]
+    pub fn get(&self, x: u32, y: u32) -> Option<f32> {
+        if x < self.width && y < self.height {
+            self.values.get((y * self.width + x) as usize).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Remaps the values so that the smallest is `0.0` and the largest is `1.0`.
+    pub fn normalize(&mut self) {
+        let (min, max) = self
+            .values
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), v| {
+                (min.min(*v), max.max(*v))
+            });
+        let range = max - min;
+        if range > f32::EPSILON {
+            self.values
+                .iter_mut()
+                .for_each(|value| *value = (*value - min) / range);
+        }
+    }
+
+    /// Grayscale rgba data, values outside of `0.0..=1.0` are clamped.
+    #[inline]
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.to_rgba_with(|value| {
+            let gray = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            [gray, gray, gray, 255]
+        })
+    }
+
+    /// Rgba data with the colors of the given mapping, for example a gradient for terrain or an
+    /// alpha ramp for clouds.
+    pub fn to_rgba_with(&self, color: impl Fn(f32) -> [u8; 4]) -> Vec<u8> {
+        self.values.iter().flat_map(|value| color(*value)).collect()
+    }
+
+    /// Uploads the [`NoiseGrid::to_rgba`] data as image.
+    #[inline]
+    pub fn create_image(&self, image_system: &ImageSystem) -> Result<Arc<Image>, UploadError> {
+        image_system.create_image_and_enqueue_upload(self.to_rgba(), self.width, self.height)
+    }
+}
@@ -0,0 +1,165 @@
+//! Deterministic random numbers: the same seed always produces the same sequence on every
+//! platform, so gameplay can be reproduced for replays and tests.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// A seedable PCG32 (XSH RR) generator. Generators with the same seed but a different stream
+/// produce independent sequences. The state can be serialized to resume the sequence later.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct Rng {
+    state: u64,
+    increment: u64,
+}
+
+impl Rng {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    /// Creates the generator for the given seed and stream.
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            // must be odd
+            increment: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    #[inline]
+    pub fn from_seed(seed: u64) -> Self {
+        Self::new(seed, 0)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let state = self.state;
+        self.state = state
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xor_shifted = (((state >> 18) ^ state) >> 27) as u32;
+        let rotation = (state >> 59) as u32;
+        xor_shifted.rotate_right(rotation)
+    }
+
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        (u64::from(self.next_u32()) << 32) | u64::from(self.next_u32())
+    }
+
+    /// A value in `0.0..1.0`.
+    #[inline]
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// A value in `0.0..1.0`.
+    #[inline]
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// An unbiased value in the range, `range.start` if the range is empty.
+    pub fn range_u32(&mut self, range: Range<u32>) -> u32 {
+        let span = range.end.saturating_sub(range.start);
+        if span == 0 {
+            return range.start;
+        }
+        // reject the values of the incomplete last span to avoid the modulo bias
+        let threshold = span.wrapping_neg() % span;
+        loop {
+            let value = self.next_u32();
+            if value >= threshold {
+                return range.start + value % span;
+            }
+        }
+    }
+
+    /// A value in the range, `range.start` if the range is empty.
+    #[inline]
+    pub fn range_f32(&mut self, range: Range<f32>) -> f32 {
+        if range.end <= range.start {
+            return range.start;
+        }
+        range.start + self.next_f32() * (range.end - range.start)
+    }
+
+    /// Returns `true` with the given probability (`0.0..=1.0`).
+    #[inline]
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    #[inline]
+    pub fn choose<'a, T>(&mut self, values: &'a [T]) -> Option<&'a T> {
+        if values.is_empty() {
+            None
+        } else {
+            values.get(self.range_u32(0..values.len() as u32) as usize)
+        }
+    }
+
+    /// Shuffles the values with the Fisher-Yates algorithm.
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        for index in (1..values.len()).rev() {
+            let other = self.range_u32(0..index as u32 + 1) as usize;
+            values.swap(index, other);
+        }
+    }
+}
+
+/// Independent [`Rng`]s for each subsystem (like `"ai"`, `"loot"` or `"particles"`), derived
+/// from a single seed. Since every subsystem draws from its own stream, adding or removing
+/// random calls in one subsystem does not change the sequence of the others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
+pub struct RngStreams {
+    seed: u64,
+    streams: BTreeMap<String, Rng>,
+}
+
+impl RngStreams {
+    #[inline]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: BTreeMap::default(),
+        }
+    }
+
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The generator of the stream with the given name, which is created on first use.
+    pub fn stream(&mut self, name: &str) -> &mut Rng {
+        if !self.streams.contains_key(name) {
+            let rng = Rng::new(self.seed, stream_id(name));
+            self.streams.insert(name.to_string(), rng);
+        }
+        self.streams.get_mut(name).expect("Just inserted")
+    }
+
+    /// Restarts all streams from the seed.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.streams.clear();
+    }
+}
+
+/// The FNV-1a hash of the name, which unlike the hashers of the std is stable across platforms
+/// and releases.
+fn stream_id(name: &str) -> u64 {
+    name.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
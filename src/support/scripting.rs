@@ -0,0 +1,403 @@
+//! Game logic in [Rhai](https://rhai.rs) scripts, which are reloaded when the file changes so
+//! behavior can be iterated on without recompiling the game.
+//!
+//! A script can define `init()`, called once after the first load, and `update(dt)`, called every
+//! frame. Both are called with an object map as `this`, which keeps its values across reloads:
+//!
+//! ```rhai
+//! fn init() {
+//!     this.x = 0.0;
+//! }
+//!
+//! fn update(dt) {
+//!     if is_pressed("Right") {
+//!         this.x += 100.0 * dt;
+//!     }
+//!     set_draw_color(1.0, 0.5, 0.0, 1.0);
+//!     fill_rect(this.x, 100.0, 32.0, 32.0);
+//! }
+//! ```
+//!
+//! The scripts do not access the engine directly. The host passes the input to the
+//! [`ScriptHost`] and applies what the script requested afterwards:
+//!
+//! ```no_run
+//! use hotrod::support::scripting::ScriptHost;
+//! # fn frame(
+//! #     events: &[hotrod::sdl2::event::Event],
+//! #     canvas: &mut hotrod::engine::system::canvas::buffered_layer::BufferedCanvasLayer,
+//! #     view: &mut hotrod::support::world2d::view::Map2dView,
+//! #     music: &hotrod::support::music::MusicPlayer,
+//! # ) -> Result<(), hotrod::support::scripting::ScriptError> {
+//! let mut script = ScriptHost::new("scripts/player.rhai")?;
+//!
+//! // in the game loop
+//! events.iter().for_each(|event| script.on_event(event));
+//! script.observe_view(view);
+//! if let Err(e) = script.reload_if_changed() {
+//!     // the previous version of the script keeps running
+//!     eprintln!("{e}");
+//! }
+//! script.update(1.0 / 60.0)?;
+//! script.apply_to_canvas(canvas);
+//! script.apply_to_view(view);
+//! script.apply_to_music(music);
+//! for _sound in script.take_sounds() {
+//!     // play the sound effect
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Functions available to scripts
+//!
+//! | Function | Description |
+//! |---|---|
+//! | `is_pressed(binding)` | Whether the [`InputBinding`] with the name, like `"Space"` or `"Mouse Left"`, is held down |
+//! | `mouse_x()`, `mouse_y()` | The last position of the mouse on the screen |
+//! | `set_draw_color(r, g, b, a)` | The color of the following canvas primitives |
+//! | `draw_line(x0, y0, x1, y1)`, `draw_rect(x, y, w, h)`, `fill_rect(x, y, w, h)` | Draws onto the canvas |
+//! | `play_sound(name)` | Requests a sound effect, see [`ScriptHost::take_sounds`] |
+//! | `music_play(index)`, `music_next()`, `music_stop()`, `music_volume(volume)` | Controls the [`MusicPlayer`] |
+//! | `view_x()`, `view_y()`, `view_zoom()` | The world position and zoom of the observed [`Map2dView`] |
+//! | `view_move(dx, dy)`, `view_set_position(x, y)`, `view_set_zoom(zoom)` | Moves the [`Map2dView`] |
+
+use crate::engine::input::InputBinding;
+use crate::engine::system::canvas::buffered_layer::BufferedCanvasLayer;
+use crate::engine::types::world2d::{Dim, Pos};
+use crate::support::music::MusicPlayer;
+use crate::support::world2d::view::Map2dView;
+use rhai::{CallFnOptions, Dynamic, EvalAltResult, Map, Scope, AST, FLOAT, INT};
+use rustc_hash::FxHashSet;
+use sdl2::event::Event;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptError {
+    #[error("Failed to read the script {0}: {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("Failed to compile the script {0}: {1}")]
+    Compile(PathBuf, #[source] Box<EvalAltResult>),
+    #[error("Failed to call {0}() of the script: {1}")]
+    Call(&'static str, #[source] Box<EvalAltResult>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CanvasCommand {
+    SetDrawColor([f32; 4]),
+    DrawLine(Pos<f32>, Pos<f32>),
+    DrawRect(Pos<f32>, Dim<f32>),
+    FillRect(Pos<f32>, Dim<f32>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MusicCommand {
+    Play(usize),
+    Next,
+    Stop,
+    SetVolume(f32),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ViewCommand {
+    MoveBy(Dim<f32>),
+    SetPosition(Pos<f32>),
+    SetZoom(f32),
+}
+
+/// What the scripts can see of and request from the host, shared with the registered functions.
+struct ScriptState {
+    pressed: FxHashSet<InputBinding>,
+    mouse: Pos<f32>,
+    view: Option<(Pos<f32>, f32)>,
+    canvas: Vec<CanvasCommand>,
+    music: Vec<MusicCommand>,
+    view_commands: Vec<ViewCommand>,
+    sounds: Vec<String>,
+}
+
+impl Default for ScriptState {
+    fn default() -> Self {
+        Self {
+            pressed: FxHashSet::default(),
+            mouse: Pos::new(0.0, 0.0),
+            view: None,
+            canvas: Vec::default(),
+            music: Vec::default(),
+            view_commands: Vec::default(),
+            sounds: Vec::default(),
+        }
+    }
+}
+
+pub struct ScriptHost {
+    engine: rhai::Engine,
+    path: PathBuf,
+    ast: AST,
+    modified: Option<SystemTime>,
+    scope: Scope<'static>,
+    /// Bound as `this` for `init()` and `update(dt)`
+    this: Dynamic,
+    state: Rc<RefCell<ScriptState>>,
+}
+
+impl ScriptHost {
+    /// Compiles the script and calls its `init()` function, if it has one.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, ScriptError> {
+        let state = Rc::new(RefCell::new(ScriptState::default()));
+        let mut engine = rhai::Engine::new();
+        register_functions(&mut engine, &state);
+
+        let path = path.into();
+        let modified = modified(&path)?;
+        let ast = engine
+            .compile_file(path.clone())
+            .map_err(|e| ScriptError::Compile(path.clone(), e))?;
+
+        let mut this = Self {
+            engine,
+            path,
+            ast,
+            modified,
+            scope: Scope::new(),
+            this: Dynamic::from_map(Map::new()),
+            state,
+        };
+        this.call("init", ())?;
+        Ok(this)
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Recompiles the script if the file was modified since it was loaded. If the new version
+    /// fails to compile, the previous version is kept. The values of `this` are kept as well, but
+    /// `init()` is not called again.
+    pub fn reload_if_changed(&mut self) -> Result<bool, ScriptError> {
+        let modified = modified(&self.path)?;
+        if modified == self.modified {
+            return Ok(false);
+        }
+        self.modified = modified;
+        self.ast = self
+            .engine
+            .compile_file(self.path.clone())
+            .map_err(|e| ScriptError::Compile(self.path.clone(), e))?;
+        info!("Reloaded the script {}", self.path.display());
+        Ok(true)
+    }
+
+    /// Tracks the held down inputs and the mouse position for the script.
+    pub fn on_event(&mut self, event: &Event) {
+        let mut state = self.state.borrow_mut();
+        if let Some((binding, pressed)) = InputBinding::from_event(event) {
+            if pressed {
+                state.pressed.insert(binding);
+            } else {
+                state.pressed.remove(&binding);
+            }
+        }
+        match event {
+            Event::MouseMotion { x, y, .. }
+            | Event::MouseButtonDown { x, y, .. }
+            | Event::MouseButtonUp { x, y, .. } => {
+                state.mouse = Pos::new(*x as f32, *y as f32);
+            }
+            _ => {}
+        }
+    }
+
+    /// Makes the position and zoom of the view available to the script.
+    pub fn observe_view(&mut self, view: &Map2dView) {
+        self.state.borrow_mut().view = Some((view.viewed_world_position(), view.zoom()));
+    }
+
+    /// Calls the `update(dt)` function of the script, if it has one.
+    #[inline]
+    pub fn update(&mut self, delta_seconds: f32) -> Result<(), ScriptError> {
+        self.call("update", (delta_seconds as FLOAT,))
+    }
+
+    /// Draws what the script requested since the last call.
+    pub fn apply_to_canvas(&mut self, canvas: &mut BufferedCanvasLayer) {
+        for command in self.state.borrow_mut().canvas.drain(..) {
+            match command {
+                CanvasCommand::SetDrawColor(color) => canvas.set_draw_color(color),
+                CanvasCommand::DrawLine(from, to) => canvas.draw_line(from, to),
+                CanvasCommand::DrawRect(pos, dim) => canvas.draw_rect(pos, dim),
+                CanvasCommand::FillRect(pos, dim) => canvas.fill_rect(pos, dim),
+            }
+        }
+    }
+
+    /// Moves the view as the script requested since the last call.
+    pub fn apply_to_view(&mut self, view: &mut Map2dView) {
+        for command in self.state.borrow_mut().view_commands.drain(..) {
+            match command {
+                ViewCommand::MoveBy(delta) => {
+                    let pos = view.viewed_world_position();
+                    view.set_viewed_world_position(pos.x + delta.x, pos.y + delta.y);
+                }
+                ViewCommand::SetPosition(pos) => view.set_viewed_world_position(pos.x, pos.y),
+                ViewCommand::SetZoom(zoom) => view.set_zoom(zoom),
+            }
+        }
+    }
+
+    /// Controls the music as the script requested since the last call.
+    pub fn apply_to_music(&mut self, music: &MusicPlayer) {
+        for command in self.state.borrow_mut().music.drain(..) {
+            match command {
+                MusicCommand::Play(index) => music.play(index),
+                MusicCommand::Next => music.next(),
+                MusicCommand::Stop => music.stop(),
+                MusicCommand::SetVolume(volume) => music.set_volume(volume),
+            }
+        }
+    }
+
+    /// The names of the sound effects the script requested since the last call.
+    #[inline]
+    pub fn take_sounds(&mut self) -> Vec<String> {
+        core::mem::take(&mut self.state.borrow_mut().sounds)
+    }
+
+    fn call(&mut self, name: &'static str, args: impl rhai::FuncArgs) -> Result<(), ScriptError> {
+        if !self.ast.iter_functions().any(|f| f.name == name) {
+            return Ok(());
+        }
+        self.engine
+            .call_fn_with_options::<Dynamic>(
+                CallFnOptions::new()
+                    .eval_ast(false)
+                    .bind_this_ptr(&mut self.this),
+                &mut self.scope,
+                &self.ast,
+                name,
+                args,
+            )
+            .map(drop)
+            .map_err(|e| ScriptError::Call(name, e))
+    }
+}
+
+fn modified(path: &Path) -> Result<Option<SystemTime>, ScriptError> {
+    std::fs::metadata(path)
+        .map(|metadata| metadata.modified().ok())
+        .map_err(|e| ScriptError::Io(path.to_path_buf(), e))
+}
+
+fn register_functions(engine: &mut rhai::Engine, state: &Rc<RefCell<ScriptState>>) {
+    let s = Rc::clone(state);
+    engine.register_fn("is_pressed", move |binding: &str| {
+        InputBinding::from_str(binding)
+            .map(|binding| s.borrow().pressed.contains(&binding))
+            .unwrap_or(false)
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("mouse_x", move || s.borrow().mouse.x as FLOAT);
+    let s = Rc::clone(state);
+    engine.register_fn("mouse_y", move || s.borrow().mouse.y as FLOAT);
+
+    let s = Rc::clone(state);
+    engine.register_fn(
+        "set_draw_color",
+        move |r: FLOAT, g: FLOAT, b: FLOAT, a: FLOAT| {
+            s.borrow_mut().canvas.push(CanvasCommand::SetDrawColor([
+                r as f32, g as f32, b as f32, a as f32,
+            ]))
+        },
+    );
+    let s = Rc::clone(state);
+    engine.register_fn(
+        "draw_line",
+        move |x0: FLOAT, y0: FLOAT, x1: FLOAT, y1: FLOAT| {
+            s.borrow_mut().canvas.push(CanvasCommand::DrawLine(
+                Pos::new(x0 as f32, y0 as f32),
+                Pos::new(x1 as f32, y1 as f32),
+            ))
+        },
+    );
+    let s = Rc::clone(state);
+    engine.register_fn(
+        "draw_rect",
+        move |x: FLOAT, y: FLOAT, w: FLOAT, h: FLOAT| {
+            s.borrow_mut().canvas.push(CanvasCommand::DrawRect(
+                Pos::new(x as f32, y as f32),
+                Dim::new(w as f32, h as f32),
+            ))
+        },
+    );
+    let s = Rc::clone(state);
+    engine.register_fn(
+        "fill_rect",
+        move |x: FLOAT, y: FLOAT, w: FLOAT, h: FLOAT| {
+            s.borrow_mut().canvas.push(CanvasCommand::FillRect(
+                Pos::new(x as f32, y as f32),
+                Dim::new(w as f32, h as f32),
+            ))
+        },
+    );
+
+    let s = Rc::clone(state);
+    engine.register_fn("play_sound", move |name: &str| {
+        s.borrow_mut().sounds.push(name.to_string())
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("music_play", move |index: INT| {
+        s.borrow_mut()
+            .music
+            .push(MusicCommand::Play(index.max(0) as usize))
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("music_next", move || {
+        s.borrow_mut().music.push(MusicCommand::Next)
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("music_stop", move || {
+        s.borrow_mut().music.push(MusicCommand::Stop)
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("music_volume", move |volume: FLOAT| {
+        s.borrow_mut()
+            .music
+            .push(MusicCommand::SetVolume(volume as f32))
+    });
+
+    let s = Rc::clone(state);
+    engine.register_fn("view_x", move || {
+        s.borrow().view.map_or(0.0, |(pos, _)| pos.x) as FLOAT
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("view_y", move || {
+        s.borrow().view.map_or(0.0, |(pos, _)| pos.y) as FLOAT
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("view_zoom", move || {
+        s.borrow().view.map_or(1.0, |(_, zoom)| zoom) as FLOAT
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("view_move", move |dx: FLOAT, dy: FLOAT| {
+        s.borrow_mut()
+            .view_commands
+            .push(ViewCommand::MoveBy(Dim::new(dx as f32, dy as f32)))
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("view_set_position", move |x: FLOAT, y: FLOAT| {
+        s.borrow_mut()
+            .view_commands
+            .push(ViewCommand::SetPosition(Pos::new(x as f32, y as f32)))
+    });
+    let s = Rc::clone(state);
+    engine.register_fn("view_set_zoom", move |zoom: FLOAT| {
+        s.borrow_mut()
+            .view_commands
+            .push(ViewCommand::SetZoom(zoom as f32))
+    });
+}
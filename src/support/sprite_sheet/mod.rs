@@ -8,6 +8,10 @@ pub mod generator;
 pub mod xml_texture_atlas;
 
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
 pub struct SpriteSheet<T> {
     size: Dim<T>,
     sprites: Vec<Sprite<T>>,
@@ -83,6 +87,10 @@ impl<T> Index<&str> for SpriteSheet<T> {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
 pub struct Sprite<T> {
     pub pos: Pos<T>,
     pub dim: Dim<T>,
@@ -0,0 +1,316 @@
+//! An egui widget to paint tile maps with the sprites of a [`SpriteSheet`].
+//!
+//! The widget does not modify the map itself, but returns the [`TileEdit`]s of the frame, so
+//! that the game can apply them to however its chunks are stored and record them for undo.
+//! The tiles are painted through egui meshes, the sprite sheet therefore has to be registered
+//! as an egui texture (see [`egui::Context::load_texture`]).
+
+use crate::engine::types::world2d::Pos;
+use crate::support::sprite_sheet::SpriteSheet;
+use crate::support::world2d::view::Map2dView;
+use egui::{
+    Color32, ImageButton, Mesh, PointerButton, Rect, Response, Sense, Shape, Stroke, TextureId, Ui,
+    Vec2,
+};
+
+/// Read access to the tiles displayed by the [`TileMapEditor`], a tile is the index of a sprite
+/// in the [`SpriteSheet`] or [`None`] if empty.
+pub trait TileSource {
+    /// The amount of tiles in x and y direction.
+    fn dimensions(&self) -> (u32, u32);
+
+    fn tile(&self, x: u32, y: u32) -> Option<usize>;
+}
+
+#[cfg(feature = "world2d-snapshot")]
+impl TileSource for crate::support::world2d::snapshot::TerrainGrid<Option<usize>> {
+    #[inline]
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width(), self.height())
+    }
+
+    #[inline]
+    fn tile(&self, x: u32, y: u32) -> Option<usize> {
+        self.get(x, y).copied().flatten()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TileEdit {
+    pub x: u32,
+    pub y: u32,
+    /// The new sprite index, [`None`] if the tile was erased
+    pub tile: Option<usize>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TileTool {
+    Paint,
+    Erase,
+}
+
+/// Shows the palette of the [`SpriteSheet`], the tools and the map. The map is painted with the
+/// primary button, zoomed with the scroll wheel and panned by dragging with the secondary or
+/// middle button.
+pub struct TileMapEditor {
+    view: Map2dView,
+    tile_size: f32,
+    tool: TileTool,
+    selected: Option<usize>,
+    palette_width: f32,
+    show_grid: bool,
+    last_edit: Option<(u32, u32)>,
+}
+
+impl TileMapEditor {
+    const ZOOM_STEP: f32 = 1.2;
+    const PALETTE_TILE_SIZE: f32 = 32.0;
+
+    /// The size of a tile in world units at a zoom of `1.0`.
+    #[inline]
+    pub fn new(tile_size: f32) -> Self {
+        Self {
+            view: Map2dView::new(0, 0),
+            tile_size,
+            tool: TileTool::Paint,
+            selected: None,
+            palette_width: 160.0,
+            show_grid: true,
+            last_edit: None,
+        }
+    }
+
+    #[inline]
+    pub fn with_palette_width(mut self, width: f32) -> Self {
+        self.palette_width = width;
+        self
+    }
+
+    #[inline]
+    pub fn with_grid(mut self, show_grid: bool) -> Self {
+        self.show_grid = show_grid;
+        self
+    }
+
+    #[inline]
+    pub fn view(&self) -> &Map2dView {
+        &self.view
+    }
+
+    #[inline]
+    pub fn view_mut(&mut self) -> &mut Map2dView {
+        &mut self.view
+    }
+
+    #[inline]
+    pub fn tool(&self) -> TileTool {
+        self.tool
+    }
+
+    #[inline]
+    pub fn set_tool(&mut self, tool: TileTool) {
+        self.tool = tool;
+    }
+
+    /// The index of the sprite that is painted with [`TileTool::Paint`].
+    #[inline]
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    #[inline]
+    pub fn set_selected(&mut self, selected: Option<usize>) {
+        self.selected = selected;
+    }
+
+    /// Shows the editor for the tiles, whose sprites are looked up in the `sheet` (in uv
+    /// coordinates, see [`SpriteSheet::into_uv`]) of the egui `texture`.
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        texture: TextureId,
+        sheet: &SpriteSheet<f32>,
+        tiles: &impl TileSource,
+    ) -> Vec<TileEdit> {
+        let mut edits = Vec::new();
+        ui.horizontal_top(|ui| {
+            ui.vertical(|ui| {
+                ui.set_width(self.palette_width);
+                self.show_tools(ui);
+                ui.separator();
+                self.show_palette(ui, texture, sheet);
+            });
+            ui.separator();
+            edits = self.show_map(ui, texture, sheet, tiles);
+        });
+        edits
+    }
+
+    fn show_tools(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.tool, TileTool::Paint, "Paint");
+            ui.selectable_value(&mut self.tool, TileTool::Erase, "Erase");
+        });
+        ui.checkbox(&mut self.show_grid, "Grid");
+    }
+
+    fn show_palette(&mut self, ui: &mut Ui, texture: TextureId, sheet: &SpriteSheet<f32>) {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (index, sprite) in sheet.iter().enumerate() {
+                    let button = ImageButton::new((texture, Vec2::splat(Self::PALETTE_TILE_SIZE)))
+                        .uv(uv_rect(sprite.pos, sprite.pos + sprite.dim))
+                        .selected(self.selected == Some(index));
+
+                    if ui.add(button).clicked() {
+                        self.selected = Some(index);
+                        self.tool = TileTool::Paint;
+                    }
+                }
+            });
+        });
+    }
+
+    fn show_map(
+        &mut self,
+        ui: &mut Ui,
+        texture: TextureId,
+        sheet: &SpriteSheet<f32>,
+        tiles: &impl TileSource,
+    ) -> Vec<TileEdit> {
+        let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
+        let rect = response.rect;
+        self.view
+            .update_screen_size(rect.width() as u32, rect.height() as u32);
+        self.update_view(ui, &response);
+
+        let (width, height) = tiles.dimensions();
+        let sprites = sheet.iter().collect::<Vec<_>>();
+        let to_screen = |x: f32, y: f32| {
+            let pos = self
+                .view
+                .position_world_to_screen(Pos::new(x * self.tile_size, y * self.tile_size));
+            rect.min + Vec2::new(pos.x, pos.y)
+        };
+
+        // only the visible tiles
+        let (min_x, min_y) = self.tile_at(rect, rect.min);
+        let (max_x, max_y) = self.tile_at(rect, rect.max);
+        let range_x = min_x.floor().max(0.0) as u32..(max_x.ceil().max(0.0) as u32).min(width);
+        let range_y = min_y.floor().max(0.0) as u32..(max_y.ceil().max(0.0) as u32).min(height);
+
+        let mut mesh = Mesh::with_texture(texture);
+        for y in range_y.clone() {
+            for x in range_x.clone() {
+                if let Some(sprite) = tiles.tile(x, y).and_then(|tile| sprites.get(tile)) {
+                    mesh.add_rect_with_uv(
+                        Rect::from_min_max(
+                            to_screen(x as f32, y as f32),
+                            to_screen(x as f32 + 1.0, y as f32 + 1.0),
+                        ),
+                        uv_rect(sprite.pos, sprite.pos + sprite.dim),
+                        Color32::WHITE,
+                    );
+                }
+            }
+        }
+
+        let painter = painter.with_clip_rect(rect);
+        painter.add(Shape::mesh(mesh));
+
+        if self.show_grid {
+            let stroke = Stroke::new(1.0, Color32::from_white_alpha(32));
+            for x in range_x.start..=range_x.end {
+                painter.line_segment(
+                    [
+                        to_screen(x as f32, range_y.start as f32),
+                        to_screen(x as f32, range_y.end as f32),
+                    ],
+                    stroke,
+                );
+            }
+            for y in range_y.start..=range_y.end {
+                painter.line_segment(
+                    [
+                        to_screen(range_x.start as f32, y as f32),
+                        to_screen(range_x.end as f32, y as f32),
+                    ],
+                    stroke,
+                );
+            }
+        }
+
+        let hovered = response
+            .hover_pos()
+            .map(|pos| self.tile_at(rect, pos))
+            .filter(|(x, y)| *x >= 0.0 && *y >= 0.0 && *x < width as f32 && *y < height as f32)
+            .map(|(x, y)| (x as u32, y as u32));
+
+        if let Some((x, y)) = hovered {
+            painter.rect_stroke(
+                Rect::from_min_max(
+                    to_screen(x as f32, y as f32),
+                    to_screen(x as f32 + 1.0, y as f32 + 1.0),
+                ),
+                0.0,
+                Stroke::new(2.0, Color32::YELLOW),
+            );
+        }
+
+        let mut edits = Vec::new();
+        let painting = response.dragged_by(PointerButton::Primary)
+            || response.clicked_by(PointerButton::Primary);
+        match hovered {
+            Some((x, y)) if painting && self.last_edit != Some((x, y)) => {
+                let tile = match self.tool {
+                    TileTool::Paint => self.selected,
+                    TileTool::Erase => None,
+                };
+                if (self.tool == TileTool::Erase || tile.is_some()) && tiles.tile(x, y) != tile {
+                    edits.push(TileEdit { x, y, tile });
+                }
+                self.last_edit = Some((x, y));
+            }
+            _ if !painting => self.last_edit = None,
+            _ => {}
+        }
+        edits
+    }
+
+    fn update_view(&mut self, ui: &Ui, response: &Response) {
+        if let Some(pos) = response.hover_pos() {
+            let scroll = ui.input(|input| input.raw_scroll_delta.y);
+            let zoom = if scroll > 0.0 {
+                Some(self.view.zoom() * Self::ZOOM_STEP)
+            } else if scroll < 0.0 {
+                Some(self.view.zoom() / Self::ZOOM_STEP)
+            } else {
+                None
+            };
+            if let Some(zoom) = zoom {
+                let pos = pos - response.rect.min;
+                self.view
+                    .update_zoom_at_screen_position(zoom, Pos::new(pos.x, pos.y));
+            }
+        }
+
+        if response.dragged_by(PointerButton::Secondary)
+            || response.dragged_by(PointerButton::Middle)
+        {
+            let delta = response.drag_delta();
+            self.view.move_by_screen_delta(delta.x, delta.y);
+        }
+    }
+
+    /// The fractional tile coordinates at the screen position.
+    fn tile_at(&self, rect: Rect, pos: egui::Pos2) -> (f32, f32) {
+        let pos = pos - rect.min;
+        let world = self.view.position_screen_to_world(Pos::new(pos.x, pos.y));
+        (world.x / self.tile_size, world.y / self.tile_size)
+    }
+}
+
+#[inline]
+fn uv_rect(min: Pos<f32>, max: Pos<f32>) -> Rect {
+    Rect::from_min_max(egui::pos2(min.x, min.y), egui::pos2(max.x, max.y))
+}
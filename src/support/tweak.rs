@@ -0,0 +1,401 @@
+//! Named values that can be changed while the game is running, to tune constants without
+//! recompiling.
+//!
+//! ```no_run
+//! use hotrod::support::tweak::Tweaks;
+//!
+//! let mut tweaks = Tweaks::default().with_file("tweaks.txt");
+//! let gravity = tweaks.register_f32("physics.gravity", 9.81, 0.0..=30.0);
+//! let debug_draw = tweaks.register("debug.draw_hitboxes", false);
+//!
+//! // in the game loop
+//! let _acceleration = gravity.get();
+//! if debug_draw.get() {
+//!     // ...
+//! }
+//! // and inside an egui window: tweaks.show(ui);
+//! ```
+
+use crate::engine::types::world2d::Dim;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TweakValue {
+    F32 {
+        value: f32,
+        range: RangeInclusive<f32>,
+    },
+    Bool(bool),
+    Color([f32; 4]),
+    Vec2(Dim<f32>),
+}
+
+impl TweakValue {
+    /// Replaces the value if the other value is of the same type, keeps the range of an
+    /// [`TweakValue::F32`].
+    fn assign(&mut self, other: &TweakValue) -> bool {
+        match (self, other) {
+            (TweakValue::F32 { value, range }, TweakValue::F32 { value: other, .. }) => {
+                *value = other.clamp(*range.start(), *range.end());
+            }
+            (TweakValue::Bool(value), TweakValue::Bool(other)) => *value = *other,
+            (TweakValue::Color(value), TweakValue::Color(other)) => *value = *other,
+            (TweakValue::Vec2(value), TweakValue::Vec2(other)) => *value = *other,
+            _ => return false,
+        }
+        true
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        match value {
+            "true" => Some(TweakValue::Bool(true)),
+            "false" => Some(TweakValue::Bool(false)),
+            _ if value.starts_with('[') && value.ends_with(']') => {
+                let values = value[1..value.len() - 1]
+                    .split(',')
+                    .map(|v| v.trim().parse::<f32>().ok())
+                    .collect::<Option<Vec<_>>>()?;
+                match values[..] {
+                    [x, y] => Some(TweakValue::Vec2(Dim::new(x, y))),
+                    [r, g, b, a] => Some(TweakValue::Color([r, g, b, a])),
+                    _ => None,
+                }
+            }
+            _ => value.parse().ok().map(|value| TweakValue::F32 {
+                value,
+                range: f32::MIN..=f32::MAX,
+            }),
+        }
+    }
+}
+
+impl Display for TweakValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TweakValue::F32 { value, .. } => write!(f, "{value:?}"),
+            TweakValue::Bool(value) => write!(f, "{value}"),
+            TweakValue::Color([r, g, b, a]) => write!(f, "[{r:?}, {g:?}, {b:?}, {a:?}]"),
+            TweakValue::Vec2(value) => write!(f, "[{:?}, {:?}]", value.x, value.y),
+        }
+    }
+}
+
+/// The types that can be registered as [`Tweak`].
+pub trait TweakType: Copy {
+    fn into_value(self) -> TweakValue;
+
+    fn from_value(value: &TweakValue) -> Option<Self>;
+}
+
+impl TweakType for f32 {
+    #[inline]
+    fn into_value(self) -> TweakValue {
+        TweakValue::F32 {
+            value: self,
+            range: f32::MIN..=f32::MAX,
+        }
+    }
+
+    #[inline]
+    fn from_value(value: &TweakValue) -> Option<Self> {
+        match value {
+            TweakValue::F32 { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl TweakType for bool {
+    #[inline]
+    fn into_value(self) -> TweakValue {
+        TweakValue::Bool(self)
+    }
+
+    #[inline]
+    fn from_value(value: &TweakValue) -> Option<Self> {
+        match value {
+            TweakValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// A RGBA color
+impl TweakType for [f32; 4] {
+    #[inline]
+    fn into_value(self) -> TweakValue {
+        TweakValue::Color(self)
+    }
+
+    #[inline]
+    fn from_value(value: &TweakValue) -> Option<Self> {
+        match value {
+            TweakValue::Color(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl TweakType for Dim<f32> {
+    #[inline]
+    fn into_value(self) -> TweakValue {
+        TweakValue::Vec2(self)
+    }
+
+    #[inline]
+    fn from_value(value: &TweakValue) -> Option<Self> {
+        match value {
+            TweakValue::Vec2(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+struct TweakEntry {
+    default: TweakValue,
+    current: Arc<Mutex<TweakValue>>,
+}
+
+/// A handle to a registered value, cheap to clone and to read every frame.
+pub struct Tweak<T> {
+    current: Arc<Mutex<TweakValue>>,
+    default: T,
+}
+
+impl<T: TweakType> Clone for Tweak<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            current: Arc::clone(&self.current),
+            default: self.default,
+        }
+    }
+}
+
+impl<T: TweakType> Tweak<T> {
+    /// A handle that is not part of any [`Tweaks`] and always returns the value.
+    #[inline]
+    pub fn constant(value: T) -> Self {
+        Self {
+            current: Arc::new(Mutex::new(value.into_value())),
+            default: value,
+        }
+    }
+
+    #[inline]
+    pub fn get(&self) -> T {
+        self.current
+            .lock()
+            .ok()
+            .and_then(|value| T::from_value(&*value))
+            .unwrap_or(self.default)
+    }
+
+    #[inline]
+    pub fn set(&self, value: T) {
+        if let Ok(mut current) = self.current.lock() {
+            current.assign(&value.into_value());
+        }
+    }
+}
+
+/// The registry of all [`Tweak`]s of a game.
+#[derive(Default)]
+pub struct Tweaks {
+    entries: BTreeMap<String, TweakEntry>,
+    /// Values that were loaded but not registered yet
+    loaded: BTreeMap<String, TweakValue>,
+    file: Option<PathBuf>,
+}
+
+impl Tweaks {
+    /// Loads the values from the file, if it exists, and stores changes made in the inspector
+    /// back into it.
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        if path.exists() {
+            if let Err(e) = self.load(&path) {
+                warn!("Failed to load the tweaks from {}: {e}", path.display());
+            }
+        }
+        self.file = Some(path);
+        self
+    }
+
+    /// Registers the value with the given name or returns the handle to the already registered
+    /// value. If a value with that name was loaded, it replaces the default.
+    pub fn register<T: TweakType>(&mut self, name: impl Into<String>, default: T) -> Tweak<T> {
+        self.register_value(name.into(), default, default.into_value())
+    }
+
+    /// Like [`Tweaks::register`], but the inspector shows a slider for the range.
+    pub fn register_f32(
+        &mut self,
+        name: impl Into<String>,
+        default: f32,
+        range: RangeInclusive<f32>,
+    ) -> Tweak<f32> {
+        self.register_value(
+            name.into(),
+            default,
+            TweakValue::F32 {
+                value: default,
+                range,
+            },
+        )
+    }
+
+    fn register_value<T: TweakType>(
+        &mut self,
+        name: String,
+        default: T,
+        value: TweakValue,
+    ) -> Tweak<T> {
+        if let Some(entry) = self.entries.get(&name) {
+            if T::from_value(&entry.default).is_some() {
+                return Tweak {
+                    current: Arc::clone(&entry.current),
+                    default,
+                };
+            }
+            warn!("Tweak {name:?} is re-registered with a different type");
+        }
+
+        let mut current = value.clone();
+        if let Some(loaded) = self.loaded.remove(&name) {
+            if !current.assign(&loaded) {
+                warn!("Ignoring the loaded value of the tweak {name:?} because of its type");
+            }
+        }
+
+        let current = Arc::new(Mutex::new(current));
+        self.entries.insert(
+            name,
+            TweakEntry {
+                default: value,
+                current: Arc::clone(&current),
+            },
+        );
+        Tweak { current, default }
+    }
+
+    /// Resets all values to their defaults.
+    pub fn reset(&mut self) {
+        for entry in self.entries.values() {
+            if let Ok(mut current) = entry.current.lock() {
+                *current = entry.default.clone();
+            }
+        }
+    }
+
+    /// Reads the values from the file, one `name = value` pair per line.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        for (index, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line
+                .split_once('=')
+                .and_then(|(name, value)| Some((name.trim(), TweakValue::parse(value)?)))
+            else {
+                warn!("Ignoring invalid tweak in line {}: {line:?}", index + 1);
+                continue;
+            };
+
+            match self.entries.get(name) {
+                Some(entry) => {
+                    if let Ok(mut current) = entry.current.lock() {
+                        current.assign(&value);
+                    }
+                }
+                None => {
+                    self.loaded.insert(name.to_string(), value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes all values that differ from their defaults.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let mut content = String::new();
+        for (name, entry) in &self.entries {
+            if let Ok(current) = entry.current.lock() {
+                if *current != entry.default {
+                    content.push_str(&format!("{name} = {current}\n"));
+                }
+            }
+        }
+        for (name, value) in &self.loaded {
+            content.push_str(&format!("{name} = {value}\n"));
+        }
+        std::fs::write(path, content)
+    }
+
+    #[cfg(feature = "ui-egui")]
+    fn save_to_file(&self) {
+        if let Some(path) = &self.file {
+            if let Err(e) = self.save(path) {
+                warn!("Failed to save the tweaks to {}: {e}", path.display());
+            }
+        }
+    }
+
+    /// Shows an inspector with an editor for every registered value, changes are saved to the
+    /// file (see [`Tweaks::with_file`]) immediately.
+    #[cfg(feature = "ui-egui")]
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+
+        egui::Grid::new("hotrod-tweaks")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for (name, entry) in &self.entries {
+                    let Ok(mut current) = entry.current.lock() else {
+                        continue;
+                    };
+
+                    ui.label(name);
+                    changed |= match &mut *current {
+                        TweakValue::F32 { value, range } => {
+                            if *range.start() > f32::MIN && *range.end() < f32::MAX {
+                                ui.add(egui::Slider::new(value, range.clone()))
+                            } else {
+                                ui.add(egui::DragValue::new(value).speed(0.01))
+                            }
+                        }
+                        TweakValue::Bool(value) => ui.checkbox(value, ""),
+                        TweakValue::Color(value) => ui.color_edit_button_rgba_unmultiplied(value),
+                        TweakValue::Vec2(value) => {
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut value.x).speed(0.01))
+                                    | ui.add(egui::DragValue::new(&mut value.y).speed(0.01))
+                            })
+                            .inner
+                        }
+                    }
+                    .changed();
+
+                    if ui
+                        .add_enabled(*current != entry.default, egui::Button::new("Reset"))
+                        .clicked()
+                    {
+                        *current = entry.default.clone();
+                        changed = true;
+                    }
+                    ui.end_row();
+                }
+            });
+
+        if changed {
+            self.save_to_file();
+        }
+    }
+}
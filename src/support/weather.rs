@@ -0,0 +1,338 @@
+//! Ready-made rain and snow. The particles are simulated on the CPU in world coordinates and
+//! wrap around the visible area of the [`Map2dView`], so the weather covers the whole screen
+//! regardless of where the camera moves. Rain streaks are drawn through the [`TrailPipeline`],
+//! snow flakes and rain splashes through the [`GlowingBallsPipeline`].
+//!
+//! [`TrailPipeline`]: crate::engine::system::vulkan::trails::TrailPipeline
+//! [`GlowingBallsPipeline`]: crate::engine::system::vulkan::glowing_balls::GlowingBallsPipeline
+
+use crate::engine::accessibility::AccessibilityOptions;
+use crate::engine::system::vulkan::glowing_balls::GlowingBall;
+use crate::engine::system::vulkan::pipelines::VulkanPipelines;
+use crate::engine::system::vulkan::trails::{Trail, TrailPoint};
+use crate::engine::system::vulkan::DrawError;
+use crate::engine::types::world2d::{Dim, Pos};
+use crate::support::rng::Rng;
+use crate::support::world2d::view::Map2dView;
+use std::time::Duration;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum WeatherKind {
+    Rain,
+    Snow,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Particle {
+    pos: Pos<f32>,
+    fall_speed: f32,
+    age: f32,
+    lifetime: f32,
+    /// The phase of the sway of snow flakes
+    phase: f32,
+    size: f32,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Droplet {
+    pos: Pos<f32>,
+    velocity: Dim<f32>,
+    age: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Weather {
+    kind: WeatherKind,
+    intensity: f32,
+    wind: f32,
+    max_particles: usize,
+    fall_speed: f32,
+    color: [f32; 4],
+    /// See [`AccessibilityOptions::motion_scale`]
+    motion_scale: f32,
+    particles: Vec<Particle>,
+    droplets: Vec<Droplet>,
+    rng: Rng,
+}
+
+impl Weather {
+    const RAIN_STREAK_DURATION: f32 = 0.03;
+    const SPLASH_DROPLETS: usize = 3;
+    const SPLASH_LIFETIME: f32 = 0.25;
+    const SPLASH_GRAVITY: f32 = 600.0;
+    const SNOW_SWAY_AMPLITUDE: f32 = 20.0;
+    const SNOW_SWAY_FREQUENCY: f32 = 1.3;
+
+    /// Falling rain streaks that burst into splashes when they hit the ground.
+    pub fn rain(seed: u64) -> Self {
+        Self {
+            kind: WeatherKind::Rain,
+            intensity: 1.0,
+            wind: 0.0,
+            max_particles: 1500,
+            fall_speed: 900.0,
+            color: [0.7, 0.75, 0.85, 0.5],
+            motion_scale: 1.0,
+            particles: Vec::default(),
+            droplets: Vec::default(),
+            rng: Rng::from_seed(seed),
+        }
+    }
+
+    /// Slowly drifting snow flakes.
+    pub fn snow(seed: u64) -> Self {
+        Self {
+            kind: WeatherKind::Snow,
+            intensity: 1.0,
+            wind: 0.0,
+            max_particles: 800,
+            fall_speed: 60.0,
+            color: [1.0, 1.0, 1.0, 0.9],
+            motion_scale: 1.0,
+            particles: Vec::default(),
+            droplets: Vec::default(),
+            rng: Rng::from_seed(seed),
+        }
+    }
+
+    /// The share of the maximum amount of particles, in `0.0..=1.0`.
+    #[inline]
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.set_intensity(intensity);
+        self
+    }
+
+    /// The horizontal speed added by the wind, in world units per second.
+    #[inline]
+    pub fn with_wind(mut self, wind: f32) -> Self {
+        self.wind = wind;
+        self
+    }
+
+    /// The amount of particles on the screen at full intensity.
+    #[inline]
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        self
+    }
+
+    /// The vertical speed, in world units per second.
+    #[inline]
+    pub fn with_fall_speed(mut self, fall_speed: f32) -> Self {
+        self.fall_speed = fall_speed;
+        self
+    }
+
+    #[inline]
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Slows the particles down with [`AccessibilityOptions::reduced_motion`].
+    #[inline]
+    pub fn with_accessibility(mut self, options: &AccessibilityOptions) -> Self {
+        self.set_accessibility(options);
+        self
+    }
+
+    #[inline]
+    pub fn set_accessibility(&mut self, options: &AccessibilityOptions) {
+        self.motion_scale = options.motion_scale();
+    }
+
+    #[inline]
+    pub fn kind(&self) -> WeatherKind {
+        self.kind
+    }
+
+    #[inline]
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Changes the intensity gradually: new particles are only spawned while there are too few
+    /// and excess particles are removed as soon as they reach the end of their lifetime.
+    #[inline]
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity.clamp(0.0, 1.0);
+    }
+
+    #[inline]
+    pub fn wind(&self) -> f32 {
+        self.wind
+    }
+
+    #[inline]
+    pub fn set_wind(&mut self, wind: f32) {
+        self.wind = wind;
+    }
+
+    pub fn update(&mut self, delta: Duration, view: &Map2dView) {
+        let delta = delta.as_secs_f32();
+        let (min, max) = visible_area(view);
+        let size = max - min;
+        if size.x <= 0.0 || size.y <= 0.0 {
+            return;
+        }
+
+        let target = (self.max_particles as f32 * self.intensity) as usize;
+        // the lifetimes keep running at full speed, so slowed down rain hits the ground earlier
+        let motion = delta * self.motion_scale;
+        let mut index = 0;
+        while index < self.particles.len() {
+            let particle = &mut self.particles[index];
+            particle.age += delta;
+            particle.pos.x += self.wind * motion;
+            particle.pos.y += particle.fall_speed * motion;
+            if self.kind == WeatherKind::Snow {
+                let sway = (particle.age * Self::SNOW_SWAY_FREQUENCY + particle.phase).cos();
+                particle.pos.x += sway * Self::SNOW_SWAY_AMPLITUDE * motion;
+            }
+
+            // keep the particles within the visible area, so the weather tiles across the world
+            particle.pos.x = min.x + (particle.pos.x - min.x).rem_euclid(size.x);
+            particle.pos.y = min.y + (particle.pos.y - min.y).rem_euclid(size.y);
+
+            if particle.age >= particle.lifetime {
+                let particle = self.particles.swap_remove(index);
+                if self.kind == WeatherKind::Rain {
+                    self.splash(particle.pos);
+                }
+                // replace it right away unless the intensity was reduced
+                if self.particles.len() < target {
+                    self.spawn(min, size);
+                }
+            } else {
+                index += 1;
+            }
+        }
+
+        while self.particles.len() < target {
+            self.spawn(min, size);
+        }
+
+        self.droplets.retain_mut(|droplet| {
+            droplet.age += delta;
+            droplet.velocity.y += Self::SPLASH_GRAVITY * motion;
+            droplet.pos += droplet.velocity * motion;
+            droplet.age < Self::SPLASH_LIFETIME
+        });
+    }
+
+    fn spawn(&mut self, min: Pos<f32>, size: Dim<f32>) {
+        let rng = &mut self.rng;
+        let (lifetime, particle_size) = match self.kind {
+            // the time until it hits the ground
+            WeatherKind::Rain => (rng.range_f32(0.2..1.5), rng.range_f32(1.0..1.8)),
+            WeatherKind::Snow => (rng.range_f32(4.0..12.0), rng.range_f32(1.0..3.5)),
+        };
+        self.particles.push(Particle {
+            pos: Pos::new(
+                min.x + rng.next_f32() * size.x,
+                min.y + rng.next_f32() * size.y,
+            ),
+            // smaller flakes and drops are further away and fall slower
+            fall_speed: self.fall_speed * (0.6 + particle_size * 0.2),
+            age: 0.0,
+            lifetime,
+            phase: rng.range_f32(0.0..std::f32::consts::TAU),
+            size: particle_size,
+        });
+    }
+
+    fn splash(&mut self, pos: Pos<f32>) {
+        for _ in 0..Self::SPLASH_DROPLETS {
+            let velocity = Dim::new(
+                self.rng.range_f32(-60.0..60.0) + self.wind * 0.2,
+                self.rng.range_f32(-150.0..-60.0),
+            );
+            self.droplets.push(Droplet {
+                pos,
+                velocity,
+                age: 0.0,
+            });
+        }
+    }
+
+    /// The rain streaks in screen coordinates, to be drawn with the [`TrailPipeline`].
+    ///
+    /// [`TrailPipeline`]: crate::engine::system::vulkan::trails::TrailPipeline
+    pub fn streaks(&self, view: &Map2dView) -> Vec<Trail> {
+        if self.kind != WeatherKind::Rain {
+            return Vec::new();
+        }
+        self.particles
+            .iter()
+            .map(|particle| {
+                let velocity = Dim::new(self.wind, particle.fall_speed) * self.motion_scale;
+                let tail = particle.pos - velocity * Self::RAIN_STREAK_DURATION;
+                let width = view.scalar_distance_world_to_screen(particle.size);
+                let mut trail = Trail::new(2).with_taper(false);
+                for pos in [tail, particle.pos] {
+                    let pos = view.position_world_to_screen(pos);
+                    trail.push(TrailPoint {
+                        pos: [pos.x, pos.y],
+                        width,
+                        color: self.color,
+                    });
+                }
+                trail
+            })
+            .collect()
+    }
+
+    /// The snow flakes and rain splashes in world coordinates, to be drawn with the
+    /// [`GlowingBallsPipeline`].
+    ///
+    /// [`GlowingBallsPipeline`]: crate::engine::system::vulkan::glowing_balls::GlowingBallsPipeline
+    pub fn balls(&self) -> Vec<GlowingBall> {
+        let flakes = self
+            .particles
+            .iter()
+            .filter(|_| self.kind == WeatherKind::Snow)
+            .map(|particle| GlowingBall {
+                pos: [particle.pos.x, particle.pos.y],
+                color: self.color,
+                radius: particle.size,
+                corona: particle.size * 0.5,
+                late_alpha: 1.0,
+            });
+        let droplets = self.droplets.iter().map(|droplet| GlowingBall {
+            pos: [droplet.pos.x, droplet.pos.y],
+            color: self.color,
+            radius: 1.0,
+            corona: 0.5,
+            late_alpha: 1.0 - droplet.age / Self::SPLASH_LIFETIME,
+        });
+        flakes.chain(droplets).collect()
+    }
+
+    /// Draws the streaks and the balls, the world view has to match the [`Map2dView`].
+    pub fn draw<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        pipelines: &VulkanPipelines,
+        view: &Map2dView,
+    ) -> Result<(), DrawError> {
+        let streaks = self.streaks(view);
+        if !streaks.is_empty() {
+            pipelines.trail.draw(builder, &streaks)?;
+        }
+        let balls = self.balls();
+        if !balls.is_empty() {
+            pipelines.glowing_balls.draw(builder, balls)?;
+        }
+        Ok(())
+    }
+}
+
+/// The corners of the visible area of the view in world coordinates.
+fn visible_area(view: &Map2dView) -> (Pos<f32>, Pos<f32>) {
+    let (width, height) = view.screen_size();
+    (
+        view.position_screen_to_world(Pos::new(0.0, 0.0)),
+        view.position_screen_to_world(Pos::new(width as f32, height as f32)),
+    )
+}
@@ -1 +1,7 @@
+pub mod nameplates;
+#[cfg(feature = "world2d-snapshot")]
+pub mod snapshot;
+pub mod spatial_audio;
+#[cfg(feature = "world2d")]
+pub mod terrain_shading;
 pub mod view;
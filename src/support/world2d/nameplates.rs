@@ -0,0 +1,311 @@
+//! Health bars and name plates for many units at once. Unlike the egui panels of
+//! [`WorldUi`], all bars are drawn with a single instanced draw call of the
+//! [`SdfShapePipeline`] and all labels with the [`TexturedPipeline::draw_quads`], so hundreds of
+//! units stay cheap. The bars keep their size on the screen while the view zooms.
+//!
+//! ```no_run
+//! # use hotrod::engine::types::world2d::{Dim, Pos};
+//! # use hotrod::support::world2d::nameplates::{NameplateBatch, NameplateStyle};
+//! # use hotrod::support::world2d::view::Map2dView;
+//! # let view = Map2dView::new(800, 600);
+//! # let units = [(Pos::new(120.0, 80.0), 0.75)];
+//! let mut nameplates =
+//!     NameplateBatch::new(NameplateStyle::default().with_world_offset(Dim::new(0.0, -16.0)));
+//! // once per frame, after the view was updated
+//! nameplates.update(
+//!     &view,
+//!     units
+//!         .iter()
+//!         .map(|(pos, health)| (*pos, *health, [0.2, 0.8, 0.2, 1.0])),
+//! );
+//! // and while rendering: nameplates.draw(&mut builder, &pipelines.sdf_shapes)
+//! ```
+//!
+//! The labels are rendered by the [`FontRenderer`] into its atlas, there is no SDF text
+//! rendering yet. Each distinct text, size and color is cached separately, and the atlas pages of
+//! labels drawn every frame are never recycled, so they do not flicker. Keep them short and few
+//! distinct, like names or levels, so they fit into the atlas.
+//!
+//! [`WorldUi`]: crate::support::world_ui::WorldUi
+//! [`FontRenderer`]: crate::engine::system::ttf::FontRenderer
+
+use crate::engine::system::vulkan::sdf_shapes::{SdfShape, SdfShapePipeline};
+#[cfg(feature = "ttf-sdl2")]
+use crate::engine::system::vulkan::textured::TexturedPipeline;
+use crate::engine::system::vulkan::DrawError;
+use crate::engine::types::world2d::{Dim, Pos};
+use crate::support::world2d::view::Map2dView;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+
+/// A bar above a unit, see [`NameplateBatch::update`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Nameplate {
+    /// The position of the unit in the world
+    pub position: Pos<f32>,
+    /// How much of the bar is filled, from `0.0` to `1.0`
+    pub fraction: f32,
+    pub color: [f32; 4],
+}
+
+impl From<(Pos<f32>, f32, [f32; 4])> for Nameplate {
+    #[inline]
+    fn from((position, fraction, color): (Pos<f32>, f32, [f32; 4])) -> Self {
+        Self {
+            position,
+            fraction,
+            color,
+        }
+    }
+}
+
+/// How the bars and labels of a [`NameplateBatch`] look, shared by all of them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize),
+    serde(default)
+)]
+pub struct NameplateStyle {
+    /// The width and height of the bar in pixels
+    pub bar_size: [f32; 2],
+    pub corner_radius: f32,
+    pub border_width: f32,
+    pub border_color: [f32; 4],
+    /// The color of the empty part of the bar
+    pub background_color: [f32; 4],
+    /// Added to the position of the unit in world units, so it scales with the zoom, like the
+    /// height of the sprite to place the bar above it
+    pub world_offset: Dim<f32>,
+    /// Added after the conversion into pixels, so it stays the same while zooming
+    pub screen_offset: [f32; 2],
+    /// The pixels between the bar and the label above it
+    pub label_gap: f32,
+    /// How far a bar may be outside the screen before it is culled, in pixels
+    pub margin: f32,
+}
+
+impl Default for NameplateStyle {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            bar_size: [32.0, 5.0],
+            corner_radius: 2.0,
+            border_width: 1.0,
+            border_color: [0.0, 0.0, 0.0, 0.8],
+            background_color: [0.1, 0.1, 0.1, 0.6],
+            world_offset: Dim::new(0.0, 0.0),
+            screen_offset: [0.0, 0.0],
+            label_gap: 2.0,
+            margin: 0.0,
+        }
+    }
+}
+
+impl NameplateStyle {
+    #[inline]
+    pub fn with_bar_size(mut self, width: f32, height: f32) -> Self {
+        self.bar_size = [width, height];
+        self
+    }
+
+    #[inline]
+    pub fn with_corner_radius(mut self, radius: f32) -> Self {
+        self.corner_radius = radius;
+        self
+    }
+
+    #[inline]
+    pub fn with_border(mut self, width: f32, color: [f32; 4]) -> Self {
+        self.border_width = width;
+        self.border_color = color;
+        self
+    }
+
+    #[inline]
+    pub fn with_background_color(mut self, color: [f32; 4]) -> Self {
+        self.background_color = color;
+        self
+    }
+
+    #[inline]
+    pub fn with_world_offset(mut self, offset: Dim<f32>) -> Self {
+        self.world_offset = offset;
+        self
+    }
+
+    #[inline]
+    pub fn with_screen_offset(mut self, offset: [f32; 2]) -> Self {
+        self.screen_offset = offset;
+        self
+    }
+
+    #[inline]
+    pub fn with_label_gap(mut self, gap: f32) -> Self {
+        self.label_gap = gap;
+        self
+    }
+
+    #[inline]
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        self.margin = margin.max(0.0);
+        self
+    }
+}
+
+/// The bars and labels of a frame, see the [module documentation](self). The buffers are reused
+/// between frames, so updating does not allocate once they are large enough.
+#[derive(Default)]
+pub struct NameplateBatch {
+    style: NameplateStyle,
+    shapes: Vec<SdfShape>,
+    visible_bars: usize,
+    #[cfg(feature = "ttf-sdl2")]
+    labels: Vec<crate::engine::system::vulkan::textured::TexturedQuad>,
+}
+
+impl NameplateBatch {
+    pub fn new(style: NameplateStyle) -> Self {
+        Self {
+            style,
+            ..Self::default()
+        }
+    }
+
+    #[inline]
+    pub fn style(&self) -> &NameplateStyle {
+        &self.style
+    }
+
+    /// Applies to the next [`NameplateBatch::update`].
+    #[inline]
+    pub fn set_style(&mut self, style: NameplateStyle) {
+        self.style = style;
+    }
+
+    /// The center of the bar of a unit at the position in pixels of the screen of the view.
+    #[inline]
+    pub fn bar_center(&self, view: &Map2dView, position: Pos<f32>) -> [f32; 2] {
+        let screen = view.position_world_to_screen(position + self.style.world_offset);
+        [
+            screen.x + self.style.screen_offset[0],
+            screen.y + self.style.screen_offset[1],
+        ]
+    }
+
+    /// Whether the bar at the center is (partially) on the screen of the view.
+    pub fn is_visible(&self, view: &Map2dView, [x, y]: [f32; 2]) -> bool {
+        let (width, height) = view.screen_size();
+        let half_width = self.style.bar_size[0] / 2.0 + self.style.margin;
+        let half_height = self.style.bar_size[1] / 2.0 + self.style.margin;
+        x + half_width >= 0.0
+            && y + half_height >= 0.0
+            && x - half_width <= width as f32
+            && y - half_height <= height as f32
+    }
+
+    /// Replaces the bars of the previous frame, bars that are off-screen are skipped.
+    pub fn update<I>(&mut self, view: &Map2dView, nameplates: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<Nameplate>,
+    {
+        self.shapes.clear();
+        self.visible_bars = 0;
+        let style = self.style;
+        let [width, height] = style.bar_size;
+        let inner_height = (height - 2.0 * style.border_width).max(0.0);
+        let inner_width = (width - 2.0 * style.border_width).max(0.0);
+        let inner_radius = (style.corner_radius - style.border_width)
+            .max(0.0)
+            .min(inner_height / 2.0);
+
+        for nameplate in nameplates.into_iter().map(Into::into) {
+            let center = self.bar_center(view, nameplate.position);
+            if !self.is_visible(view, center) {
+                continue;
+            }
+            self.visible_bars += 1;
+            self.shapes.push(
+                SdfShape::rounded_box(center, style.bar_size, style.corner_radius)
+                    .with_fill_color(style.background_color)
+                    .with_border(style.border_width, style.border_color),
+            );
+            // NaN is treated as empty
+            let fraction = if nameplate.fraction > 0.0 {
+                nameplate.fraction.min(1.0)
+            } else {
+                0.0
+            };
+            let fill_width = inner_width * fraction;
+            if fill_width > 0.0 && inner_height > 0.0 {
+                // the fill grows from the left edge within the border
+                let left = center[0] - inner_width / 2.0;
+                self.shapes.push(
+                    SdfShape::rounded_box(
+                        [left + fill_width / 2.0, center[1]],
+                        [fill_width, inner_height],
+                        inner_radius.min(fill_width / 2.0),
+                    )
+                    .with_fill_color(nameplate.color),
+                );
+            }
+        }
+    }
+
+    /// The amount of bars of the last [`NameplateBatch::update`] that are on the screen.
+    #[inline]
+    pub fn visible_bars(&self) -> usize {
+        self.visible_bars
+    }
+
+    /// Replaces the labels of the previous frame with texts centered above the bars of the
+    /// units. Labels of off-screen units are skipped.
+    #[cfg(feature = "ttf-sdl2")]
+    pub fn update_labels<'t, I>(
+        &mut self,
+        view: &Map2dView,
+        font_renderer: &mut crate::engine::system::ttf::FontRenderer,
+        textures: &crate::engine::system::ttf::TextTextures,
+        size: u16,
+        color: [u8; 4],
+        labels: I,
+    ) where
+        I: IntoIterator<Item = (Pos<f32>, &'t str)>,
+    {
+        self.labels.clear();
+        for (position, text) in labels {
+            let center = self.bar_center(view, position);
+            if !self.is_visible(view, center) {
+                continue;
+            }
+            let quad = font_renderer.prepare_quad(textures, text, size, color, [0.0, 0.0]);
+            let [width, height] = quad.vertices[2].pos;
+            let bottom = center[1] - self.style.bar_size[1] / 2.0 - self.style.label_gap;
+            self.labels.push(
+                quad.translated([(center[0] - width / 2.0).round(), (bottom - height).round()]),
+            );
+        }
+    }
+
+    /// Draws the bars of the last [`NameplateBatch::update`] with a single draw call.
+    pub fn draw<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        pipeline: &SdfShapePipeline,
+    ) -> Result<(), DrawError> {
+        if self.shapes.is_empty() {
+            return Ok(());
+        }
+        pipeline.draw(builder, self.shapes.iter().copied())
+    }
+
+    /// Draws the labels of the last [`NameplateBatch::update_labels`].
+    #[cfg(feature = "ttf-sdl2")]
+    pub fn draw_labels<P>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<P>,
+        pipeline: &TexturedPipeline,
+    ) -> Result<(), DrawError> {
+        pipeline.draw_quads(builder, &self.labels)
+    }
+}
@@ -0,0 +1,447 @@
+//! A versioned binary snapshot of a 2D world to quickly add saving and loading to a game.
+//!
+//! A snapshot file consists of a small uncompressed header followed by the zlib compressed
+//! bincode encoding of the [`WorldSnapshot`]:
+//!
+//! | Bytes  | Content                                                    |
+//! |--------|------------------------------------------------------------|
+//! | 4      | [`MAGIC`]                                                  |
+//! | 2      | [`FORMAT_VERSION`], little endian                          |
+//! | 4      | The version of the game data, little endian                |
+//! | ...    | The compressed payload                                     |
+//!
+//! The version of the game data is chosen by the game and is increased whenever the terrain
+//! cell or entity types change. Snapshots of older versions are upgraded by the hooks of the
+//! [`SnapshotMigrations`] while loading.
+//!
+//! ```no_run
+//! use hotrod::support::world2d::snapshot::{SnapshotMigrations, WorldSnapshot};
+//!
+//! #[derive(serde_derive::Serialize, serde_derive::Deserialize)]
+//! struct Unit {
+//!     x: f32,
+//!     y: f32,
+//!     health: u16,
+//! }
+//!
+//! #[derive(serde_derive::Deserialize)]
+//! struct UnitV1 {
+//!     x: f32,
+//!     y: f32,
+//! }
+//!
+//! let migrations = SnapshotMigrations::new(2).with_typed_migration(1, |old: WorldSnapshot<u8, UnitV1>| {
+//!     old.map_entities(|u| Unit {
+//!         x: u.x,
+//!         y: u.y,
+//!         health: 100,
+//!     })
+//! });
+//!
+//! let snapshot = WorldSnapshot::<u8, Unit>::load("save.bin", &migrations).unwrap();
+//! snapshot.save("save.bin", migrations.version()).unwrap();
+//! ```
+
+use crate::support::world2d::view::Map2dView;
+use bincode::Options;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+pub const MAGIC: [u8; 4] = *b"HRWS";
+
+/// The version of the container format itself, independent of the version of the game data.
+pub const FORMAT_VERSION: u16 = 1;
+
+/// The default of [`SnapshotMigrations::with_size_limit`], 256 MiB.
+pub const DEFAULT_SIZE_LIMIT: u64 = 256 * 1024 * 1024;
+
+/// A rectangular grid of terrain cells, stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct TerrainGrid<T> {
+    width: u32,
+    height: u32,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> TerrainGrid<T> {
+    #[inline]
+    pub fn new(width: u32, height: u32, fill: T) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![fill; width as usize * height as usize],
+        }
+    }
+}
+
+impl<T> TerrainGrid<T> {
+    /// Returns [`None`] if the amount of cells does not match the dimensions.
+    #[inline]
+    pub fn from_cells(width: u32, height: u32, cells: Vec<T>) -> Option<Self> {
+        if cells.len() == width as usize * height as usize {
+            Some(Self {
+                width,
+                height,
+                cells,
+            })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, x: u32, y: u32) -> Option<&T> {
+        self.index(x, y).map(|index| &self.cells[index])
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, x: u32, y: u32) -> Option<&mut T> {
+        self.index(x, y).map(|index| &mut self.cells[index])
+    }
+
+    /// Returns `false` if the position is outside the grid.
+    #[inline]
+    pub fn set(&mut self, x: u32, y: u32, value: T) -> bool {
+        match self.get_mut(x, y) {
+            Some(cell) => {
+                *cell = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[inline]
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    /// Whether the amount of cells matches the dimensions, which is not guaranteed for grids
+    /// that were decoded from untrusted data.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.cells.len() as u64 == u64::from(self.width) * u64::from(self.height)
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> TerrainGrid<U> {
+        TerrainGrid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.into_iter().map(f).collect(),
+        }
+    }
+}
+
+/// The viewed world position and zoom of a [`Map2dView`]. The screen size is not part of the
+/// snapshot, since it belongs to the window and not to the world.
+#[derive(Debug, Copy, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct CameraSnapshot {
+    pub x: f32,
+    pub y: f32,
+    pub zoom: f32,
+}
+
+impl Default for CameraSnapshot {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            zoom: 1.0,
+        }
+    }
+}
+
+impl CameraSnapshot {
+    #[inline]
+    pub fn apply_to(&self, view: &mut Map2dView) {
+        view.set_viewed_world_position(self.x, self.y);
+        view.set_zoom(self.zoom);
+    }
+}
+
+impl From<&Map2dView> for CameraSnapshot {
+    #[inline]
+    fn from(view: &Map2dView) -> Self {
+        let position = view.viewed_world_position();
+        Self {
+            x: position.x,
+            y: position.y,
+            zoom: view.zoom(),
+        }
+    }
+}
+
+/// The state of a 2D world: any amount of terrain layers of cell type `T`, the entities of
+/// type `E` and the camera.
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct WorldSnapshot<T, E> {
+    pub terrain: Vec<TerrainGrid<T>>,
+    pub entities: Vec<E>,
+    pub camera: CameraSnapshot,
+}
+
+impl<T, E> Default for WorldSnapshot<T, E> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            terrain: Vec::default(),
+            entities: Vec::default(),
+            camera: CameraSnapshot::default(),
+        }
+    }
+}
+
+impl<T, E> WorldSnapshot<T, E> {
+    pub fn map_terrain<U>(self, mut f: impl FnMut(T) -> U) -> WorldSnapshot<U, E> {
+        WorldSnapshot {
+            terrain: self
+                .terrain
+                .into_iter()
+                .map(|grid| grid.map(&mut f))
+                .collect(),
+            entities: self.entities,
+            camera: self.camera,
+        }
+    }
+
+    pub fn map_entities<U>(self, f: impl FnMut(E) -> U) -> WorldSnapshot<T, U> {
+        WorldSnapshot {
+            terrain: self.terrain,
+            entities: self.entities.into_iter().map(f).collect(),
+            camera: self.camera,
+        }
+    }
+}
+
+impl<T: Serialize, E: Serialize> WorldSnapshot<T, E> {
+    /// Writes the snapshot, tagged with the given version of the game data.
+    pub fn write(&self, mut writer: impl Write, version: u32) -> Result<(), Error> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&version.to_le_bytes())?;
+
+        let mut encoder = ZlibEncoder::new(writer, Compression::default());
+        bincode::serialize_into(&mut encoder, self)?;
+        encoder.finish()?.flush()?;
+        Ok(())
+    }
+
+    /// Writes the snapshot to a temporary file first and then replaces the file at the given
+    /// path, so that a crash while saving does not destroy the previous save. The temporary file
+    /// is flushed to the disk before it replaces the previous save.
+    pub fn save(&self, path: impl AsRef<Path>, version: u32) -> Result<(), Error> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        self.write(&mut writer, version)?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned, E: DeserializeOwned> WorldSnapshot<T, E> {
+    /// Reads a snapshot and upgrades it to the version of the [`SnapshotMigrations`]. The
+    /// payload may not exceed [`SnapshotMigrations::size_limit`] and all terrain grids must have
+    /// as many cells as their dimensions require.
+    pub fn read(mut reader: impl Read, migrations: &SnapshotMigrations) -> Result<Self, Error> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(Error::InvalidMagic);
+        }
+
+        let mut format_version = [0u8; 2];
+        reader.read_exact(&mut format_version)?;
+        let format_version = u16::from_le_bytes(format_version);
+        if format_version != FORMAT_VERSION {
+            return Err(Error::UnsupportedFormatVersion(format_version));
+        }
+
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+
+        let limit = migrations.size_limit();
+        let snapshot: Self = if version == migrations.version() {
+            bincode_options(limit).deserialize_from(ZlibDecoder::new(reader))?
+        } else {
+            let mut payload = Vec::new();
+            ZlibDecoder::new(reader)
+                .take(limit.saturating_add(1))
+                .read_to_end(&mut payload)?;
+            if payload.len() as u64 > limit {
+                return Err(Error::SizeLimitExceeded(limit));
+            }
+            let payload = migrations.migrate(version, payload)?;
+            bincode_options(limit).deserialize(&payload)?
+        };
+
+        if let Some(index) = snapshot.terrain.iter().position(|grid| !grid.is_valid()) {
+            let grid = &snapshot.terrain[index];
+            return Err(Error::InvalidTerrain {
+                index,
+                width: grid.width,
+                height: grid.height,
+                cells: grid.cells.len(),
+            });
+        }
+        Ok(snapshot)
+    }
+
+    #[inline]
+    pub fn load(path: impl AsRef<Path>, migrations: &SnapshotMigrations) -> Result<Self, Error> {
+        Self::read(BufReader::new(File::open(path)?), migrations)
+    }
+}
+
+/// The same encoding as [`bincode::serialize`], but decoding fails instead of allocating more
+/// than `limit` bytes for a corrupted or malicious payload.
+#[inline]
+fn bincode_options(limit: u64) -> impl Options {
+    bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(limit)
+}
+
+type MigrationHook = Box<dyn Fn(Vec<u8>) -> Result<Vec<u8>, Error> + Send + Sync>;
+
+/// The current version of the game data and the hooks to upgrade the payload of older
+/// snapshots, one version at a time.
+pub struct SnapshotMigrations {
+    version: u32,
+    size_limit: u64,
+    hooks: BTreeMap<u32, MigrationHook>,
+}
+
+impl SnapshotMigrations {
+    #[inline]
+    pub fn new(version: u32) -> Self {
+        Self {
+            version,
+            size_limit: DEFAULT_SIZE_LIMIT,
+            hooks: BTreeMap::default(),
+        }
+    }
+
+    #[inline]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The maximum size of the uncompressed payload in bytes, larger snapshots are rejected
+    /// while loading.
+    #[inline]
+    pub fn with_size_limit(mut self, limit: u64) -> Self {
+        self.size_limit = limit;
+        self
+    }
+
+    #[inline]
+    pub fn size_limit(&self) -> u64 {
+        self.size_limit
+    }
+
+    /// Registers the hook that converts the uncompressed bincode payload of version
+    /// `from_version` into the payload of version `from_version + 1`.
+    #[inline]
+    pub fn with_migration(
+        mut self,
+        from_version: u32,
+        hook: impl Fn(Vec<u8>) -> Result<Vec<u8>, Error> + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.insert(from_version, Box::new(hook));
+        self
+    }
+
+    /// Like [`SnapshotMigrations::with_migration`], but decodes the payload as `Old` and encodes
+    /// the converted `New` value.
+    #[inline]
+    pub fn with_typed_migration<Old: DeserializeOwned, New: Serialize>(
+        self,
+        from_version: u32,
+        convert: impl Fn(Old) -> New + Send + Sync + 'static,
+    ) -> Self {
+        self.with_migration(from_version, move |payload| {
+            let old = bincode::deserialize::<Old>(&payload)?;
+            Ok(bincode::serialize(&convert(old))?)
+        })
+    }
+
+    fn migrate(&self, mut version: u32, mut payload: Vec<u8>) -> Result<Vec<u8>, Error> {
+        if version > self.version {
+            return Err(Error::UnsupportedVersion {
+                version,
+                current: self.version,
+            });
+        }
+        while version < self.version {
+            let hook = self
+                .hooks
+                .get(&version)
+                .ok_or(Error::MissingMigration(version))?;
+            debug!("Migrating world snapshot from version {version}");
+            payload = hook(payload)?;
+            version += 1;
+        }
+        Ok(payload)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Not a world snapshot")]
+    InvalidMagic,
+    #[error("Unsupported snapshot format version {0}, expected {FORMAT_VERSION}")]
+    UnsupportedFormatVersion(u16),
+    #[error("The snapshot version {version} is newer than the supported version {current}")]
+    UnsupportedVersion { version: u32, current: u32 },
+    #[error("No migration from snapshot version {0} to the next version")]
+    MissingMigration(u32),
+    #[error("The uncompressed snapshot exceeds the size limit of {0} bytes")]
+    SizeLimitExceeded(u64),
+    #[error("Terrain layer {index} has {cells} cells, but is {width}x{height}")]
+    InvalidTerrain {
+        index: usize,
+        width: u32,
+        height: u32,
+        cells: usize,
+    },
+    #[error("Failed to encode or decode the snapshot: {0}")]
+    EncodingError(#[from] bincode::Error),
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+}
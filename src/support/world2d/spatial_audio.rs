@@ -0,0 +1,283 @@
+//! Positional audio for 2d worlds: the volume and stereo panning of sound emitters is derived
+//! from their world position relative to the camera of a [`Map2dView`].
+//!
+//! The engine owns a [`SpatialAudio`] (see `Engine::spatial_audio_mut`) and updates it at the end
+//! of every frame from the view of [`SpatialAudio::set_listener`]. With the `audio` feature, the
+//! mix of each emitter that is bound to a channel with [`AudioEmitter::with_channel`] is then
+//! applied to that channel of the `AudioSystem` while it is playing. Otherwise, the computed
+//! [`StereoMix`] can be applied manually, for example with [`StereoMix::to_u8`].
+//!
+//! ```no_run
+//! # use hotrod::engine::types::world2d::Pos;
+//! # use hotrod::support::world2d::spatial_audio::AudioEmitter;
+//! # use hotrod::support::world2d::view::Map2dView;
+//! # let mut engine = hotrod::engine::Engine::default();
+//! # let view = Map2dView::new(800, 600);
+//! // play the sound on a reserved channel, so no other sound is panned by the emitter
+//! let waterfall = engine
+//!     .spatial_audio_mut()
+//!     .add(AudioEmitter::new(Pos::new(250.0, 40.0)).with_channel(0));
+//! // once per frame, after the view was updated
+//! engine.spatial_audio_mut().set_listener(&view);
+//! ```
+
+use crate::engine::types::handle::{Handle, HandleMap};
+use crate::engine::types::world2d::Pos;
+use crate::support::world2d::view::Map2dView;
+
+/// Refers to an emitter of a [`SpatialAudio`], stale once the emitter was removed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EmitterId(Handle<(AudioEmitter, StereoMix)>);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AudioEmitter {
+    pub pos: Pos<f32>,
+    /// The volume without any attenuation, in `0.0..=1.0`
+    pub volume: f32,
+    /// Up to this distance to the listener (in world units), the emitter is not attenuated
+    pub min_distance: f32,
+    /// From this distance to the listener (in world units), the emitter is inaudible
+    pub max_distance: f32,
+    /// The mixer channel the sound of the emitter plays on, which is panned by the engine
+    pub channel: Option<i32>,
+}
+
+impl AudioEmitter {
+    #[inline]
+    pub fn new(pos: Pos<f32>) -> Self {
+        Self {
+            pos,
+            volume: 1.0,
+            min_distance: 100.0,
+            max_distance: 1000.0,
+            channel: None,
+        }
+    }
+
+    #[inline]
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    #[inline]
+    pub fn with_distances(mut self, min_distance: f32, max_distance: f32) -> Self {
+        self.min_distance = min_distance;
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// The channel should be reserved, see `AudioSystem::reserve_channels`, otherwise other
+    /// sounds playing on it are panned as well.
+    #[inline]
+    pub fn with_channel(mut self, channel: i32) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+}
+
+/// The volume and panning of an emitter as heard by the listener.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct StereoMix {
+    /// In `0.0..=1.0`
+    pub volume: f32,
+    /// From `-1.0` (left) over `0.0` (center) to `1.0` (right)
+    pub pan: f32,
+}
+
+impl StereoMix {
+    /// The gain of the left and the right channel, panned with constant power so that the
+    /// loudness does not drop in the center.
+    #[inline]
+    pub fn gains(&self) -> (f32, f32) {
+        let angle = (self.pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+        (self.volume * angle.cos(), self.volume * angle.sin())
+    }
+
+    /// The gains scaled to `0..=255`, as expected by SDL2_mixer.
+    #[inline]
+    pub fn to_u8(&self) -> (u8, u8) {
+        let (left, right) = self.gains();
+        (
+            (left.clamp(0.0, 1.0) * 255.0).round() as u8,
+            (right.clamp(0.0, 1.0) * 255.0).round() as u8,
+        )
+    }
+}
+
+/// The registered emitters and their [`StereoMix`], which is recomputed by
+/// [`SpatialAudio::update`] each frame.
+#[derive(Debug, Clone)]
+pub struct SpatialAudio {
+    emitters: HandleMap<(AudioEmitter, StereoMix)>,
+    /// The view the engine updates the mixes for at the end of each frame
+    listener: Option<Map2dView>,
+    /// The share of the screen width over which the panning goes from fully left to fully right
+    pan_width: f32,
+    /// The maximal panning, so emitters outside the screen are never hard left or hard right
+    max_pan: f32,
+}
+
+impl Default for SpatialAudio {
+    fn default() -> Self {
+        Self {
+            emitters: HandleMap::default(),
+            listener: None,
+            pan_width: 1.0,
+            max_pan: 0.8,
+        }
+    }
+}
+
+impl SpatialAudio {
+    /// The share of the screen width over which the panning goes from left to right. With `1.0`,
+    /// emitters at the left edge of the screen are panned fully to the left.
+    #[inline]
+    pub fn with_pan_width(mut self, pan_width: f32) -> Self {
+        self.pan_width = pan_width;
+        self
+    }
+
+    /// Limits the panning to `-max_pan..=max_pan`, so distant emitters are never only heard on
+    /// one side.
+    #[inline]
+    pub fn with_max_pan(mut self, max_pan: f32) -> Self {
+        self.max_pan = max_pan.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn add(&mut self, emitter: AudioEmitter) -> EmitterId {
+        EmitterId(self.emitters.insert((emitter, StereoMix::default())))
+    }
+
+    #[inline]
+    pub fn remove(&mut self, id: EmitterId) -> Option<AudioEmitter> {
+        self.emitters.remove(id.0).map(|(emitter, _)| emitter)
+    }
+
+    #[inline]
+    pub fn emitter(&self, id: EmitterId) -> Option<&AudioEmitter> {
+        self.emitters.get(id.0).map(|(emitter, _)| emitter)
+    }
+
+    #[inline]
+    pub fn emitter_mut(&mut self, id: EmitterId) -> Option<&mut AudioEmitter> {
+        self.emitters.get_mut(id.0).map(|(emitter, _)| emitter)
+    }
+
+    #[inline]
+    pub fn set_position(&mut self, id: EmitterId, pos: Pos<f32>) {
+        if let Some(emitter) = self.emitter_mut(id) {
+            emitter.pos = pos;
+        }
+    }
+
+    /// The mix of the emitter as of the last [`SpatialAudio::update`].
+    #[inline]
+    pub fn mix(&self, id: EmitterId) -> Option<StereoMix> {
+        self.emitters.get(id.0).map(|(_, mix)| *mix)
+    }
+
+    #[inline]
+    pub fn mixes(&self) -> impl Iterator<Item = (EmitterId, StereoMix)> + '_ {
+        self.emitters
+            .iter()
+            .map(|(id, (_, mix))| (EmitterId(id), *mix))
+    }
+
+    /// The view the mixes are updated for by the engine at the end of each frame, the listener is
+    /// located at its center. Without a listener, the mixes are only updated by
+    /// [`SpatialAudio::update`].
+    pub fn set_listener(&mut self, view: &Map2dView) {
+        match &mut self.listener {
+            Some(listener) => listener.clone_from(view),
+            None => self.listener = Some(view.clone()),
+        }
+    }
+
+    #[inline]
+    pub fn clear_listener(&mut self) {
+        self.listener = None;
+    }
+
+    /// Recomputes the mix of every emitter, the listener is located at the center of the view.
+    pub fn update(&mut self, view: &Map2dView) {
+        Self::update_mixes(&mut self.emitters, self.pan_width, self.max_pan, view);
+    }
+
+    /// Updates the mixes for the listener and applies them to the channels of the emitters that
+    /// are playing, called by the engine at the end of each frame.
+    pub(crate) fn on_frame_completed(
+        &mut self,
+        #[cfg(feature = "audio")] audio: Option<&crate::engine::system::audio::AudioSystem>,
+    ) {
+        let Some(listener) = &self.listener else {
+            return;
+        };
+        Self::update_mixes(&mut self.emitters, self.pan_width, self.max_pan, listener);
+
+        #[cfg(feature = "audio")]
+        if let Some(audio) = audio {
+            for (emitter, mix) in self.emitters.values() {
+                if let Some(playback) = emitter.channel.map(|channel| audio.channel(channel)) {
+                    if playback.is_playing() {
+                        let (left, right) = mix.to_u8();
+                        playback.set_gains(left, right);
+                    }
+                }
+            }
+        }
+    }
+
+    fn update_mixes(
+        emitters: &mut HandleMap<(AudioEmitter, StereoMix)>,
+        pan_width: f32,
+        max_pan: f32,
+        view: &Map2dView,
+    ) {
+        for (emitter, mix) in emitters.values_mut() {
+            *mix = Self::compute_mix(pan_width, max_pan, emitter, view);
+        }
+    }
+
+    /// The mix of an emitter that is not registered, for example for a one-shot sound.
+    #[inline]
+    pub fn mix_for(&self, emitter: &AudioEmitter, view: &Map2dView) -> StereoMix {
+        Self::compute_mix(self.pan_width, self.max_pan, emitter, view)
+    }
+
+    fn compute_mix(
+        pan_width: f32,
+        max_pan: f32,
+        emitter: &AudioEmitter,
+        view: &Map2dView,
+    ) -> StereoMix {
+        let listener = view.viewed_world_position();
+        let distance =
+            ((emitter.pos.x - listener.x).powi(2) + (emitter.pos.y - listener.y).powi(2)).sqrt();
+
+        let falloff = emitter.max_distance - emitter.min_distance;
+        let attenuation = if distance <= emitter.min_distance {
+            1.0
+        } else if falloff <= 0.0 || distance >= emitter.max_distance {
+            0.0
+        } else {
+            1.0 - (distance - emitter.min_distance) / falloff
+        };
+
+        let (width, _) = view.screen_size();
+        let half_width = width as f32 * 0.5 * pan_width;
+        let screen = view.position_world_to_screen(emitter.pos);
+        let pan = if half_width > 0.0 {
+            (screen.x - width as f32 * 0.5) / half_width
+        } else {
+            0.0
+        };
+
+        StereoMix {
+            // squared, which is closer to the perceived loudness than a linear falloff
+            volume: (emitter.volume * attenuation * attenuation).clamp(0.0, 1.0),
+            pan: pan.clamp(-max_pan, max_pan),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Derives the `shading` of terrain tiles from a heightmap, so hills and valleys are visible on
+//! flat tile textures.
+//!
+//! ```no_run
+//! use hotrod::engine::system::vulkan::world2d::terrain::InstanceData;
+//! use hotrod::support::noise::{Fbm, NoiseGrid, Simplex};
+//! use hotrod::support::world2d::terrain_shading::{Heightmap, SlopeShading};
+//!
+//! let mut heights = NoiseGrid::generate(&Fbm::new(Simplex::new(7)), 64, 64, 1.0 / 16.0);
+//! heights.normalize();
+//! let heightmap = Heightmap::from(heights);
+//!
+//! let mut tiles = (0..64 * 64)
+//!     .map(|index| InstanceData {
+//!         tile_pos: [(index % 64) as f32, (index / 64) as f32],
+//!         uv0: [0.0, 0.0],
+//!         uv1: [1.0, 1.0],
+//!         shading: 0.0,
+//!     })
+//!     .collect::<Vec<_>>();
+//! heightmap.shade_tiles(&mut tiles, &SlopeShading::default().with_height_scale(8.0));
+//! ```
+
+use crate::engine::system::vulkan::world2d::terrain::InstanceData;
+use crate::support::noise::NoiseGrid;
+
+/// A row-major grid of heights, one per tile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    width: u32,
+    height: u32,
+    heights: Vec<f32>,
+}
+
+impl From<NoiseGrid> for Heightmap {
+    #[inline]
+    fn from(grid: NoiseGrid) -> Self {
+        Self::new(grid.width(), grid.height(), grid.values().to_vec())
+    }
+}
+
+impl Heightmap {
+    /// # Panics
+    ///
+    /// If the amount of heights is not `width * height`.
+    pub fn new(width: u32, height: u32, heights: Vec<f32>) -> Self {
+        assert_eq!(
+            (width * height) as usize,
+            heights.len(),
+            "The heights do not match the dimensions of the heightmap"
+        );
+        Self {
+            width,
+            height,
+            heights,
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    /// The height at the position, positions outside of the heightmap are clamped to its edges.
+    pub fn get(&self, x: i64, y: i64) -> f32 {
+        if self.heights.is_empty() {
+            return 0.0;
+        }
+        let x = x.clamp(0, i64::from(self.width) - 1) as usize;
+        let y = y.clamp(0, i64::from(self.height) - 1) as usize;
+        self.heights[y * self.width as usize + x]
+    }
+
+    /// The change of the height per tile in x and y direction, by central differences.
+    pub fn gradient(&self, x: u32, y: u32) -> [f32; 2] {
+        let (x, y) = (i64::from(x), i64::from(y));
+        [
+            (self.get(x + 1, y) - self.get(x - 1, y)) * 0.5,
+            (self.get(x, y + 1) - self.get(x, y - 1)) * 0.5,
+        ]
+    }
+
+    /// The normalized surface normal, the heights are multiplied with `height_scale` first.
+    pub fn normal(&self, x: u32, y: u32, height_scale: f32) -> [f32; 3] {
+        let [dx, dy] = self.gradient(x, y);
+        normalize([-dx * height_scale, -dy * height_scale, 1.0])
+    }
+
+    /// The shading of each tile in row-major order, see [`SlopeShading`].
+    pub fn shading(&self, shading: &SlopeShading) -> Vec<f32> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| shading.shade(self.normal(x, y, shading.height_scale)))
+            .collect()
+    }
+
+    /// Sets the shading of the tiles, which are expected in the row-major order of the
+    /// heightmap. Superfluous tiles are left unchanged.
+    pub fn shade_tiles(&self, tiles: &mut [InstanceData], shading: &SlopeShading) {
+        for (tile, value) in tiles.iter_mut().zip(self.shading(shading)) {
+            tile.shading = value;
+        }
+    }
+
+    /// A tangent-space normal map in rgba, with the x, y and z components mapped from
+    /// `-1.0..=1.0` to `0..=255`. The data is linear and has to be uploaded into an image with a
+    /// `UNORM` format, not the `SRGB` format of regular textures.
+    pub fn normal_map_rgba(&self, height_scale: f32) -> Vec<u8> {
+        let encode = |value: f32| ((value * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8;
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                let [nx, ny, nz] = self.normal(x, y, height_scale);
+                [encode(nx), encode(ny), encode(nz), 255]
+            })
+            .collect()
+    }
+}
+
+/// Darkens the slopes that face away from the light, flat tiles are not shaded.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SlopeShading {
+    /// The normalized direction towards the light
+    light: [f32; 3],
+    height_scale: f32,
+    max_shading: f32,
+}
+
+impl Default for SlopeShading {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            // from the top left, like most 2d art
+            light: normalize([-1.0, -1.0, 1.0]),
+            height_scale: 1.0,
+            max_shading: 0.6,
+        }
+    }
+}
+
+impl SlopeShading {
+    /// The direction towards the light, with `z` pointing out of the screen.
+    #[inline]
+    pub fn with_light(mut self, direction: [f32; 3]) -> Self {
+        self.light = normalize(direction);
+        self
+    }
+
+    /// The factor the heights are multiplied with, larger values give steeper slopes.
+    #[inline]
+    pub fn with_height_scale(mut self, height_scale: f32) -> Self {
+        self.height_scale = height_scale;
+        self
+    }
+
+    /// The shading of a slope facing entirely away from the light, `1.0` is black.
+    #[inline]
+    pub fn with_max_shading(mut self, max_shading: f32) -> Self {
+        self.max_shading = max_shading.clamp(0.0, 1.0);
+        self
+    }
+
+    fn shade(&self, normal: [f32; 3]) -> f32 {
+        let lit = dot(normal, self.light).max(0.0);
+        let flat = self.light[2].max(f32::EPSILON);
+        (1.0 - lit / flat).clamp(0.0, 1.0) * self.max_shading
+    }
+}
+
+#[inline]
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[inline]
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt();
+    if length > f32::EPSILON {
+        [v[0] / length, v[1] / length, v[2] / length]
+    } else {
+        [0.0, 0.0, 1.0]
+    }
+}
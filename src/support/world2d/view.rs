@@ -1,6 +1,12 @@
+use crate::engine::input::StickValue;
 use crate::engine::system::vulkan::desc::binding_201_world_2d_view::World2dView;
 use crate::engine::types::world2d::{Dim, Pos};
 
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde-io",
+    derive(serde_derive::Serialize, serde_derive::Deserialize)
+)]
 pub struct Map2dView {
     screen_width: u32,
     screen_height: u32,
@@ -26,12 +32,25 @@ impl Map2dView {
         self.screen_height = height;
     }
 
+    #[inline]
+    pub fn screen_size(&self) -> (u32, u32) {
+        (self.screen_width, self.screen_height)
+    }
+
     #[inline]
     pub fn move_by_screen_delta(&mut self, dx: f32, dy: f32) {
         self.view_x -= dx / self.zoom;
         self.view_y -= dy / self.zoom;
     }
 
+    /// Pans the view into the direction of the stick, by up to `screen_distance` when it is
+    /// fully deflected.
+    #[inline]
+    pub fn pan_by_stick(&mut self, stick: StickValue, screen_distance: f32) {
+        let delta = stick.to_dim(screen_distance);
+        self.move_by_screen_delta(-delta.x, -delta.y);
+    }
+
     #[inline]
     pub fn zoom(&self) -> f32 {
         self.zoom
@@ -84,6 +103,18 @@ impl Map2dView {
         self.view_x = x;
         self.view_y = y;
     }
+
+    #[inline]
+    pub fn viewed_world_position(&self) -> Pos<f32> {
+        Pos::new(self.view_x, self.view_y)
+    }
+
+    /// Sets the zoom while keeping the viewed world position in the center of the screen, see
+    /// [`Map2dView::update_zoom_at_screen_position`] to zoom at any other position.
+    #[inline]
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom;
+    }
 }
 
 pub trait ZoomChangeSource {
@@ -0,0 +1,195 @@
+//! egui panels that are attached to positions in the world, like health bars, name plates or
+//! speech bubbles. [`WorldUi`] converts the world positions with the [`Map2dView`] into egui
+//! points every frame, so the panels follow the world while the view pans and zooms. Panels whose
+//! anchor left the screen are not shown, the rest is clipped to the world on the screen.
+//!
+//! ```no_run
+//! # use hotrod::ui::egui;
+//! # use hotrod::support::world_ui::{WorldAnchor, WorldUi};
+//! # use hotrod::support::world2d::view::Map2dView;
+//! # use hotrod::engine::types::world2d::{Dim, Pos};
+//! # let ctx = egui::Context::default();
+//! # let view = Map2dView::new(800, 600);
+//! let world_ui = WorldUi::new(&ctx, &view);
+//! let anchor = WorldAnchor::new(Pos::new(120.0, 80.0))
+//!     .with_world_offset(Dim::new(0.0, -16.0))
+//!     .with_align(egui::Align2::CENTER_BOTTOM);
+//! world_ui.show(&ctx, ("health", 7), anchor, |ui| {
+//!     ui.add(egui::ProgressBar::new(0.75).desired_width(48.0));
+//! });
+//! ```
+
+use crate::engine::system::viewport::ViewportRect;
+use crate::engine::types::world2d::{Dim, Pos};
+use crate::support::world2d::view::Map2dView;
+use egui::{Align2, Area, Context, Id, InnerResponse, Order, Pos2, Rect, Ui, Vec2};
+use std::hash::Hash;
+
+/// Where a panel is attached to, see [`WorldUi::show`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WorldAnchor {
+    /// The position in the world
+    pub position: Pos<f32>,
+    /// Added to the position in world units, so it scales with the zoom, like the height of a
+    /// head to show a name plate above
+    pub world_offset: Dim<f32>,
+    /// Added after the conversion into egui points, so it stays the same while zooming
+    pub screen_offset: Vec2,
+    /// Which point of the panel is placed at the anchor, the center by default
+    pub align: Align2,
+    /// Whether the panel reacts to the pointer, `false` by default so it does not block the
+    /// input of the world below it
+    pub interactable: bool,
+}
+
+impl WorldAnchor {
+    #[inline]
+    pub fn new(position: Pos<f32>) -> Self {
+        Self {
+            position,
+            world_offset: Dim::new(0.0, 0.0),
+            screen_offset: Vec2::ZERO,
+            align: Align2::CENTER_CENTER,
+            interactable: false,
+        }
+    }
+
+    #[inline]
+    pub fn with_world_offset(mut self, offset: Dim<f32>) -> Self {
+        self.world_offset = offset;
+        self
+    }
+
+    #[inline]
+    pub fn with_screen_offset(mut self, offset: impl Into<Vec2>) -> Self {
+        self.screen_offset = offset.into();
+        self
+    }
+
+    #[inline]
+    pub fn with_align(mut self, align: Align2) -> Self {
+        self.align = align;
+        self
+    }
+
+    #[inline]
+    pub fn with_interactable(mut self, interactable: bool) -> Self {
+        self.interactable = interactable;
+        self
+    }
+}
+
+/// Shows egui [`Area`]s at world positions for a frame, see the [module documentation](self).
+/// Create it after the [`Map2dView`] was updated for the frame, so the panels do not lag behind
+/// the world.
+#[derive(Clone)]
+pub struct WorldUi<'a> {
+    view: &'a Map2dView,
+    pixels_per_point: f32,
+    /// The top left corner of the world on the screen in pixels
+    origin: [f32; 2],
+    /// The world on the screen in egui points, the panels are clipped to it
+    clip_rect: Rect,
+    /// How far an anchor may be outside the `clip_rect` before its panel is hidden, in points
+    margin: f32,
+}
+
+impl<'a> WorldUi<'a> {
+    /// The default of [`WorldUi::with_margin`].
+    pub const DEFAULT_MARGIN: f32 = 32.0;
+
+    /// For a world that covers the whole window, whose size is the screen size of the view.
+    pub fn new(ctx: &Context, view: &'a Map2dView) -> Self {
+        let pixels_per_point = ctx.pixels_per_point();
+        let (width, height) = view.screen_size();
+        Self {
+            view,
+            pixels_per_point,
+            origin: [0.0, 0.0],
+            clip_rect: Rect::from_min_size(
+                Pos2::ZERO,
+                Vec2::new(width as f32, height as f32) / pixels_per_point,
+            ),
+            margin: Self::DEFAULT_MARGIN,
+        }
+    }
+
+    /// For a world that is rendered into the viewport of a [`ViewportLayout`], like the view of
+    /// one player of a split screen.
+    ///
+    /// [`ViewportLayout`]: crate::engine::system::viewport::ViewportLayout
+    pub fn with_viewport(mut self, viewport: ViewportRect) -> Self {
+        self.origin = [viewport.x as f32, viewport.y as f32];
+        self.clip_rect = Rect::from_min_size(
+            Pos2::new(self.origin[0], self.origin[1]) / self.pixels_per_point,
+            Vec2::new(viewport.width as f32, viewport.height as f32) / self.pixels_per_point,
+        );
+        self
+    }
+
+    /// Panels stay visible while their anchor is at most this far outside the screen, so
+    /// panels that are partially on the screen are clipped instead of popping out.
+    #[inline]
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        self.margin = margin.max(0.0);
+        self
+    }
+
+    /// The part of the screen the panels are clipped to, in egui points.
+    #[inline]
+    pub fn clip_rect(&self) -> Rect {
+        self.clip_rect
+    }
+
+    /// The world position in egui points.
+    pub fn world_to_ui(&self, position: Pos<f32>) -> Pos2 {
+        let screen = self.view.position_world_to_screen(position);
+        Pos2::new(
+            (self.origin[0] + screen.x) / self.pixels_per_point,
+            (self.origin[1] + screen.y) / self.pixels_per_point,
+        )
+    }
+
+    /// Where the panel of the anchor is placed in egui points.
+    #[inline]
+    pub fn anchor_pos(&self, anchor: &WorldAnchor) -> Pos2 {
+        self.world_to_ui(anchor.position + anchor.world_offset) + anchor.screen_offset
+    }
+
+    /// Whether the panel of the anchor is shown by [`WorldUi::show`].
+    #[inline]
+    pub fn is_visible(&self, anchor: &WorldAnchor) -> bool {
+        self.clip_rect
+            .expand(self.margin)
+            .contains(self.anchor_pos(anchor))
+    }
+
+    /// Shows the contents in an [`Area`] at the anchor, [`None`] if the anchor is off-screen.
+    /// The `id` has to be unique among the panels, like the id of the entity together with the
+    /// kind of the panel. The panels are drawn below the windows of egui.
+    pub fn show<R>(
+        &self,
+        ctx: &Context,
+        id: impl Hash,
+        anchor: WorldAnchor,
+        add_contents: impl FnOnce(&mut Ui) -> R,
+    ) -> Option<InnerResponse<R>> {
+        if !self.is_visible(&anchor) {
+            return None;
+        }
+        let clip_rect = self.clip_rect;
+        Some(
+            Area::new(Id::new(id))
+                .order(Order::Background)
+                .fixed_pos(self.anchor_pos(&anchor))
+                .pivot(anchor.align)
+                .constrain(false)
+                .movable(false)
+                .interactable(anchor.interactable)
+                .show(ctx, |ui| {
+                    ui.set_clip_rect(clip_rect.intersect(ui.clip_rect()));
+                    add_contents(ui)
+                }),
+        )
+    }
+}